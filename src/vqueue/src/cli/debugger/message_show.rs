@@ -95,6 +95,7 @@ mod tests {
         .unwrap();
 
         let connect_uuid = ctx.connect.connect_uuid;
+        let transaction_id = ctx.mail_from.transaction_id;
 
         let connect_timestamp = ctx.connect.connect_timestamp;
         let connect_timestamp =
@@ -127,19 +128,31 @@ mod tests {
   "skipped": null,
   "tls": null,
   "auth": null,
+  "client_rdns": null,
+  "connection_blocked": false,
+  "dnsbl": {{}},
   "client_name": "client.testserver.com",
   "using_deprecated": false,
+  "capabilities": [],
   "reverse_path": "client@testserver.com",
   "mail_timestamp": "{mail_timestamp}",
   "message_uuid": "{msg_uuid}",
+  "transaction_id": "{transaction_id}",
   "spf": null,
   "utf8": false,
+  "require_tls": false,
+  "envelop_id": null,
+  "ret": null,
+  "asserted_sender": null,
   "forward_paths": [
     "recipient@testserver.com"
   ],
   "delivery": {{}},
   "transaction_type": "internal",
-  "dkim": null
+  "dsn": {{}},
+  "dkim": null,
+  "authentication_results": false,
+  "quarantine": null
 }}
 Message body:
 {{
@@ -184,6 +197,7 @@ Message body:
         .unwrap();
 
         let connect_uuid = ctx.connect.connect_uuid;
+        let transaction_id = ctx.mail_from.transaction_id;
 
         let connect_timestamp = ctx.connect.connect_timestamp;
         let connect_timestamp =
@@ -216,19 +230,31 @@ Message body:
   "skipped": null,
   "tls": null,
   "auth": null,
+  "client_rdns": null,
+  "connection_blocked": false,
+  "dnsbl": {{}},
   "client_name": "client.testserver.com",
   "using_deprecated": false,
+  "capabilities": [],
   "reverse_path": "client@testserver.com",
   "mail_timestamp": "{mail_timestamp}",
   "message_uuid": "{msg_uuid}",
+  "transaction_id": "{transaction_id}",
   "spf": null,
   "utf8": false,
+  "require_tls": false,
+  "envelop_id": null,
+  "ret": null,
+  "asserted_sender": null,
   "forward_paths": [
     "recipient@testserver.com"
   ],
   "delivery": {{}},
   "transaction_type": "internal",
-  "dkim": null
+  "dsn": {{}},
+  "dkim": null,
+  "authentication_results": false,
+  "quarantine": null
 }}
 Message body:
 {}"#,
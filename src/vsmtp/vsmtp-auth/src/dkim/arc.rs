@@ -0,0 +1,461 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+//! Authenticated Received Chain, see <https://www.rfc-editor.org/rfc/rfc8617>.
+
+use super::{
+    signature::select_headers, Canonicalization, PrivateKey, PublicKey, SigningAlgorithm,
+    SigningError,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use vsmtp_mail_parser::RawBody;
+
+/// Name of the header carrying the authentication results of an instance.
+pub const AUTHENTICATION_RESULTS: &str = "ARC-Authentication-Results";
+/// Name of the header carrying the signature of the message of an instance.
+pub const MESSAGE_SIGNATURE: &str = "ARC-Message-Signature";
+/// Name of the header carrying the signature of the chain up to an instance.
+pub const SEAL: &str = "ARC-Seal";
+
+// NOTE: the headers of a set, in the order they are hashed by the seal.
+const SET_HEADERS: [&str; 3] = [AUTHENTICATION_RESULTS, MESSAGE_SIGNATURE, SEAL];
+const MAX_INSTANCE: usize = 50;
+
+/// The state of the chain before the set added by a sealer (the `cv=` tag of the seal).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ChainValidation {
+    /// The message has no set yet.
+    None,
+    /// All the sets of the chain are present and consistent, the seals and
+    /// the latest message signature are verified.
+    Pass,
+    /// The chain is broken.
+    Fail,
+}
+
+/// Failure to seal a message.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, thiserror::Error)]
+pub enum ArcError {
+    /// The latest seal has `cv=fail`, the chain must not be extended.
+    #[error("the chain has failed at instance {0}, it cannot be sealed again")]
+    ChainFailed(usize),
+    /// The chain has reached the maximum number of instances.
+    #[error("the chain already contains {MAX_INSTANCE} sets")]
+    TooManySets,
+    /// The signature of one of the headers failed.
+    #[error("{0}")]
+    Signing(SigningError),
+}
+
+/// The headers added by a sealer, values only.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArcSet {
+    /// The instance number of the set, `i=`.
+    pub instance: usize,
+    /// The state of the chain before this set.
+    pub chain_validation: ChainValidation,
+    /// Value of the `ARC-Authentication-Results` header.
+    pub authentication_results: String,
+    /// Value of the `ARC-Message-Signature` header.
+    pub message_signature: String,
+    /// Value of the `ARC-Seal` header.
+    pub seal: String,
+}
+
+impl ArcSet {
+    /// The headers of the set, in the order they must appear on top of the message.
+    #[must_use]
+    pub fn headers(&self) -> [(&'static str, &str); 3] {
+        [
+            (SEAL, &self.seal),
+            (MESSAGE_SIGNATURE, &self.message_signature),
+            (AUTHENTICATION_RESULTS, &self.authentication_results),
+        ]
+    }
+}
+
+fn tag<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    value.split(';').find_map(|tag| {
+        let (key, value) = tag.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// The public keys of the sealers of a chain, indexed by their DNS query (`selector._domainkey.sdid`).
+pub type PublicKeys = std::collections::HashMap<String, PublicKey>;
+
+fn dns_query(value: &str) -> Option<String> {
+    Some(format!("{}._domainkey.{}", tag(value, "s")?, tag(value, "d")?))
+}
+
+/// The value of a signature header, with an empty `b=` tag.
+fn without_signature(value: &str) -> String {
+    value
+        .split(';')
+        .map(|tag| match tag.split_once('=') {
+            Some((key, _)) if key.trim().eq_ignore_ascii_case("b") => format!("{key}="),
+            _ => tag.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Verify the `b=` tag of a signature header, `data` producing the signed data
+/// from the value of the header without its signature.
+fn verify_signature(
+    value: &str,
+    public_keys: &PublicKeys,
+    data: impl FnOnce(&str) -> String,
+) -> bool {
+    let (Some(signing_algorithm), Some(public_key), Some(signature)) = (
+        tag(value, "a").and_then(|a| a.parse::<SigningAlgorithm>().ok()),
+        dns_query(value).and_then(|query| public_keys.get(&query)),
+        tag(value, "b")
+            .and_then(|b| STANDARD.decode(b.split_whitespace().collect::<String>()).ok()),
+    ) else {
+        return false;
+    };
+
+    public_key
+        .inner
+        .verify(
+            &signing_algorithm
+                .get_preferred_hash_algo()
+                .hash(data(&without_signature(value))),
+            &signature,
+            signing_algorithm,
+        )
+        .is_ok()
+}
+
+/// The sets found in a message, indexed by instance.
+pub(super) struct Chain {
+    pub(super) sets: std::collections::BTreeMap<usize, [Vec<(String, String)>; 3]>,
+    // NOTE: an ARC header without a valid instance number.
+    malformed: bool,
+}
+
+impl Chain {
+    pub(super) fn new(message: &RawBody) -> Self {
+        let mut sets = std::collections::BTreeMap::<usize, [Vec<_>; 3]>::new();
+        let mut malformed = false;
+
+        for (key, value) in message.headers() {
+            let Some(position) = SET_HEADERS
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(key.trim()))
+            else {
+                continue;
+            };
+            tag(&value, "i")
+                .and_then(|i| i.parse::<usize>().ok())
+                .filter(|i| (1..=MAX_INSTANCE).contains(i))
+                .map_or_else(
+                    || malformed = true,
+                    |instance| sets.entry(instance).or_default()[position].push((key, value)),
+                );
+        }
+
+        Self { sets, malformed }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.sets.keys().next_back().copied().unwrap_or(0)
+    }
+
+    fn chain_validation_of(&self, instance: usize) -> Option<ChainValidation> {
+        match self.sets.get(&instance)?[2].as_slice() {
+            [(_, seal)] => tag(seal, "cv")?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value of the header `position` of the set `instance`, if the set is consistent.
+    fn header_of(&self, instance: usize, position: usize) -> Option<&str> {
+        match self.sets.get(&instance)?[position].as_slice() {
+            [(_, value)] => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The DNS queries of the public keys needed by [`Chain::validate`].
+    fn public_key_queries(&self) -> Vec<String> {
+        let latest = self.len();
+        let mut queries = (1..=latest)
+            .filter_map(|instance| self.header_of(instance, 2))
+            .chain(self.header_of(latest, 1))
+            .filter_map(dns_query)
+            .collect::<Vec<_>>();
+        queries.sort();
+        queries.dedup();
+        queries
+    }
+
+    /// Verify the signature and the body hash of the message signature of `instance`.
+    fn verify_message_signature(
+        &self,
+        message: &RawBody,
+        instance: usize,
+        public_keys: &PublicKeys,
+    ) -> bool {
+        let Some(message_signature) = self.header_of(instance, 1) else {
+            return false;
+        };
+        let (Some(signing_algorithm), Some(body_hash), Some(headers_field)) = (
+            tag(message_signature, "a").and_then(|a| a.parse::<SigningAlgorithm>().ok()),
+            tag(message_signature, "bh"),
+            tag(message_signature, "h"),
+        ) else {
+            return false;
+        };
+
+        let expected = STANDARD.encode(
+            signing_algorithm.get_preferred_hash_algo().hash(
+                canonicalization().canonicalize_body(
+                    &message
+                        .body()
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                ),
+            ),
+        );
+        if body_hash.split_whitespace().collect::<String>() != expected {
+            return false;
+        }
+
+        let headers_field = headers_field
+            .split(':')
+            .map(|header| header.trim().to_owned())
+            .collect::<Vec<_>>();
+        verify_signature(message_signature, public_keys, |unsigned| {
+            message_signature_input(message, &headers_field, unsigned)
+        })
+    }
+
+    /// Verify the signature of the seal of `instance`, covering the sets up to `instance`.
+    fn verify_seal(&self, instance: usize, public_keys: &PublicKeys) -> bool {
+        let (Some(authentication_results), Some(message_signature), Some(seal)) = (
+            self.header_of(instance, 0),
+            self.header_of(instance, 1),
+            self.header_of(instance, 2),
+        ) else {
+            return false;
+        };
+
+        let mut previous_headers = self.headers_below(instance);
+        previous_headers.push(format!("{AUTHENTICATION_RESULTS}:{authentication_results}"));
+        previous_headers.push(format!("{MESSAGE_SIGNATURE}:{message_signature}"));
+        verify_signature(seal, public_keys, |unsigned| {
+            seal_input(&previous_headers, unsigned)
+        })
+    }
+
+    /// Check the structure of the chain: one set per instance from 1 to the latest,
+    /// each containing exactly one header of each kind, and the `cv=` of the seals.
+    /// Then verify the signatures of all the seals, and of the latest message signature
+    /// (the previous ones can be broken by the modifications of the intermediaries).
+    pub(super) fn validate(
+        &self,
+        message: &RawBody,
+        public_keys: &PublicKeys,
+    ) -> Result<ChainValidation, ArcError> {
+        let latest = self.len();
+        if self.chain_validation_of(latest) == Some(ChainValidation::Fail) {
+            return Err(ArcError::ChainFailed(latest));
+        }
+        if latest >= MAX_INSTANCE {
+            return Err(ArcError::TooManySets);
+        }
+
+        let consistent = !self.malformed
+            && (1..=latest).all(|instance| {
+                self.sets
+                    .get(&instance)
+                    .map_or(false, |set| set.iter().all(|headers| headers.len() == 1))
+                    && self.chain_validation_of(instance)
+                        == Some(if instance == 1 {
+                            ChainValidation::None
+                        } else {
+                            ChainValidation::Pass
+                        })
+            });
+
+        let verified = || {
+            self.verify_message_signature(message, latest, public_keys)
+                && (1..=latest).all(|instance| self.verify_seal(instance, public_keys))
+        };
+
+        Ok(match (consistent, latest) {
+            (true, 0) => ChainValidation::None,
+            (true, _) if verified() => ChainValidation::Pass,
+            (false | true, _) => ChainValidation::Fail,
+        })
+    }
+
+    /// The headers of the sets below `instance`, in the order they are hashed by the seal.
+    pub(super) fn headers_below(&self, instance: usize) -> Vec<String> {
+        self.sets
+            .range(..instance)
+            .flat_map(|(_, set)| set.iter().flatten())
+            .map(|(key, value)| format!("{key}:{value}"))
+            .collect()
+    }
+}
+
+fn canonicalization() -> Canonicalization {
+    "relaxed/relaxed"
+        .parse()
+        .expect("arc only uses relaxed canonicalization")
+}
+
+/// The data signed by the `ARC-Message-Signature` header, whose `b=` tag is empty.
+pub(super) fn message_signature_input(
+    message: &RawBody,
+    headers_field: &[String],
+    message_signature: &str,
+) -> String {
+    let canonicalization = canonicalization();
+
+    let mut output = canonicalization.canonicalize_headers(&select_headers(headers_field, message));
+    output.push_str(
+        &canonicalization.canonicalize_header(&format!("{MESSAGE_SIGNATURE}:{message_signature}")),
+    );
+    output
+}
+
+/// The data signed by the `ARC-Seal` header, whose `b=` tag is empty.
+pub(super) fn seal_input(previous_headers: &[String], seal: &str) -> String {
+    let canonicalization = canonicalization();
+
+    let mut output = canonicalization.canonicalize_headers(previous_headers);
+    output.push_str(&canonicalization.canonicalize_header(&format!("{SEAL}:{seal}")));
+    output
+}
+
+fn sign(
+    private_key: &PrivateKey,
+    signing_algorithm: SigningAlgorithm,
+    data: &str,
+) -> Result<String, ArcError> {
+    private_key
+        .sign(
+            signing_algorithm,
+            &signing_algorithm.get_preferred_hash_algo().hash(data),
+        )
+        .map(|signature| STANDARD.encode(signature))
+        .map_err(|e| ArcError::Signing(SigningError::from(e)))
+}
+
+/// The DNS queries (`selector._domainkey.sdid`) of the public keys needed by [`seal`]
+/// to verify the chain of the message.
+#[must_use]
+pub fn public_key_queries(message: &RawBody) -> Vec<String> {
+    Chain::new(message).public_key_queries()
+}
+
+/// Produce the set of the next instance of the chain of the message.
+///
+/// The `ARC-Message-Signature` is a DKIM signature of `headers_field` and of the body,
+/// the `ARC-Seal` signs all the sets of the chain, including the new one.
+///
+/// The chain is verified with `public_keys` (see [`public_key_queries`]) before being extended,
+/// a missing key or a bad signature results in `cv=fail`.
+///
+/// # Errors
+///
+/// * the latest seal of the chain has `cv=fail`
+/// * the chain already contains 50 sets
+/// * the signature failed
+pub fn seal(
+    message: &RawBody,
+    private_key: &PrivateKey,
+    sdid: &str,
+    selector: &str,
+    authentication_results: &str,
+    headers_field: &[String],
+    public_keys: &PublicKeys,
+) -> Result<ArcSet, ArcError> {
+    let chain = Chain::new(message);
+    let chain_validation = chain.validate(message, public_keys)?;
+    let instance = chain.len() + 1;
+
+    let signing_algorithm = private_key.get_preferred_signing_algo();
+    let canonicalization = canonicalization();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+
+    let authentication_results = format!("i={instance}; {}", authentication_results.trim());
+
+    let headers_field = headers_field
+        .iter()
+        .filter(|header| !SET_HEADERS.iter().any(|i| i.eq_ignore_ascii_case(header)))
+        .cloned()
+        .collect::<Vec<_>>();
+    let body_hash = STANDARD.encode(
+        signing_algorithm.get_preferred_hash_algo().hash(
+            canonicalization.canonicalize_body(
+                &message
+                    .body()
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+            ),
+        ),
+    );
+    let mut message_signature = format!(
+        "i={instance}; a={signing_algorithm}; c={canonicalization}; d={sdid}; s={selector};\r\n\tt={timestamp}; h={};\r\n\tbh={body_hash};\r\n\tb=",
+        headers_field.join(":"),
+    );
+    message_signature.push_str(&sign(
+        private_key,
+        signing_algorithm,
+        &message_signature_input(message, &headers_field, &message_signature),
+    )?);
+
+    // NOTE: a broken chain cannot be hashed reliably, the seal only covers the new set.
+    let mut previous_headers = if chain_validation == ChainValidation::Fail {
+        vec![]
+    } else {
+        chain.headers_below(instance)
+    };
+    previous_headers.push(format!("{AUTHENTICATION_RESULTS}:{authentication_results}"));
+    previous_headers.push(format!("{MESSAGE_SIGNATURE}:{message_signature}"));
+
+    let mut seal = format!(
+        "i={instance}; a={signing_algorithm}; t={timestamp}; cv={chain_validation};\r\n\td={sdid}; s={selector};\r\n\tb="
+    );
+    seal.push_str(&sign(
+        private_key,
+        signing_algorithm,
+        &seal_input(&previous_headers, &seal),
+    )?);
+
+    Ok(ArcSet {
+        instance,
+        chain_validation,
+        authentication_results,
+        message_signature,
+        seal,
+    })
+}
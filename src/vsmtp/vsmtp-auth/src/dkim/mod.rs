@@ -16,6 +16,7 @@
 */
 
 mod algorithm;
+pub mod arc;
 mod canonicalization;
 mod private_key;
 mod public_key;
@@ -24,6 +25,7 @@ mod signature;
 
 #[cfg(test)]
 mod tests {
+    mod arc;
     mod hash_header;
     mod sign_verify;
     mod parse {
@@ -61,6 +63,12 @@ mod verify {
     pub struct VerificationResult {
         /// TODO: should be an enum
         pub status: String,
+        /// The signing domain identifier of the verified signature, if any.
+        #[serde(default)]
+        pub sdid: Option<String>,
+        /// The agent or user identifier of the verified signature, if any.
+        #[serde(default)]
+        pub auid: Option<String>,
     }
 
     #[must_use]
@@ -116,27 +116,9 @@ impl Signature {
     }
 
     pub(super) fn get_header_for_hash(&self, message: &RawBody) -> String {
-        let mut last_index = std::collections::HashMap::<String, usize>::new();
-
-        let headers = message.headers();
-
-        let mut output = vec![];
-        for header in &self.headers_field {
-            let idx = last_index
-                .get(&header.to_lowercase())
-                .map_or(headers.len(), |x| *x);
-
-            if let Some((pos, (key, value))) = headers[..idx]
-                .iter()
-                .enumerate()
-                .rfind(|(_, (key, _))| key.eq_ignore_ascii_case(header))
-            {
-                last_index.insert(key.to_lowercase(), pos);
-                output.push(format!("{key}:{value}"));
-            }
-        }
-
-        let mut output = self.canonicalization.canonicalize_headers(&output);
+        let mut output = self
+            .canonicalization
+            .canonicalize_headers(&select_headers(&self.headers_field, message));
 
         output.push_str(
             &self
@@ -157,6 +139,31 @@ impl Signature {
     }
 }
 
+/// Select the headers listed in `headers_field` from the bottom of the message,
+/// a name listed several times selects the next occurrence upward.
+pub(super) fn select_headers(headers_field: &[String], message: &RawBody) -> Vec<String> {
+    let mut last_index = std::collections::HashMap::<String, usize>::new();
+
+    let headers = message.headers();
+
+    let mut output = vec![];
+    for header in headers_field {
+        let idx = last_index
+            .get(&header.to_lowercase())
+            .map_or(headers.len(), |x| *x);
+
+        if let Some((pos, (key, value))) = headers[..idx]
+            .iter()
+            .enumerate()
+            .rfind(|(_, (key, _))| key.eq_ignore_ascii_case(header))
+        {
+            last_index.insert(key.to_lowercase(), pos);
+            output.push(format!("{key}:{value}"));
+        }
+    }
+    output
+}
+
 const HEADER_KEY_LOWER: &str = "dkim-signature:";
 
 impl std::str::FromStr for Signature {
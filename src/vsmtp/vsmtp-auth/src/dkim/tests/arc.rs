@@ -0,0 +1,226 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::dkim::{
+    arc::{
+        message_signature_input, public_key_queries, seal, seal_input, ArcError, ArcSet, Chain,
+        ChainValidation, PublicKeys, SEAL,
+    },
+    private_key::PrivateKey,
+    PublicKey, SigningAlgorithm,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use vsmtp_mail_parser::{MessageBody, RawBody};
+use vsmtp_test::config::local_msg;
+
+const HEADERS: [&str; 4] = ["From", "To", "Subject", "Date"];
+
+fn keys() -> (PrivateKey, PublicKey) {
+    let mut rng = rand::thread_rng();
+
+    let private_key = rsa::RsaPrivateKey::new(&mut rng, 1024).unwrap();
+    let public_key = PublicKey::try_from(rsa::RsaPublicKey::from(&private_key)).unwrap();
+
+    (PrivateKey::Rsa(Box::new(private_key)), public_key)
+}
+
+fn public_keys(public_key: PublicKey) -> PublicKeys {
+    [("foobar._domainkey.localhost".to_owned(), public_key)].into()
+}
+
+fn seal_message(
+    message: &MessageBody,
+    private_key: &PrivateKey,
+    public_keys: &PublicKeys,
+) -> Result<ArcSet, ArcError> {
+    seal(
+        message.inner(),
+        private_key,
+        "localhost",
+        "foobar",
+        "localhost; spf=pass smtp.mailfrom=localhost",
+        &HEADERS.map(str::to_string),
+        public_keys,
+    )
+}
+
+fn add_set(message: &mut MessageBody, set: &ArcSet) {
+    for (name, value) in set.headers().into_iter().rev() {
+        message.prepend_header(name, value);
+    }
+}
+
+fn unsigned(value: &str) -> &str {
+    &value[..value.rfind("b=").unwrap() + 2]
+}
+
+fn verify(public_key: &PublicKey, data: &str, signature: &str) {
+    public_key
+        .inner
+        .verify(
+            &SigningAlgorithm::RsaSha256
+                .get_preferred_hash_algo()
+                .hash(data),
+            &STANDARD.decode(signature).unwrap(),
+            SigningAlgorithm::RsaSha256,
+        )
+        .unwrap();
+}
+
+/// Verify the signatures of the latest set of `sealed`, `original` being the message before sealing.
+fn verify_latest_set(original: &RawBody, sealed: &RawBody, public_key: &PublicKey) {
+    let chain = Chain::new(sealed);
+    let instance = chain.len();
+    let [_, message_signature, seal] = &chain.sets[&instance];
+    let (message_signature, seal) = (&message_signature[0].1, &seal[0].1);
+
+    verify(
+        public_key,
+        &message_signature_input(
+            original,
+            &HEADERS.map(str::to_string),
+            unsigned(message_signature),
+        ),
+        message_signature.rsplit_once("b=").unwrap().1.trim(),
+    );
+
+    let mut previous_headers = chain.headers_below(instance + 1);
+    previous_headers.pop();
+    verify(
+        public_key,
+        &seal_input(&previous_headers, unsigned(seal)),
+        seal.rsplit_once("b=").unwrap().1.trim(),
+    );
+}
+
+#[test]
+fn first_instance() {
+    let (private_key, public_key) = keys();
+    let mut message = local_msg();
+    let original = message.inner().clone();
+
+    let set = seal_message(&message, &private_key, &PublicKeys::new()).unwrap();
+    assert_eq!(set.instance, 1);
+    assert_eq!(set.chain_validation, ChainValidation::None);
+    assert_eq!(
+        set.authentication_results,
+        "i=1; localhost; spf=pass smtp.mailfrom=localhost"
+    );
+    assert!(set.message_signature.starts_with("i=1; a=rsa-sha256;"));
+    assert!(set.seal.starts_with("i=1; a=rsa-sha256;"));
+    assert!(set.seal.contains("cv=none;"));
+
+    add_set(&mut message, &set);
+    assert!(message
+        .inner()
+        .raw_headers()
+        .first()
+        .unwrap()
+        .starts_with(SEAL));
+
+    verify_latest_set(&original, message.inner(), &public_key);
+}
+
+#[test]
+fn chained_instance() {
+    let (private_key, public_key) = keys();
+    let mut message = local_msg();
+
+    let public_keys = public_keys(public_key.clone());
+
+    let first = seal_message(&message, &private_key, &public_keys).unwrap();
+    add_set(&mut message, &first);
+    let original = message.inner().clone();
+    assert_eq!(
+        public_key_queries(message.inner()),
+        ["foobar._domainkey.localhost"]
+    );
+
+    let second = seal_message(&message, &private_key, &public_keys).unwrap();
+    assert_eq!(second.instance, 2);
+    assert_eq!(second.chain_validation, ChainValidation::Pass);
+    assert!(second.authentication_results.starts_with("i=2; "));
+    assert!(second.seal.contains("cv=pass;"));
+
+    add_set(&mut message, &second);
+    verify_latest_set(&original, message.inner(), &public_key);
+}
+
+#[test]
+fn broken_chain() {
+    let (private_key, _) = keys();
+    let mut message = local_msg();
+
+    // NOTE: a set without its message signature.
+    message.prepend_header(
+        "ARC-Authentication-Results",
+        "i=1; localhost; spf=pass smtp.mailfrom=localhost",
+    );
+    message.prepend_header(
+        "ARC-Seal",
+        "i=1; a=rsa-sha256; cv=none; d=localhost; s=foobar; b=",
+    );
+
+    let set = seal_message(&message, &private_key, &PublicKeys::new()).unwrap();
+    assert_eq!(set.instance, 2);
+    assert_eq!(set.chain_validation, ChainValidation::Fail);
+
+    add_set(&mut message, &set);
+    assert!(matches!(
+        seal_message(&message, &private_key, &PublicKeys::new()),
+        Err(ArcError::ChainFailed(2))
+    ));
+}
+
+#[test]
+fn unknown_public_key() {
+    let (private_key, _) = keys();
+    let mut message = local_msg();
+
+    let first = seal_message(&message, &private_key, &PublicKeys::new()).unwrap();
+    add_set(&mut message, &first);
+
+    let second = seal_message(&message, &private_key, &PublicKeys::new()).unwrap();
+    assert_eq!(second.chain_validation, ChainValidation::Fail);
+}
+
+#[test]
+fn forged_seal() {
+    let (private_key, public_key) = keys();
+    let (other_private_key, _) = keys();
+    let mut message = local_msg();
+
+    // NOTE: a set with a consistent structure, signed by another key than the one published.
+    let first = seal_message(&message, &other_private_key, &PublicKeys::new()).unwrap();
+    add_set(&mut message, &first);
+
+    let second = seal_message(&message, &private_key, &public_keys(public_key)).unwrap();
+    assert_eq!(second.chain_validation, ChainValidation::Fail);
+}
+
+#[test]
+fn modified_body() {
+    let (private_key, public_key) = keys();
+    let public_keys = public_keys(public_key);
+    let mut message = local_msg();
+
+    let first = seal_message(&message, &private_key, &public_keys).unwrap();
+    add_set(&mut message, &first);
+    message.append_body("-- appended after the seal\r\n").unwrap();
+
+    let second = seal_message(&message, &private_key, &public_keys).unwrap();
+    assert_eq!(second.chain_validation, ChainValidation::Fail);
+}
@@ -50,9 +50,12 @@ impl From<viaspf::QueryResult> for Result {
     }
 }
 
+/// Evaluate the SPF policy of `sender` for the client `ip`.
 ///
+/// The `resolver` can be any [`viaspf::lookup::Lookup`] implementation,
+/// usually the [`trust_dns_resolver::TokioAsyncResolver`] of the server.
 pub async fn evaluate(
-    resolver: &trust_dns_resolver::TokioAsyncResolver,
+    resolver: &impl viaspf::lookup::Lookup,
     ip: std::net::IpAddr,
     sender: &viaspf::Sender,
 ) -> Result {
@@ -60,3 +63,110 @@ pub async fn evaluate(
         .await
         .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, Details, Result};
+    use viaspf::lookup::{Lookup, LookupError, LookupResult, Name};
+
+    /// A DNS backend answering TXT queries from a static table.
+    struct MockLookup(std::collections::HashMap<&'static str, &'static str>);
+
+    #[async_trait::async_trait]
+    impl Lookup for MockLookup {
+        async fn lookup_a<'lookup, 'a>(
+            &'lookup self,
+            _: &'a Name,
+        ) -> LookupResult<Vec<std::net::Ipv4Addr>> {
+            Err(LookupError::NoRecords)
+        }
+
+        async fn lookup_aaaa<'lookup, 'a>(
+            &'lookup self,
+            _: &'a Name,
+        ) -> LookupResult<Vec<std::net::Ipv6Addr>> {
+            Err(LookupError::NoRecords)
+        }
+
+        async fn lookup_mx<'lookup, 'a>(&'lookup self, _: &'a Name) -> LookupResult<Vec<Name>> {
+            Err(LookupError::NoRecords)
+        }
+
+        async fn lookup_txt<'lookup, 'a>(
+            &'lookup self,
+            name: &'a Name,
+        ) -> LookupResult<Vec<String>> {
+            self.0
+                .get(name.as_str().trim_end_matches('.'))
+                .map(|txt| vec![(*txt).to_owned()])
+                .ok_or(LookupError::NoRecords)
+        }
+
+        async fn lookup_ptr<'lookup>(
+            &'lookup self,
+            _: std::net::IpAddr,
+        ) -> LookupResult<Vec<Name>> {
+            Err(LookupError::NoRecords)
+        }
+    }
+
+    async fn run(records: &[(&'static str, &'static str)], ip: &str, sender: &str) -> Result {
+        evaluate(
+            &MockLookup(records.iter().copied().collect()),
+            ip.parse().unwrap(),
+            &viaspf::Sender::from_address(sender).unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn include_chain() {
+        let records = [
+            ("example.com", "v=spf1 include:_spf.example.com -all"),
+            ("_spf.example.com", "v=spf1 include:_spf2.example.com ~all"),
+            ("_spf2.example.com", "v=spf1 ip4:192.0.2.0/24 -all"),
+        ];
+
+        let result = run(&records, "192.0.2.10", "john.doe@example.com").await;
+        assert_eq!(
+            result,
+            Result {
+                result: "pass".to_owned(),
+                details: Details::Mechanism("include:_spf.example.com".to_owned()),
+            }
+        );
+
+        let result = run(&records, "198.51.100.1", "john.doe@example.com").await;
+        assert_eq!(
+            result,
+            Result {
+                result: "fail".to_owned(),
+                details: Details::Mechanism("all".to_owned()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn plus_all() {
+        let result = run(
+            &[("example.com", "v=spf1 +all")],
+            "198.51.100.1",
+            "john.doe@example.com",
+        )
+        .await;
+
+        assert_eq!(
+            result,
+            Result {
+                result: "pass".to_owned(),
+                details: Details::Mechanism("all".to_owned()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn no_record() {
+        let result = run(&[], "198.51.100.1", "john.doe@example.com").await;
+        assert_eq!(result.result, "none");
+    }
+}
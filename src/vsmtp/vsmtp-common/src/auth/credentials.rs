@@ -33,6 +33,36 @@ pub enum Credentials {
         /// [ email / 1*255TCHAR ]
         token: String,
     },
+    /// verify the OAuth 2.0 token send by the `OAUTHBEARER` or `XOAUTH2` mechanism
+    BearerToken {
+        /// the user to authenticate as, optional for `OAUTHBEARER`
+        authid: Option<String>,
+        /// the token, without the `Bearer` scheme
+        token: String,
+    },
+    /// the login of a client authenticated by a trusted upstream MTA, forwarded with `XCLIENT`
+    Forwarded {
+        ///
+        authid: String,
+    },
+}
+
+impl Credentials {
+    /// The user the client authenticates as, `None` for the anonymous tokens
+    /// and the bearer tokens without `authid`.
+    #[must_use]
+    #[inline]
+    pub fn authid(&self) -> Option<&str> {
+        match self {
+            Self::Verify { authid, .. }
+            | Self::BearerToken {
+                authid: Some(authid),
+                ..
+            }
+            | Self::Forwarded { authid } => Some(authid),
+            Self::AnonymousToken { .. } | Self::BearerToken { authid: None, .. } => None,
+        }
+    }
 }
 
 #[cfg(not(debug_assertions))]
@@ -48,6 +78,15 @@ impl std::fmt::Debug for Credentials {
                 .debug_struct("Credentials::AnonymousToken")
                 .field("token", &"***")
                 .finish(),
+            Credentials::BearerToken { authid, .. } => f
+                .debug_struct("Credentials::BearerToken")
+                .field("authid", authid)
+                .field("token", &"***")
+                .finish(),
+            Credentials::Forwarded { authid } => f
+                .debug_struct("Credentials::Forwarded")
+                .field("authid", authid)
+                .finish(),
         }
     }
 }
@@ -73,6 +112,18 @@ impl serde::Serialize for Credentials {
                 s.serialize_field("token", "***")?;
                 s.end()
             }
+            Credentials::BearerToken { .. } => {
+                let mut s =
+                    serializer.serialize_struct_variant("Credentials", 2, "BearerToken", 2)?;
+                s.serialize_field("authid", "***")?;
+                s.serialize_field("token", "***")?;
+                s.end()
+            }
+            Credentials::Forwarded { .. } => {
+                let mut s = serializer.serialize_struct_variant("Credentials", 3, "Forwarded", 1)?;
+                s.serialize_field("authid", "***")?;
+                s.end()
+            }
         }
     }
 }
@@ -119,8 +170,41 @@ impl TryFrom<(&rsasl::callback::SessionData, &rsasl::callback::Context<'_>)> for
                     .ok_or(Error::MissingField)?
                     .to_owned(),
             }),
+            mech if mech == Mechanism::OAuthBearer.as_ref() => Ok(Self::BearerToken {
+                authid: context
+                    .get_ref::<rsasl::property::AuthzId>()
+                    .map(str::to_owned),
+                token: strip_bearer_scheme(
+                    context
+                        .get_ref::<rsasl::property::OAuthBearerToken>()
+                        .ok_or(Error::MissingField)?,
+                )
+                .to_owned(),
+            }),
+            // the `Bearer` scheme is already removed by the mechanism
+            mech if mech == Mechanism::XOAuth2.as_ref() => Ok(Self::BearerToken {
+                authid: Some(
+                    context
+                        .get_ref::<rsasl::property::AuthId>()
+                        .ok_or(Error::MissingField)?
+                        .to_owned(),
+                ),
+                token: context
+                    .get_ref::<rsasl::property::OAuthBearerToken>()
+                    .ok_or(Error::MissingField)?
+                    .to_owned(),
+            }),
             // mech if mech == Mechanism::CramMd5.as_ref() => todo!(),
             _ => Err(Error::Unimplemented),
         }
     }
 }
+
+/// The `auth` value of `OAUTHBEARER` is formatted as `Bearer <token>`,
+/// see <https://datatracker.ietf.org/doc/html/rfc6750#section-2.1>
+fn strip_bearer_scheme(value: &str) -> &str {
+    value
+        .split_once(' ')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map_or(value, |(_, token)| token.trim_start())
+}
@@ -43,6 +43,14 @@ pub enum Mechanism {
     /// Common
     /// See <https://datatracker.ietf.org/doc/html/rfc4505>
     Anonymous,
+    /// OAuth 2.0 bearer token
+    /// See <https://datatracker.ietf.org/doc/html/rfc7628>
+    #[strum(serialize = "OAUTHBEARER")]
+    OAuthBearer,
+    /// Non-standard predecessor of `OAUTHBEARER`, used by common providers
+    /// See <https://developers.google.com/gmail/imap/xoauth2-protocol>
+    #[strum(serialize = "XOAUTH2")]
+    XOAuth2,
     /*
     - EXTERNAL
     - SECURID
@@ -55,7 +63,6 @@ pub enum Mechanism {
     - OPENID20
     - GSSAPI
     - GS2-KRB5
-    - XOAUTH-2
     */
 }
 
@@ -65,7 +72,7 @@ impl Mechanism {
     #[must_use]
     pub const fn client_first(self) -> bool {
         match self {
-            Self::Plain | Self::Anonymous => true,
+            Self::Plain | Self::Anonymous | Self::OAuthBearer | Self::XOAuth2 => true,
             Self::Login | Self::CramMd5 => false,
         }
     }
@@ -75,7 +82,12 @@ impl Mechanism {
     #[must_use]
     pub const fn must_be_under_tls(self) -> bool {
         match self {
-            Self::Plain | Self::Login | Self::CramMd5 | Self::Anonymous => true,
+            Self::Plain
+            | Self::Login
+            | Self::CramMd5
+            | Self::Anonymous
+            | Self::OAuthBearer
+            | Self::XOAuth2 => true,
         }
     }
 }
@@ -91,6 +103,17 @@ mod tests {
         assert_eq!(Mechanism::Login.to_string(), "LOGIN");
         assert_eq!(Mechanism::CramMd5.to_string(), "CRAM-MD5");
         assert_eq!(Mechanism::Anonymous.to_string(), "ANONYMOUS");
+        assert_eq!(Mechanism::OAuthBearer.to_string(), "OAUTHBEARER");
+        assert_eq!(Mechanism::XOAuth2.to_string(), "XOAUTH2");
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            "OAUTHBEARER".parse::<Mechanism>().unwrap(),
+            Mechanism::OAuthBearer
+        );
+        assert_eq!("XOAUTH2".parse::<Mechanism>().unwrap(), Mechanism::XOAuth2);
     }
 
     #[test]
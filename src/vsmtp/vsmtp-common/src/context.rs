@@ -18,7 +18,7 @@ use crate::{
     auth::Credentials,
     status, transfer,
     transport::{AbstractTransport, DeliverTo, WrapperSerde},
-    Address, CipherSuite, ClientName, Domain, ProtocolVersion,
+    Address, CipherSuite, ClientName, Domain, DsnReturn, ProtocolVersion, RecipientDsn,
 };
 use vsmtp_auth::{dkim, spf};
 
@@ -151,6 +151,10 @@ macro_rules! after {
     };
 }
 
+fn elapsed_since(timestamp: &time::OffsetDateTime) -> std::time::Duration {
+    std::time::Duration::try_from(time::OffsetDateTime::now_utc() - *timestamp).unwrap_or_default()
+}
+
 impl Context {
     /// Get the current SMTP stage of the transaction
     #[inline]
@@ -202,6 +206,10 @@ impl Context {
                 skipped: None,
                 tls: None,
                 auth: None,
+                client_rdns: None,
+                connection_blocked: false,
+                dnsbl: std::collections::HashMap::new(),
+                tarpit: None,
             },
         })
     }
@@ -224,6 +232,7 @@ impl Context {
                     helo: HeloProperties {
                         client_name,
                         using_deprecated,
+                        capabilities: vec![],
                     },
                 });
                 Ok(self)
@@ -231,6 +240,7 @@ impl Context {
             Self::Helo(ContextHelo { helo, .. }) => {
                 helo.client_name = client_name;
                 helo.using_deprecated = using_deprecated;
+                helo.capabilities.clear();
                 Ok(self)
             }
             Self::MailFrom(_) | Self::RcptTo(_) | Self::Finished(_) => Err(Error::Conversion {}),
@@ -259,11 +269,19 @@ impl Context {
 
     /// Convert the context to a [`ContextMailFrom`] or overwrite the existing one
     ///
+    /// `require_tls` is the `REQUIRETLS` option of the `MAIL FROM` command (rfc 8689),
+    /// it must only be set if the session [`Self::is_secured`].
+    ///
     /// # Errors
     ///
     /// * state if not [`Stage::Helo`] or [`Stage::MailFrom`]
     #[inline]
-    pub fn to_mail_from(&mut self, reverse_path: Option<Address>, utf8: bool) -> Result<(), Error> {
+    pub fn to_mail_from(
+        &mut self,
+        reverse_path: Option<Address>,
+        utf8: bool,
+        require_tls: bool,
+    ) -> Result<(), Error> {
         match self {
             Self::Helo(ContextHelo { connect, helo }) => {
                 let now = time::OffsetDateTime::now_utc();
@@ -274,14 +292,22 @@ impl Context {
                         reverse_path,
                         mail_timestamp: now,
                         message_uuid: uuid::Uuid::new_v4(),
+                        transaction_id: uuid::Uuid::new_v4(),
                         spf: None,
                         utf8,
+                        require_tls,
+                        envelop_id: None,
+                        ret: None,
+                        asserted_sender: None,
+                        sender_has_mx: None,
                     },
                 });
                 Ok(())
             }
             Self::MailFrom(ContextMailFrom { mail_from, .. }) => {
                 mail_from.reverse_path = reverse_path;
+                mail_from.require_tls = require_tls;
+                mail_from.sender_has_mx = None;
                 Ok(())
             }
             Self::Connect(_) | Self::RcptTo(_) | Self::Finished(_) => Err(Error::Conversion {}),
@@ -307,7 +333,12 @@ impl Context {
                     helo: helo.clone(),
                     mail_from: mail_from.clone(),
                     rcpt_to: rcpt_to.clone(),
-                    finished: FinishedProperties { dkim: None },
+                    finished: FinishedProperties {
+                        dkim: None,
+                        dkim_public_keys: std::collections::HashMap::new(),
+                        authentication_results: false,
+                        quarantine: None,
+                    },
                 });
                 Ok(())
             }
@@ -423,7 +454,22 @@ impl Context {
         }
     }
 
-    /// Set the [`TlsProperties`] of the connection.
+    /// The user the client has authenticated as using the SMTP+SASL protocol,
+    /// `None` if the connection is not authenticated or the credentials have no user.
+    #[must_use]
+    #[inline]
+    pub fn auth_user(&self) -> Option<&str> {
+        self.auth()
+            .as_ref()
+            .filter(|auth| auth.authenticated)
+            .and_then(|auth| auth.credentials.as_ref())
+            .and_then(Credentials::authid)
+    }
+
+    /// Set the [`TlsProperties`] of the connection, and convert the context
+    /// back to a [`ContextConnect`]: the client must issue a new `EHLO`.
+    ///
+    /// See <https://datatracker.ietf.org/doc/html/rfc3207#section-4.2>
     ///
     /// # Errors
     ///
@@ -435,6 +481,7 @@ impl Context {
         protocol_version: rustls::ProtocolVersion,
         cipher_suite: rustls::CipherSuite,
         peer_certificates: Option<Vec<rustls::Certificate>>,
+        client_certificate: Option<ClientCertificate>,
         alpn_protocol: Option<Vec<u8>>,
     ) -> Result<(), Error> {
         match self {
@@ -442,12 +489,54 @@ impl Context {
                 connect.tls = Some(TlsProperties {
                     protocol_version: ProtocolVersion(protocol_version),
                     cipher_suite: CipherSuite(cipher_suite),
+                    client_certificate,
                     peer_certificates,
                     alpn_protocol,
+                    sni: sni.clone(),
                 });
                 if let Some(sni) = sni {
                     connect.server_name = sni;
                 }
+                *self = Self::Connect(ContextConnect {
+                    connect: connect.clone(),
+                });
+                Ok(())
+            }
+            Self::MailFrom(ContextMailFrom { .. })
+            | Self::RcptTo(ContextRcptTo { .. })
+            | Self::Finished(ContextFinished { .. }) => Err(Error::Conversion {}),
+        }
+    }
+
+    /// Overwrite the properties of the client with the ones forwarded by a trusted
+    /// upstream MTA with the `XCLIENT` command, and convert the context back to a
+    /// [`ContextConnect`], or to a [`ContextHelo`] if the name of the client is known.
+    ///
+    /// See <https://www.postfix.org/XCLIENT_README.html>
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Connect`] or [`Stage::Helo`]
+    #[inline]
+    pub fn to_forwarded(
+        &mut self,
+        client_addr: std::net::SocketAddr,
+        helo: Option<HeloProperties>,
+        auth: Option<AuthProperties>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Connect(ContextConnect { connect }) | Self::Helo(ContextHelo { connect, .. }) => {
+                if connect.client_addr.ip() != client_addr.ip() {
+                    connect.client_rdns = None;
+                    connect.dnsbl.clear();
+                }
+                connect.client_addr = client_addr;
+                connect.auth = auth;
+                let connect = connect.clone();
+                *self = match helo {
+                    Some(helo) => Self::Helo(ContextHelo { connect, helo }),
+                    None => Self::Connect(ContextConnect { connect }),
+                };
                 Ok(())
             }
             Self::MailFrom(ContextMailFrom { .. })
@@ -477,6 +566,46 @@ impl Context {
         }
     }
 
+    /// Get the capabilities advertised in the reply to `EHLO`, empty if the client used `HELO`.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Helo`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn advertised_capabilities(&self) -> Result<&[String], Error> {
+        match self {
+            Self::Connect(ContextConnect { .. }) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(Helo),
+            }
+            .into()),
+            Self::Helo(ContextHelo { helo, .. })
+            | Self::MailFrom(ContextMailFrom { helo, .. })
+            | Self::RcptTo(ContextRcptTo { helo, .. })
+            | Self::Finished(ContextFinished { helo, .. }) => Ok(&helo.capabilities),
+        }
+    }
+
+    /// Set the capabilities advertised in the reply to `EHLO`.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Helo`]
+    #[inline]
+    pub fn set_advertised_capabilities(&mut self, capabilities: Vec<String>) -> Result<(), Error> {
+        match self {
+            Self::Helo(ContextHelo { helo, .. }) => {
+                helo.capabilities = capabilities;
+                Ok(())
+            }
+            Self::Connect(ContextConnect { .. })
+            | Self::MailFrom(ContextMailFrom { .. })
+            | Self::RcptTo(ContextRcptTo { .. })
+            | Self::Finished(ContextFinished { .. }) => Err(Error::Conversion {}),
+        }
+    }
+
     /// Get the [`TlsProperties`] of the connection.
     #[must_use]
     #[inline]
@@ -490,6 +619,124 @@ impl Context {
         }
     }
 
+    /// Get the names of the client resolved from its address, `None` if not resolved yet.
+    #[must_use]
+    #[inline]
+    pub fn client_rdns(&self) -> Option<&[Domain]> {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => connect.client_rdns.as_deref(),
+        }
+    }
+
+    /// Set the names of the client resolved from its address.
+    #[inline]
+    pub fn set_client_rdns(&mut self, client_rdns: Vec<Domain>) {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => {
+                connect.client_rdns = Some(client_rdns);
+            }
+        }
+    }
+
+    /// Get the listing of the client address in the DNS blocklist `zone`,
+    /// `None` if the zone has not been queried yet, `Some(None)` if the address is not listed.
+    #[must_use]
+    #[inline]
+    pub fn dnsbl(&self, zone: &str) -> Option<Option<&DnsblListing>> {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => {
+                connect.dnsbl.get(zone).map(Option::as_ref)
+            }
+        }
+    }
+
+    /// Record the listing of the client address in the DNS blocklist `zone`.
+    #[inline]
+    pub fn set_dnsbl(&mut self, zone: String, listing: Option<DnsblListing>) {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => {
+                connect.dnsbl.insert(zone, listing);
+            }
+        }
+    }
+
+    /// Is the client address rejected by the `server.smtp.access` lists.
+    #[must_use]
+    #[inline]
+    pub const fn connection_blocked(&self) -> bool {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => connect.connection_blocked,
+        }
+    }
+
+    /// Record that the client address is rejected by the `server.smtp.access` lists.
+    #[inline]
+    pub fn set_connection_blocked(&mut self, connection_blocked: bool) {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => {
+                connect.connection_blocked = connection_blocked;
+            }
+        }
+    }
+
+    /// Request a delay before sending the reply of the current command.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or [`Stage::RcptTo`]
+    #[inline]
+    #[function_name::named]
+    pub fn set_tarpit(&mut self, delay: std::time::Duration) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::Finished(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: vec![Stage::MailFrom, Stage::RcptTo],
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. }) => {
+                connect.tarpit = Some(delay);
+                Ok(())
+            }
+        }
+    }
+
+    /// Take the delay requested before sending the reply of the current command.
+    #[inline]
+    pub fn take_tarpit(&mut self) -> Option<std::time::Duration> {
+        match self {
+            Self::Connect(ContextConnect { connect })
+            | Self::Helo(ContextHelo { connect, .. })
+            | Self::MailFrom(ContextMailFrom { connect, .. })
+            | Self::RcptTo(ContextRcptTo { connect, .. })
+            | Self::Finished(ContextFinished { connect, .. }) => connect.tarpit.take(),
+        }
+    }
+
     /// Get the [`AuthProperties`] of the connection.
     #[must_use]
     #[inline]
@@ -577,6 +824,7 @@ impl Context {
             | Self::RcptTo(ContextRcptTo { mail_from, .. })
             | Self::Finished(ContextFinished { mail_from, .. }) => {
                 mail_from.reverse_path = reverse_path;
+                mail_from.sender_has_mx = None;
                 Ok(())
             }
         }
@@ -602,6 +850,24 @@ impl Context {
         }
     }
 
+    /// Get the time elapsed since the TCP/IP connection, zero if the clock went backward.
+    #[must_use]
+    #[inline]
+    pub fn connection_age(&self) -> std::time::Duration {
+        elapsed_since(self.connection_timestamp())
+    }
+
+    /// Get the time elapsed since the `MAIL FROM` has been received, zero if the clock
+    /// went backward.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    pub fn transaction_age(&self) -> Result<std::time::Duration, Error> {
+        self.mail_timestamp().map(elapsed_since)
+    }
+
     /// Get the message id
     ///
     /// # Errors
@@ -622,6 +888,26 @@ impl Context {
         }
     }
 
+    /// Get the identifier of the transaction, generated when the `MAIL FROM` is received.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn transaction_id(&self) -> Result<&uuid::Uuid, Error> {
+        match self {
+            Self::Connect { .. } | Self::Helo { .. } => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => Ok(&mail_from.transaction_id),
+        }
+    }
+
     /// Generate a new message id in the context
     ///
     /// # Errors
@@ -657,6 +943,224 @@ impl Context {
         }
     }
 
+    /// Check if the client requested the message to be relayed only over TLS (rfc 8689).
+    ///
+    /// Unlike [`Self::is_secured`], which describes the incoming session, this flag
+    /// applies to every hop the message will take.
+    #[inline]
+    #[must_use]
+    pub fn is_require_tls(&self) -> bool {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => false,
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => mail_from.require_tls,
+        }
+    }
+
+    /// Set the DSN arguments of the `MAIL FROM` command (rfc 3461).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn set_mail_from_dsn(
+        &mut self,
+        envelop_id: Option<String>,
+        ret: Option<DsnReturn>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => {
+                mail_from.envelop_id = envelop_id;
+                mail_from.ret = ret;
+                Ok(())
+            }
+        }
+    }
+
+    /// Set the `AUTH` argument of the `MAIL FROM` command (rfc 4954).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn set_asserted_sender(&mut self, asserted_sender: Option<Address>) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => {
+                mail_from.asserted_sender = asserted_sender;
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the `AUTH` argument of the `MAIL FROM` command (rfc 4954).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn asserted_sender(&self) -> Result<Option<&Address>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => {
+                Ok(mail_from.asserted_sender.as_ref())
+            }
+        }
+    }
+
+    /// Get the result of the MX lookup of the sender domain, `None` if it has not been done yet.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn sender_has_mx(&self) -> Result<Option<bool>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => Ok(mail_from.sender_has_mx),
+        }
+    }
+
+    /// Record the result of the MX lookup of the sender domain, for the rest of the transaction.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn set_sender_has_mx(&mut self, has_mx: bool) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => {
+                mail_from.sender_has_mx = Some(has_mx);
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the `ENVID` argument of the `MAIL FROM` command (rfc 3461).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn envelop_id(&self) -> Result<Option<&str>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => {
+                Ok(mail_from.envelop_id.as_deref())
+            }
+        }
+    }
+
+    /// Get the `RET` argument of the `MAIL FROM` command (rfc 3461).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::MailFrom`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn dsn_return(&self) -> Result<Option<&DsnReturn>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(MailFrom),
+            }
+            .into()),
+            Self::MailFrom(ContextMailFrom { mail_from, .. })
+            | Self::RcptTo(ContextRcptTo { mail_from, .. })
+            | Self::Finished(ContextFinished { mail_from, .. }) => Ok(mail_from.ret.as_ref()),
+        }
+    }
+
+    /// Set the DSN arguments of the `RCPT TO` command of a recipient (rfc 3461).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::RcptTo`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn set_recipient_dsn(
+        &mut self,
+        forward_path: Address,
+        dsn: RecipientDsn,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(RcptTo),
+            }
+            .into()),
+            Self::RcptTo(ContextRcptTo { rcpt_to, .. })
+            | Self::Finished(ContextFinished { rcpt_to, .. }) => {
+                rcpt_to.dsn.insert(forward_path, dsn);
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the DSN arguments of the `RCPT TO` command of a recipient (rfc 3461).
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::RcptTo`] or after
+    #[inline]
+    #[function_name::named]
+    pub fn recipient_dsn(&self, forward_path: &Address) -> Result<Option<&RecipientDsn>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) => Err(FieldAccessError {
+                field: function_name!().to_owned(),
+                stage: after!(RcptTo),
+            }
+            .into()),
+            Self::RcptTo(ContextRcptTo { rcpt_to, .. })
+            | Self::Finished(ContextFinished { rcpt_to, .. }) => Ok(rcpt_to.dsn.get(forward_path)),
+        }
+    }
+
     /// Add a recipient at the end of the list of forward paths.
     /// If the state was [`Stage::MailFrom`], the state is changed to [`Stage::RcptTo`].
     ///
@@ -694,6 +1198,7 @@ impl Context {
                         ))
                         .collect::<_>(),
                         forward_paths: vec![forward_path],
+                        dsn: std::collections::HashMap::new(),
                     },
                 });
                 Ok(())
@@ -733,6 +1238,7 @@ impl Context {
             Self::RcptTo(ContextRcptTo { rcpt_to, .. })
             | Self::Finished(ContextFinished { rcpt_to, .. }) => {
                 rcpt_to.forward_paths.retain(|rcpt| rcpt != forward_path);
+                rcpt_to.dsn.remove(forward_path);
 
                 for rcpts in &mut rcpt_to.delivery.values_mut() {
                     if let Some(index) = rcpts.iter().position(|(rcpt, _)| *rcpt == *forward_path) {
@@ -933,6 +1439,7 @@ impl Context {
                         transaction_type,
                         delivery: std::collections::HashMap::new(),
                         forward_paths: vec![],
+                        dsn: std::collections::HashMap::new(),
                     },
                 });
                 Ok(())
@@ -1031,6 +1538,128 @@ impl Context {
         }
     }
 
+    /// Get the public keys already fetched for the DKIM signatures using the DNS `query`.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Finished`]
+    #[inline]
+    #[function_name::named]
+    pub fn dkim_public_keys(
+        &self,
+        query: &str,
+    ) -> Result<Option<&Result<Vec<dkim::PublicKey>, String>>, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) | Self::RcptTo(_) => {
+                Err(FieldAccessError {
+                    field: function_name!().to_owned(),
+                    stage: after!(Finished),
+                }
+                .into())
+            }
+            Self::Finished(ContextFinished { finished, .. }) => {
+                Ok(finished.dkim_public_keys.get(query))
+            }
+        }
+    }
+
+    /// Store the public keys fetched with the DNS `query`, or the DKIM status of the failed lookup.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Finished`]
+    #[inline]
+    #[function_name::named]
+    pub fn set_dkim_public_keys(
+        &mut self,
+        query: String,
+        keys: Result<Vec<dkim::PublicKey>, String>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) | Self::RcptTo(_) => {
+                Err(FieldAccessError {
+                    field: function_name!().to_owned(),
+                    stage: after!(Finished),
+                }
+                .into())
+            }
+            Self::Finished(ContextFinished { finished, .. }) => {
+                finished.dkim_public_keys.insert(query, keys);
+                Ok(())
+            }
+        }
+    }
+
+    /// Record the quarantine of the message in the folder `name`, now.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Finished`]
+    #[inline]
+    #[function_name::named]
+    pub fn set_quarantine(&mut self, name: String, reason: String) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) | Self::RcptTo(_) => {
+                Err(FieldAccessError {
+                    field: function_name!().to_owned(),
+                    stage: after!(Finished),
+                }
+                .into())
+            }
+            Self::Finished(ContextFinished { finished, .. }) => {
+                finished.quarantine = Some(QuarantineProperties {
+                    name,
+                    reason,
+                    timestamp: time::OffsetDateTime::now_utc(),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Has the server added its `Authentication-Results` header during this transaction.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Finished`]
+    #[inline]
+    #[function_name::named]
+    pub fn authentication_results(&self) -> Result<bool, Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) | Self::RcptTo(_) => {
+                Err(FieldAccessError {
+                    field: function_name!().to_owned(),
+                    stage: after!(Finished),
+                }
+                .into())
+            }
+            Self::Finished(ContextFinished { finished, .. }) => Ok(finished.authentication_results),
+        }
+    }
+
+    /// Record that the server has added its `Authentication-Results` header.
+    ///
+    /// # Errors
+    ///
+    /// * state if not [`Stage::Finished`]
+    #[inline]
+    #[function_name::named]
+    pub fn set_authentication_results(&mut self) -> Result<(), Error> {
+        match self {
+            Self::Connect(_) | Self::Helo(_) | Self::MailFrom(_) | Self::RcptTo(_) => {
+                Err(FieldAccessError {
+                    field: function_name!().to_owned(),
+                    stage: after!(Finished),
+                }
+                .into())
+            }
+            Self::Finished(ContextFinished { finished, .. }) => {
+                finished.authentication_results = true;
+                Ok(())
+            }
+        }
+    }
+
     /// Convert the instance into a [`ContextFinished`].
     ///
     /// # Errors
@@ -1068,6 +1697,32 @@ pub struct TlsProperties {
     pub peer_certificates: Option<Vec<rustls::Certificate>>,
     ///
     pub alpn_protocol: Option<Vec<u8>>,
+    /// Server name indicated by the client during the handshake
+    #[serde(default)]
+    pub sni: Option<Domain>,
+    /// Identity of the client, if it has presented a certificate
+    #[serde(default)]
+    pub client_certificate: Option<ClientCertificate>,
+}
+
+/// Identity of a client authenticated with a certificate during the TLS handshake
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "testing", derive(PartialEq, Eq))]
+pub struct ClientCertificate {
+    /// Distinguished name of the subject, for example `CN=client.com, O=Example`
+    pub subject: String,
+    /// Alternative names of the subject, prefixed by their type (`DNS:`, `email:`, `URI:` or `IP:`)
+    pub subject_alt_names: Vec<String>,
+}
+
+/// Listing of an address in a DNS blocklist, see <https://www.rfc-editor.org/rfc/rfc5782>
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "testing", derive(PartialEq, Eq))]
+pub struct DnsblListing {
+    /// The A records of the query, usually in `127.0.0.0/8`, encoding the reason of the listing
+    pub records: Vec<std::net::Ipv4Addr>,
+    /// The TXT record of the query, if the zone provides one
+    pub explanation: Option<String>,
 }
 
 fn de_peer_certificates<'de, D>(
@@ -1127,6 +1782,19 @@ pub struct ConnectProperties {
     pub tls: Option<TlsProperties>,
     ///
     pub auth: Option<AuthProperties>,
+    /// Names of the client from the PTR records of its address, `None` until resolved.
+    #[serde(default)]
+    pub client_rdns: Option<Vec<Domain>>,
+    /// The client address is rejected by the `server.smtp.access` lists.
+    #[serde(default)]
+    pub connection_blocked: bool,
+    /// Listings of the client address in the DNS blocklists queried by the rules,
+    /// by zone, `None` if the address is not listed in the zone.
+    #[serde(default)]
+    pub dnsbl: std::collections::HashMap<String, Option<DnsblListing>>,
+    /// Delay requested by `tarpit()` before sending the reply of the current command.
+    #[serde(skip)]
+    pub tarpit: Option<std::time::Duration>,
 }
 
 /// Properties accessible after the HELO/EHLO command
@@ -1137,6 +1805,9 @@ pub struct HeloProperties {
     pub client_name: ClientName,
     ///
     pub using_deprecated: bool,
+    /// Capabilities advertised in the reply to `EHLO`, empty if the client used `HELO`.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
 }
 
 /// Properties accessible after the MAIL FROM command
@@ -1150,10 +1821,30 @@ pub struct MailFromProperties {
     pub mail_timestamp: time::OffsetDateTime,
     ///
     pub message_uuid: uuid::Uuid,
+    /// identifier of the transaction, shared by the messages produced by the transaction
+    /// (unlike `message_uuid`), to correlate the logs and the files written by the rules.
+    #[serde(default = "uuid::Uuid::new_v4")]
+    pub transaction_id: uuid::Uuid,
     ///
     pub spf: Option<spf::Result>,
     /// the transaction should support utf8 content
     pub utf8: bool,
+    /// the message must only be relayed over TLS-protected sessions (rfc 8689)
+    #[serde(default)]
+    pub require_tls: bool,
+    /// `ENVID` argument of the `MAIL FROM` command (rfc 3461)
+    #[serde(default)]
+    pub envelop_id: Option<String>,
+    /// `RET` argument of the `MAIL FROM` command (rfc 3461)
+    #[serde(default)]
+    pub ret: Option<DsnReturn>,
+    /// `AUTH` argument of the `MAIL FROM` command (rfc 4954), the identity of the submitter
+    /// asserted by a trusted client, `None` if the submitter is unknown
+    #[serde(default)]
+    pub asserted_sender: Option<Address>,
+    /// the sender domain accepts mails (MX, A or AAAA records), `None` until `sender_has_mx()` is called
+    #[serde(skip)]
+    pub sender_has_mx: Option<bool>,
 }
 
 /// Properties accessible after the RCPT TO command
@@ -1166,6 +1857,9 @@ pub struct RcptToProperties {
     pub delivery: std::collections::HashMap<WrapperSerde, DeliverTo>,
     ///
     pub transaction_type: TransactionType,
+    /// `NOTIFY` and `ORCPT` arguments of the `RCPT TO` commands (rfc 3461)
+    #[serde(default)]
+    pub dsn: std::collections::HashMap<Address, RecipientDsn>,
 }
 
 /// Properties accessible once the message has been fully received
@@ -1174,6 +1868,29 @@ pub struct RcptToProperties {
 pub struct FinishedProperties {
     ///
     pub dkim: Option<dkim::VerificationResult>,
+    /// The public keys fetched for the DKIM signatures of the message, by DNS query,
+    /// or the DKIM status of the failed lookup
+    #[serde(skip)]
+    pub dkim_public_keys: std::collections::HashMap<String, Result<Vec<dkim::PublicKey>, String>>,
+    /// The server has added its `Authentication-Results` header during this transaction.
+    #[serde(default)]
+    pub authentication_results: bool,
+    /// The quarantine of the message requested by the rules.
+    #[serde(default)]
+    pub quarantine: Option<QuarantineProperties>,
+}
+
+/// Name, reason and time of the quarantine of a message
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "testing", derive(PartialEq, Eq))]
+pub struct QuarantineProperties {
+    /// Name of the quarantine folder
+    pub name: String,
+    /// Why the message has been placed in quarantine
+    pub reason: String,
+    /// When the message has been placed in quarantine
+    #[serde(with = "time::serde::iso8601")]
+    pub timestamp: time::OffsetDateTime,
 }
 #[doc(hidden)]
 #[allow(clippy::module_name_repetitions)]
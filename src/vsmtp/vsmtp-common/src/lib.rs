@@ -73,6 +73,8 @@ mod types {
     pub mod address;
     pub mod client_name;
     pub mod domain;
+    pub mod dsn;
+    pub mod rejection_reason;
     pub mod reply;
     pub mod reply_code;
     pub mod target;
@@ -84,6 +86,8 @@ pub use types::{
     address::Address,
     client_name::ClientName,
     domain::{domain_iter, Domain},
+    dsn::{DsnReturn, NotifyOn, OriginalRecipient, RecipientDsn},
+    rejection_reason::RejectionReason,
     reply::Reply,
     reply_code::*,
     target::Target,
@@ -96,9 +100,10 @@ pub mod transport;
 
 mod context;
 pub use context::{
-    AuthProperties, ConnectProperties, Context, ContextConnect, ContextFinished, ContextHelo,
-    ContextMailFrom, ContextRcptTo, Error, FieldAccessError, FinishedProperties, HeloProperties,
-    MailFromProperties, RcptToProperties, Stage, TlsProperties, TransactionType,
+    AuthProperties, ClientCertificate, ConnectProperties, Context, ContextConnect, ContextFinished,
+    ContextHelo, ContextMailFrom, ContextRcptTo, DnsblListing, Error, FieldAccessError,
+    FinishedProperties, HeloProperties, MailFromProperties, QuarantineProperties, RcptToProperties,
+    Stage, TlsProperties, TransactionType,
 };
 
 /// abstraction of the libc
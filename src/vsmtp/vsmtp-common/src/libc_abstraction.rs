@@ -115,6 +115,25 @@ pub fn chown(path: &std::path::Path, user: Option<u32>, group: Option<u32>) -> a
     }
 }
 
+/// Apply an exclusive advisory lock on an open file, blocking until it is available.
+/// The lock is released when the file is closed.
+///
+/// # Errors
+///
+/// see flock(2) ERRORS
+#[inline]
+pub fn flock_exclusive(file: &std::fs::File) -> anyhow::Result<()> {
+    #[allow(unsafe_code)]
+    // SAFETY: ffi call, the file descriptor is valid for the lifetime of `file`
+    match unsafe { libc::flock(std::os::unix::io::AsRawFd::as_raw_fd(file), libc::LOCK_EX) } {
+        0i32 => Ok(()),
+        _ => Err(anyhow::anyhow!(
+            "flock: '{}'",
+            std::io::Error::last_os_error()
+        )),
+    }
+}
+
 /// Returns the index of the network interface corresponding to the name `@name`
 ///
 /// # Errors
@@ -14,7 +14,9 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use crate::libc_abstraction::{chown, if_indextoname, if_nametoindex, setgid, setuid};
+use crate::libc_abstraction::{
+    chown, flock_exclusive, if_indextoname, if_nametoindex, setgid, setuid,
+};
 
 #[test]
 fn test_setuid_current() {
@@ -72,3 +74,26 @@ fn test_chown_file() {
 
     std::fs::remove_file(file_to_create).unwrap();
 }
+
+#[test]
+fn test_flock_exclusive() {
+    let path = "./flock_exclusive";
+    let file = std::fs::File::create(path).unwrap();
+    flock_exclusive(&file).unwrap();
+
+    let other = std::fs::File::open(path).unwrap();
+    #[allow(unsafe_code)]
+    // SAFETY: ffi call
+    let locked = unsafe {
+        libc::flock(
+            std::os::unix::io::AsRawFd::as_raw_fd(&other),
+            libc::LOCK_EX | libc::LOCK_NB,
+        )
+    };
+    assert_eq!(locked, -1i32);
+
+    drop(file);
+    flock_exclusive(&other).unwrap();
+
+    std::fs::remove_file(path).unwrap();
+}
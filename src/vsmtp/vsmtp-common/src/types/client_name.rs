@@ -25,9 +25,9 @@ use crate::Domain;
 pub enum ClientName {
     /// FQDN of the client.
     Domain(Domain),
-    /// IP address of the client.
+    /// IP address of the client, displayed as an address literal (`[192.0.2.1]`).
     Ip4(std::net::Ipv4Addr),
-    /// IP address of the client.
+    /// IP address of the client, displayed as an address literal (`[IPv6:2001:db8::1]`).
     Ip6(std::net::Ipv6Addr),
 }
 
@@ -36,8 +36,8 @@ impl std::fmt::Display for ClientName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Domain(domain) => write!(f, "{domain}"),
-            Self::Ip4(ip) => write!(f, "{ip}"),
-            Self::Ip6(ip) => write!(f, "{ip}"),
+            Self::Ip4(ip) => write!(f, "[{ip}]"),
+            Self::Ip6(ip) => write!(f, "[IPv6:{ip}]"),
         }
     }
 }
@@ -0,0 +1,83 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::Address;
+
+/// <https://www.rfc-editor.org/rfc/rfc3461>
+/// return either the full message or only the headers.
+/// Only applies to DSNs that indicate delivery failure for at least one recipient.
+/// If a DSN contains no indications of delivery failure, only the headers of the message should be returned.
+#[allow(clippy::exhaustive_enums, clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DsnReturn {
+    /// Complete message
+    Full,
+    /// Only the message headers
+    Headers,
+}
+
+/// <https://www.rfc-editor.org/rfc/rfc3461>
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::exhaustive_enums)]
+pub enum NotifyOn {
+    /// This message must explicitly not produce a DSN.
+    Never,
+    // NOTE: this should be implemented as a bitmask
+    /// One or more scenarios that should produce a DSN.
+    Some {
+        /// The delivery of the message to the recipient was successful.
+        success: bool,
+        /// The delivery of the message to the recipient failed.
+        failure: bool,
+        /// The delivery of the message to the recipient has been delayed.
+        delay: bool,
+    },
+}
+
+impl Default for NotifyOn {
+    /// Only the failures produce a DSN if the client did not use the `NOTIFY` argument.
+    #[inline]
+    fn default() -> Self {
+        Self::Some {
+            success: false,
+            failure: true,
+            delay: false,
+        }
+    }
+}
+
+/// <https://www.rfc-editor.org/rfc/rfc3461>
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[allow(clippy::exhaustive_structs)]
+pub struct OriginalRecipient {
+    /// The type of address used in the `ORCPT` argument. (rfc822)
+    pub addr_type: String,
+    /// The original recipient address.
+    pub mailbox: Address,
+}
+
+/// DSN arguments of the `RCPT TO` command, stored for each recipient.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[allow(clippy::exhaustive_structs, clippy::module_name_repetitions)]
+pub struct RecipientDsn {
+    /// `NOTIFY` argument of the `RCPT TO` command
+    pub notify_on: NotifyOn,
+    /// `ORCPT` argument of the `RCPT TO` command
+    pub original_forward_path: Option<OriginalRecipient>,
+}
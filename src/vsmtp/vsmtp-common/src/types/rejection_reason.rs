@@ -0,0 +1,188 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::Reply;
+
+/// Reason of a rejection sent to the client, used to look up the reply
+/// configured in `server.smtp.replies`.
+#[allow(clippy::exhaustive_enums, clippy::module_name_repetitions)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// The client address is denied by the access lists.
+    ConnectionRefused,
+    /// The client sent too many recipients in the transaction.
+    TooManyRecipients,
+    /// The client has exceeded the rate limit of recipients.
+    RateLimited,
+    /// The size declared with `MAIL FROM` exceeds the maximum size of a message.
+    MessageTooBig,
+    /// `REQUIRETLS` was requested on an unencrypted session.
+    RequireTls,
+    /// The client is not allowed to use `XCLIENT`.
+    XClientUnauthorized,
+    /// The listener requires `STARTTLS` before the transaction.
+    TlsRequired,
+    /// The listener requires the client to authenticate before the transaction.
+    AuthRequired,
+    /// `code::c554_7_1()`
+    RelayDenied,
+    /// `code::c550_7_20()`
+    DkimNotFound,
+    /// `code::c550_7_21()`
+    DkimNotAcceptable,
+    /// `code::c550_7_22()`
+    DkimAuthorMismatch,
+    /// `code::c550_7_23()`
+    SpfFailed,
+    /// `code::c550_7_24()`
+    SpfError,
+    /// `code::c550_7_25()`
+    ReverseDnsFailed,
+    /// `code::c500_7_26()`
+    MultipleAuthenticationFailed,
+    /// `code::c550_7_27()`
+    SenderNullMx,
+    /// `code::c556_1_10()`
+    RecipientNullMx,
+    /// `code::c451_7_1()`
+    Greylisted,
+    /// `code::c451_3_0()`
+    MultipleDestinations,
+    /// `code::c550_1_1()`
+    UnknownAccount,
+    /// The message has more `Received` headers than `server.smtp.received_count_max`.
+    TooManyHops,
+}
+
+impl RejectionReason {
+    /// Reply sent to the client when none is configured for this reason.
+    ///
+    /// # Panics
+    ///
+    /// * if one of the default replies is not a valid reply, which is a bug
+    #[inline]
+    #[allow(clippy::expect_used)]
+    pub fn default_reply(self) -> Reply {
+        match self {
+            Self::ConnectionRefused => "554 5.7.1 Connection refused\r\n",
+            Self::TooManyRecipients => "452 Requested action not taken: too many recipients\r\n",
+            Self::RateLimited => "450 4.7.1 Too many recipients, try again later\r\n",
+            Self::MessageTooBig => "552 5.3.4 Message size exceeds fixed maximum message size\r\n",
+            Self::RequireTls => "530 5.7.10 REQUIRETLS needs a TLS-protected session\r\n",
+            Self::XClientUnauthorized => "550 5.7.0 Insufficient authorization\r\n",
+            Self::TlsRequired => "530 5.7.0 Must issue a STARTTLS command first\r\n",
+            Self::AuthRequired => "530 5.7.0 Authentication required\r\n",
+            Self::RelayDenied => "554 5.7.1 Relay access denied\r\n",
+            Self::DkimNotFound => "550 5.7.20 No passing DKIM signature found\r\n",
+            Self::DkimNotAcceptable => "550 5.7.21 No acceptable DKIM signature found\r\n",
+            Self::DkimAuthorMismatch => {
+                "550 5.7.22 No valid author-matched DKIM signature found\r\n"
+            }
+            Self::SpfFailed => "550 5.7.23 SPF validation failed\r\n",
+            Self::SpfError => "550 5.7.24 SPF validation error\r\n",
+            Self::ReverseDnsFailed => "550 5.7.25 Reverse DNS validation failed\r\n",
+            Self::MultipleAuthenticationFailed => {
+                "500 5.7.26 Multiple authentication checks failed\r\n"
+            }
+            Self::SenderNullMx => "550 5.7.27 Sender address has null MX\r\n",
+            Self::RecipientNullMx => "556 5.1.10 Recipient address has null MX\r\n",
+            Self::Greylisted => "451 4.7.1 Sender is not authorized. Please try again.\r\n",
+            Self::MultipleDestinations => {
+                "451 4.3.0 Multiple destination domains per transaction is unsupported. Please try again.\r\n"
+            }
+            Self::UnknownAccount => {
+                "550 5.1.1 The email account that you tried to reach does not exist. Please try again.\r\n"
+            }
+            Self::TooManyHops => "554 5.4.6 Too many hops\r\n",
+        }
+        .parse()
+        .expect("valid reply")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RejectionReason;
+
+    #[test]
+    fn default_replies() {
+        assert_eq!(
+            RejectionReason::RelayDenied.default_reply().to_string(),
+            "554 5.7.1 Relay access denied\r\n"
+        );
+        assert_eq!(
+            RejectionReason::DkimNotFound.default_reply().to_string(),
+            "550 5.7.20 No passing DKIM signature found\r\n"
+        );
+        assert_eq!(
+            RejectionReason::DkimNotAcceptable
+                .default_reply()
+                .to_string(),
+            "550 5.7.21 No acceptable DKIM signature found\r\n"
+        );
+        assert_eq!(
+            RejectionReason::DkimAuthorMismatch
+                .default_reply()
+                .to_string(),
+            "550 5.7.22 No valid author-matched DKIM signature found\r\n"
+        );
+        assert_eq!(
+            RejectionReason::SpfFailed.default_reply().to_string(),
+            "550 5.7.23 SPF validation failed\r\n"
+        );
+        assert_eq!(
+            RejectionReason::SpfError.default_reply().to_string(),
+            "550 5.7.24 SPF validation error\r\n"
+        );
+        assert_eq!(
+            RejectionReason::ReverseDnsFailed
+                .default_reply()
+                .to_string(),
+            "550 5.7.25 Reverse DNS validation failed\r\n"
+        );
+        assert_eq!(
+            RejectionReason::MultipleAuthenticationFailed
+                .default_reply()
+                .to_string(),
+            "500 5.7.26 Multiple authentication checks failed\r\n"
+        );
+        assert_eq!(
+            RejectionReason::SenderNullMx.default_reply().to_string(),
+            "550 5.7.27 Sender address has null MX\r\n"
+        );
+        assert_eq!(
+            RejectionReason::RecipientNullMx.default_reply().to_string(),
+            "556 5.1.10 Recipient address has null MX\r\n"
+        );
+        assert_eq!(
+            RejectionReason::Greylisted.default_reply().to_string(),
+            "451 4.7.1 Sender is not authorized. Please try again.\r\n"
+        );
+        assert_eq!(RejectionReason::MultipleDestinations.default_reply().to_string(), "451 4.3.0 Multiple destination domains per transaction is unsupported. Please try again.\r\n");
+        assert_eq!(
+            RejectionReason::UnknownAccount.default_reply().to_string(),
+            "550 5.1.1 The email account that you tried to reach does not exist. Please try again.\r\n"
+        );
+        assert_eq!(
+            RejectionReason::TooManyHops.default_reply().to_string(),
+            "554 5.4.6 Too many hops\r\n"
+        );
+    }
+}
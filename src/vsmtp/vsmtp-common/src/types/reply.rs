@@ -151,6 +151,65 @@ impl Reply {
             .collect::<String>()
     }
 
+    /// Return the reply with the enhanced status code `enhanced`, if it has none.
+    ///
+    /// ```
+    /// # use vsmtp_common::Reply;
+    /// let reply = "250 Ok\r\n".parse::<Reply>().unwrap();
+    /// assert_eq!(reply.with_enhanced_code("2.1.0").to_string(), "250 2.1.0 Ok\r\n");
+    ///
+    /// let reply = "550 5.7.1 Relay access denied\r\n".parse::<Reply>().unwrap();
+    /// assert_eq!(
+    ///   reply.with_enhanced_code("5.1.1").to_string(),
+    ///   "550 5.7.1 Relay access denied\r\n"
+    /// );
+    /// ```
+    #[inline]
+    pub fn with_enhanced_code(self, enhanced: &str) -> Self {
+        match self.code {
+            ReplyCode::Enhanced { .. } => self,
+            ReplyCode::Code { code } => {
+                let reply = Self {
+                    code: ReplyCode::Enhanced {
+                        code,
+                        enhanced: enhanced.to_owned(),
+                    },
+                    text: self.text,
+                    folded: String::new(),
+                };
+                Self {
+                    folded: reply.fold(),
+                    ..reply
+                }
+            }
+        }
+    }
+
+    /// Return the reply without its enhanced status code.
+    ///
+    /// ```
+    /// # use vsmtp_common::Reply;
+    /// let reply = "554 5.7.1 Relay access denied\r\n".parse::<Reply>().unwrap();
+    /// assert_eq!(reply.without_enhanced_code().to_string(), "554 Relay access denied\r\n");
+    /// ```
+    #[inline]
+    pub fn without_enhanced_code(self) -> Self {
+        match self.code {
+            ReplyCode::Code { .. } => self,
+            ReplyCode::Enhanced { code, .. } => {
+                let reply = Self {
+                    code: ReplyCode::Code { code },
+                    text: self.text,
+                    folded: String::new(),
+                };
+                Self {
+                    folded: reply.fold(),
+                    ..reply
+                }
+            }
+        }
+    }
+
     /// Return the reply received, with no [`ReplyCode`], no ending CRLF
     #[inline]
     pub fn lines(&self) -> impl Iterator<Item = &String> {
@@ -80,6 +80,39 @@ impl ReplyCode {
         }
     }
 
+    /// Enhanced status code (rfc 3463) of a reply `code` sent without one: the code
+    /// dedicated to the meaning of `code`, or the generic `X.0.0` of its class.
+    ///
+    /// `None` for the intermediate and the informational replies (`1xx`, `3xx`).
+    #[must_use]
+    #[inline]
+    pub const fn default_details(code: u16) -> Option<&'static str> {
+        match code {
+            200..=299 => Some(match code {
+                235 => "2.7.0",
+                251 => "2.1.5",
+                _ => "2.0.0",
+            }),
+            400..=499 => Some(match code {
+                451 => "4.3.0",
+                452 => "4.3.1",
+                _ => "4.0.0",
+            }),
+            500..=599 => Some(match code {
+                500 => "5.5.2",
+                501 | 504 | 555 => "5.5.4",
+                502 | 503 => "5.5.1",
+                530 => "5.7.0",
+                535 => "5.7.8",
+                538 => "5.7.11",
+                552 => "5.3.4",
+                553 => "5.1.3",
+                _ => "5.0.0",
+            }),
+            _ => None,
+        }
+    }
+
     fn try_parse(which: i32, words: &[&str]) -> Option<Self> {
         match (which, words) {
             (ENHANCED, [_, "", ..]) => None,
@@ -67,6 +67,68 @@ fn ip6_has_scope_id(input: &str) -> bool {
     input.rfind('%').is_some()
 }
 
+/// Generate a unique value for the `Message-ID` header, without the angle brackets.
+///
+/// The id is `<base32(random 128 bits)>.<unix time in nanoseconds>@<hostname>`,
+/// which is a valid `msg-id` (see rfc5322 section 3.6.4). The hostname falls back
+/// to `localhost` if it cannot be read or is not a valid `dot-atom-text`.
+#[inline]
+#[must_use]
+pub fn generate_msg_id() -> String {
+    let mut random = [0; 16];
+    if getrandom::getrandom(&mut random).is_err() {
+        // NOTE: uuid v4 have 122 random bits, also taken from the os.
+        random = uuid::Uuid::new_v4().into_bytes();
+    }
+
+    // a clock set before the epoch is not an error, the random part stays unique.
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_nanos());
+
+    let hostname = hostname::get()
+        .ok()
+        .and_then(|hostname| hostname.into_string().ok())
+        .filter(|hostname| is_dot_atom_text(hostname))
+        .unwrap_or_else(|| "localhost".to_owned());
+
+    format!("{}.{nanos}@{hostname}", base32(random))
+}
+
+/// Encode 128 bits in lowercase base32 (rfc4648), without padding.
+fn base32(input: [u8; 16]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+    // 128 bits, completed with 2 zero bits, are 26 characters of 5 bits.
+    let value = u128::from_be_bytes(input);
+    (0..26u32)
+        .map(|i| {
+            let bits = if i < 25 {
+                value >> (123 - 5 * i)
+            } else {
+                value << 2u32
+            };
+            #[allow(
+                clippy::indexing_slicing,
+                clippy::as_conversions,
+                clippy::cast_possible_truncation
+            )]
+            // the index is masked to 5 bits, lower than the alphabet length.
+            char::from(ALPHABET[(bits & 0x1F) as usize])
+        })
+        .collect()
+}
+
+fn is_dot_atom_text(input: &str) -> bool {
+    !input.is_empty()
+        && input.split('.').all(|atom| {
+            !atom.is_empty()
+                && atom
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c))
+        })
+}
+
 #[cfg(test)]
 mod test {
 
@@ -84,4 +146,30 @@ mod test {
         );
         // NOTE: I did not add an scope id test here because it changes between machines.
     }
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32([0; 16]), "aaaaaaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(base32(*b"0123456789abcdef"), "gaytemzugu3doobzmfrggzdfmy");
+        assert_eq!(base32([0xFF; 16]), "77777777777777777777777774");
+    }
+
+    #[test]
+    fn test_generate_msg_id() {
+        let ids = (0..10_000usize)
+            .map(|_| generate_msg_id())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids.len(), 10_000);
+
+        for id in ids {
+            let (left, right) = id.split_once('@').unwrap();
+            let (random, nanos) = left.split_once('.').unwrap();
+            assert_eq!(random.len(), 26, "{id}");
+            assert!(random
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c)));
+            nanos.parse::<u128>().unwrap();
+            assert!(is_dot_atom_text(right), "{id}");
+        }
+    }
 }
@@ -18,8 +18,9 @@ use super::{wants::WantsValidate, with::Builder};
 use crate::{
     config::field::{
         FieldApp, FieldAppLogs, FieldAppVSL, FieldServer, FieldServerInterfaces, FieldServerLogs,
-        FieldServerQueues, FieldServerSMTP, FieldServerSMTPError, FieldServerSMTPTimeoutClient,
-        FieldServerSystem, FieldServerSystemThreadPool,
+        FieldServerQueues, FieldServerSMTP, FieldServerSMTPAccess, FieldServerSMTPError,
+        FieldServerSMTPTimeoutClient, FieldServerSMTPVrfy, FieldServerSystem,
+        FieldServerSystemThreadPool,
     },
     Config,
 };
@@ -31,6 +32,7 @@ impl Builder<WantsValidate> {
     ///
     /// *
     #[must_use]
+    #[allow(clippy::too_many_lines)]
     pub fn validate(self) -> Config {
         let virtual_entries = self.state;
         let dns = virtual_entries.parent;
@@ -55,6 +57,7 @@ impl Builder<WantsValidate> {
             server: FieldServer {
                 name: srv.name,
                 client_count_max: srv.client_count_max,
+                client_count_max_per_ip: FieldServer::default_client_count_max_per_ip(),
                 message_size_limit: srv.message_size_limit,
                 system: FieldServerSystem {
                     user: srv_syst.user,
@@ -70,14 +73,22 @@ impl Builder<WantsValidate> {
                     addr: srv_inet.addr,
                     addr_submission: srv_inet.addr_submission,
                     addr_submissions: srv_inet.addr_submissions,
+                    addr_proxied: srv_inet.addr_proxied,
+                    addr_lmtp: vec![],
+                    listeners: vec![],
                 },
                 logs: FieldServerLogs {
                     filename: srv_logs.filename,
                     level: srv_logs.level,
+                    format: crate::field::LogFormat::default(),
+                    rotation: crate::field::LogRotation::default(),
+                    max_files: None,
                     #[cfg(any(feature = "journald", feature = "syslog"))]
                     sys_level: FieldServerLogs::default_sys_level(),
                     #[cfg(feature = "syslog")]
                     syslog: crate::field::SyslogSocket::default(),
+                    #[cfg(feature = "otlp")]
+                    otlp: None,
                 },
                 queues: FieldServerQueues {
                     dirpath: srv_delivery.dirpath,
@@ -99,20 +110,44 @@ impl Builder<WantsValidate> {
                         rcpt_to: smtp_error.timeout_client.rcpt_to,
                         data: smtp_error.timeout_client.data,
                     },
+                    xclient_trusted: vec![],
+                    rate_limit: None,
+                    greeting_delay: None,
+                    access: FieldServerSMTPAccess::default(),
+                    vrfy: FieldServerSMTPVrfy::default(),
+                    banner: FieldServerSMTP::default_banner(),
+                    help: FieldServerSMTP::default_help(),
+                    replies: std::collections::BTreeMap::new(),
+                    rdns_timeout: FieldServerSMTP::default_rdns_timeout(),
+                    mx_timeout: FieldServerSMTP::default_mx_timeout(),
+                    session_timeout: None,
+                    drain_timeout: FieldServerSMTP::default_drain_timeout(),
+                    tarpit_max: FieldServerSMTP::default_tarpit_max(),
+                    line_length_max: FieldServerSMTP::default_line_length_max(),
+                    line_length_policy: vsmtp_protocol::LineLengthPolicy::default(),
+                    prescan: None,
+                    received_count_max: FieldServerSMTP::default_received_count_max(),
                 },
                 esmtp: esmtp.esmtp,
                 dns: dns.config,
                 r#virtual: virtual_entries.r#virtual,
+                #[cfg(feature = "metrics")]
+                metrics: None,
             },
             app: FieldApp {
                 dirpath: app.dirpath,
                 vsl: FieldAppVSL {
                     domain_dir: app_vsl.domain_dir,
                     filter_path: app_vsl.filter_path,
+                    auto_reload: false,
+                    auto_reload_debounce: FieldAppVSL::default_auto_reload_debounce(),
                 },
                 logs: FieldAppLogs {
                     filename: app_logs.filename,
                 },
+                greylist: None,
+                srs: None,
+                geoip: None,
             },
         }
     }
@@ -143,4 +178,36 @@ mod tests {
             .without_virtual_entries()
             .validate();
     }
+
+    #[test]
+    fn with_interfaces() {
+        let addr_proxied = vec!["127.0.0.1:10025".parse().unwrap()];
+
+        let config = Config::builder()
+            .with_current_version()
+            .without_path()
+            .with_server_name("testserver.com".parse::<vsmtp_common::Domain>().unwrap())
+            .with_user_group_and_default_system("root", "root")
+            .unwrap()
+            .with_interfaces(
+                &["127.0.0.1:25".parse().unwrap()],
+                &["127.0.0.1:587".parse().unwrap()],
+                &["127.0.0.1:465".parse().unwrap()],
+                &addr_proxied,
+            )
+            .with_default_logs_settings()
+            .with_default_delivery()
+            .without_tls_support()
+            .with_default_smtp_options()
+            .with_default_smtp_error_handler()
+            .with_default_extensions()
+            .with_default_app()
+            .with_default_vsl_settings()
+            .with_default_app_logs()
+            .with_system_dns()
+            .without_virtual_entries()
+            .validate();
+
+        assert_eq!(config.server.interfaces.addr_proxied, addr_proxied);
+    }
 }
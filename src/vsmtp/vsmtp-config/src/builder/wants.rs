@@ -63,6 +63,7 @@ pub struct WantsServerLogs {
     pub(super) addr: Vec<std::net::SocketAddr>,
     pub(super) addr_submission: Vec<std::net::SocketAddr>,
     pub(super) addr_submissions: Vec<std::net::SocketAddr>,
+    pub(super) addr_proxied: Vec<std::net::SocketAddr>,
 }
 
 ///
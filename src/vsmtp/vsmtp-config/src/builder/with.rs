@@ -245,6 +245,7 @@ impl Builder<WantsServerInterfaces> {
             &ipv4_localhost.addr,
             &ipv4_localhost.addr_submission,
             &ipv4_localhost.addr_submissions,
+            &ipv4_localhost.addr_proxied,
         )
     }
 
@@ -255,6 +256,7 @@ impl Builder<WantsServerInterfaces> {
         addr: &[std::net::SocketAddr],
         addr_submission: &[std::net::SocketAddr],
         addr_submissions: &[std::net::SocketAddr],
+        addr_proxied: &[std::net::SocketAddr],
     ) -> Builder<WantsServerLogs> {
         Builder::<WantsServerLogs> {
             state: WantsServerLogs {
@@ -262,6 +264,7 @@ impl Builder<WantsServerInterfaces> {
                 addr: addr.to_vec(),
                 addr_submission: addr_submission.to_vec(),
                 addr_submissions: addr_submissions.to_vec(),
+                addr_proxied: addr_proxied.to_vec(),
             },
         }
     }
@@ -350,6 +353,7 @@ impl Builder<WantsServerTLSConfig> {
                     )],
                     cipher_suite: FieldServerTls::default_cipher_suite(),
                     root: None,
+                    client_auth: None,
                 }),
             },
         })
@@ -57,11 +57,20 @@ pub mod field {
         pub name: Domain,
         /// Maximum number of client served at the same time.
         ///
-        /// The client will be rejected if the server is full.
+        /// The client will be rejected with a `421` reply if the server is full.
         ///
         /// If this value is `-1`, then the server will accept any number of client.
         #[serde(default = "FieldServer::default_client_count_max")]
         pub client_count_max: i64,
+        /// Maximum number of client served at the same time from the same address.
+        ///
+        /// The client will be rejected with a `421` reply if its address already uses
+        /// all of its sessions, even if the server is not full. The address of a client
+        /// of a proxied interface is the one of its PROXY header.
+        ///
+        /// If this value is `-1` (the default), then there is no limit per address.
+        #[serde(default = "FieldServer::default_client_count_max_per_ip")]
+        pub client_count_max_per_ip: i64,
         /// Maximum size in bytes of the message.
         #[serde(default = "FieldServer::default_message_size_limit")]
         pub message_size_limit: usize,
@@ -91,6 +100,21 @@ pub mod field {
         /// see [`FieldServerVirtual`]
         #[serde(default)]
         pub r#virtual: std::collections::BTreeMap<Domain, FieldServerVirtual>,
+        /// Expose the metrics of the server in the `Prometheus` format,
+        /// disabled if not set.
+        #[cfg(feature = "metrics")]
+        #[serde(default)]
+        pub metrics: Option<FieldServerMetrics>,
+    }
+
+    /// Configure the HTTP endpoint serving the metrics.
+    #[cfg(feature = "metrics")]
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerMetrics {
+        /// Address the HTTP server is bound to, the metrics are served on `/metrics`.
+        #[serde(default = "FieldServerMetrics::default_address")]
+        pub address: std::net::SocketAddr,
     }
 
     /// Readonly configuration for the dkim module.
@@ -181,6 +205,49 @@ pub mod field {
         #[serde(default)]
         #[serde(deserialize_with = "crate::parser::socket_addr::deserialize")]
         pub addr_submissions: Vec<std::net::SocketAddr>,
+        /// List of address for the protocol SMTP, behind a load balancer sending
+        /// the PROXY protocol header (v1 or v2) before the SMTP session.
+        #[serde(default)]
+        #[serde(deserialize_with = "crate::parser::socket_addr::deserialize")]
+        pub addr_proxied: Vec<std::net::SocketAddr>,
+        /// List of address for the protocol LMTP, to receive messages for local delivery.
+        #[serde(default)]
+        #[serde(deserialize_with = "crate::parser::socket_addr::deserialize")]
+        pub addr_lmtp: Vec<std::net::SocketAddr>,
+        /// Addresses served with their own kind of connection and policy,
+        /// in addition to the ones above.
+        #[serde(default)]
+        pub listeners: Vec<FieldServerInterfacesListener>,
+    }
+
+    /// Requirement of TLS on a listener.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ListenerTls {
+        /// `STARTTLS` is advertised if `server.tls` is set, but not required.
+        #[default]
+        Optional,
+        /// The transaction and the authentication are refused until `STARTTLS` is issued.
+        Required,
+    }
+
+    /// An address served by `vSMTP`, with the policy applied to its connections.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerInterfacesListener {
+        /// Address to bind, either ipv4 or ipv6.
+        #[serde(deserialize_with = "crate::parser::socket_addr::deserialize_one")]
+        pub addr: std::net::SocketAddr,
+        /// Kind of connection, `relay` by default. The `tunneled` connections
+        /// start with the TLS handshake (implicit TLS, port 465).
+        #[serde(default)]
+        pub kind: vsmtp_protocol::ConnectionKind,
+        /// Requirement of `STARTTLS`, `optional` by default.
+        #[serde(default)]
+        pub tls: ListenerTls,
+        /// Refuse `MAIL FROM` until the client is authenticated, `false` by default.
+        #[serde(default)]
+        pub auth_required: bool,
     }
 
     /// The field related to the logs.
@@ -200,6 +267,16 @@ pub mod field {
             deserialize_with = "crate::parser::tracing_directive::deserialize"
         )]
         pub level: Vec<tracing_subscriber::filter::Directive>,
+        /// Format of the records written in the log files, either `text` or `json`.
+        #[serde(default)]
+        pub format: LogFormat,
+        /// How often the log files are rotated, either `daily`, `hourly` or `never`.
+        #[serde(default)]
+        pub rotation: LogRotation,
+        /// Number of log files to keep when the logs are rotated,
+        /// the oldest files are removed when the server starts.
+        #[serde(default)]
+        pub max_files: Option<std::num::NonZeroUsize>,
 
         /// Level of the logs sent to the system log, either `journald` or `syslog`.
         #[cfg(any(feature = "journald", feature = "syslog"))]
@@ -211,6 +288,37 @@ pub mod field {
         #[cfg(feature = "syslog")]
         #[serde(default)]
         pub syslog: SyslogSocket,
+
+        /// Export the SMTP sessions as traces with the `OpenTelemetry` protocol,
+        /// disabled if not set.
+        #[cfg(feature = "otlp")]
+        #[serde(default)]
+        pub otlp: Option<FieldServerLogsOtlp>,
+    }
+
+    /// Format of the records written in the log files.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum LogFormat {
+        /// Human readable records, one line per event.
+        #[default]
+        Text,
+        /// One JSON object per event, with the fields of the spans
+        /// (connection id, client address, SMTP stage ...).
+        Json,
+    }
+
+    /// Rotation strategy of the log files.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum LogRotation {
+        /// A new file is created every day.
+        Daily,
+        /// A new file is created every hour.
+        Hourly,
+        /// The logs are always written to the same file.
+        #[default]
+        Never,
     }
 
     /// Configure how the logs are sent to the system log.
@@ -237,6 +345,16 @@ pub mod field {
         },
     }
 
+    /// Configure the export of the spans to an `OpenTelemetry` collector.
+    #[cfg(feature = "otlp")]
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerLogsOtlp {
+        /// Address of the collector receiving the spans over gRPC.
+        #[serde(default = "FieldServerLogsOtlp::default_endpoint")]
+        pub endpoint: String,
+    }
+
     /// The configuration of the `working queue`.
     #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
@@ -331,17 +449,34 @@ pub mod field {
         /// * if some,              will used these values
         #[serde(default)]
         pub root: Option<FieldServerVirtualTls>,
+        /// Request a certificate from the clients, disabled by default.
+        #[serde(default)]
+        pub client_auth: Option<FieldServerTlsClientAuth>,
+    }
+
+    /// Authentication of the clients with the certificate presented during the TLS handshake.
+    #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerTlsClientAuth {
+        /// Certificate authorities used to verify the certificates of the clients.
+        pub ca: SecretFile<Vec<rustls::Certificate>>,
+        /// Abort the handshake if the client does not present a certificate.
+        ///
+        /// `false` by default, the certificate is then verified only if the client sends one.
+        #[serde(default)]
+        pub required: bool,
     }
 
     /// Configuration of the client's error handling.
     #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
     pub struct FieldServerSMTPError {
-        /// The maximum number of errors before the client is delay between each response.
+        /// The maximum number of consecutive errors before the client is delay between each response.
+        /// A successful reply resets the count.
         ///
         /// `-1` to disable
         pub soft_count: i64,
-        /// The maximum number of errors before the client is disconnected.
+        /// The maximum number of consecutive errors before the client is disconnected with a `421` reply.
         ///
         /// `-1` to disable
         pub hard_count: i64,
@@ -372,6 +507,97 @@ pub mod field {
         pub data: std::time::Duration,
     }
 
+    /// Token bucket of the recipients accepted from a client address.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerSMTPRateLimit {
+        /// Number of recipients added to the bucket every `period`.
+        pub rate: u32,
+        /// Period at which `rate` recipients are added to the bucket.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldServerSMTPRateLimit::default_period"
+        )]
+        pub period: std::time::Duration,
+        /// Size of the bucket, the number of recipients accepted at once.
+        pub burst: u32,
+    }
+
+    /// Delay of the banner, to detect the clients sending data before being allowed to.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerSMTPGreetingDelay {
+        /// Time waited before sending the banner.
+        #[serde(with = "humantime_serde")]
+        pub delay: std::time::Duration,
+        /// Close the connection of a client sending data during the delay,
+        /// otherwise the banner is sent at the end of the delay as usual.
+        ///
+        /// `true` by default.
+        #[serde(default = "FieldServerSMTPGreetingDelay::default_reject")]
+        pub reject: bool,
+        /// Reply sent before closing the connection of a client sending data during the delay.
+        #[serde(default = "FieldServerSMTPGreetingDelay::default_reply")]
+        pub reply: vsmtp_common::Reply,
+    }
+
+    /// Filtering of the client addresses, applied before the banner is sent.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerSMTPAccess {
+        /// Networks (IPv4 or IPv6 CIDR) whose connections are accepted.
+        #[serde(default)]
+        pub allow: Vec<ipnet::IpNet>,
+        /// Networks (IPv4 or IPv6 CIDR) whose connections are closed.
+        #[serde(default)]
+        pub deny: Vec<ipnet::IpNet>,
+        /// An address listed in both `allow` and `deny` is accepted,
+        /// otherwise the `deny` list takes precedence.
+        ///
+        /// `true` by default.
+        #[serde(default = "FieldServerSMTPAccess::default_allow_overrides_deny")]
+        pub allow_overrides_deny: bool,
+        /// Send a `554` reply before closing a blocked connection,
+        /// otherwise the connection is closed silently.
+        ///
+        /// `true` by default.
+        #[serde(default = "FieldServerSMTPAccess::default_reply")]
+        pub reply: bool,
+    }
+
+    /// Inspection of the message while it is received, aborting it as soon as
+    /// a limit is reached, the rest of the message being discarded.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldServerSMTPPrescan {
+        /// Size of the message past which it is aborted with a `552` reply.
+        #[serde(default)]
+        pub size_max: Option<usize>,
+        /// Patterns aborting the message with a `554` reply when found in a line of the message,
+        /// the comparison is case sensitive and a pattern spanning several lines is not found.
+        #[serde(default)]
+        pub signatures: Vec<String>,
+        /// Number of bytes at the beginning of the message in which the signatures are searched.
+        ///
+        /// `65536` by default.
+        #[serde(default = "FieldServerSMTPPrescan::default_depth")]
+        pub depth: usize,
+    }
+
+    /// Answer of the server to the `VRFY` and `EXPN` commands.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum FieldServerSMTPVrfy {
+        /// Reply `252`, the address is neither confirmed nor denied,
+        /// which prevents the harvesting of the addresses.
+        #[default]
+        Cannot,
+        /// Look up the local part in the users of the system, for the server's domain.
+        Lookup,
+        /// Reply `502`, the commands are not implemented.
+        Disabled,
+    }
+
     /// Policy of the extension AUTH.
     #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
@@ -396,7 +622,7 @@ pub mod field {
     #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
     pub struct FieldServerSMTP {
-        /// Maximum number of recipients received in the envelop.
+        /// Maximum number of recipients received in the envelop, the following ones are refused with a `452` reply.
         #[serde(default = "FieldServerSMTP::default_rcpt_count_max")]
         pub rcpt_count_max: usize,
         /// SMTP's error policy.
@@ -405,6 +631,89 @@ pub mod field {
         /// SMTP's timeout policy.
         #[serde(default)]
         pub timeout_client: FieldServerSMTPTimeoutClient,
+        /// Addresses of the upstream MTAs allowed to forward the identity of their clients
+        /// with the `XCLIENT` command. The command is rejected for any other client.
+        ///
+        /// The `AUTH` argument of `MAIL FROM` is also trusted from these addresses,
+        /// as from the authenticated clients.
+        #[serde(default)]
+        pub xclient_trusted: Vec<std::net::IpAddr>,
+        /// Throttling of the recipients per client address, disabled by default.
+        #[serde(default)]
+        pub rate_limit: Option<FieldServerSMTPRateLimit>,
+        /// Delay of the banner and rejection of the clients talking before it, disabled by default.
+        #[serde(default)]
+        pub greeting_delay: Option<FieldServerSMTPGreetingDelay>,
+        /// Networks allowed or denied to connect, checked before the banner is sent.
+        #[serde(default)]
+        pub access: FieldServerSMTPAccess,
+        /// Answer to the `VRFY` and `EXPN` commands, `252` by default.
+        #[serde(default)]
+        pub vrfy: FieldServerSMTPVrfy,
+        /// Text of the `220` banner, one reply line per line of the text. `{hostname}` is replaced
+        /// by the name of the server (the SNI on a tunneled connection) and `{version}` by the version of vSMTP.
+        #[serde(default = "FieldServerSMTP::default_banner")]
+        pub banner: String,
+        /// Text of the `214` reply to the `HELP` command without argument,
+        /// one reply line per line of the text. `{commands}` is replaced by the commands
+        /// available in the session (`STARTTLS` and `AUTH` only when they can be used).
+        /// Lists the available commands by default.
+        #[serde(default = "FieldServerSMTP::default_help")]
+        pub help: String,
+        /// Replies sent to the client instead of the default ones, for each reason of rejection.
+        /// A reply is either a string (`"554 5.7.1 Relay denied"`) or an object
+        /// (`#{ code: 554, enhanced: "5.7.1", text: "Relay denied" }`).
+        #[serde(default)]
+        pub replies: std::collections::BTreeMap<vsmtp_common::RejectionReason, vsmtp_common::Reply>,
+        /// Maximum delay of the reverse DNS lookup of the client address,
+        /// the client is considered without PTR record past this delay.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldServerSMTP::default_rdns_timeout"
+        )]
+        pub rdns_timeout: std::time::Duration,
+        /// Maximum delay of the MX lookup of the sender domain by `sender_has_mx()`,
+        /// the lookup is a temporary error past this delay.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldServerSMTP::default_mx_timeout"
+        )]
+        pub mx_timeout: std::time::Duration,
+        /// Maximum duration of a session, whatever the activity of the client, disabled by default.
+        /// Past this delay, the client receives a `421` reply and is disconnected
+        /// before its next command.
+        #[serde(default, with = "humantime_serde")]
+        pub session_timeout: Option<std::time::Duration>,
+        /// Delay given to the transactions in progress to complete when the server stops,
+        /// the sessions still opened past this delay are closed.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldServerSMTP::default_drain_timeout"
+        )]
+        pub drain_timeout: std::time::Duration,
+        /// Upper bound of the delay requested by `tarpit()` before a rejection is sent.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldServerSMTP::default_tarpit_max"
+        )]
+        pub tarpit_max: std::time::Duration,
+        /// Maximum length of a line of a message, "\r\n" included,
+        /// the leading dot added for transparency is not counted.
+        #[serde(default = "FieldServerSMTP::default_line_length_max")]
+        pub line_length_max: usize,
+        /// Handling of the lines longer than `line_length_max`: `strict` rejects the message
+        /// with a `500` reply, `wrap` splits the line and `log` only warns (by default).
+        #[serde(default)]
+        pub line_length_policy: vsmtp_protocol::LineLengthPolicy,
+        /// Inspection of the message while it is received, disabled by default:
+        /// the message is fully received before the rules are run.
+        #[serde(default)]
+        pub prescan: Option<FieldServerSMTPPrescan>,
+        /// Maximum number of `Received` headers of a message (rfc 5321 section 6.3), the messages
+        /// with more hops are considered in a loop and rejected before the rules are run.
+        /// `null` to disable the check.
+        #[serde(default = "FieldServerSMTP::default_received_count_max")]
+        pub received_count_max: Option<usize>,
     }
 
     /// Parameters for Extended SMTP.
@@ -416,6 +725,12 @@ pub mod field {
         /// Authentication policy.
         #[serde(default = "FieldServerESMTP::default_auth")]
         pub auth: Option<FieldServerSMTPAuth>,
+        /// Do not advertise the `AUTH` extension and reject the command until the connection
+        /// is secured with TLS, whatever the mechanism.
+        ///
+        /// `true` by default.
+        #[serde(default = "FieldServerESMTP::default_auth_require_tls")]
+        pub auth_require_tls: bool,
         /// TODO:
         #[serde(default = "FieldServerESMTP::default_eightbitmime")]
         pub eightbitmime: bool,
@@ -428,6 +743,11 @@ pub mod field {
         /// Enable chunking.
         #[serde(default = "FieldServerESMTP::default_chunking")]
         pub chunking: bool,
+        /// Advertise the `ENHANCEDSTATUSCODES` extension (rfc 2034), the replies sent after
+        /// an `EHLO` are prefixed with an enhanced status code, and the ones sent to a client
+        /// greeting with `HELO` have none.
+        #[serde(default = "FieldServerESMTP::default_enhanced_status_codes")]
+        pub enhanced_status_codes: bool,
         /// Maximum size of the message in bytes.
         /// A parameter value of 0 (zero) indicates that no fixed maximum message size is in force.
         /// <https://datatracker.ietf.org/doc/html/rfc1870>
@@ -505,13 +825,26 @@ pub mod field {
     }
 
     /// Configuration of the application run by `vSMTP`.
-    #[derive(Default, Debug, PartialEq, Eq, Clone, serde::Deserialize, serde::Serialize)]
+    #[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
     pub struct FieldAppVSL {
         /// Directory containing filtering rules per domain.
         pub domain_dir: Option<std::path::PathBuf>,
         /// Entry point for the rule engine.
         pub filter_path: Option<std::path::PathBuf>,
+        /// Reload the rules when a script changes on disk, in addition to `SIGHUP`.
+        ///
+        /// Only the rules are reloaded: the configuration, including the `config.vsl` of the domains,
+        /// is the one loaded at startup and a change requires a restart of the server.
+        #[serde(default)]
+        pub auto_reload: bool,
+        /// Delay without any change on the scripts before reloading the rules,
+        /// the changes of the other files do not delay the reload.
+        #[serde(
+            with = "humantime_serde",
+            default = "FieldAppVSL::default_auto_reload_debounce"
+        )]
+        pub auto_reload_debounce: std::time::Duration,
     }
 
     /// Application's parameter of the logs, same properties than [`FieldServerLogs`].
@@ -523,6 +856,42 @@ pub mod field {
         pub filename: std::path::PathBuf,
     }
 
+    /// Greylisting of the triplets (client network, sender, recipient) checked by `greylist::check()`.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldAppGreylist {
+        /// Delay before a retry of the same triplet is accepted.
+        #[serde(with = "humantime_serde", default = "FieldAppGreylist::default_delay")]
+        pub delay: std::time::Duration,
+        /// Triplets not seen for this duration are removed from the store.
+        #[serde(with = "humantime_serde", default = "FieldAppGreylist::default_expiry")]
+        pub expiry: std::time::Duration,
+    }
+
+    /// Sender Rewriting Scheme used by `envelop::srs_forward()` and `envelop::srs_reverse()`.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldAppSrs {
+        /// Secret key of the HMAC authenticating the rewritten addresses.
+        pub secret: String,
+        /// Rewritten addresses older than this duration are refused by `envelop::srs_reverse()`.
+        #[serde(with = "humantime_serde", default = "FieldAppSrs::default_max_age")]
+        pub max_age: std::time::Duration,
+    }
+
+    /// `MaxMind` databases used by `geoip::client_asn()` and `geoip::client_country()`,
+    /// loaded once at startup.
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct FieldAppGeoIp {
+        /// Path of a `GeoLite2` ASN database.
+        #[serde(default)]
+        pub asn: Option<std::path::PathBuf>,
+        /// Path of a `GeoLite2` Country database.
+        #[serde(default)]
+        pub country: Option<std::path::PathBuf>,
+    }
+
     /// Configuration of the application run by `vSMTP`.
     #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
     #[serde(deny_unknown_fields)]
@@ -536,5 +905,14 @@ pub mod field {
         /// see [`FieldAppLogs`]
         #[serde(default)]
         pub logs: FieldAppLogs,
+        /// Greylisting, disabled by default. The triplets are stored in `{dirpath}/greylist.json`.
+        #[serde(default)]
+        pub greylist: Option<FieldAppGreylist>,
+        /// Sender Rewriting Scheme, disabled by default.
+        #[serde(default)]
+        pub srs: Option<FieldAppSrs>,
+        /// Geolocation of the clients, disabled by default.
+        #[serde(default)]
+        pub geoip: Option<FieldAppGeoIp>,
     }
 }
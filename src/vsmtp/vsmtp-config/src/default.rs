@@ -15,18 +15,26 @@
  *
 */
 
+#[cfg(feature = "otlp")]
+use crate::config::field::FieldServerLogsOtlp;
+#[cfg(feature = "metrics")]
+use crate::config::field::FieldServerMetrics;
 #[cfg(feature = "syslog")]
 use crate::config::field::SyslogSocket;
 use crate::{
     config::field::{
-        FieldApp, FieldAppLogs, FieldAppVSL, FieldQueueDelivery, FieldQueueWorking, FieldServer,
-        FieldServerDNS, FieldServerInterfaces, FieldServerLogs, FieldServerQueues, FieldServerSMTP,
-        FieldServerSMTPAuth, FieldServerSMTPError, FieldServerSMTPTimeoutClient, FieldServerSystem,
-        FieldServerSystemThreadPool, FieldServerTls, FieldServerVirtual, ResolverOptsWrapper,
+        FieldApp, FieldAppGreylist, FieldAppLogs, FieldAppSrs, FieldAppVSL, FieldQueueDelivery,
+        FieldQueueWorking, FieldServer, FieldServerDNS, FieldServerInterfaces, FieldServerLogs,
+        FieldServerQueues, FieldServerSMTP, FieldServerSMTPAccess, FieldServerSMTPAuth,
+        FieldServerSMTPError, FieldServerSMTPGreetingDelay, FieldServerSMTPPrescan,
+        FieldServerSMTPRateLimit, FieldServerSMTPTimeoutClient, FieldServerSMTPVrfy,
+        FieldServerSystem, FieldServerSystemThreadPool, FieldServerTls, FieldServerVirtual,
+        LogFormat, LogRotation, ResolverOptsWrapper,
     },
     field::FieldServerESMTP,
     Config,
 };
+use std::fmt::Write as _;
 use vsmtp_common::{auth::Mechanism, Domain};
 
 impl Default for Config {
@@ -112,6 +120,7 @@ impl Config {
                 // default function instead of using the derivative macro.
                 name: FieldServer::hostname(),
                 client_count_max: FieldServer::default_client_count_max(),
+                client_count_max_per_ip: FieldServer::default_client_count_max_per_ip(),
                 message_size_limit: FieldServer::default_message_size_limit(),
                 interfaces: FieldServerInterfaces::default(),
                 logs: FieldServerLogs::default(),
@@ -121,6 +130,8 @@ impl Config {
                 esmtp: FieldServerESMTP::default(),
                 dns: FieldServerDNS::default(),
                 r#virtual: std::collections::BTreeMap::default(),
+                #[cfg(feature = "metrics")]
+                metrics: None,
             },
             app: FieldApp::default(),
             path: None,
@@ -133,6 +144,7 @@ impl Default for FieldServer {
         Self {
             name: Self::hostname(),
             client_count_max: Self::default_client_count_max(),
+            client_count_max_per_ip: Self::default_client_count_max_per_ip(),
             message_size_limit: Self::default_message_size_limit(),
             system: FieldServerSystem::default(),
             interfaces: FieldServerInterfaces::default(),
@@ -143,6 +155,8 @@ impl Default for FieldServer {
             esmtp: FieldServerESMTP::default(),
             dns: FieldServerDNS::default(),
             r#virtual: std::collections::BTreeMap::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
         }
     }
 }
@@ -161,6 +175,10 @@ impl FieldServer {
         16
     }
 
+    pub(crate) const fn default_client_count_max_per_ip() -> i64 {
+        -1
+    }
+
     pub(crate) const fn default_message_size_limit() -> usize {
         10_000_000
     }
@@ -231,6 +249,9 @@ impl FieldServerInterfaces {
             addr: vec!["127.0.0.1:25".parse().expect("valid")],
             addr_submission: vec!["127.0.0.1:587".parse().expect("valid")],
             addr_submissions: vec!["127.0.0.1:465".parse().expect("valid")],
+            addr_proxied: vec![],
+            addr_lmtp: vec![],
+            listeners: vec![],
         }
     }
 }
@@ -240,10 +261,15 @@ impl Default for FieldServerLogs {
         Self {
             filename: Self::default_filename(),
             level: Self::default_level(),
+            format: LogFormat::default(),
+            rotation: LogRotation::default(),
+            max_files: None,
             #[cfg(any(feature = "journald", feature = "syslog"))]
             sys_level: Self::default_sys_level(),
             #[cfg(feature = "syslog")]
             syslog: SyslogSocket::default(),
+            #[cfg(feature = "otlp")]
+            otlp: None,
         }
     }
 }
@@ -274,6 +300,20 @@ impl SyslogSocket {
     }
 }
 
+#[cfg(feature = "otlp")]
+impl FieldServerLogsOtlp {
+    pub(crate) fn default_endpoint() -> String {
+        "http://localhost:4317".to_string()
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl FieldServerMetrics {
+    pub(crate) fn default_address() -> std::net::SocketAddr {
+        "127.0.0.1:9090".parse().expect("valid")
+    }
+}
+
 #[cfg(feature = "syslog")]
 impl Default for SyslogSocket {
     fn default() -> Self {
@@ -401,6 +441,23 @@ impl Default for FieldServerSMTP {
             rcpt_count_max: Self::default_rcpt_count_max(),
             error: FieldServerSMTPError::default(),
             timeout_client: FieldServerSMTPTimeoutClient::default(),
+            xclient_trusted: vec![],
+            rate_limit: None,
+            greeting_delay: None,
+            access: FieldServerSMTPAccess::default(),
+            vrfy: FieldServerSMTPVrfy::default(),
+            banner: Self::default_banner(),
+            help: Self::default_help(),
+            replies: std::collections::BTreeMap::new(),
+            rdns_timeout: Self::default_rdns_timeout(),
+            mx_timeout: Self::default_mx_timeout(),
+            session_timeout: None,
+            drain_timeout: Self::default_drain_timeout(),
+            tarpit_max: Self::default_tarpit_max(),
+            line_length_max: Self::default_line_length_max(),
+            line_length_policy: vsmtp_protocol::LineLengthPolicy::default(),
+            prescan: None,
+            received_count_max: Self::default_received_count_max(),
         }
     }
 }
@@ -409,16 +466,101 @@ impl FieldServerSMTP {
     pub(crate) const fn default_rcpt_count_max() -> usize {
         1000
     }
+
+    pub(crate) const fn default_rdns_timeout() -> std::time::Duration {
+        std::time::Duration::from_secs(2)
+    }
+
+    pub(crate) const fn default_mx_timeout() -> std::time::Duration {
+        std::time::Duration::from_secs(5)
+    }
+
+    pub(crate) const fn default_drain_timeout() -> std::time::Duration {
+        std::time::Duration::from_secs(30)
+    }
+
+    pub(crate) const fn default_tarpit_max() -> std::time::Duration {
+        std::time::Duration::from_secs(30)
+    }
+
+    pub(crate) const fn default_line_length_max() -> usize {
+        1000
+    }
+
+    pub(crate) const fn default_received_count_max() -> Option<usize> {
+        Some(30)
+    }
+
+    pub(crate) fn default_banner() -> String {
+        "{hostname} Service ready".to_owned()
+    }
+
+    pub(crate) fn default_help() -> String {
+        "Commands supported: {commands}".to_owned()
+    }
+
+    /// The `220` banner, one reply line per line of `banner`, with the `{hostname}` and `{version}`
+    /// placeholders replaced. An empty banner is the name of the server.
+    ///
+    /// # Errors
+    ///
+    /// * the lines of `banner` do not make a valid reply
+    pub fn banner_reply(&self, hostname: &str) -> anyhow::Result<vsmtp_common::Reply> {
+        let banner = match self.banner.trim() {
+            "" => "{hostname}",
+            banner => banner,
+        };
+
+        banner
+            .lines()
+            .fold(String::new(), |mut reply, line| {
+                let _ = write!(
+                    reply,
+                    "220 {}\r\n",
+                    line.replace("{hostname}", hostname)
+                        .replace("{version}", env!("CARGO_PKG_VERSION"))
+                );
+                reply
+            })
+            .parse()
+    }
+
+    /// Reply to the `HELP` command without argument, one `214` reply line per line of `help`,
+    /// with the `{commands}` placeholder replaced by the commands available in the session.
+    ///
+    /// # Errors
+    ///
+    /// * `help` is empty, or its lines do not make a valid reply
+    pub fn help_reply(&self, commands: &str) -> anyhow::Result<vsmtp_common::Reply> {
+        self.help
+            .lines()
+            .fold(String::new(), |mut reply, line| {
+                let _ = write!(reply, "214 {}\r\n", line.replace("{commands}", commands));
+                reply
+            })
+            .parse()
+    }
+
+    /// Reply sent to the client for a rejection, the one configured in `replies`
+    /// or the default one of the reason.
+    pub fn reply(&self, reason: vsmtp_common::RejectionReason) -> vsmtp_common::Reply {
+        self.replies
+            .get(&reason)
+            .cloned()
+            .unwrap_or_else(|| reason.default_reply())
+    }
 }
 
 impl Default for FieldServerESMTP {
     fn default() -> Self {
         Self {
             auth: None,
+            auth_require_tls: Self::default_auth_require_tls(),
             eightbitmime: Self::default_eightbitmime(),
             smtputf8: Self::default_smtputf8(),
             pipelining: Self::default_pipelining(),
             chunking: Self::default_chunking(),
+            enhanced_status_codes: Self::default_enhanced_status_codes(),
             size: Self::default_size(),
         }
     }
@@ -429,6 +571,10 @@ impl FieldServerESMTP {
         None
     }
 
+    pub(crate) const fn default_auth_require_tls() -> bool {
+        true
+    }
+
     pub(crate) const fn default_eightbitmime() -> bool {
         true
     }
@@ -445,6 +591,10 @@ impl FieldServerESMTP {
         false
     }
 
+    pub(crate) const fn default_enhanced_status_codes() -> bool {
+        false
+    }
+
     pub(crate) const fn default_size() -> usize {
         20_000_000
     }
@@ -514,6 +664,112 @@ impl Default for FieldServerSMTPError {
     }
 }
 
+impl Default for FieldServerSMTPAccess {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            deny: vec![],
+            allow_overrides_deny: Self::default_allow_overrides_deny(),
+            reply: Self::default_reply(),
+        }
+    }
+}
+
+impl FieldServerSMTPAccess {
+    pub(crate) const fn default_allow_overrides_deny() -> bool {
+        true
+    }
+
+    pub(crate) const fn default_reply() -> bool {
+        true
+    }
+}
+
+impl FieldServerSMTPPrescan {
+    pub(crate) const fn default_depth() -> usize {
+        65536
+    }
+}
+
+impl vsmtp_protocol::Prescan for FieldServerSMTPPrescan {
+    fn scan(&self, size: usize, line: &[u8]) -> Option<vsmtp_common::Reply> {
+        if self.size_max.map_or(false, |size_max| size > size_max) {
+            return Some(
+                "552 5.3.4 Message size exceeds fixed maximum message size\r\n"
+                    .parse()
+                    .expect("valid reply"),
+            );
+        }
+        // NOTE: the line is only scanned if it starts within the depth.
+        if size - line.len() < self.depth
+            && self.signatures.iter().any(|signature| {
+                !signature.is_empty()
+                    && line
+                        .windows(signature.len())
+                        .any(|window| window == signature.as_bytes())
+            })
+        {
+            return Some(
+                "554 5.7.1 Message content rejected\r\n"
+                    .parse()
+                    .expect("valid reply"),
+            );
+        }
+        None
+    }
+}
+
+impl FieldServerSMTPRateLimit {
+    pub(crate) const fn default_period() -> std::time::Duration {
+        std::time::Duration::from_secs(1)
+    }
+}
+
+impl Default for FieldAppVSL {
+    fn default() -> Self {
+        Self {
+            domain_dir: None,
+            filter_path: None,
+            auto_reload: false,
+            auto_reload_debounce: Self::default_auto_reload_debounce(),
+        }
+    }
+}
+
+impl FieldAppVSL {
+    pub(crate) const fn default_auto_reload_debounce() -> std::time::Duration {
+        std::time::Duration::from_millis(500)
+    }
+}
+
+impl FieldAppGreylist {
+    pub(crate) const fn default_delay() -> std::time::Duration {
+        std::time::Duration::from_secs(5 * 60)
+    }
+
+    pub(crate) const fn default_expiry() -> std::time::Duration {
+        std::time::Duration::from_secs(36 * 24 * 60 * 60)
+    }
+}
+
+impl FieldAppSrs {
+    pub(crate) const fn default_max_age() -> std::time::Duration {
+        std::time::Duration::from_secs(21 * 24 * 60 * 60)
+    }
+}
+
+impl FieldServerSMTPGreetingDelay {
+    pub(crate) const fn default_reject() -> bool {
+        true
+    }
+
+    pub(crate) fn default_reply() -> vsmtp_common::Reply {
+        "554 5.5.1 Protocol error, data received before the banner\r\n"
+            .parse()
+            .expect("valid reply")
+    }
+}
+
 impl Default for FieldServerSMTPTimeoutClient {
     fn default() -> Self {
         Self {
@@ -532,6 +788,9 @@ impl Default for FieldApp {
             dirpath: Self::default_dirpath(),
             vsl: FieldAppVSL::default(),
             logs: FieldAppLogs::default(),
+            greylist: None,
+            srs: None,
+            geoip: None,
         }
     }
 }
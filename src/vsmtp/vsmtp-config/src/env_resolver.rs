@@ -0,0 +1,165 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use anyhow::Context;
+
+fn is_env_var_name(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn push_string_literal(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Length of the literal starting at the beginning of `script` and delimited by `quote`,
+/// including both delimiters, or the whole `script` if the literal is not terminated.
+fn literal_len(script: &str, quote: char, escapes: bool) -> usize {
+    let mut chars = script.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if escapes => {
+                chars.next();
+            }
+            c if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    script.len()
+}
+
+/// Length of the (possibly nested) block comment starting at the beginning of `script`.
+fn block_comment_len(script: &str) -> usize {
+    let mut depth = 0_usize;
+    let mut i = 0;
+    while i < script.len() {
+        if script[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if script[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += script[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    script.len()
+}
+
+/// Replace each `${NAME}` of the script by the value of the environment
+/// variable `NAME`, written as a string literal.
+///
+/// The comments, string and character literals are left untouched, as well as
+/// the template strings (`` `...` ``) which use the same syntax for interpolation.
+///
+/// # Errors
+///
+/// * A referenced variable is not defined.
+pub fn substitute_env_vars(script: &str) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(script.len());
+    let mut rest = script;
+
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '/' if rest.starts_with("//") => rest.find('\n').unwrap_or(rest.len()),
+            '/' if rest.starts_with("/*") => block_comment_len(rest),
+            '"' | '\'' => literal_len(rest, c, true),
+            '`' => literal_len(rest, c, false),
+            '$' if rest.starts_with("${") => match rest.find('}').map(|end| &rest[2..end]) {
+                Some(name) if is_env_var_name(name) => {
+                    let value = std::env::var(name)
+                        .with_context(|| format!("Environment variable `{name}` is not defined"))?;
+                    push_string_literal(&mut output, &value);
+                    rest = &rest[name.len() + 3..];
+                    continue;
+                }
+                _ => 1,
+            },
+            c => c.len_utf8(),
+        };
+        output.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    Ok(output)
+}
+
+/// Resolve the modules imported by the configuration in the `.vsl` files of a directory,
+/// like [`rhai::module_resolvers::FileModuleResolver`], after substituting the environment
+/// variables they reference (see [`substitute_env_vars`]).
+pub struct EnvModuleResolver {
+    base_path: std::path::PathBuf,
+}
+
+impl EnvModuleResolver {
+    /// Resolve the imports relatively to `base_path`.
+    pub fn new(base_path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+        }
+    }
+}
+
+impl rhai::ModuleResolver for EnvModuleResolver {
+    fn resolve(
+        &self,
+        engine: &rhai::Engine,
+        _: Option<&str>,
+        path: &str,
+        pos: rhai::Position,
+    ) -> Result<rhai::Shared<rhai::Module>, Box<rhai::EvalAltResult>> {
+        let in_module = |error: Box<rhai::EvalAltResult>| {
+            Box::new(rhai::EvalAltResult::ErrorInModule(
+                path.to_owned(),
+                error,
+                pos,
+            ))
+        };
+
+        let mut file_path = self.base_path.join(path);
+        file_path.set_extension("vsl");
+
+        let script = std::fs::read_to_string(&file_path)
+            .map_err(|_| rhai::EvalAltResult::ErrorModuleNotFound(path.to_owned(), pos))?;
+        let script = substitute_env_vars(&script).map_err(|error| {
+            in_module(rhai::EvalAltResult::ErrorRuntime(format!("{error:#}").into(), pos).into())
+        })?;
+
+        let mut ast = engine
+            .compile(script)
+            .map_err(|error| in_module(error.into()))?;
+        ast.set_source(path);
+
+        rhai::Module::eval_ast_as_new(rhai::Scope::new(), &ast, engine)
+            .map(Into::into)
+            .map_err(in_module)
+    }
+}
@@ -80,6 +80,7 @@ pub mod builder {
 
 mod config;
 mod default;
+mod env_resolver;
 mod rustls_helper;
 mod virtual_tls;
 
@@ -90,7 +91,7 @@ use config::field::FieldServerVirtual;
 pub use dns_resolver::DnsResolvers;
 
 pub use config::{field, Config};
-pub use rustls_helper::get_rustls_config;
+pub use rustls_helper::{check_certified_key, get_rustls_config, CertificateInfo};
 
 use builder::{Builder, WantsVersion};
 use vsmtp_common::Domain;
@@ -113,6 +114,11 @@ impl Config {
     /// * Version requirements are not fulfilled.
     /// * A mandatory field is missing. (when no default value is provided)
     /// * File could not be opened or read.
+    /// * The file references an undefined environment variable.
+    ///
+    /// The calls such as `env("TLS_KEY")` are replaced by the value of the environment
+    /// variable before the script, the modules it imports and the configurations of
+    /// the domains are compiled.
     ///
     /// [JSON]: https://fr.wikipedia.org/wiki/JavaScript_Object_Notation
     pub fn from_vsl_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
@@ -127,6 +133,9 @@ impl Config {
 
         let script =
             std::fs::read_to_string(path).context(format!("Cannot read file at {path:?}"))?;
+        let script = env_resolver::substitute_env_vars(&script).context(format!(
+            "Cannot substitute the environment variables in {path:?}"
+        ))?;
 
         let mut config = Self::from_vsl_script(script, Some(&vsmtp_config_dir))?;
 
@@ -156,12 +165,7 @@ impl Config {
         let mut engine = rhai::Engine::new();
 
         if let Some(resolve_path) = resolve_path.as_ref() {
-            engine.set_module_resolver(
-                rhai::module_resolvers::FileModuleResolver::new_with_path_and_extension(
-                    resolve_path,
-                    "vsl",
-                ),
-            );
+            engine.set_module_resolver(env_resolver::EnvModuleResolver::new(resolve_path));
         }
 
         engine.register_global_module(vsmtp_plugin_vsl::unix_module().into());
@@ -198,6 +202,36 @@ impl Config {
             );
         }
 
+        if config.server.logs.rotation == field::LogRotation::Never
+            && config.server.logs.max_files.is_some()
+        {
+            anyhow::bail!(
+                "The number of log files to keep (`server.logs.max_files`) cannot be set when the logs are not rotated (`server.logs.rotation` is 'never')"
+            );
+        }
+
+        if config
+            .server
+            .smtp
+            .rate_limit
+            .as_ref()
+            .map_or(false, |rate_limit| rate_limit.period.is_zero())
+        {
+            anyhow::bail!("The period of the rate limit (`server.smtp.rate_limit.period`) cannot be zero");
+        }
+
+        if let Err(error) = config
+            .server
+            .smtp
+            .banner_reply(&config.server.name.to_string())
+        {
+            anyhow::bail!("The text of the banner (`server.smtp.banner`) is not valid: {error}");
+        }
+
+        if let Err(error) = config.server.smtp.help_reply("HELO EHLO MAIL RCPT DATA QUIT") {
+            anyhow::bail!("The text of the `HELP` reply (`server.smtp.help`) is not valid: {error}");
+        }
+
         config.get_domain_config(&engine)?;
 
         Ok(config)
@@ -252,7 +286,8 @@ impl Config {
                     .file_name()
                     .map(|filename| filename.to_string_lossy().to_string())
                     .as_deref()
-                    .map(<Domain as std::str::FromStr>::from_str) else {
+                    .map(<Domain as std::str::FromStr>::from_str)
+                else {
                     continue;
                 };
 
@@ -278,7 +313,16 @@ impl Config {
         let config_path = domain_dir.join("config.vsl");
 
         if config_path.exists() {
-            let ast = engine.compile_file(config_path.clone()).with_context(|| {
+            let script = std::fs::read_to_string(&config_path)
+                .with_context(|| format!("Cannot read file at {config_path:?}"))
+                .and_then(|script| env_resolver::substitute_env_vars(&script))
+                .with_context(|| {
+                    format!(
+                        "Failed to compile configuration at '{}'",
+                        config_path.display()
+                    )
+                })?;
+            let ast = engine.compile(script).with_context(|| {
                 format!(
                     "Failed to compile configuration at '{}'",
                     config_path.display()
@@ -22,16 +22,26 @@ where
     D: serde::Deserializer<'de>,
 {
     <Vec<String> as serde::Deserialize>::deserialize(deserializer)?
-        .into_iter()
-        .map(|s| {
-            <std::net::SocketAddr as std::str::FromStr>::from_str(&s)
-                .or_else(|_| ipv6_with_scope_id(&s))
-                .or_else(|_| get_first_valid_socket_from_default_resolver(&s))
-        })
+        .iter()
+        .map(|s| parse(s))
         .collect::<anyhow::Result<Vec<std::net::SocketAddr>>>()
         .map_err(serde::de::Error::custom)
 }
 
+pub fn deserialize_one<'de, D>(deserializer: D) -> Result<std::net::SocketAddr, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    parse(&<String as serde::Deserialize>::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
+
+fn parse(s: &str) -> anyhow::Result<std::net::SocketAddr> {
+    <std::net::SocketAddr as std::str::FromStr>::from_str(s)
+        .or_else(|_| ipv6_with_scope_id(s))
+        .or_else(|_| get_first_valid_socket_from_default_resolver(s))
+}
+
 fn get_first_valid_socket_from_default_resolver(s: &str) -> anyhow::Result<std::net::SocketAddr> {
     let (fqdn, port) = s
         .rsplit_once(':')
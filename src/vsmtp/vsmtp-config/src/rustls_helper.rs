@@ -17,7 +17,9 @@
 use rustls::ALL_CIPHER_SUITES;
 use vsmtp_common::Domain;
 
-use crate::field::{FieldServerTls, FieldServerVirtual, FieldServerVirtualTls};
+use crate::field::{
+    FieldServerTls, FieldServerTlsClientAuth, FieldServerVirtual, FieldServerVirtualTls,
+};
 
 struct TlsLogger;
 impl rustls::KeyLog for TlsLogger {
@@ -58,6 +60,27 @@ impl rustls::server::ResolvesServerCert for CertResolver {
     }
 }
 
+fn to_client_cert_verifier(
+    client_auth: Option<&FieldServerTlsClientAuth>,
+) -> anyhow::Result<std::sync::Arc<dyn rustls::server::ClientCertVerifier>> {
+    let Some(FieldServerTlsClientAuth { ca, required }) = client_auth else {
+        return Ok(rustls::server::NoClientAuth::boxed());
+    };
+
+    let mut roots = rustls::RootCertStore::empty();
+    for i in &ca.inner {
+        roots
+            .add(i)
+            .map_err(|e| anyhow::anyhow!("cannot add client ca '{}': {e}", ca.path.display()))?;
+    }
+
+    Ok(if *required {
+        rustls::server::AllowAnyAuthenticatedClient::new(roots).boxed()
+    } else {
+        rustls::server::AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed()
+    })
+}
+
 #[doc(hidden)]
 pub fn get_rustls_config(
     config: &FieldServerTls,
@@ -119,7 +142,7 @@ pub fn get_rustls_config(
         .with_protocol_versions(protocol_version)
         .map_err(|e| anyhow::anyhow!("cannot initialize tls config: '{e}'"))?
         // TODO: allow configurable ClientAuth (DANE)
-        .with_client_cert_verifier(rustls::server::NoClientAuth::boxed())
+        .with_client_cert_verifier(to_client_cert_verifier(config.client_auth.as_ref())?)
         .with_cert_resolver(std::sync::Arc::new(CertResolver {
             sni_resolver: cert_resolver,
             default_cert: config
@@ -142,3 +165,323 @@ pub fn get_rustls_config(
 
     Ok(tls_config)
 }
+
+/// The details of a certificate, reported by [`check_certified_key`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// Distinguished name of the subject.
+    pub subject: String,
+    /// Start of the validity period.
+    pub not_before: std::time::SystemTime,
+    /// End of the validity period.
+    pub not_after: std::time::SystemTime,
+    /// Subject alternative names, such as `DNSName(example.com)`.
+    pub subject_alt_names: Vec<String>,
+}
+
+/// Check that the private key can be used by `rustls`, that it matches the first
+/// certificate of the chain, and that this certificate is valid at `now`.
+///
+/// # Errors
+///
+/// * the certificate chain is empty or cannot be parsed
+/// * the private key is not supported, or does not match the certificate
+/// * the certificate is not valid yet, or has expired
+pub fn check_certified_key(
+    tls: &FieldServerVirtualTls,
+    now: std::time::SystemTime,
+) -> anyhow::Result<CertificateInfo> {
+    fn to_system_time(time: x509_parser::time::ASN1Time) -> anyhow::Result<std::time::SystemTime> {
+        Ok(
+            std::time::UNIX_EPOCH
+                + std::time::Duration::from_secs(u64::try_from(time.timestamp())?),
+        )
+    }
+
+    const CHALLENGE: &[u8] = b"vsmtp tls check";
+
+    let certificate = tls.certificate.inner.first().ok_or_else(|| {
+        anyhow::anyhow!(
+            "certificate chain is empty: '{}'",
+            tls.certificate.path.display()
+        )
+    })?;
+    let (_, certificate) = x509_parser::parse_x509_certificate(&certificate.0).map_err(|e| {
+        anyhow::anyhow!(
+            "cannot parse certificate '{}': {e}",
+            tls.certificate.path.display()
+        )
+    })?;
+
+    let signer = rustls::sign::any_supported_type(&tls.private_key.inner)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "private key is not supported '{}': {e}",
+                tls.private_key.path.display()
+            )
+        })?
+        .choose_scheme(&[
+            rustls::SignatureScheme::RSA_PSS_SHA256,
+            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
+            rustls::SignatureScheme::ECDSA_NISTP384_SHA384,
+            rustls::SignatureScheme::ED25519,
+        ])
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "private key has no signature scheme supported: '{}'",
+                tls.private_key.path.display()
+            )
+        })?;
+
+    let algorithm: &dyn ring::signature::VerificationAlgorithm = match signer.scheme() {
+        rustls::SignatureScheme::RSA_PSS_SHA256 => &ring::signature::RSA_PSS_2048_8192_SHA256,
+        rustls::SignatureScheme::ECDSA_NISTP256_SHA256 => &ring::signature::ECDSA_P256_SHA256_ASN1,
+        rustls::SignatureScheme::ECDSA_NISTP384_SHA384 => &ring::signature::ECDSA_P384_SHA384_ASN1,
+        _ => &ring::signature::ED25519,
+    };
+
+    ring::signature::UnparsedPublicKey::new(
+        algorithm,
+        &certificate.public_key().subject_public_key.data,
+    )
+    .verify(CHALLENGE, &signer.sign(CHALLENGE)?)
+    .map_err(|_| {
+        anyhow::anyhow!(
+            "private key '{}' does not match the certificate '{}'",
+            tls.private_key.path.display(),
+            tls.certificate.path.display()
+        )
+    })?;
+
+    let validity = certificate.validity();
+    let not_before = to_system_time(validity.not_before)?;
+    let not_after = to_system_time(validity.not_after)?;
+
+    anyhow::ensure!(
+        not_before <= now,
+        "certificate '{}' is not valid before {}",
+        tls.certificate.path.display(),
+        validity.not_before
+    );
+    anyhow::ensure!(
+        now <= not_after,
+        "certificate '{}' has expired on {}",
+        tls.certificate.path.display(),
+        validity.not_after
+    );
+
+    Ok(CertificateInfo {
+        subject: certificate.subject().to_string(),
+        not_before,
+        not_after,
+        subject_alt_names: certificate
+            .subject_alternative_name()?
+            .map(|san| {
+                san.value
+                    .general_names
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_certified_key, get_rustls_config};
+    use crate::field::{FieldServerTls, FieldServerVirtual, FieldServerVirtualTls};
+    use vsmtp_test::get_tls_file;
+
+    // the certificates of the test suite are valid from 2019-06-09 to 2024-11-29.
+    fn in_validity_period() -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_577_836_800) // 2020-01-01
+    }
+
+    fn virtual_tls(name: &str, certificate: &str, key: &str) -> FieldServerVirtualTls {
+        let _droppable = std::fs::DirBuilder::new().create("./tmp");
+
+        let certificate_path = format!("./tmp/{name}.crt");
+        let private_key_path = format!("./tmp/{name}.key");
+        std::fs::write(&certificate_path, certificate).unwrap();
+        std::fs::write(&private_key_path, key).unwrap();
+
+        serde_json::from_value(serde_json::json!({
+            "certificate": certificate_path,
+            "private_key": private_key_path,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn matching_pair() {
+        let info = check_certified_key(
+            &virtual_tls(
+                "check_matching",
+                get_tls_file::get_certificate(),
+                get_tls_file::get_rsa_key(),
+            ),
+            in_validity_period(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            info.subject_alt_names,
+            [
+                "DNSName(testserver.com)",
+                "DNSName(second.testserver.com)",
+                "DNSName(localhost)"
+            ]
+        );
+        assert!(info.not_before < in_validity_period());
+        assert!(in_validity_period() < info.not_after);
+    }
+
+    #[test]
+    fn mismatched_pair() {
+        let error = check_certified_key(
+            &virtual_tls(
+                "check_mismatched",
+                get_tls_file::get_certificate(),
+                get_tls_file::get_ec256_key(),
+            ),
+            in_validity_period(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "private key './tmp/check_mismatched.key' does not match the certificate './tmp/check_mismatched.crt'"
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let tls = virtual_tls(
+            "check_expired",
+            get_tls_file::get_certificate(),
+            get_tls_file::get_rsa_key(),
+        );
+        let info = check_certified_key(&tls, in_validity_period()).unwrap();
+
+        let error = check_certified_key(&tls, info.not_after + std::time::Duration::from_secs(1))
+            .unwrap_err();
+
+        assert!(error.to_string().contains("has expired on"), "{error}");
+    }
+
+    /// Accept any certificate, the test only checks which one is presented.
+    struct AcceptAny;
+
+    impl rustls::client::ServerCertVerifier for AcceptAny {
+        fn verify_server_cert(
+            &self,
+            _: &rustls::Certificate,
+            _: &[rustls::Certificate],
+            _: &rustls::ServerName,
+            _: &mut dyn Iterator<Item = &[u8]>,
+            _: &[u8],
+            _: std::time::SystemTime,
+        ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        }
+    }
+
+    /// Run a handshake in memory, and return the certificate presented by the server.
+    fn presented_certificate(
+        server_config: &std::sync::Arc<rustls::ServerConfig>,
+        sni: &str,
+    ) -> Result<rustls::Certificate, rustls::Error> {
+        let client_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(std::sync::Arc::new(AcceptAny))
+            .with_no_client_auth();
+
+        let mut client = rustls::ClientConnection::new(
+            std::sync::Arc::new(client_config),
+            sni.try_into().unwrap(),
+        )?;
+        let mut server = rustls::ServerConnection::new(server_config.clone())?;
+
+        while client.is_handshaking() {
+            let mut buffer = vec![];
+            client.write_tls(&mut buffer).unwrap();
+            server.read_tls(&mut buffer.as_slice()).unwrap();
+            server.process_new_packets()?;
+
+            let mut buffer = vec![];
+            server.write_tls(&mut buffer).unwrap();
+            client.read_tls(&mut buffer.as_slice()).unwrap();
+            client.process_new_packets()?;
+        }
+
+        Ok(client.peer_certificates().unwrap()[0].clone())
+    }
+
+    #[test]
+    fn certificate_selected_by_sni() {
+        let rsa = || {
+            virtual_tls(
+                "sni_rsa",
+                get_tls_file::get_certificate(),
+                get_tls_file::get_rsa_key(),
+            )
+        };
+        let ec256 = || {
+            virtual_tls(
+                "sni_ec256",
+                get_tls_file::get_ec256_certificate(),
+                get_tls_file::get_ec256_key(),
+            )
+        };
+
+        let virtual_entries = [
+            ("testserver.com", rsa()),
+            ("second.testserver.com", ec256()),
+        ]
+        .into_iter()
+        .map(|(domain, tls)| {
+            (
+                domain.parse().unwrap(),
+                FieldServerVirtual {
+                    tls: Some(tls),
+                    dns: None,
+                    dkim: None,
+                },
+            )
+        })
+        .collect();
+
+        let mut tls_config: FieldServerTls = serde_json::from_value(serde_json::json!({
+            "protocol_version": ["TLSv1.2", "TLSv1.3"],
+        }))
+        .unwrap();
+
+        let server_config =
+            std::sync::Arc::new(get_rustls_config(&tls_config, &virtual_entries).unwrap());
+
+        assert_eq!(
+            presented_certificate(&server_config, "testserver.com").unwrap(),
+            rsa().certificate.inner[0]
+        );
+        assert_eq!(
+            presented_certificate(&server_config, "second.testserver.com").unwrap(),
+            ec256().certificate.inner[0]
+        );
+        // no certificate for this name, and no default one
+        assert!(presented_certificate(&server_config, "unknown.com").is_err());
+
+        tls_config.root = Some(ec256());
+        let server_config =
+            std::sync::Arc::new(get_rustls_config(&tls_config, &virtual_entries).unwrap());
+
+        assert_eq!(
+            presented_certificate(&server_config, "testserver.com").unwrap(),
+            rsa().certificate.inner[0]
+        );
+        assert_eq!(
+            presented_certificate(&server_config, "unknown.com").unwrap(),
+            ec256().certificate.inner[0]
+        );
+    }
+}
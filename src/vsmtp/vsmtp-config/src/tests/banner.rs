@@ -0,0 +1,67 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::Config;
+
+fn with_banner(banner: &str) -> anyhow::Result<Config> {
+    Config::from_vsl_script(
+        format!(
+            r#"
+fn on_config(config) {{
+    config.server.system.user = "root";
+    config.server.system.group = "root";
+    config.server.name = "mail.example.com";
+    config.server.smtp.banner = {banner:?};
+    config
+}}
+"#
+        ),
+        None,
+    )
+}
+
+#[test]
+fn custom_banner() {
+    let config = with_banner("{hostname} ESMTP\nUnsolicited bulk email is not accepted").unwrap();
+    assert_eq!(
+        config
+            .server
+            .smtp
+            .banner_reply("mail.example.com")
+            .unwrap()
+            .to_string(),
+        "220-mail.example.com ESMTP\r\n220 Unsolicited bulk email is not accepted\r\n"
+    );
+}
+
+#[test]
+fn empty_banner() {
+    let config = with_banner("").unwrap();
+    assert_eq!(
+        config
+            .server
+            .smtp
+            .banner_reply("mail.example.com")
+            .unwrap()
+            .to_string(),
+        "220 mail.example.com\r\n"
+    );
+}
+
+#[test]
+fn invalid_banner() {
+    assert!(with_banner("{hostname} ESMTP\n2.0.0 ready").is_err());
+}
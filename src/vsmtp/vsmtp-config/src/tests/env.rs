@@ -0,0 +1,65 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::Config;
+
+fn path_of(file: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src/tests/env", file])
+}
+
+#[test]
+fn substituted_from_env_file() {
+    dotenv::from_path(path_of("vsmtp.env")).unwrap();
+
+    let config = Config::from_vsl_file(path_of("config.vsl")).unwrap();
+    // the comments, string literals and template strings of the script are left untouched.
+    assert_eq!(config.server.name.to_string(), "testserver.com");
+    assert_eq!(
+        config.app.dirpath,
+        std::path::PathBuf::from("${VSMTP_UNDEFINED_APP_DIRPATH}")
+    );
+
+    let root = config.server.tls.unwrap().root.unwrap();
+
+    assert_eq!(
+        root.certificate.path,
+        std::path::PathBuf::from("../../../examples/config/tls/certificate.crt")
+    );
+    assert_eq!(
+        root.private_key.path,
+        std::path::PathBuf::from("../../../examples/config/tls/private_key.key")
+    );
+}
+
+#[test]
+fn undefined_variable() {
+    let error = Config::from_vsl_file(path_of("undefined.vsl")).unwrap_err();
+
+    assert_eq!(
+        error.root_cause().to_string(),
+        "environment variable not found"
+    );
+    assert!(format!("{error:#}")
+        .contains("Environment variable `VSMTP_UNDEFINED_SERVER_NAME` is not defined"));
+}
+
+#[test]
+fn undefined_variable_in_module() {
+    let error = Config::from_vsl_file(path_of("undefined_in_module.vsl")).unwrap_err();
+
+    assert!(format!("{error:#}")
+        .contains("Environment variable `VSMTP_UNDEFINED_SERVER_NAME` is not defined"));
+}
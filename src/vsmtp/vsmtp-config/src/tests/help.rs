@@ -0,0 +1,64 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::Config;
+
+fn with_help(help: &str) -> anyhow::Result<Config> {
+    Config::from_vsl_script(
+        format!(
+            r#"
+fn on_config(config) {{
+    config.server.system.user = "root";
+    config.server.system.group = "root";
+    config.server.smtp.help = {help:?};
+    config
+}}
+"#
+        ),
+        None,
+    )
+}
+
+#[test]
+fn custom_help() {
+    let config =
+        with_help("Commands supported: HELO EHLO MAIL RCPT DATA QUIT\nSee https://example.com")
+            .unwrap();
+    assert_eq!(
+        config.server.smtp.help_reply("HELO").unwrap().to_string(),
+        "214-Commands supported: HELO EHLO MAIL RCPT DATA QUIT\r\n214 See https://example.com\r\n"
+    );
+}
+
+#[test]
+fn invalid_help() {
+    assert!(with_help("").is_err());
+    assert!(with_help("Supported:\n2.0.0 HELO EHLO").is_err());
+}
+
+#[test]
+fn default_help() {
+    let config = with_help("Commands supported: {commands}").unwrap();
+    assert_eq!(
+        config
+            .server
+            .smtp
+            .help_reply("HELO EHLO MAIL RCPT DATA QUIT")
+            .unwrap()
+            .to_string(),
+        "214 Commands supported: HELO EHLO MAIL RCPT DATA QUIT\r\n"
+    );
+}
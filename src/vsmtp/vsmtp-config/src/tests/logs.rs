@@ -0,0 +1,72 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::{
+    field::{LogFormat, LogRotation},
+    Config,
+};
+
+fn with_logs(logs: &str) -> anyhow::Result<Config> {
+    Config::from_vsl_script(
+        format!(
+            r#"
+fn on_config(config) {{
+    config.server.system.user = "root";
+    config.server.system.group = "root";
+    config.server.logs = #{{ {logs} }};
+    config
+}}
+"#
+        ),
+        None,
+    )
+}
+
+#[test]
+fn rotation() {
+    let config = with_logs(r#"rotation: "hourly", max_files: 24"#).unwrap();
+
+    assert_eq!(config.server.logs.rotation, LogRotation::Hourly);
+    assert_eq!(config.server.logs.max_files.map(usize::from), Some(24));
+
+    let config = with_logs("").unwrap();
+
+    assert_eq!(config.server.logs.rotation, LogRotation::Never);
+    assert_eq!(config.server.logs.max_files, None);
+}
+
+#[test]
+fn max_files_without_rotation() {
+    assert_eq!(
+        with_logs(r#"rotation: "never", max_files: 7"#)
+            .unwrap_err()
+            .to_string(),
+        "The number of log files to keep (`server.logs.max_files`) cannot be set when the logs are not rotated (`server.logs.rotation` is 'never')"
+    );
+    assert!(with_logs("max_files: 7").is_err());
+    assert!(with_logs(r#"rotation: "daily", max_files: 0"#).is_err());
+}
+
+#[test]
+fn format() {
+    let config = with_logs(r#"format: "json""#).unwrap();
+    assert_eq!(config.server.logs.format, LogFormat::Json);
+
+    let config = with_logs("").unwrap();
+    assert_eq!(config.server.logs.format, LogFormat::Text);
+
+    assert!(with_logs(r#"format: "yaml""#).is_err());
+}
@@ -14,6 +14,12 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
+mod banner;
+mod env;
+mod help;
+mod logs;
+mod rate_limit;
+mod replies;
 mod root_example {
     mod logging;
     mod secured;
@@ -0,0 +1,52 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::Config;
+
+fn with_rate_limit(rate_limit: &str) -> anyhow::Result<Config> {
+    Config::from_vsl_script(
+        format!(
+            r#"
+fn on_config(config) {{
+    config.server.system.user = "root";
+    config.server.system.group = "root";
+    config.server.smtp.rate_limit = #{{ {rate_limit} }};
+    config
+}}
+"#
+        ),
+        None,
+    )
+}
+
+#[test]
+fn period() {
+    let config = with_rate_limit(r#"rate: 10, period: "1m", burst: 20"#).unwrap();
+    assert_eq!(
+        config.server.smtp.rate_limit.unwrap().period,
+        std::time::Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn zero_period() {
+    assert_eq!(
+        with_rate_limit(r#"rate: 10, period: "0s", burst: 20"#)
+            .unwrap_err()
+            .to_string(),
+        "The period of the rate limit (`server.smtp.rate_limit.period`) cannot be zero"
+    );
+}
@@ -0,0 +1,84 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::Config;
+use vsmtp_common::{RejectionReason, Reply};
+
+fn with_replies(replies: &str) -> anyhow::Result<Config> {
+    Config::from_vsl_script(
+        format!(
+            r#"
+fn on_config(config) {{
+    config.server.system.user = "root";
+    config.server.system.group = "root";
+    config.server.smtp.replies = #{{ {replies} }};
+    config
+}}
+"#
+        ),
+        None,
+    )
+}
+
+#[test]
+fn custom_replies() {
+    let config = with_replies(
+        r#"
+        relay_denied: "554 5.7.1 Relaying is restricted to our customers",
+        greylisted: #{ code: 450, enhanced: "4.7.1", text: "Greylisted, come back later" },
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config
+            .server
+            .smtp
+            .reply(RejectionReason::RelayDenied)
+            .to_string(),
+        "554 5.7.1 Relaying is restricted to our customers\r\n"
+    );
+    assert_eq!(
+        config
+            .server
+            .smtp
+            .reply(RejectionReason::Greylisted)
+            .to_string(),
+        "450 4.7.1 Greylisted, come back later\r\n"
+    );
+    assert_eq!(
+        config.server.smtp.reply(RejectionReason::SpfFailed),
+        RejectionReason::SpfFailed.default_reply()
+    );
+}
+
+#[test]
+fn default_replies() {
+    let config = with_replies("").unwrap();
+
+    assert!(config.server.smtp.replies.is_empty());
+    assert_eq!(
+        config.server.smtp.reply(RejectionReason::RelayDenied),
+        "554 5.7.1 Relay access denied\r\n".parse::<Reply>().unwrap()
+    );
+}
+
+#[test]
+fn invalid_replies() {
+    assert!(with_replies(r#"not_a_reason: "554 5.7.1 Denied""#).is_err());
+    assert!(with_replies(r#"relay_denied: "Denied""#).is_err());
+    assert!(with_replies(r#"relay_denied: #{ enhanced: "5.7.1", text: "Denied" }"#).is_err());
+}
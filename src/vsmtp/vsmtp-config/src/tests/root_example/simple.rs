@@ -35,7 +35,8 @@ fn parse() {
             .with_interfaces(
                 &["127.0.0.1:25".parse().unwrap()],
                 &["127.0.0.1:587".parse().unwrap()],
-                &["127.0.0.1:465".parse().unwrap()]
+                &["127.0.0.1:465".parse().unwrap()],
+                &[]
             )
             .with_default_logs_settings()
             .with_default_delivery()
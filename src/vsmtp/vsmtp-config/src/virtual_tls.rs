@@ -15,7 +15,7 @@
  *
 */
 use crate::{
-    field::{FieldServerVirtualTls, SecretFile},
+    field::{FieldServerTlsClientAuth, FieldServerVirtualTls, SecretFile},
     parser::{tls_certificate, tls_private_key},
 };
 use vsmtp_auth::dkim;
@@ -126,3 +126,20 @@ impl FieldServerVirtualTls {
         })
     }
 }
+
+impl FieldServerTlsClientAuth {
+    /// create a client authentication configuration from the certificate authorities path.
+    ///
+    /// # Errors
+    ///
+    /// * certificate file not found.
+    pub fn from_path(ca: &str, required: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            ca: SecretFile::<Vec<rustls::Certificate>> {
+                inner: tls_certificate::from_path(ca)?,
+                path: ca.into(),
+            },
+            required,
+        })
+    }
+}
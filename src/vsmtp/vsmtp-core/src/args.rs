@@ -43,7 +43,7 @@ pub struct Args {
     #[clap(short, long, action)]
     pub config: String,
 
-    /// Absolute path of a dotenv file.
+    /// Absolute path of a dotenv file, its variables can be referenced in the configuration as `${VAR}`.
     #[clap(short, long, action)]
     pub env: Option<String>,
 
@@ -77,6 +77,18 @@ pub enum Commands {
     ConfigShow,
     /// Show the difference between the loaded config and the default one
     ConfigDiff,
+    /// Check the certificates and private keys of the loaded config, showing their
+    /// validity period and subject alternative names, and exit with an error on any problem
+    TlsCheck,
+    /// Run the rules of a stage against a message, without any SMTP session,
+    /// and show the resulting status and the changes made to the headers
+    Run {
+        /// Path of the message to process. (.eml format)
+        eml: std::path::PathBuf,
+        /// Stage of the rules to run.
+        #[clap(short, long, action)]
+        stage: vsmtp_rule_engine::ExecutionStage,
+    },
 }
 
 #[cfg(test)]
@@ -141,6 +153,46 @@ mod tests {
             <Args as clap::Parser>::try_parse_from(["", "-c", "path", "config-diff"]).unwrap()
         );
 
+        assert_eq!(
+            Args {
+                version: false,
+                command: Some(Commands::TlsCheck),
+                config: "path".to_string(),
+                env: None,
+                no_daemon: false,
+                stdout: false,
+                timeout: None
+            },
+            <Args as clap::Parser>::try_parse_from(["", "-c", "path", "tls-check"]).unwrap()
+        );
+
+        assert_eq!(
+            Args {
+                version: false,
+                command: Some(Commands::Run {
+                    eml: "message.eml".into(),
+                    stage: vsmtp_rule_engine::ExecutionStage::PreQ
+                }),
+                config: "path".to_string(),
+                env: None,
+                no_daemon: false,
+                stdout: false,
+                timeout: None
+            },
+            <Args as clap::Parser>::try_parse_from([
+                "",
+                "-c",
+                "path",
+                "run",
+                "message.eml",
+                "--stage",
+                "preq"
+            ])
+            .unwrap()
+        );
+
+        assert!(<Args as clap::Parser>::try_parse_from(["", "run", "message.eml"]).is_err());
+
         assert_eq!(
             Args {
                 version: true,
@@ -35,67 +35,217 @@
 )]
 
 mod args;
+#[cfg(feature = "otlp")]
+mod otlp;
+#[cfg(feature = "syslog")]
+mod syslog;
 
 pub use args::{Args, Commands};
 
+use anyhow::Context;
+
 // Tokio-tracing systems
 // pub mod tracing_subscriber;
 
 #[cfg(debug_assertions)]
-macro_rules! get_fmt {
+macro_rules! get_text_fmt {
     () => {
         tracing_subscriber::fmt::layer()
             .with_file(true)
             .with_line_number(true)
             .with_thread_ids(true)
             .with_target(true)
-            .with_ansi(false)
     };
 }
 
 #[cfg(not(debug_assertions))]
-macro_rules! get_fmt {
+macro_rules! get_text_fmt {
     () => {
         tracing_subscriber::fmt::layer()
             .compact()
             .with_thread_ids(false)
             .with_target(false)
-            .with_ansi(false)
+    };
+}
+
+macro_rules! get_fmt {
+    ($format:expr, $writer:expr, $ansi:expr) => {
+        match $format {
+            LogFormat::Text => get_text_fmt!()
+                .with_ansi($ansi)
+                .with_writer($writer)
+                .boxed(),
+            // NOTE: the fields of the spans (connection id, client address, stage ...)
+            //       are attached to each record.
+            LogFormat::Json => tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_file(cfg!(debug_assertions))
+                .with_line_number(cfg!(debug_assertions))
+                .with_thread_ids(cfg!(debug_assertions))
+                .with_target(true)
+                .with_writer($writer)
+                .boxed(),
+        }
     };
 }
 
 macro_rules! file_writer {
-    ($filename:expr, $filter:expr) => {{
+    ($logs:expr, $filename:expr, $writer_backend:expr, $filter:expr) => {{
         use tracing_subscriber::fmt::writer::MakeWriterExt;
 
+        let logs: &vsmtp_config::field::FieldServerLogs = $logs;
         let filename: &std::path::Path = $filename;
-        let writer_backend = if let (Some(directory), Some(file_name)) = (
+        let writer_backend: SharedAppender = $writer_backend;
+        if let (Some(directory), Some(file_name), Some(max_files)) = (
             filename.parent(),
             filename.file_name().and_then(std::ffi::OsStr::to_str),
+            logs.max_files,
         ) {
-            tracing_appender::rolling::never(directory, file_name)
-        } else {
-            anyhow::bail!(
-                "filepath at '{}' does not have a parent or is not valid",
-                filename.display()
-            )
-        };
+            remove_oldest_log_files(directory, file_name, max_files.get())?;
+        }
 
-        get_fmt!().with_writer(writer_backend.with_filter($filter))
+        get_fmt!(logs.format, writer_backend.with_filter($filter), false)
     }};
 }
 
+/// Build the appender of the log file at `path`, rolled according to the configuration.
+fn file_appender(
+    logs: &vsmtp_config::field::FieldServerLogs,
+    path: &std::path::Path,
+) -> anyhow::Result<tracing_appender::rolling::RollingFileAppender> {
+    use vsmtp_config::field::LogRotation;
+
+    let (Some(directory), Some(file_name)) = (
+        path.parent(),
+        path.file_name().and_then(std::ffi::OsStr::to_str),
+    ) else {
+        anyhow::bail!(
+            "filepath at '{}' does not have a parent or is not valid",
+            path.display()
+        )
+    };
+
+    Ok(match logs.rotation {
+        LogRotation::Daily => tracing_appender::rolling::daily(directory, file_name),
+        LogRotation::Hourly => tracing_appender::rolling::hourly(directory, file_name),
+        LogRotation::Never => tracing_appender::rolling::never(directory, file_name),
+    })
+}
+
+/// The appender of a log file, shared by all the writers of the file.
+#[derive(Clone)]
+struct SharedAppender(std::sync::Arc<tracing_appender::rolling::RollingFileAppender>);
+
+impl SharedAppender {
+    fn new(
+        logs: &vsmtp_config::field::FieldServerLogs,
+        path: &std::path::Path,
+    ) -> anyhow::Result<Self> {
+        Ok(Self(std::sync::Arc::new(file_appender(logs, path)?)))
+    }
+}
+
+impl std::io::Write for SharedAppender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        use tracing_subscriber::fmt::MakeWriter;
+        self.0.make_writer().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        use tracing_subscriber::fmt::MakeWriter;
+        self.0.make_writer().flush()
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for SharedAppender {
+    type Writer = tracing_appender::rolling::RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.0.make_writer()
+    }
+}
+
+/// Remove the oldest files rolled from `file_name`, keeping the `max_files` most recent ones.
+fn remove_oldest_log_files(
+    directory: &std::path::Path,
+    file_name: &str,
+    max_files: usize,
+) -> anyhow::Result<()> {
+    let prefix = format!("{file_name}.");
+
+    let mut rolled_files = std::fs::read_dir(directory)
+        .with_context(|| format!("Cannot read the log directory '{}'", directory.display()))?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map_or(false, |kind| kind.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(std::ffi::OsStr::to_str)
+                .map_or(false, |name| name.starts_with(&prefix))
+        })
+        .collect::<Vec<_>>();
+
+    // NOTE: the date appended to the rolled files sorts them in chronological order.
+    rolled_files.sort_unstable_by(|a, b| b.cmp(a));
+
+    for path in rolled_files.into_iter().skip(max_files) {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Cannot remove the log file '{}'", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Exporters of the tracing subsystem running on background threads.
+///
+/// They are started by [`Exporters::start`] once the process has been daemonized,
+/// and flushed when dropped.
+#[must_use]
+pub struct Exporters {
+    #[cfg(feature = "otlp")]
+    otlp: otlp::Otlp,
+}
+
+impl Exporters {
+    /// Start the exporters enabled in the configuration.
+    ///
+    /// # Errors
+    ///
+    /// * an exporter could not be built
+    pub fn start(&mut self, config: &vsmtp_config::Config) -> anyhow::Result<()> {
+        #[cfg(feature = "otlp")]
+        if let Some(otlp) = &config.server.logs.otlp {
+            self.otlp.start(otlp)?;
+        }
+        #[cfg(not(feature = "otlp"))]
+        let _ = config;
+
+        Ok(())
+    }
+}
+
 /// Initialize the tracing subsystem.
 ///
 /// # Errors
 ///
-#[allow(clippy::items_after_statements)]
-pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<()> {
+#[allow(clippy::items_after_statements, clippy::too_many_lines)]
+pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<Exporters> {
     const TARGET_VSL_LOG: &str = "vsmtp_rule_engine::api::logging::logging";
-    #[allow(unused_imports)]
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+    use vsmtp_config::field::LogFormat;
 
-    let subscriber = tracing_subscriber::registry().with({
+    let subscriber = tracing_subscriber::registry();
+
+    // NOTE: reloaded with the exporter by `Exporters::start`.
+    #[cfg(feature = "otlp")]
+    let (subscriber, otlp) = {
+        let (layer, otlp) = otlp::Otlp::layer();
+        (subscriber.with(layer), otlp)
+    };
+
+    let subscriber = subscriber.with({
         let mut e = tracing_subscriber::EnvFilter::default();
         for i in &config.server.logs.level {
             e = e.add_directive(i.clone());
@@ -115,14 +265,23 @@ pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<(
         ),
     );
 
+    let format = config.server.logs.format;
+    let server_appender = SharedAppender::new(&config.server.logs, &config.server.logs.filename)?;
+    #[cfg(feature = "syslog")]
+    let syslog_appender = server_appender.clone();
     let subscriber = subscriber
         .with(file_writer!(
+            &config.server.logs,
             &config.server.logs.filename,
+            server_appender,
             |metadata| metadata.target() != TARGET_VSL_LOG
         ))
-        .with(file_writer!(&config.app.logs.filename, |metadata| metadata
-            .target()
-            == TARGET_VSL_LOG));
+        .with(file_writer!(
+            &config.server.logs,
+            &config.app.logs.filename,
+            SharedAppender::new(&config.server.logs, &config.app.logs.filename)?,
+            |metadata| metadata.target() == TARGET_VSL_LOG
+        ));
 
     #[cfg(feature = "journald")]
     let subscriber = {
@@ -137,8 +296,7 @@ pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<(
     macro_rules! try_init {
         ($s:expr) => {
             if args.stdout {
-                $s.with(get_fmt!().with_writer(std::io::stdout).with_ansi(true))
-                    .try_init()
+                $s.with(get_fmt!(format, std::io::stdout, true)).try_init()
             } else {
                 $s.try_init()
             }?
@@ -147,31 +305,21 @@ pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<(
 
     cfg_if::cfg_if! {
         if #[cfg(feature = "syslog")] {
-            use tracing_rfc_5424::transport::{TcpTransport, UdpTransport, UnixSocket};
-            use vsmtp_config::field::SyslogSocket;
             let sys_level = config.server.logs.sys_level;
 
-            macro_rules! syslog_writer {
-                ($s:expr, $transport:expr) => {
-                    $s.with(
-                        tracing_rfc_5424::layer::Layer::with_transport($transport).with_filter(
-                            tracing_subscriber::filter::filter_fn(move |i| *i.level() <= sys_level),
-                        ),
-                    )
-                };
-            }
-
-            match &config.server.logs.syslog {
-                SyslogSocket::Udp { server } => {
-                    try_init!(syslog_writer!(subscriber, UdpTransport::new(server)?));
-                }
-                SyslogSocket::Tcp { server } => {
-                    try_init!(syslog_writer!(subscriber, TcpTransport::new(server)?));
-                }
-                SyslogSocket::Unix { path } => {
-                    try_init!(syslog_writer!(subscriber, UnixSocket::new(path)?));
-                }
-            };
+            // NOTE: an unreachable syslog daemon does not prevent the startup,
+            //       the socket is reopened in the background.
+            try_init!(subscriber.with(
+                tracing_rfc_5424::layer::Layer::with_transport(syslog::Transport::new(
+                    config.server.logs.syslog.clone(),
+                    config.server.logs.format,
+                    // NOTE: the records are written in the server log file, with the same appender.
+                    syslog_appender,
+                ))
+                .with_filter(tracing_subscriber::filter::filter_fn(move |i| {
+                    *i.level() <= sys_level
+                })),
+            ));
         } else {
             try_init!(subscriber);
         }
@@ -194,6 +342,16 @@ pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<(
             debug_info += "tokio_console=true,";
         }
     }
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "otlp")] {
+            debug_info += "otlp=true,";
+        }
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "metrics")] {
+            debug_info += "metrics=true,";
+        }
+    }
 
     tracing::info!(
         server = ?config.server.logs.filename,
@@ -203,5 +361,87 @@ pub fn init_logs(args: &Args, config: &vsmtp_config::Config) -> anyhow::Result<(
         debug_info
     );
 
-    Ok(())
+    Ok(Exporters {
+        #[cfg(feature = "otlp")]
+        otlp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_oldest_log_files;
+
+    #[test]
+    fn remove_oldest_rolled_files() {
+        let directory = std::env::temp_dir().join("vsmtp_remove_oldest_rolled_files");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for name in [
+            "vsmtp.log.2023-01-01-10",
+            "vsmtp.log.2023-01-01-11",
+            "vsmtp.log.2023-01-01-12",
+            "vsmtp.log.2023-01-02-00",
+            "app.log.2023-01-01-10",
+        ] {
+            std::fs::write(directory.join(name), "").unwrap();
+        }
+
+        remove_oldest_log_files(&directory, "vsmtp.log", 2).unwrap();
+
+        let mut remaining = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        remaining.sort();
+
+        assert_eq!(
+            remaining,
+            [
+                "app.log.2023-01-01-10",
+                "vsmtp.log.2023-01-01-12",
+                "vsmtp.log.2023-01-02-00"
+            ]
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "syslog")]
+    #[test]
+    fn syslog_severity() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+
+        let transport = super::syslog::Transport::new(
+            vsmtp_config::field::SyslogSocket::Udp {
+                server: server.local_addr().unwrap(),
+            },
+            vsmtp_config::field::LogFormat::Text,
+            std::io::sink(),
+        );
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_rfc_5424::layer::Layer::with_transport(transport));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!("error");
+            tracing::warn!("warn");
+            tracing::info!("info");
+        });
+
+        let mut buffer = [0; 1024];
+        let mut receive = || {
+            let size = server.recv(&mut buffer).unwrap();
+            String::from_utf8_lossy(&buffer[..size]).into_owned()
+        };
+
+        // NOTE: the priority is `facility * 8 + severity`, with the facility LOG_USER (1).
+        assert!(receive().starts_with("<11>"));
+        assert!(receive().starts_with("<12>"));
+        assert!(receive().starts_with("<14>"));
+    }
 }
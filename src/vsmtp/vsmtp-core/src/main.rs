@@ -17,8 +17,13 @@
 use anyhow::Context;
 use clap::{crate_name, crate_version};
 use vsmtp::{Args, Commands};
-use vsmtp_common::libc_abstraction::{daemon, initgroups};
-use vsmtp_config::Config;
+use vsmtp_common::{
+    libc_abstraction::{daemon, initgroups},
+    Address, ClientName, TransactionType,
+};
+use vsmtp_config::{check_certified_key, Config, DnsResolvers};
+use vsmtp_mail_parser::MessageBody;
+use vsmtp_rule_engine::{ExecutionStage, RuleEngine};
 use vsmtp_server::{socket_bind_anyhow, start_runtime};
 
 fn main() {
@@ -44,6 +49,154 @@ fn bind_sockets(addr: &[std::net::SocketAddr]) -> anyhow::Result<Vec<std::net::T
         .collect::<anyhow::Result<Vec<std::net::TcpListener>>>()
 }
 
+fn print_diff(left: &str, right: &str) {
+    for diff in diff::lines(left, right) {
+        match diff {
+            diff::Result::Left(left) => println!("-\x1b[0;31m{left}\x1b[0m"),
+            diff::Result::Both(same, _) => println!(" {same}"),
+            diff::Result::Right(right) => println!("+\x1b[0;32m{right}\x1b[0m"),
+        }
+    }
+}
+
+fn headers_of(message: &MessageBody) -> String {
+    message
+        .inner()
+        .headers_lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Run the rules of `stage` against the message stored at `eml`.
+///
+/// The envelop is built from the `From`, `To`, `Cc` and `Bcc` headers of the message.
+fn dry_run(config: Config, eml: &std::path::Path, stage: ExecutionStage) -> anyhow::Result<()> {
+    let content = std::fs::read_to_string(eml)
+        .with_context(|| format!("Cannot read the message '{}'", eml.display()))?;
+    // NOTE: the messages stored on disk usually do not use CRLF line endings.
+    let content = content
+        .lines()
+        .map(|line| format!("{line}\r\n"))
+        .collect::<String>();
+    let message = MessageBody::try_from(content.as_str()).context("Cannot parse the message")?;
+
+    let reverse_path = message
+        .get_addresses("From")
+        .first()
+        .map(|mailbox| mailbox.address.parse::<Address>())
+        .transpose()
+        .context("Invalid address in the 'From' header")?;
+    let forward_paths = ["To", "Cc", "Bcc"]
+        .into_iter()
+        .flat_map(|header| message.get_addresses(header))
+        .map(|mailbox| mailbox.address.parse::<Address>())
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("Invalid address in the recipients headers")?;
+    anyhow::ensure!(
+        !forward_paths.is_empty(),
+        "The message has no recipient ('To', 'Cc' or 'Bcc' headers)"
+    );
+
+    let config = std::sync::Arc::new(config);
+    let queue_manager =
+        <vqueue::fs::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])?;
+    let resolvers = std::sync::Arc::new(
+        DnsResolvers::from_config(&config).context("could not initialize dns")?,
+    );
+    let rule_engine = RuleEngine::new(config.clone(), resolvers, queue_manager)?;
+
+    let is_handled = |address: &Address| rule_engine.is_handled_domain(&address.domain());
+    let transaction_type = match &reverse_path {
+        Some(reverse_path) if is_handled(reverse_path) => {
+            if forward_paths.iter().all(is_handled) {
+                TransactionType::Internal
+            } else {
+                TransactionType::Outgoing {
+                    domain: reverse_path.domain(),
+                }
+            }
+        }
+        _ => TransactionType::Incoming(
+            forward_paths
+                .iter()
+                .find(|forward_path| is_handled(forward_path))
+                .map(Address::domain),
+        ),
+    };
+
+    let context = vsmtp_rule_engine::local_context(
+        config.server.name.clone(),
+        ClientName::Ip4(std::net::Ipv4Addr::LOCALHOST),
+        reverse_path,
+        forward_paths,
+        transaction_type,
+    );
+
+    let headers_before = headers_of(&message);
+    let (_, message, status) = rule_engine.dry_run(stage, context, message)?;
+
+    println!("Status: {}", serde_json::to_string_pretty(&status)?);
+    println!("Headers:");
+    print_diff(&headers_before, &headers_of(&message));
+
+    Ok(())
+}
+
+fn tls_check(config: &Config) -> anyhow::Result<()> {
+    let root = config
+        .server
+        .tls
+        .as_ref()
+        .and_then(|tls| tls.root.as_ref())
+        .map(|root| ("root".to_string(), root));
+    let virtual_entries = config
+        .server
+        .r#virtual
+        .iter()
+        .filter_map(|(domain, entry)| entry.tls.as_ref().map(|tls| (domain.to_string(), tls)));
+
+    let now = std::time::SystemTime::now();
+    let mut failed = 0;
+    let mut checked = 0;
+
+    for (name, tls) in root.into_iter().chain(virtual_entries) {
+        checked += 1;
+        match check_certified_key(tls, now) {
+            Ok(info) => {
+                println!("{name}: ok");
+                println!("  subject: {}", info.subject);
+                println!(
+                    "  not before: {}",
+                    humantime::format_rfc3339_seconds(info.not_before)
+                );
+                println!(
+                    "  not after: {}",
+                    humantime::format_rfc3339_seconds(info.not_after)
+                );
+                println!(
+                    "  subject alternative names: {}",
+                    info.subject_alt_names.join(", ")
+                );
+            }
+            Err(error) => {
+                failed += 1;
+                println!("{name}: {error}");
+            }
+        }
+    }
+
+    anyhow::ensure!(
+        failed == 0,
+        "{failed} of the {checked} certificates are invalid"
+    );
+    if checked == 0 {
+        println!("No certificate configured.");
+    }
+
+    Ok(())
+}
+
 fn try_main() -> anyhow::Result<()> {
     let args = <Args as clap::Parser>::parse();
 
@@ -57,6 +210,11 @@ fn try_main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    // loaded before the configuration, which can reference the variables of the file.
+    if let Some(t) = &args.env {
+        dotenv::from_path(t)?;
+    }
+
     let config = Config::from_vsl_file(&args.config).context("Cannot parse the configuration")?;
 
     if let Some(command) = args.command {
@@ -69,24 +227,29 @@ fn try_main() -> anyhow::Result<()> {
             Commands::ConfigDiff => {
                 let loaded_config = serde_json::to_string_pretty(&config)?;
                 let default_config = serde_json::to_string_pretty(&Config::default())?;
-                for diff in diff::lines(&default_config, &loaded_config) {
-                    match diff {
-                        diff::Result::Left(left) => println!("-\x1b[0;31m{left}\x1b[0m"),
-                        diff::Result::Both(same, _) => println!(" {same}"),
-                        diff::Result::Right(right) => println!("+\x1b[0;32m{right}\x1b[0m"),
-                    }
-                }
+                print_diff(&default_config, &loaded_config);
                 return Ok(());
             }
+            Commands::TlsCheck => return tls_check(&config),
+            Commands::Run { eml, stage } => return dry_run(config, &eml, stage),
         }
     }
 
-    vsmtp::init_logs(&args, &config)?;
+    let mut exporters = vsmtp::init_logs(&args, &config)?;
 
     let sockets = (
         bind_sockets(&config.server.interfaces.addr)?,
         bind_sockets(&config.server.interfaces.addr_submission)?,
         bind_sockets(&config.server.interfaces.addr_submissions)?,
+        bind_sockets(&config.server.interfaces.addr_proxied)?,
+        bind_sockets(&config.server.interfaces.addr_lmtp)?,
+        config
+            .server
+            .interfaces
+            .listeners
+            .iter()
+            .map(|listener| Ok((socket_bind_anyhow(listener.addr)?, *listener)))
+            .collect::<anyhow::Result<Vec<_>>>()?,
     );
 
     if !args.no_daemon {
@@ -106,9 +269,7 @@ fn try_main() -> anyhow::Result<()> {
         // setuid(config.server.system.user.uid())?;
     }
 
-    if let Some(t) = args.env {
-        dotenv::from_path(t)?;
-    }
+    exporters.start(&config)?;
 
     start_runtime(config, sockets, args.timeout.map(|t| t.0))
 }
@@ -0,0 +1,96 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+ */
+
+use opentelemetry_otlp::WithExportConfig;
+use tracing_subscriber::{reload, Registry};
+use vsmtp_config::field::FieldServerLogsOtlp;
+
+type OtlpLayer =
+    Option<tracing_opentelemetry::OpenTelemetryLayer<Registry, opentelemetry_sdk::trace::Tracer>>;
+
+/// Export of the spans to an `OpenTelemetry` collector.
+///
+/// The layer is registered empty when the logs are initialized, and the exporter
+/// is plugged in by [`Otlp::start`], as its threads would not survive the daemonization.
+pub struct Otlp {
+    handle: reload::Handle<OtlpLayer, Registry>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl Otlp {
+    pub fn layer() -> (reload::Layer<OtlpLayer, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(None);
+        (
+            layer,
+            Self {
+                handle,
+                runtime: None,
+            },
+        )
+    }
+
+    pub fn start(&mut self, config: &FieldServerLogsOtlp) -> anyhow::Result<()> {
+        // NOTE: two `OpenTelemetry` layers cannot be registered on the same subscriber.
+        anyhow::ensure!(
+            !cfg!(feature = "telemetry"),
+            "The spans cannot be exported over OTLP when vSMTP is built with the `telemetry` feature"
+        );
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("otlp-exporter")
+            .enable_all()
+            .build()?;
+
+        // NOTE: the gRPC channel and the batch processor are spawned on the current runtime.
+        let tracer = {
+            let _guard = runtime.enter();
+            opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(&config.endpoint),
+                )
+                .with_trace_config(opentelemetry_sdk::trace::config().with_resource(
+                    opentelemetry_sdk::Resource::new([opentelemetry::KeyValue::new(
+                        "service.name",
+                        "vsmtp",
+                    )]),
+                ))
+                .install_batch(opentelemetry_sdk::runtime::Tokio)?
+        };
+
+        self.handle
+            .reload(Some(tracing_opentelemetry::layer().with_tracer(tracer)))?;
+        self.runtime = Some(runtime);
+
+        tracing::info!(endpoint = config.endpoint, "Exporting the spans over OTLP.");
+
+        Ok(())
+    }
+}
+
+impl Drop for Otlp {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            // NOTE: dropping the provider flushes the spans of the last transactions.
+            opentelemetry::global::shutdown_tracer_provider();
+            runtime.shutdown_timeout(std::time::Duration::from_secs(5));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+ */
+
+use tracing_rfc_5424::{
+    rfc5424::Rfc5424,
+    transport::{self, TcpTransport, UdpTransport, UnixSocket},
+};
+use vsmtp_config::field::{LogFormat, SyslogSocket};
+
+type Connection = Box<dyn transport::Transport<Rfc5424, Error = transport::Error> + Send>;
+
+const MIN_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+const MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Transport to the syslog daemon, reconnected when the socket cannot be reached.
+///
+/// The records are also written in the log files, so an unreachable daemon
+/// is reported once in the `fallback` writer instead of failing the startup or every write.
+pub struct Transport {
+    socket: SyslogSocket,
+    format: LogFormat,
+    state: std::sync::Mutex<State>,
+}
+
+struct State {
+    connection: Option<Connection>,
+    // NOTE: the events emitted while a layer handles an event are discarded by `tracing`,
+    //       the notices of the transport are written directly.
+    fallback: Box<dyn std::io::Write + Send>,
+    down: bool,
+    next_attempt: std::time::Instant,
+    backoff: std::time::Duration,
+    dropped: usize,
+}
+
+impl Transport {
+    /// The socket is opened when the first record is sent.
+    pub fn new(
+        socket: SyslogSocket,
+        format: LogFormat,
+        fallback: impl std::io::Write + Send + 'static,
+    ) -> Self {
+        Self {
+            socket,
+            format,
+            state: std::sync::Mutex::new(State {
+                connection: None,
+                fallback: Box::new(fallback),
+                down: false,
+                next_attempt: std::time::Instant::now(),
+                backoff: MIN_BACKOFF,
+                dropped: 0,
+            }),
+        }
+    }
+
+    fn connect(&self) -> transport::Result<Connection> {
+        Ok(match &self.socket {
+            SyslogSocket::Udp { server } => Box::new(UdpTransport::new(server)?),
+            SyslogSocket::Tcp { server } => Box::new(TcpTransport::new(server)?),
+            SyslogSocket::Unix { path } => Box::new(UnixSocket::new(path)?),
+        })
+    }
+
+    fn disconnected(&self, state: &mut State, error: &transport::Error) {
+        if !state.down {
+            state.down = true;
+            self.report(
+                state,
+                tracing::Level::WARN,
+                &format!(
+                    "Cannot send the logs to syslog at {:?}, retrying in the background: {error}",
+                    self.socket
+                ),
+            );
+        }
+        state.dropped += 1;
+        state.next_attempt = std::time::Instant::now() + state.backoff;
+        state.backoff = std::cmp::min(state.backoff * 2, MAX_BACKOFF);
+    }
+
+    fn reconnected(&self, state: &mut State, connection: Connection) {
+        state.connection = Some(connection);
+        state.backoff = MIN_BACKOFF;
+        if std::mem::take(&mut state.down) {
+            let dropped = std::mem::take(&mut state.dropped);
+            self.report(
+                state,
+                tracing::Level::INFO,
+                &format!("Connection to syslog restored, {dropped} records were not sent."),
+            );
+        }
+    }
+
+    fn report(&self, state: &mut State, level: tracing::Level, message: &str) {
+        let timestamp = humantime::format_rfc3339_micros(std::time::SystemTime::now());
+        let record = match self.format {
+            LogFormat::Text => format!("{timestamp} {level:>5} {message}\n"),
+            LogFormat::Json => format!(
+                "{}\n",
+                serde_json::json!({
+                    "timestamp": timestamp.to_string(),
+                    "level": level.as_str(),
+                    "fields": { "message": message },
+                    "target": module_path!(),
+                })
+            ),
+        };
+        // NOTE: nowhere left to report the error.
+        let _ = state.fallback.write_all(record.as_bytes());
+    }
+}
+
+impl transport::Transport<Rfc5424> for Transport {
+    // NOTE: an error would be logged by the layer for each record.
+    type Error = std::convert::Infallible;
+
+    fn send(&self, buf: Vec<u8>) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(connection) = &state.connection {
+            if connection.send(buf.clone()).is_ok() {
+                return Ok(());
+            }
+            // NOTE: the daemon may have been restarted, the socket is reopened right away.
+            state.connection = None;
+            state.next_attempt = std::time::Instant::now();
+        }
+
+        if std::time::Instant::now() < state.next_attempt {
+            state.dropped += 1;
+            return Ok(());
+        }
+
+        match self
+            .connect()
+            .and_then(|connection| connection.send(buf).map(|()| connection))
+        {
+            Ok(connection) => self.reconnected(&mut state, connection),
+            Err(error) => self.disconnected(&mut state, &error),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transport, MIN_BACKOFF};
+    use tracing_subscriber::layer::SubscriberExt;
+    use vsmtp_config::field::{LogFormat, SyslogSocket};
+
+    #[derive(Clone, Default)]
+    struct Output(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn lines(&self, pattern: &str) -> usize {
+            String::from_utf8_lossy(&self.0.lock().unwrap())
+                .lines()
+                .filter(|line| line.contains(pattern))
+                .count()
+        }
+    }
+
+    #[test]
+    fn unix_socket_unreachable() {
+        let path = std::env::temp_dir().join("vsmtp_syslog_unix_socket_unreachable.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let output = Output::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer({
+                        let output = output.clone();
+                        move || output.clone()
+                    }),
+            )
+            .with(tracing_rfc_5424::layer::Layer::with_transport(
+                Transport::new(
+                    SyslogSocket::Unix { path: path.clone() },
+                    LogFormat::Text,
+                    output.clone(),
+                ),
+            ));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                tracing::error!("record while syslog is down");
+            }
+            assert_eq!(output.lines("record while syslog is down"), 3);
+            assert_eq!(output.lines("Cannot send the logs to syslog"), 1);
+
+            let daemon = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+            daemon
+                .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+                .unwrap();
+            std::thread::sleep(MIN_BACKOFF);
+
+            tracing::error!("record after restart");
+            let mut buffer = [0; 1024];
+            let size = daemon.recv(&mut buffer).unwrap();
+            assert!(String::from_utf8_lossy(&buffer[..size]).contains("record after restart"));
+            assert_eq!(output.lines("restored, 3 records were not sent"), 1);
+            assert_eq!(output.lines("Cannot send the logs to syslog"), 1);
+        });
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -176,7 +176,13 @@ impl Deliver {
             // .ok_or(TransferErrorsVariant::TlsNoCertificate {})?,
 
             SenderParameters::from(Target::Domain(domain.clone()))
-                .smtp_send(&ctx.connect.server_name, &envelop, message, None)
+                .smtp_send(
+                    &ctx.connect.server_name,
+                    &envelop,
+                    message,
+                    None,
+                    ctx.mail_from.require_tls,
+                )
                 .await
                 .map_err(|e| Variant::Delivery(vec![(Target::Domain(domain.clone()), e)]))?;
             return Ok(());
@@ -208,7 +214,13 @@ impl Deliver {
             // .ok_or(TransferErrorsVariant::TlsNoCertificate {})?,
 
             match SenderParameters::from(Target::Domain((*mx).clone()))
-                .smtp_send(&ctx.connect.server_name, &envelop, message, None)
+                .smtp_send(
+                    &ctx.connect.server_name,
+                    &envelop,
+                    message,
+                    None,
+                    ctx.mail_from.require_tls,
+                )
                 .await
             {
                 Ok(response) => {
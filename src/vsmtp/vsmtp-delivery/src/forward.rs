@@ -16,9 +16,12 @@
 */
 use crate::{send::SenderParameters, to_lettre_envelope};
 use vsmtp_common::{
-    transfer::{error::Variant, Status},
+    transfer::{
+        error::{Envelop, Variant},
+        Status,
+    },
     transport::{AbstractTransport, DeliverTo},
-    Address, ContextFinished,
+    Address, ContextFinished, Domain, Reply,
 };
 extern crate alloc;
 
@@ -64,6 +67,37 @@ impl Forward {
         }
     }
 
+    /// Send the message to the server right away, instead of delivering it once
+    /// the transaction is over, and return the reply of the server.
+    ///
+    /// A rejection of the server (4xx or 5xx) is returned as a reply.
+    ///
+    /// # Errors
+    ///
+    /// * there is no recipient
+    /// * the server could not be reached, or did not reply
+    #[inline]
+    pub async fn relay(
+        &self,
+        hello_name: &Domain,
+        from: &Option<Address>,
+        to: &[Address],
+        message: &[u8],
+        require_tls: bool,
+    ) -> Result<Reply, Variant> {
+        if to.is_empty() {
+            return Err(Envelop::NoRecipient.into());
+        }
+
+        tracing::debug!(?self.payload.params, "Relaying email.");
+
+        self.payload
+            .params
+            .smtp_relay(hello_name, from.as_ref(), to, message, require_tls)
+            .await
+            .map_err(|error| Variant::Delivery(vec![(self.payload.params.host.clone(), error)]))
+    }
+
     async fn deliver_inner(
         &self,
         ctx: &ContextFinished,
@@ -80,7 +114,13 @@ impl Forward {
 
         self.payload
             .params
-            .smtp_send(&ctx.connect.server_name, &envelop, message, None)
+            .smtp_send(
+                &ctx.connect.server_name,
+                &envelop,
+                message,
+                None,
+                ctx.mail_from.require_tls,
+            )
             .await
             .map_err(|e| Variant::Delivery(vec![(self.payload.params.host.clone(), e)]))
     }
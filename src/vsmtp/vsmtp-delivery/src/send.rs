@@ -16,15 +16,17 @@
 */
 use futures_util::FutureExt;
 use vsmtp_common::{
+    auth::Mechanism,
     transfer::{
         error::{Delivery, Queuer},
         Status,
     },
     transport::WrapperSerde,
-    ContextFinished, Domain, Target, SMTP_PORT, SUBMISSIONS_PORT, SUBMISSION_PORT,
+    Address, ContextFinished, Domain, Reply, Target, SMTP_PORT, SUBMISSIONS_PORT, SUBMISSION_PORT,
 };
 use vsmtp_config::Config;
 use vsmtp_mail_parser::MessageBody;
+use vsmtp_protocol::{rustls, Client, ConnectionKind};
 extern crate alloc;
 
 ///
@@ -179,6 +181,17 @@ pub enum TlsPolicy {
     Tunnel,
 }
 
+impl TlsPolicy {
+    /// Upgrade the policy so that the message is never relayed in clear,
+    /// if the sender used the `REQUIRETLS` option (rfc 8689).
+    pub(crate) const fn with_require_tls(self, require_tls: bool) -> Self {
+        match self {
+            Self::None | Self::StarttlsOpportunistic if require_tls => Self::StarttlsRequired,
+            otherwise => otherwise,
+        }
+    }
+}
+
 const SUPPORTED_TLS_POLICY: &[TlsPolicy; 4] = &[
     TlsPolicy::None,
     TlsPolicy::StarttlsOpportunistic,
@@ -378,6 +391,49 @@ impl From<Target> for SenderParameters {
     }
 }
 
+/// Stop the exchange with the server if it rejected the command, and return its reply.
+macro_rules! return_if_rejected {
+    ($client:expr, $reply:expr) => {{
+        let reply = $reply;
+        if reply.code().is_error() {
+            if let Err(error) = $client.quit().await {
+                tracing::debug!(%error, "Failed to quit the connection.");
+            }
+            return Ok(reply);
+        }
+    }};
+}
+
+fn connection_error(error: impl std::fmt::Display) -> Delivery {
+    Delivery::Connection {
+        with_source: Some(error.to_string()),
+    }
+}
+
+fn tls_error(error: impl std::fmt::Display) -> Delivery {
+    Delivery::Tls {
+        with_source: Some(error.to_string()),
+    }
+}
+
+fn webpki_client_config() -> alloc::sync::Arc<rustls::ClientConfig> {
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+
+    alloc::sync::Arc::new(
+        rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_store)
+            .with_no_client_auth(),
+    )
+}
+
 impl SenderParameters {
     #[allow(clippy::module_name_repetitions)]
     pub(crate) async fn smtp_send(
@@ -386,6 +442,7 @@ impl SenderParameters {
         envelop: &lettre::address::Envelope,
         message: &[u8],
         certificate: Option<Vec<rustls::Certificate>>,
+        require_tls: bool,
     ) -> Result<lettre::transport::smtp::response::Response, Delivery> {
         use lettre::transport::smtp::{
             client::{Certificate, Tls, TlsParameters},
@@ -400,8 +457,10 @@ impl SenderParameters {
             self.hello_name.as_ref().unwrap_or(hello_name).to_string(),
         ));
 
+        let tls = self.tls.with_require_tls(require_tls);
+
         if matches!(
-            &self.tls,
+            tls,
             TlsPolicy::StarttlsOpportunistic | TlsPolicy::StarttlsRequired | TlsPolicy::Tunnel
         ) {
             let mut tls_builder = TlsParameters::builder(self.host.to_string());
@@ -421,7 +480,7 @@ impl SenderParameters {
 
             let params = tls_builder.build()?;
 
-            builder = builder.tls(match self.tls {
+            builder = builder.tls(match tls {
                 TlsPolicy::StarttlsOpportunistic => Tls::Opportunistic(params),
                 TlsPolicy::StarttlsRequired => Tls::Required(params),
                 TlsPolicy::Tunnel => Tls::Wrapper(params),
@@ -440,6 +499,137 @@ impl SenderParameters {
             .await
             .map_err(Into::into)
     }
+
+    /// Run a transaction with the server using [`vsmtp_protocol::Client`], and return
+    /// the reply to the message, or the reply to the first command rejected by the server.
+    pub(crate) async fn smtp_relay(
+        &self,
+        hello_name: &Domain,
+        from: Option<&Address>,
+        to: &[Address],
+        message: &[u8],
+        require_tls: bool,
+    ) -> Result<Reply, Delivery> {
+        let (tcp_stream, server_name) = match &self.host {
+            Target::Domain(domain) => (
+                tokio::net::TcpStream::connect((domain.to_string(), self.port)).await?,
+                rustls::ServerName::try_from(domain.to_string().trim_end_matches('.'))
+                    .map_err(tls_error)?,
+            ),
+            Target::Ip(ip) => (
+                tokio::net::TcpStream::connect((*ip, self.port)).await?,
+                rustls::ServerName::IpAddress(*ip),
+            ),
+            Target::Socket(socket) => (
+                tokio::net::TcpStream::connect(socket).await?,
+                rustls::ServerName::IpAddress(socket.ip()),
+            ),
+        };
+        let hello_name = self.hello_name.as_ref().unwrap_or(hello_name).to_string();
+        let mut client = Client::new(tcp_stream, ConnectionKind::Relay);
+
+        match self.tls.with_require_tls(require_tls) {
+            TlsPolicy::Tunnel => {
+                let mut client = client
+                    .upgrade_tls(webpki_client_config(), server_name)
+                    .await
+                    .map_err(tls_error)?;
+                return_if_rejected!(
+                    client,
+                    client.read_greeting().await.map_err(connection_error)?
+                );
+                return_if_rejected!(
+                    client,
+                    client.ehlo(&hello_name).await.map_err(connection_error)?
+                );
+                self.smtp_transaction(client, from, to, message).await
+            }
+            tls => {
+                return_if_rejected!(
+                    client,
+                    client.read_greeting().await.map_err(connection_error)?
+                );
+                return_if_rejected!(
+                    client,
+                    client.ehlo(&hello_name).await.map_err(connection_error)?
+                );
+
+                if tls != TlsPolicy::None && client.has_extension("STARTTLS") {
+                    let mut client = client
+                        .starttls(webpki_client_config(), server_name)
+                        .await
+                        .map_err(tls_error)?;
+                    return_if_rejected!(
+                        client,
+                        client.ehlo(&hello_name).await.map_err(connection_error)?
+                    );
+                    self.smtp_transaction(client, from, to, message).await
+                } else if tls == TlsPolicy::StarttlsRequired {
+                    Err(tls_error("the server does not support STARTTLS"))
+                } else {
+                    self.smtp_transaction(client, from, to, message).await
+                }
+            }
+        }
+    }
+
+    async fn smtp_transaction<W, R>(
+        &self,
+        mut client: Client<W, R>,
+        from: Option<&Address>,
+        to: &[Address],
+        message: &[u8],
+    ) -> Result<Reply, Delivery>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+        R: tokio::io::AsyncRead + Unpin + Send,
+    {
+        if let Some((authid, authpass)) = &self.credentials {
+            let mechanism = client
+                .auth_mechanisms()
+                .into_iter()
+                .find(|i| matches!(i, Mechanism::Plain | Mechanism::Login))
+                .ok_or_else(|| Delivery::Client {
+                    with_source: Some("no supported authentication mechanism".to_owned()),
+                })?;
+            return_if_rejected!(
+                client,
+                client
+                    .auth(mechanism, authid, authpass)
+                    .await
+                    .map_err(connection_error)?
+            );
+        }
+
+        return_if_rejected!(
+            client,
+            client
+                .mail_from(from, Some(message.len()))
+                .await
+                .map_err(connection_error)?
+        );
+        for forward_path in to {
+            return_if_rejected!(
+                client,
+                client
+                    .rcpt_to(forward_path)
+                    .await
+                    .map_err(connection_error)?
+            );
+        }
+
+        let reply = client
+            .data(message)
+            .await
+            .map_err(connection_error)?
+            .pop()
+            .ok_or(Delivery::ReplyParsing { with_source: None })?;
+
+        if let Err(error) = client.quit().await {
+            tracing::debug!(%error, "Failed to quit the connection.");
+        }
+        Ok(reply)
+    }
 }
 
 #[cfg(test)]
@@ -500,4 +690,23 @@ mod tests {
             }
         }
     }
+
+    #[rstest::rstest]
+    #[case(TlsPolicy::None, false, TlsPolicy::None)]
+    #[case(TlsPolicy::None, true, TlsPolicy::StarttlsRequired)]
+    #[case(
+        TlsPolicy::StarttlsOpportunistic,
+        false,
+        TlsPolicy::StarttlsOpportunistic
+    )]
+    #[case(TlsPolicy::StarttlsOpportunistic, true, TlsPolicy::StarttlsRequired)]
+    #[case(TlsPolicy::StarttlsRequired, true, TlsPolicy::StarttlsRequired)]
+    #[case(TlsPolicy::Tunnel, true, TlsPolicy::Tunnel)]
+    fn policy_with_require_tls(
+        #[case] policy: TlsPolicy,
+        #[case] require_tls: bool,
+        #[case] expected: TlsPolicy,
+    ) {
+        assert_eq!(policy.with_require_tls(require_tls), expected);
+    }
 }
@@ -57,6 +57,8 @@ impl MailMimeParser {
                     // FIXME: should header content be traced ?
                     tracing::trace!("new mime header found: '{name}' => '{value}'",);
                     mime_headers.push(get_mime_header(&name, &value));
+                    // NOTE: kept in place for a message without `MIME-Version`.
+                    headers.0.push((name, value));
                 }
 
                 Some((name, value)) => {
@@ -81,18 +83,14 @@ impl MailMimeParser {
                     let has_mime_version = headers.0.iter().any(|(name, _)| name == "mime-version");
                     tracing::trace!("mime-version header found?: {has_mime_version}",);
 
-                    return Ok(Mail {
-                        headers,
-                        body: if has_mime_version {
-                            BodyType::Mime(Box::new(self.as_mime_body(
-                                content,
-                                mime_headers,
-                                None,
-                            )?))
-                        } else {
-                            BodyType::Regular(self.as_regular_body(content)?)
-                        },
-                    });
+                    let body = if has_mime_version {
+                        headers.0.retain(|(name, _)| !is_mime_header(name));
+                        BodyType::Mime(Box::new(self.as_mime_body(content, mime_headers, None)?))
+                    } else {
+                        BodyType::Regular(self.as_regular_body(content)?)
+                    };
+
+                    return Ok(Mail { headers, body });
                 }
             };
 
@@ -42,13 +42,18 @@ pub use implementation::{
 };
 
 mod message {
+    pub mod address;
+    pub mod encoded_word;
     pub mod mail;
     #[allow(clippy::module_name_repetitions)]
     pub mod message_body;
     pub mod mime_type;
     pub mod raw_body;
+    mod transfer_encoding;
 }
 
+pub use message::address::*;
+pub use message::encoded_word::*;
 pub use message::mail::*;
 pub use message::message_body::*;
 pub use message::mime_type::*;
@@ -0,0 +1,300 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+/// A mailbox found in an address header (`From`, `To`, `Cc` ...).
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc5322#section-3.4>
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Mailbox {
+    /// The display name, unquoted, or the comment following a bare address.
+    pub display_name: Option<String>,
+    /// The address, without angle brackets nor comments.
+    pub address: String,
+}
+
+/// Parse the value of an address header into a list of mailboxes.
+///
+/// Groups are flattened (the group name is dropped), quoted strings and
+/// comments are handled. Invalid entries are skipped.
+#[must_use]
+pub fn parse_address_list(input: &str) -> Vec<Mailbox> {
+    split_mailboxes(input)
+        .iter()
+        .filter_map(|mailbox| parse_mailbox(mailbox))
+        .collect()
+}
+
+/// Split an address list on top level `,`, removing the group syntax.
+fn split_mailboxes(input: &str) -> Vec<String> {
+    let mut mailboxes = vec![];
+    let mut current = String::new();
+    let (mut in_quote, mut in_angle, mut is_escaped) = (false, false, false);
+    let mut comment_depth = 0_usize;
+
+    for c in input.chars() {
+        if is_escaped {
+            is_escaped = false;
+            current.push(c);
+            continue;
+        }
+
+        match c {
+            '\\' if in_quote || comment_depth > 0 => is_escaped = true,
+            '"' if comment_depth == 0 => in_quote = !in_quote,
+            '(' if !in_quote => comment_depth += 1,
+            ')' if !in_quote && comment_depth > 0 => comment_depth -= 1,
+            '<' if !in_quote && comment_depth == 0 => in_angle = true,
+            '>' if !in_quote && comment_depth == 0 => in_angle = false,
+            _ if in_quote || in_angle || comment_depth > 0 => {}
+            ',' | ';' => {
+                mailboxes.push(std::mem::take(&mut current));
+                continue;
+            }
+            // start of a group, the display name of the group is dropped.
+            ':' => {
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(c);
+    }
+
+    mailboxes.push(current);
+    mailboxes
+}
+
+/// Remove the comments of `input`, returning the remaining text and the
+/// content of the last comment.
+fn strip_comments(input: &str) -> (String, Option<String>) {
+    let mut output = String::with_capacity(input.len());
+    let mut comment = String::new();
+    let mut last_comment = None;
+    let (mut in_quote, mut is_escaped) = (false, false);
+    let mut depth = 0_usize;
+
+    for c in input.chars() {
+        if is_escaped {
+            is_escaped = false;
+            if depth > 0 {
+                comment.push(c);
+            } else {
+                output.push(c);
+            }
+            continue;
+        }
+
+        match c {
+            '\\' if in_quote || depth > 0 => {
+                is_escaped = true;
+                if depth > 0 {
+                    continue;
+                }
+            }
+            '"' if depth == 0 => in_quote = !in_quote,
+            '(' if !in_quote => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            ')' if !in_quote && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    last_comment = Some(std::mem::take(&mut comment));
+                    // a comment is equivalent to a whitespace.
+                    output.push(' ');
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        if depth > 0 {
+            comment.push(c);
+        } else {
+            output.push(c);
+        }
+    }
+
+    (output, last_comment)
+}
+
+/// Unquote the quoted strings of a phrase and collapse the whitespaces.
+fn unquote_phrase(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let (mut in_quote, mut is_escaped) = (false, false);
+
+    for c in input.chars() {
+        match c {
+            _ if is_escaped => {
+                is_escaped = false;
+                output.push(c);
+            }
+            '\\' if in_quote => is_escaped = true,
+            '"' => in_quote = !in_quote,
+            c if c.is_whitespace() && !in_quote => {
+                if !output.ends_with(' ') {
+                    output.push(' ');
+                }
+            }
+            '\r' | '\n' => {}
+            _ => output.push(c),
+        }
+    }
+
+    output.trim().to_string()
+}
+
+/// Remove the unquoted whitespaces of an address.
+fn clean_address(input: &str) -> String {
+    let mut in_quote = false;
+    let mut is_escaped = false;
+
+    input
+        .chars()
+        .filter(|c| {
+            let keep = in_quote || !c.is_whitespace();
+            if is_escaped {
+                is_escaped = false;
+            } else if *c == '\\' && in_quote {
+                is_escaped = true;
+            } else if *c == '"' {
+                in_quote = !in_quote;
+            }
+            keep
+        })
+        .collect()
+}
+
+fn parse_mailbox(input: &str) -> Option<Mailbox> {
+    let (input, comment) = strip_comments(input);
+
+    let mut in_quote = false;
+    let mut is_escaped = false;
+    let angle = input.char_indices().find(|(_, c)| {
+        let found = !in_quote && *c == '<';
+        if is_escaped {
+            is_escaped = false;
+        } else if *c == '\\' && in_quote {
+            is_escaped = true;
+        } else if *c == '"' {
+            in_quote = !in_quote;
+        }
+        found
+    });
+
+    let (display_name, address) = match angle {
+        Some((start, _)) => {
+            let end = input[start..]
+                .find('>')
+                .map_or(input.len(), |end| start + end);
+            let address = &input[start + 1..end];
+            // remove the obsolete source route, see <https://datatracker.ietf.org/doc/html/rfc5322#section-4.4>
+            let address = address.rsplit_once(':').map_or(address, |(_, addr)| addr);
+
+            (unquote_phrase(&input[..start]), clean_address(address))
+        }
+        None => (
+            comment.map(|c| unquote_phrase(&c)).unwrap_or_default(),
+            clean_address(&input),
+        ),
+    };
+
+    if address.is_empty() {
+        return None;
+    }
+
+    Some(Mailbox {
+        display_name: (!display_name.is_empty()).then_some(display_name),
+        address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mailbox(display_name: Option<&str>, address: &str) -> Mailbox {
+        Mailbox {
+            display_name: display_name.map(str::to_string),
+            address: address.to_string(),
+        }
+    }
+
+    #[test]
+    fn bare_address() {
+        assert_eq!(
+            parse_address_list(" john.doe@example.com"),
+            vec![mailbox(None, "john.doe@example.com")]
+        );
+    }
+
+    #[test]
+    fn display_names() {
+        assert_eq!(
+            parse_address_list(
+                r#" John Doe <john.doe@example.com>, "Doe, Jane \"JD\"" <jane.doe@example.com>"#
+            ),
+            vec![
+                mailbox(Some("John Doe"), "john.doe@example.com"),
+                mailbox(Some(r#"Doe, Jane "JD""#), "jane.doe@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_local_part_with_comma() {
+        assert_eq!(
+            parse_address_list(r#" "doe, john"@example.com, <jane@example.com>"#),
+            vec![
+                mailbox(None, r#""doe, john"@example.com"#),
+                mailbox(None, "jane@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn group() {
+        assert_eq!(
+            parse_address_list(
+                " A Group:Ed Jones <c@a.test>,joe@where.test,John <jdoe@one.test>;, mary@x.test"
+            ),
+            vec![
+                mailbox(Some("Ed Jones"), "c@a.test"),
+                mailbox(None, "joe@where.test"),
+                mailbox(Some("John"), "jdoe@one.test"),
+                mailbox(None, "mary@x.test"),
+            ]
+        );
+        assert_eq!(parse_address_list(" undisclosed-recipients:;"), vec![]);
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            parse_address_list(
+                " Pete(A nice \\) chap) <pete(his account)@silly.test(his host)>,\r\n john@example.com (John (the) Doe)"
+            ),
+            vec![
+                mailbox(Some("Pete"), "pete@silly.test"),
+                mailbox(Some("John (the) Doe"), "john@example.com"),
+            ]
+        );
+    }
+}
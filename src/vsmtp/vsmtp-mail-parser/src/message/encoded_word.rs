@@ -0,0 +1,223 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use base64::Engine;
+
+/// Some encoders omit the padding of the `B` encoding.
+pub(super) const BASE64: base64::engine::GeneralPurpose = base64::engine::GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    base64::engine::GeneralPurposeConfig::new()
+        .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+);
+
+/// Decode the encoded-words (`=?charset?encoding?text?=`) of a header value.
+///
+/// Both the `B` and `Q` encodings are supported, with the `utf-8`, `us-ascii`,
+/// `iso-8859-1` and `windows-1252` charsets. The whitespace between two adjacent
+/// encoded-words is removed, and the text outside of encoded-words is left untouched.
+///
+/// Return `None` if an encoded-word could not be decoded.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc2047>
+#[must_use]
+pub fn decode_encoded_words(input: &str) -> Option<String> {
+    let mut output = String::with_capacity(input.len());
+    // bytes of the previous adjacent encoded-words, decoded together so that
+    // a character split across two encoded-words is kept whole.
+    let mut pending: Option<(String, Vec<u8>)> = None;
+    let mut whitespace = String::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        if let Some((charset, bytes, len)) = parse_encoded_word(rest) {
+            let bytes = bytes?;
+            match &mut pending {
+                Some((pending_charset, pending_bytes))
+                    if pending_charset.eq_ignore_ascii_case(&charset) =>
+                {
+                    pending_bytes.extend(bytes);
+                }
+                Some(_) => {
+                    let (charset, bytes) = pending.replace((charset, bytes))?;
+                    output.push_str(&decode_charset(&charset, &bytes)?);
+                }
+                None => {
+                    output.push_str(&std::mem::take(&mut whitespace));
+                    pending = Some((charset, bytes));
+                }
+            }
+            whitespace.clear();
+            rest = &rest[len..];
+            continue;
+        }
+
+        let c = rest.chars().next()?;
+        rest = &rest[c.len_utf8()..];
+
+        if matches!(c, ' ' | '\t' | '\r' | '\n') {
+            whitespace.push(c);
+            continue;
+        }
+
+        if let Some((charset, bytes)) = pending.take() {
+            output.push_str(&decode_charset(&charset, &bytes)?);
+        }
+        output.push_str(&std::mem::take(&mut whitespace));
+        output.push(c);
+    }
+
+    if let Some((charset, bytes)) = pending {
+        output.push_str(&decode_charset(&charset, &bytes)?);
+    }
+    output.push_str(&whitespace);
+
+    Some(output)
+}
+
+/// Parse the encoded-word at the start of `input`, returning its charset,
+/// its decoded bytes (`None` if the text is invalid) and its length.
+fn parse_encoded_word(input: &str) -> Option<(String, Option<Vec<u8>>, usize)> {
+    let word = input.strip_prefix("=?")?;
+    let word = &word[..word
+        .find(|c: char| c.is_ascii_whitespace())
+        .unwrap_or(word.len())];
+
+    let (charset, word) = word.split_once('?')?;
+    let (encoding, word) = word.split_once('?')?;
+    let end = word.find("?=")?;
+    let text = &word[..end];
+
+    if charset.is_empty() || text.contains('?') {
+        return None;
+    }
+
+    let bytes = match encoding {
+        "B" | "b" => BASE64.decode(text).ok(),
+        "Q" | "q" => decode_q(text),
+        _ => return None,
+    };
+    let len =
+        "=?".len() + charset.len() + "?".len() + encoding.len() + "?".len() + end + "?=".len();
+    // RFC 2231 language suffix (`utf-8*en`).
+    let charset = charset
+        .split_once('*')
+        .map_or(charset, |(charset, _)| charset);
+
+    Some((charset.to_string(), bytes, len))
+}
+
+/// Decode the `Q` encoding, a variant of quoted-printable where `_` is a space.
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'_' => output.push(b' '),
+            b'=' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                output.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            _ => output.push(byte),
+        }
+    }
+
+    Some(output)
+}
+
+/// Code points of the `0x80..=0x9F` range of `windows-1252`, the rest being identical to `iso-8859-1`.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+pub(super) fn decode_charset(charset: &str, bytes: &[u8]) -> Option<String> {
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => String::from_utf8(bytes.to_vec()).ok(),
+        "us-ascii" | "ascii" => bytes
+            .is_ascii()
+            .then(|| bytes.iter().copied().map(char::from).collect()),
+        "iso-8859-1" | "iso_8859-1" | "latin1" | "l1" => {
+            Some(bytes.iter().copied().map(char::from).collect())
+        }
+        "windows-1252" | "cp1252" => Some(
+            bytes
+                .iter()
+                .map(|byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252[usize::from(byte - 0x80)],
+                    _ => char::from(*byte),
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unencoded() {
+        assert_eq!(
+            decode_encoded_words("Unit test are cool =? not a word ?=").as_deref(),
+            Some("Unit test are cool =? not a word ?=")
+        );
+    }
+
+    #[test]
+    fn base64_utf8() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?B?SGVsbG8gd8O2cmxkIQ==?=").as_deref(),
+            Some("Hello wörld!")
+        );
+    }
+
+    #[test]
+    fn quoted_printable_latin1() {
+        assert_eq!(
+            decode_encoded_words("=?ISO-8859-1?Q?Caf=E9_cr=E8me?= au lait").as_deref(),
+            Some("Café crème au lait")
+        );
+    }
+
+    #[test]
+    fn adjacent_words() {
+        // "é" is split across the two encoded-words.
+        assert_eq!(
+            decode_encoded_words(
+                "Re: =?utf-8?q?caf=C3?=\r\n =?utf-8?b?qSE=?= (=?windows-1252?q?=80?=)"
+            )
+            .as_deref(),
+            Some("Re: café! (€)")
+        );
+        assert_eq!(
+            decode_encoded_words("=?us-ascii?q?a?= =?iso-8859-1?q?b?=  c").as_deref(),
+            Some("ab  c")
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode_encoded_words("=?utf-8?q?caf=C3?="), None);
+        assert_eq!(decode_encoded_words("=?unknown?q?foo?="), None);
+        assert_eq!(decode_encoded_words("=?utf-8?b?#invalid?="), None);
+        assert_eq!(decode_encoded_words("=?us-ascii?q?=E9?="), None);
+    }
+}
@@ -15,7 +15,7 @@
  *
 */
 
-use super::mime_type::Mime;
+use super::mime_type::{Mime, MimeBodyType, MimePart};
 
 /// we use Vec instead of a `HashMap` because header ordering is important.
 #[allow(clippy::module_name_repetitions)]
@@ -78,6 +78,47 @@ pub struct Mail {
     pub body: BodyType,
 }
 
+/// Maximum length of a header line recommended by rfc 5322, without the CRLF.
+pub const HEADER_LINE_LENGTH: usize = 78;
+
+/// Fold the value of the header `name` so that the lines of the header are at most
+/// [`HEADER_LINE_LENGTH`] characters long.
+///
+/// A CRLF is inserted before the whitespace closest to the limit, so the unfolded
+/// value is unchanged. A word longer than a line (like a base64 signature) is kept whole.
+#[must_use]
+pub fn fold_header(name: &str, value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + value.len() / HEADER_LINE_LENGTH * 2);
+    let mut line_length = name.len() + ": ".len();
+
+    let mut word_start = 0;
+    let boundaries = value
+        .match_indices([' ', '\t'])
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(value.len()));
+
+    for word_end in boundaries {
+        let word = &value[word_start..word_end];
+        if word.is_empty() {
+            continue;
+        }
+        if line_length + word.len() > HEADER_LINE_LENGTH
+            && !output.is_empty()
+            && crate::helpers::start_with_fws(word)
+        {
+            output.push_str("\r\n");
+            line_length = 0;
+        }
+        output.push_str(word);
+        line_length = word
+            .rfind('\n')
+            .map_or(line_length + word.len(), |idx| word.len() - idx - 1);
+        word_start = word_end;
+    }
+
+    output
+}
+
 #[derive(Debug)]
 struct HeaderFoldable<'a>(&'a str, &'a str);
 
@@ -225,6 +266,77 @@ impl Mail {
         }
     }
 
+    /// Is the body of the message a `multipart/*` content.
+    #[must_use]
+    pub fn is_multipart(&self) -> bool {
+        matches!(
+            &self.body,
+            BodyType::Mime(mime) if matches!(mime.content, MimeBodyType::Multipart(_))
+        )
+    }
+
+    /// Enumerate the leaf parts of the message, nested multiparts included.
+    ///
+    /// A message that is not multipart is a single part.
+    #[must_use]
+    pub fn mime_parts(&self) -> Vec<MimePart> {
+        match &self.body {
+            BodyType::Regular(content) => vec![MimePart {
+                content_type: "text/plain".to_string(),
+                filename: None,
+                size: content.iter().map(|line| line.len() + 2).sum(),
+                content_transfer_encoding: "7bit".to_string(),
+                content_disposition: None,
+            }],
+            BodyType::Mime(mime) => {
+                let mut parts = vec![];
+                mime.collect_parts(None, &mut parts);
+                parts
+            }
+            BodyType::Undefined => vec![],
+        }
+    }
+
+    /// Decode the content of the text parts of the message (`text/*` parts that are
+    /// not attachments), from their content transfer encoding and their charset.
+    ///
+    /// A message that is not multipart is a single text part, decoded from the
+    /// `Content-Transfer-Encoding` and `Content-Type` headers of the message.
+    #[must_use]
+    pub fn text_parts(&self) -> Vec<String> {
+        match &self.body {
+            BodyType::Regular(content) => vec![super::transfer_encoding::decode_content(
+                content,
+                self.get_header("Content-Transfer-Encoding")
+                    .map_or("7bit", str::trim),
+                self.get_header("Content-Type")
+                    .map(|value| crate::get_mime_header("Content-Type", value))
+                    .as_ref()
+                    .and_then(|header| header.args.get("charset"))
+                    .map(String::as_str),
+            )],
+            BodyType::Mime(mime) => {
+                let mut texts = vec![];
+                mime.collect_texts(None, &mut texts);
+                texts
+            }
+            BodyType::Undefined => vec![],
+        }
+    }
+
+    /// Replace the parts of the message whose filename has one of the `extensions`
+    /// (case insensitive, with or without the leading dot) by a text part noting the removal.
+    /// The rest of the MIME structure is left untouched.
+    ///
+    /// Return the filenames of the parts removed.
+    pub fn strip_attachments(&mut self, extensions: &[String]) -> Vec<String> {
+        let mut removed = vec![];
+        if let BodyType::Mime(mime) = &mut self.body {
+            mime.strip_attachments(None, extensions, &mut removed);
+        }
+        removed
+    }
+
     /// get the value of an header, return None if it does not exists.
     #[must_use]
     pub fn get_header(&self, name: &str) -> Option<&str> {
@@ -268,6 +380,37 @@ impl Mail {
         self.headers.0.extend(headers);
     }
 
+    /// insert new headers before the first header named `anchor`,
+    /// or prepend them if `anchor` does not exists.
+    pub fn insert_headers_before(
+        &mut self,
+        anchor: &str,
+        headers: impl IntoIterator<Item = (String, String)>,
+    ) {
+        let index = self.find_header(anchor).unwrap_or(0);
+        self.headers.0.splice(index..index, headers);
+    }
+
+    /// insert new headers after the first header named `anchor`,
+    /// or push them if `anchor` does not exists.
+    pub fn insert_headers_after(
+        &mut self,
+        anchor: &str,
+        headers: impl IntoIterator<Item = (String, String)>,
+    ) {
+        let index = self
+            .find_header(anchor)
+            .map_or(self.headers.0.len(), |index| index + 1);
+        self.headers.0.splice(index..index, headers);
+    }
+
+    fn find_header(&self, name: &str) -> Option<usize> {
+        self.headers
+            .0
+            .iter()
+            .position(|(header, _)| header.eq_ignore_ascii_case(name))
+    }
+
     /// Remove a header from the list.
     pub fn remove_header(&mut self, name: &str) -> bool {
         if let Some(index) = self
@@ -282,6 +425,25 @@ impl Mail {
             false
         }
     }
+
+    /// remove every header named `name`, returning the number of headers removed.
+    pub fn remove_all_headers(&mut self, name: &str) -> usize {
+        let len = self.headers.0.len();
+        self.headers
+            .0
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        len - self.headers.0.len()
+    }
+
+    /// Remove the occurrences of a header for which `predicate` returns `true`
+    /// when called with the value of the header, returning the number of headers removed.
+    pub fn remove_headers_if(&mut self, name: &str, predicate: impl Fn(&str) -> bool) -> usize {
+        let len = self.headers.0.len();
+        self.headers
+            .0
+            .retain(|(header, value)| !(header.eq_ignore_ascii_case(name) && predicate(value)));
+        len - self.headers.0.len()
+    }
 }
 
 #[cfg(test)]
@@ -341,6 +503,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_fold_header() {
+        let mut value = (0..50)
+            .map(|i| format!("w{i:03}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        value.truncate(200);
+
+        let folded = fold_header("Authentication-Results", &value);
+        let header = format!("Authentication-Results: {folded}");
+
+        assert!(header.lines().count() > 1);
+        for line in header.split("\r\n") {
+            assert!(line.len() <= HEADER_LINE_LENGTH, "{line:?}");
+        }
+        assert_eq!(folded.replace("\r\n", ""), value);
+    }
+
+    #[test]
+    fn test_fold_header_long_word() {
+        let signature = "a".repeat(200);
+        let value = format!("v=1; b={signature}");
+
+        assert_eq!(
+            fold_header("DKIM-Signature", &value),
+            format!("v=1;\r\n b={signature}")
+        );
+        assert_eq!(fold_header("Subject", "short"), "short");
+    }
+
     #[test]
     fn test_append_headers() {
         let mut mail = Mail {
@@ -388,6 +580,48 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_insert_headers() {
+        let mut mail = Mail {
+            headers: MailHeaders(vec![
+                ("Received".to_string(), "from b by c".to_string()),
+                ("Received".to_string(), "from a by b".to_string()),
+                ("Subject".to_string(), "testing an email".to_string()),
+            ]),
+            body: BodyType::Regular(vec!["email content".to_string()]),
+        };
+
+        mail.insert_headers_before(
+            "received",
+            [("Authentication-Results".to_string(), "c; none".to_string())],
+        );
+        mail.insert_headers_after(
+            "Received",
+            [("X-After".to_string(), "first hop".to_string())],
+        );
+        mail.insert_headers_before("X-Unknown", [("X-Top".to_string(), "top".to_string())]);
+        mail.insert_headers_after(
+            "X-Unknown",
+            [("X-Bottom".to_string(), "bottom".to_string())],
+        );
+
+        assert_eq!(
+            format!("{mail}"),
+            [
+                "X-Top: top\r\n",
+                "Authentication-Results: c; none\r\n",
+                "Received: from b by c\r\n",
+                "X-After: first hop\r\n",
+                "Received: from a by b\r\n",
+                "Subject: testing an email\r\n",
+                "X-Bottom: bottom\r\n",
+                "\r\n",
+                "email content\r\n"
+            ]
+            .concat()
+        );
+    }
+
     #[test]
     fn test_rcpt_mutation() {
         let mut mail = Mail::default();
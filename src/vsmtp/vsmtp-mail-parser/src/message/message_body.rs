@@ -15,7 +15,7 @@
  *
 */
 
-use crate::{implementation::basic_parser::BasicParser, Mail, MailParser, RawBody};
+use crate::{implementation::basic_parser::BasicParser, BodyType, Mail, MailParser, RawBody};
 
 // NOTE: should it be a tristate enum?
 // enum {
@@ -96,6 +96,23 @@ impl MessageBody {
             .map(str::to_string)
     }
 
+    /// get the value of an header with its RFC 2047 encoded-words decoded,
+    /// falling back to the raw value if they could not be decoded.
+    /// return None if it does not exists or when the body is empty.
+    #[must_use]
+    pub fn get_header_decoded(&self, name: &str) -> Option<String> {
+        self.get_header(name)
+            .map(|header| crate::decode_encoded_words(&header).unwrap_or(header))
+    }
+
+    /// Get the mailboxes of an address header (`From`, `To`, `Cc` ...),
+    /// return an empty list if the header does not exists.
+    #[must_use]
+    pub fn get_addresses(&self, name: &str) -> Vec<crate::Mailbox> {
+        self.get_header(name)
+            .map_or_else(Vec::new, |header| crate::parse_address_list(&header))
+    }
+
     /// Count the number of headers with the given name.
     #[must_use]
     pub fn count_header(&self, name: &str) -> usize {
@@ -104,7 +121,9 @@ impl MessageBody {
             .map_or_else(|| self.raw.count_header(name), |p| p.count_header(name))
     }
 
-    /// rewrite a header with a new value or add it to the header section.
+    /// Rewrite the value of the first header named `name` (case insensitive), or append
+    /// the header if it does not exist. The other occurrences of the header are left untouched,
+    /// see [`MessageBody::set_all_headers`].
     pub fn set_header(&mut self, name: &str, value: &str) {
         if let Some(parsed) = &mut self.parsed {
             parsed.set_header(name, &format!("{value}\r\n"));
@@ -113,6 +132,13 @@ impl MessageBody {
         self.raw.set_header(name, &format!("{value}\r\n"));
     }
 
+    /// Remove every occurrence of a header, and append it with the given value,
+    /// so that the message contains exactly one header named `name`.
+    pub fn set_all_headers(&mut self, name: &str, value: &str) {
+        self.remove_all_headers(name);
+        self.append_header(name, value);
+    }
+
     /// Rename a header.
     pub fn rename_header(&mut self, old: &str, new: &str) {
         if let Some(parsed) = &mut self.parsed {
@@ -145,6 +171,28 @@ impl MessageBody {
         self.raw.prepend_header([format!("{name}: {value}\r\n")]);
     }
 
+    /// insert a header before the first header named `anchor`,
+    /// or prepend it if `anchor` does not exists.
+    pub fn insert_header_before(&mut self, anchor: &str, name: &str, value: &str) {
+        if let Some(parsed) = &mut self.parsed {
+            parsed.insert_headers_before(anchor, [(name.to_string(), value.to_string())]);
+        }
+
+        self.raw
+            .insert_header_before(anchor, name, &format!("{value}\r\n"));
+    }
+
+    /// insert a header after the first header named `anchor`,
+    /// or append it if `anchor` does not exists.
+    pub fn insert_header_after(&mut self, anchor: &str, name: &str, value: &str) {
+        if let Some(parsed) = &mut self.parsed {
+            parsed.insert_headers_after(anchor, [(name.to_string(), value.to_string())]);
+        }
+
+        self.raw
+            .insert_header_after(anchor, name, &format!("{value}\r\n"));
+    }
+
     /// Remove a header from the list.
     pub fn remove_header(&mut self, name: &str) -> bool {
         if let Some(parsed) = &mut self.parsed {
@@ -155,6 +203,130 @@ impl MessageBody {
         self.raw.remove_header(name)
     }
 
+    /// Remove every occurrence of a header from the list,
+    /// returning the number of headers removed.
+    pub fn remove_all_headers(&mut self, name: &str) -> usize {
+        if let Some(parsed) = &mut self.parsed {
+            // NOTE: the result for a parsed email is ignored.
+            parsed.remove_all_headers(name);
+        }
+
+        self.raw.remove_all_headers(name)
+    }
+
+    /// Remove the occurrences of a header for which `predicate` returns `true`
+    /// when called with the value of the header, returning the number of headers removed.
+    pub fn remove_headers_if(&mut self, name: &str, predicate: impl Fn(&str) -> bool) -> usize {
+        if let Some(parsed) = &mut self.parsed {
+            // NOTE: the result for a parsed email is ignored.
+            parsed.remove_headers_if(name, &predicate);
+        }
+
+        self.raw.remove_headers_if(name, predicate)
+    }
+
+    /// Replace the body of the message, leaving the headers untouched.
+    /// Line endings are converted to CRLF.
+    ///
+    /// # Errors
+    ///
+    /// * the message was already parsed, and the new body could not be parsed,
+    ///   the message is left unchanged.
+    pub fn set_body(&mut self, body: &str) -> anyhow::Result<()> {
+        self.update_raw(|raw| raw.set_body(to_crlf(body)))
+    }
+
+    /// Append content at the end of the body of the message.
+    /// Line endings are converted to CRLF.
+    ///
+    /// # Errors
+    ///
+    /// * the message was already parsed, and the new body could not be parsed,
+    ///   the message is left unchanged.
+    pub fn append_body(&mut self, content: &str) -> anyhow::Result<()> {
+        self.update_raw(|raw| {
+            if raw
+                .body()
+                .as_ref()
+                .map_or(false, |body| !body.is_empty() && !body.ends_with("\r\n"))
+            {
+                raw.append_body("\r\n");
+            }
+            raw.append_body(&to_crlf(content));
+        })
+    }
+
+    /// Remove the attachments whose filename has one of the `extensions`, see [`Mail::strip_attachments`].
+    /// The body is rebuilt from the parsed message, the headers are left untouched unless the
+    /// whole message is the attachment removed.
+    ///
+    /// Return the filenames of the attachments removed.
+    ///
+    /// # Errors
+    ///
+    /// * the message could not be parsed, or parsed again once rebuilt,
+    ///   the message is left unchanged.
+    pub fn strip_attachments(&mut self, extensions: &[String]) -> anyhow::Result<Vec<String>> {
+        self.parsed::<crate::MailMimeParser>()?;
+        let previous = self.clone();
+
+        let (removed, body, mime_headers) = {
+            let mail = self.parsed::<crate::MailMimeParser>()?;
+            let BodyType::Mime(mime) = &mail.body else {
+                return Ok(vec![]);
+            };
+            let headers = mime.headers.clone();
+
+            let removed = mail.strip_attachments(extensions);
+            if removed.is_empty() {
+                return Ok(removed);
+            }
+
+            let BodyType::Mime(mime) = &mail.body else {
+                unreachable!("the body is still a MIME body");
+            };
+            // NOTE: the whole message was the attachment, its content headers are replaced.
+            let mime_headers = (mime.headers != headers).then(|| (headers, mime.headers.clone()));
+            (removed, mime.content_to_string(), mime_headers)
+        };
+
+        if let Some((old, new)) = mime_headers {
+            for header in &old {
+                self.raw.remove_all_headers(&header.name);
+            }
+            for header in new {
+                if let Some((name, value)) = header.to_string().split_once(": ") {
+                    self.raw.add_header(name, value);
+                }
+            }
+        }
+        self.raw.set_body(body);
+        if let Err(error) = self.parse::<crate::MailMimeParser>() {
+            *self = previous;
+            return Err(error);
+        }
+
+        Ok(removed)
+    }
+
+    /// Apply `update` to the raw part, and keep the parsed part in sync with it.
+    /// If the message was parsed and cannot be parsed once updated, both parts
+    /// are restored to their previous state.
+    fn update_raw(&mut self, update: impl FnOnce(&mut RawBody)) -> anyhow::Result<()> {
+        if self.parsed.is_none() {
+            update(&mut self.raw);
+            return Ok(());
+        }
+
+        let previous = self.raw.clone();
+        update(&mut self.raw);
+        if let Err(error) = self.parse::<crate::MailMimeParser>() {
+            self.raw = previous;
+            return Err(error);
+        }
+        Ok(())
+    }
+
     /// # Errors
     ///
     /// * the value produced by the [`MailParser`] was not a parsed [`Mail`]
@@ -179,3 +351,13 @@ impl MessageBody {
         self.parsed::<P>()
     }
 }
+
+/// Convert the line endings of `content` to CRLF, the last line included.
+fn to_crlf(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    for line in content.lines() {
+        output.push_str(line);
+        output.push_str("\r\n");
+    }
+    output
+}
@@ -15,6 +15,7 @@
  *
 */
 use super::mail::Mail;
+use std::fmt::Write;
 
 /// header of a mime section
 #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
@@ -59,7 +60,22 @@ impl std::fmt::Display for MimeHeader {
         f.write_str(&self.value)?;
 
         for (key, value) in &self.args {
-            f.write_fmt(format_args!("; {key}={value}"))?;
+            // NOTE: the quotes are removed by the parser, a value with special
+            // characters (like a boundary or a filename) must be quoted again.
+            if value.is_empty()
+                || value.contains(|c: char| {
+                    c.is_ascii_whitespace()
+                        || c.is_ascii_control()
+                        || "()<>@,;:\\\"/[]?=".contains(c)
+                })
+            {
+                f.write_fmt(format_args!(
+                    "; {key}=\"{}\"",
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                ))?;
+            } else {
+                f.write_fmt(format_args!("; {key}={value}"))?;
+            }
         }
 
         f.write_str("\r\n")?;
@@ -103,20 +119,134 @@ pub struct Mime {
     pub content: MimeBodyType,
 }
 
-impl std::fmt::Display for Mime {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for i in &self.headers {
-            write!(f, "{i}")?;
+/// A leaf part of a MIME message, see [`Mail::mime_parts`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct MimePart {
+    /// The content type of the part (`text/plain`, `application/pdf` ...), lowercase.
+    pub content_type: String,
+    /// The `filename` parameter of the `Content-Disposition` header, or the `name`
+    /// parameter of the `Content-Type` header, with its encoded-words decoded.
+    pub filename: Option<String>,
+    /// The size of the content, as transferred (before decoding).
+    pub size: usize,
+    /// The content transfer encoding of the part, `7bit` if not specified.
+    pub content_transfer_encoding: String,
+    /// The disposition of the part (`inline`, `attachment` ...), lowercase.
+    pub content_disposition: Option<String>,
+}
+
+impl MimePart {
+    /// Is the part an attachment, meaning it has an `attachment` disposition,
+    /// or a filename without being explicitly `inline`.
+    #[must_use]
+    pub fn is_attachment(&self) -> bool {
+        match self.content_disposition.as_deref() {
+            Some("attachment") => true,
+            Some("inline") => false,
+            _ => self.filename.is_some(),
         }
-        f.write_str("\r\n")?;
+    }
+}
+
+impl Mime {
+    /// push the leaf parts of this section in `parts`, nested multiparts included.
+    pub(crate) fn collect_parts(&self, parent: Option<&[MimeHeader]>, parts: &mut Vec<MimePart>) {
+        let size = match &self.content {
+            MimeBodyType::Multipart(multipart) => {
+                for part in &multipart.parts {
+                    part.collect_parts(Some(&self.headers), parts);
+                }
+                return;
+            }
+            MimeBodyType::Regular(content) => content.iter().map(|line| line.len() + 2).sum(),
+            MimeBodyType::Embedded(mail) => mail.to_string().len(),
+        };
+
+        parts.push(self.to_part(parent, size));
+    }
 
+    /// push the decoded content of the text leaf parts of this section in `texts`,
+    /// attachments excluded, nested multiparts and messages included.
+    pub(crate) fn collect_texts(&self, parent: Option<&[MimeHeader]>, texts: &mut Vec<String>) {
         match &self.content {
-            MimeBodyType::Regular(regular) => {
-                for i in regular {
-                    write!(f, "{i}")?;
-                    f.write_str("\r\n")?;
+            MimeBodyType::Multipart(multipart) => {
+                for part in &multipart.parts {
+                    part.collect_texts(Some(&self.headers), texts);
+                }
+            }
+            MimeBodyType::Regular(content) => {
+                let part = self.to_part(parent, 0);
+                if part.content_type.starts_with("text/") && !part.is_attachment() {
+                    texts.push(super::transfer_encoding::decode_content(
+                        content,
+                        &part.content_transfer_encoding,
+                        self.header("content-type")
+                            .and_then(|header| header.args.get("charset"))
+                            .map(String::as_str),
+                    ));
+                }
+            }
+            MimeBodyType::Embedded(mail) => {
+                if !self.to_part(parent, 0).is_attachment() {
+                    texts.extend(mail.text_parts());
                 }
-                Ok(())
+            }
+        }
+    }
+
+    /// Replace the leaf parts of this section whose filename has one of the `extensions`
+    /// by a text part noting the removal, nested multiparts included.
+    /// The filenames of the parts removed are pushed in `removed`.
+    pub(crate) fn strip_attachments(
+        &mut self,
+        parent: Option<&[MimeHeader]>,
+        extensions: &[String],
+        removed: &mut Vec<String>,
+    ) {
+        if let MimeBodyType::Multipart(multipart) = &mut self.content {
+            for part in &mut multipart.parts {
+                part.strip_attachments(Some(&self.headers), extensions, removed);
+            }
+            return;
+        }
+
+        let Some(filename) = self.to_part(parent, 0).filename else {
+            return;
+        };
+        let lowercase = filename.to_lowercase();
+        if !extensions.iter().any(|extension| {
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            !extension.is_empty() && lowercase.ends_with(&format!(".{extension}"))
+        }) {
+            return;
+        }
+
+        tracing::info!(filename, "Removing attachment.");
+        *self = Self {
+            headers: vec![MimeHeader {
+                name: "content-type".to_string(),
+                value: "text/plain".to_string(),
+                args: std::collections::HashMap::from([(
+                    "charset".to_string(),
+                    "utf-8".to_string(),
+                )]),
+            }],
+            content: MimeBodyType::Regular(vec![format!(
+                "The attachment \"{}\" has been removed.",
+                filename.replace(char::is_control, "")
+            )]),
+        };
+        removed.push(filename);
+    }
+
+    /// The content of the section as written in a message, without its headers.
+    pub(crate) fn content_to_string(&self) -> String {
+        match &self.content {
+            MimeBodyType::Regular(regular) => {
+                regular.iter().fold(String::new(), |mut out, line| {
+                    let _ = write!(out, "{line}\r\n");
+                    out
+                })
             }
             MimeBodyType::Multipart(multipart) => {
                 let boundary = self
@@ -125,11 +255,52 @@ impl std::fmt::Display for Mime {
                     .find_map(|header| header.args.get("boundary"))
                     .unwrap();
 
-                write!(f, "{}", MimeMultipartDisplayable(multipart, boundary))
+                MimeMultipartDisplayable(multipart, boundary).to_string()
             }
-            MimeBodyType::Embedded(mail) => write!(f, "{mail}"),
+            MimeBodyType::Embedded(mail) => mail.to_string(),
         }
     }
+
+    fn header(&self, name: &str) -> Option<&MimeHeader> {
+        self.headers.iter().find(|header| header.name == name)
+    }
+
+    fn to_part(&self, parent: Option<&[MimeHeader]>, size: usize) -> MimePart {
+        let (r#type, subtype) =
+            crate::helpers::get_mime_type(&self.headers, parent).unwrap_or(("text", "plain"));
+
+        MimePart {
+            content_type: format!("{type}/{subtype}"),
+            filename: self
+                .header("content-disposition")
+                .and_then(|header| header.args.get("filename"))
+                .or_else(|| {
+                    self.header("content-type")
+                        .and_then(|header| header.args.get("name"))
+                })
+                .map(|filename| {
+                    crate::decode_encoded_words(filename).unwrap_or_else(|| filename.clone())
+                }),
+            size,
+            content_transfer_encoding: self
+                .header("content-transfer-encoding")
+                .map_or_else(|| "7bit".to_string(), |header| header.value.clone()),
+            content_disposition: self
+                .header("content-disposition")
+                .map(|header| header.value.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for i in &self.headers {
+            write!(f, "{i}")?;
+        }
+        f.write_str("\r\n")?;
+
+        f.write_str(&self.content_to_string())
+    }
 }
 
 #[cfg(test)]
@@ -41,11 +41,30 @@ impl RawBody {
         }
     }
 
+    /// Replace the body, leaving the headers untouched.
+    pub fn set_body(&mut self, body: String) {
+        self.body = Some(body);
+    }
+
+    /// Append content at the end of the body.
+    pub fn append_body(&mut self, content: &str) {
+        self.body.get_or_insert_with(String::new).push_str(content);
+    }
+
     /// Return an iterator over the headers field
     pub fn headers_lines(&self) -> impl Iterator<Item = &str> {
         self.headers.iter().map(String::as_str)
     }
 
+    /// Size in bytes of the message as written by [`std::fmt::Display`],
+    /// computed without serializing it.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.headers.iter().map(String::len).sum::<usize>()
+            + "\r\n".len()
+            + self.body.as_ref().map_or(0, String::len)
+    }
+
     ///
     #[must_use]
     pub const fn body(&self) -> &Option<String> {
@@ -170,6 +189,37 @@ impl RawBody {
         self.headers.splice(..0, headers);
     }
 
+    /// Insert a header before the first header named `anchor`,
+    /// or prepend it if `anchor` does not exist.
+    pub fn insert_header_before(&mut self, anchor: &str, name: &str, value: &str) {
+        let index = self.find_header(anchor).unwrap_or(0);
+        self.headers.insert(index, format!("{name}: {value}"));
+    }
+
+    /// Insert a header after the first header named `anchor` (and its folded lines),
+    /// or append it if `anchor` does not exist.
+    pub fn insert_header_after(&mut self, anchor: &str, name: &str, value: &str) {
+        let index = self
+            .find_header(anchor)
+            .map_or(self.headers.len(), |index| {
+                index
+                    + 1
+                    + self.headers[index + 1..]
+                        .iter()
+                        .take_while(|s| s.starts_with(' ') || s.starts_with('\t'))
+                        .count()
+            });
+        self.headers.insert(index, format!("{name}: {value}"));
+    }
+
+    fn find_header(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| {
+            header
+                .split_once(':')
+                .map_or(false, |(key, _)| key.eq_ignore_ascii_case(name))
+        })
+    }
+
     /// Remove a header from the list.
     pub fn remove_header(&mut self, name: &str) -> bool {
         if let Some(index) = self.headers.iter().position(|header| {
@@ -183,6 +233,58 @@ impl RawBody {
             false
         }
     }
+
+    /// Remove every occurrence of a header (and its folded lines) from the list,
+    /// returning the number of headers removed.
+    pub fn remove_all_headers(&mut self, name: &str) -> usize {
+        let mut count = 0;
+        let mut in_removed = false;
+        self.headers.retain(|header| {
+            if header.starts_with(' ') || header.starts_with('\t') {
+                return !in_removed;
+            }
+            in_removed = header
+                .split_once(':')
+                .map_or(false, |(key, _)| key.eq_ignore_ascii_case(name));
+            if in_removed {
+                count += 1;
+            }
+            !in_removed
+        });
+        count
+    }
+
+    /// Remove the occurrences of a header (and their folded lines) for which `predicate`
+    /// returns `true` when called with the value of the header, returning the number of headers removed.
+    pub fn remove_headers_if(&mut self, name: &str, predicate: impl Fn(&str) -> bool) -> usize {
+        let mut count = 0;
+        let mut headers = Vec::with_capacity(self.headers.len());
+        let mut lines = std::mem::take(&mut self.headers).into_iter().peekable();
+
+        while let Some(header) = lines.next() {
+            let mut block = vec![header];
+            while let Some(folded) =
+                lines.next_if(|line| line.starts_with(' ') || line.starts_with('\t'))
+            {
+                block.push(folded);
+            }
+
+            let removed = block[0]
+                .split_once(':')
+                .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map_or(false, |(_, value)| {
+                    predicate(&[value, &block[1..].concat()].concat())
+                });
+            if removed {
+                count += 1;
+            } else {
+                headers.extend(block);
+            }
+        }
+
+        self.headers = headers;
+        count
+    }
 }
 
 impl std::fmt::Display for RawBody {
@@ -0,0 +1,132 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use base64::Engine;
+
+/// Decode the content of a body part, as transferred with the content transfer `encoding`
+/// (`base64`, `quoted-printable` or left untouched for the others), in the `charset` of the part.
+///
+/// A content that cannot be decoded is returned as transferred, and a content in
+/// an unsupported charset is decoded as `utf-8`, the invalid sequences being replaced.
+#[must_use]
+pub(super) fn decode_content(lines: &[String], encoding: &str, charset: Option<&str>) -> String {
+    let bytes = if encoding.eq_ignore_ascii_case("base64") {
+        super::encoded_word::BASE64
+            .decode(
+                lines
+                    .iter()
+                    .flat_map(|line| line.bytes())
+                    .filter(|byte| !byte.is_ascii_whitespace())
+                    .collect::<Vec<_>>(),
+            )
+            .ok()
+    } else if encoding.eq_ignore_ascii_case("quoted-printable") {
+        Some(decode_quoted_printable(lines))
+    } else {
+        None
+    };
+
+    let Some(bytes) = bytes else {
+        return lines.join("\n");
+    };
+
+    charset
+        .and_then(|charset| super::encoded_word::decode_charset(charset, &bytes))
+        .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// See <https://datatracker.ietf.org/doc/html/rfc2045#section-6.7>
+///
+/// An `=` not followed by two hexadecimal digits is kept as is.
+fn decode_quoted_printable(lines: &[String]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(lines.iter().map(String::len).sum());
+
+    for (index, line) in lines.iter().enumerate() {
+        // trailing whitespaces are added by some transports and must be removed.
+        let line = line.trim_end_matches(|c| c == ' ' || c == '\t');
+        let (line, soft_break) = line
+            .strip_suffix('=')
+            .map_or((line, false), |line| (line, true));
+
+        let mut bytes = line.as_bytes();
+        while let Some((&byte, rest)) = bytes.split_first() {
+            let hex = rest
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            if let (b'=', Some(decoded)) = (byte, hex) {
+                output.push(decoded);
+                bytes = &rest[2..];
+            } else {
+                output.push(byte);
+                bytes = rest;
+            }
+        }
+
+        if !soft_break && index + 1 != lines.len() {
+            output.push(b'\n');
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_content;
+
+    fn lines(content: &str) -> Vec<String> {
+        content.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn quoted_printable() {
+        assert_eq!(
+            decode_content(
+                &lines("Cr=C3=A9dit imm=C3=A9diat, cliquez =\nici  \n1+1=3D2 =ZZ =+1"),
+                "quoted-printable",
+                Some("utf-8")
+            ),
+            "Crédit immédiat, cliquez ici\n1+1=2 =ZZ =+1"
+        );
+    }
+
+    #[test]
+    fn base64_latin1() {
+        assert_eq!(
+            decode_content(&lines("Q2Fm6SBjcuhtZQ\n=="), "BASE64", Some("iso-8859-1")),
+            "Café crème"
+        );
+    }
+
+    #[test]
+    fn invalid_base64() {
+        assert_eq!(
+            decode_content(&lines("not base64!"), "base64", None),
+            "not base64!"
+        );
+    }
+
+    #[test]
+    fn unencoded() {
+        assert_eq!(
+            decode_content(&lines("Hello=20world\n!"), "7bit", None),
+            "Hello=20world\n!"
+        );
+    }
+}
@@ -89,3 +89,60 @@ fn test_set_and_append_header() {
         Some(new_header_message.to_string())
     );
 }
+
+#[test]
+fn text_parts_without_mime_version() {
+    for (encoding, body) in [
+        ("base64", "Q3LDqWRpdCBpbW3DqWRpYXQsIGF1Y3VuIGZyYWlzLg==\r\n"),
+        (
+            "quoted-printable",
+            "Cr=C3=A9dit imm=C3=A9diat,=\r\n aucun frais.\r\n",
+        ),
+    ] {
+        let mut message = MessageBody::new(
+            vec![
+                "From: john <john@example.com>\r\n".to_string(),
+                "Date: tue, 30 nov 2021 20:54:27 +0100\r\n".to_string(),
+                "Content-Type: text/plain; charset=utf-8\r\n".to_string(),
+                format!("Content-Transfer-Encoding: {encoding}\r\n"),
+            ],
+            body.to_string(),
+        );
+
+        assert!(message.parsed::<MailMimeParser>().unwrap().text_parts()[0]
+            .contains("Crédit immédiat, aucun frais."));
+    }
+}
+
+#[test]
+fn set_body_left_unchanged_on_error() {
+    let mut message = MessageBody::new(
+        vec![
+            "From: john <john@example.com>\r\n".to_string(),
+            "Date: tue, 30 nov 2021 20:54:27 +0100\r\n".to_string(),
+            "MIME-Version: 1.0\r\n".to_string(),
+            "Content-Type: multipart/mixed; boundary=\"foo\"\r\n".to_string(),
+        ],
+        [
+            "--foo\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "hello\r\n",
+            "--foo--\r\n",
+        ]
+        .concat(),
+    );
+    message.parse::<MailMimeParser>().unwrap();
+    let previous = message.clone();
+
+    assert!(message.set_body("no boundary here\n").is_err());
+    assert_eq!(message, previous);
+
+    message.append_body("epilogue\n").unwrap();
+    assert!(message
+        .inner()
+        .body()
+        .as_ref()
+        .unwrap()
+        .ends_with("--foo--\r\nepilogue\r\n"));
+}
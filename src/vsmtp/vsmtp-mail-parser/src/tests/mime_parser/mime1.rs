@@ -160,3 +160,72 @@ fn mime_parser() {
         }
     );
 }
+
+#[test]
+fn mime_parts() {
+    let mail = crate::MailParser::parse_sync(
+        &mut MailMimeParser::default(),
+        MAIL.lines()
+            .map(|l| l.as_bytes().to_vec())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap()
+    .unwrap_right();
+
+    assert_eq!(
+        mail.mime_parts()
+            .into_iter()
+            .map(|part| (
+                part.content_type,
+                part.filename,
+                part.content_transfer_encoding
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "text/plain".to_string(),
+                None,
+                "quoted-printable".to_string()
+            ),
+            (
+                "text/html".to_string(),
+                None,
+                "quoted-printable".to_string()
+            ),
+            (
+                "text/plain".to_string(),
+                Some("customers.txt".to_string()),
+                "base64".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn text_parts() {
+    let mail = crate::MailParser::parse_sync(
+        &mut MailMimeParser::default(),
+        MAIL.lines()
+            .map(|l| l.as_bytes().to_vec())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap()
+    .unwrap_right();
+
+    assert_eq!(
+        mail.text_parts(),
+        vec![
+            "Please see the attached file for a list of customers to contact.\n".to_string(),
+            concat!(
+                "<html>\n",
+                "<head></head>\n",
+                "<body>\n",
+                "<h1>Hello!</h1>\n",
+                "<p>Please see the attached file for a list of customers to contact.</p>\n",
+                "</body>\n",
+                "</html>\n"
+            )
+            .to_string(),
+        ]
+    );
+}
@@ -31,6 +31,14 @@ pub enum ParserError {
         /// Actual size.
         got: usize,
     },
+    /// A line of the message is longer than expected.
+    #[error("line is not supposed to be longer than {expected} bytes but got {got}")]
+    LineTooLong {
+        /// Maximum length expected.
+        expected: usize,
+        /// Actual length.
+        got: usize,
+    },
     /// The email size exceeds the SIZE EHLO extension.
     #[error("mail is not supposed to be bigger than {expected} bytes but was {got} bytes long")]
     MailSizeExceeded {
@@ -0,0 +1,375 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::{reader::Reader, writer::Writer, ConnectionKind, Error, Verb};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio_rustls::rustls;
+use tokio_stream::StreamExt;
+use vsmtp_common::{auth::Mechanism, Address, Reply};
+
+/// Stream of a [`Client`] secured by TLS.
+pub type TlsStream = tokio_rustls::client::TlsStream<tokio::net::TcpStream>;
+
+/// A SMTP client, sending the commands one by one and waiting for their reply.
+pub struct Client<W: tokio::io::AsyncWrite + Unpin + Send, R: tokio::io::AsyncRead + Unpin + Send> {
+    sink: Writer<W>,
+    stream: Reader<R>,
+    kind: ConnectionKind,
+    // NOTE: the lines of the reply to `EHLO`, except the first one (the server's name).
+    extensions: Vec<String>,
+    // NOTE: on LMTP connection, the server replies for each accepted recipient after the message.
+    accepted_recipients: usize,
+}
+
+impl Client<tokio::net::tcp::OwnedWriteHalf, tokio::net::tcp::OwnedReadHalf> {
+    /// Create a new [`Client`] from a TCP/IP stream.
+    ///
+    /// On a [`ConnectionKind::Tunneled`] connection, [`Client::upgrade_tls`] must be
+    /// called before reading the greeting.
+    #[inline]
+    #[must_use]
+    pub fn new(tcp_stream: tokio::net::TcpStream, kind: ConnectionKind) -> Self {
+        let (read, write) = tcp_stream.into_split();
+        Self {
+            sink: Writer::new(write),
+            stream: Reader::new(read, false),
+            kind,
+            extensions: vec![],
+            accepted_recipients: 0,
+        }
+    }
+
+    /// Initialize a TLS handshake on the stream, without sending `STARTTLS`.
+    ///
+    /// # Errors
+    ///
+    /// * the TLS handshake failed
+    #[inline]
+    pub async fn upgrade_tls(
+        self,
+        config: alloc::sync::Arc<rustls::ClientConfig>,
+        server_name: rustls::ServerName,
+    ) -> Result<Client<tokio::io::WriteHalf<TlsStream>, tokio::io::ReadHalf<TlsStream>>, Error>
+    {
+        #[allow(clippy::expect_used)]
+        let tcp_stream = self
+            .sink
+            .into_inner()
+            .reunite(self.stream.into_inner())
+            .expect("valid stream/sink pair");
+
+        let tls_stream = tokio_rustls::TlsConnector::from(config)
+            .connect(server_name, tcp_stream)
+            .await?;
+
+        // FIXME: see https://github.com/tokio-rs/tls/issues/40
+        let (read, write) = tokio::io::split(tls_stream);
+
+        Ok(Client {
+            sink: Writer::new(write),
+            stream: Reader::new(read, false),
+            kind: self.kind,
+            // NOTE: the extensions must be discovered again with a new `EHLO`.
+            extensions: vec![],
+            accepted_recipients: 0,
+        })
+    }
+
+    /// Send `STARTTLS` and initialize a TLS handshake if the server is ready.
+    ///
+    /// # Errors
+    ///
+    /// * the server did not reply `220` to `STARTTLS`
+    /// * the TLS handshake failed
+    /// * [`std::io::Error`] produced by the underlying stream
+    #[inline]
+    pub async fn starttls(
+        mut self,
+        config: alloc::sync::Arc<rustls::ClientConfig>,
+        server_name: rustls::ServerName,
+    ) -> Result<Client<tokio::io::WriteHalf<TlsStream>, tokio::io::ReadHalf<TlsStream>>, Error>
+    {
+        let reply = self.command(Verb::StartTls.as_ref()).await?;
+        if reply.code().value() != 220 {
+            return Err(Error::unexpected_reply(&reply));
+        }
+        self.upgrade_tls(config, server_name).await
+    }
+}
+
+impl<W: tokio::io::AsyncWrite + Unpin + Send, R: tokio::io::AsyncRead + Unpin + Send> Client<W, R> {
+    /// Type of the connection.
+    #[inline]
+    #[must_use]
+    pub const fn kind(&self) -> ConnectionKind {
+        self.kind
+    }
+
+    /// Extensions advertised by the server in its reply to `EHLO` (or `LHLO`),
+    /// with their parameters, e.g. `SIZE 20000000`.
+    #[inline]
+    #[must_use]
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Has the server advertised the extension `keyword` (case insensitive) ?
+    #[inline]
+    #[must_use]
+    pub fn has_extension(&self, keyword: &str) -> bool {
+        self.extension_params(keyword).is_some()
+    }
+
+    /// Maximum size of a message accepted by the server, advertised with the `SIZE` extension.
+    ///
+    /// Return `None` if the extension is not advertised, or without a limit.
+    #[inline]
+    #[must_use]
+    pub fn message_size_max(&self) -> Option<usize> {
+        self.extension_params("SIZE")?
+            .next()?
+            .parse()
+            .ok()
+            .filter(|size| *size != 0)
+    }
+
+    /// Authentication mechanisms advertised by the server, the unknown ones are ignored.
+    #[inline]
+    #[must_use]
+    pub fn auth_mechanisms(&self) -> Vec<Mechanism> {
+        self.extension_params("AUTH")
+            .map(|params| params.filter_map(|m| m.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn extension_params(&self, keyword: &str) -> Option<impl Iterator<Item = &str>> {
+        self.extensions.iter().find_map(|line| {
+            let mut words = line.split_whitespace();
+            words
+                .next()
+                .filter(|i| i.eq_ignore_ascii_case(keyword))
+                .map(|_| words)
+        })
+    }
+
+    async fn read_reply(&mut self) -> Result<Reply, Error> {
+        let replies = self.stream.as_reply_stream();
+        tokio::pin!(replies);
+        replies
+            .next()
+            .await
+            .unwrap_or_else(|| Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()))
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Reply, Error> {
+        self.sink.write_all(command).await?;
+        self.read_reply().await
+    }
+
+    /// Read the greeting sent by the server at the beginning of the connection.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn read_greeting(&mut self) -> Result<Reply, Error> {
+        self.read_reply().await
+    }
+
+    /// Send `EHLO` (or `LHLO` on a [`ConnectionKind::Lmtp`] connection), the extensions
+    /// advertised by the server are available with [`Client::extensions`].
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn ehlo(&mut self, client_name: &str) -> Result<Reply, Error> {
+        let verb = if self.kind == ConnectionKind::Lmtp {
+            Verb::Lhlo
+        } else {
+            Verb::Ehlo
+        };
+        let reply = self
+            .command(&format!("{}{client_name}\r\n", verb.as_ref()))
+            .await?;
+
+        self.extensions = if reply.code().is_error() {
+            vec![]
+        } else {
+            reply.lines().skip(1).map(|i| i.trim().to_owned()).collect()
+        };
+        Ok(reply)
+    }
+
+    /// Authenticate with the `PLAIN` or `LOGIN` mechanism, return the final reply of the server.
+    ///
+    /// # Errors
+    ///
+    /// * the `mechanism` is not supported by the client
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn auth(
+        &mut self,
+        mechanism: Mechanism,
+        authid: &str,
+        authpass: &str,
+    ) -> Result<Reply, Error> {
+        match mechanism {
+            Mechanism::Plain => {
+                let initial_response = STANDARD.encode(format!("\0{authid}\0{authpass}"));
+                self.command(&format!(
+                    "{}{mechanism} {initial_response}\r\n",
+                    Verb::Auth.as_ref()
+                ))
+                .await
+            }
+            Mechanism::Login => {
+                let mut reply = self
+                    .command(&format!("{}{mechanism}\r\n", Verb::Auth.as_ref()))
+                    .await?;
+                for response in [authid, authpass] {
+                    if reply.code().value() != 334 {
+                        break;
+                    }
+                    reply = self
+                        .command(&format!("{}\r\n", STANDARD.encode(response)))
+                        .await?;
+                }
+                Ok(reply)
+            }
+            Mechanism::CramMd5
+            | Mechanism::Anonymous
+            | Mechanism::OAuthBearer
+            | Mechanism::XOAuth2 => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("authentication mechanism {mechanism} is not supported by the client"),
+            )
+            .into()),
+        }
+    }
+
+    /// Start a new transaction, the `SIZE` parameter is sent only if the server supports it.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn mail_from(
+        &mut self,
+        reverse_path: Option<&Address>,
+        size: Option<usize>,
+    ) -> Result<Reply, Error> {
+        self.accepted_recipients = 0;
+
+        let size = size
+            .filter(|_| self.has_extension("SIZE"))
+            .map(|size| format!(" SIZE={size}"))
+            .unwrap_or_default();
+
+        self.command(&format!(
+            "{}<{}>{size}\r\n",
+            Verb::MailFrom.as_ref(),
+            reverse_path.map_or("", Address::full)
+        ))
+        .await
+    }
+
+    /// Add a recipient to the transaction.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn rcpt_to(&mut self, forward_path: &Address) -> Result<Reply, Error> {
+        let reply = self
+            .command(&format!("{}<{forward_path}>\r\n", Verb::RcptTo.as_ref()))
+            .await?;
+        if !reply.code().is_error() {
+            self.accepted_recipients += 1;
+        }
+        Ok(reply)
+    }
+
+    /// Send the message, the lines starting with a `.` are escaped.
+    ///
+    /// Return the reply to the end of data, or one reply per accepted recipient
+    /// on a [`ConnectionKind::Lmtp`] connection. If the server does not accept
+    /// the `DATA` command, its reply is returned instead.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn data(&mut self, message: &[u8]) -> Result<Vec<Reply>, Error> {
+        let reply = self.command(Verb::Data.as_ref()).await?;
+        if reply.code().value() != 354 {
+            return Ok(vec![reply]);
+        }
+
+        let mut buffer = Vec::with_capacity(message.len() + 5);
+        for line in message.split_inclusive(|c| *c == b'\n') {
+            if line.first() == Some(&b'.') {
+                buffer.push(b'.');
+            }
+            buffer.extend_from_slice(line);
+        }
+        if !buffer.is_empty() && !buffer.ends_with(b"\r\n") {
+            buffer.extend_from_slice(b"\r\n");
+        }
+        buffer.extend_from_slice(b".\r\n");
+        self.sink.write_all_bytes(&buffer).await?;
+
+        let expected = if self.kind == ConnectionKind::Lmtp {
+            self.accepted_recipients
+        } else {
+            1
+        };
+        let mut replies = Vec::with_capacity(expected);
+        for _ in 0..expected {
+            replies.push(self.read_reply().await?);
+        }
+        Ok(replies)
+    }
+
+    /// Abort the current transaction.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn rset(&mut self) -> Result<Reply, Error> {
+        self.accepted_recipients = 0;
+        self.command(Verb::Rset.as_ref()).await
+    }
+
+    /// Ask the server to close the connection.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying stream
+    /// * the reply is not valid
+    #[inline]
+    pub async fn quit(mut self) -> Result<Reply, Error> {
+        self.command(Verb::Quit.as_ref()).await
+    }
+}
@@ -17,6 +17,7 @@
 
 use crate::{ConnectionKind, Error, ParseArgsError};
 use vsmtp_common::{auth::Mechanism, Address, ClientName, Domain};
+pub use vsmtp_common::{DsnReturn, NotifyOn, OriginalRecipient};
 
 macro_rules! strip_suffix_crlf {
     ($v:expr) => {
@@ -79,8 +80,8 @@ impl AcceptArgs {
 /// Information received from the client at the HELO command.
 #[non_exhaustive]
 pub struct HeloArgs {
-    /// Name of the client.
-    pub client_name: Domain,
+    /// Name of the client, a domain or an address literal.
+    pub client_name: ClientName,
 }
 
 /// Information received from the client at the EHLO command.
@@ -104,20 +105,6 @@ pub enum MimeBodyType {
     // Binary,
 }
 
-/// <https://www.rfc-editor.org/rfc/rfc3461>
-/// return either the full message or only the headers.
-/// Only applies to DSNs that indicate delivery failure for at least one recipient.
-/// If a DSN contains no indications of delivery failure, only the headers of the message should be returned.
-#[allow(clippy::exhaustive_enums)]
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum DsnReturn {
-    /// Complete message
-    Full,
-    /// Only the message headers
-    Headers,
-}
-
 /// Information received from the client at the MAIL FROM command.
 #[non_exhaustive]
 pub struct MailFromArgs {
@@ -125,8 +112,9 @@ pub struct MailFromArgs {
     pub reverse_path: Option<Address>,
     /// (8BITMIME)
     pub mime_body_type: Option<MimeBodyType>,
-    // TODO:
-    // Option<String>       (AUTH)
+    /// rfc 4954 : `AUTH` argument, the identity of the submitter asserted by the client,
+    /// `None` if the argument is missing or `AUTH=<>` (unknown submitter)
+    pub auth: Option<Address>,
     /// (SIZE)
     pub size: Option<usize>,
     /// smtputf8 extension allowing utf8 email
@@ -136,35 +124,8 @@ pub struct MailFromArgs {
     pub envelop_id: Option<String>,
     /// `RET` argument of the `MAIL FROM` command
     pub ret: Option<DsnReturn>,
-}
-
-/// <https://www.rfc-editor.org/rfc/rfc3461>
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
-#[allow(clippy::exhaustive_enums)]
-pub enum NotifyOn {
-    /// This message must explicitly not produce a DSN.
-    Never,
-    // NOTE: this should be implemented as a bitmask
-    /// One or more scenarios that should produce a DSN.
-    Some {
-        /// The delivery of the message to the recipient was successful.
-        success: bool,
-        /// The delivery of the message to the recipient failed.
-        failure: bool,
-        /// The delivery of the message to the recipient has been delayed.
-        delay: bool,
-    },
-}
-
-/// <https://www.rfc-editor.org/rfc/rfc3461>
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-#[allow(clippy::exhaustive_structs)]
-pub struct OriginalRecipient {
-    /// The type of address used in the `ORCPT` argument. (rfc822)
-    pub addr_type: String,
-    /// The original recipient address.
-    pub mailbox: Address,
+    /// rfc 8689 : the message must only be relayed over TLS-protected sessions
+    pub requiretls: bool,
 }
 
 /// Information received from the client at the RCPT TO command.
@@ -188,6 +149,58 @@ pub struct AuthArgs {
     pub initial_response: Option<Vec<u8>>,
 }
 
+/// Information received from the client at the HELP command.
+#[non_exhaustive]
+pub struct HelpArgs {
+    /// The command the client asks help for, if any.
+    pub command: Option<String>,
+}
+
+/// Information received from the client at the VRFY command.
+#[non_exhaustive]
+pub struct VrfyArgs {
+    /// The user name or mailbox to verify.
+    pub query: String,
+}
+
+/// Information received from the client at the EXPN command.
+#[non_exhaustive]
+pub struct ExpnArgs {
+    /// The mailing list to expand.
+    pub list: String,
+}
+
+/// Protocol used by the original client, see [`XClientArgs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum XClientProto {
+    /// The client has been greeted with `HELO`.
+    Smtp,
+    /// The client has been greeted with `EHLO`.
+    Esmtp,
+}
+
+/// Information received from a trusted upstream MTA at the XCLIENT command,
+/// describing the original client.
+///
+/// The attributes not sent, or sent with the `[UNAVAILABLE]` or `[TEMPUNAVAIL]` values, are `None`.
+/// <https://www.postfix.org/XCLIENT_README.html>
+#[non_exhaustive]
+pub struct XClientArgs {
+    /// Address of the client (`ADDR`).
+    pub addr: Option<std::net::IpAddr>,
+    /// Port of the client (`PORT`).
+    pub port: Option<u16>,
+    /// Name of the client, resolved by the upstream MTA (`NAME`).
+    pub name: Option<String>,
+    /// Name sent by the client in the `HELO` or `EHLO` command (`HELO`).
+    pub helo: Option<ClientName>,
+    /// Protocol used by the client (`PROTO`).
+    pub proto: Option<XClientProto>,
+    /// Login of the client, authenticated by the upstream MTA (`LOGIN`).
+    pub login: Option<String>,
+}
+
 fn split_args(slice: &[u8]) -> Option<(&[u8], &[u8])> {
     slice.iter().position(|c| *c == b'=').map(|pos| {
         let (k, v) = slice.split_at(pos);
@@ -200,17 +213,48 @@ impl TryFrom<UnparsedArgs> for HeloArgs {
 
     #[inline]
     fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
-        let value = strip_suffix_crlf!(value).to_vec();
+        let value = String::from_utf8(strip_suffix_crlf!(value).to_vec())?;
 
         Ok(Self {
-            client_name: Domain::from_utf8(
-                addr::parse_domain_name(&String::from_utf8(value)?)
+            client_name: parse_client_name(&value)?,
+        })
+    }
+}
+
+fn parse_client_name(value: &str) -> Result<ClientName, ParseArgsError> {
+    // some clients send their ip address without the brackets of the address literal,
+    // the unspecified address cannot be the one of a client.
+    match value.parse::<std::net::IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => return Err(ParseArgsError::InvalidArgs),
+        Ok(std::net::IpAddr::V4(ip)) => return Ok(ClientName::Ip4(ip)),
+        Ok(std::net::IpAddr::V6(ip)) => return Ok(ClientName::Ip6(ip)),
+        Err(_) => {}
+    }
+
+    if !value.is_ascii() {
+        return Err(ParseArgsError::InvalidArgs);
+    }
+
+    Ok(match value {
+        ipv6 if ipv6.to_lowercase().starts_with("[ipv6:") && ipv6.ends_with(']') => {
+            match ipv6.get("[IPv6:".len()..ipv6.len() - 1) {
+                Some(ipv6) => ClientName::Ip6(ipv6.parse::<std::net::Ipv6Addr>()?),
+                None => return Err(ParseArgsError::InvalidArgs),
+            }
+        }
+        ipv4 if ipv4.starts_with('[') && ipv4.ends_with(']') => match ipv4.get(1..ipv4.len() - 1) {
+            Some(ipv4) => ClientName::Ip4(ipv4.parse::<std::net::Ipv4Addr>()?),
+            None => return Err(ParseArgsError::InvalidArgs),
+        },
+        domain => ClientName::Domain(
+            Domain::from_utf8(
+                addr::parse_domain_name(domain)
                     .map_err(|_err| ParseArgsError::InvalidArgs)?
                     .as_str(),
             )
             .map_err(|_err| ParseArgsError::InvalidArgs)?,
-        })
-    }
+        ),
+    })
 }
 
 impl TryFrom<UnparsedArgs> for EhloArgs {
@@ -220,34 +264,65 @@ impl TryFrom<UnparsedArgs> for EhloArgs {
     fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
         let value = String::from_utf8(strip_suffix_crlf!(value).to_vec())?;
 
-        if !value.is_ascii() {
-            return Err(ParseArgsError::InvalidArgs);
-        }
+        Ok(Self {
+            client_name: parse_client_name(&value)?,
+        })
+    }
+}
 
-        let client_name = match &value {
-            ipv6 if ipv6.to_lowercase().starts_with("[ipv6:") && ipv6.ends_with(']') => {
-                match ipv6.get("[IPv6:".len()..ipv6.len() - 1) {
-                    Some(ipv6) => ClientName::Ip6(ipv6.parse::<std::net::Ipv6Addr>()?),
-                    None => return Err(ParseArgsError::InvalidArgs),
-                }
-            }
-            ipv4 if ipv4.starts_with('[') && ipv4.ends_with(']') => {
-                match ipv4.get(1..ipv4.len() - 1) {
-                    Some(ipv4) => ClientName::Ip4(ipv4.parse::<std::net::Ipv4Addr>()?),
-                    None => return Err(ParseArgsError::InvalidArgs),
-                }
-            }
-            domain => ClientName::Domain(
-                Domain::from_utf8(
-                    addr::parse_domain_name(domain)
-                        .map_err(|_err| ParseArgsError::InvalidArgs)?
-                        .as_str(),
-                )
-                .map_err(|_err| ParseArgsError::InvalidArgs)?,
-            ),
-        };
+fn parse_vrfy_string(value: &UnparsedArgs) -> Result<String, ParseArgsError> {
+    let value = String::from_utf8(strip_suffix_crlf!(value).to_vec())?;
+    let value = value.trim();
+
+    if value.is_empty() {
+        return Err(ParseArgsError::InvalidArgs);
+    }
+    Ok(value.to_owned())
+}
+
+/// Parse the optional argument of a command matched without its separator (`HELP`, `NOOP`),
+/// `HELPX` or `NOOPX` are not valid commands.
+pub fn parse_optional_string(value: &UnparsedArgs) -> Result<Option<String>, ParseArgsError> {
+    let value = std::str::from_utf8(strip_suffix_crlf!(value))?;
+
+    if !value.is_empty() && !value.starts_with(|c: char| c.is_ascii_whitespace()) {
+        return Err(ParseArgsError::InvalidArgs);
+    }
+    let value = value.trim();
+
+    Ok((!value.is_empty()).then(|| value.to_owned()))
+}
+
+impl TryFrom<UnparsedArgs> for HelpArgs {
+    type Error = ParseArgsError;
+
+    #[inline]
+    fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            command: parse_optional_string(&value)?,
+        })
+    }
+}
 
-        Ok(Self { client_name })
+impl TryFrom<UnparsedArgs> for VrfyArgs {
+    type Error = ParseArgsError;
+
+    #[inline]
+    fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            query: parse_vrfy_string(&value)?,
+        })
+    }
+}
+
+impl TryFrom<UnparsedArgs> for ExpnArgs {
+    type Error = ParseArgsError;
+
+    #[inline]
+    fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            list: parse_vrfy_string(&value)?,
+        })
     }
 }
 
@@ -311,7 +386,7 @@ impl MailFromArgs {
                 }
             }
             Some((key, value)) if key.eq_ignore_ascii_case(b"SIZE") => {
-                if self.mime_body_type.is_some() {
+                if self.size.is_some() {
                     Err(ParseArgsError::InvalidArgs)
                 } else {
                     self.size = Some(
@@ -334,6 +409,20 @@ impl MailFromArgs {
                     Ok(())
                 }
             }
+            Some((key, value)) if key.eq_ignore_ascii_case(b"AUTH") => {
+                if self.auth.is_some() {
+                    return Err(ParseArgsError::InvalidArgs);
+                }
+                let value = decode_xtext(value)?;
+                self.auth = match value.as_str() {
+                    "<>" => None,
+                    mailbox => Some(
+                        <Address as std::str::FromStr>::from_str(mailbox)
+                            .map_err(|_error| ParseArgsError::InvalidMailAddress { mail: value })?,
+                    ),
+                };
+                Ok(())
+            }
             Some((key, value)) if key.eq_ignore_ascii_case(b"ENVID") => {
                 if self.envelop_id.is_some() {
                     Err(ParseArgsError::InvalidArgs)
@@ -356,6 +445,14 @@ impl MailFromArgs {
                 self.use_smtputf8 = true;
                 Ok(())
             }
+            value if value.eq_ignore_ascii_case(b"REQUIRETLS") => {
+                if self.requiretls {
+                    Err(ParseArgsError::InvalidArgs)
+                } else {
+                    self.requiretls = true;
+                    Ok(())
+                }
+            }
             _ => Err(ParseArgsError::InvalidArgs),
         }
     }
@@ -382,10 +479,12 @@ impl TryFrom<UnparsedArgs> for MailFromArgs {
         let mut result = Self {
             reverse_path: None,
             mime_body_type: None,
+            auth: None,
             size: None,
             use_smtputf8: false,
             envelop_id: None,
             ret: None,
+            requiretls: false,
         };
 
         for arg in args {
@@ -441,70 +540,33 @@ impl RcptToArgs {
                 }
             }
             Some((key, value)) if key.eq_ignore_ascii_case(b"NOTIFY") => {
-                const SUCCESS: &[u8] = b"SUCCESS";
-                const FAILURE: &[u8] = b"FAILURE";
-                const DELAY: &[u8] = b"DELAY";
-                const VARIANTS: &[&[u8]] = &[SUCCESS, FAILURE, DELAY];
-
-                let mut notify = None;
-
-                let mut begin = 0;
-                let it = memchr::memchr_iter(b'|', value);
-                for pos in it {
-                    let v = &value[begin..=pos];
-
-                    #[allow(clippy::pattern_type_mismatch)]
-                    match (v, &mut notify) {
-                        (value, Some(NotifyOn::Never))
-                            if VARIANTS.iter().any(|i| i.eq_ignore_ascii_case(value)) =>
-                        {
-                            return Err(ParseArgsError::InvalidArgs)
-                        }
-                        (value, None) if value.eq_ignore_ascii_case(b"NEVER") => {
-                            notify = Some(NotifyOn::Never);
-                        }
-                        (value, None) if value.eq_ignore_ascii_case(SUCCESS) => {
-                            notify = Some(NotifyOn::Some {
-                                success: true,
-                                failure: false,
-                                delay: false,
-                            });
-                        }
-                        (value, None) if value.eq_ignore_ascii_case(b"FAILURE") => {
-                            notify = Some(NotifyOn::Some {
-                                success: false,
-                                failure: true,
-                                delay: false,
-                            });
-                        }
-                        (value, None) if value.eq_ignore_ascii_case(DELAY) => {
-                            notify = Some(NotifyOn::Some {
-                                success: false,
-                                failure: false,
-                                delay: true,
-                            });
-                        }
-                        (value, Some(NotifyOn::Some { success, .. }))
-                            if value.eq_ignore_ascii_case(SUCCESS) =>
-                        {
-                            *success = true;
-                        }
-                        (value, Some(NotifyOn::Some { failure, .. }))
-                            if value.eq_ignore_ascii_case(FAILURE) =>
-                        {
-                            *failure = true;
+                // "NEVER" or a comma separated list of "SUCCESS", "FAILURE" and "DELAY"
+                // see https://www.rfc-editor.org/rfc/rfc3461#section-4.1
+                let keywords = value.split(|c| *c == b',').collect::<Vec<_>>();
+
+                self.notify_on = match keywords.as_slice() {
+                    &[never] if never.eq_ignore_ascii_case(b"NEVER") => NotifyOn::Never,
+                    keywords => {
+                        let (mut success, mut failure, mut delay) = (false, false, false);
+                        for keyword in keywords {
+                            let flag = match keyword {
+                                k if k.eq_ignore_ascii_case(b"SUCCESS") => &mut success,
+                                k if k.eq_ignore_ascii_case(b"FAILURE") => &mut failure,
+                                k if k.eq_ignore_ascii_case(b"DELAY") => &mut delay,
+                                _ => return Err(ParseArgsError::InvalidArgs),
+                            };
+                            if *flag {
+                                return Err(ParseArgsError::InvalidArgs);
+                            }
+                            *flag = true;
                         }
-                        (value, Some(NotifyOn::Some { delay, .. }))
-                            if value.eq_ignore_ascii_case(DELAY) =>
-                        {
-                            *delay = true;
+                        NotifyOn::Some {
+                            success,
+                            failure,
+                            delay,
                         }
-                        _ => return Err(ParseArgsError::InvalidArgs),
                     }
-
-                    begin = pos;
-                }
-
+                };
                 Ok(())
             }
             _ => Err(ParseArgsError::InvalidArgs),
@@ -534,11 +596,7 @@ impl TryFrom<UnparsedArgs> for RcptToArgs {
             forward_path: <Address as std::str::FromStr>::from_str(&mailbox)
                 .map_err(|_error| ParseArgsError::InvalidMailAddress { mail: mailbox })?,
             original_forward_path: None,
-            notify_on: NotifyOn::Some {
-                success: false,
-                failure: true,
-                delay: false,
-            },
+            notify_on: NotifyOn::default(),
         };
 
         for arg in args {
@@ -553,6 +611,103 @@ impl TryFrom<UnparsedArgs> for RcptToArgs {
     }
 }
 
+/// Decode a value encoded as `xtext`, see <https://datatracker.ietf.org/doc/html/rfc3461#section-4>
+fn decode_xtext(value: &[u8]) -> Result<String, ParseArgsError> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut chars = value.iter().copied();
+    while let Some(char) = chars.next() {
+        if char == b'+' {
+            let hex = [
+                chars.next().ok_or(ParseArgsError::InvalidArgs)?,
+                chars.next().ok_or(ParseArgsError::InvalidArgs)?,
+            ];
+            decoded.push(
+                u8::from_str_radix(core::str::from_utf8(&hex)?, 16)
+                    .map_err(|_err| ParseArgsError::InvalidArgs)?,
+            );
+        } else {
+            decoded.push(char);
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
+
+impl TryFrom<UnparsedArgs> for XClientArgs {
+    type Error = ParseArgsError;
+
+    #[inline]
+    fn try_from(value: UnparsedArgs) -> Result<Self, Self::Error> {
+        let value = strip_suffix_crlf!(value);
+
+        let mut result = Self {
+            addr: None,
+            port: None,
+            name: None,
+            helo: None,
+            proto: None,
+            login: None,
+        };
+
+        let mut attributes = value
+            .split(u8::is_ascii_whitespace)
+            .filter(|s| !s.is_empty())
+            .peekable();
+        if attributes.peek().is_none() {
+            return Err(ParseArgsError::InvalidArgs);
+        }
+
+        for attribute in attributes {
+            let (key, value) = split_args(attribute).ok_or(ParseArgsError::InvalidArgs)?;
+            let value = decode_xtext(value)?;
+            let value = if value == "[UNAVAILABLE]" || value == "[TEMPUNAVAIL]" {
+                None
+            } else {
+                Some(value)
+            };
+
+            match key {
+                key if key.eq_ignore_ascii_case(b"ADDR") => {
+                    result.addr = value
+                        .map(|addr| match addr.get(.."IPV6:".len()) {
+                            Some(prefix) if prefix.eq_ignore_ascii_case("IPV6:") => addr
+                                .get("IPV6:".len()..)
+                                .unwrap_or_default()
+                                .parse::<std::net::Ipv6Addr>()
+                                .map(std::net::IpAddr::V6),
+                            _ => addr.parse::<std::net::Ipv4Addr>().map(std::net::IpAddr::V4),
+                        })
+                        .transpose()?;
+                }
+                key if key.eq_ignore_ascii_case(b"PORT") => {
+                    result.port = value
+                        .map(|port| port.parse().map_err(|_err| ParseArgsError::InvalidArgs))
+                        .transpose()?;
+                }
+                key if key.eq_ignore_ascii_case(b"NAME") => result.name = value,
+                key if key.eq_ignore_ascii_case(b"HELO") => {
+                    result.helo = value.as_deref().map(parse_client_name).transpose()?;
+                }
+                key if key.eq_ignore_ascii_case(b"PROTO") => {
+                    result.proto = match value.as_deref() {
+                        Some(proto) if proto.eq_ignore_ascii_case("SMTP") => {
+                            Some(XClientProto::Smtp)
+                        }
+                        Some(proto) if proto.eq_ignore_ascii_case("ESMTP") => {
+                            Some(XClientProto::Esmtp)
+                        }
+                        Some(_) => return Err(ParseArgsError::InvalidArgs),
+                        None => None,
+                    };
+                }
+                key if key.eq_ignore_ascii_case(b"LOGIN") => result.login = value,
+                _ => return Err(ParseArgsError::InvalidArgs),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
 /// SMTP Command.
 #[derive(
     Debug, Copy, Clone, PartialEq, Eq, strum::AsRefStr, strum::EnumString, strum::EnumVariantNames,
@@ -565,6 +720,10 @@ pub enum Verb {
     /// Used to identify the SMTP client to the SMTP server and request smtp extensions.
     #[strum(serialize = "EHLO ")]
     Ehlo,
+    /// Used to identify the LMTP client to the LMTP server, replacing `EHLO`.
+    /// <https://datatracker.ietf.org/doc/html/rfc2033>
+    #[strum(serialize = "LHLO ")]
+    Lhlo,
     /// This command is used to initiate a mail transaction in which the mail
     /// data is delivered to an SMTP server that may, in turn, deliver it to
     /// one or more mailboxes or pass it on to another system (possibly using
@@ -595,8 +754,8 @@ pub enum Verb {
     #[strum(serialize = "HELP")]
     Help,
     /// This command does not affect any parameters or previously entered
-    /// commands.
-    #[strum(serialize = "NOOP\r\n")]
+    /// commands. The optional argument is ignored.
+    #[strum(serialize = "NOOP")]
     Noop,
     /// See "Transport Layer Security"
     /// <https://datatracker.ietf.org/doc/html/rfc3207>
@@ -606,6 +765,19 @@ pub enum Verb {
     /// <https://datatracker.ietf.org/doc/html/rfc4954>
     #[strum(serialize = "AUTH ")]
     Auth,
+    /// Forward the identity of the original client, sent by a trusted upstream MTA.
+    /// <https://www.postfix.org/XCLIENT_README.html>
+    #[strum(serialize = "XCLIENT ")]
+    XClient,
+    /// Ask the server to confirm that the argument identifies a user or mailbox.
+    /// <https://datatracker.ietf.org/doc/html/rfc5321#section-3.5>
+    #[strum(serialize = "VRFY ")]
+    Vrfy,
+    /// Ask the server to confirm that the argument identifies a mailing list,
+    /// and if so, to return the membership of that list.
+    /// <https://datatracker.ietf.org/doc/html/rfc5321#section-3.5>
+    #[strum(serialize = "EXPN ")]
+    Expn,
     /// Any other buffer received while expecting a command is considered an
     /// unknown.
     Unknown,
@@ -613,12 +785,212 @@ pub enum Verb {
 
 impl Verb {
     /// check if the answer of the verb is bufferable (cf. pipelining)
-    // Note: missing VRFY, EXPN, TURN
+    ///
+    /// The other verbs are synchronization points, they must be the last command of
+    /// a pipelined group and their reply is sent with the ones of the group.
+    /// <https://datatracker.ietf.org/doc/html/rfc2920#section-3.1>
+    // Note: missing TURN
     #[inline]
     #[must_use]
     pub const fn is_bufferable(self) -> bool {
-        !matches!(self, Self::Ehlo | Self::Data | Self::Quit | Self::Noop)
+        !matches!(
+            self,
+            Self::Ehlo
+                | Self::Lhlo
+                | Self::Data
+                | Self::Quit
+                | Self::Noop
+                | Self::StartTls
+                | Self::Auth
+                | Self::XClient
+                | Self::Vrfy
+                | Self::Expn
+        )
     }
 }
 
 pub type Batch = Vec<Result<Command<Verb, UnparsedArgs>, Error>>;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case("mail.example.com", ClientName::Domain("mail.example.com".parse().unwrap()))]
+    #[case("[192.0.2.1]", ClientName::Ip4("192.0.2.1".parse().unwrap()))]
+    #[case("[IPv6:2001:db8::1]", ClientName::Ip6("2001:db8::1".parse().unwrap()))]
+    #[case("[ipv6:2001:db8::1]", ClientName::Ip6("2001:db8::1".parse().unwrap()))]
+    fn client_name(#[case] args: &str, #[case] expected: ClientName) {
+        let args = UnparsedArgs(format!("{args}\r\n").into_bytes());
+
+        assert_eq!(
+            HeloArgs::try_from(args.clone()).unwrap().client_name,
+            expected
+        );
+        assert_eq!(EhloArgs::try_from(args).unwrap().client_name, expected);
+    }
+
+    #[rstest::rstest]
+    #[case("[]")]
+    #[case("[192.0.2.256]")]
+    #[case("[2001:db8::1]")]
+    #[case("[IPv6:192.0.2.1]")]
+    #[case("[192.0.2.1")]
+    fn client_name_invalid_literal(#[case] args: &str) {
+        let args = UnparsedArgs(format!("{args}\r\n").into_bytes());
+
+        assert!(HeloArgs::try_from(args.clone()).is_err());
+        assert!(EhloArgs::try_from(args).is_err());
+    }
+
+    #[test]
+    fn help_args() {
+        let args = |args: &str| UnparsedArgs(format!("{args}\r\n").into_bytes());
+
+        assert_eq!(HelpArgs::try_from(args("")).unwrap().command, None);
+        assert_eq!(HelpArgs::try_from(args("  ")).unwrap().command, None);
+        assert_eq!(
+            HelpArgs::try_from(args(" MAIL")).unwrap().command,
+            Some("MAIL".to_owned())
+        );
+        assert!(HelpArgs::try_from(args("X")).is_err());
+    }
+
+    #[test]
+    fn vrfy_expn_args() {
+        let args = |args: &str| UnparsedArgs(format!("{args}\r\n").into_bytes());
+
+        assert_eq!(VrfyArgs::try_from(args(" root ")).unwrap().query, "root");
+        assert_eq!(
+            VrfyArgs::try_from(args("<john@example.com>"))
+                .unwrap()
+                .query,
+            "<john@example.com>"
+        );
+        assert_eq!(ExpnArgs::try_from(args("staff")).unwrap().list, "staff");
+        assert!(VrfyArgs::try_from(args("  ")).is_err());
+        assert!(ExpnArgs::try_from(args("")).is_err());
+    }
+
+    fn rcpt_to(args: &str) -> Result<RcptToArgs, ParseArgsError> {
+        RcptToArgs::try_from(UnparsedArgs(format!("{args}\r\n").into_bytes()))
+    }
+
+    #[test]
+    fn rcpt_to_dsn() {
+        let args = rcpt_to("<a@b> NOTIFY=SUCCESS,FAILURE ORCPT=rfc822;a@b").unwrap();
+
+        assert_eq!(args.forward_path, vsmtp_common::addr!("a@b"));
+        assert_eq!(
+            args.notify_on,
+            NotifyOn::Some {
+                success: true,
+                failure: true,
+                delay: false
+            }
+        );
+        assert_eq!(
+            args.original_forward_path,
+            Some(OriginalRecipient {
+                addr_type: "rfc822".to_owned(),
+                mailbox: vsmtp_common::addr!("a@b"),
+            })
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("<a@b>", NotifyOn::default())]
+    #[case("<a@b> NOTIFY=NEVER", NotifyOn::Never)]
+    #[case(
+        "<a@b> notify=delay",
+        NotifyOn::Some { success: false, failure: false, delay: true }
+    )]
+    #[case(
+        "<a@b> NOTIFY=FAILURE,DELAY,SUCCESS",
+        NotifyOn::Some { success: true, failure: true, delay: true }
+    )]
+    fn rcpt_to_notify(#[case] args: &str, #[case] expected: NotifyOn) {
+        assert_eq!(rcpt_to(args).unwrap().notify_on, expected);
+    }
+
+    #[rstest::rstest]
+    #[case("<a@b> NOTIFY=")]
+    #[case("<a@b> NOTIFY=NEVER,SUCCESS")]
+    #[case("<a@b> NOTIFY=SUCCESS,SUCCESS")]
+    #[case("<a@b> NOTIFY=SUCCESS,")]
+    #[case("<a@b> NOTIFY=ALWAYS")]
+    #[case("<a@b> ORCPT=a@b")]
+    fn rcpt_to_invalid_dsn(#[case] args: &str) {
+        assert!(rcpt_to(args).is_err());
+    }
+
+    #[test]
+    fn mail_from_dsn() {
+        let args =
+            MailFromArgs::try_from(UnparsedArgs(b"<a@b> RET=HDRS ENVID=QQ314159\r\n".to_vec()))
+                .unwrap();
+
+        assert_eq!(args.ret, Some(DsnReturn::Headers));
+        assert_eq!(args.envelop_id, Some("QQ314159".to_owned()));
+    }
+
+    #[rstest::rstest]
+    #[case("<a@b> AUTH=<>", None)]
+    #[case("<a@b> AUTH=e+3Dmc2@example.com", Some("e=mc2@example.com"))]
+    #[case("<> auth=john@doe", Some("john@doe"))]
+    fn mail_from_auth(#[case] args: &str, #[case] expected: Option<&str>) {
+        let args =
+            MailFromArgs::try_from(UnparsedArgs(format!("{args}\r\n").into_bytes())).unwrap();
+
+        assert_eq!(args.auth, expected.map(|i| i.parse().unwrap()));
+    }
+
+    #[rstest::rstest]
+    #[case("<a@b> AUTH=")]
+    #[case("<a@b> AUTH=john")]
+    #[case("<a@b> AUTH=john+2")]
+    #[case("<a@b> AUTH=a@b AUTH=a@b")]
+    fn mail_from_invalid_auth(#[case] args: &str) {
+        assert!(MailFromArgs::try_from(UnparsedArgs(format!("{args}\r\n").into_bytes())).is_err());
+    }
+
+    fn xclient(args: &str) -> Result<XClientArgs, ParseArgsError> {
+        XClientArgs::try_from(UnparsedArgs(format!("{args}\r\n").into_bytes()))
+    }
+
+    #[test]
+    fn xclient_attributes() {
+        let args = xclient(
+            "ADDR=IPV6:2001:db8::1 PORT=4242 NAME=[UNAVAILABLE] helo=client.example.com PROTO=ESMTP LOGIN=john+2Bdoe",
+        )
+        .unwrap();
+
+        assert_eq!(args.addr, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(args.port, Some(4242));
+        assert_eq!(args.name, None);
+        assert_eq!(
+            args.helo,
+            Some(ClientName::Domain("client.example.com".parse().unwrap()))
+        );
+        assert_eq!(args.proto, Some(XClientProto::Esmtp));
+        assert_eq!(args.login, Some("john+doe".to_owned()));
+
+        assert_eq!(
+            xclient("ADDR=192.0.2.1").unwrap().addr,
+            Some("192.0.2.1".parse().unwrap())
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("")]
+    #[case("ADDR")]
+    #[case("ADDR=client.example.com")]
+    #[case("PORT=65536")]
+    #[case("PROTO=LMTP")]
+    #[case("LOGIN=john+2")]
+    #[case("DESTADDR=192.0.2.1")]
+    fn xclient_invalid(#[case] args: &str) {
+        assert!(xclient(args).is_err());
+    }
+}
@@ -42,4 +42,12 @@ pub enum ConnectionKind {
     /// Connection coming for submissionS (MSA on port 465)
     /// see <https://datatracker.ietf.org/doc/html/rfc8314>
     Tunneled,
+    /// Connection relayed by a load balancer, starting with a PROXY protocol header (v1 or v2)
+    /// carrying the address of the real client, then handled as a relay connection.
+    /// see <https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt>
+    Proxied,
+    /// Connection coming for local delivery, using LMTP (`LHLO` greeting and one
+    /// reply per recipient after the message)
+    /// see <https://datatracker.ietf.org/doc/html/rfc2033>
+    Lmtp,
 }
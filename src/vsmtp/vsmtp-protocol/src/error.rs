@@ -189,10 +189,42 @@ impl Error {
         .into()
     }
 
+    pub(crate) fn line_too_long(expected: usize, got: usize) -> Self {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            ParseArgsError::LineTooLong { expected, got },
+        )
+        .into()
+    }
+
+    pub(crate) fn message_aborted() -> Self {
+        std::io::Error::new(
+            std::io::ErrorKind::ConnectionAborted,
+            "message aborted by the pre-scan".to_owned(),
+        )
+        .into()
+    }
+
     pub(crate) fn no_crlf() -> Self {
         std::io::Error::new(std::io::ErrorKind::InvalidData, "No CRLF found".to_owned()).into()
     }
 
+    pub(crate) fn invalid_proxy_header(reason: &str) -> Self {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid PROXY protocol header: {reason}"),
+        )
+        .into()
+    }
+
+    pub(crate) fn unexpected_reply(reply: &vsmtp_common::Reply) -> Self {
+        std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("unexpected reply: {}", reply.as_ref().trim_end()),
+        )
+        .into()
+    }
+
     /// Produce an error with a timeout message.
     #[must_use]
     #[inline]
@@ -263,6 +295,14 @@ pub enum ParseArgsError {
         /// actual size of the buffer we got
         got: usize,
     },
+    /// A line of the message is too long, "\r\n" included.
+    #[error("line is not supposed to be longer than {expected} bytes but got {got}")]
+    LineTooLong {
+        /// line length limit
+        expected: usize,
+        /// actual length of the line
+        got: usize,
+    },
     /// mail address is invalid (for rcpt, mail from ...)
     #[error("")]
     InvalidMailAddress {
@@ -17,7 +17,7 @@
 
 //! vSMTP protocol implementation
 //!
-//! Currently only implement a ESMTPSA server.
+//! Implement a ESMTPSA server, and a minimal ESMTP client.
 
 #![doc(html_no_source)]
 #![deny(missing_docs)]
@@ -48,24 +48,28 @@
 
 extern crate alloc;
 
+mod client;
 mod command;
 mod connection_kind;
 mod error;
+mod proxy_protocol;
 mod reader;
 mod receiver;
 mod receiver_handler;
 mod smtp_sasl;
 mod writer;
 
+pub use client::{Client, TlsStream};
 pub use command::{
-    AcceptArgs, AuthArgs, DsnReturn, EhloArgs, HeloArgs, MailFromArgs, NotifyOn, OriginalRecipient,
-    RcptToArgs, UnparsedArgs, Verb,
+    AcceptArgs, AuthArgs, DsnReturn, EhloArgs, ExpnArgs, HeloArgs, HelpArgs, MailFromArgs,
+    NotifyOn, OriginalRecipient, RcptToArgs, UnparsedArgs, Verb, VrfyArgs, XClientArgs,
+    XClientProto,
 };
 pub use connection_kind::ConnectionKind;
 pub use error::{Error, ErrorKind, ParseArgsError};
-pub use reader::Reader;
+pub use reader::{LineLengthPolicy, Reader};
 pub use receiver::{Receiver, ReceiverContext};
-pub use receiver_handler::ReceiverHandler;
+pub use receiver_handler::{HeloCheck, Prescan, RateLimit, ReceiverHandler};
 pub use rsasl;
 pub use smtp_sasl::{AuthError, CallbackWrap};
 pub use tokio_rustls;
@@ -0,0 +1,325 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::Error;
+
+/// The human-readable (v1) header starts with this prefix.
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// A v1 header, including the CRLF, is never longer than this.
+const V1_MAX_LEN: usize = 107;
+
+/// The binary (v2) header starts with this signature.
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Size of the fixed part of the v2 header (signature, command, family and length).
+const V2_HEADER_LEN: usize = 16;
+
+/// Information extracted from a PROXY protocol header, sent by a load balancer
+/// before any other data.
+///
+/// See <https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt>
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProxyHeader {
+    /// The connection has been established by the proxy itself (health check...),
+    /// or the original addresses are unknown: the addresses of the socket must be used.
+    Local,
+    /// The connection has been relayed by the proxy on behalf of a client.
+    Proxied {
+        /// Address of the client connected to the proxy.
+        source: std::net::SocketAddr,
+    },
+}
+
+/// `true` if the first bytes of `buffer` are compatible with `expected`.
+fn is_prefix_of(buffer: &[u8], expected: &[u8]) -> bool {
+    let len = buffer.len().min(expected.len());
+    buffer[..len] == expected[..len]
+}
+
+/// Try to parse a PROXY protocol header at the beginning of `buffer`.
+///
+/// # Returns
+///
+/// * `None` if more bytes are required to take a decision
+/// * the header and the number of bytes it uses in the buffer
+///
+/// # Errors
+///
+/// * the buffer does not start with a PROXY protocol header
+/// * the header is malformed
+pub fn parse(buffer: &[u8]) -> Result<Option<(ProxyHeader, usize)>, Error> {
+    if is_prefix_of(buffer, V1_PREFIX) {
+        if buffer.len() < V1_PREFIX.len() {
+            return Ok(None);
+        }
+        parse_v1(buffer)
+    } else if is_prefix_of(buffer, V2_SIGNATURE) {
+        parse_v2(buffer)
+    } else {
+        Err(Error::invalid_proxy_header("missing signature"))
+    }
+}
+
+fn parse_v1(buffer: &[u8]) -> Result<Option<(ProxyHeader, usize)>, Error> {
+    let window = &buffer[..buffer.len().min(V1_MAX_LEN)];
+    let Some(end) = window.windows(2).position(|w| w == b"\r\n") else {
+        return if buffer.len() >= V1_MAX_LEN {
+            Err(Error::invalid_proxy_header("v1 header too long"))
+        } else {
+            Ok(None)
+        };
+    };
+
+    let line = std::str::from_utf8(&buffer[..end])?;
+    let mut fields = line.split(' ').skip(1);
+
+    let header = match fields.next() {
+        Some("UNKNOWN") => ProxyHeader::Local,
+        Some(protocol @ ("TCP4" | "TCP6")) => {
+            let (Some(source), Some(destination), Some(source_port), Some(destination_port), None) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                return Err(Error::invalid_proxy_header("v1 wrong number of fields"));
+            };
+
+            let parse_ip = |ip: &str| -> Result<std::net::IpAddr, Error> {
+                let ip = if protocol == "TCP4" {
+                    ip.parse::<std::net::Ipv4Addr>().map(std::net::IpAddr::V4)
+                } else {
+                    ip.parse::<std::net::Ipv6Addr>().map(std::net::IpAddr::V6)
+                };
+                ip.map_err(|_e| Error::invalid_proxy_header("v1 invalid address"))
+            };
+            let parse_port = |port: &str| -> Result<u16, Error> {
+                port.parse::<u16>()
+                    .map_err(|_e| Error::invalid_proxy_header("v1 invalid port"))
+            };
+
+            let source = std::net::SocketAddr::new(parse_ip(source)?, parse_port(source_port)?);
+            let _destination = (parse_ip(destination)?, parse_port(destination_port)?);
+
+            ProxyHeader::Proxied { source }
+        }
+        _ => return Err(Error::invalid_proxy_header("v1 unknown protocol")),
+    };
+
+    Ok(Some((header, end + 2)))
+}
+
+fn parse_v2(buffer: &[u8]) -> Result<Option<(ProxyHeader, usize)>, Error> {
+    const NIBBLE: u8 = 4;
+
+    if buffer.len() < V2_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let (version, command) = (buffer[12] >> NIBBLE, buffer[12] & 0x0F);
+    let (family, transport) = (buffer[13] >> NIBBLE, buffer[13] & 0x0F);
+    let len = usize::from(u16::from_be_bytes([buffer[14], buffer[15]]));
+
+    if version != 2 {
+        return Err(Error::invalid_proxy_header("v2 unsupported version"));
+    }
+    if buffer.len() < V2_HEADER_LEN + len {
+        return Ok(None);
+    }
+    let addresses = &buffer[V2_HEADER_LEN..V2_HEADER_LEN + len];
+
+    let header = match (command, family) {
+        // LOCAL command, or PROXY command with an unspecified family / unix socket.
+        (0x0, _) | (0x1, 0x0 | 0x3) => ProxyHeader::Local,
+        (0x1, 0x1 | 0x2) if transport != 0x1 => {
+            return Err(Error::invalid_proxy_header("v2 transport is not a stream"))
+        }
+        (0x1, 0x1) if addresses.len() >= 12 => {
+            let mut ip = [0; 4];
+            ip.copy_from_slice(&addresses[..4]);
+            ProxyHeader::Proxied {
+                source: std::net::SocketAddr::new(
+                    std::net::Ipv4Addr::from(ip).into(),
+                    u16::from_be_bytes([addresses[8], addresses[9]]),
+                ),
+            }
+        }
+        (0x1, 0x2) if addresses.len() >= 36 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&addresses[..16]);
+            ProxyHeader::Proxied {
+                source: std::net::SocketAddr::new(
+                    std::net::Ipv6Addr::from(ip).into(),
+                    u16::from_be_bytes([addresses[32], addresses[33]]),
+                ),
+            }
+        }
+        (0x1, 0x1 | 0x2) => return Err(Error::invalid_proxy_header("v2 addresses truncated")),
+        (0x1, _) => return Err(Error::invalid_proxy_header("v2 unknown family")),
+        _ => return Err(Error::invalid_proxy_header("v2 unknown command")),
+    };
+
+    Ok(Some((header, V2_HEADER_LEN + len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ProxyHeader};
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v1_tcp4() {
+        let input = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 25\r\nEHLO foo\r\n";
+        let (header, len) = parse(input).unwrap().unwrap();
+        assert_eq!(
+            header,
+            ProxyHeader::Proxied {
+                source: "192.0.2.1:56324".parse().unwrap()
+            }
+        );
+        assert_eq!(&input[len..], b"EHLO foo\r\n");
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v1_tcp6() {
+        let input = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 25\r\n";
+        assert_eq!(
+            parse(input).unwrap().unwrap(),
+            (
+                ProxyHeader::Proxied {
+                    source: "[2001:db8::1]:56324".parse().unwrap()
+                },
+                input.len()
+            )
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v1_unknown() {
+        let input = b"PROXY UNKNOWN\r\n";
+        assert_eq!(
+            parse(input).unwrap().unwrap(),
+            (ProxyHeader::Local, input.len())
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v1_incomplete() {
+        assert!(parse(b"").unwrap().is_none());
+        assert!(parse(b"PRO").unwrap().is_none());
+        assert!(parse(b"PROXY TCP4 192.0.2.1").unwrap().is_none());
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v1_malformed() {
+        parse(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n").unwrap_err();
+        parse(b"PROXY TCP4 2001:db8::1 198.51.100.1 56324 25\r\n").unwrap_err();
+        parse(b"PROXY TCP4 192.0.2.1 198.51.100.1 99999 25\r\n").unwrap_err();
+        parse(b"PROXY UDP4 192.0.2.1 198.51.100.1 56324 25\r\n").unwrap_err();
+        parse(&[b"PROXY ".as_slice(), &[b'a'; 200]].concat()).unwrap_err();
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn not_proxy() {
+        parse(b"EHLO foo\r\n").unwrap_err();
+        parse(b"\r\n\r\nfoo").unwrap_err();
+    }
+
+    fn v2(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let len = u16::try_from(addresses.len()).unwrap_or_default();
+        [
+            super::V2_SIGNATURE,
+            &[0x20 | command, family],
+            &len.to_be_bytes(),
+            addresses,
+        ]
+        .concat()
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v2_tcp4() {
+        let input = v2(
+            0x1,
+            0x11,
+            &[192, 0, 2, 1, 198, 51, 100, 1, 0xDC, 0x04, 0, 25],
+        );
+        assert_eq!(
+            parse(&input).unwrap().unwrap(),
+            (
+                ProxyHeader::Proxied {
+                    source: "192.0.2.1:56324".parse().unwrap()
+                },
+                input.len()
+            )
+        );
+        assert!(parse(&input[..input.len() - 1]).unwrap().is_none());
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v2_tcp6() {
+        let src = "2001:db8::1"
+            .parse::<std::net::Ipv6Addr>()
+            .unwrap()
+            .octets();
+        let dst = "2001:db8::2"
+            .parse::<std::net::Ipv6Addr>()
+            .unwrap()
+            .octets();
+        let input = v2(
+            0x1,
+            0x21,
+            &[&src, &dst, [0xDC, 0x04, 0, 25].as_slice()].concat(),
+        );
+        assert_eq!(
+            parse(&input).unwrap().unwrap(),
+            (
+                ProxyHeader::Proxied {
+                    source: "[2001:db8::1]:56324".parse().unwrap()
+                },
+                input.len()
+            )
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v2_local() {
+        let input = v2(0x0, 0x00, &[]);
+        assert_eq!(
+            parse(&input).unwrap().unwrap(),
+            (ProxyHeader::Local, input.len())
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[test]
+    fn v2_malformed() {
+        parse(&v2(0x1, 0x11, &[192, 0, 2, 1])).unwrap_err();
+        parse(&v2(0x1, 0x12, &[0; 12])).unwrap_err();
+        parse(&v2(0x2, 0x11, &[0; 12])).unwrap_err();
+        let mut bad_version = v2(0x1, 0x11, &[0; 12]);
+        bad_version[12] = 0x11;
+        parse(&bad_version).unwrap_err();
+    }
+}
@@ -15,7 +15,12 @@
  *
 */
 
-use crate::{command::Batch, command::Command, Error, UnparsedArgs, Verb};
+use crate::{
+    command::Batch,
+    command::Command,
+    proxy_protocol::{self, ProxyHeader},
+    Error, UnparsedArgs, Verb,
+};
 use tokio::io::AsyncReadExt;
 use tokio_stream::StreamExt;
 use vsmtp_common::Reply;
@@ -26,6 +31,33 @@ use vsmtp_common::Reply;
 /// - SMTPUTF8 (+10 characters)
 const MAX_LINE_SIZE: usize = 1024;
 
+/// Handling of the lines of a message longer than the limit,
+/// see <https://www.rfc-editor.org/rfc/rfc5321#section-4.5.3.1.6>.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Hash,
+    strum::Display,
+    strum::EnumString,
+    serde_with::DeserializeFromStr,
+    serde_with::SerializeDisplay,
+)]
+#[strum(serialize_all = "lowercase")]
+#[non_exhaustive]
+pub enum LineLengthPolicy {
+    /// The message is read until the end, then rejected with a `500` reply.
+    Strict,
+    /// The line is split in several lines of the maximum length.
+    Wrap,
+    /// The line is accepted as is, a warning is logged.
+    #[default]
+    Log,
+}
+
 fn find(bytes: &[u8], search: &[u8]) -> Option<usize> {
     bytes
         .windows(search.len())
@@ -77,14 +109,17 @@ where
             if !self.buffer.is_empty() {
                 self.n = self.buffer.len();
             }
+            let mut yielded = false;
             loop {
                 if let Some(pos) = find(&self.buffer[..self.n], b"\r\n") {
                     let out = self.buffer.split_to(pos + 2);
                     self.n -= out.len();
+                    yielded = true;
                     yield Vec::<u8>::from(out);
-                    if self.buffer.is_empty() {
-                        return;
-                    }
+                } else if yielded {
+                    // NOTE: the commands already received are answered before
+                    // waiting for the end of the window.
+                    return;
                 } else {
                     self.buffer.reserve(self.additional_reserve);
                     let read_size = self.inner.read_buf(self.buffer).await?;
@@ -120,13 +155,58 @@ impl<R: tokio::io::AsyncRead + Unpin + Send> Reader<R> {
     }
 
     /// Consume the instance and return the underlying reader.
+    ///
+    /// The bytes received but not consumed yet are discarded.
     #[must_use]
     #[inline]
-    #[allow(clippy::missing_const_for_fn)]
     pub fn into_inner(self) -> R {
+        if !self.buffer.is_empty() {
+            tracing::warn!(
+                len = self.buffer.len(),
+                "Discarding bytes received before releasing the stream."
+            );
+        }
         self.inner
     }
 
+    /// Read the PROXY protocol header at the beginning of the stream.
+    /// The bytes received after the header are kept for the next reads.
+    ///
+    /// # Errors
+    ///
+    /// * the stream does not start with a valid PROXY protocol header
+    /// * [`std::io::Error`] produced by the underlying reader
+    pub(crate) async fn read_proxy_header(&mut self) -> Result<ProxyHeader, Error> {
+        loop {
+            if let Some((header, len)) = proxy_protocol::parse(&self.buffer)? {
+                let _header = self.buffer.split_to(len);
+                return Ok(header);
+            }
+
+            self.buffer.reserve(self.additional_reserve);
+            if self.inner.read_buf(&mut self.buffer).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+    }
+
+    /// Wait for the client to send data, the bytes received are kept for the next reads.
+    ///
+    /// Return `false` if the client closed the connection.
+    ///
+    /// # Errors
+    ///
+    /// * [`std::io::Error`] produced by the underlying reader
+    pub(crate) async fn wait_data(&mut self) -> std::io::Result<bool> {
+        if !self.buffer.is_empty() {
+            return Ok(true);
+        }
+
+        // NOTE: `read_buf` is cancel safe, nothing is lost if the caller gives up waiting.
+        self.buffer.reserve(self.additional_reserve);
+        Ok(self.inner.read_buf(&mut self.buffer).await? != 0)
+    }
+
     // instantiate a new ReaderWindow object from an existing reader
     #[allow(clippy::wrong_self_convention)]
     fn to_window_reader(&mut self) -> ReaderWindow<'_, R> {
@@ -153,8 +233,13 @@ impl<R: tokio::io::AsyncRead + Unpin + Send> Reader<R> {
                 let window_content = window_reader.flush_window();
                 tokio::pin!(window_content);
                 while let Some(cmd) = window_content.next().await {
-                    batch.push(parse_command_line(&cmd?));
-                    if !pipelined {
+                    let command = parse_command_line(&cmd?);
+                    // NOTE: the bytes following a synchronization point (the message after a DATA,
+                    // the SASL exchange after an AUTH ...) are left in the buffer.
+                    let synchronize = !pipelined
+                        || matches!(command, Ok((verb, _)) if !verb.is_bufferable());
+                    batch.push(command);
+                    if synchronize {
                         break;
                     }
                 }
@@ -171,7 +256,7 @@ impl<R: tokio::io::AsyncRead + Unpin + Send> Reader<R> {
         &mut self,
     ) -> impl tokio_stream::Stream<Item = std::io::Result<Vec<u8>>> + '_ {
         async_stream::try_stream! {
-            let mut n = 0;
+            let mut n = self.buffer.len();
 
             loop {
                 if let Some(pos) = find(&self.buffer[..n], b"\r\n") {
@@ -195,30 +280,69 @@ impl<R: tokio::io::AsyncRead + Unpin + Send> Reader<R> {
     }
 
     /// Produce a stream of lines to generate IMF compliant messages.
+    ///
+    /// The length of a line includes the "\r\n", but not the dot added for transparency,
+    /// the lines longer than `line_length_max` are handled according to `line_length_policy`.
     #[inline]
     pub fn as_message_stream(
         &mut self,
         size_limit: usize,
+        line_length_max: usize,
+        line_length_policy: LineLengthPolicy,
     ) -> impl tokio_stream::Stream<Item = Result<Vec<u8>, Error>> + '_ {
         async_stream::stream! {
             let mut size = 0;
+            let mut line_too_long = None;
 
             for await line in self.as_line_stream() {
                 let mut line = line?;
                 tracing::trace!("<< {:?}", std::str::from_utf8(&line));
 
                 if line == b".\r\n" {
+                    // NOTE: the message is read until the end, so that the rest of
+                    // the body is not interpreted as commands.
+                    if size >= size_limit {
+                        yield Err(Error::buffer_too_long(size_limit, size));
+                    } else if let Some(got) = line_too_long {
+                        yield Err(Error::line_too_long(line_length_max, got));
+                    }
                     return;
                 }
                 if line.first() == Some(&b'.') {
                     line = line[1..].to_vec();
                 }
 
-                // TODO: handle line length max ?
                 size += line.len();
-                if size >= size_limit {
-                    yield Err(Error::buffer_too_long(size_limit, size));
-                    return;
+                if size >= size_limit || line_too_long.is_some() {
+                    continue;
+                }
+
+                if line.len() > line_length_max {
+                    match line_length_policy {
+                        LineLengthPolicy::Strict => {
+                            line_too_long = Some(line.len());
+                            continue;
+                        }
+                        LineLengthPolicy::Wrap => {
+                            tracing::warn!(
+                                len = line.len(),
+                                max = line_length_max,
+                                "Wrapping a line too long."
+                            );
+                            let content = &line[..line.len() - 2];
+                            for chunk in content.chunks(line_length_max.saturating_sub(2).max(1)) {
+                                yield Ok([chunk, b"\r\n".as_slice()].concat());
+                            }
+                            continue;
+                        }
+                        LineLengthPolicy::Log => {
+                            tracing::warn!(
+                                len = line.len(),
+                                max = line_length_max,
+                                "Received a line too long."
+                            );
+                        }
+                    }
                 }
 
                 yield Ok(line);
@@ -265,6 +389,7 @@ impl<R: tokio::io::AsyncRead + Unpin + Send> Reader<R> {
 
 #[cfg(test)]
 mod tests {
+    use tokio::io::AsyncWriteExt;
     use tokio_stream::StreamExt;
 
     use crate::{
@@ -424,6 +549,90 @@ mod tests {
         assert_cmd_batch(&output, &expected);
     }
 
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn window_stream_stops_at_data() {
+        let input = [
+            "MAIL FROM:<mrose@dbc.mtview.ca.us>\r\n",
+            "RCPT TO:<ned@innosoft.com>\r\n",
+            "RCPT TO:<dan@innosoft.com>\r\n",
+            "DATA\r\n",
+            "Subject: pipelined\r\n",
+            "\r\n",
+            ".\r\n",
+        ]
+        .concat();
+
+        let cursor = std::io::Cursor::new(input);
+        let mut reader = super::Reader::new(cursor, true);
+        let verbs = {
+            let stream = reader.as_window_stream();
+            tokio::pin!(stream);
+            let output = stream.try_next().await.unwrap().unwrap();
+            output
+                .into_iter()
+                .map(|cmd| cmd.unwrap().0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            verbs,
+            [
+                command::Verb::MailFrom,
+                command::Verb::RcptTo,
+                command::Verb::RcptTo,
+                command::Verb::Data
+            ]
+        );
+
+        let message = reader
+            .as_message_stream(1000, 1000, super::LineLengthPolicy::Strict)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            message,
+            [b"Subject: pipelined\r\n".to_vec(), b"\r\n".to_vec()]
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn window_stream_partial_line() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut reader = super::Reader::new(server, true);
+        let stream = reader
+            .as_window_stream()
+            .timeout(std::time::Duration::from_secs(1));
+        tokio::pin!(stream);
+
+        client
+            .write_all(b"MAIL FROM:<mrose@dbc.mtview.ca.us>\r\nRCPT TO:<ned@")
+            .await
+            .unwrap();
+        let output = stream.try_next().await.unwrap().unwrap().unwrap();
+        let expected = vec![std::result::Result::<
+            (command::Verb, command::UnparsedArgs),
+            Error,
+        >::Ok((
+            command::Verb::MailFrom,
+            command::UnparsedArgs(b"<mrose@dbc.mtview.ca.us>\r\n".to_vec()),
+        ))];
+        assert_eq!(output.len(), expected.len());
+        assert_cmd_batch(&output, &expected);
+
+        client.write_all(b"innosoft.com>\r\n").await.unwrap();
+        let output = stream.try_next().await.unwrap().unwrap().unwrap();
+        let expected = vec![std::result::Result::<
+            (command::Verb, command::UnparsedArgs),
+            Error,
+        >::Ok((
+            command::Verb::RcptTo,
+            command::UnparsedArgs(b"<ned@innosoft.com>\r\n".to_vec()),
+        ))];
+        assert_eq!(output.len(), expected.len());
+        assert_cmd_batch(&output, &expected);
+    }
+
     #[allow(clippy::unwrap_used)]
     #[tokio::test]
     async fn window_stream_no_lines() {
@@ -437,4 +646,58 @@ mod tests {
         let output = stream.try_next().await.unwrap().unwrap().unwrap();
         assert!(output.is_empty());
     }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn message_stream_line_length_strict() {
+        // NOTE: 1000 octets with the CRLF once the leading dot is removed.
+        let stuffed = format!("..{}\r\n", "a".repeat(997));
+        let input = [stuffed.as_str(), "foo\r\n", ".\r\n"].concat();
+
+        let mut reader = super::Reader::new(std::io::Cursor::new(input), true);
+        let message = reader
+            .as_message_stream(100_000, 1000, super::LineLengthPolicy::Strict)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            message,
+            [stuffed[1..].as_bytes().to_vec(), b"foo\r\n".to_vec()]
+        );
+
+        let input = ["a".repeat(999), "\r\n.\r\nQUIT\r\n".to_owned()].concat();
+        let mut reader = super::Reader::new(std::io::Cursor::new(input), true);
+        let message = reader
+            .as_message_stream(100_000, 1000, super::LineLengthPolicy::Strict)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(message.len(), 1);
+        assert_eq!(
+            message[0].as_ref().unwrap_err().to_string(),
+            "smtp protocol error: InvalidInput: line is not supposed to be longer than 1000 bytes but got 1001"
+        );
+        let lines = reader.as_line_stream();
+        tokio::pin!(lines);
+        assert_eq!(
+            lines.try_next().await.unwrap(),
+            Some(b"QUIT\r\n".to_vec())
+        );
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn message_stream_line_length_wrap() {
+        let input = "abcdefgh\r\n.\r\n";
+
+        let mut reader = super::Reader::new(std::io::Cursor::new(input), true);
+        let message = reader
+            .as_message_stream(100_000, 5, super::LineLengthPolicy::Wrap)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            message,
+            [b"abc\r\n".to_vec(), b"def\r\n".to_vec(), b"gh\r\n".to_vec()]
+        );
+    }
 }
@@ -15,12 +15,23 @@
  *
 */
 use crate::{
-    reader::Reader, writer::WindowWriter, AcceptArgs, AuthArgs, ConnectionKind, EhloArgs, Error,
-    HeloArgs, MailFromArgs, RcptToArgs, ReceiverHandler, Verb,
+    command::{parse_optional_string, Batch},
+    proxy_protocol::ProxyHeader,
+    reader::{LineLengthPolicy, Reader},
+    writer::WindowWriter,
+    AcceptArgs, AuthArgs, ConnectionKind, EhloArgs, Error, ExpnArgs, HeloArgs, HeloCheck, HelpArgs,
+    MailFromArgs, Prescan, RateLimit, RcptToArgs, ReceiverHandler, Verb, VrfyArgs, XClientArgs,
 };
 use tokio_rustls::rustls;
 use tokio_stream::StreamExt;
-use vsmtp_common::{auth::Mechanism, Reply, Stage};
+use tracing::Instrument;
+use vsmtp_common::{auth::Mechanism, Address, Reply, ReplyCode, Stage};
+
+/// Delay allowed to the load balancer to send the PROXY protocol header.
+const PROXY_HEADER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Maximum length of a line of text, "\r\n" included, see <https://www.rfc-editor.org/rfc/rfc5321#section-4.5.3.1.6>.
+const LINE_LENGTH_MAX: usize = 1000;
 
 enum HandshakeOutcome {
     Message,
@@ -35,6 +46,107 @@ enum HandshakeOutcome {
     Quit,
 }
 
+/// Wait for the deadline of the session, forever if there is none.
+async fn wait_deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Give the lines of `message` to `prescan`, once it produces a reply the rest of the message
+/// is read and discarded, the reply is stored in `aborted` and the stream ends with an error.
+fn prescan_message<'a>(
+    message: impl tokio_stream::Stream<Item = Result<Vec<u8>, Error>> + Send + 'a,
+    prescan: Option<&'a dyn Prescan>,
+    aborted: &'a mut Option<Reply>,
+) -> impl tokio_stream::Stream<Item = Result<Vec<u8>, Error>> + Send + 'a {
+    async_stream::stream! {
+        tokio::pin!(message);
+        let mut size = 0;
+
+        while let Some(line) = message.next().await {
+            if let (Ok(line), Some(prescan)) = (&line, prescan) {
+                size += line.len();
+                if let Some(reply) = prescan.scan(size, line) {
+                    tracing::info!(
+                        size,
+                        reply = reply.as_ref().trim_end(),
+                        "Message aborted by the pre-scan."
+                    );
+                    // NOTE: the message is read until the end, so that the rest of
+                    // the body is not interpreted as commands.
+                    while message.next().await.is_some() {}
+                    *aborted = Some(reply);
+                    yield Err(Error::message_aborted());
+                    return;
+                }
+            }
+            yield line;
+        }
+    }
+}
+
+/// Wait until the server is shutting down, forever if the session is not drained.
+async fn wait_shutdown(shutdown: &mut Option<tokio::sync::watch::Receiver<bool>>) {
+    let Some(shutdown) = shutdown else {
+        return std::future::pending().await;
+    };
+    while !*shutdown.borrow_and_update() {
+        if shutdown.changed().await.is_err() {
+            // NOTE: the server has been dropped without shutting down.
+            return std::future::pending().await;
+        }
+    }
+}
+
+/// Wait `delay` before replying to a tarpitted client, aborted if the client closes the connection.
+///
+/// The commands sent by the client in the meantime are kept in `pipelined`.
+///
+/// Return `false` if the client closed the connection.
+#[allow(clippy::future_not_send)]
+async fn wait_tarpit<S>(
+    mut command_stream: std::pin::Pin<&mut S>,
+    pipelined: &mut Option<Batch>,
+    delay: std::time::Duration,
+) -> bool
+where
+    S: tokio_stream::Stream<Item = Result<std::io::Result<Batch>, tokio_stream::Elapsed>>,
+{
+    let sleep = tokio::time::sleep(delay);
+    tokio::pin!(sleep);
+    loop {
+        // NOTE: the bytes following a batch (the message after a DATA ...) are not commands,
+        // once the client has sent one, the closing of the connection cannot be detected.
+        if pipelined.is_some() {
+            sleep.await;
+            return true;
+        }
+        tokio::select! {
+            () = &mut sleep => return true,
+            next = command_stream.next() => match next {
+                Some(Ok(Ok(batch))) if !batch.is_empty() => *pipelined = Some(batch),
+                // NOTE: the client is still connected, but has not sent anything for a while.
+                Some(Err(_elapsed)) => {}
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Enhanced status code of a reply without one, depending on the command it answers.
+const fn enhanced_code(code: u16, verb: Option<Verb>) -> Option<&'static str> {
+    match (verb, code) {
+        (Some(Verb::MailFrom), 250) => Some("2.1.0"),
+        (Some(Verb::MailFrom), 553) => Some("5.1.7"),
+        (Some(Verb::RcptTo), 250) => Some("2.1.5"),
+        (Some(Verb::RcptTo), 452) => Some("4.5.3"),
+        (Some(Verb::RcptTo), 550) => Some("5.1.1"),
+        _ => ReplyCode::default_details(code),
+    }
+}
+
 pub struct ErrorCounter {
     pub error_count: i64,
     pub threshold_soft_error: i64,
@@ -46,15 +158,79 @@ pub struct ErrorCounter {
 #[derive(Default)]
 pub struct ReceiverContext {
     outcome: Option<HandshakeOutcome>,
+    message_size_max: usize,
+    greeting_delay: Option<std::time::Duration>,
+    session_timeout: Option<std::time::Duration>,
+    tarpit: Option<std::time::Duration>,
+    // NOTE: `None` if the extension is disabled, `Some(true)` once the client greeted with `EHLO`.
+    enhanced_status_codes: Option<bool>,
 }
 
 impl ReceiverContext {
+    /// Maximum size in bytes of a message accepted by the [`Receiver`].
+    ///
+    /// A message exceeding this size is rejected with a `552` reply after the `DATA` command.
+    #[inline]
+    #[must_use]
+    pub const fn message_size_max(&self) -> usize {
+        self.message_size_max
+    }
+
     /// Make the [`Receiver`] quit the connection early, and close cleanly.
     #[inline]
     pub fn deny(&mut self) {
         self.outcome = Some(HandshakeOutcome::Quit);
     }
 
+    /// Make the [`Receiver`] wait before sending the banner, the client sending data
+    /// in the meantime is reported with [`ReceiverHandler::on_early_talker()`].
+    #[inline]
+    pub fn delay_greeting(&mut self, delay: std::time::Duration) {
+        self.greeting_delay = Some(delay);
+    }
+
+    /// Make the [`Receiver`] close the connection with a `421` reply once `timeout`
+    /// has elapsed since the connection was accepted, whatever the activity of the client.
+    ///
+    /// The deadline is checked while waiting for the next command, the command or
+    /// the message being processed is completed first.
+    #[inline]
+    pub fn limit_session(&mut self, timeout: std::time::Duration) {
+        self.session_timeout = Some(timeout);
+    }
+
+    /// Make the [`Receiver`] wait `delay` before sending the reply of the current command.
+    ///
+    /// The wait is aborted, and the session closed, if the client closes the connection.
+    #[inline]
+    pub fn tarpit(&mut self, delay: std::time::Duration) {
+        self.tarpit = Some(delay);
+    }
+
+    /// Add the enhanced status code of the reply to `verb` if the client greeted with `EHLO`,
+    /// and remove it otherwise. The reply is untouched if the extension is disabled.
+    ///
+    /// `verb` is `None` for the replies which do not follow a command (banner, end of message...).
+    pub(crate) fn enhance_reply(&mut self, reply: Reply, verb: Option<Verb>) -> Reply {
+        let Some(esmtp) = self.enhanced_status_codes.as_mut() else {
+            return reply;
+        };
+        match verb {
+            // NOTE: the replies to the greeting have no enhanced status code (rfc 2034 #4).
+            Some(verb @ (Verb::Helo | Verb::Ehlo | Verb::Lhlo)) => {
+                if !reply.code().is_error() {
+                    *esmtp = verb != Verb::Helo;
+                }
+                reply.without_enhanced_code()
+            }
+            _ if !*esmtp => reply.without_enhanced_code(),
+            _ => match enhanced_code(reply.code().value(), verb) {
+                Some(enhanced) => reply.with_enhanced_code(enhanced),
+                None => reply,
+            },
+        }
+    }
+
     /// Make the [`Receiver`] initialize a TLS handshake.
     #[inline]
     pub fn upgrade_tls(
@@ -93,7 +269,19 @@ pub struct Receiver<
     context: ReceiverContext,
     kind: ConnectionKind,
     message_size_max: usize,
+    line_length_max: usize,
+    line_length_policy: LineLengthPolicy,
+    prescan: Option<Box<dyn Prescan>>,
     support_pipelining: bool,
+    // NOTE: only used on LMTP connection, to reply for each accepted recipient after the message.
+    lmtp_recipients: Vec<Address>,
+    // NOTE: given to the handler to throttle the recipients, updated by the PROXY header and XCLIENT.
+    client_addr: std::net::SocketAddr,
+    reverse_path: Option<Address>,
+    // NOTE: kept across the TLS upgrade, the whole session is bounded.
+    session_deadline: Option<tokio::time::Instant>,
+    // NOTE: set to `true` by the server when it stops accepting connections.
+    shutdown: Option<tokio::sync::watch::Receiver<bool>>,
     v: std::marker::PhantomData<V>,
     h: std::marker::PhantomData<H>,
 }
@@ -156,11 +344,27 @@ where
             let secured_receiver = Receiver {
                 sink,
                 stream,
-                context: ReceiverContext { outcome: None },
+                context: ReceiverContext {
+                    outcome: None,
+                    message_size_max: self.message_size_max,
+                    greeting_delay: None,
+                    session_timeout: None,
+                    tarpit: None,
+                    // NOTE: the client greets again on the secured session.
+                    enhanced_status_codes: self.context.enhanced_status_codes.map(|_| false),
+                },
                 error_counter: self.error_counter,
                 kind: self.kind,
                 message_size_max: self.message_size_max,
+                line_length_max: self.line_length_max,
+                line_length_policy: self.line_length_policy,
+                prescan: self.prescan,
                 support_pipelining: self.support_pipelining,
+                lmtp_recipients: self.lmtp_recipients,
+                client_addr: self.client_addr,
+                reverse_path: None,
+                session_deadline: self.session_deadline,
+                shutdown: self.shutdown,
                 v: self.v,
                 h: self.h,
             }.into_secured_stream(
@@ -188,6 +392,10 @@ where
         message_size_max: usize,
         support_pipelining: bool,
     ) -> Self {
+        // NOTE: replaced by the address given to `into_stream`.
+        let client_addr = tcp_stream
+            .peer_addr()
+            .unwrap_or_else(|_| std::net::SocketAddr::from(([0, 0, 0, 0], 0)));
         let (read, write) = tcp_stream.into_split();
         let (stream, sink) = (
             Reader::new(read, support_pipelining),
@@ -201,14 +409,67 @@ where
                 threshold_soft_error,
                 threshold_hard_error,
             },
-            context: ReceiverContext { outcome: None },
+            context: ReceiverContext {
+                outcome: None,
+                message_size_max,
+                greeting_delay: None,
+                session_timeout: None,
+                tarpit: None,
+                enhanced_status_codes: None,
+            },
             kind,
             message_size_max,
+            line_length_max: LINE_LENGTH_MAX,
+            line_length_policy: LineLengthPolicy::default(),
+            prescan: None,
             support_pipelining,
+            lmtp_recipients: vec![],
+            client_addr,
+            reverse_path: None,
+            session_deadline: None,
+            shutdown: None,
             v: std::marker::PhantomData,
             h: std::marker::PhantomData,
         }
     }
+
+    /// Drain the session when `shutdown` is set to `true`: the transaction in progress
+    /// is completed, then the client receives a `421` reply and is disconnected.
+    #[inline]
+    #[must_use]
+    pub fn with_shutdown(mut self, shutdown: tokio::sync::watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Prefix the replies with an enhanced status code (rfc 2034) once the client greeted
+    /// with `EHLO`, and remove the enhanced status codes of the replies otherwise.
+    #[inline]
+    #[must_use]
+    pub fn with_enhanced_status_codes(mut self, enabled: bool) -> Self {
+        self.context.enhanced_status_codes = enabled.then_some(false);
+        self
+    }
+
+    /// Limit the length of the lines of the messages, "\r\n" included
+    /// (1000 octets by default, and warn about the longer lines).
+    #[inline]
+    #[must_use]
+    pub const fn with_line_length(mut self, max: usize, policy: LineLengthPolicy) -> Self {
+        self.line_length_max = max;
+        self.line_length_policy = policy;
+        self
+    }
+
+    /// Scan the messages while they are received, a message is aborted as soon as `prescan`
+    /// produces a reply. Without it, the message is fully received before being handled.
+    #[inline]
+    #[must_use]
+    pub fn with_prescan(mut self, prescan: Option<impl Prescan + 'static>) -> Self {
+        self.prescan = prescan.map(|prescan| Box::new(prescan) as Box<dyn Prescan>);
+        self
+    }
+
     /// Handle the inner stream to produce a [`tokio_stream::Stream`], each item
     /// being a successful SMTP transaction.
     ///
@@ -238,7 +499,7 @@ where
             })
     }
 
-    #[allow(clippy::panic)]
+    #[allow(clippy::panic, clippy::too_many_lines)]
     fn into_stream_with_error<Fun, Future>(
         mut self,
         on_accept: Fun,
@@ -252,6 +513,29 @@ where
         Future: std::future::Future<Output = (H, ReceiverContext, Option<Reply>)>,
     {
         async_stream::try_stream! {
+            // NOTE: a malformed or missing header close the connection without any reply,
+            // the peer is not a SMTP client we can talk to.
+            let client_addr = if self.kind == ConnectionKind::Proxied {
+                match tokio::time::timeout(
+                    PROXY_HEADER_TIMEOUT,
+                    self.stream.read_proxy_header()
+                ).await {
+                    Ok(Ok(ProxyHeader::Proxied { source })) => source,
+                    Ok(Ok(ProxyHeader::Local)) => client_addr,
+                    Ok(Err(e)) => {
+                        Err(e)?;
+                        return;
+                    }
+                    Err(_elapsed) => {
+                        Err(Error::timeout(PROXY_HEADER_TIMEOUT, "no PROXY protocol header received"))?;
+                        return;
+                    }
+                }
+            } else {
+                client_addr
+            };
+            self.client_addr = client_addr;
+
             let accepted = on_accept(
                 AcceptArgs {
                     client_addr,
@@ -261,8 +545,18 @@ where
                     uuid,
                 }
             ).await;
+            self.session_deadline = accepted
+                .1
+                .session_timeout
+                .map(|timeout| tokio::time::Instant::now() + timeout);
+
             let mut handler = match accepted {
-                (mut handler, ReceiverContext{ outcome: None }, Some(reply_accept)) => {
+                (mut handler, ReceiverContext{ outcome: None, greeting_delay, .. }, Some(reply_accept)) => {
+                    if let Some(delay) = greeting_delay {
+                        if !self.wait_greeting_delay(&mut handler, delay).await? {
+                            return;
+                        }
+                    }
                     self.sink
                         .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply_accept)
                         .await?;
@@ -273,13 +567,14 @@ where
                         config,
                         handshake_timeout
                     }),
+                    ..
                 }, None) => {
                     for await i in self.upgrade_tls(handler, config, handshake_timeout) {
                         yield i?;
                     }
                     return;
                 }
-                (mut handler, ReceiverContext{ outcome: Some(HandshakeOutcome::Quit) }, reply_accept) => {
+                (mut handler, ReceiverContext{ outcome: Some(HandshakeOutcome::Quit), .. }, reply_accept) => {
                     if let Some(reply_accept) = reply_accept {
                         self.sink
                             .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply_accept)
@@ -293,22 +588,7 @@ where
             loop {
                 match self.smtp_handshake(&mut handler).await? {
                     HandshakeOutcome::Message => {
-                        let message_stream = self.stream.as_message_stream(self.message_size_max).fuse();
-                        tokio::pin!(message_stream);
-
-                        let (mut reply, completed) = handler.on_message(&mut self.context, message_stream).await;
-                        if let Some(completed) = completed {
-                            for item in completed {
-                                if let Some(error) = handler.on_message_completed(item).await {
-                                    reply = error;
-                                    break;
-                                }
-                            }
-                        }
-                        self.sink
-                            .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply)
-                            .await?;
-
+                        self.handle_message(&mut handler).await?;
                         yield ();
                     },
                     HandshakeOutcome::UpgradeTLS { config, handshake_timeout } => {
@@ -326,7 +606,7 @@ where
                             .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply)
                             .await?;
 
-                        if matches!(std::mem::take(&mut self.context).outcome, Some(HandshakeOutcome::Quit)) {
+                        if matches!(self.context.outcome.take(), Some(HandshakeOutcome::Quit)) {
                             return;
                         }
 
@@ -378,22 +658,7 @@ where
             loop {
                 match self.smtp_handshake(&mut handler).await? {
                     HandshakeOutcome::Message => {
-                        let message_stream = self.stream.as_message_stream(self.message_size_max).fuse();
-                        tokio::pin!(message_stream);
-
-                        let (mut reply, completed) = handler.on_message(&mut self.context, message_stream).await;
-                        if let Some(completed) = completed {
-                            for item in completed {
-                                if let Some(error) = handler.on_message_completed(item).await {
-                                    reply = error;
-                                    break;
-                                }
-                            }
-                        }
-                        self.sink
-                            .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply)
-                            .await?;
-
+                        self.handle_message(&mut handler).await?;
                         yield ();
                     },
                     HandshakeOutcome::UpgradeTLS { .. } => panic!("smtp_handshake should not return UpgradeTLS"),
@@ -406,7 +671,7 @@ where
                             .direct_send_reply(&mut self.context, &mut self.error_counter, &mut handler, reply)
                             .await?;
 
-                        if matches!(std::mem::take(&mut self.context).outcome, Some(HandshakeOutcome::Quit)) {
+                        if matches!(self.context.outcome.take(), Some(HandshakeOutcome::Quit)) {
                             return;
                         }
 
@@ -417,6 +682,183 @@ where
         }
     }
 
+    /// Send the `421` reply of a session exceeding its deadline, the connection is then closed.
+    #[allow(clippy::future_not_send)]
+    async fn close_expired_session(
+        sink: &mut WindowWriter<W>,
+        context: &mut ReceiverContext,
+        error_counter: &mut ErrorCounter,
+        handler: &mut T,
+    ) -> Result<(), Error> {
+        tracing::warn!("Closing the session, its time limit is exceeded");
+        #[allow(clippy::expect_used)]
+        sink.direct_send_reply(
+            context,
+            error_counter,
+            handler,
+            "421 4.4.2 Session time limit exceeded - closing connection\r\n"
+                .parse()
+                .expect("valid syntax"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Send the `421` reply of a session drained by the shutdown of the server.
+    #[allow(clippy::future_not_send)]
+    async fn close_drained_session(
+        sink: &mut WindowWriter<W>,
+        context: &mut ReceiverContext,
+        error_counter: &mut ErrorCounter,
+        handler: &mut T,
+    ) -> Result<(), Error> {
+        tracing::info!("Closing the session, the server is shutting down");
+        #[allow(clippy::expect_used)]
+        sink.direct_send_reply(
+            context,
+            error_counter,
+            handler,
+            "421 4.3.2 Service shutting down - closing connection\r\n"
+                .parse()
+                .expect("valid syntax"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Wait before sending the banner, and report the client sending data in the meantime.
+    ///
+    /// # Returns
+    ///
+    /// * `false` if the connection must be closed
+    #[allow(clippy::future_not_send)]
+    async fn wait_greeting_delay(
+        &mut self,
+        handler: &mut T,
+        delay: std::time::Duration,
+    ) -> Result<bool, Error> {
+        let deadline = tokio::time::Instant::now() + delay;
+        match tokio::time::timeout_at(deadline, self.stream.wait_data()).await {
+            Ok(Ok(true)) => {
+                if let Some(reply) = handler.on_early_talker().await {
+                    self.sink
+                        .direct_send_reply(
+                            &mut self.context,
+                            &mut self.error_counter,
+                            handler,
+                            reply,
+                        )
+                        .await?;
+                    return Ok(false);
+                }
+                tokio::time::sleep_until(deadline).await;
+                Ok(true)
+            }
+            // NOTE: the client left before the banner.
+            Ok(Ok(false)) => Ok(false),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_elapsed) => Ok(true),
+        }
+    }
+
+    /// Receive the message and send the reply of the transaction, or one reply per
+    /// accepted recipient on a LMTP connection.
+    #[allow(clippy::future_not_send)]
+    #[tracing::instrument(
+        name = "message",
+        skip_all,
+        fields(
+            message_uuid = tracing::field::Empty,
+            transaction_id = tracing::field::Empty,
+            connection_age_ms = tracing::field::Empty,
+            transaction_age_ms = tracing::field::Empty,
+        )
+    )]
+    async fn handle_message(&mut self, handler: &mut T) -> Result<(), Error> {
+        let span = tracing::Span::current();
+        if let Some(uuid) = handler.get_message_uuid() {
+            span.record("message_uuid", tracing::field::display(uuid));
+        }
+        if let Some(id) = handler.get_transaction_id() {
+            span.record("transaction_id", tracing::field::display(id));
+        }
+        let (connection_age, transaction_age) = handler.get_ages();
+        if let Some(age) = connection_age {
+            span.record(
+                "connection_age_ms",
+                u64::try_from(age.as_millis()).unwrap_or(u64::MAX),
+            );
+        }
+        if let Some(age) = transaction_age {
+            span.record(
+                "transaction_age_ms",
+                u64::try_from(age.as_millis()).unwrap_or(u64::MAX),
+            );
+        }
+
+        let mut aborted = None;
+        let (reply, completed) = {
+            let message_stream = prescan_message(
+                self.stream.as_message_stream(
+                    self.message_size_max,
+                    self.line_length_max,
+                    self.line_length_policy,
+                ),
+                self.prescan.as_deref(),
+                &mut aborted,
+            )
+            .fuse();
+            tokio::pin!(message_stream);
+
+            handler.on_message(&mut self.context, message_stream).await
+        };
+        // NOTE: the handler received an error instead of the end of the message.
+        let (mut reply, completed) = aborted.map_or((reply, completed), |reply| (reply, None));
+
+        if self.kind == ConnectionKind::Lmtp {
+            let mut replies = vec![];
+            for item in completed.into_iter().flatten() {
+                replies.extend(handler.on_message_completed_lmtp(item).await);
+            }
+
+            let recipients = std::mem::take(&mut self.lmtp_recipients);
+            if recipients.is_empty() {
+                self.sink
+                    .direct_send_reply(&mut self.context, &mut self.error_counter, handler, reply)
+                    .await?;
+                return Ok(());
+            }
+            for rcpt in recipients {
+                let rcpt_reply = replies
+                    .iter()
+                    .position(|rcpt_reply| rcpt_reply.0 == rcpt)
+                    .map_or_else(|| reply.clone(), |idx| replies.swap_remove(idx).1);
+                self.sink
+                    .direct_send_reply(
+                        &mut self.context,
+                        &mut self.error_counter,
+                        handler,
+                        rcpt_reply,
+                    )
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        if let Some(completed) = completed {
+            for item in completed {
+                if let Some(error) = handler.on_message_completed(item).await {
+                    reply = error;
+                    break;
+                }
+            }
+        }
+        self.sink
+            .direct_send_reply(&mut self.context, &mut self.error_counter, handler, reply)
+            .await?;
+        Ok(())
+    }
+
     /// SMTP handshake (generate the envelope and metadata).
     ///
     /// # Returns
@@ -431,6 +873,21 @@ where
                     Err(e) => handler.on_args_error(&e).await,
                 }
             };
+            ($args_output:ty, $args:expr, $on_event:tt, using_deprecated: $using_deprecated:expr) => {
+                match <$args_output>::try_from($args) {
+                    Ok(args) => match handler
+                        .on_helo_check(&args.client_name, $using_deprecated)
+                        .await
+                    {
+                        HeloCheck::Allow => handler.$on_event(&mut self.context, args).await,
+                        HeloCheck::Reject(reply) => {
+                            self.context.deny();
+                            reply
+                        }
+                    },
+                    Err(e) => handler.on_args_error(&e).await,
+                }
+            };
             ($args_output:ty, $args:expr, Option: $on_event:tt) => {
                 match <$args_output>::try_from($args) {
                     Ok(args) => handler.$on_event(&mut self.context, args).await,
@@ -439,14 +896,62 @@ where
             };
         }
 
+        let mut shutdown = self.shutdown.clone();
         let command_stream = self
             .stream
             .as_window_stream()
             .timeout(std::time::Duration::from_secs(30));
         tokio::pin!(command_stream);
+        // NOTE: the commands received during a tarpit.
+        let mut pipelined = None;
+
+        // NOTE: the deadline of the session is checked between the commands only,
+        //       so that a command (or a message) is never interrupted while being processed.
+        let deadline = self.session_deadline;
 
         loop {
-            let commands_batch = match command_stream.try_next().await {
+            if deadline.map_or(false, |deadline| tokio::time::Instant::now() >= deadline) {
+                Self::close_expired_session(
+                    &mut self.sink,
+                    &mut self.context,
+                    &mut self.error_counter,
+                    handler,
+                )
+                .await?;
+                return Ok(HandshakeOutcome::Quit);
+            }
+
+            // NOTE: a transaction in progress is completed before the session is drained.
+            let in_transaction = matches!(handler.get_stage(), Stage::MailFrom | Stage::RcptTo);
+            let next = if let Some(batch) = pipelined.take() {
+                Ok(Some(Ok(batch)))
+            } else {
+                tokio::select! {
+                    biased;
+                    () = wait_deadline(deadline) => {
+                        Self::close_expired_session(
+                            &mut self.sink,
+                            &mut self.context,
+                            &mut self.error_counter,
+                            handler,
+                        )
+                        .await?;
+                        return Ok(HandshakeOutcome::Quit);
+                    }
+                    () = wait_shutdown(&mut shutdown), if !in_transaction => {
+                        Self::close_drained_session(
+                            &mut self.sink,
+                            &mut self.context,
+                            &mut self.error_counter,
+                            handler,
+                        )
+                        .await?;
+                        return Ok(HandshakeOutcome::Quit);
+                    }
+                    next = command_stream.try_next() => next,
+                }
+            };
+            let commands_batch = match next {
                 // FIXME: remove intermediate result
                 Ok(Some(Ok(commands_batch))) if !commands_batch.is_empty() => commands_batch,
                 Err(e) => {
@@ -467,7 +972,23 @@ where
                 }
                 _ => return Ok(HandshakeOutcome::Quit),
             };
-            for command in commands_batch {
+            let mut commands_batch = commands_batch.into_iter();
+            for command in commands_batch.by_ref() {
+                if shutdown
+                    .as_ref()
+                    .map_or(false, |shutdown| *shutdown.borrow())
+                    && !matches!(handler.get_stage(), Stage::MailFrom | Stage::RcptTo)
+                {
+                    Self::close_drained_session(
+                        &mut self.sink,
+                        &mut self.context,
+                        &mut self.error_counter,
+                        handler,
+                    )
+                    .await?;
+                    return Ok(HandshakeOutcome::Quit);
+                }
+
                 let (verb, args) = match command {
                     Ok(command) => command,
                     Err(e) => {
@@ -495,35 +1016,134 @@ where
                 tracing::trace!("<< {:?} ; {:?}", verb, std::str::from_utf8(&args.0));
 
                 let stage = handler.get_stage();
-                let reply = match (verb, stage) {
-                    (Verb::Helo, _) => Some(handle_args!(HeloArgs, args, on_helo)),
-                    (Verb::Ehlo, _) => Some(handle_args!(EhloArgs, args, on_ehlo)),
-                    (Verb::Noop, _) => Some(handler.on_noop().await),
-                    (Verb::Rset, _) => Some(handler.on_rset().await),
-                    (Verb::StartTls, Stage::Connect | Stage::Helo) => {
-                        Some(handler.on_starttls(&mut self.context).await)
-                    }
-                    (Verb::Auth, Stage::Connect | Stage::Helo) => {
-                        handle_args!(AuthArgs, args, Option: on_auth)
-                    }
-                    (Verb::MailFrom, Stage::Helo | Stage::MailFrom) => {
-                        Some(handle_args!(MailFromArgs, args, on_mail_from))
-                    }
-                    (Verb::RcptTo, Stage::MailFrom | Stage::RcptTo) => {
-                        Some(handle_args!(RcptToArgs, args, on_rcpt_to))
-                    }
-                    (Verb::Data, Stage::RcptTo) => {
-                        self.context.outcome = Some(HandshakeOutcome::Message);
-                        Some(handler.on_data().await)
+                let span = tracing::info_span!(
+                    "command",
+                    otel.name = verb.as_ref().trim_end_matches([' ', ':', '\r', '\n']),
+                    %stage,
+                    message_uuid = tracing::field::Empty,
+                    transaction_id = tracing::field::Empty,
+                );
+                if let Some(id) = handler.get_transaction_id() {
+                    span.record("transaction_id", tracing::field::display(id));
+                }
+
+                let reply = async {
+                    match (verb, stage) {
+                        (Verb::Helo | Verb::Ehlo, _) if self.kind == ConnectionKind::Lmtp => {
+                            Some(handler.on_bad_greeting(verb).await)
+                        }
+                        (Verb::Lhlo, _) if self.kind != ConnectionKind::Lmtp => {
+                            Some(handler.on_bad_greeting(verb).await)
+                        }
+                        (Verb::Helo, _) => Some(handle_args!(
+                            HeloArgs,
+                            args,
+                            on_helo,
+                            using_deprecated: true
+                        )),
+                        (Verb::Ehlo | Verb::Lhlo, _) => Some(handle_args!(
+                            EhloArgs,
+                            args,
+                            on_ehlo,
+                            using_deprecated: false
+                        )),
+                        // NOTE: the argument is ignored, and the transaction is left untouched.
+                        (Verb::Noop, _) => Some(match parse_optional_string(&args) {
+                            Ok(_) => handler.on_noop().await,
+                            Err(e) => handler.on_args_error(&e).await,
+                        }),
+                        (Verb::Rset, _) => {
+                            self.lmtp_recipients.clear();
+                            Some(handler.on_rset().await)
+                        }
+                        (Verb::StartTls, Stage::Connect | Stage::Helo) => {
+                            Some(handler.on_starttls(&mut self.context).await)
+                        }
+                        (Verb::Auth, Stage::Connect | Stage::Helo) => {
+                            handle_args!(AuthArgs, args, Option: on_auth)
+                        }
+                        (Verb::XClient, Stage::Connect | Stage::Helo) => {
+                            let forwarded = XClientArgs::try_from(args.clone()).ok();
+                            let reply = handle_args!(XClientArgs, args, on_xclient);
+                            if let Some(forwarded) = forwarded {
+                                if !reply.code().is_error() {
+                                    self.client_addr = std::net::SocketAddr::new(
+                                        forwarded.addr.unwrap_or_else(|| self.client_addr.ip()),
+                                        forwarded.port.unwrap_or_else(|| self.client_addr.port()),
+                                    );
+                                }
+                            }
+                            Some(reply)
+                        }
+                        (Verb::MailFrom, Stage::Helo | Stage::MailFrom) => {
+                            self.lmtp_recipients.clear();
+                            let reverse_path = MailFromArgs::try_from(args.clone())
+                                .ok()
+                                .and_then(|args| args.reverse_path);
+                            let reply = handle_args!(MailFromArgs, args, on_mail_from);
+                            if !reply.code().is_error() {
+                                self.reverse_path = reverse_path;
+                            }
+                            Some(reply)
+                        }
+                        (Verb::RcptTo, Stage::MailFrom | Stage::RcptTo) => {
+                            // NOTE: the malformed commands do not consume the rate limit of the client.
+                            match RcptToArgs::try_from(args) {
+                                Ok(args) => match handler
+                                    .on_rate_limit(self.client_addr, self.reverse_path.as_ref())
+                                    .await
+                                {
+                                    RateLimit::Defer(reply) | RateLimit::Reject(reply) => {
+                                        Some(reply)
+                                    }
+                                    RateLimit::Allow => {
+                                        let forward_path = (self.kind == ConnectionKind::Lmtp)
+                                            .then(|| args.forward_path.clone());
+                                        let reply =
+                                            handler.on_rcpt_to(&mut self.context, args).await;
+                                        if let Some(forward_path) = forward_path {
+                                            if !reply.code().is_error() {
+                                                self.lmtp_recipients.push(forward_path);
+                                            }
+                                        }
+                                        Some(reply)
+                                    }
+                                },
+                                Err(e) => Some(handler.on_args_error(&e).await),
+                            }
+                        }
+                        (Verb::Data, Stage::RcptTo) => {
+                            self.context.outcome = Some(HandshakeOutcome::Message);
+                            Some(handler.on_data().await)
+                        }
+                        (Verb::Quit, _) => {
+                            self.context.outcome = Some(HandshakeOutcome::Quit);
+                            Some(handler.on_quit().await)
+                        }
+                        (Verb::Vrfy, _) => Some(handle_args!(VrfyArgs, args, on_vrfy)),
+                        (Verb::Expn, _) => Some(handle_args!(ExpnArgs, args, on_expn)),
+                        (Verb::Help, _) => Some(handle_args!(HelpArgs, args, on_help)),
+                        (Verb::Unknown, _) => Some(handler.on_unknown(args.0).await),
+                        otherwise => Some(handler.on_bad_sequence(otherwise).await),
                     }
-                    (Verb::Quit, _) => {
-                        self.context.outcome = Some(HandshakeOutcome::Quit);
-                        Some(handler.on_quit().await)
+                }
+                .instrument(span.clone())
+                .await;
+                if let Some(uuid) = handler.get_message_uuid() {
+                    span.record("message_uuid", tracing::field::display(uuid));
+                }
+                if let Some(id) = handler.get_transaction_id() {
+                    span.record("transaction_id", tracing::field::display(id));
+                }
+                if let Some(delay) = self.context.tarpit.take() {
+                    if !wait_tarpit(command_stream.as_mut(), &mut pipelined, delay)
+                        .instrument(span.clone())
+                        .await
+                    {
+                        tracing::info!("Client left during the tarpit.");
+                        return Ok(HandshakeOutcome::Quit);
                     }
-                    (Verb::Help, _) => Some(handler.on_help(args).await),
-                    (Verb::Unknown, _) => Some(handler.on_unknown(args.0).await),
-                    otherwise => Some(handler.on_bad_sequence(otherwise).await),
-                };
+                }
                 if let Some(reply) = reply {
                     self.sink
                         .send_reply(
@@ -533,14 +1153,27 @@ where
                             reply,
                             verb,
                         )
+                        .instrument(span)
                         .await?;
                 }
+
+                // NOTE: the commands pipelined after a STARTTLS have been sent in plaintext,
+                // they must not be executed on the secured session (CVE-2011-0411),
+                // nor the ones pipelined after a denied command.
+                if self.context.outcome.is_some() {
+                    break;
+                }
+            }
+
+            let discarded = commands_batch.count();
+            if discarded != 0 {
+                tracing::warn!(%discarded, "Discarding commands pipelined after the end of the handshake.");
             }
 
             if !self.sink.is_empty() {
                 self.sink.flush().await?;
             }
-            if let Some(done) = std::mem::take(&mut self.context).outcome {
+            if let Some(done) = self.context.outcome.take() {
                 return Ok(done);
             }
         }
@@ -17,11 +17,45 @@
 
 use crate::{
     receiver::ReceiverContext, smtp_sasl::CallbackWrap, AuthArgs, AuthError, EhloArgs, Error,
-    HeloArgs, MailFromArgs, ParseArgsError, RcptToArgs, UnparsedArgs, Verb,
+    ExpnArgs, HeloArgs, HelpArgs, MailFromArgs, ParseArgsError, RcptToArgs, Verb, VrfyArgs,
+    XClientArgs,
 };
 use tokio_rustls::rustls;
 // TODO: should we move these type in this crate
-use vsmtp_common::{Reply, Stage};
+use vsmtp_common::{Address, ClientName, Reply, Stage};
+
+/// Outcome of [`ReceiverHandler::on_rate_limit()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RateLimit {
+    /// The recipient is handled.
+    Allow,
+    /// The recipient is not handled, the client can try again later (`4xx` reply).
+    Defer(Reply),
+    /// The recipient is not handled (`5xx` reply).
+    Reject(Reply),
+}
+
+/// Outcome of [`ReceiverHandler::on_helo_check()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HeloCheck {
+    /// The client name is accepted, the command is handled.
+    Allow,
+    /// The client name is refused, the reply is sent and the connection is closed (`5xx` reply).
+    Reject(Reply),
+}
+
+/// Inspection of the message while it is received, given to [`Receiver::with_prescan()`](crate::Receiver::with_prescan).
+///
+/// Each line is scanned before being given to [`ReceiverHandler::on_message()`], the first
+/// reply produced aborts the message: the rest of the body is read but discarded, the handler
+/// gets an error and the client receives the reply once the message is ended.
+pub trait Prescan: Send + Sync {
+    /// Scan a line of the message (dot-unstuffed, "\r\n" included),
+    /// `size` being the number of bytes received so far, this line included.
+    fn scan(&self, size: usize, line: &[u8]) -> Option<Reply>;
+}
 
 // NOTE: could have 3 trait to make the implementation easier
 // PreTransactionHandler + TransactionHandler + PostTransactionHandler
@@ -36,6 +70,27 @@ pub trait ReceiverHandler {
     /// This function is called after each command to get the context stage.
     fn get_stage(&self) -> Stage;
 
+    /// Identifier of the message of the current transaction, if any.
+    /// This function is called after each command to attach it to the command's span.
+    #[inline]
+    fn get_message_uuid(&self) -> Option<uuid::Uuid> {
+        None
+    }
+
+    /// Identifier of the current transaction, if any.
+    /// This function is called around each command to attach it to the command's span.
+    #[inline]
+    fn get_transaction_id(&self) -> Option<uuid::Uuid> {
+        None
+    }
+
+    /// Time elapsed since the connection, and since the start of the current transaction
+    /// if any. This function is called when the message is received to attach them to its span.
+    #[inline]
+    fn get_ages(&self) -> (Option<std::time::Duration>, Option<std::time::Duration>) {
+        (None, None)
+    }
+
     /// Create an instance capable to handle the SASL handshake.
     fn generate_sasl_callback(&self) -> CallbackWrap;
 
@@ -62,6 +117,18 @@ pub trait ReceiverHandler {
         result: Result<(), AuthError>,
     ) -> Reply;
 
+    /// Called after parsing the arguments of a [`Verb::Helo`] or [`Verb::Ehlo`] command,
+    /// before [`ReceiverHandler::on_helo()`] or [`ReceiverHandler::on_ehlo()`], to validate
+    /// the name given by the client.
+    #[inline]
+    async fn on_helo_check(
+        &mut self,
+        _client_name: &ClientName,
+        _using_deprecated: bool,
+    ) -> HeloCheck {
+        HeloCheck::Allow
+    }
+
     /// Called after receiving a [`Verb::Helo`] command.
     async fn on_helo(&mut self, ctx: &mut ReceiverContext, args: HeloArgs) -> Reply;
 
@@ -71,6 +138,17 @@ pub trait ReceiverHandler {
     /// Called after receiving a [`Verb::MailFrom`] command.
     async fn on_mail_from(&mut self, ctx: &mut ReceiverContext, args: MailFromArgs) -> Reply;
 
+    /// Called after receiving a [`Verb::RcptTo`] command, before [`ReceiverHandler::on_rcpt_to()`],
+    /// to throttle the clients.
+    #[inline]
+    async fn on_rate_limit(
+        &mut self,
+        _client_addr: std::net::SocketAddr,
+        _reverse_path: Option<&Address>,
+    ) -> RateLimit {
+        RateLimit::Allow
+    }
+
     /// Called after receiving a [`Verb::RcptTo`] command.
     async fn on_rcpt_to(&mut self, ctx: &mut ReceiverContext, args: RcptToArgs) -> Reply;
 
@@ -89,15 +167,64 @@ pub trait ReceiverHandler {
     /// If this callback returns `Some`, the reply produced by [`ReceiverHandler::on_message()`] is discarded.
     async fn on_message_completed(&mut self, item: Self::Item) -> Option<Reply>;
 
+    /// Called instead of [`ReceiverHandler::on_message_completed()`] on a
+    /// [`ConnectionKind::Lmtp`](crate::ConnectionKind::Lmtp) connection.
+    ///
+    /// Returns the reply of each recipient of the message.
+    /// The recipients missing in the output receive the reply produced by [`ReceiverHandler::on_message()`].
+    async fn on_message_completed_lmtp(&mut self, item: Self::Item) -> Vec<(Address, Reply)>;
+
     /// Called when the number of reply considered as error reached a threshold (hard).
     async fn on_hard_error(&mut self, ctx: &mut ReceiverContext, reply: Reply) -> Reply;
 
     /// Called when the number of reply considered as error reached a threshold (soft).
     async fn on_soft_error(&mut self, ctx: &mut ReceiverContext, reply: Reply) -> Reply;
 
+    /// Called for each reply sent to the client, once the error thresholds have been applied.
+    #[inline]
+    fn on_reply(&mut self, _: &Reply) {}
+
+    /// Called when the client sends data before the banner, during the delay
+    /// requested with [`ReceiverContext::delay_greeting()`].
+    ///
+    /// If this callback returns `Some`, the reply is sent instead of the banner and the connection is closed.
+    #[inline]
+    async fn on_early_talker(&mut self) -> Option<Reply> {
+        None
+    }
+
     /// Called after receiving a [`Verb::Rset`] command.
     async fn on_rset(&mut self) -> Reply;
 
+    /// Called after receiving a [`Verb::XClient`] command.
+    ///
+    /// The handler must check that the peer is trusted to forward the identity of its clients.
+    #[inline]
+    async fn on_xclient(&mut self, _: &mut ReceiverContext, _: XClientArgs) -> Reply {
+        #[allow(clippy::expect_used)]
+        "502 Command not implemented\r\n"
+            .parse()
+            .expect("valid syntax")
+    }
+
+    /// Called after receiving a [`Verb::Vrfy`] command.
+    #[inline]
+    async fn on_vrfy(&mut self, _: &mut ReceiverContext, _: VrfyArgs) -> Reply {
+        #[allow(clippy::expect_used)]
+        "502 Command not implemented\r\n"
+            .parse()
+            .expect("valid syntax")
+    }
+
+    /// Called after receiving a [`Verb::Expn`] command.
+    #[inline]
+    async fn on_expn(&mut self, _: &mut ReceiverContext, _: ExpnArgs) -> Reply {
+        #[allow(clippy::expect_used)]
+        "502 Command not implemented\r\n"
+            .parse()
+            .expect("valid syntax")
+    }
+
     /// Called after receiving a [`Verb::Data`] command.
     #[inline]
     async fn on_data(&mut self) -> Reply {
@@ -123,9 +250,9 @@ pub trait ReceiverHandler {
         "250 Ok\r\n".parse().expect("valid syntax")
     }
 
-    /// Called after receiving a [`Verb::Help`] command.
+    /// Called after receiving a [`Verb::Help`] command, in any stage of the transaction.
     #[inline]
-    async fn on_help(&mut self, _: UnparsedArgs) -> Reply {
+    async fn on_help(&mut self, _: &mut ReceiverContext, _: HelpArgs) -> Reply {
         #[allow(clippy::expect_used)]
         "214 joining us https://viridit.com/support"
             .parse()
@@ -135,7 +262,7 @@ pub trait ReceiverHandler {
     /// Called after receiving an unknown command (unrecognized or unimplemented).
     #[inline]
     async fn on_unknown(&mut self, buffer: Vec<u8>) -> Reply {
-        let unimplemented_command = [b"VRFY".as_slice(), b"EXPN".as_slice(), b"TURN".as_slice()];
+        let unimplemented_command = [b"TURN".as_slice()];
 
         #[allow(clippy::expect_used)]
         if unimplemented_command.iter().any(|c| {
@@ -155,6 +282,16 @@ pub trait ReceiverHandler {
         }
     }
 
+    /// Called after receiving a greeting which does not match the protocol of the connection
+    /// (`HELO` or `EHLO` on a LMTP connection, `LHLO` on a SMTP connection).
+    #[inline]
+    async fn on_bad_greeting(&mut self, _: Verb) -> Reply {
+        #[allow(clippy::expect_used)]
+        "500 Syntax error command unrecognized\r\n"
+            .parse()
+            .expect("valid syntax")
+    }
+
     /// Called when the stage of the transaction (obtained with [`get_stage`](Self::get_stage))
     /// and the command are not compatible.
     #[inline]
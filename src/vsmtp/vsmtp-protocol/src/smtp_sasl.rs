@@ -81,6 +81,60 @@ pub enum AuthError {
     ConfigError(#[from] rsasl::prelude::SASLError),
 }
 
+// FIXME: rsasl+async
+macro_rules! block_on {
+    ($future:expr) => {
+        tokio::task::block_in_place(move || tokio::runtime::Handle::current().block_on($future))
+    };
+}
+
+/// Buffer the data written by a step of the mechanism, sent as a single `334` reply on flush.
+struct AdapterSMTPandSASL<'writer, W: tokio::io::AsyncWrite + Unpin + Send> {
+    sink: &'writer mut W,
+    mechanism: Mechanism,
+    challenge: Option<Vec<u8>>,
+}
+
+/// `LOGIN` has never been standardized, and the legacy clients expect the prompts of
+/// the historical implementations instead of the ones of `rsasl` (`User Name\0` and `Password\0`).
+///
+/// See <https://datatracker.ietf.org/doc/html/draft-murchison-sasl-login-00>
+fn login_challenge(challenge: Vec<u8>) -> Vec<u8> {
+    match challenge.as_slice() {
+        b"User Name\0" => b"Username:".to_vec(),
+        b"Password\0" => b"Password:".to_vec(),
+        _ => challenge,
+    }
+}
+
+#[allow(clippy::missing_trait_methods)]
+impl<'writer, W: tokio::io::AsyncWrite + Unpin + Send> std::io::Write
+    for AdapterSMTPandSASL<'writer, W>
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.challenge
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut challenge = self.challenge.take();
+        if self.mechanism == Mechanism::Login {
+            challenge = challenge.map(login_challenge);
+        }
+        let sink = &mut *self.sink;
+        block_on! { async move {
+            if let Some(challenge) = challenge {
+                sink.write_all(b"334 ").await?;
+                sink.write_all(STANDARD.encode(challenge).as_bytes()).await?;
+                sink.write_all(b"\r\n").await?;
+            }
+            tokio::io::AsyncWriteExt::flush(sink).await
+        }}
+    }
+}
+
 impl<
         T: ReceiverHandler + Send,
         V: rsasl::validate::Validation + Send,
@@ -96,35 +150,6 @@ where
         mechanism: Mechanism,
         initial_response: Option<Vec<u8>>,
     ) -> Result<(), AuthError> {
-        // FIXME: rsasl+async
-        macro_rules! block_on {
-            ($future:expr) => {
-                tokio::task::block_in_place(move || {
-                    tokio::runtime::Handle::current().block_on($future)
-                })
-            };
-        }
-
-        struct AdapterSMTPandSASL<'writer, W: tokio::io::AsyncWrite + Unpin + Send>(&'writer mut W);
-
-        #[allow(clippy::missing_trait_methods)]
-        impl<'writer, W: tokio::io::AsyncWrite + Unpin + Send> std::io::Write
-            for AdapterSMTPandSASL<'writer, W>
-        {
-            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-                block_on! { async move {
-                    self.0.write_all(b"334 ").await?;
-                    self.0.write_all(STANDARD.encode(buf).as_bytes()).await?;
-                    self.0.write_all(b"\r\n").await?;
-                    std::io::Result::Ok(())
-                }}
-                .map(|_| buf.len())
-            }
-
-            fn flush(&mut self) -> std::io::Result<()> {
-                block_on! { tokio::io::AsyncWriteExt::flush(&mut self.0) }
-            }
-        }
         let callback = handler.generate_sasl_callback();
 
         let rsasl_config = rsasl::config::SASLConfig::builder()
@@ -139,7 +164,11 @@ where
             rsasl::prelude::Mechname::parse(temp.as_bytes()).expect("mechanism is valid");
         let mut session = sasl_server.start_suggested(selected)?;
 
-        let mut adapter = AdapterSMTPandSASL(self.sink.as_mut());
+        let mut adapter = AdapterSMTPandSASL {
+            sink: self.sink.as_mut(),
+            mechanism,
+            challenge: None,
+        };
         let challenge_stream = self.stream.as_line_stream().map(|line| {
             let l = line.map(|buffer| {
                 buffer
@@ -178,29 +207,39 @@ where
             (None, true) => None,
             (None, false) => {
                 std::io::Write::write(&mut adapter, &[])?;
+                std::io::Write::flush(&mut adapter)?;
                 next_challenge_line!(challenge_stream)
             }
             (Some(_), true) => return Err(AuthError::ClientMustNotStart),
             (Some(data), false) => Some(STANDARD.decode(data)?),
         };
 
-        #[allow(clippy::wildcard_enum_match_arm)]
-        while session
-            .step(data.as_deref(), &mut adapter)
-            .map_err(|e| match e {
-                rsasl::prelude::SessionError::ValidationError(
-                    rsasl::validate::ValidationError::Boxed(e),
-                ) => AuthError::ValidationError(e),
-                otherwise => AuthError::SessionError(otherwise),
-            })?
-            .is_running()
-        {
+        loop {
+            #[allow(clippy::wildcard_enum_match_arm)]
+            let state = session
+                .step(data.as_deref(), &mut adapter)
+                .map_err(|e| match e {
+                    rsasl::prelude::SessionError::ValidationError(
+                        rsasl::validate::ValidationError::Boxed(e),
+                    ) => AuthError::ValidationError(e),
+                    otherwise => AuthError::SessionError(otherwise),
+                })?;
+            std::io::Write::flush(&mut adapter)?;
+
+            if !state.is_running() {
+                break;
+            }
             data = next_challenge_line!(challenge_stream);
         }
 
-        #[allow(clippy::todo)]
+        // The mechanism has finished without validating the client,
+        // e.g. after an error challenge of `OAUTHBEARER` / `XOAUTH2`.
         session.validation().map_or_else(
-            || todo!("what happen when the validator return nothing ?"),
+            || {
+                Err(AuthError::ValidationError(
+                    "the client has not been validated".into(),
+                ))
+            },
             |_v| Ok(()),
         )
     }
@@ -79,6 +79,7 @@ impl<W: tokio::io::AsyncWrite + Unpin + Send> WindowWriter<W> {
     }
 
     /// update error counters and return appropriate message based on these counters.
+    /// The counter is reset by a successful reply.
     async fn handle_error<T: ReceiverHandler + Send>(
         &mut self,
         ctx: &mut ReceiverContext,
@@ -87,6 +88,8 @@ impl<W: tokio::io::AsyncWrite + Unpin + Send> WindowWriter<W> {
         reply: Reply,
     ) -> Reply {
         if !reply.code().is_error() {
+            // NOTE: only the consecutive errors are counted.
+            error_counter.error_count = 0;
             return reply;
         }
         error_counter.error_count += 1;
@@ -111,10 +114,15 @@ impl<W: tokio::io::AsyncWrite + Unpin + Send> WindowWriter<W> {
         reply: Reply,
     ) -> std::io::Result<()> {
         let final_reply = self.handle_error(ctx, error_counter, handler, reply).await;
-        self.write_all(final_reply.as_ref()).await
+        let final_reply = ctx.enhance_reply(final_reply, None);
+        handler.on_reply(&final_reply);
+        // NOTE: the replies of the previous commands are sent first to keep the order.
+        self.buffer.push(final_reply);
+        self.flush().await
     }
 
-    /// analyze the message if it can be stored in a buffer. It is send directly otherwise.
+    /// analyze the message if it can be stored in a buffer. The buffer is sent
+    /// with the reply of a synchronization point otherwise.
     pub async fn send_reply<T: ReceiverHandler + Send>(
         &mut self,
         ctx: &mut ReceiverContext,
@@ -124,14 +132,13 @@ impl<W: tokio::io::AsyncWrite + Unpin + Send> WindowWriter<W> {
         verb: Verb,
     ) -> std::io::Result<()> {
         let final_reply = self.handle_error(ctx, error_counter, handler, reply).await;
+        let final_reply = ctx.enhance_reply(final_reply, Some(verb));
+        handler.on_reply(&final_reply);
+        self.buffer.push(final_reply);
         if verb.is_bufferable() {
-            if !self.buffer.is_empty() {
-                self.flush().await?;
-            }
-            return self.write_all(final_reply.as_ref()).await;
+            return Ok(());
         }
-        self.buffer.push(final_reply);
-        Ok(())
+        self.flush().await
     }
 
     /// send all buffered response in one go.
@@ -208,24 +215,25 @@ impl<W: tokio::io::AsyncWrite + Unpin + Send> Writer<W> {
         handler: &mut T,
         reply: Reply,
     ) -> std::io::Result<()> {
-        if !reply.code().is_error() {
-            return self.write_all(reply.as_ref()).await;
-        }
-        error_counter.error_count += 1;
-
-        let hard_error = error_counter.threshold_hard_error;
-        let soft_error = error_counter.threshold_soft_error;
-
-        if hard_error != -1 && error_counter.error_count >= hard_error {
-            let reply = handler.on_hard_error(ctx, reply).await;
-            return self.write_all(reply.as_ref()).await;
-        }
-
-        if soft_error != -1 && error_counter.error_count >= soft_error {
-            let reply = handler.on_soft_error(ctx, reply).await;
-            return self.write_all(reply.as_ref()).await;
-        }
+        let reply = if reply.code().is_error() {
+            error_counter.error_count += 1;
+
+            let hard_error = error_counter.threshold_hard_error;
+            let soft_error = error_counter.threshold_soft_error;
+
+            if hard_error != -1 && error_counter.error_count >= hard_error {
+                handler.on_hard_error(ctx, reply).await
+            } else if soft_error != -1 && error_counter.error_count >= soft_error {
+                handler.on_soft_error(ctx, reply).await
+            } else {
+                reply
+            }
+        } else {
+            error_counter.error_count = 0;
+            reply
+        };
 
+        handler.on_reply(&reply);
         self.write_all(reply.as_ref()).await
     }
 }
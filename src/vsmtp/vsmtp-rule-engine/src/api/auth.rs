@@ -70,6 +70,14 @@ mod auth {
                 tracing::trace!(token);
                 Ok(state::deny())
             }
+            Some(Credentials::BearerToken { .. }) => {
+                tracing::warn!("Cannot authenticate unix user with a bearer token");
+                Ok(state::deny())
+            }
+            Some(Credentials::Forwarded { .. }) => {
+                tracing::warn!("Cannot authenticate unix user with forwarded credentials");
+                Ok(state::deny())
+            }
             None => {
                 tracing::warn!("No credentials found to authenticate a unix user with");
                 Ok(state::deny())
@@ -81,7 +89,8 @@ mod auth {
     ///
     /// # Effective smtp stage
     ///
-    /// `authenticate` stage only.
+    /// All of them, the client is authenticated once the `authenticate` rules have accepted
+    /// its credentials.
     ///
     /// # Return
     ///
@@ -93,7 +102,7 @@ mod auth {
     /// # vsmtp_test::vsl::run(
     /// # |builder| Ok(builder.add_root_filter_rules(r#"
     /// #{
-    ///     authenticate: [
+    ///     mail: [
     ///        action "log info" || log("info", `client authenticated: ${auth::is_authenticated()}`),
     ///     ]
     /// }
@@ -103,7 +112,47 @@ mod auth {
     /// # rhai-autodocs:index:2
     #[rhai_fn(name = "is_authenticated", return_raw)]
     pub fn is_authenticated(ncc: NativeCallContext) -> EngineResult<bool> {
-        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read()).auth().is_some())
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read()).is_authenticated())
+    }
+
+    /// Get the user the client has authenticated as.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, the client is authenticated once the `authenticate` rules have accepted
+    /// its credentials.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the `authid` of the credentials, or an empty string if the client
+    ///   is not authenticated or authenticated with an anonymous token.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        rule "only john can send as the ceo" || {
+    ///           if ctx::mail_from() == "ceo@example.com" && auth::auth_user() != "john" {
+    ///              state::deny()
+    ///           } else {
+    ///              state::next()
+    ///           }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn(name = "auth_user", return_raw)]
+    pub fn user(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .auth_user()
+            .unwrap_or_default()
+            .to_owned())
     }
 
     /// Get authentication credentials from the client.
@@ -129,7 +178,7 @@ mod auth {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:3
+    /// # rhai-autodocs:index:4
     #[rhai_fn(name = "credentials", return_raw)]
     pub fn credentials(ncc: NativeCallContext) -> EngineResult<Credentials> {
         vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -166,7 +215,7 @@ mod auth {
     ///        action "log auth type" || {
     ///             let credentials = auth::credentials();
     ///
-    ///             // Logs here will output 'Verify' or 'AnonymousToken'.
+    ///             // Logs here will output 'Verify', 'AnonymousToken', 'BearerToken' or 'Forwarded'.
     ///             // depending on the authentication type.
     ///             log("info", `credentials type: ${credentials.type}`);
     ///         },
@@ -175,14 +224,14 @@ mod auth {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:4
+    /// # rhai-autodocs:index:5
     #[rhai_fn(global, get = "type", pure)]
     pub fn get_type(credentials: &mut Credentials) -> String {
         credentials.to_string()
     }
 
     /// Get the `authid` property of the connection.
-    /// Can only be use on 'Verify' authentication typed credentials.
+    /// Can only be use on 'Verify', 'BearerToken' and 'Forwarded' authentication typed credentials.
     ///
     /// # Effective smtp stage
     ///
@@ -208,12 +257,17 @@ mod auth {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:5
+    /// # rhai-autodocs:index:6
     #[rhai_fn(global, get = "authid", return_raw, pure)]
     pub fn get_authid(credentials: &mut Credentials) -> EngineResult<String> {
         match credentials {
-            Credentials::Verify { authid, .. } => Ok(authid.clone()),
-            Credentials::AnonymousToken { .. } => {
+            Credentials::Verify { authid, .. }
+            | Credentials::Forwarded { authid }
+            | Credentials::BearerToken {
+                authid: Some(authid),
+                ..
+            } => Ok(authid.clone()),
+            Credentials::AnonymousToken { .. } | Credentials::BearerToken { authid: None, .. } => {
                 Err(format!("no `authid` available in credentials of type `{credentials}`").into())
             }
         }
@@ -246,12 +300,14 @@ mod auth {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:6
+    /// # rhai-autodocs:index:7
     #[rhai_fn(global, get = "authpass", return_raw, pure)]
     pub fn get_authpass(credentials: &mut Credentials) -> EngineResult<String> {
         match credentials {
             Credentials::Verify { authpass, .. } => Ok(authpass.clone()),
-            Credentials::AnonymousToken { .. } => Err(format!(
+            Credentials::AnonymousToken { .. }
+            | Credentials::BearerToken { .. }
+            | Credentials::Forwarded { .. } => Err(format!(
                 "no `authpass` available in credentials of type `{credentials}`"
             )
             .into()),
@@ -285,17 +341,61 @@ mod auth {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:7
+    /// # rhai-autodocs:index:8
     #[rhai_fn(global, get = "anonymous_token", return_raw, pure)]
     pub fn get_anonymous_token(credentials: &mut Credentials) -> EngineResult<String> {
         match credentials {
             Credentials::AnonymousToken { token } => Ok(token.clone()),
-            Credentials::Verify { .. } => Err(format!(
+            Credentials::Verify { .. }
+            | Credentials::BearerToken { .. }
+            | Credentials::Forwarded { .. } => Err(format!(
                 "no `anonymous_token` available in credentials of type `{credentials}`"
             )
             .into()),
         }
     }
+
+    /// Get the `bearer_token` property of the connection.
+    /// Can only be use on 'BearerToken' authentication typed credentials,
+    /// produced by the `OAUTHBEARER` and `XOAUTH2` mechanisms.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `authenticate` only.
+    ///
+    /// # Return
+    ///
+    /// * `String` - the OAuth 2.0 token, without the `Bearer` scheme.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     authenticate: [
+    ///        action "log bearer token" || {
+    ///             let credentials = auth::credentials();
+    ///             log("info", `credentials token: ${credentials.bearer_token}`);
+    ///         },
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:9
+    #[rhai_fn(global, get = "bearer_token", return_raw, pure)]
+    pub fn get_bearer_token(credentials: &mut Credentials) -> EngineResult<String> {
+        match credentials {
+            Credentials::BearerToken { token, .. } => Ok(token.clone()),
+            Credentials::Verify { .. }
+            | Credentials::AnonymousToken { .. }
+            | Credentials::Forwarded { .. } => Err(format!(
+                "no `bearer_token` available in credentials of type `{credentials}`"
+            )
+            .into()),
+        }
+    }
 }
 
 fn execute_testsaslauthd(authid: &str, authpass: &str) -> EngineResult<Status> {
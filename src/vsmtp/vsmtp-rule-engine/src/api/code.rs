@@ -15,17 +15,24 @@
  *
 */
 
-use crate::api::SharedObject;
+use crate::{api::SharedObject, get_global};
 use rhai::plugin::{
     Dynamic, FnAccess, FnNamespace, Module, NativeCallContext, PluginFunction, RhaiResult, TypeId,
 };
 
 pub use code::*;
 
-/// Predefined codes for SMTP responses.
+/// The reply configured in `server.smtp.replies` for the reason, or its default one.
+fn reply(ncc: &NativeCallContext, reason: vsmtp_common::RejectionReason) -> SharedObject {
+    SharedObject::new(vsmtp_plugin_vsl::objects::Object::Code(
+        get_global!(ncc, srv).config.server.smtp.reply(reason),
+    ))
+}
+
+/// Predefined codes for SMTP responses, their text can be changed in `server.smtp.replies`.
 #[rhai::plugin::export_module]
 mod code {
-    use vsmtp_plugin_vsl::objects::constructors::code_enhanced;
+    use vsmtp_common::RejectionReason;
 
     /// Return a relay access denied code.
     ///
@@ -54,8 +61,8 @@ mod code {
     /// # rhai-autodocs:index:1
     #[must_use]
     #[rhai_fn(name = "c554_7_1")]
-    pub fn c554_7_1() -> SharedObject {
-        code_enhanced(554, "5.7.1", "Relay access denied").expect("valid code")
+    pub fn c554_7_1(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::RelayDenied)
     }
 
     /// Return a DKIM Failure code. (RFC 6376)
@@ -84,8 +91,8 @@ mod code {
     /// # rhai-autodocs:index:2
     #[must_use]
     #[rhai_fn(name = "c550_7_20")]
-    pub fn c550_7_20() -> SharedObject {
-        code_enhanced(550, "5.7.20", "No passing DKIM signature found").expect("valid code")
+    pub fn c550_7_20(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::DkimNotFound)
     }
 
     /// Return a DKIM Failure code. (RFC 6376)
@@ -114,8 +121,8 @@ mod code {
     /// # rhai-autodocs:index:3
     #[must_use]
     #[rhai_fn(name = "c550_7_21")]
-    pub fn c550_7_21() -> SharedObject {
-        code_enhanced(550, "5.7.21", "No acceptable DKIM signature found").expect("valid code")
+    pub fn c550_7_21(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::DkimNotAcceptable)
     }
 
     /// Return a DKIM Failure code. (RFC 6376)
@@ -144,13 +151,8 @@ mod code {
     /// # rhai-autodocs:index:4
     #[must_use]
     #[rhai_fn(name = "c550_7_22")]
-    pub fn c550_7_22() -> SharedObject {
-        code_enhanced(
-            550,
-            "5.7.22",
-            "No valid author-matched DKIM signature found",
-        )
-        .expect("valid code")
+    pub fn c550_7_22(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::DkimAuthorMismatch)
     }
 
     /// Return a SPF Failure code. (RFC 7208)
@@ -179,8 +181,8 @@ mod code {
     /// # rhai-autodocs:index:5
     #[must_use]
     #[rhai_fn(name = "c550_7_23")]
-    pub fn c550_7_23() -> SharedObject {
-        code_enhanced(550, "5.7.23", "SPF validation failed").expect("valid code")
+    pub fn c550_7_23(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::SpfFailed)
     }
 
     /// Return a SPF Failure code. (RFC 7208)
@@ -209,8 +211,8 @@ mod code {
     /// # rhai-autodocs:index:6
     #[must_use]
     #[rhai_fn(name = "c550_7_24")]
-    pub fn c550_7_24() -> SharedObject {
-        code_enhanced(550, "5.7.24", "SPF validation error").expect("valid code")
+    pub fn c550_7_24(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::SpfError)
     }
 
     /// Return a reverse DNS Failure code.
@@ -238,8 +240,8 @@ mod code {
     /// # rhai-autodocs:index:7
     #[must_use]
     #[rhai_fn(name = "c550_7_25")]
-    pub fn c550_7_25() -> SharedObject {
-        code_enhanced(550, "5.7.25", "Reverse DNS validation failed").expect("valid code")
+    pub fn c550_7_25(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::ReverseDnsFailed)
     }
 
     /// Return a multiple authentication failures code.
@@ -267,8 +269,8 @@ mod code {
     /// # rhai-autodocs:index:8
     #[must_use]
     #[rhai_fn(name = "c500_7_26")]
-    pub fn c550_7_26() -> SharedObject {
-        code_enhanced(500, "5.7.26", "Multiple authentication checks failed").expect("valid code")
+    pub fn c550_7_26(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::MultipleAuthenticationFailed)
     }
 
     /// Return a Null MX cod. (RFC 7505)
@@ -297,8 +299,8 @@ mod code {
     /// # rhai-autodocs:index:9
     #[must_use]
     #[rhai_fn(name = "c550_7_27")]
-    pub fn c550_7_27() -> SharedObject {
-        code_enhanced(550, "5.7.27", "Sender address has null MX").expect("valid code")
+    pub fn c550_7_27(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::SenderNullMx)
     }
 
     /// Return a Null MX cod. (RFC 7505)
@@ -329,8 +331,8 @@ mod code {
     /// # rhai-autodocs:index:10
     #[must_use]
     #[rhai_fn(name = "c556_1_10")]
-    pub fn c556_1_10() -> SharedObject {
-        code_enhanced(556, "5.1.10", "Recipient address has null MX").expect("valid code")
+    pub fn c556_1_10(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::RecipientNullMx)
     }
 
     /// Return a greylisting code (<https://www.rfc-editor.org/rfc/rfc6647.html#section-2.1>)
@@ -358,9 +360,8 @@ mod code {
     /// # rhai-autodocs:index:11
     #[must_use]
     #[rhai_fn(name = "c451_7_1")]
-    pub fn greylist() -> SharedObject {
-        code_enhanced(451, "4.7.1", "Sender is not authorized. Please try again.")
-            .expect("valid code")
+    pub fn greylist(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::Greylisted)
     }
 
     /// Multiple destination domains per transaction is unsupported code.
@@ -390,13 +391,8 @@ mod code {
     /// # rhai-autodocs:index:12
     #[must_use]
     #[rhai_fn(name = "c451_3_0")]
-    pub fn multi_destination() -> SharedObject {
-        code_enhanced(
-            451,
-            "4.3.0",
-            "Multiple destination domains per transaction is unsupported. Please try again.",
-        )
-        .expect("valid code")
+    pub fn multi_destination(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::MultipleDestinations)
     }
 
     /// Multiple destination domains per transaction is unsupported code.
@@ -426,71 +422,7 @@ mod code {
     /// # rhai-autodocs:index:13
     #[must_use]
     #[rhai_fn(name = "c550_1_1")]
-    pub fn unknown_account() -> SharedObject {
-        code_enhanced(
-            550,
-            "5.1.1",
-            "The email account that you tried to reach does not exist. Please try again.",
-        )
-        .expect("valid code")
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn codes() {
-        assert_eq!(
-            code::c554_7_1().to_string(),
-            "554 5.7.1 Relay access denied\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_20().to_string(),
-            "550 5.7.20 No passing DKIM signature found\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_21().to_string(),
-            "550 5.7.21 No acceptable DKIM signature found\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_22().to_string(),
-            "550 5.7.22 No valid author-matched DKIM signature found\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_23().to_string(),
-            "550 5.7.23 SPF validation failed\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_24().to_string(),
-            "550 5.7.24 SPF validation error\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_25().to_string(),
-            "550 5.7.25 Reverse DNS validation failed\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_26().to_string(),
-            "500 5.7.26 Multiple authentication checks failed\r\n".to_string()
-        );
-        assert_eq!(
-            code::c550_7_27().to_string(),
-            "550 5.7.27 Sender address has null MX\r\n".to_string()
-        );
-        assert_eq!(
-            code::c556_1_10().to_string(),
-            "556 5.1.10 Recipient address has null MX\r\n".to_string()
-        );
-        assert_eq!(
-            code::greylist().to_string(),
-            "451 4.7.1 Sender is not authorized. Please try again.\r\n".to_string()
-        );
-        assert_eq!(code::multi_destination().to_string(), "451 4.3.0 Multiple destination domains per transaction is unsupported. Please try again.\r\n".to_string());
-        assert_eq!(
-            code::unknown_account().to_string(),
-            "550 5.1.1 The email account that you tried to reach does not exist. Please try again.\r\n"
-                .to_string()
-        );
+    pub fn unknown_account(ncc: NativeCallContext) -> SharedObject {
+        reply(&ncc, RejectionReason::UnknownAccount)
     }
 }
@@ -370,6 +370,132 @@ mod dkim {
         Ok(result)
     }
 
+    /// Verify all the `DKIM-Signature` headers of the message.
+    ///
+    /// Unlike `dkim::verify`, each signature is verified, the result is not stored
+    /// in the context and no `Authentication-Results` header is added.
+    /// The public keys are fetched once for all the signatures sharing the same
+    /// selector and domain.
+    ///
+    /// # Return
+    ///
+    /// * `array` - one map per signature, in the order of the headers, with the fields:
+    ///   * `status` - "pass", "fail", "neutral" (the signature or the key is malformed),
+    ///                "temperror" (the key could not be fetched, the verification can be retried later),
+    ///                "permerror" (the key does not exist), or "none" (the key is in testing mode).
+    ///   * `sdid`   - the signing domain identifier (empty if the signature is malformed).
+    ///   * `auid`   - the agent or user identifier (empty if the signature is malformed).
+    ///
+    /// The array is empty if the message is not signed.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // The message received.
+    /// let msg = r#"
+    /// DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/relaxed; d=github.com;
+    /// 	s=pf2023; t=1680072674;
+    /// 	bh=RprtMST4/9zuJ2sHMc/XzPU24+EpKHxKeMv9WGr9GGc=;
+    /// 	h=Date:From:To:Subject:From;
+    /// 	b=ewM8CN8h+YIoodsw4j+PWNf2PzE9tgUpMqW877vIjGtCfn82Sl7m8EwVUAmiXbw1z
+    /// 	 KO3fBgM2YYOTAuDXEc46jgwEVQnWocfTnXvXMn1JsGLaRZX35w7X6ON1fPOoCm0CmN
+    /// 	 THodL0qR4oPEXCPItAysl9r7PKkhxGDrzBLXapVg=
+    /// Date: Tue, 28 Mar 2023 23:51:14 -0700
+    /// From: "dependabot[bot]" <noreply@github.com>
+    /// To: mlala@negabit.com
+    /// Message-ID: <viridIT/vSMTP/push/refs/heads/dependabot/cargo/clap-4.2.0/ff7841-e82e9d@github.com>
+    /// Subject: [viridIT/vSMTP] e82e9d: Build(deps): Bump clap from 4.1.11 to 4.2.0
+    /// Mime-Version: 1.0
+    /// Content-Type: text/plain;
+    ///  charset=UTF-8
+    /// Content-Transfer-Encoding: 7bit
+    /// Approved: =?UTF-8?Q?hello_there_=F0=9F=91=8B?=
+    ///
+    ///   Branch: refs/heads/dependabot/cargo/clap-4.2.0
+    ///   Home:   https://github.com/viridIT/vSMTP
+    ///   Commit: e82e9d9382dc44a296a889ee2ec7c6126e77d988
+    ///       https://github.com/viridIT/vSMTP/commit/e82e9d9382dc44a296a889ee2ec7c6126e77d988
+    ///   Author: dependabot[bot] <49699333+dependabot[bot]@users.noreply.github.com>
+    ///   Date:   2023-03-29 (Wed, 29 Mar 2023)
+    ///
+    ///   Changed paths:
+    ///     M Cargo.lock
+    ///     M src/vqueue/Cargo.toml
+    ///     M src/vsmtp/vsmtp-core/Cargo.toml
+    ///
+    ///   Log Message:
+    ///   -----------
+    ///   Build(deps): Bump clap from 4.1.11 to 4.2.0
+    ///
+    /// Bumps [clap](https://github.com/clap-rs/clap) from 4.1.11 to 4.2.0.
+    /// - [Release notes](https://github.com/clap-rs/clap/releases)
+    /// - [Changelog](https://github.com/clap-rs/clap/blob/master/CHANGELOG.md)
+    /// - [Commits](https://github.com/clap-rs/clap/compare/v4.1.11...clap_complete-v4.2.0)
+    ///
+    /// ---
+    /// updated-dependencies:
+    /// - dependency-name: clap
+    ///   dependency-type: direct:production
+    ///   update-type: version-update:semver-minor
+    /// ...
+    ///
+    /// Signed-off-by: dependabot[bot] <support@github.com>
+    ///
+    ///
+    ///
+    /// "#;
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(msg[1..].replace("\n", "\r\n").as_str()).unwrap();
+    ///
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///         rule "verify dkim" || {
+    ///             for result in dkim::verify_all() {
+    ///                 log("info", `dkim signature of ${result.sdid}: ${result.status}`);
+    ///
+    ///                 if result.status == "temperror" {
+    ///                     return state::deny(code(451, "4.7.5", "dkim: temporary failure, try again later"));
+    ///                 }
+    ///
+    ///                 if result.sdid == "github.com" && result.status == "pass" {
+    ///                     return state::accept();
+    ///                 }
+    ///             }
+    ///
+    ///             state::deny()
+    ///         }
+    ///    ]
+    ///  }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status};
+    /// # use vsmtp_rule_engine::ExecutionStage;
+    /// # assert_eq!(states[&ExecutionStage::PreQ].2, Status::Accept("250 Ok".parse::<vsmtp_common::Reply>().unwrap()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:8
+    #[rhai_fn(return_raw)]
+    pub fn verify_all(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
+        super::Impl::verify_all(
+            &get_global!(ncc, ctx),
+            &get_global!(ncc, msg),
+            &get_global!(ncc, srv),
+            // is the `expire_time` of the signature over `now +/- epsilon` (as seconds)
+            100,
+        )
+    }
+
     /// Produce a `DKIM-Signature` header.
     ///
     /// # Args
@@ -428,7 +554,7 @@ mod dkim {
     /// # assert_eq!(states[&ExecutionStage::PreQ].2, Status::Accept("250 Ok".parse::<vsmtp_common::Reply>().unwrap()));
     /// ```
     ///
-    /// # rhai-autodocs:index:8
+    /// # rhai-autodocs:index:9
     #[rhai_fn(name = "sign", return_raw)]
     pub fn sign(ncc: NativeCallContext, params: rhai::Map) -> EngineResult<()> {
         let signature = vsl_generic_ok!(super::Impl::generate_signature(
@@ -439,6 +565,250 @@ mod dkim {
 
         crate::api::message::prepend_header(ncc, "DKIM-Signature", &signature)
     }
+
+    /// Produce a `DKIM-Signature` header using a private key stored in a file.
+    ///
+    /// The message is signed with the "relaxed/relaxed" canonicalization.
+    /// The key is read from the file at each call, prefer declaring it in the
+    /// configuration and use `dkim::get_private_keys` for high volumes of mail.
+    ///
+    /// # Args
+    ///
+    /// * `selector`         - the DNS selector to expose the public key & for the verifier
+    /// * `sdid`             - the signing domain identifier.
+    /// * `private_key_path` - the path of the private key (rsa or ed25519, pem encoded) to sign the mail,
+    ///                        associated with the public key in the `selector._domainkey.sdid`
+    ///                        DNS record.
+    /// * `headers`          - list of headers to sign. (optional, default: ["From", "To", "Subject", "Date"])
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rules = r#"#{
+    ///   preq: [
+    ///     action "sign dkim" || {
+    ///       dkim::sign("2022-09", "testserver.com", "/etc/vsmtp/dkim/private_key.pem");
+    ///       dkim::sign("2022-09", "testserver.com", "/etc/vsmtp/dkim/private_key.pem", ["From", "To"]);
+    ///     },
+    /// #   rule "trailing" || state::accept(),
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let private_key_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../vsmtp-test/src/template/certs/private_key.rsa.key");
+    /// # let rules = rules.replace("/etc/vsmtp/dkim/private_key.pem", private_key_path);
+    ///
+    /// # let states = vsmtp_test::vsl::run(move |builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(&rules)?
+    /// #        .with_outgoing(&rules)?
+    /// #        .with_internal(&rules)?
+    /// #      .build()
+    /// #   .build()));
+    /// # use vsmtp_common::{status::Status};
+    /// # use vsmtp_rule_engine::ExecutionStage;
+    /// # assert_eq!(states[&ExecutionStage::PreQ].2, Status::Accept("250 Ok".parse::<vsmtp_common::Reply>().unwrap()));
+    /// # let message = states[&ExecutionStage::PreQ].1.inner();
+    /// # let private_key = <rsa::RsaPrivateKey as rsa::pkcs1::DecodeRsaPrivateKey>::read_pkcs1_pem_file(private_key_path).unwrap();
+    /// # let public_key = rsa::pkcs8::EncodePublicKey::to_public_key_pem(
+    /// #   &rsa::RsaPublicKey::from(&private_key), rsa::pkcs8::LineEnding::LF
+    /// # ).unwrap();
+    /// # let public_key = format!(
+    /// #   "v=DKIM1; k=rsa; p={}",
+    /// #   public_key.lines().filter(|line| !line.starts_with("-----")).collect::<String>()
+    /// # ).parse::<vsmtp_auth::dkim::PublicKey>().unwrap();
+    /// # let signatures = message.headers().into_iter()
+    /// #   .filter(|(key, _)| key == "DKIM-Signature")
+    /// #   .map(|(key, value)| format!("{key}:{value}").parse::<vsmtp_auth::dkim::Signature>().unwrap())
+    /// #   .collect::<Vec<_>>();
+    /// # assert_eq!(signatures.len(), 2);
+    /// # for signature in signatures {
+    /// #   vsmtp_auth::dkim::verify(&signature, message, &public_key).unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:10
+    #[rhai_fn(name = "sign", return_raw)]
+    pub fn sign_with_key_file(
+        ncc: NativeCallContext,
+        selector: &str,
+        sdid: &str,
+        private_key_path: &str,
+    ) -> EngineResult<()> {
+        sign_with_key_file_and_headers(
+            ncc,
+            selector,
+            sdid,
+            private_key_path,
+            ["From", "To", "Subject", "Date"]
+                .into_iter()
+                .map(rhai::Dynamic::from)
+                .collect(),
+        )
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "sign", return_raw)]
+    pub fn sign_with_key_file_and_headers(
+        ncc: NativeCallContext,
+        selector: &str,
+        sdid: &str,
+        private_key_path: &str,
+        headers: rhai::Array,
+    ) -> EngineResult<()> {
+        let params = SignatureParams {
+            sdid: Some(sdid.to_string()),
+            selector: selector.to_string(),
+            private_key: super::Impl::read_private_key(private_key_path)?,
+            headers_field: Some(headers.into_iter().map(|h| h.to_string()).collect()),
+            canonicalization: Some("relaxed/relaxed".parse().expect("default values are valid")),
+        };
+
+        let signature = vsl_generic_ok!(super::Impl::generate_signature(
+            &vsl_guard_ok!(get_global!(ncc, msg).read()),
+            &vsl_guard_ok!(get_global!(ncc, ctx).read()),
+            params
+        ));
+
+        crate::api::message::prepend_header(ncc, "DKIM-Signature", &signature)
+    }
+
+    /// Add an ARC set (`ARC-Authentication-Results`, `ARC-Message-Signature` and `ARC-Seal`
+    /// headers) on top of the message, chaining from the sets already present (rfc 8617).
+    ///
+    /// The authentication results of the set are copied from the `Authentication-Results`
+    /// header of the server (see `msg::add_authentication_results`), `none` if there is no such header.
+    /// The existing chain is checked for missing or duplicated sets and for the `cv=` of its seals,
+    /// then the signatures of its seals and of its latest message signature are verified
+    /// with the public keys fetched from the DNS. The new seal has `cv=fail` if any of these checks fails.
+    ///
+    /// # Args
+    ///
+    /// * `selector`         - the DNS selector to expose the public key & for the verifier
+    /// * `sdid`             - the signing domain identifier.
+    /// * `private_key_path` - the path of the private key (rsa or ed25519, pem encoded) to seal the mail,
+    ///                        associated with the public key in the `selector._domainkey.sdid`
+    ///                        DNS record.
+    /// * `headers`          - list of headers to sign. (optional, default: ["From", "To", "Subject", "Date"])
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * the private key cannot be read.
+    /// * the latest seal of the message has failed (`cv=fail`), the chain must not be extended.
+    /// * the message already contains 50 sets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rules = r#"#{
+    ///   preq: [
+    ///     action "seal arc" || {
+    ///       msg::add_authentication_results("spf", "pass smtp.mailfrom=testserver.com");
+    ///       dkim::arc_seal("2022-09", "testserver.com", "/etc/vsmtp/dkim/private_key.pem");
+    ///     },
+    /// #   action "seal again" || dkim::arc_seal("2022-09", "testserver.com", "/etc/vsmtp/dkim/private_key.pem", ["From"]),
+    /// #   rule "trailing" || state::accept(),
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let private_key_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../vsmtp-test/src/template/certs/private_key.rsa.key");
+    /// # let rules = rules.replace("/etc/vsmtp/dkim/private_key.pem", private_key_path);
+    ///
+    /// # let states = vsmtp_test::vsl::run(move |builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(&rules)?
+    /// #        .with_outgoing(&rules)?
+    /// #        .with_internal(&rules)?
+    /// #      .build()
+    /// #   .build()));
+    /// # use vsmtp_common::{status::Status};
+    /// # use vsmtp_rule_engine::ExecutionStage;
+    /// # assert_eq!(states[&ExecutionStage::PreQ].2, Status::Accept("250 Ok".parse::<vsmtp_common::Reply>().unwrap()));
+    /// # let headers = states[&ExecutionStage::PreQ].1.inner().headers();
+    /// # assert_eq!(
+    /// #   headers.iter().take(6).map(|(key, value)| (key.as_str(), value.split(';').next().unwrap().trim())).collect::<Vec<_>>(),
+    /// #   [
+    /// #     ("ARC-Seal", "i=2"), ("ARC-Message-Signature", "i=2"), ("ARC-Authentication-Results", "i=2"),
+    /// #     ("ARC-Seal", "i=1"), ("ARC-Message-Signature", "i=1"), ("ARC-Authentication-Results", "i=1"),
+    /// #   ]
+    /// # );
+    /// # // NOTE: the public key of the first seal is not published in the DNS.
+    /// # assert!(headers[0].1.contains("cv=fail;"));
+    /// # assert!(headers[3].1.contains("cv=none;"));
+    /// # assert_eq!(headers[5].1.trim(), "i=1; testserver.com; spf=pass smtp.mailfrom=testserver.com");
+    /// ```
+    ///
+    /// # rhai-autodocs:index:11
+    #[rhai_fn(name = "arc_seal", return_raw)]
+    pub fn arc_seal(
+        ncc: NativeCallContext,
+        selector: &str,
+        sdid: &str,
+        private_key_path: &str,
+    ) -> EngineResult<()> {
+        arc_seal_with_headers(
+            ncc,
+            selector,
+            sdid,
+            private_key_path,
+            ["From", "To", "Subject", "Date"]
+                .into_iter()
+                .map(rhai::Dynamic::from)
+                .collect(),
+        )
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "arc_seal", return_raw)]
+    pub fn arc_seal_with_headers(
+        ncc: NativeCallContext,
+        selector: &str,
+        sdid: &str,
+        private_key_path: &str,
+        headers: rhai::Array,
+    ) -> EngineResult<()> {
+        let private_key = super::Impl::read_private_key(private_key_path)?;
+        let authserv_id = crate::api::utils::get_root_domain(
+            &vsl_guard_ok!(get_global!(ncc, ctx).read())
+                .server_name()
+                .to_string(),
+        );
+
+        let message = get_global!(ncc, msg);
+        let public_keys = super::Impl::arc_public_keys(
+            &get_global!(ncc, srv),
+            backend::arc::public_key_queries(vsl_guard_ok!(message.read()).inner()),
+        );
+
+        // NOTE: the lock is held from the hash of the chain to the insertion of the set.
+        let mut message = vsl_guard_ok!(message.write());
+        let set = super::Impl::arc_seal(
+            &message,
+            &authserv_id,
+            selector,
+            sdid,
+            &private_key,
+            &public_keys,
+            &headers
+                .into_iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>(),
+        )?;
+
+        for (name, value) in set.headers().into_iter().rev() {
+            message.prepend_header(name, value);
+        }
+        Ok(())
+    }
 }
 
 ///
@@ -542,10 +912,17 @@ impl Impl {
             .map_or_else(
                 || Err("no `dkim_result` available".into()),
                 |dkim_result| {
-                    Ok(rhai::Map::from_iter([(
+                    let mut map = rhai::Map::from_iter([(
                         "status".into(),
                         dkim_result.status.clone().into(),
-                    )]))
+                    )]);
+                    if let Some(sdid) = &dkim_result.sdid {
+                        map.insert("sdid".into(), sdid.clone().into());
+                    }
+                    if let Some(auid) = &dkim_result.auid {
+                        map.insert("auid".into(), auid.clone().into());
+                    }
+                    Ok(map)
                 },
             )
     }
@@ -614,6 +991,80 @@ impl Impl {
         })
     }
 
+    pub fn read_private_key(path: &str) -> EngineResult<std::sync::Arc<backend::PrivateKey>> {
+        <vsmtp_config::field::SecretFile<std::sync::Arc<backend::PrivateKey>> as serde::Deserialize>::deserialize(
+            serde::de::value::StrDeserializer::<serde::de::value::Error>::new(path),
+        )
+        .map(|key| key.inner)
+        .map_err(|err| format!("failed to read dkim private key at '{path}': {err}").into())
+    }
+
+    /// Fetch the public keys of the sealers of an ARC chain, the keys that cannot be fetched
+    /// or parsed are missing from the result.
+    fn arc_public_keys(server: &Server, queries: Vec<String>) -> backend::arc::PublicKeys {
+        let resolver = server.resolvers.get_resolver_root();
+
+        queries
+            .into_iter()
+            .filter_map(|query| {
+                let lookup = resolver.txt_lookup(query.as_str());
+                let key = block_on!(lookup)
+                    .map_err(|error| error.to_string())
+                    .and_then(|records| {
+                        records
+                            .into_iter()
+                            .find_map(|record| {
+                                <backend::PublicKey as std::str::FromStr>::from_str(
+                                    &record.to_string(),
+                                )
+                                .ok()
+                            })
+                            .ok_or_else(|| "no valid key record".to_owned())
+                    });
+
+                match key {
+                    Ok(key) => Some((query, key)),
+                    Err(error) => {
+                        tracing::warn!(%query, %error, "Failed to fetch the public key of an ARC set.");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn arc_seal(
+        message: &MessageBody,
+        authserv_id: &str,
+        selector: &str,
+        sdid: &str,
+        private_key: &backend::PrivateKey,
+        public_keys: &backend::arc::PublicKeys,
+        headers_field: &[String],
+    ) -> EngineResult<backend::arc::ArcSet> {
+        let authentication_results = message
+            .get_header("Authentication-Results")
+            .filter(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .and_then(|id| id.split_whitespace().next())
+                    == Some(authserv_id)
+            })
+            .unwrap_or_else(|| format!("{authserv_id}; none"));
+
+        backend::arc::seal(
+            message.inner(),
+            private_key,
+            sdid,
+            selector,
+            &authentication_results,
+            headers_field,
+            public_keys,
+        )
+        .map_err(|e| format!("failed to seal the message: {e}").into())
+    }
+
     #[tracing::instrument(ret, err)]
     fn generate_signature(
         message: &MessageBody,
@@ -650,6 +1101,8 @@ impl Impl {
                     "`status` is missing in DKIM verification result".into()
                 })?
                 .to_string(),
+            sdid: result.get("sdid").map(ToString::to_string),
+            auid: result.get("auid").map(ToString::to_string),
         };
 
         Ok(vsl_generic_ok!(vsl_guard_ok!(ctx.write()).set_dkim(result)))
@@ -745,6 +1198,97 @@ impl Impl {
         )]))
     }
 
+    pub fn verify_all(
+        ctx: &Context,
+        msg: &Message,
+        srv: &Server,
+        expiration_epsilon: u64,
+    ) -> EngineResult<rhai::Array> {
+        fn to_result(status: &str, signature: Option<&backend::Signature>) -> rhai::Dynamic {
+            rhai::Dynamic::from_map(rhai::Map::from_iter([
+                ("status".into(), status.into()),
+                (
+                    "sdid".into(),
+                    signature
+                        .map_or_else(String::new, |s| s.sdid.clone())
+                        .into(),
+                ),
+                (
+                    "auid".into(),
+                    signature
+                        .map_or_else(String::new, |s| s.auid.clone())
+                        .into(),
+                ),
+            ]))
+        }
+
+        let mut results = rhai::Array::new();
+
+        for input in crate::api::message::Impl::get_header_untouched(msg, "DKIM-Signature") {
+            let signature = match Self::parse_signature(&input.to_string()) {
+                Ok(signature) => signature,
+                Err(error) => {
+                    tracing::warn!(%error, "Failed to parse DKIM signature, continuing ...");
+                    results.push(to_result(&Self::get_dkim_error_status(&error), None));
+                    continue;
+                }
+            };
+
+            if signature.has_expired(expiration_epsilon) {
+                tracing::warn!("DKIM signature expired, continuing ...");
+                results.push(to_result("fail", Some(&signature)));
+                continue;
+            }
+
+            // NOTE: the keys are cached in the context for the duration of the transaction.
+            let query = signature.get_dns_query();
+            let cached =
+                vsl_generic_ok!(vsl_guard_ok!(ctx.read()).dkim_public_keys(&query)).cloned();
+            let signature_keys = if let Some(signature_keys) = cached {
+                signature_keys
+            } else {
+                let signature_keys = Self::get_public_key(srv, &signature, "cycle")
+                    .map_err(|error| Self::get_dkim_error_status(&error));
+                vsl_generic_ok!(
+                    vsl_guard_ok!(ctx.write()).set_dkim_public_keys(query, signature_keys.clone())
+                );
+                signature_keys
+            };
+
+            let status = match signature_keys {
+                Err(status) => status,
+                Ok(signature_keys) if signature_keys.is_empty() => "permerror".to_string(),
+                Ok(signature_keys) => {
+                    let mut status = "fail".to_string();
+                    for key in &signature_keys {
+                        let verified = Self::verify(&vsl_guard_ok!(msg.read()), &signature, key);
+                        match verified {
+                            Err(error) => {
+                                tracing::warn!(%error, "DKIM signature verification failed");
+                                status = Self::get_dkim_error_status(&error);
+                            }
+                            Ok(()) if key.has_debug_flag() => {
+                                tracing::warn!("DKIM signature contains `debug_flag`");
+                                status = "none".to_string();
+                                break;
+                            }
+                            Ok(()) => {
+                                tracing::debug!("DKIM signature successfully verified.");
+                                status = "pass".to_string();
+                                break;
+                            }
+                        }
+                    }
+                    status
+                }
+            };
+
+            results.push(to_result(&status, Some(&signature)));
+        }
+
+        Ok(results)
+    }
+
     fn get_dkim_error_status(error: &DkimErrors) -> String {
         strum::EnumMessage::get_message(error)
             .expect("`DkimErrors` must have a `message` for each variant")
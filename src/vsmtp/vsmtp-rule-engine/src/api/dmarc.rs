@@ -15,7 +15,7 @@
  *
 */
 
-use crate::api::{EngineResult, Message, Server};
+use crate::api::{Context, EngineResult, Message, Server};
 use rhai::plugin::{
     Dynamic, FnAccess, FnNamespace, Module, NativeCallContext, PluginFunction, RhaiResult, TypeId,
 };
@@ -52,21 +52,11 @@ mod dmarc {
     pub fn check(ncc: NativeCallContext) -> EngineResult<vsmtp_common::status::Status> {
         let msg = get_global!(ncc, msg);
         let srv = get_global!(ncc, srv);
-
-        let rfc5322_from = super::parse_rfc5322_from(&msg)?;
-        let rfc5322_from = rfc5322_from.domain();
-        let record = get_dmarc_record(&srv, &rfc5322_from)?;
+        let ctx = get_global!(ncc, ctx);
 
         // tracing::warn!(%error, "DMARC record not found:");
         // return rule_state::next();
-        let ctx = get_global!(ncc, ctx);
-
-        let dkim = crate::api::dkim::Impl::verify_inner(
-            &ctx, &msg, &srv, // TODO: only take `d == rfc5322_from`
-            5, "cycle", 1000,
-        )?;
-
-        let spf = crate::api::spf::check(&ctx, &srv)?;
+        let evaluation = super::evaluate(&ctx, &msg, &srv)?;
         let ctx = vsl_guard_ok!(ctx.read());
 
         let (hostname, sender, client_ip) = {
@@ -85,23 +75,17 @@ mod dmarc {
  reason="{}"
  smtp.mailfrom={}"#,
             crate::api::utils::get_root_domain(&ctx.server_name().to_string()),
-            dkim.get("status")
+            evaluation
+                .dkim
+                .get("status")
                 .map(std::string::ToString::to_string)
                 .unwrap_or_default(),
-            spf.result,
-            crate::api::spf::key_value_list(&spf, &hostname, sender_addr, &client_ip),
+            evaluation.spf.result,
+            crate::api::spf::key_value_list(&evaluation.spf, &hostname, sender_addr, &client_ip),
             sender_addr
         );
 
-        let dmarc_pass = dmarc_check(
-            &record,
-            &rfc5322_from,
-            &dkim,
-            &sender
-                .as_ref()
-                .map_or("null".to_string(), |s| s.domain().to_string()),
-            spf.result.as_str(),
-        );
+        let dmarc_pass = evaluation.alignment.pass();
 
         crate::api::message::Impl::prepend_header(
             &msg,
@@ -117,15 +101,153 @@ mod dmarc {
         Ok(if dmarc_pass {
             state::next()
         } else {
-            tracing::warn!(record = %record.receiver_policy, "DMARC check failed.");
+            tracing::warn!(record = %evaluation.record.receiver_policy, "DMARC check failed.");
 
-            match record.receiver_policy {
+            match evaluation.record.receiver_policy {
                 vsmtp_auth::dmarc::ReceiverPolicy::None => state::next(),
                 vsmtp_auth::dmarc::ReceiverPolicy::Quarantine => state::quarantine_str("dmarc"),
                 vsmtp_auth::dmarc::ReceiverPolicy::Reject => state::deny(/*code_...*/),
             }
         })
     }
+
+    /// WARNING: Low level API, use `dmarc::check` instead if you do not need
+    /// to peek inside the dmarc result data.
+    ///
+    /// Evaluate the DMARC policy of the domain of the `From` header, without
+    /// modifying the message.
+    ///
+    /// The spf and dkim results already computed in the rules (with `spf::check`
+    /// or `dkim::verify` for example) are reused.
+    ///
+    /// # Return
+    ///
+    /// * `map` - the result of the dmarc evaluation, contains the following keys:
+    ///     * `result` - "pass" if dkim or spf is aligned, "fail" otherwise.
+    ///     * `alignment` - a map with the `dkim` and `spf` keys, set to `true`
+    ///                     if the identifier passed and is aligned with the `From` domain.
+    ///     * `policy` - the policy requested by the domain owner ("none", "quarantine" or "reject").
+    ///     * `disposition` - the policy to apply to this message, "none" if the result is "pass".
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// #{
+    ///     preq: [
+    ///         rule "check dmarc" || {
+    ///             const dmarc = dmarc::check_raw();
+    ///
+    ///             switch dmarc.disposition {
+    ///                 "quarantine" => state::quarantine("dmarc"),
+    ///                 "reject" => state::deny(),
+    ///                 _ => state::next(),
+    ///             }
+    ///         },
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:2
+    #[rhai_fn(name = "check_raw", return_raw)]
+    pub fn check_raw(ncc: NativeCallContext) -> EngineResult<rhai::Map> {
+        let msg = get_global!(ncc, msg);
+        let srv = get_global!(ncc, srv);
+        let ctx = get_global!(ncc, ctx);
+
+        super::evaluate(&ctx, &msg, &srv).map(|evaluation| evaluation.to_map())
+    }
+}
+
+/// Identifiers that passed and are aligned with the RFC5322.From domain.
+#[derive(Debug, PartialEq, Eq)]
+struct Alignment {
+    dkim: bool,
+    spf: bool,
+}
+
+impl Alignment {
+    /// A message passes DMARC if at least one identifier is aligned.
+    const fn pass(&self) -> bool {
+        self.dkim || self.spf
+    }
+}
+
+/// Result of a DMARC evaluation, and the data used to compute it.
+struct Evaluation {
+    record: vsmtp_auth::dmarc::Record,
+    dkim: rhai::Map,
+    spf: vsmtp_auth::spf::Result,
+    alignment: Alignment,
+}
+
+impl Evaluation {
+    fn to_map(&self) -> rhai::Map {
+        let policy = self.record.get_policy();
+        let pass = self.alignment.pass();
+
+        rhai::Map::from_iter([
+            ("result".into(), if pass { "pass" } else { "fail" }.into()),
+            (
+                "alignment".into(),
+                rhai::Dynamic::from_map(rhai::Map::from_iter([
+                    ("dkim".into(), self.alignment.dkim.into()),
+                    ("spf".into(), self.alignment.spf.into()),
+                ])),
+            ),
+            (
+                "disposition".into(),
+                if pass {
+                    "none".to_string()
+                } else {
+                    policy.clone()
+                }
+                .into(),
+            ),
+            ("policy".into(), policy.into()),
+        ])
+    }
+}
+
+/// Fetch the DMARC record of the RFC5322.From domain and check the alignment
+/// of the dkim and spf results, reusing them if they are already stored in the context.
+fn evaluate(ctx: &Context, msg: &Message, srv: &Server) -> EngineResult<Evaluation> {
+    let rfc5322_from = parse_rfc5322_from(msg)?;
+    let rfc5322_from = rfc5322_from.domain();
+    let record = get_dmarc_record(srv, &rfc5322_from)?;
+
+    let dkim = crate::api::dkim::Impl::verify_inner(
+        ctx, msg, srv, // TODO: only take `d == rfc5322_from`
+        5, "cycle", 1000,
+    )?;
+
+    let stored_spf = vsl_generic_ok!(vsl_guard_ok!(ctx.read()).spf()).cloned();
+    let spf = match stored_spf {
+        Some(spf) => spf,
+        None => crate::api::spf::check(ctx, srv)?,
+    };
+
+    let spf_mail_from = vsl_generic_ok!(vsl_guard_ok!(ctx.read()).reverse_path())
+        .as_ref()
+        .map_or("null".to_string(), |s| s.domain().to_string());
+
+    let alignment = dmarc_check(
+        &record,
+        &rfc5322_from,
+        &dkim,
+        &spf_mail_from,
+        spf.result.as_str(),
+    );
+
+    Ok(Evaluation {
+        record,
+        dkim,
+        spf,
+        alignment,
+    })
 }
 
 fn dmarc_check(
@@ -134,34 +256,25 @@ fn dmarc_check(
     dkim_result: &rhai::Map,
     spf_mail_from: &str,
     spf_result: &str,
-) -> bool {
-    let dkim_domain: String = match dkim_result
-        .get("sdid")
-        .cloned()
-        .and_then(rhai::Dynamic::try_cast)
-    {
-        Some(domain) => domain,
-        None => return false,
-    };
-    let dkim_status: String = match dkim_result
-        .get("status")
-        .cloned()
-        .and_then(rhai::Dynamic::try_cast)
-    {
-        Some(status) => status,
-        None => return false,
+) -> Alignment {
+    let get = |key: &str| -> Option<String> {
+        dkim_result
+            .get(key)
+            .cloned()
+            .and_then(rhai::Dynamic::try_cast)
     };
 
     let rfc5322_from = rfc5322_from.to_string();
-    if record.dkim_is_aligned(&rfc5322_from, &dkim_domain) && dkim_status == "pass" {
-        return true;
-    }
 
-    if record.spf_is_aligned(&rfc5322_from, spf_mail_from) && spf_result == "pass" {
-        return true;
+    Alignment {
+        dkim: match (get("sdid"), get("status")) {
+            (Some(dkim_domain), Some(dkim_status)) => {
+                dkim_status == "pass" && record.dkim_is_aligned(&rfc5322_from, &dkim_domain)
+            }
+            _ => false,
+        },
+        spf: spf_result == "pass" && record.spf_is_aligned(&rfc5322_from, spf_mail_from),
     }
-
-    false
 }
 
 /// Get the address of the sender in the message body, also known as RFC5322.From
@@ -203,3 +316,64 @@ fn get_dmarc_record(server: &Server, domain: &Domain) -> EngineResult<vsmtp_auth
 
     Ok(first)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{dmarc_check, Alignment};
+
+    fn dkim(status: &str, sdid: &str) -> rhai::Map {
+        rhai::Map::from_iter([
+            ("status".into(), status.into()),
+            ("sdid".into(), sdid.into()),
+        ])
+    }
+
+    #[test]
+    fn dkim_aligned_spf_not_aligned() {
+        let record = "v=DMARC1; p=reject".parse().unwrap();
+        let alignment = dmarc_check(
+            &record,
+            &"example.com".parse().unwrap(),
+            &dkim("pass", "mail.example.com"),
+            "other.org",
+            "pass",
+        );
+
+        assert_eq!(
+            alignment,
+            Alignment {
+                dkim: true,
+                spf: false
+            }
+        );
+        assert!(alignment.pass());
+    }
+
+    #[test]
+    fn strict_alignment() {
+        let record = "v=DMARC1; p=quarantine; adkim=s; aspf=s".parse().unwrap();
+        let alignment = dmarc_check(
+            &record,
+            &"example.com".parse().unwrap(),
+            &dkim("pass", "mail.example.com"),
+            "mail.example.com",
+            "pass",
+        );
+
+        assert!(!alignment.pass());
+    }
+
+    #[test]
+    fn nothing_passed() {
+        let record = "v=DMARC1; p=none".parse().unwrap();
+        let alignment = dmarc_check(
+            &record,
+            &"example.com".parse().unwrap(),
+            &rhai::Map::from_iter([("status".into(), "none".into())]),
+            "example.com",
+            "softfail",
+        );
+
+        assert!(!alignment.pass());
+    }
+}
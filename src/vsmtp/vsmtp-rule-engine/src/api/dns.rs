@@ -130,6 +130,175 @@ mod dns {
     pub fn rlookup_obj(ncc: NativeCallContext, name: SharedObject) -> EngineResult<rhai::Array> {
         super::rlookup(ncc, &name.to_string())
     }
+
+    /// Look up the client address in a DNS blocklist (DNSBL), by querying the A and TXT
+    /// records of the reversed address in the zone, `2.0.0.127.zen.spamhaus.org` for `127.0.0.2`.
+    ///
+    /// The result of each zone is cached for the connection, a zone is queried once
+    /// even if the function is called at several stages or for each recipient.
+    /// A failed lookup is considered as not listed.
+    ///
+    /// # Args
+    ///
+    /// * `zone` - The zone of the blocklist.
+    /// * `zones` - An array of zones, queried one after the other.
+    ///
+    /// # Return
+    ///
+    /// * `map` - with a single zone, the listing of the client in the zone:
+    ///   * `zone` - the zone queried.
+    ///   * `listed` - is the client listed in the zone.
+    ///   * `records` - the A records of the listing, an array of IPs usually in `127.0.0.0/8`
+    ///     encoding the reason of the listing. The array is empty if the client is not listed.
+    ///   * `explanation` - the TXT record of the listing, `()` if the zone does not provide one.
+    /// * `array` - with an array of zones, the listings of the client in the zones that list it.
+    ///   The array is empty if the client is not listed.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "dnsbl" || {
+    ///       let listing = dns::check_dnsbl("zen.spamhaus.org");
+    ///       if listing.listed {
+    ///         state::deny(`554 5.7.1 Client listed by ${listing.zone}: ${listing.explanation}`)
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     },
+    ///     rule "dnsbl multiple zones" || {
+    ///       let listings = dns::check_dnsbl(["zen.spamhaus.org", "bl.spamcop.net"]);
+    ///       if listings.len() >= 2 {
+    ///         state::deny(`554 5.7.1 Client listed by ${listings.map(|l| l.zone)}`)
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn(name = "check_dnsbl")]
+    #[must_use]
+    pub fn check_dnsbl(ncc: NativeCallContext, zone: &str) -> rhai::Map {
+        let listing = super::Impl::dnsbl(&get_global!(ncc, srv), &get_global!(ncc, ctx), zone);
+        super::Impl::dnsbl_to_map(zone, listing)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "check_dnsbl")]
+    #[must_use]
+    pub fn check_dnsbl_zones(ncc: NativeCallContext, zones: rhai::Array) -> rhai::Array {
+        let (srv, ctx) = (get_global!(ncc, srv), get_global!(ncc, ctx));
+
+        zones
+            .into_iter()
+            .map(|zone| zone.to_string())
+            .filter_map(|zone| {
+                super::Impl::dnsbl(&srv, &ctx, &zone)
+                    .map(|listing| super::Impl::dnsbl_to_map(&zone, Some(listing)).into())
+            })
+            .collect()
+    }
+
+    /// Does the domain of the sender accept mails, and therefore the bounces of the messages
+    /// it sends. The domain must have MX records, or no MX record but an A or AAAA record
+    /// (rfc 5321 #5.1), and must not publish a null MX (rfc 7505).
+    ///
+    /// The lookup gives up after `server.smtp.mx_timeout` (5 seconds by default), its result
+    /// is cached for the transaction. The null sender (`MAIL FROM:<>`) has no domain to check,
+    /// and is considered as accepting mails.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - `true` if the domain of the sender accepts mails, `false` if it has no record.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * The lookup failed or timed out (temperror), the answer is unknown.
+    ///   The error can be caught to defer the transaction instead of denying it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   mail: [
+    ///     rule "sender accepts bounces" || {
+    ///       try {
+    ///         if dns::sender_has_mx() {
+    ///           state::next()
+    ///         } else {
+    ///           state::deny(code::c550_7_27())
+    ///         }
+    ///       } catch (error) {
+    ///         state::deny("451 4.4.3 Cannot resolve the domain of the sender, try again later")
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:4
+    #[rhai_fn(name = "sender_has_mx", return_raw)]
+    pub fn sender_has_mx(ncc: NativeCallContext) -> EngineResult<bool> {
+        super::Impl::sender_has_mx(&get_global!(ncc, srv), &get_global!(ncc, ctx))
+    }
+
+    /// Is the domain of the current recipient handled by the server: the name of the server,
+    /// or a domain (or the subdomain of a domain) of the `server.virtual` entries.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - `true` if the domain of the recipient is local.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` and onwards, see `ctx::rcpt()` for the recipient checked.
+    ///
+    /// # Errors
+    ///
+    /// * No recipient has been received yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   rcpt: [
+    ///     rule "no relay" || {
+    ///       if dns::recipient_domain_is_local() {
+    ///         state::next()
+    ///       } else {
+    ///         state::deny(code::c554_7_1())
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:5
+    #[rhai_fn(name = "recipient_domain_is_local", return_raw)]
+    pub fn recipient_domain_is_local(ncc: NativeCallContext) -> EngineResult<bool> {
+        super::Impl::recipient_domain_is_local(&get_global!(ncc, srv), &get_global!(ncc, ctx))
+    }
 }
 
 struct Impl;
@@ -160,4 +329,101 @@ impl Impl {
             .map(|record| rhai::Dynamic::from(record.to_string()))
             .collect::<rhai::Array>())
     }
+
+    fn dnsbl(
+        server: &Server,
+        context: &super::Context,
+        zone: &str,
+    ) -> Option<vsmtp_common::DnsblListing> {
+        let cached = vsl_guard_ok!(context.read())
+            .dnsbl(zone)
+            .map(Option::<&_>::cloned);
+
+        cached.unwrap_or_else(|| {
+            let ip = vsl_guard_ok!(context.read()).client_addr().ip();
+            let resolver = server.resolvers.get_resolver_root();
+
+            let listing = block_on!(crate::dnsbl::lookup(&resolver, ip, zone));
+            vsl_guard_ok!(context.write()).set_dnsbl(zone.to_owned(), listing.clone());
+            listing
+        })
+    }
+
+    fn sender_has_mx(server: &Server, context: &super::Context) -> EngineResult<bool> {
+        let (cached, reverse_path) = {
+            let context = vsl_guard_ok!(context.read());
+            (
+                context
+                    .sender_has_mx()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?,
+                context
+                    .reverse_path()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .clone(),
+            )
+        };
+        if let Some(has_mx) = cached {
+            return Ok(has_mx);
+        }
+        let Some(reverse_path) = reverse_path else {
+            return Ok(true);
+        };
+
+        let resolver = server.resolvers.get_resolver_root();
+        let has_mx = block_on!(crate::mx::has_mx(
+            &resolver,
+            &reverse_path.domain().to_string(),
+            server.config.server.smtp.mx_timeout
+        ))
+        .map_err::<Box<rhai::EvalAltResult>, _>(|error| format!("temperror: {error}").into())?;
+
+        vsl_guard_ok!(context.write())
+            .set_sender_has_mx(has_mx)
+            .map_err(Into::<crate::error::RuntimeError>::into)?;
+        Ok(has_mx)
+    }
+
+    fn recipient_domain_is_local(server: &Server, context: &super::Context) -> EngineResult<bool> {
+        let domain = vsl_guard_ok!(context.read())
+            .forward_paths()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .last()
+            .ok_or_else(|| crate::error::RuntimeError::Generic {
+                message: "recipient are empty".to_string(),
+            })?
+            .domain();
+
+        let config = &server.config.server;
+        Ok(domain == config.name
+            || vsmtp_common::domain_iter(&domain.to_string()).any(|parent| {
+                parent
+                    .parse::<vsmtp_common::Domain>()
+                    .map_or(false, |parent| config.r#virtual.contains_key(&parent))
+            }))
+    }
+
+    fn dnsbl_to_map(zone: &str, listing: Option<vsmtp_common::DnsblListing>) -> rhai::Map {
+        let (records, explanation) = listing.map_or_else(
+            || (vec![], None),
+            |listing| (listing.records, listing.explanation),
+        );
+
+        rhai::Map::from_iter([
+            ("zone".into(), Dynamic::from(zone.to_owned())),
+            ("listed".into(), Dynamic::from_bool(!records.is_empty())),
+            (
+                "records".into(),
+                Dynamic::from_array(
+                    records
+                        .into_iter()
+                        .map(|record| Dynamic::from(record.to_string()))
+                        .collect(),
+                ),
+            ),
+            (
+                "explanation".into(),
+                explanation.map_or(Dynamic::UNIT, Dynamic::from),
+            ),
+        ])
+    }
 }
@@ -85,6 +85,8 @@ mod envelop {
     /// * `old_addr` - the recipient to replace.
     /// * `new_addr` - the new address to use when replacing `old_addr`.
     ///
+    /// Also available as `envelop::rewrite_rcpt`.
+    ///
     /// # Effective smtp stage
     ///
     /// `rcpt` and onwards.
@@ -107,7 +109,7 @@ mod envelop {
     /// ```
     ///
     /// # rhai-autodocs:index:2
-    #[rhai_fn(name = "rw_rcpt", return_raw)]
+    #[rhai_fn(name = "rw_rcpt", name = "rewrite_rcpt", return_raw)]
     pub fn rewrite_rcpt_str_str(
         ncc: NativeCallContext,
         old_addr: &str,
@@ -122,7 +124,7 @@ mod envelop {
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rw_rcpt", return_raw)]
+    #[rhai_fn(name = "rw_rcpt", name = "rewrite_rcpt", return_raw)]
     pub fn rewrite_rcpt_obj_str(
         ncc: NativeCallContext,
         old_addr: SharedObject,
@@ -137,7 +139,7 @@ mod envelop {
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rw_rcpt", return_raw)]
+    #[rhai_fn(name = "rw_rcpt", name = "rewrite_rcpt", return_raw)]
     pub fn rewrite_rcpt_str_obj(
         ncc: NativeCallContext,
         old_addr: &str,
@@ -152,7 +154,7 @@ mod envelop {
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rw_rcpt", return_raw)]
+    #[rhai_fn(name = "rw_rcpt", name = "rewrite_rcpt", return_raw)]
     pub fn rewrite_rcpt_obj_obj(
         ncc: NativeCallContext,
         old_addr: SharedObject,
@@ -232,6 +234,8 @@ mod envelop {
     ///
     /// * `rcpt` - the recipient to remove.
     ///
+    /// Also available as `envelop::remove_rcpt`.
+    ///
     /// # Effective smtp stage
     ///
     /// All of them.
@@ -252,16 +256,105 @@ mod envelop {
     /// ```
     ///
     /// # rhai-autodocs:index:5
-    #[rhai_fn(name = "rm_rcpt", return_raw)]
+    #[rhai_fn(name = "rm_rcpt", name = "remove_rcpt", return_raw)]
     pub fn remove_rcpt_envelop_str(ncc: NativeCallContext, addr: &str) -> EngineResult<()> {
         super::remove_rcpt_envelop(&mut get_global!(ncc, ctx), addr)
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rm_rcpt", return_raw)]
+    #[rhai_fn(name = "rm_rcpt", name = "remove_rcpt", return_raw)]
     pub fn remove_rcpt_envelop_obj(ncc: NativeCallContext, addr: SharedObject) -> EngineResult<()> {
         super::remove_rcpt_envelop(&mut get_global!(ncc, ctx), &addr.to_string())
     }
+
+    /// Rewrite the sender with the Sender Rewriting Scheme, so that a forwarded message
+    /// passes the SPF checks of the next hop. The original sender is encoded into
+    /// `SRS0=hash=timestamp=domain=local@alias_domain`, authenticated with `app.srs.secret`.
+    ///
+    /// The null sender (`<>`) of the bounces is left untouched.
+    ///
+    /// # Args
+    ///
+    /// * `alias_domain` - the domain of the forwarder, receiving the bounces.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * `app.srs` is not set in the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.srs = Some(vsmtp_config::field::FieldAppSrs {
+    /// #     secret: "secret".to_string(),
+    /// #     max_age: std::time::Duration::from_secs(21 * 24 * 60 * 60),
+    /// # });
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "forward" || envelop::srs_forward("forwarder.net"),
+    ///     ]
+    /// }
+    /// # "#)?.build()), None, config);
+    /// # let sender = states[&vsmtp_rule_engine::ExecutionStage::MailFrom].0.reverse_path().unwrap().clone().unwrap();
+    /// # assert!(sender.full().starts_with("SRS0="));
+    /// # assert!(sender.full().ends_with("=testserver.com=client@forwarder.net"));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:6
+    #[rhai_fn(name = "srs_forward", return_raw)]
+    pub fn srs_forward(ncc: NativeCallContext, alias_domain: &str) -> EngineResult<()> {
+        super::srs_forward_envelop(&mut get_global!(ncc, ctx), &get_global!(ncc, srv), alias_domain)
+    }
+
+    /// Replace the current recipient, rewritten by `envelop::srs_forward` on a previous
+    /// message, by the original sender, so that a bounce reaches its author.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` only.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - `true` if the recipient has been rewritten, `false` if it is not a `SRS0` address.
+    ///
+    /// # Errors
+    ///
+    /// * `app.srs` is not set in the configuration.
+    /// * the hash of the recipient is not valid (not produced with `app.srs.secret`).
+    /// * the recipient is older than `app.srs.max_age`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.srs = Some(vsmtp_config::field::FieldAppSrs {
+    /// #     secret: "secret".to_string(),
+    /// #     max_age: std::time::Duration::from_secs(21 * 24 * 60 * 60),
+    /// # });
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///        rule "bounce of a forwarded message" || {
+    ///            if envelop::srs_reverse() { state::accept() } else { state::next() }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#)?.build()), None, config);
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::RcptTo].2, vsmtp_common::status::Status::Next);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:7
+    #[rhai_fn(name = "srs_reverse", return_raw)]
+    pub fn srs_reverse(ncc: NativeCallContext) -> EngineResult<bool> {
+        super::srs_reverse_envelop(&mut get_global!(ncc, ctx), get_global!(ncc, srv))
+    }
 }
 
 fn rewrite_mail_from_envelop(context: &mut Context, new_addr: &str) -> EngineResult<()> {
@@ -333,3 +426,48 @@ fn remove_rcpt_envelop(context: &mut Context, addr: &str) -> EngineResult<()> {
         .map_err::<Box<rhai::EvalAltResult>, _>(|e| e.to_string().into())?;
     Ok(())
 }
+
+fn srs_forward_envelop(context: &mut Context, srv: &Server, alias_domain: &str) -> EngineResult<()> {
+    let Some(srs) = &srv.srs else {
+        return Err("SRS is disabled, `app.srs` must be set in the configuration".into());
+    };
+
+    let mut context = vsl_guard_ok!(context.write());
+    let Some(sender) = context
+        .reverse_path()
+        .map_err::<Box<rhai::EvalAltResult>, _>(|e| e.to_string().into())?
+    else {
+        return Ok(());
+    };
+
+    let rewritten = srs
+        .forward(sender, alias_domain)
+        .map_err::<Box<rhai::EvalAltResult>, _>(|e| e.to_string().into())?;
+    context
+        .set_reverse_path(Some(rewritten))
+        .map_err(|e| e.to_string().into())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn srs_reverse_envelop(context: &mut Context, srv: Server) -> EngineResult<bool> {
+    let Some(srs) = &srv.srs else {
+        return Err("SRS is disabled, `app.srs` must be set in the configuration".into());
+    };
+
+    let recipient = vsl_guard_ok!(context.read())
+        .forward_paths()
+        .map_err::<Box<rhai::EvalAltResult>, _>(|e| e.to_string().into())?
+        .last()
+        .cloned()
+        .ok_or_else::<Box<rhai::EvalAltResult>, _>(|| "recipient are empty".into())?;
+
+    let Some(original) = srs
+        .reverse(&recipient)
+        .map_err::<Box<rhai::EvalAltResult>, _>(|e| e.to_string().into())?
+    else {
+        return Ok(false);
+    };
+
+    rewrite_rcpt(context, srv.clone(), recipient.full(), original.full())?;
+    Ok(true)
+}
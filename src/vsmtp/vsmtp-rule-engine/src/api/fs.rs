@@ -15,11 +15,12 @@
  *
 */
 
-use crate::api::{Context, EngineResult, Message, Server};
+use crate::api::{Context, EngineResult, Message, Server, SharedObject};
 use rhai::plugin::{
     mem, Dynamic, EvalAltResult, FnAccess, FnNamespace, ImmutableString, Module, NativeCallContext,
     PluginFunction, RhaiResult, TypeId,
 };
+use vsmtp_common::status::Status;
 
 pub use fs::*;
 
@@ -29,17 +30,37 @@ mod fs {
     use crate::get_global;
 
     /// Export the current raw message to a file as an `eml` file.
-    /// The message id of the email is used to name the file.
+    /// The message id of the email is used to name the file, unless a template is given.
+    ///
+    /// The template is the name of the file, without the `.eml` extension, and can contain
+    /// the following placeholders:
+    ///
+    /// * `{msgid}` - the message id (the default template).
+    /// * `{txid}` - the transaction id, shared by the messages of a transaction
+    ///   (see `ctx::transaction_id()`).
+    /// * `{date}` - the timestamp of the `MAIL FROM` command, in UTC (`20230620T140312Z`),
+    ///   so that the files can be sorted chronologically.
+    /// * `{sender}` - the address of the sender, or `MAILER-DAEMON` for the null sender.
+    /// * `{rcpt}` - the addresses of the recipients, separated by a comma.
+    ///
+    /// Path separators and control characters are removed from the name of the file,
+    /// so the email is always written in `dir`.
     ///
     /// # Args
     ///
     /// * `dir` - the directory where to store the email. Relative to the
     /// application path.
+    /// * `template` - (optional) the template used to name the file.
     ///
     /// # Effective smtp stage
     ///
     /// `preq` and onwards.
     ///
+    /// # Errors
+    ///
+    /// * The template contains an unknown or unclosed placeholder.
+    /// * The folder or the file could not be created.
+    ///
     /// # Examples
     ///
     /// ```
@@ -50,6 +71,7 @@ mod fs {
     /// #{
     ///     preq: [
     ///        action "write to file" || fs::write("archives"),
+    ///        action "write to sorted file" || fs::write("sorted", "{date}_{msgid}"),
     ///     ]
     /// }
     /// # "#;
@@ -62,10 +84,21 @@ mod fs {
     /// #      .build()
     /// #   .build()), None, config);
     /// # eprintln!("{:?}", dir.path());
-    /// # assert!(std::path::PathBuf::from_iter([
-    /// #     dir.path(),
-    /// #     &std::path::Path::new("archives")
-    /// # ]).exists());
+    /// # let files = std::fs::read_dir(dir.path().join("archives"))
+    /// #     .unwrap()
+    /// #     .map(|entry| entry.unwrap().path())
+    /// #     .collect::<Vec<_>>();
+    /// # assert_eq!(files.len(), 1);
+    /// # assert_eq!(files[0].extension().unwrap(), "eml");
+    /// # let files = std::fs::read_dir(dir.path().join("sorted"))
+    /// #     .unwrap()
+    /// #     .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+    /// #     .collect::<Vec<_>>();
+    /// # assert_eq!(files.len(), 1);
+    /// # assert!(files[0].ends_with(&format!(
+    /// #     "Z_{}.eml",
+    /// #     states[&vsmtp_rule_engine::ExecutionStage::PreQ].0.message_uuid().unwrap()
+    /// # )), "{}", files[0]);
     /// ```
     ///
     /// # rhai-autodocs:index:1
@@ -76,16 +109,31 @@ mod fs {
             &get_global!(ncc, ctx),
             &get_global!(ncc, msg),
             dir,
+            super::DEFAULT_FILENAME_TEMPLATE,
+        )
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "write", return_raw)]
+    pub fn write_str_str(ncc: NativeCallContext, dir: &str, template: &str) -> EngineResult<()> {
+        super::write(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            &get_global!(ncc, msg),
+            dir,
+            template,
         )
     }
 
     /// Write the content of the current email with it's metadata in a json file.
-    /// The message id of the email is used to name the file.
+    /// The message id of the email is used to name the file, unless a template is given,
+    /// with the placeholders of `fs::write`.
     ///
     /// # Args
     ///
     /// * `dir` - the directory where to store the email. Relative to the
     /// application path.
+    /// * `template` - (optional) the template used to name the file, without the `.json` extension.
     ///
     /// # Effective smtp stage
     ///
@@ -123,52 +171,590 @@ mod fs {
     /// # rhai-autodocs:index:2
     #[rhai_fn(name = "dump", return_raw)]
     pub fn dump_str(ncc: NativeCallContext, dir: &str) -> EngineResult<()> {
-        super::dump(&get_global!(ncc, srv), &get_global!(ncc, ctx), dir)
+        super::dump(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            dir,
+            super::DEFAULT_FILENAME_TEMPLATE,
+        )
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "dump", return_raw)]
+    pub fn dump_str_str(ncc: NativeCallContext, dir: &str, template: &str) -> EngineResult<()> {
+        super::dump(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            dir,
+            template,
+        )
+    }
+
+    /// Same as `fs::write`, but the message is gzip-compressed in a `<message-id>.eml.gz` file.
+    ///
+    /// Emails are mostly text and compress well: the file is usually 3 to 5 times
+    /// smaller than the raw message. Attachments that are already compressed (images,
+    /// archives, ...) only lose the overhead of their base64 encoding, about 25%.
+    ///
+    /// # Args
+    ///
+    /// * `dir` - the directory where to store the email. Relative to the
+    /// application path.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///        action "write to compressed file" || fs::write_gz("archives"),
+    ///     ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), None, config);
+    /// # let files = std::fs::read_dir(dir.path().join("archives"))
+    /// #     .unwrap()
+    /// #     .map(|entry| entry.unwrap().path())
+    /// #     .collect::<Vec<_>>();
+    /// # assert_eq!(files.len(), 1);
+    /// # assert!(files[0].to_str().unwrap().ends_with(".eml.gz"));
+    /// # let mut body = String::new();
+    /// # std::io::Read::read_to_string(
+    /// #     &mut flate2::read::GzDecoder::new(std::fs::File::open(&files[0]).unwrap()),
+    /// #     &mut body,
+    /// # ).unwrap();
+    /// # assert_eq!(body, vsmtp_test::config::local_msg().inner().to_string());
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn(name = "write_gz", return_raw)]
+    pub fn write_gz_str(ncc: NativeCallContext, dir: &str) -> EngineResult<()> {
+        super::write_gz(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            &get_global!(ncc, msg),
+            dir,
+        )
+    }
+
+    /// Same as `fs::dump`, but the json is gzip-compressed in a `<message-id>.json.gz` file.
+    ///
+    /// The json is pretty-printed and made of repeated keys, so it usually compresses
+    /// to a fifth of its size or less.
+    ///
+    /// # Args
+    ///
+    /// * `dir` - the directory where to store the email. Relative to the
+    /// application path.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///        action "dump to compressed file" || fs::dump_gz("metadata"),
+    ///     ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), None, config);
+    /// # let files = std::fs::read_dir(dir.path().join("metadata"))
+    /// #     .unwrap()
+    /// #     .map(|entry| entry.unwrap().path())
+    /// #     .collect::<Vec<_>>();
+    /// # assert_eq!(files.len(), 1);
+    /// # assert!(files[0].to_str().unwrap().ends_with(".json.gz"));
+    /// # let json: serde_json::Value = serde_json::from_reader(
+    /// #     flate2::read::GzDecoder::new(std::fs::File::open(&files[0]).unwrap())
+    /// # ).unwrap();
+    /// # assert!(json["Finished"]["message_uuid"].is_string());
+    /// ```
+    ///
+    /// # rhai-autodocs:index:4
+    #[rhai_fn(name = "dump_gz", return_raw)]
+    pub fn dump_gz_str(ncc: NativeCallContext, dir: &str) -> EngineResult<()> {
+        super::dump_gz(&get_global!(ncc, srv), &get_global!(ncc, ctx), dir)
+    }
+
+    /// Deliver the current raw message in a maildir folder.
+    ///
+    /// The `tmp`, `new` and `cur` subdirectories are created if needed. The message
+    /// is first written in `tmp` with a unique name (`<time>.<pid>_<seq>.<host>`),
+    /// then moved into `new`, so that a mail user agent never reads a partial message.
+    ///
+    /// # Args
+    ///
+    /// * `dir` - the maildir folder where to store the email. Relative to the
+    /// application path.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * The message has not been received yet.
+    /// * The maildir folders or the message file could not be created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///        action "write to maildir" || fs::write_maildir("Maildir"),
+    ///     ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), None, config);
+    /// # let maildir = dir.path().join("Maildir");
+    /// # assert!(maildir.join("cur").exists());
+    /// # assert_eq!(std::fs::read_dir(maildir.join("tmp")).unwrap().count(), 0);
+    /// # let new = std::fs::read_dir(maildir.join("new"))
+    /// #     .unwrap()
+    /// #     .map(|entry| entry.unwrap().path())
+    /// #     .collect::<Vec<_>>();
+    /// # assert_eq!(new.len(), 1);
+    /// # assert_eq!(
+    /// #     std::fs::read_to_string(&new[0]).unwrap(),
+    /// #     vsmtp_test::config::local_msg().inner().to_string()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:5
+    #[rhai_fn(name = "write_maildir", return_raw)]
+    pub fn write_maildir_str(ncc: NativeCallContext, dir: &str) -> EngineResult<()> {
+        super::write_maildir(&get_global!(ncc, srv), &get_global!(ncc, msg), dir)
+    }
+
+    /// Append the current raw message to a mbox file.
+    ///
+    /// The message is preceded by a `From ` separator line containing the sender
+    /// and the connection timestamp of the client (see rfc4155), and lines of the
+    /// message starting with `From ` are escaped with `>`. The lines of the file end
+    /// with `LF`, the `CRLF` line endings of the message are converted.
+    /// An advisory lock (flock) is held on the file while the message is appended.
+    ///
+    /// # Args
+    ///
+    /// * `path` - the path of the mbox file, created if it does not exist.
+    /// Relative to the application path.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "From the body\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///        action "append to mbox" || fs::write_mbox("archives.mbox"),
+    ///     ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg), config);
+    /// # let mbox = std::fs::read_to_string(dir.path().join("archives.mbox")).unwrap();
+    /// # let (separator, message) = mbox.split_once('\n').unwrap();
+    /// # assert!(separator.starts_with("From "), "{separator}");
+    /// # assert_eq!(message, "Subject: Unit test are cool\n\n>From the body\n\n");
+    /// ```
+    ///
+    /// # rhai-autodocs:index:6
+    #[rhai_fn(name = "write_mbox", return_raw)]
+    pub fn write_mbox_str(ncc: NativeCallContext, path: &str) -> EngineResult<()> {
+        super::write_mbox(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            &get_global!(ncc, msg),
+            path,
+        )
+    }
+
+    /// Place the email in a named quarantine folder, and stop the delivery.
+    ///
+    /// The raw message (`.eml`) and the context of the transaction (`.json`) are written in the
+    /// `quarantine/<name>` folder of the application path, named after the message id. The context
+    /// contains a `quarantine` object with the name, the reason and the timestamp of the quarantine.
+    ///
+    /// The returned status is the same as `state::quarantine(name)`: when returned by a rule,
+    /// the following rules of the stage are skipped and the email is never delivered.
+    ///
+    /// # Args
+    ///
+    /// * `name` - the name of the quarantine folder, it cannot contain a path separator.
+    /// * `reason` - (optional) why the email was placed in quarantine.
+    ///
+    /// # Return
+    ///
+    /// * `status` - the quarantine status.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * The name is invalid.
+    /// * The quarantine folder or the files could not be created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let rules = r#"
+    /// #{
+    ///     preq: [
+    ///        rule "quarantine spam" || {
+    ///          if msg::has_header("X-Spam") {
+    ///            fs::quarantine("spam", "tagged by the anti-spam")
+    ///          } else {
+    ///            state::next()
+    ///          }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#;
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// #   "X-Spam: yes\r\n",
+    /// #   "\r\n",
+    /// #   "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg), config);
+    /// # assert_eq!(
+    /// #     states[&vsmtp_rule_engine::ExecutionStage::PreQ].2,
+    /// #     vsmtp_common::status::Status::Quarantine("spam".to_string())
+    /// # );
+    /// # assert_eq!(std::fs::read_dir(dir.path().join("quarantine/spam")).unwrap().count(), 2);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:7
+    #[rhai_fn(name = "quarantine", return_raw)]
+    pub fn quarantine_str(ncc: NativeCallContext, name: &str) -> EngineResult<Status> {
+        quarantine_str_str(ncc, name, "")
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "quarantine", return_raw)]
+    pub fn quarantine_str_str(
+        ncc: NativeCallContext,
+        name: &str,
+        reason: &str,
+    ) -> EngineResult<Status> {
+        super::quarantine(
+            &get_global!(ncc, srv),
+            &get_global!(ncc, ctx),
+            &get_global!(ncc, msg),
+            name,
+            reason,
+        )
+    }
+
+    /// Check if a value is one of the entries of a list stored in a file,
+    /// like a blocklist or an allowlist.
+    ///
+    /// The file contains one entry per line, surrounding whitespaces are trimmed,
+    /// and the empty lines and the lines starting with `#` are ignored.
+    ///
+    /// The file is read on its first lookup and kept in memory for all the connections,
+    /// it is read again only when its modification time changes.
+    ///
+    /// # Args
+    ///
+    /// * `path` - the path of the file. Relative to the application path.
+    /// * `value` - the value to look for, compared to the entries as is (case sensitive).
+    ///
+    /// # Return
+    ///
+    /// * `bool` - true if the value is one of the entries of the file.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Errors
+    ///
+    /// * The file could not be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().expect("fs api: failed to create tmpdir");
+    /// # std::fs::write(
+    /// #     dir.path().join("blocklist.txt"),
+    /// #     "# clients denied at connect\n192.0.2.1\n127.0.0.1\n",
+    /// # ).unwrap();
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(|builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     connect: [
+    ///        rule "blocklist" || {
+    ///          if fs::in_file("blocklist.txt", ctx::client_ip()) {
+    ///            state::deny()
+    ///          } else {
+    ///            state::next()
+    ///          }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#)?.build()), None, config);
+    /// # assert_eq!(
+    /// #     states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #     vsmtp_rule_engine::api::state::deny()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:8
+    #[rhai_fn(name = "in_file", return_raw)]
+    pub fn in_file_str_str(ncc: NativeCallContext, path: &str, value: &str) -> EngineResult<bool> {
+        super::in_file(&get_global!(ncc, srv), path, value)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "in_file", return_raw)]
+    pub fn in_file_str_obj(
+        ncc: NativeCallContext,
+        path: &str,
+        value: SharedObject,
+    ) -> EngineResult<bool> {
+        super::in_file(&get_global!(ncc, srv), path, &value.to_string())
     }
 }
 
-// TODO: handle canonicalization
-fn write(srv: &Server, ctx: &Context, message: &Message, dir: &str) -> EngineResult<()> {
-    let mut dir = srv.config.app.dirpath.join(dir);
+/// Look for `value` in the list at `path`, relative to the application path.
+fn in_file(srv: &Server, path: &str, value: &str) -> EngineResult<bool> {
+    let path = srv.config.app.dirpath.join(path);
+    srv.lists
+        .contains(&path, value)
+        .map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("cannot read list '{}': {err}", path.display()).into()
+        })
+}
+
+/// Create a folder (and its parents) relative to the application path.
+fn create_app_folder(srv: &Server, dir: &str) -> EngineResult<std::path::PathBuf> {
+    let dir = srv.config.app.dirpath.join(dir);
     std::fs::create_dir_all(&dir).map_err::<Box<EvalAltResult>, _>(|err| {
         format!("cannot create folder '{}': {err}", dir.display()).into()
     })?;
 
-    dir.push(format!(
-        "{}.eml",
-        vsl_guard_ok!(ctx.read())
-            .message_uuid()
-            .map_err(Into::<crate::error::RuntimeError>::into)?
-    ));
+    Ok(dir)
+}
+
+/// Write `content` at `path`, gzip-compressed if `gzip` is set.
+///
+/// The content is written in a temporary file first, then renamed,
+/// so that a partially written file is never visible at `path`.
+fn write_atomically(path: &std::path::Path, content: &[u8], gzip: bool) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
 
-    let file = std::fs::OpenOptions::new()
+    let result = std::fs::OpenOptions::new()
         .create(true)
         .write(true)
-        .open(&dir)
-        .map_err::<Box<EvalAltResult>, _>(|err| {
-            format!("failed to write email at {}: {err}", dir.display()).into()
-        })?;
-    let mut writer = std::io::LineWriter::new(file);
+        .truncate(true)
+        .open(&tmp)
+        .and_then(|file| {
+            let file = if gzip {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(file, flate2::Compression::default());
+                std::io::Write::write_all(&mut encoder, content)?;
+                encoder.finish()?
+            } else {
+                let mut writer = std::io::LineWriter::new(file);
+                std::io::Write::write_all(&mut writer, content)?;
+                writer
+                    .into_inner()
+                    .map_err(std::io::IntoInnerError::into_error)?
+            };
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&tmp, path));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Name of the files written by `fs::write` when no template is given.
+const DEFAULT_FILENAME_TEMPLATE: &str = "{msgid}";
+
+const FILENAME_DATE_FORMAT: &[time::format_description::FormatItem<'_>] =
+    time::macros::format_description!("[year][month][day]T[hour][minute][second]Z");
+
+/// Expand the placeholders of a filename template, and remove the path separators
+/// and control characters of the result.
+fn expand_filename_template(template: &str, ctx: &vsmtp_common::Context) -> EngineResult<String> {
+    let mut filename = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        filename.push_str(&rest[..start]);
+        let (placeholder, tail) = rest[start + 1..]
+            .split_once('}')
+            .ok_or_else(|| format!("unclosed placeholder in the filename template '{template}'"))?;
+
+        match placeholder {
+            "msgid" => filename.push_str(
+                &ctx.message_uuid()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .to_string(),
+            ),
+            "txid" => filename.push_str(
+                &ctx.transaction_id()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .to_string(),
+            ),
+            "date" => filename.push_str(
+                &ctx.mail_timestamp()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .to_offset(time::UtcOffset::UTC)
+                    .format(&FILENAME_DATE_FORMAT)
+                    .map_err::<Box<EvalAltResult>, _>(|err| err.to_string().into())?,
+            ),
+            "sender" => filename.push_str(
+                &ctx.reverse_path()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .as_ref()
+                    .map_or_else(|| "MAILER-DAEMON".to_owned(), ToString::to_string),
+            ),
+            "rcpt" => filename.push_str(
+                &ctx.forward_paths()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder '{{{placeholder}}}' in the filename template '{template}'"
+                )
+                .into())
+            }
+        }
+        rest = tail;
+    }
+    filename.push_str(rest);
+
+    // NOTE: prevent a directory traversal with the values of the context, like `../`.
+    Ok(filename
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\') && !c.is_control())
+        .collect())
+}
+
+// TODO: handle canonicalization
+fn write_eml(
+    srv: &Server,
+    ctx: &Context,
+    message: &Message,
+    dir: &str,
+    template: &str,
+    gzip: bool,
+) -> EngineResult<()> {
+    let mut dir = create_app_folder(srv, dir)?;
+
+    dir.push(format!(
+        "{}.eml{}",
+        expand_filename_template(template, &vsl_guard_ok!(ctx.read()))?,
+        if gzip { ".gz" } else { "" }
+    ));
 
     let body = &message
         .read()
         .map_err::<Box<EvalAltResult>, _>(|e| e.to_string().into())?;
 
-    std::io::Write::write_all(&mut writer, body.inner().to_string().as_bytes())
-        .map_err(|err| format!("failed to write email at {dir:?}: {err}").into())
+    write_atomically(&dir, body.inner().to_string().as_bytes(), gzip)
+        .map_err(|err| format!("failed to write email at {}: {err}", dir.display()).into())
 }
 
-fn dump(srv: &Server, ctx: &Context, dir: &str) -> EngineResult<()> {
-    let mut dir = srv.config.app.dirpath.join(dir);
-    std::fs::create_dir_all(&dir).map_err::<Box<EvalAltResult>, _>(|err| {
-        format!("cannot create folder '{}': {err}", dir.display()).into()
-    })?;
+fn write(
+    srv: &Server,
+    ctx: &Context,
+    message: &Message,
+    dir: &str,
+    template: &str,
+) -> EngineResult<()> {
+    write_eml(srv, ctx, message, dir, template, false)
+}
+
+fn write_gz(srv: &Server, ctx: &Context, message: &Message, dir: &str) -> EngineResult<()> {
+    write_eml(srv, ctx, message, dir, DEFAULT_FILENAME_TEMPLATE, true)
+}
+
+fn dump(srv: &Server, ctx: &Context, dir: &str, template: &str) -> EngineResult<()> {
+    let mut dir = create_app_folder(srv, dir)?;
 
     dir.push(format!(
         "{}.json",
-        vsl_guard_ok!(ctx.read())
-            .message_uuid()
-            .map_err(Into::<crate::error::RuntimeError>::into)?
+        expand_filename_template(template, &vsl_guard_ok!(ctx.read()))?
     ));
 
     let mut file = std::fs::OpenOptions::new()
@@ -189,3 +775,179 @@ fn dump(srv: &Server, ctx: &Context, dir: &str) -> EngineResult<()> {
     )
     .map_err(|err| format!("failed to dump email at {dir:?}: {err}").into())
 }
+
+fn dump_gz(srv: &Server, ctx: &Context, dir: &str) -> EngineResult<()> {
+    let mut dir = create_app_folder(srv, dir)?;
+    let ctx = vsl_guard_ok!(ctx.read());
+
+    dir.push(format!(
+        "{}.json.gz",
+        ctx.message_uuid()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+    ));
+
+    let json = serde_json::to_string_pretty(&*ctx).map_err::<Box<EvalAltResult>, _>(|err| {
+        format!("failed to dump email at {}: {err}", dir.display()).into()
+    })?;
+
+    write_atomically(&dir, json.as_bytes(), true)
+        .map_err(|err| format!("failed to dump email at {}: {err}", dir.display()).into())
+}
+
+fn quarantine(
+    srv: &Server,
+    ctx: &Context,
+    message: &Message,
+    name: &str,
+    reason: &str,
+) -> EngineResult<Status> {
+    if name.is_empty() || name == ".." || name.contains(std::path::is_separator) {
+        return Err(format!("invalid quarantine name '{name}'").into());
+    }
+
+    vsl_generic_ok!(vsl_guard_ok!(ctx.write()).set_quarantine(name.to_string(), reason.to_string()));
+
+    let dir = format!("quarantine/{name}");
+    write_eml(srv, ctx, message, &dir, DEFAULT_FILENAME_TEMPLATE, false)?;
+
+    let mut path = create_app_folder(srv, &dir)?;
+    let ctx = vsl_generic_ok!(vsl_guard_ok!(ctx.read()).clone().unwrap_finished());
+    path.push(format!("{}.json", ctx.mail_from.message_uuid));
+
+    // NOTE: same file and format as the context stored by the server in the quarantine queue
+    //       when the status is returned, so the record is kept when the server rewrites it.
+    let json = serde_json::to_string_pretty(&ctx).map_err::<Box<EvalAltResult>, _>(|err| {
+        format!("failed to dump email at {}: {err}", path.display()).into()
+    })?;
+    write_atomically(&path, json.as_bytes(), false).map_err::<Box<EvalAltResult>, _>(|err| {
+        format!("failed to dump email at {}: {err}", path.display()).into()
+    })?;
+
+    tracing::warn!(name, reason, "Email placed in quarantine.");
+
+    Ok(Status::Quarantine(name.to_string()))
+}
+
+fn write_maildir(srv: &Server, message: &Message, dir: &str) -> EngineResult<()> {
+    static SEQUENCE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    let body = vsl_guard_ok!(message.read());
+    if *body == vsmtp_mail_parser::MessageBody::default() {
+        return Err(
+            "cannot write the message in a maildir: the message has not been received yet".into(),
+        );
+    }
+
+    let maildir = srv.config.app.dirpath.join(dir);
+    for sub in ["tmp", "new", "cur"] {
+        let sub = maildir.join(sub);
+        std::fs::create_dir_all(&sub).map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("cannot create folder '{}': {err}", sub.display()).into()
+        })?;
+    }
+
+    let filename = format!(
+        "{}.{}_{}.{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs()),
+        std::process::id(),
+        SEQUENCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        // NOTE: '/' and ':' are not allowed in a maildir file name.
+        srv.config
+            .server
+            .name
+            .to_string()
+            .replace('/', "\\057")
+            .replace(':', "\\072")
+    );
+    let tmp = maildir.join("tmp").join(&filename);
+    let new = maildir.join("new").join(&filename);
+
+    let mut file = std::fs::OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(&tmp)
+        .map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("failed to write email at {}: {err}", tmp.display()).into()
+        })?;
+
+    let result = std::io::Write::write_all(&mut file, body.inner().to_string().as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("failed to write email at {}: {err}", tmp.display()).into()
+        })
+        .and_then(|()| {
+            std::fs::rename(&tmp, &new).map_err(|err| {
+                format!(
+                    "failed to move email from {} to {}: {err}",
+                    tmp.display(),
+                    new.display()
+                )
+                .into()
+            })
+        });
+
+    // NOTE: the `tmp` folder of a maildir only holds the messages being delivered.
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+const CTIME_FORMAT: &[time::format_description::FormatItem<'_>] = time::macros::format_description!(
+    "[weekday repr:short] [month repr:short] [day padding:space] [hour]:[minute]:[second] [year]"
+);
+
+fn write_mbox(srv: &Server, ctx: &Context, message: &Message, path: &str) -> EngineResult<()> {
+    let path = srv.config.app.dirpath.join(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("cannot create folder '{}': {err}", parent.display()).into()
+        })?;
+    }
+
+    let separator = {
+        let ctx = vsl_guard_ok!(ctx.read());
+        format!(
+            "From {} {}\n",
+            ctx.reverse_path()
+                .map_err(Into::<crate::error::RuntimeError>::into)?
+                .as_ref()
+                .map_or_else(|| "MAILER-DAEMON".to_owned(), ToString::to_string),
+            ctx.connection_timestamp()
+                .format(&CTIME_FORMAT)
+                .map_err::<Box<EvalAltResult>, _>(|err| err.to_string().into())?
+        )
+    };
+
+    let message = vsl_guard_ok!(message.read()).inner().to_string();
+    let mut content = separator;
+    // NOTE: a mbox is a local unix file, its lines end with LF like the separator.
+    for line in message.lines() {
+        if line.trim_start_matches('>').starts_with("From ") {
+            content.push('>');
+        }
+        content.push_str(line);
+        content.push('\n');
+    }
+    // NOTE: messages in a mbox are separated by an empty line.
+    content.push('\n');
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err::<Box<EvalAltResult>, _>(|err| {
+            format!("failed to open mbox at {}: {err}", path.display()).into()
+        })?;
+
+    vsmtp_common::libc_abstraction::flock_exclusive(&file).map_err::<Box<EvalAltResult>, _>(
+        |err| format!("failed to lock mbox at {}: {err}", path.display()).into(),
+    )?;
+
+    // NOTE: the lock is released when the file is closed.
+    std::io::Write::write_all(&mut file, content.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|err| format!("failed to write email at {}: {err}", path.display()).into())
+}
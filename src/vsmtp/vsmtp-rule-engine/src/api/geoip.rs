@@ -0,0 +1,123 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::api::EngineResult;
+use crate::get_global;
+use rhai::plugin::{
+    Dynamic, FnAccess, FnNamespace, Module, NativeCallContext, PluginFunction, RhaiResult, TypeId,
+};
+
+pub use geoip::*;
+
+/// Geolocation of the clients, with the `MaxMind` databases of `app.geoip`.
+#[rhai::plugin::export_module]
+mod geoip {
+
+    /// Get the autonomous system of the client address, from the database `app.geoip.asn`.
+    ///
+    /// # Return
+    ///
+    /// * `map` - the `number` and the `organization` of the autonomous system,
+    ///   empty if the address is not in the database.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Errors
+    ///
+    /// * `app.geoip.asn` is not set in the configuration.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #{
+    ///   connect: [
+    ///     rule "deny a hosting provider" || {
+    ///       if geoip::client_asn().number == 64496 {
+    ///         state::deny()
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:1
+    #[rhai_fn(name = "client_asn", return_raw)]
+    pub fn client_asn(ncc: NativeCallContext) -> EngineResult<rhai::Map> {
+        let srv = get_global!(ncc, srv);
+        let Some(geoip) = srv.geoip.as_ref().filter(|geoip| geoip.has_asn()) else {
+            return Err("`app.geoip.asn` must be set in the configuration".into());
+        };
+        let client_ip = vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .client_addr()
+            .ip();
+
+        Ok(geoip
+            .asn(client_ip)
+            .map(|asn| {
+                rhai::Map::from_iter([
+                    (
+                        "number".into(),
+                        rhai::Dynamic::from(rhai::INT::from(asn.number)),
+                    ),
+                    ("organization".into(), asn.organization.into()),
+                ])
+            })
+            .unwrap_or_default())
+    }
+
+    /// Get the ISO 3166-1 code of the country of the client address ("FR", "US" ...),
+    /// from the database `app.geoip.country`.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the code of the country, empty if the address is not in the database.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Errors
+    ///
+    /// * `app.geoip.country` is not set in the configuration.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #{
+    ///   connect: [
+    ///     action "log the country" || log("info", `client from ${geoip::client_country()}`),
+    ///   ],
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:2
+    #[rhai_fn(name = "client_country", return_raw)]
+    pub fn client_country(ncc: NativeCallContext) -> EngineResult<String> {
+        let srv = get_global!(ncc, srv);
+        let Some(geoip) = srv.geoip.as_ref().filter(|geoip| geoip.has_country()) else {
+            return Err("`app.geoip.country` must be set in the configuration".into());
+        };
+        let client_ip = vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .client_addr()
+            .ip();
+
+        Ok(geoip.country(client_ip).unwrap_or_default())
+    }
+}
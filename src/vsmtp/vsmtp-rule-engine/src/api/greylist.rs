@@ -0,0 +1,122 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::api::EngineResult;
+use crate::get_global;
+use rhai::plugin::{
+    Dynamic, FnAccess, FnNamespace, Module, NativeCallContext, PluginFunction, RhaiResult, TypeId,
+};
+use vsmtp_common::status::Status;
+
+pub use greylist::*;
+
+/// Greylisting of the clients, see <https://www.rfc-editor.org/rfc/rfc6647>.
+#[rhai::plugin::export_module]
+mod greylist {
+    use crate::greylist::Decision;
+    use vsmtp_common::RejectionReason;
+
+    /// Defer the first attempt of a triplet (client network, sender, recipient),
+    /// and accept the retries of the triplet once `app.greylist.delay` has elapsed.
+    ///
+    /// The client network is the `/24` of the client address in IPv4 and the `/64` in IPv6,
+    /// so that the retries from another address of a pool of servers are accepted.
+    /// The triplets are stored in `{app.dirpath}/greylist.json` and removed once
+    /// they have not been seen for `app.greylist.expiry`.
+    ///
+    /// The decision is written in the logs.
+    ///
+    /// # Return
+    ///
+    /// * `state::next()` - the client has retried after the delay.
+    /// * `state::reject(code::c451_7_1())` - the triplet is deferred, the reply can be changed
+    ///   with `server.smtp.replies.greylisted`.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` only.
+    ///
+    /// # Errors
+    ///
+    /// * `app.greylist` is not set in the configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let mut config = vsmtp_test::config::local_test();
+    /// # config.app.dirpath = dir.path().into();
+    /// # config.app.greylist = Some(vsmtp_config::field::FieldAppGreylist {
+    /// #     delay: std::time::Duration::from_secs(300),
+    /// #     expiry: std::time::Duration::from_secs(3600),
+    /// # });
+    /// # let states = vsmtp_test::vsl::run_with_msg_and_config(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///         rule "greylisting" || greylist::check(),
+    ///     ]
+    /// }
+    /// # "#)?.build()), None, config);
+    /// # use vsmtp_common::{status::Status, Reply};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::RcptTo].2,
+    /// #   Status::Reject(
+    /// #     "451 4.7.1 Sender is not authorized. Please try again.\r\n".parse().unwrap(),
+    /// #   )
+    /// # );
+    /// # assert!(dir.path().join("greylist.json").exists());
+    /// ```
+    ///
+    /// # rhai-autodocs:index:1
+    #[rhai_fn(name = "check", return_raw)]
+    pub fn check(ncc: NativeCallContext) -> EngineResult<Status> {
+        let srv = get_global!(ncc, srv);
+        let Some(greylist) = &srv.greylist else {
+            return Err(
+                "greylisting is disabled, `app.greylist` must be set in the configuration".into(),
+            );
+        };
+
+        let (client_ip, sender, recipient) = {
+            let ctx = get_global!(ncc, ctx);
+            let ctx = vsl_guard_ok!(ctx.read());
+
+            (
+                ctx.client_addr().ip(),
+                ctx.reverse_path()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .clone(),
+                ctx.forward_paths()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .last()
+                    .ok_or_else(|| crate::error::RuntimeError::Generic {
+                        message: "recipient are empty".to_string(),
+                    })?
+                    .clone(),
+            )
+        };
+
+        Ok(
+            match greylist.check(client_ip, sender.as_ref(), &recipient) {
+                Decision::Passed => Status::Next,
+                Decision::FirstContact | Decision::TooEarly => {
+                    Status::Reject(srv.config.server.smtp.reply(RejectionReason::Greylisted))
+                }
+            },
+        )
+    }
+}
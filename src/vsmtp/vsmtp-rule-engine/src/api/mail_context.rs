@@ -22,8 +22,10 @@ use crate::{
     get_global,
 };
 use rhai::plugin::{
-    Dynamic, FnAccess, FnNamespace, Module, NativeCallContext, PluginFunction, RhaiResult, TypeId,
+    mem, Dynamic, FnAccess, FnNamespace, ImmutableString, Module, NativeCallContext,
+    PluginFunction, RhaiResult, TypeId,
 };
+use vsmtp_common::{Address, NotifyOn};
 use vsmtp_plugin_vsl::objects::Object;
 
 pub use mail_context::*;
@@ -144,6 +146,100 @@ mod mail_context {
         ))
     }
 
+    /// Get the names of the client, resolved from the PTR records of its address.
+    ///
+    /// The lookup is performed on the first call with the DNS resolver of the server,
+    /// and gives up after `server.smtp.rdns_timeout` (2 seconds by default).
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the name of the client, or an empty string if its address has no PTR record.
+    /// * `array` - the names of the client, if its address has multiple PTR records.
+    ///
+    /// # Example
+    ///
+    ///```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "require a PTR record" || {
+    ///       if ctx::client_rdns() == "" {
+    ///         state::deny("550 5.7.25 The client address has no PTR record")
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:5
+    #[rhai_fn(name = "client_rdns", return_raw)]
+    pub fn client_rdns(ncc: NativeCallContext) -> EngineResult<Dynamic> {
+        let ctx = get_global!(ncc, ctx);
+
+        if vsl_guard_ok!(ctx.read()).client_rdns().is_none() {
+            let srv = get_global!(ncc, srv);
+            let resolver = srv.resolvers.get_resolver_root();
+            let timeout = srv.config.server.smtp.rdns_timeout;
+            let ip = vsl_guard_ok!(ctx.read()).client_addr().ip();
+
+            let names = block_on!(crate::client_rdns(&resolver, ip, timeout));
+            vsl_guard_ok!(ctx.write()).set_client_rdns(names);
+        }
+
+        let mut names = vsl_guard_ok!(ctx.read())
+            .client_rdns()
+            .unwrap_or_default()
+            .iter()
+            .map(|name| name.to_string().trim_end_matches('.').to_string())
+            .collect::<Vec<_>>();
+
+        Ok(match names.len() {
+            0 => Dynamic::from(String::new()),
+            1 => Dynamic::from(names.swap_remove(0)),
+            _ => Dynamic::from_array(names.into_iter().map(Dynamic::from).collect()),
+        })
+    }
+
+    /// Is the client address rejected by the `server.smtp.access` lists of the configuration.
+    ///
+    /// A blocked connection is closed once the `connect` rules have run,
+    /// whatever their status.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Example
+    ///
+    ///```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     action "log blocked clients" || {
+    ///       if ctx::connection_blocked() {
+    ///         log("warn", `connection from ${ctx::client_ip()} blocked`);
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:6
+    #[rhai_fn(name = "connection_blocked", return_raw)]
+    pub fn connection_blocked(ncc: NativeCallContext) -> EngineResult<bool> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read()).connection_blocked())
+    }
+
     /// Get the full server address.
     ///
     /// # Effective smtp stage
@@ -169,7 +265,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:5
+    /// # rhai-autodocs:index:7
     #[rhai_fn(name = "server_address", return_raw)]
     pub fn server_address(ncc: NativeCallContext) -> EngineResult<String> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -202,7 +298,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:6
+    /// # rhai-autodocs:index:8
     #[rhai_fn(name = "server_ip", return_raw)]
     pub fn server_ip(ncc: NativeCallContext) -> EngineResult<String> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -236,7 +332,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:7
+    /// # rhai-autodocs:index:9
     #[rhai_fn(name = "server_port", return_raw)]
     pub fn server_port(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
         Ok(rhai::INT::from(
@@ -271,7 +367,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:8
+    /// # rhai-autodocs:index:10
     #[rhai_fn(name = "connection_timestamp", return_raw)]
     pub fn connection_timestamp(ncc: NativeCallContext) -> EngineResult<time::OffsetDateTime> {
         Ok(*vsl_guard_ok!(get_global!(ncc, ctx).read()).connection_timestamp())
@@ -302,7 +398,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:9
+    /// # rhai-autodocs:index:11
     #[rhai_fn(name = "server_name", return_raw)]
     pub fn server_name(ncc: NativeCallContext) -> EngineResult<String> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -335,12 +431,239 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:10
+    /// # rhai-autodocs:index:12
     #[rhai_fn(name = "is_secured", return_raw)]
     pub fn is_secured(ncc: NativeCallContext) -> EngineResult<bool> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read()).tls().is_some())
     }
 
+    /// Get the version of the TLS protocol negotiated with the client.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// all of them.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the protocol version (`TLSv1_2` or `TLSv1_3`), or an empty string
+    ///   if the connection is not secured.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   helo: [
+    ///     rule "deny tls 1.2" || {
+    ///       if ctx::tls_version() == "TLSv1_2" { state::deny() } else { state::next() }
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:13
+    #[rhai_fn(name = "tls_version", return_raw)]
+    pub fn tls_version(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .tls()
+            .as_ref()
+            .map(|tls| tls.protocol_version.to_string())
+            .unwrap_or_default())
+    }
+
+    /// Get the cipher suite negotiated with the client.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// all of them.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the cipher suite (for example `TLS_AES_256_GCM_SHA384`), or an empty string
+    ///   if the connection is not secured.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   helo: [
+    ///     action "log cipher" || {
+    ///       log("info", `cipher suite: ${ctx::tls_cipher()}`)
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:14
+    #[rhai_fn(name = "tls_cipher", return_raw)]
+    pub fn tls_cipher(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .tls()
+            .as_ref()
+            .map(|tls| tls.cipher_suite.to_string())
+            .unwrap_or_default())
+    }
+
+    /// Get the server name indicated by the client during the TLS handshake (SNI).
+    ///
+    /// # Effective smtp stage
+    ///
+    /// all of them.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the server name requested by the client, or an empty string
+    ///   if the connection is not secured or if the client did not send one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   helo: [
+    ///     action "log sni" || {
+    ///       log("info", `sni: ${ctx::sni()}`)
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:15
+    #[rhai_fn(name = "sni", return_raw)]
+    pub fn sni(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .tls()
+            .as_ref()
+            .and_then(|tls| tls.sni.as_ref())
+            .map(ToString::to_string)
+            .unwrap_or_default())
+    }
+
+    /// Get the subject of the certificate presented by the client during the TLS handshake.
+    ///
+    /// The clients are requested a certificate only if `server.tls.client_auth` is set.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// all of them.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the distinguished name of the subject (for example `CN=client.com, O=Example`),
+    ///   or an empty string if the client did not present a certificate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   helo: [
+    ///     rule "client certificate" || {
+    ///       if ctx::client_cert_subject() == "" { state::deny() } else { state::next() }
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:16
+    #[rhai_fn(name = "client_cert_subject", return_raw)]
+    pub fn client_cert_subject(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .tls()
+            .as_ref()
+            .and_then(|tls| tls.client_certificate.as_ref())
+            .map(|cert| cert.subject.clone())
+            .unwrap_or_default())
+    }
+
+    /// Get the subject alternative names of the certificate presented by the client
+    /// during the TLS handshake.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// all of them.
+    ///
+    /// # Return
+    ///
+    /// * `Array of strings` - the names prefixed by their type (`DNS:`, `email:`, `URI:` or `IP:`),
+    ///   or an empty array if the client did not present a certificate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   mail: [
+    ///     rule "relay for certified clients" || {
+    ///       if "DNS:relay.example.com" in ctx::client_cert_san() { state::accept() } else { state::next() }
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:17
+    #[rhai_fn(name = "client_cert_san", return_raw)]
+    pub fn client_cert_san(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .tls()
+            .as_ref()
+            .and_then(|tls| tls.client_certificate.as_ref())
+            .map(|cert| {
+                cert.subject_alt_names
+                    .iter()
+                    .cloned()
+                    .map(Dynamic::from)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Has the client requested the message to be relayed only over TLS,
+    /// using the `REQUIRETLS` option of the `MAIL FROM` command (rfc 8689).
+    ///
+    /// The option is only accepted if the connection is secured (see `ctx::is_secured`),
+    /// the delivery will then refuse to relay the message over an unencrypted connection.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * bool - `true` if the client used the `REQUIRETLS` option, `false` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   mail: [
+    ///     action "log requiretls" || {
+    ///       log("info", `REQUIRETLS: ${ctx::is_require_tls()}`)
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:18
+    #[rhai_fn(name = "is_require_tls", return_raw)]
+    pub fn is_require_tls(ncc: NativeCallContext) -> EngineResult<bool> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read()).is_require_tls())
+    }
+
     /// Get the value of the `HELO/EHLO` command sent by the client.
     ///
     /// # Effective smtp stage
@@ -364,7 +687,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:11
+    /// # rhai-autodocs:index:19
     #[rhai_fn(name = "helo", return_raw)]
     pub fn helo(ncc: NativeCallContext) -> EngineResult<String> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -373,6 +696,42 @@ mod mail_context {
             .map_err(Into::<crate::error::RuntimeError>::into)?)
     }
 
+    /// Get the capabilities advertised by the server in the reply to the `EHLO` command.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards. The reply is produced after the rules of the `helo` stage.
+    ///
+    /// # Return
+    ///
+    /// * `Array of strings` - the capabilities with their parameters (`SIZE 20000000`),
+    ///   empty if the client used `HELO`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "log capabilities" || log("info", `capabilities: ${ctx::advertised_capabilities()}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:20
+    #[rhai_fn(name = "advertised_capabilities", return_raw)]
+    pub fn advertised_capabilities(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .advertised_capabilities()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .iter()
+            .cloned()
+            .map(rhai::Dynamic::from)
+            .collect())
+    }
+
     /// Get the value of the `MAIL FROM` command sent by the client.
     ///
     /// # Effective smtp stage
@@ -395,7 +754,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:12
+    /// # rhai-autodocs:index:21
     #[rhai_fn(return_raw)]
     pub fn mail_from(ncc: NativeCallContext) -> EngineResult<SharedObject> {
         let reverse_path = vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -433,7 +792,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:13
+    /// # rhai-autodocs:index:22
     #[rhai_fn(name = "rcpt_list", return_raw)]
     pub fn rcpt_list(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -447,6 +806,43 @@ mod mail_context {
             .collect())
     }
 
+    /// Get the number of recipients received by the client.
+    ///
+    /// The server refuses the recipients above `server.smtp.rcpt_count_max`
+    /// with a `452` reply, so the number returned never exceeds this limit.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` and onwards. In the `rcpt` stage, the current recipient is included.
+    ///
+    /// # Return
+    ///
+    /// * `int` - the number of recipients.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///        action "log recipient count" || log("info", `recipient #${ctx::rcpt_count()}: ${ctx::rcpt()}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:23
+    #[rhai_fn(name = "rcpt_count", return_raw)]
+    pub fn rcpt_count(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        let count = vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .forward_paths()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .len();
+
+        Ok(rhai::INT::try_from(count).unwrap_or(rhai::INT::MAX))
+    }
+
     /// Get the value of the current `RCPT TO` command sent by the client.
     ///
     /// # Effective smtp stage
@@ -472,7 +868,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:14
+    /// # rhai-autodocs:index:24
     #[rhai_fn(name = "rcpt", return_raw)]
     pub fn rcpt(ncc: NativeCallContext) -> EngineResult<SharedObject> {
         let rcpt = vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -487,6 +883,222 @@ mod mail_context {
         Ok(std::sync::Arc::new(Object::Address(rcpt)))
     }
 
+    /// Get the `ENVID` argument of the `MAIL FROM` command (rfc 3461),
+    /// an identifier of the transaction chosen by the client and sent back in the delivery status notifications.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the envelope id.
+    /// * `()` - the client did not use the `ENVID` argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run_with_msg(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "log envid" || log("info", `envelope id: ${ctx::dsn_envid()}`),
+    /// #      rule "assert" || if ctx::dsn_envid() == () { state::accept() } else { state::deny() },
+    ///     ]
+    /// }
+    /// # "#)?.build()), None,
+    /// # );
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # use vsmtp_rule_engine::ExecutionStage;
+    /// # assert_eq!(states[&ExecutionStage::MailFrom].2, Status::Accept(
+    /// #  "250 Ok\r\n".parse::<Reply>().unwrap(),
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:25
+    #[rhai_fn(name = "dsn_envid", return_raw)]
+    pub fn dsn_envid(ncc: NativeCallContext) -> EngineResult<rhai::Dynamic> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .envelop_id()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .map_or(rhai::Dynamic::UNIT, |envid| envid.to_string().into()))
+    }
+
+    /// Get the `RET` argument of the `MAIL FROM` command (rfc 3461),
+    /// telling if the delivery status notifications must include the full message or only its headers.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `string` - "full" or "hdrs".
+    /// * `()` - the client did not use the `RET` argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "log ret" || log("info", `dsn return: ${ctx::dsn_ret()}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:26
+    #[rhai_fn(name = "dsn_ret", return_raw)]
+    pub fn dsn_ret(ncc: NativeCallContext) -> EngineResult<rhai::Dynamic> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .dsn_return()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .map_or(rhai::Dynamic::UNIT, |ret| {
+                match ret {
+                    vsmtp_common::DsnReturn::Full => "full",
+                    vsmtp_common::DsnReturn::Headers => "hdrs",
+                }
+                .into()
+            }))
+    }
+
+    /// Get the `AUTH` argument of the `MAIL FROM` command (rfc 4954), the identity of the
+    /// submitter of the message asserted by the client.
+    ///
+    /// The argument is only trusted from authenticated clients, and from the addresses
+    /// in `server.smtp.xclient_trusted`. It is replaced by `AUTH=<>` for any other client.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `address` - the asserted identity of the submitter.
+    /// * `()` - the submitter is unknown (`AUTH=<>`, no `AUTH` argument, or an untrusted client).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run_with_msg(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "log submitter" || log("info", `submitted by: ${ctx::asserted_sender()}`),
+    /// #      rule "assert" || if ctx::asserted_sender() == () { state::accept() } else { state::deny() },
+    ///     ]
+    /// }
+    /// # "#)?.build()), None,
+    /// # );
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # use vsmtp_rule_engine::ExecutionStage;
+    /// # assert_eq!(states[&ExecutionStage::MailFrom].2, Status::Accept(
+    /// #  "250 Ok\r\n".parse::<Reply>().unwrap(),
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:27
+    #[rhai_fn(name = "asserted_sender", return_raw)]
+    pub fn asserted_sender(ncc: NativeCallContext) -> EngineResult<rhai::Dynamic> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .asserted_sender()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .map_or(rhai::Dynamic::UNIT, |sender| {
+                rhai::Dynamic::from(SharedObject::new(Object::Address(sender.clone())))
+            }))
+    }
+
+    /// Get the `NOTIFY` argument of the `RCPT TO` command of a recipient (rfc 3461),
+    /// the events producing a delivery status notification for this recipient.
+    ///
+    /// # Args
+    ///
+    /// * `rcpt` - the address of the recipient.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `Array of strings` - `["never"]`, or any of "success", "failure" and "delay".
+    ///   If the client did not use the `NOTIFY` argument, only failures are notified.
+    /// * `()` - the address is not a recipient received with the `RCPT TO` command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///        action "log notify" || log("info", `notify ${ctx::rcpt()} on: ${ctx::dsn_notify(ctx::rcpt())}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:28
+    #[rhai_fn(name = "dsn_notify", return_raw)]
+    pub fn dsn_notify_str(ncc: NativeCallContext, rcpt: &str) -> EngineResult<rhai::Dynamic> {
+        super::dsn_notify(&get_global!(ncc, ctx), rcpt)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "dsn_notify", return_raw)]
+    pub fn dsn_notify_obj(
+        ncc: NativeCallContext,
+        rcpt: SharedObject,
+    ) -> EngineResult<rhai::Dynamic> {
+        super::dsn_notify(&get_global!(ncc, ctx), &rcpt.to_string())
+    }
+
+    /// Get the `ORCPT` argument of the `RCPT TO` command of a recipient (rfc 3461),
+    /// the original address of the recipient given by the client.
+    ///
+    /// # Args
+    ///
+    /// * `rcpt` - the address of the recipient.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `rcpt` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the original recipient, with its address type (i.e. "rfc822;john.doe@example.com").
+    /// * `()` - the client did not use the `ORCPT` argument for this recipient.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///        action "log orcpt" || log("info", `original recipient: ${ctx::dsn_orcpt(ctx::rcpt())}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:29
+    #[rhai_fn(name = "dsn_orcpt", return_raw)]
+    pub fn dsn_orcpt_str(ncc: NativeCallContext, rcpt: &str) -> EngineResult<rhai::Dynamic> {
+        super::dsn_orcpt(&get_global!(ncc, ctx), rcpt)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "dsn_orcpt", return_raw)]
+    pub fn dsn_orcpt_obj(
+        ncc: NativeCallContext,
+        rcpt: SharedObject,
+    ) -> EngineResult<rhai::Dynamic> {
+        super::dsn_orcpt(&get_global!(ncc, ctx), &rcpt.to_string())
+    }
+
     /// Get the time of reception of the email.
     ///
     /// # Effective smtp stage
@@ -510,7 +1122,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:15
+    /// # rhai-autodocs:index:30
     #[rhai_fn(name = "mail_timestamp", return_raw)]
     pub fn mail_timestamp(ncc: NativeCallContext) -> EngineResult<time::OffsetDateTime> {
         Ok(*vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -541,7 +1153,7 @@ mod mail_context {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:16
+    /// # rhai-autodocs:index:31
     #[rhai_fn(name = "message_id", return_raw)]
     pub fn message_id(ncc: NativeCallContext) -> EngineResult<String> {
         Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
@@ -549,4 +1161,144 @@ mod mail_context {
             .map_err(Into::<crate::error::RuntimeError>::into)?
             .to_string())
     }
+
+    /// Get the time elapsed since the client connected, in milliseconds.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Return
+    ///
+    /// * `int` - the age of the connection, never negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     rcpt: [
+    ///        action "log slow clients" || {
+    ///          if ctx::connection_age_ms() > 60000 {
+    ///            log("warn", `slow client ${ctx::client_ip()}: ${ctx::connection_age_ms()}ms`);
+    ///          }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:32
+    #[rhai_fn(name = "connection_age_ms", return_raw)]
+    pub fn connection_age_ms(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        Ok(as_millis(
+            vsl_guard_ok!(get_global!(ncc, ctx).read()).connection_age(),
+        ))
+    }
+
+    /// Get the time elapsed since the `MAIL FROM` command of the transaction, in milliseconds.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `int` - the age of the transaction, never negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     preq: [
+    ///        action "log transaction time" || log("info", `transaction received in ${ctx::transaction_age_ms()}ms`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:33
+    #[rhai_fn(name = "transaction_age_ms", return_raw)]
+    pub fn transaction_age_ms(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        Ok(as_millis(
+            vsl_guard_ok!(get_global!(ncc, ctx).read())
+                .transaction_age()
+                .map_err(Into::<crate::error::RuntimeError>::into)?,
+        ))
+    }
+
+    /// Get the id of the transaction, generated when the `MAIL FROM` command is received.
+    ///
+    /// Unlike `ctx::message_id()`, the id is the same for all the messages produced by the
+    /// transaction. It is attached to the logs of the transaction, and can be used to name
+    /// the files written by `fs::write` and `fs::dump` with the `{txid}` placeholder.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the transaction id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     mail: [
+    ///        action "log transaction" || log("info", `transaction ${ctx::transaction_id()} started`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:34
+    #[rhai_fn(name = "transaction_id", return_raw)]
+    pub fn transaction_id(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, ctx).read())
+            .transaction_id()
+            .map_err(Into::<crate::error::RuntimeError>::into)?
+            .to_string())
+    }
+}
+
+fn as_millis(duration: std::time::Duration) -> rhai::INT {
+    rhai::INT::try_from(duration.as_millis()).unwrap_or(rhai::INT::MAX)
+}
+
+fn dsn_notify(context: &Context, rcpt: &str) -> EngineResult<rhai::Dynamic> {
+    let rcpt = vsl_conversion_ok!("address", <Address as std::str::FromStr>::from_str(rcpt));
+
+    Ok(vsl_guard_ok!(context.read())
+        .recipient_dsn(&rcpt)
+        .map_err(Into::<crate::error::RuntimeError>::into)?
+        .map_or(rhai::Dynamic::UNIT, |dsn| match dsn.notify_on {
+            NotifyOn::Never => rhai::Array::from(["never".into()]).into(),
+            NotifyOn::Some {
+                success,
+                failure,
+                delay,
+            } => [(success, "success"), (failure, "failure"), (delay, "delay")]
+                .into_iter()
+                .filter_map(|(enabled, on)| enabled.then(|| rhai::Dynamic::from(on)))
+                .collect::<rhai::Array>()
+                .into(),
+        }))
+}
+
+fn dsn_orcpt(context: &Context, rcpt: &str) -> EngineResult<rhai::Dynamic> {
+    let rcpt = vsl_conversion_ok!("address", <Address as std::str::FromStr>::from_str(rcpt));
+
+    Ok(vsl_guard_ok!(context.read())
+        .recipient_dsn(&rcpt)
+        .map_err(Into::<crate::error::RuntimeError>::into)?
+        .and_then(|dsn| dsn.original_forward_path.as_ref())
+        .map_or(rhai::Dynamic::UNIT, |orcpt| {
+            format!("{};{}", orcpt.addr_type, orcpt.mailbox).into()
+        }))
 }
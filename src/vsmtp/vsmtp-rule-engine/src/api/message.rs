@@ -17,7 +17,7 @@
 
 use crate::{
     api::{
-        EngineResult, {Message, SharedObject},
+        EngineResult, {Context, Message, SharedObject},
     },
     get_global,
 };
@@ -105,6 +105,135 @@ mod message {
         has_header(ncc, &header.to_string())
     }
 
+    /// Checks if any occurrence of a header has exactly the given value.
+    ///
+    /// The name of the header is case insensitive, the value is case sensitive
+    /// (see `msg::header_equals_ignore_case`). The value of the header is unfolded,
+    /// and the surrounding whitespaces are ignored.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to search.
+    /// * `value` - the expected value of the header.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because the
+    /// email is received at this point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "X-Mailer: foo\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "X-Mailer: bar\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "check the mailer" || {
+    ///       if msg::header_equals("x-mailer", "bar") && !msg::header_equals("X-Mailer", "BAR") {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept("250 Ok".parse::<vsmtp_common::Reply>().unwrap()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:40
+    #[rhai_fn(name = "header_equals", return_raw)]
+    pub fn header_equals(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<bool> {
+        Ok(super::Impl::header_equals(
+            &get_global!(ncc, msg),
+            header,
+            value,
+            false,
+        ))
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_equals", return_raw)]
+    pub fn header_equals_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        value: &str,
+    ) -> EngineResult<bool> {
+        header_equals(ncc, &header.to_string(), value)
+    }
+
+    /// Checks if any occurrence of a header has the given value, ignoring the case
+    /// of the value, like `msg::header_equals`.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to search.
+    /// * `value` - the expected value of the header.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because the
+    /// email is received at this point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   preq: [
+    ///     rule "reject auto replies" || {
+    ///       if msg::header_equals_ignore_case("Auto-Submitted", "auto-replied") {
+    ///         state::deny();
+    ///       } else {
+    ///         state::next();
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:41
+    #[rhai_fn(name = "header_equals_ignore_case", return_raw)]
+    pub fn header_equals_ignore_case(
+        ncc: NativeCallContext,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<bool> {
+        Ok(super::Impl::header_equals(
+            &get_global!(ncc, msg),
+            header,
+            value,
+            true,
+        ))
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_equals_ignore_case", return_raw)]
+    pub fn header_equals_ignore_case_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        value: &str,
+    ) -> EngineResult<bool> {
+        header_equals_ignore_case(ncc, &header.to_string(), value)
+    }
+
     /// Count the number of headers with the given name.
     ///
     /// # Args
@@ -169,6 +298,64 @@ mod message {
         super::Impl::count_header(&get_global!(ncc, msg), &header.to_string())
     }
 
+    /// Count the `Received` headers of the message, the number of hops
+    /// the message went through (rfc 5321 section 6.3).
+    ///
+    /// The messages with more `Received` headers than `server.smtp.received_count_max`
+    /// are rejected before the rules are run.
+    ///
+    /// # Return
+    ///
+    /// * `int` - the number of `Received` headers.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because the
+    /// message is empty until then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Received: from mx2.example.com by mx3.example.com\r\n",
+    /// "Received: from mx1.example.com by mx2.example.com\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "hops" || {
+    ///       if msg::received_count() > 10 {
+    ///         state::deny()
+    ///       } else {
+    ///         state::accept(`250 ${msg::received_count()} hops`)
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 2 hops\r\n".parse().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:43
+    #[rhai_fn(name = "received_count", return_raw)]
+    pub fn received_count(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        super::Impl::count_header(&get_global!(ncc, msg), "Received")
+    }
+
     /// Get a specific header from the incoming message.
     ///
     /// # Args
@@ -238,15 +425,17 @@ mod message {
         get_header(ncc, &header.to_string())
     }
 
-    /// Get a list of all headers.
+    /// Get the n-th occurrence of a specific header from the incoming message.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to search. (optional, if not set, returns every header)
+    /// * `header` - the name of the header to get.
+    /// * `index` - the position (0-based) of the occurrence, in the order of the header section.
     ///
     /// # Return
     ///
-    /// * `array` - all of the headers found in the message.
+    /// * `string` - the header value, or an empty string if there are not enough
+    ///   occurrences of the header.
     ///
     /// # Effective smtp stage
     ///
@@ -257,7 +446,8 @@ mod message {
     ///
     /// ```
     /// # let msg = r#"
-    /// X-My-Header: 250 foo
+    /// Received: from mx2.example.com by mx3.example.com
+    /// Received: from mx1.example.com by mx2.example.com
     /// Subject: Unit test are cool
     ///
     /// Hello world!
@@ -265,58 +455,72 @@ mod message {
     /// ; // .eml ends here
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(msg[1..].replace("\n", "\r\n").as_str()).unwrap();
     ///
-    /// # let states = vsmtp_test::vsl::run_with_msg(
-    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "display headers" || {
-    ///         log("info", `all headers: ${msg::get_all_headers()}`);
-    ///         log("info", `all "Return-Path" headers: ${msg::get_all_headers("Return-Path")}`);
+    ///     rule "get_header_at" || {
+    ///       if msg::get_header_at("Received", 0) != "from mx2.example.com by mx3.example.com"
+    ///         || msg::get_header_at(identifier("Received"), 1) != "from mx1.example.com by mx2.example.com"
+    ///         || msg::get_header_at("Received", 2) != "" {
+    ///         state::deny();
+    ///       } else {
+    ///         state::accept(`250 ${msg::get_header_at("Received", 1)}`);
+    ///       }
     ///     }
     ///   ]
     /// }
-    /// # "#)?.build()), Some(msg));
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 from mx1.example.com by mx2.example.com\r\n".parse().unwrap()
+    /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:5
-    #[rhai_fn(name = "get_all_headers", return_raw)]
-    pub fn get_all_headers(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
-        Ok(vsl_guard_ok!(get_global!(ncc, msg).read())
-            .inner()
-            .raw_headers()
-            .iter()
-            .map(|raw| rhai::Dynamic::from(raw.clone()))
-            .collect())
-    }
-
-    #[doc(hidden)]
-    #[rhai_fn(name = "get_all_headers", return_raw)]
-    pub fn get_all_headers_str(ncc: NativeCallContext, name: &str) -> EngineResult<rhai::Array> {
-        Ok(super::Impl::get_all_headers(&get_global!(ncc, msg), name))
+    #[rhai_fn(name = "get_header_at", return_raw)]
+    pub fn get_header_at(
+        ncc: NativeCallContext,
+        header: &str,
+        index: rhai::INT,
+    ) -> EngineResult<String> {
+        Ok(super::Impl::get_header_at(
+            &get_global!(ncc, msg),
+            header,
+            index,
+        ))
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "get_all_headers", return_raw)]
-    pub fn get_all_headers_obj(
+    #[rhai_fn(name = "get_header_at", return_raw)]
+    pub fn get_header_at_obj(
         ncc: NativeCallContext,
-        name: SharedObject,
-    ) -> EngineResult<rhai::Array> {
-        Ok(super::Impl::get_all_headers(
-            &get_global!(ncc, msg),
-            &name.to_string(),
-        ))
+        header: SharedObject,
+        index: rhai::INT,
+    ) -> EngineResult<String> {
+        get_header_at(ncc, &header.to_string(), index)
     }
 
-    /// Get a list of all headers of a specific name with it's name and value
-    /// separated by a column.
+    /// Get a specific header from the incoming message, exactly as it was received.
+    ///
+    /// Unlike `get_header`, the value is not trimmed, and folded lines are kept
+    /// joined with their `\r\n` and continuation whitespace. Only the line
+    /// terminator of the header is removed.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to search.
+    /// * `header` - the name of the header to get.
     ///
     /// # Return
     ///
-    /// * `array` - all header values, or an empty array if the header was not found.
+    /// * `string` - the raw header value, or an empty string if the header was not found.
     ///
     /// # Effective smtp stage
     ///
@@ -326,65 +530,93 @@ mod message {
     /// # Examples
     ///
     /// ```
-    /// # let msg = r#"
-    /// X-My-Header: 250 foo
-    /// Subject: Unit test are cool
-    ///
-    /// Hello world!
-    /// # "#
-    /// ; // .eml ends here
-    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(msg[1..].replace("\n", "\r\n").as_str()).unwrap();
-    ///
-    /// # let states = vsmtp_test::vsl::run_with_msg(
-    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test\r\n",
+    /// "  are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// let rules = r#"
     /// #{
-    ///     postq: [
-    ///         action "display return path" || {
-    ///             // Will display "Return-Path: value".
-    ///             log("info", msg::get_header_untouched("Return-Path"));
-    ///         }
-    ///     ],
+    ///   preq: [
+    ///     rule "get_header_raw" || {
+    ///       if msg::get_header_raw("Subject") == " Unit test\r\n  are cool"
+    ///         && msg::get_header_raw(identifier("X-Unknown")) == "" {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
+    ///     }
+    ///   ]
     /// }
-    /// # "#)?.build()), Some(msg));
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 Ok".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:6
-    #[rhai_fn(return_raw)]
-    pub fn get_header_untouched(ncc: NativeCallContext, name: &str) -> EngineResult<rhai::Array> {
-        Ok(super::Impl::get_header_untouched(
-            &get_global!(ncc, msg),
-            name,
-        ))
+    #[rhai_fn(name = "get_header_raw", return_raw)]
+    pub fn get_header_raw(ncc: NativeCallContext, header: &str) -> EngineResult<String> {
+        Ok(super::Impl::get_header_raw(&get_global!(ncc, msg), header))
     }
 
-    /// Add a new header **at the end** of the header list in the message.
+    #[doc(hidden)]
+    #[rhai_fn(name = "get_header_raw", return_raw)]
+    pub fn get_header_raw_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+    ) -> EngineResult<String> {
+        get_header_raw(ncc, &header.to_string())
+    }
+
+    /// Get a specific header from the incoming message, with its RFC 2047
+    /// encoded-words (`=?UTF-8?B?...?=`) decoded.
+    ///
+    /// Both the `B` and `Q` encodings are supported, with the `utf-8`, `us-ascii`,
+    /// `iso-8859-1` and `windows-1252` charsets. The text outside of encoded-words
+    /// is left untouched, and the raw value is returned if the header could not be decoded.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to append.
-    /// * `value` - the value of the header to append.
+    /// * `header` - the name of the header to get.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the decoded header value, or an empty string if the header was not found.
     ///
     /// # Effective smtp stage
     ///
-    /// All of them. Even though the email is not received at the current stage,
-    /// vsmtp stores new headers and will add them on top of the ones received once
-    /// the `preq` stage is reached.
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
     ///
     /// # Examples
     ///
     /// ```
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
-    /// "X-My-Header: 250 foo\r\n",
-    /// "Subject: Unit test are cool\r\n",
+    /// "Subject: =?UTF-8?B?Q2Fmw6k=?= au =?ISO-8859-1?Q?lait_cr=E8me?=\r\n",
     /// "\r\n",
     /// "Hello world!\r\n",
     /// # )).unwrap();
     /// # let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "append_header" || {
-    ///       msg::append_header("X-My-Header-2", "bar");
-    ///       msg::append_header("X-My-Header-3", identifier("baz"));
+    ///     rule "get_header_decoded" || {
+    ///       if msg::get_header_decoded("Subject") == "Café au lait crème"
+    ///         && msg::get_header_decoded(identifier("X-Unknown")) == "" {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
     ///     }
     ///   ]
     /// }
@@ -397,60 +629,68 @@ mod message {
     /// #        .with_internal(rules)?
     /// #      .build()
     /// #   .build()), Some(msg));
-    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
-    /// #   "X-My-Header: 250 foo\r\n".to_string(),
-    /// #   "Subject: Unit test are cool\r\n".to_string(),
-    /// #   "X-My-Header-2: bar\r\n".to_string(),
-    /// #   "X-My-Header-3: baz\r\n".to_string(),
-    /// # ]);
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 Ok".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:7
-    #[rhai_fn(name = "append_header", return_raw)]
-    pub fn append_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
-        super::Impl::append_header(&get_global!(ncc, msg), &header, &value);
-        Ok(())
+    #[rhai_fn(name = "get_header_decoded", return_raw)]
+    pub fn get_header_decoded(ncc: NativeCallContext, header: &str) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, msg).read())
+            .get_header_decoded(header)
+            .unwrap_or_default())
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "append_header", return_raw)]
-    pub fn append_header_str_obj(
+    #[rhai_fn(name = "get_header_decoded", return_raw)]
+    pub fn get_header_decoded_obj(
         ncc: NativeCallContext,
-        header: &str,
-        value: SharedObject,
-    ) -> EngineResult<()> {
-        super::Impl::append_header(&get_global!(ncc, msg), &header, &value.to_string());
-        Ok(())
+        header: SharedObject,
+    ) -> EngineResult<String> {
+        get_header_decoded(ncc, &header.to_string())
     }
 
-    /// Add a new header on top all other headers in the message.
+    /// Check if the value of a header matches a regular expression.
+    ///
+    /// The pattern is compiled on each call, patterns that compile to a program
+    /// bigger than 1MiB are rejected.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to prepend.
-    /// * `value` - the value of the header to prepend.
+    /// * `header` - the name of the header to check.
+    /// * `pattern` - the regular expression to search in the value of the header.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - true if the first occurrence of the header matches the pattern,
+    ///            false otherwise or if the header was not found.
+    ///
+    /// # Errors
+    ///
+    /// * The pattern is not a valid regular expression, or is too big.
     ///
     /// # Effective smtp stage
     ///
-    /// All of them. Even though the email is not received at the current stage,
-    /// vsmtp stores new headers and will add them on top of the ones received once
-    /// the `preq` stage is reached.
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
     ///
     /// # Examples
     ///
     /// ```
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
-    /// "X-My-Header: 250 foo\r\n",
-    /// "Subject: Unit test are cool\r\n",
+    /// "Subject: [SPAM] Buy now!\r\n",
     /// "\r\n",
     /// "Hello world!\r\n",
     /// # )).unwrap();
     /// # let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "prepend_header" || {
-    ///       msg::prepend_header("X-My-Header-2", "bar");
-    ///       msg::prepend_header("X-My-Header-3", identifier("baz"));
+    ///     rule "header_matches" || {
+    ///       let spam = msg::header_matches("Subject", "^\\[SPAM\\]");
+    ///       let unknown = msg::header_matches(identifier("X-Unknown"), ".*");
+    ///       state::accept(`250 ${spam} ${unknown}`);
     ///     }
     ///   ]
     /// }
@@ -463,64 +703,95 @@ mod message {
     /// #        .with_internal(rules)?
     /// #      .build()
     /// #   .build()), Some(msg));
-    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
-    /// #   "X-My-Header-3: baz\r\n".to_string(),
-    /// #   "X-My-Header-2: bar\r\n".to_string(),
-    /// #   "X-My-Header: 250 foo\r\n".to_string(),
-    /// #   "Subject: Unit test are cool\r\n".to_string(),
-    /// # ]);
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 true false\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:8
-    #[rhai_fn(name = "prepend_header", return_raw)]
-    pub fn prepend_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
-        super::Impl::prepend_header(&get_global!(ncc, msg), header, value);
-        Ok(())
+    #[rhai_fn(name = "header_matches", return_raw)]
+    pub fn header_matches(
+        ncc: NativeCallContext,
+        header: &str,
+        pattern: &str,
+    ) -> EngineResult<bool> {
+        super::Impl::header_matches(&get_global!(ncc, msg), header, pattern)
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "prepend_header", return_raw)]
-    pub fn prepend_header_str_obj(
+    #[rhai_fn(name = "header_matches", return_raw)]
+    pub fn header_matches_obj_str(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        pattern: &str,
+    ) -> EngineResult<bool> {
+        super::Impl::header_matches(&get_global!(ncc, msg), &header.to_string(), pattern)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_matches", return_raw)]
+    pub fn header_matches_str_obj(
         ncc: NativeCallContext,
         header: &str,
-        value: SharedObject,
-    ) -> EngineResult<()> {
-        super::Impl::prepend_header(&get_global!(ncc, msg), header, &value.to_string());
-        Ok(())
+        pattern: SharedObject,
+    ) -> EngineResult<bool> {
+        super::Impl::header_matches(&get_global!(ncc, msg), header, &pattern.to_string())
     }
 
-    /// Replace an existing header value by a new value, or append a new header
-    /// to the message.
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_matches", return_raw)]
+    pub fn header_matches_obj_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        pattern: SharedObject,
+    ) -> EngineResult<bool> {
+        super::Impl::header_matches(
+            &get_global!(ncc, msg),
+            &header.to_string(),
+            &pattern.to_string(),
+        )
+    }
+
+    /// Extract a capture group of a regular expression from the value of a header.
+    ///
+    /// The pattern is compiled on each call, patterns that compile to a program
+    /// bigger than 1MiB are rejected.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to set or add.
-    /// * `value` - the value of the header to set or add.
+    /// * `header` - the name of the header to search.
+    /// * `pattern` - the regular expression to search in the value of the header.
+    /// * `group` - the index of the capture group to extract, 0 being the whole match.
     ///
-    /// # Effective smtp stage
+    /// # Return
     ///
-    /// All of them. Even though the email is not received at the current stage,
-    /// vsmtp stores new headers and will add them on top to the ones received once
-    /// the `preq` stage is reached.
+    /// * `string` - the captured substring of the first occurrence of the header,
+    ///              or an empty string if the header was not found, the pattern did not match
+    ///              or the group did not participate in the match.
     ///
-    /// Be aware that if you want to set a header value from the original message,
-    /// you must use `set_header` in the `preq` stage and onwards.
+    /// # Errors
+    ///
+    /// * The pattern is not a valid regular expression, or is too big.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
     ///
     /// # Examples
     ///
     /// ```
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
-    /// "Subject: The initial header value\r\n",
+    /// "X-Spam-Status: Yes, score=12.5 required=5.0\r\n",
     /// "\r\n",
     /// "Hello world!\r\n",
     /// # )).unwrap();
     /// # let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "set_header" || {
-    ///       msg::set_header("Subject", "The header value has been updated");
-    ///       msg::set_header("Subject", identifier("The header value has been updated again"));
-    ///       state::accept(`250 ${msg::get_header("Subject")}`);
+    ///     rule "header_capture" || {
+    ///       state::accept(`250 ${msg::header_capture("X-Spam-Status", "score=([0-9.]+)", 1)}`);
     ///     }
     ///   ]
     /// }
@@ -533,36 +804,72 @@ mod message {
     /// #        .with_internal(rules)?
     /// #      .build()
     /// #   .build()), Some(msg));
-    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # use vsmtp_common::status::Status;
     /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
-    /// #  "250 The header value has been updated again\r\n".parse().unwrap()
+    /// #  "250 12.5\r\n".parse::<vsmtp_common::Reply>().unwrap()
     /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:9
-    #[rhai_fn(name = "set_header", return_raw)]
-    pub fn set_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
-        super::Impl::set_header(&get_global!(ncc, msg), header, value);
-        Ok(())
+    #[rhai_fn(name = "header_capture", return_raw)]
+    pub fn header_capture(
+        ncc: NativeCallContext,
+        header: &str,
+        pattern: &str,
+        group: rhai::INT,
+    ) -> EngineResult<String> {
+        super::Impl::header_capture(&get_global!(ncc, msg), header, pattern, group)
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "set_header", return_raw)]
-    pub fn set_header_str_obj(
+    #[rhai_fn(name = "header_capture", return_raw)]
+    pub fn header_capture_obj_str(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        pattern: &str,
+        group: rhai::INT,
+    ) -> EngineResult<String> {
+        super::Impl::header_capture(&get_global!(ncc, msg), &header.to_string(), pattern, group)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_capture", return_raw)]
+    pub fn header_capture_str_obj(
         ncc: NativeCallContext,
         header: &str,
-        value: SharedObject,
-    ) -> EngineResult<()> {
-        super::Impl::set_header(&get_global!(ncc, msg), header, &value.to_string());
-        Ok(())
+        pattern: SharedObject,
+        group: rhai::INT,
+    ) -> EngineResult<String> {
+        super::Impl::header_capture(&get_global!(ncc, msg), header, &pattern.to_string(), group)
     }
 
-    /// Replace an existing header name by a new value.
+    #[doc(hidden)]
+    #[rhai_fn(name = "header_capture", return_raw)]
+    pub fn header_capture_obj_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+        pattern: SharedObject,
+        group: rhai::INT,
+    ) -> EngineResult<String> {
+        super::Impl::header_capture(
+            &get_global!(ncc, msg),
+            &header.to_string(),
+            &pattern.to_string(),
+            group,
+        )
+    }
+
+    /// Parse the mailboxes of an address header (`From`, `To`, `Cc` ...)
+    /// following RFC 5322. Groups, quoted display names and comments are handled.
     ///
     /// # Args
     ///
-    /// * `old` - the name of the header to rename.
-    /// * `new` - the new new of the header.
+    /// * `header` - the name of the header to parse.
+    ///
+    /// # Return
+    ///
+    /// * `array` - a map for each mailbox, with the `display_name` (empty if not set)
+    ///             and `address` keys. Empty if the header was not found.
     ///
     /// # Effective smtp stage
     ///
@@ -573,28 +880,24 @@ mod message {
     ///
     /// ```
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
-    /// "Subject: The initial header value\r\n",
+    /// "From: \"Doe, John\" <john.doe@example.com>\r\n",
+    /// "To: friends: jane@example.com, Bob <bob@example.com>;\r\n",
     /// "\r\n",
     /// "Hello world!\r\n",
     /// # )).unwrap();
-    ///
-    /// # let rules = r#"
+    /// let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "rename_header" || {
-    ///       msg::rename_header("Subject", "bob");
-    ///       if msg::has_header("Subject") { return state::deny(); }
-    ///
-    ///       msg::rename_header("bob", identifier("Subject"));
-    ///       if msg::has_header("bob") { return state::deny(); }
-    ///
-    ///       msg::rename_header(identifier("Subject"), "foo");
-    ///       if msg::has_header("Subject") { return state::deny(); }
-    ///
-    ///       msg::rename_header(identifier("foo"), identifier("Subject"));
-    ///       if msg::has_header("foo") { return state::deny(); }
+    ///     rule "parse_addresses" || {
+    ///       let from = msg::parse_addresses("From");
+    ///       let to = msg::parse_addresses(identifier("To"));
     ///
-    ///       state::accept(`250 ${msg::get_header("Subject")}`);
+    ///       if from[0].display_name == "Doe, John" && from[0].address == "john.doe@example.com"
+    ///         && to.len() == 2 && to[0].display_name == "" && to[1].address == "bob@example.com" {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
     ///     }
     ///   ]
     /// }
@@ -607,88 +910,1629 @@ mod message {
     /// #        .with_internal(rules)?
     /// #      .build()
     /// #   .build()), Some(msg));
-    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # use vsmtp_common::status::Status;
     /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
-    /// #  "250 The initial header value\r\n".parse().unwrap()
+    /// #  "250 Ok".parse::<vsmtp_common::Reply>().unwrap()
     /// # ));
     /// ```
     ///
     /// # rhai-autodocs:index:10
-    #[rhai_fn(name = "rename_header", return_raw)]
-    pub fn rename_header(ncc: NativeCallContext, old: &str, new: &str) -> EngineResult<()> {
-        super::Impl::rename_header(&get_global!(ncc, msg), old, new);
-        Ok(())
+    #[rhai_fn(name = "parse_addresses", return_raw)]
+    pub fn parse_addresses(ncc: NativeCallContext, header: &str) -> EngineResult<rhai::Array> {
+        Ok(super::Impl::parse_addresses(&get_global!(ncc, msg), header))
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rename_header", return_raw)]
-    pub fn rename_header_str_obj(
+    #[rhai_fn(name = "parse_addresses", return_raw)]
+    pub fn parse_addresses_obj(
         ncc: NativeCallContext,
-        old: &str,
-        new: SharedObject,
-    ) -> EngineResult<()> {
-        super::Impl::rename_header(&get_global!(ncc, msg), old, &new.to_string());
-        Ok(())
+        header: SharedObject,
+    ) -> EngineResult<rhai::Array> {
+        parse_addresses(ncc, &header.to_string())
+    }
+
+    /// Get a list of all headers.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to search. (optional, if not set, returns every header)
+    ///
+    /// # Return
+    ///
+    /// * `array` - all of the headers found in the message.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = r#"
+    /// X-My-Header: 250 foo
+    /// Subject: Unit test are cool
+    ///
+    /// Hello world!
+    /// # "#
+    /// ; // .eml ends here
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(msg[1..].replace("\n", "\r\n").as_str()).unwrap();
+    ///
+    /// # let states = vsmtp_test::vsl::run_with_msg(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   preq: [
+    ///     rule "display headers" || {
+    ///         log("info", `all headers: ${msg::get_all_headers()}`);
+    ///         log("info", `all "Return-Path" headers: ${msg::get_all_headers("Return-Path")}`);
+    ///     }
+    ///   ]
+    /// }
+    /// # "#)?.build()), Some(msg));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:11
+    #[rhai_fn(name = "get_all_headers", return_raw)]
+    pub fn get_all_headers(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
+        Ok(vsl_guard_ok!(get_global!(ncc, msg).read())
+            .inner()
+            .raw_headers()
+            .iter()
+            .map(|raw| rhai::Dynamic::from(raw.clone()))
+            .collect())
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rename_header", return_raw)]
-    pub fn rename_header_obj_str(
-        ncc: NativeCallContext,
-        old: SharedObject,
-        new: &str,
-    ) -> EngineResult<()> {
-        super::Impl::rename_header(&get_global!(ncc, msg), &old.to_string(), new);
-        Ok(())
+    #[rhai_fn(name = "get_all_headers", return_raw)]
+    pub fn get_all_headers_str(ncc: NativeCallContext, name: &str) -> EngineResult<rhai::Array> {
+        Ok(super::Impl::get_all_headers(&get_global!(ncc, msg), name))
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rename_header", return_raw)]
-    pub fn rename_header_obj_obj(
+    #[rhai_fn(name = "get_all_headers", return_raw)]
+    pub fn get_all_headers_obj(
         ncc: NativeCallContext,
-        old: SharedObject,
-        new: SharedObject,
-    ) -> EngineResult<()> {
-        super::Impl::rename_header(&get_global!(ncc, msg), &old.to_string(), &new.to_string());
-        Ok(())
+        name: SharedObject,
+    ) -> EngineResult<rhai::Array> {
+        Ok(super::Impl::get_all_headers(
+            &get_global!(ncc, msg),
+            &name.to_string(),
+        ))
+    }
+
+    /// Get a list of all headers of a specific name with it's name and value
+    /// separated by a column.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to search.
+    ///
+    /// # Return
+    ///
+    /// * `array` - all header values, or an empty array if the header was not found.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = r#"
+    /// X-My-Header: 250 foo
+    /// Subject: Unit test are cool
+    ///
+    /// Hello world!
+    /// # "#
+    /// ; // .eml ends here
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(msg[1..].replace("\n", "\r\n").as_str()).unwrap();
+    ///
+    /// # let states = vsmtp_test::vsl::run_with_msg(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     postq: [
+    ///         action "display return path" || {
+    ///             // Will display "Return-Path: value".
+    ///             log("info", msg::get_header_untouched("Return-Path"));
+    ///         }
+    ///     ],
+    /// }
+    /// # "#)?.build()), Some(msg));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:12
+    #[rhai_fn(return_raw)]
+    pub fn get_header_untouched(ncc: NativeCallContext, name: &str) -> EngineResult<rhai::Array> {
+        Ok(super::Impl::get_header_untouched(
+            &get_global!(ncc, msg),
+            name,
+        ))
+    }
+
+    /// Add a new header **at the end** of the header list in the message.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to append.
+    /// * `value` - the value of the header to append.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them. Even though the email is not received at the current stage,
+    /// vsmtp stores new headers and will add them on top of the ones received once
+    /// the `preq` stage is reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "X-My-Header: 250 foo\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "append_header" || {
+    ///       msg::append_header("X-My-Header-2", "bar");
+    ///       msg::append_header("X-My-Header-3", identifier("baz"));
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "X-My-Header: 250 foo\r\n".to_string(),
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// #   "X-My-Header-2: bar\r\n".to_string(),
+    /// #   "X-My-Header-3: baz\r\n".to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:13
+    #[rhai_fn(name = "append_header", return_raw)]
+    pub fn append_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
+        super::Impl::append_header(&get_global!(ncc, msg), &header, &value);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "append_header", return_raw)]
+    pub fn append_header_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::append_header(&get_global!(ncc, msg), &header, &value.to_string());
+        Ok(())
+    }
+
+    /// Add a new header **at the end** of the header list in the message, folding its value
+    /// at whitespaces so that the lines of the header are at most 78 characters long (rfc 5322).
+    ///
+    /// Useful for long values like `Authentication-Results`, that some parsers truncate
+    /// when written on a single line. The content of the value is unchanged once unfolded.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to append.
+    /// * `value` - the value of the header to append.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "append_header_folded" || {
+    ///       msg::append_header_folded("Authentication-Results",
+    ///         "testserver.com; spf=pass smtp.mailfrom=john.doe@example.com; dkim=pass header.d=example.com");
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// #   concat!(
+    /// #     "Authentication-Results: testserver.com; spf=pass\r\n",
+    /// #     " smtp.mailfrom=john.doe@example.com; dkim=pass header.d=example.com\r\n",
+    /// #   ).to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:14
+    #[rhai_fn(name = "append_header_folded", return_raw)]
+    pub fn append_header_folded(
+        ncc: NativeCallContext,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<()> {
+        super::Impl::append_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, value),
+        );
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "append_header_folded", return_raw)]
+    pub fn append_header_folded_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::append_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, &value.to_string()),
+        );
+        Ok(())
+    }
+
+    /// Add a new header on top all other headers in the message.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to prepend.
+    /// * `value` - the value of the header to prepend.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them. Even though the email is not received at the current stage,
+    /// vsmtp stores new headers and will add them on top of the ones received once
+    /// the `preq` stage is reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "X-My-Header: 250 foo\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "prepend_header" || {
+    ///       msg::prepend_header("X-My-Header-2", "bar");
+    ///       msg::prepend_header("X-My-Header-3", identifier("baz"));
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "X-My-Header-3: baz\r\n".to_string(),
+    /// #   "X-My-Header-2: bar\r\n".to_string(),
+    /// #   "X-My-Header: 250 foo\r\n".to_string(),
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:15
+    #[rhai_fn(name = "prepend_header", return_raw)]
+    pub fn prepend_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
+        super::Impl::prepend_header(&get_global!(ncc, msg), header, value);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "prepend_header", return_raw)]
+    pub fn prepend_header_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::prepend_header(&get_global!(ncc, msg), header, &value.to_string());
+        Ok(())
+    }
+
+    /// Add a new header on top all other headers in the message, folding its value
+    /// like `msg::append_header_folded`.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to prepend.
+    /// * `value` - the value of the header to prepend.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   preq: [
+    ///     action "prepend_header_folded" || {
+    ///       msg::prepend_header_folded("X-Comment", "a long comment that is folded on several lines instead of being written on a single one");
+    ///     }
+    ///   ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:16
+    #[rhai_fn(name = "prepend_header_folded", return_raw)]
+    pub fn prepend_header_folded(
+        ncc: NativeCallContext,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<()> {
+        super::Impl::prepend_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, value),
+        );
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "prepend_header_folded", return_raw)]
+    pub fn prepend_header_folded_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::prepend_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, &value.to_string()),
+        );
+        Ok(())
+    }
+
+    /// Add a new header **before** the first header named `anchor` in the message.
+    ///
+    /// If the message does not contain any `anchor` header, the new header is
+    /// added at the top of the header list, like `prepend_header`.
+    ///
+    /// # Args
+    ///
+    /// * `anchor` - the name of the header to insert before.
+    /// * `header` - the name of the header to insert.
+    /// * `value` - the value of the header to insert.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Received: from mx2.example.com by mx3.example.com\r\n",
+    /// "Received: from mx1.example.com by mx2.example.com\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "insert_header_before" || {
+    ///       msg::insert_header_before("Received", "X-My-Header", "foo");
+    ///       msg::insert_header_before("X-Unknown", "X-My-Header-2", identifier("bar"));
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "X-My-Header-2: bar\r\n".to_string(),
+    /// #   "X-My-Header: foo\r\n".to_string(),
+    /// #   "Received: from mx2.example.com by mx3.example.com\r\n".to_string(),
+    /// #   "Received: from mx1.example.com by mx2.example.com\r\n".to_string(),
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:17
+    #[rhai_fn(name = "insert_header_before", return_raw)]
+    pub fn insert_header_before(
+        ncc: NativeCallContext,
+        anchor: &str,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<()> {
+        super::Impl::insert_header(&get_global!(ncc, msg), anchor, header, value, false);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "insert_header_before", return_raw)]
+    pub fn insert_header_before_str_obj(
+        ncc: NativeCallContext,
+        anchor: &str,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::insert_header(
+            &get_global!(ncc, msg),
+            anchor,
+            header,
+            &value.to_string(),
+            false,
+        );
+        Ok(())
+    }
+
+    /// Add a new header **after** the first header named `anchor` in the message.
+    ///
+    /// If the message does not contain any `anchor` header, the new header is
+    /// added at the end of the header list, like `append_header`.
+    ///
+    /// # Args
+    ///
+    /// * `anchor` - the name of the header to insert after.
+    /// * `header` - the name of the header to insert.
+    /// * `value` - the value of the header to insert.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Received: from mx2.example.com by mx3.example.com\r\n",
+    /// "Received: from mx1.example.com by mx2.example.com\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "insert_header_after" || {
+    ///       msg::insert_header_after("Received", "X-My-Header", "foo");
+    ///       msg::insert_header_after("X-Unknown", "X-My-Header-2", identifier("bar"));
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "Received: from mx2.example.com by mx3.example.com\r\n".to_string(),
+    /// #   "X-My-Header: foo\r\n".to_string(),
+    /// #   "Received: from mx1.example.com by mx2.example.com\r\n".to_string(),
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// #   "X-My-Header-2: bar\r\n".to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:18
+    #[rhai_fn(name = "insert_header_after", return_raw)]
+    pub fn insert_header_after(
+        ncc: NativeCallContext,
+        anchor: &str,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<()> {
+        super::Impl::insert_header(&get_global!(ncc, msg), anchor, header, value, true);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "insert_header_after", return_raw)]
+    pub fn insert_header_after_str_obj(
+        ncc: NativeCallContext,
+        anchor: &str,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::insert_header(
+            &get_global!(ncc, msg),
+            anchor,
+            header,
+            &value.to_string(),
+            true,
+        );
+        Ok(())
+    }
+
+    /// Replace the value of the first header named `header` (case insensitive),
+    /// or append a new header to the message if it does not exist.
+    ///
+    /// The other occurrences of the header are left untouched, use `msg::set_all_headers`
+    /// for the headers that must be unique, like `Content-Type`.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to set or add.
+    /// * `value` - the value of the header to set or add.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them. Even though the email is not received at the current stage,
+    /// vsmtp stores new headers and will add them on top to the ones received once
+    /// the `preq` stage is reached.
+    ///
+    /// Be aware that if you want to set a header value from the original message,
+    /// you must use `set_header` in the `preq` stage and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: The initial header value\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "set_header" || {
+    ///       msg::set_header("Subject", "The header value has been updated");
+    ///       msg::set_header("Subject", identifier("The header value has been updated again"));
+    ///       state::accept(`250 ${msg::get_header("Subject")}`);
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 The header value has been updated again\r\n".parse().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:19
+    #[rhai_fn(name = "set_header", return_raw)]
+    pub fn set_header(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
+        super::Impl::set_header(&get_global!(ncc, msg), header, value);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "set_header", return_raw)]
+    pub fn set_header_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::set_header(&get_global!(ncc, msg), header, &value.to_string());
+        Ok(())
+    }
+
+    /// Replace an existing header value by a new value, or append a new header
+    /// if the header does not exist, folding the value like `msg::append_header_folded`.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to set or add.
+    /// * `value` - the value of the header to set or add.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   preq: [
+    ///     action "set_header_folded" || {
+    ///       msg::set_header_folded("X-Comment", "a long comment that is folded on several lines instead of being written on a single one");
+    ///     }
+    ///   ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:20
+    #[rhai_fn(name = "set_header_folded", return_raw)]
+    pub fn set_header_folded(
+        ncc: NativeCallContext,
+        header: &str,
+        value: &str,
+    ) -> EngineResult<()> {
+        super::Impl::set_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, value),
+        );
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "set_header_folded", return_raw)]
+    pub fn set_header_folded_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::set_header(
+            &get_global!(ncc, msg),
+            header,
+            &vsmtp_mail_parser::fold_header(header, &value.to_string()),
+        );
+        Ok(())
+    }
+
+    /// Replace an existing header name by a new value.
+    ///
+    /// # Args
+    ///
+    /// * `old` - the name of the header to rename.
+    /// * `new` - the new new of the header.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: The initial header value\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    ///
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "rename_header" || {
+    ///       msg::rename_header("Subject", "bob");
+    ///       if msg::has_header("Subject") { return state::deny(); }
+    ///
+    ///       msg::rename_header("bob", identifier("Subject"));
+    ///       if msg::has_header("bob") { return state::deny(); }
+    ///
+    ///       msg::rename_header(identifier("Subject"), "foo");
+    ///       if msg::has_header("Subject") { return state::deny(); }
+    ///
+    ///       msg::rename_header(identifier("foo"), identifier("Subject"));
+    ///       if msg::has_header("foo") { return state::deny(); }
+    ///
+    ///       state::accept(`250 ${msg::get_header("Subject")}`);
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 The initial header value\r\n".parse().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:21
+    #[rhai_fn(name = "rename_header", return_raw)]
+    pub fn rename_header(ncc: NativeCallContext, old: &str, new: &str) -> EngineResult<()> {
+        super::Impl::rename_header(&get_global!(ncc, msg), old, new);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "rename_header", return_raw)]
+    pub fn rename_header_str_obj(
+        ncc: NativeCallContext,
+        old: &str,
+        new: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::rename_header(&get_global!(ncc, msg), old, &new.to_string());
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "rename_header", return_raw)]
+    pub fn rename_header_obj_str(
+        ncc: NativeCallContext,
+        old: SharedObject,
+        new: &str,
+    ) -> EngineResult<()> {
+        super::Impl::rename_header(&get_global!(ncc, msg), &old.to_string(), new);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "rename_header", return_raw)]
+    pub fn rename_header_obj_obj(
+        ncc: NativeCallContext,
+        old: SharedObject,
+        new: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::rename_header(&get_global!(ncc, msg), &old.to_string(), &new.to_string());
+        Ok(())
+    }
+
+    /// Add a `Message-ID` header at the top of the message if it does not have one.
+    ///
+    /// The generated id looks like `<base32 random>.<timestamp>@<hostname>`.
+    /// Calling this function multiple times does not add another header.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the value of the `Message-ID` header, existing or generated.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     action "ensure message id" || {
+    ///       let id = msg::ensure_message_id();
+    ///       log("info", `message id: ${id}`);
+    ///       msg::ensure_message_id();
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # let body = &states[&vsmtp_rule_engine::ExecutionStage::PreQ].1;
+    /// # assert_eq!(body.count_header("Message-ID"), 1);
+    /// # let id = body.get_header("Message-ID").unwrap();
+    /// # assert!(id.trim().starts_with('<') && id.ends_with('>'), "{id}");
+    /// ```
+    ///
+    /// # rhai-autodocs:index:22
+    #[rhai_fn(name = "ensure_message_id", return_raw)]
+    pub fn ensure_message_id(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(super::Impl::ensure_message_id(&get_global!(ncc, msg)))
+    }
+
+    /// Get a copy of the whole email as a string.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     postq: [
+    ///        action "display email content" || log("trace", `email content: ${msg::mail()}`),
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:23
+    #[rhai_fn(name = "mail", return_raw)]
+    pub fn mail(ncc: NativeCallContext) -> EngineResult<String> {
+        Ok(vsl_guard_ok!(get_global!(ncc, msg).read())
+            .inner()
+            .to_string())
+    }
+
+    /// Get the size in bytes of the whole email, headers and body included.
+    ///
+    /// # Return
+    ///
+    /// * `int` - the length of the string returned by `msg::mail()`, without building it.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///     preq: [
+    ///        action "tag large messages" || {
+    ///            if msg::message_size() > 10 * 1024 * 1024 {
+    ///                msg::append_header("X-Large-Message", "true");
+    ///            }
+    ///        },
+    ///     ]
+    /// }
+    /// # "#)?.build()));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:24
+    #[rhai_fn(name = "message_size", return_raw)]
+    pub fn size(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        Ok(
+            rhai::INT::try_from(vsl_guard_ok!(get_global!(ncc, msg).read()).inner().size())
+                .unwrap_or(rhai::INT::MAX),
+        )
+    }
+
+    /// Enumerate the parts of the message, nested multiparts (`multipart/mixed`,
+    /// `multipart/alternative` ...) included.
+    ///
+    /// # Return
+    ///
+    /// * `array` - a map for each leaf part of a multipart message, with the following keys:
+    ///   * `content_type` - the content type of the part, lowercase (`application/pdf` ...).
+    ///   * `filename` - the filename of the part, or an empty string if not set.
+    ///   * `size` - the size of the content, as transferred (before decoding).
+    ///   * `content_transfer_encoding` - the encoding of the part, `7bit` if not set.
+    ///   * `content_disposition` - the disposition of the part (`inline`, `attachment` ...),
+    ///     or an empty string if not set.
+    /// * `map` - the map of the body, with the same keys, if the message is not multipart.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: multipart/mixed; boundary=\"bound\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "\r\n",
+    /// "See the attached file.\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: application/zip\r\n",
+    /// "Content-Disposition: attachment; filename=\"invoice.zip\"\r\n",
+    /// "Content-Transfer-Encoding: base64\r\n",
+    /// "\r\n",
+    /// "UEsDBA==\r\n",
+    /// "--bound--\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "no zip attachments" || {
+    ///       for part in msg::mime_parts() {
+    ///         if part.content_type == "application/zip" {
+    ///           return state::deny(`554 ${part.filename} is not allowed`);
+    ///         }
+    ///       }
+    ///       state::next()
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Deny(
+    /// #  "554 invoice.zip is not allowed\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:25
+    #[rhai_fn(name = "mime_parts", return_raw)]
+    pub fn mime_parts(ncc: NativeCallContext) -> EngineResult<rhai::Dynamic> {
+        super::Impl::mime_parts(&get_global!(ncc, msg))
+    }
+
+    /// Count the attachments of the message.
+    ///
+    /// A part is an attachment if it has an `attachment` disposition, or a filename
+    /// without being explicitly `inline` (like images embedded in an html body).
+    ///
+    /// # Return
+    ///
+    /// * `number` - the number of attachments.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: multipart/mixed; boundary=\"bound\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "\r\n",
+    /// "See the attached files.\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: image/png\r\n",
+    /// "Content-Disposition: inline; filename=\"logo.png\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: application/pdf; name=\"invoice.pdf\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/csv\r\n",
+    /// "Content-Disposition: attachment; filename=\"invoice.csv\"\r\n",
+    /// "\r\n",
+    /// "--bound--\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "too many attachments" || {
+    ///       if msg::attachment_count() > 5 {
+    ///         state::deny()
+    ///       } else {
+    ///         state::accept(`250 ${msg::attachment_count()} attachments`)
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 2 attachments\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:26
+    #[rhai_fn(name = "attachment_count", return_raw)]
+    pub fn attachment_count(ncc: NativeCallContext) -> EngineResult<rhai::INT> {
+        super::Impl::attachment_count(&get_global!(ncc, msg))
+    }
+
+    /// Get the filenames of the attachments of the message.
+    ///
+    /// A part is an attachment if it has an `attachment` disposition, or a filename
+    /// without being explicitly `inline` (like images embedded in an html body).
+    ///
+    /// # Return
+    ///
+    /// * `array` - the filename of each attachment, an empty string for an attachment without name.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: multipart/mixed; boundary=\"bound\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "\r\n",
+    /// "See the attached files.\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: image/png\r\n",
+    /// "Content-Disposition: inline; filename=\"logo.png\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: application/pdf; name=\"invoice.pdf\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/csv\r\n",
+    /// "Content-Disposition: attachment; filename=\"invoice.csv\"\r\n",
+    /// "\r\n",
+    /// "--bound--\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "attachment names" || {
+    ///       state::accept(`250 ${msg::attachment_names()}`)
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 [\"invoice.pdf\", \"invoice.csv\"]\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:27
+    #[rhai_fn(name = "attachment_names", return_raw)]
+    pub fn attachment_names(ncc: NativeCallContext) -> EngineResult<rhai::Array> {
+        super::Impl::attachment_names(&get_global!(ncc, msg))
+    }
+
+    /// Remove the attachments whose filename has one of the given extensions.
+    ///
+    /// Each part removed is replaced by a text part noting the removal, the rest of
+    /// the MIME structure (the other parts, the boundaries, nested multiparts) and the
+    /// headers of the message are left untouched.
+    ///
+    /// # Args
+    ///
+    /// * `extensions` - the extensions to remove, case insensitive, with or without the leading dot.
+    ///
+    /// # Return
+    ///
+    /// * `number` - the number of attachments removed.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * the message could not be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: multipart/mixed; boundary=\"bound\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "\r\n",
+    /// "See the attached file.\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: application/octet-stream\r\n",
+    /// "Content-Disposition: attachment; filename=\"invoice.exe\"\r\n",
+    /// "Content-Transfer-Encoding: base64\r\n",
+    /// "\r\n",
+    /// "TVqQAAMAAAAEAAAA\r\n",
+    /// "--bound--\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     action "strip executables" || {
+    ///       let removed = msg::strip_attachments(["exe", "scr", "bat"]);
+    ///       if removed > 0 {
+    ///         msg::append_header("X-Attachments-Removed", `${removed}`);
+    ///       }
+    ///     },
+    ///     rule "check attachments" || {
+    ///       state::accept(`250 ${msg::attachment_count()} attachments`)
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 0 attachments\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:42
+    #[rhai_fn(name = "strip_attachments", return_raw)]
+    pub fn strip_attachments(
+        ncc: NativeCallContext,
+        extensions: rhai::Array,
+    ) -> EngineResult<rhai::INT> {
+        super::Impl::strip_attachments(&get_global!(ncc, msg), &extensions)
+    }
+
+    /// Does the text of the message contain `pattern`.
+    ///
+    /// The text parts of the message (`text/*` parts that are not attachments) are decoded
+    /// from their `quoted-printable` or `base64` transfer encoding and their charset before
+    /// being scanned, so that a phrase cannot be hidden by encoding it.
+    ///
+    /// # Args
+    ///
+    /// * `pattern` - the phrase to search, case sensitive.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - true if one of the text parts contains the phrase.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: text/plain; charset=utf-8\r\n",
+    /// "Content-Transfer-Encoding: quoted-printable\r\n",
+    /// "\r\n",
+    /// "Cr=C3=A9dit imm=C3=A9diat, aucun frais,=\r\n",
+    /// " cliquez ici.\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "phrase" || {
+    ///       if msg::body_contains("immédiat, aucun frais, cliquez") {
+    ///         state::deny()
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Deny(
+    /// #  "554 permanent problems with the remote server\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:28
+    #[rhai_fn(name = "body_contains", return_raw)]
+    pub fn body_contains(ncc: NativeCallContext, pattern: &str) -> EngineResult<bool> {
+        super::Impl::body_contains(&get_global!(ncc, msg), pattern)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "body_contains", return_raw)]
+    pub fn body_contains_obj(ncc: NativeCallContext, pattern: SharedObject) -> EngineResult<bool> {
+        super::Impl::body_contains(&get_global!(ncc, msg), &pattern.to_string())
+    }
+
+    /// Does the text of the message match the regex `pattern`.
+    ///
+    /// The text parts of the message (`text/*` parts that are not attachments) are decoded
+    /// from their `quoted-printable` or `base64` transfer encoding and their charset before
+    /// being scanned, the lines of a part are separated by `\n`.
+    ///
+    /// # Args
+    ///
+    /// * `pattern` - a regex, prefixed by `(?i)` to ignore the case.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - true if one of the text parts matches the regex.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * The pattern is not a valid regex, or is too large.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "From: john.doe@example.com\r\n",
+    /// "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+    /// "MIME-Version: 1.0\r\n",
+    /// "Content-Type: multipart/mixed; boundary=\"bound\"\r\n",
+    /// "\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "Content-Transfer-Encoding: base64\r\n",
+    /// "\r\n",
+    /// // "Claim your FREE prize now!"
+    /// "Q2xhaW0geW91ciBGUkVFIHByaXplIG5vdyE=\r\n",
+    /// "--bound\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "Content-Disposition: attachment; filename=\"terms.txt\"\r\n",
+    /// "\r\n",
+    /// "No purchase necessary.\r\n",
+    /// "--bound--\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "regex" || {
+    ///       if msg::body_matches_regex(`(?i)free\s+prize`) && !msg::body_matches_regex("purchase") {
+    ///         state::deny()
+    ///       } else {
+    ///         state::next()
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Deny(
+    /// #  "554 permanent problems with the remote server\r\n".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:29
+    #[rhai_fn(name = "body_matches_regex", return_raw)]
+    pub fn body_matches_regex(ncc: NativeCallContext, pattern: &str) -> EngineResult<bool> {
+        super::Impl::body_matches_regex(&get_global!(ncc, msg), pattern)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "body_matches_regex", return_raw)]
+    pub fn body_matches_regex_obj(
+        ncc: NativeCallContext,
+        pattern: SharedObject,
+    ) -> EngineResult<bool> {
+        super::Impl::body_matches_regex(&get_global!(ncc, msg), &pattern.to_string())
+    }
+
+    /// Replace the body of the email, leaving the headers untouched.
+    ///
+    /// # Args
+    ///
+    /// * `content` - the new body of the email. Line endings are converted to CRLF.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     action "replace body" || msg::set_body("This message has been removed.\n"),
+    ///     rule "check body" || {
+    ///       if msg::mail() == "Subject: Unit test are cool\r\n\r\nThis message has been removed.\r\n" {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 Ok".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:30
+    #[rhai_fn(name = "set_body", return_raw)]
+    pub fn set_body(ncc: NativeCallContext, content: &str) -> EngineResult<()> {
+        super::Impl::set_body(&get_global!(ncc, msg), content)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "set_body", return_raw)]
+    pub fn set_body_obj(ncc: NativeCallContext, content: SharedObject) -> EngineResult<()> {
+        super::Impl::set_body(&get_global!(ncc, msg), &content.to_string())
+    }
+
+    /// Add content at the end of the body of the email, leaving the headers untouched.
+    ///
+    /// # Args
+    ///
+    /// * `content` - the content to append, a footer or a disclaimer for example.
+    ///               Line endings are converted to CRLF.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     action "add disclaimer" || msg::append_to_body("--\nSent from vSMTP"),
+    ///     rule "check body" || {
+    ///       if msg::mail() == "Subject: Unit test are cool\r\n\r\nHello world!\r\n--\r\nSent from vSMTP\r\n" {
+    ///         state::accept();
+    ///       } else {
+    ///         state::deny();
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::status::Status;
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 Ok".parse::<vsmtp_common::Reply>().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:31
+    #[rhai_fn(name = "append_to_body", return_raw)]
+    pub fn append_to_body(ncc: NativeCallContext, content: &str) -> EngineResult<()> {
+        super::Impl::append_to_body(&get_global!(ncc, msg), content)
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(name = "append_to_body", return_raw)]
+    pub fn append_to_body_obj(ncc: NativeCallContext, content: SharedObject) -> EngineResult<()> {
+        super::Impl::append_to_body(&get_global!(ncc, msg), &content.to_string())
+    }
+
+    /// Remove an existing header from the message.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to remove.
+    ///
+    /// # Return
+    ///
+    /// * a boolean value, true if a header has been removed, false otherwise.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: The initial header value\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     rule "remove_header" || {
+    ///       msg::rm_header("Subject");
+    ///       if msg::has_header("Subject") { return state::deny(); }
+    ///
+    ///       msg::prepend_header("Subject-2", "Rust is good");
+    ///       msg::rm_header(identifier("Subject-2"));
+    ///
+    ///       msg::prepend_header("Subject-3", "Rust is good !!!!!");
+    ///
+    ///       state::accept(`250 ${msg::get_header("Subject-3")}`);
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 Rust is good !!!!!\r\n".parse().unwrap()
+    /// # ));
+    /// ```
+    ///
+    /// # rhai-autodocs:index:32
+    #[rhai_fn(name = "rm_header", return_raw)]
+    pub fn remove_header(ncc: NativeCallContext, header: &str) -> EngineResult<bool> {
+        Ok(super::Impl::remove_header(&get_global!(ncc, msg), header))
     }
 
-    /// Get a copy of the whole email as a string.
+    #[doc(hidden)]
+    #[rhai_fn(name = "rm_header", return_raw)]
+    pub fn remove_header_obj(ncc: NativeCallContext, header: SharedObject) -> EngineResult<bool> {
+        Ok(super::Impl::remove_header(
+            &get_global!(ncc, msg),
+            &header.to_string(),
+        ))
+    }
+
+    /// Remove every occurrence of a header from the message.
+    ///
+    /// # Args
+    ///
+    /// * `header` - the name of the header to remove.
+    ///
+    /// # Return
+    ///
+    /// * `number` - the number of headers removed.
     ///
     /// # Effective smtp stage
     ///
-    /// `preq` and onwards.
+    /// All of them, although it is most useful in the `preq` stage because this
+    /// is when the email body is received.
     ///
-    /// # Example
+    /// # Examples
     ///
     /// ```
-    /// # vsmtp_test::vsl::run(
-    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Received: from mx2.example.com by mx3.example.com\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "received: from mx1.example.com\r\n",
+    /// "  by mx2.example.com\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
     /// #{
-    ///     postq: [
-    ///        action "display email content" || log("trace", `email content: ${msg::mail()}`),
-    ///     ]
+    ///   preq: [
+    ///     rule "remove_all_headers" || {
+    ///       let removed = msg::rm_all_headers("Received");
+    ///       state::accept(`250 removed ${removed}, ${msg::count_header(identifier("Received"))} left`);
+    ///     }
+    ///   ]
     /// }
-    /// # "#)?.build()));
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
+    /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
+    /// #  "250 removed 2, 0 left\r\n".parse().unwrap()
+    /// # ));
     /// ```
     ///
-    /// # rhai-autodocs:index:11
-    #[rhai_fn(name = "mail", return_raw)]
-    pub fn mail(ncc: NativeCallContext) -> EngineResult<String> {
-        Ok(vsl_guard_ok!(get_global!(ncc, msg).read())
-            .inner()
-            .to_string())
+    /// # rhai-autodocs:index:33
+    #[rhai_fn(name = "rm_all_headers", return_raw)]
+    pub fn remove_all_headers(ncc: NativeCallContext, header: &str) -> EngineResult<rhai::INT> {
+        super::Impl::remove_all_headers(&get_global!(ncc, msg), header)
     }
 
-    /// Remove an existing header from the message.
+    #[doc(hidden)]
+    #[rhai_fn(name = "rm_all_headers", return_raw)]
+    pub fn remove_all_headers_obj(
+        ncc: NativeCallContext,
+        header: SharedObject,
+    ) -> EngineResult<rhai::INT> {
+        super::Impl::remove_all_headers(&get_global!(ncc, msg), &header.to_string())
+    }
+
+    /// Remove every occurrence of a header, and append it with a new value,
+    /// so that the message contains exactly one header named `header`.
     ///
     /// # Args
     ///
-    /// * `header` - the name of the header to remove.
-    ///
-    /// # Return
-    ///
-    /// * a boolean value, true if a header has been removed, false otherwise.
+    /// * `header` - the name of the header to set.
+    /// * `value` - the value of the header.
     ///
     /// # Effective smtp stage
     ///
@@ -699,23 +2543,18 @@ mod message {
     ///
     /// ```
     /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
-    /// "Subject: The initial header value\r\n",
+    /// "Content-Type: text/plain\r\n",
+    /// "Subject: Unit test are cool\r\n",
+    /// "content-type: text/html\r\n",
     /// "\r\n",
     /// "Hello world!\r\n",
     /// # )).unwrap();
     /// # let rules = r#"
     /// #{
     ///   preq: [
-    ///     rule "remove_header" || {
-    ///       msg::rm_header("Subject");
-    ///       if msg::has_header("Subject") { return state::deny(); }
-    ///
-    ///       msg::prepend_header("Subject-2", "Rust is good");
-    ///       msg::rm_header(identifier("Subject-2"));
-    ///
-    ///       msg::prepend_header("Subject-3", "Rust is good !!!!!");
-    ///
-    ///       state::accept(`250 ${msg::get_header("Subject-3")}`);
+    ///     rule "set_all_headers" || {
+    ///       msg::set_all_headers("Content-Type", "text/plain; charset=utf-8");
+    ///       state::accept(`250 ${msg::count_header("Content-Type")} ${msg::get_header("Content-Type")}`);
     ///     }
     ///   ]
     /// }
@@ -730,23 +2569,26 @@ mod message {
     /// #   .build()), Some(msg));
     /// # use vsmtp_common::{status::Status, Reply, ReplyCode::Code};
     /// # assert_eq!(states[&vsmtp_rule_engine::ExecutionStage::PreQ].2, Status::Accept(
-    /// #  "250 Rust is good !!!!!\r\n".parse().unwrap()
+    /// #  "250 1 text/plain; charset=utf-8\r\n".parse().unwrap()
     /// # ));
     /// ```
     ///
-    /// # rhai-autodocs:index:12
-    #[rhai_fn(name = "rm_header", return_raw)]
-    pub fn remove_header(ncc: NativeCallContext, header: &str) -> EngineResult<bool> {
-        Ok(super::Impl::remove_header(&get_global!(ncc, msg), header))
+    /// # rhai-autodocs:index:39
+    #[rhai_fn(name = "set_all_headers", return_raw)]
+    pub fn set_all_headers(ncc: NativeCallContext, header: &str, value: &str) -> EngineResult<()> {
+        super::Impl::set_all_headers(&get_global!(ncc, msg), header, value);
+        Ok(())
     }
 
     #[doc(hidden)]
-    #[rhai_fn(name = "rm_header", return_raw)]
-    pub fn remove_header_obj(ncc: NativeCallContext, header: SharedObject) -> EngineResult<bool> {
-        Ok(super::Impl::remove_header(
-            &get_global!(ncc, msg),
-            &header.to_string(),
-        ))
+    #[rhai_fn(name = "set_all_headers", return_raw)]
+    pub fn set_all_headers_str_obj(
+        ncc: NativeCallContext,
+        header: &str,
+        value: SharedObject,
+    ) -> EngineResult<()> {
+        super::Impl::set_all_headers(&get_global!(ncc, msg), header, &value.to_string());
+        Ok(())
     }
 
     /// Change the sender's address in the `From` header of the message.
@@ -772,7 +2614,7 @@ mod message {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:13
+    /// # rhai-autodocs:index:34
     #[rhai_fn(name = "rw_mail_from", return_raw)]
     pub fn rewrite_mail_from_message_str(
         ncc: NativeCallContext,
@@ -814,7 +2656,7 @@ mod message {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:14
+    /// # rhai-autodocs:index:35
     #[rhai_fn(name = "rw_rcpt", return_raw)]
     pub fn rewrite_rcpt_message_str_str(
         ncc: NativeCallContext,
@@ -881,7 +2723,7 @@ mod message {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:15
+    /// # rhai-autodocs:index:36
     #[rhai_fn(name = "add_rcpt", return_raw)]
     pub fn add_rcpt_message_str(ncc: NativeCallContext, new_addr: &str) -> EngineResult<()> {
         super::Impl::add_rcpt_message(&get_global!(ncc, msg), new_addr)
@@ -919,7 +2761,7 @@ mod message {
     /// # "#)?.build()));
     /// ```
     ///
-    /// # rhai-autodocs:index:16
+    /// # rhai-autodocs:index:37
     #[rhai_fn(name = "rm_rcpt", return_raw)]
     pub fn remove_rcpt_message_str(ncc: NativeCallContext, addr: &str) -> EngineResult<()> {
         super::Impl::remove_rcpt_message(&get_global!(ncc, msg), addr)
@@ -930,6 +2772,80 @@ mod message {
     pub fn remove_rcpt_message_obj(ncc: NativeCallContext, addr: SharedObject) -> EngineResult<()> {
         super::Impl::remove_rcpt_message(&get_global!(ncc, msg), &addr.to_string())
     }
+
+    /// Add the result of an authentication method to the `Authentication-Results` header
+    /// of the server (rfc 8601), identified by the root domain of the server's name.
+    ///
+    /// The results are accumulated in a single header: the first call of the transaction
+    /// prepends a new header, and the following calls append their result to it.
+    /// The `Authentication-Results` headers of the received message claiming the identifier
+    /// of the server are forged, and removed by the first call.
+    ///
+    /// # Args
+    ///
+    /// * `method` - the authentication method, `spf`, `dkim`, `dmarc`, `arc`, `auth` ...
+    /// * `result` - the result of the method, followed by its properties if any
+    ///              (`pass smtp.mailfrom=example.com` for instance).
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Errors
+    ///
+    /// * `method` is not a valid keyword.
+    /// * `result` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let msg = vsmtp_mail_parser::MessageBody::try_from(concat!(
+    /// "Subject: Unit test are cool\r\n",
+    /// "\r\n",
+    /// "Hello world!\r\n",
+    /// # )).unwrap();
+    /// # let rules = r#"
+    /// #{
+    ///   preq: [
+    ///     action "authentication results" || {
+    ///       msg::add_authentication_results("spf", "pass smtp.mailfrom=example.com");
+    ///       msg::add_authentication_results("dkim", "fail header.d=example.com");
+    ///     }
+    ///   ]
+    /// }
+    /// # "#;
+    /// # let states = vsmtp_test::vsl::run_with_msg(|builder| Ok(builder
+    /// #   .add_root_filter_rules("#{}")?
+    /// #      .add_domain_rules("testserver.com".parse().unwrap())
+    /// #        .with_incoming(rules)?
+    /// #        .with_outgoing(rules)?
+    /// #        .with_internal(rules)?
+    /// #      .build()
+    /// #   .build()), Some(msg));
+    /// # assert_eq!(*states[&vsmtp_rule_engine::ExecutionStage::PreQ].1.inner().raw_headers(), vec![
+    /// #   "Authentication-Results: testserver.com; spf=pass smtp.mailfrom=example.com; dkim=fail header.d=example.com\r\n".to_string(),
+    /// #   "Subject: Unit test are cool\r\n".to_string(),
+    /// # ]);
+    /// ```
+    ///
+    /// # rhai-autodocs:index:38
+    #[rhai_fn(name = "add_authentication_results", return_raw)]
+    pub fn add_authentication_results(
+        ncc: NativeCallContext,
+        method: &str,
+        result: &str,
+    ) -> EngineResult<()> {
+        let ctx = get_global!(ncc, ctx);
+        let authserv_id =
+            crate::api::utils::get_root_domain(&vsl_guard_ok!(ctx.read()).server_name().to_string());
+        super::Impl::add_authentication_results(
+            &ctx,
+            &get_global!(ncc, msg),
+            &authserv_id,
+            method,
+            result,
+        )
+    }
 }
 
 pub(super) struct Impl;
@@ -945,6 +2861,111 @@ impl Impl {
             .collect()
     }
 
+    pub fn header_equals(message: &Message, name: &str, value: &str, ignore_case: bool) -> bool {
+        vsl_guard_ok!(message.read())
+            .inner()
+            .headers()
+            .into_iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .any(|(_, header)| {
+                let header = header.replace("\r\n", "");
+                let header = header.trim();
+                if ignore_case {
+                    header.eq_ignore_ascii_case(value)
+                } else {
+                    header == value
+                }
+            })
+    }
+
+    pub fn get_header_at(message: &Message, name: &str, index: rhai::INT) -> String {
+        let Ok(index) = usize::try_from(index) else {
+            return String::default();
+        };
+
+        vsl_guard_ok!(message.read())
+            .inner()
+            .headers()
+            .into_iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .nth(index)
+            .map(|(_, value)| {
+                let value = value.trim_start();
+                value.strip_suffix("\r\n").unwrap_or(value).to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_header_raw(message: &Message, name: &str) -> String {
+        vsl_guard_ok!(message.read())
+            .inner()
+            .headers()
+            .into_iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.strip_suffix("\r\n").unwrap_or(&value).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Maximum size of the compiled program of a regex used on a header or the body,
+    /// prevents rules from exhausting the memory with a huge pattern.
+    const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+    fn regex(pattern: &str) -> EngineResult<regex::Regex> {
+        Ok(vsl_conversion_ok!(
+            "regex",
+            regex::RegexBuilder::new(pattern)
+                .size_limit(Self::REGEX_SIZE_LIMIT)
+                .build()
+                .map_err(|err| anyhow::anyhow!("{err}"))
+        ))
+    }
+
+    pub fn header_matches(message: &Message, name: &str, pattern: &str) -> EngineResult<bool> {
+        let regex = Self::regex(pattern)?;
+
+        Ok(vsl_guard_ok!(message.read())
+            .get_header(name)
+            .map_or(false, |value| regex.is_match(&value)))
+    }
+
+    pub fn header_capture(
+        message: &Message,
+        name: &str,
+        pattern: &str,
+        group: rhai::INT,
+    ) -> EngineResult<String> {
+        let regex = Self::regex(pattern)?;
+        let Ok(group) = usize::try_from(group) else {
+            return Ok(String::default());
+        };
+
+        Ok(vsl_guard_ok!(message.read())
+            .get_header(name)
+            .and_then(|value| {
+                regex
+                    .captures(&value)
+                    .and_then(|captures| captures.get(group))
+                    .map(|capture| capture.as_str().to_string())
+            })
+            .unwrap_or_default())
+    }
+
+    pub fn parse_addresses(message: &Message, name: &str) -> rhai::Array {
+        vsl_guard_ok!(message.read())
+            .get_addresses(name)
+            .into_iter()
+            .map(|mailbox| {
+                rhai::Dynamic::from_map(rhai::Map::from_iter([
+                    (
+                        "display_name".into(),
+                        mailbox.display_name.unwrap_or_default().into(),
+                    ),
+                    ("address".into(), mailbox.address.into()),
+                ]))
+            })
+            .collect()
+    }
+
     pub fn get_header_untouched(msg: &Message, name: &str) -> rhai::Array {
         vsl_guard_ok!(msg.read())
             .inner()
@@ -981,6 +3002,20 @@ impl Impl {
         vsl_guard_ok!(message.write()).prepend_header(header.as_ref(), value.as_ref());
     }
 
+    pub fn insert_header<T, U, V>(message: &Message, anchor: &T, header: &U, value: &V, after: bool)
+    where
+        T: AsRef<str> + ?Sized,
+        U: AsRef<str> + ?Sized,
+        V: AsRef<str> + ?Sized,
+    {
+        let mut message = vsl_guard_ok!(message.write());
+        if after {
+            message.insert_header_after(anchor.as_ref(), header.as_ref(), value.as_ref());
+        } else {
+            message.insert_header_before(anchor.as_ref(), header.as_ref(), value.as_ref());
+        }
+    }
+
     pub fn set_header<T, U>(message: &Message, header: &T, value: &U)
     where
         T: AsRef<str> + ?Sized,
@@ -989,6 +3024,14 @@ impl Impl {
         vsl_guard_ok!(message.write()).set_header(header.as_ref(), value.as_ref());
     }
 
+    pub fn set_all_headers<T, U>(message: &Message, header: &T, value: &U)
+    where
+        T: AsRef<str> + ?Sized,
+        U: AsRef<str> + ?Sized,
+    {
+        vsl_guard_ok!(message.write()).set_all_headers(header.as_ref(), value.as_ref());
+    }
+
     pub fn rename_header<T, U>(message: &Message, old: &T, new: &U)
     where
         T: AsRef<str> + ?Sized,
@@ -997,6 +3040,182 @@ impl Impl {
         vsl_guard_ok!(message.write()).rename_header(old.as_ref(), new.as_ref());
     }
 
+    pub fn add_authentication_results(
+        ctx: &Context,
+        message: &Message,
+        authserv_id: &str,
+        method: &str,
+        result: &str,
+    ) -> EngineResult<()> {
+        const HEADER: &str = "Authentication-Results";
+
+        if method.is_empty()
+            || !method
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("`{method}` is not a valid authentication method").into());
+        }
+        let result = result.trim();
+        if result.is_empty() {
+            return Err(format!("the result of `{method}` is empty").into());
+        }
+
+        let is_ours = |value: &str| {
+            let id = value.split_once(';').map_or(value, |(id, _)| id);
+            // NOTE: the authserv-id can be followed by a version.
+            id.split_whitespace().next() == Some(authserv_id)
+        };
+
+        // NOTE: the locks are held from the lookup to the update,
+        //       so that the results are never split in two headers.
+        let mut ctx = vsl_guard_ok!(ctx.write());
+        let mut message = vsl_guard_ok!(message.write());
+
+        if !vsl_generic_ok!(ctx.authentication_results()) {
+            // NOTE: the headers claiming our authserv-id before we added ours are forged (rfc 8601 section 5).
+            message.remove_headers_if(HEADER, is_ours);
+            message.prepend_header(HEADER, &format!("{authserv_id}; {method}={result}"));
+            vsl_generic_ok!(ctx.set_authentication_results());
+
+            return Ok(());
+        }
+
+        let results = message
+            .get_header(HEADER)
+            .filter(|value| is_ours(value))
+            .and_then(|value| value.split_once(';').map(|(_, results)| results.trim().to_string()))
+            .filter(|results| !results.is_empty() && results != "none");
+
+        match results {
+            Some(results) => message.set_header(
+                HEADER,
+                &format!("{authserv_id}; {results}; {method}={result}"),
+            ),
+            None => message.set_header(HEADER, &format!("{authserv_id}; {method}={result}")),
+        }
+
+        Ok(())
+    }
+
+    pub fn ensure_message_id(message: &Message) -> String {
+        // NOTE: the lock is held from the check to the insertion,
+        //       so that the header is never added twice.
+        let mut message = vsl_guard_ok!(message.write());
+        if let Some(id) = message.get_header("Message-ID") {
+            return id.trim().to_string();
+        }
+
+        let id = format!("<{}>", vsmtp_common::utils::generate_msg_id());
+        message.prepend_header("Message-ID", &id);
+        id
+    }
+
+    pub fn mime_parts(message: &Message) -> EngineResult<rhai::Dynamic> {
+        let mut writer = vsl_guard_ok!(message.write());
+        let mail = vsl_parse_ok!(writer);
+
+        let mut parts = mail
+            .mime_parts()
+            .into_iter()
+            .map(|part| {
+                rhai::Dynamic::from_map(rhai::Map::from_iter([
+                    ("content_type".into(), part.content_type.into()),
+                    ("filename".into(), part.filename.unwrap_or_default().into()),
+                    (
+                        "size".into(),
+                        rhai::INT::try_from(part.size)
+                            .unwrap_or(rhai::INT::MAX)
+                            .into(),
+                    ),
+                    (
+                        "content_transfer_encoding".into(),
+                        part.content_transfer_encoding.into(),
+                    ),
+                    (
+                        "content_disposition".into(),
+                        part.content_disposition.unwrap_or_default().into(),
+                    ),
+                ]))
+            })
+            .collect::<rhai::Array>();
+
+        Ok(if mail.is_multipart() {
+            parts.into()
+        } else {
+            parts.pop().unwrap_or(rhai::Dynamic::UNIT)
+        })
+    }
+
+    pub fn attachment_count(message: &Message) -> EngineResult<rhai::INT> {
+        let mut writer = vsl_guard_ok!(message.write());
+
+        vsl_parse_ok!(writer)
+            .mime_parts()
+            .iter()
+            .filter(|part| part.is_attachment())
+            .count()
+            .try_into()
+            .map_err::<Box<rhai::EvalAltResult>, _>(|_| "attachment count overflowed".into())
+    }
+
+    pub fn attachment_names(message: &Message) -> EngineResult<rhai::Array> {
+        let mut writer = vsl_guard_ok!(message.write());
+
+        Ok(vsl_parse_ok!(writer)
+            .mime_parts()
+            .into_iter()
+            .filter(vsmtp_mail_parser::MimePart::is_attachment)
+            .map(|part| part.filename.unwrap_or_default().into())
+            .collect())
+    }
+
+    pub fn strip_attachments(
+        message: &Message,
+        extensions: &rhai::Array,
+    ) -> EngineResult<rhai::INT> {
+        let extensions = extensions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        vsl_generic_ok!(vsl_guard_ok!(message.write()).strip_attachments(&extensions))
+            .len()
+            .try_into()
+            .map_err::<Box<rhai::EvalAltResult>, _>(|_| "attachment count overflowed".into())
+    }
+
+    pub fn body_contains(message: &Message, pattern: &str) -> EngineResult<bool> {
+        let mut writer = vsl_guard_ok!(message.write());
+
+        Ok(vsl_parse_ok!(writer)
+            .text_parts()
+            .iter()
+            .any(|text| text.contains(pattern)))
+    }
+
+    pub fn body_matches_regex(message: &Message, pattern: &str) -> EngineResult<bool> {
+        let regex = Self::regex(pattern)?;
+        let mut writer = vsl_guard_ok!(message.write());
+
+        Ok(vsl_parse_ok!(writer)
+            .text_parts()
+            .iter()
+            .any(|text| regex.is_match(text)))
+    }
+
+    pub fn set_body(message: &Message, content: &str) -> EngineResult<()> {
+        Ok(vsl_generic_ok!(
+            vsl_guard_ok!(message.write()).set_body(content)
+        ))
+    }
+
+    pub fn append_to_body(message: &Message, content: &str) -> EngineResult<()> {
+        Ok(vsl_generic_ok!(
+            vsl_guard_ok!(message.write()).append_body(content)
+        ))
+    }
+
     pub fn remove_header<T>(message: &Message, header: &T) -> bool
     where
         T: AsRef<str> + ?Sized,
@@ -1004,6 +3223,16 @@ impl Impl {
         vsl_guard_ok!(message.write()).remove_header(header.as_ref())
     }
 
+    pub fn remove_all_headers<T>(message: &Message, header: &T) -> EngineResult<rhai::INT>
+    where
+        T: AsRef<str> + ?Sized,
+    {
+        vsl_guard_ok!(message.write())
+            .remove_all_headers(header.as_ref())
+            .try_into()
+            .map_err::<Box<rhai::EvalAltResult>, _>(|_| "header count overflowed".into())
+    }
+
     fn rewrite_mail_from_message(message: &Message, new_addr: &str) -> EngineResult<()> {
         let new_addr = vsl_conversion_ok!(
             "address",
@@ -63,7 +63,7 @@ struct SpfParameters {
 #[rhai::plugin::export_module]
 mod spf {
     use crate::api::{message::Impl, state};
-    use vsmtp_common::status::Status;
+    use vsmtp_common::{status::Status, RejectionReason};
 
     use crate::get_global;
 
@@ -244,20 +244,20 @@ mod spf {
                 Ok(match query.result.as_str() {
                     "pass" => state::next(),
                     "temperror" | "permerror" => {
-                        state::deny_with_code(&mut crate::api::code::c550_7_24())?
+                        Status::Deny(srv.config.server.smtp.reply(RejectionReason::SpfError))
                     }
                     // "softfail" | "fail"
-                    _ => state::deny_with_code(&mut crate::api::code::c550_7_23())?,
+                    _ => Status::Deny(srv.config.server.smtp.reply(RejectionReason::SpfFailed)),
                 })
             }
             Policy::Soft => {
                 Ok(match query.result.as_str() {
                     "pass" | "softfail" => state::next(),
                     "temperror" | "permerror" => {
-                        state::deny_with_code(&mut crate::api::code::c550_7_24())?
+                        Status::Deny(srv.config.server.smtp.reply(RejectionReason::SpfError))
                     }
                     // "fail"
-                    _ => state::deny_with_code(&mut crate::api::code::c550_7_23())?,
+                    _ => Status::Deny(srv.config.server.smtp.reply(RejectionReason::SpfFailed)),
                 })
             }
         }
@@ -303,6 +303,74 @@ mod spf {
 
         super::check(&ctx, &srv).map(|spf| result_to_map(&spf))
     }
+
+    /// Check spf record following the Sender Policy Framework (RFC 7208)
+    /// and record the result in a `Received-SPF` header.
+    /// see <https://datatracker.ietf.org/doc/html/rfc7208>
+    ///
+    /// Unlike `spf::check`, the result is returned as is, letting the rule
+    /// decide of the status to return.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the result of the spf check, one of "pass", "fail", "softfail",
+    ///              "neutral", "none", "temperror" or "permerror".
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and onwards.
+    ///
+    /// # Note
+    ///
+    /// `spf::check_result` only checks for the sender's identity, not the `helo` value.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// #{
+    ///     rcpt: [
+    ///        rule "deny on spf fail" || {
+    ///             if spf::check_result() == "fail" {
+    ///                 state::deny(code::c550_7_23())
+    ///             } else {
+    ///                 state::next()
+    ///             }
+    ///         },
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn(name = "check_result", return_raw)]
+    pub fn check_result(ncc: NativeCallContext) -> EngineResult<String> {
+        let ctx = get_global!(ncc, ctx);
+        let srv = get_global!(ncc, srv);
+        let query = super::check(&ctx, &srv)?;
+        let msg = get_global!(ncc, msg);
+
+        let (hostname, sender, client_ip) = {
+            let ctx = vsl_guard_ok!(ctx.read());
+
+            (
+                vsmtp_plugin_vsl::unix::hostname()?,
+                vsl_generic_ok!(ctx.reverse_path()).clone(),
+                ctx.client_addr().ip().to_string(),
+            )
+        };
+
+        Impl::prepend_header(
+            &msg,
+            SPF_HEADER,
+            &super::spf_header(
+                &query,
+                &hostname,
+                sender.as_ref().map_or("null", |sender| sender.full()),
+                &client_ip,
+            ),
+        );
+
+        Ok(query.result)
+    }
 }
 
 /// Inner spf check implementation.
@@ -344,7 +412,7 @@ pub fn check(ctx: &Context, srv: &Server) -> EngineResult<vsmtp_auth::spf::Resul
 
     let resolver = srv.resolvers.get_resolver_root();
 
-    let spf_result = block_on!(vsmtp_auth::spf::evaluate(&resolver, ip, &spf_sender));
+    let spf_result = block_on!(vsmtp_auth::spf::evaluate(&*resolver, ip, &spf_sender));
 
     vsl_guard_ok!(ctx.write())
         .set_spf(spf_result.clone())
@@ -15,7 +15,10 @@
  *
 */
 
-use crate::api::{EngineResult, SharedObject};
+use crate::{
+    api::{EngineResult, SharedObject},
+    get_global,
+};
 use rhai::plugin::{
     mem, Dynamic, EvalAltResult, FnAccess, FnNamespace, ImmutableString, Module, NativeCallContext,
     PluginFunction, RhaiResult, TypeId,
@@ -36,6 +39,16 @@ fn reply_or_code_id_from_string(code: &str) -> EngineResult<Reply> {
     })
 }
 
+/// Record the delay requested by `tarpit()` in the context, and deny with `status`.
+fn tarpit_impl(ncc: &NativeCallContext, duration: &str, status: Status) -> EngineResult<Status> {
+    let delay = humantime_serde::re::humantime::parse_duration(duration)
+        .map_err::<Box<EvalAltResult>, _>(|_| {
+            format!("parameter must be a duration, not {duration:?}").into()
+        })?;
+    vsl_generic_ok!(vsl_guard_ok!(get_global!(ncc, ctx).write()).set_tarpit(delay));
+    Ok(status)
+}
+
 pub use state::*;
 
 /// Functions used to interact with the rule engine.
@@ -353,6 +366,78 @@ mod state {
         reply_or_code_id_from_string(code).map(Status::Reject)
     }
 
+    /// Deny the transaction like `deny()`, but wait before sending the reply to slow down
+    /// the client. The delay is bounded by `server.smtp.tarpit_max` in the configuration,
+    /// and the wait is aborted if the client closes the connection.
+    ///
+    /// # Args
+    ///
+    /// * `duration` - the time to wait before sending the reply, as a string. ("10s", "1m" ...)
+    /// * code - A customized code as a string or code object. (default: "554 permanent problems with the remote server")
+    ///
+    /// # Errors
+    ///
+    /// * The duration failed to be parsed.
+    /// * The function is called outside of the `mail` and `rcpt` stages.
+    /// * The object passed as parameter was not a code object.
+    /// * The string passed as parameter failed to be parsed into a valid code.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `mail` and `rcpt`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #{
+    ///     rcpt: [
+    ///         rule "slow down the clients writing to the spam trap" || {
+    ///             if ctx::rcpt().local_part == "trap" {
+    ///                 state::tarpit("20s")
+    ///             } else {
+    ///                 state::next()
+    ///             }
+    ///         },
+    ///     ],
+    /// }
+    ///
+    /// #{
+    ///     mail: [
+    ///         rule "tarpit with a custom code" || {
+    ///             tarpit("10s", "554 5.7.1 go away")
+    ///         }
+    ///     ],
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:5
+    #[rhai_fn(global, name = "tarpit", return_raw)]
+    pub fn tarpit(ncc: NativeCallContext, duration: &str) -> EngineResult<Status> {
+        super::tarpit_impl(&ncc, duration, deny())
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(global, name = "tarpit", return_raw)]
+    pub fn tarpit_with_code(
+        ncc: NativeCallContext,
+        duration: &str,
+        code: SharedObject,
+    ) -> EngineResult<Status> {
+        let reply = reply_or_code_id_from_object(&code)?;
+        super::tarpit_impl(&ncc, duration, Status::Deny(reply))
+    }
+
+    #[doc(hidden)]
+    #[rhai_fn(global, name = "tarpit", return_raw)]
+    pub fn tarpit_with_string(
+        ncc: NativeCallContext,
+        duration: &str,
+        code: &str,
+    ) -> EngineResult<Status> {
+        let reply = reply_or_code_id_from_string(code)?;
+        super::tarpit_impl(&ncc, duration, Status::Deny(reply))
+    }
+
     /// Skip all rules until the email is received and place the email in a
     /// quarantine queue. The email will never be sent to the recipients and
     /// will stop being processed after the `PreQ` stage.
@@ -387,7 +472,7 @@ mod state {
     /// }
     /// ```
     ///
-    /// # rhai-autodocs:index:5
+    /// # rhai-autodocs:index:6
     #[must_use]
     #[rhai_fn(name = "quarantine")]
     pub fn quarantine_str(queue: &str) -> Status {
@@ -413,7 +498,7 @@ mod state {
     /// }
     /// ```
     ///
-    /// # rhai-autodocs:index:6
+    /// # rhai-autodocs:index:7
     #[rhai_fn(global, name = "==", pure)]
     pub fn eq_status_operator(status_1: &mut Status, status_2: Status) -> bool {
         *status_1 == status_2
@@ -438,7 +523,7 @@ mod state {
     /// }
     /// ```
     ///
-    /// # rhai-autodocs:index:7
+    /// # rhai-autodocs:index:8
     #[rhai_fn(global, name = "!=", pure)]
     pub fn neq_status_operator(status_1: &mut Status, status_2: Status) -> bool {
         !(*status_1 == status_2)
@@ -466,7 +551,7 @@ mod state {
     /// }
     /// ```
     ///
-    /// # rhai-autodocs:index:8
+    /// # rhai-autodocs:index:9
     #[rhai_fn(global, pure)]
     pub fn to_string(status: &mut Status) -> String {
         status.as_ref().to_string()
@@ -493,7 +578,7 @@ mod state {
     /// }
     /// ```
     ///
-    /// # rhai-autodocs:index:9
+    /// # rhai-autodocs:index:10
     #[rhai_fn(global, pure)]
     pub fn to_debug(status: &mut Status) -> String {
         status.as_ref().to_string()
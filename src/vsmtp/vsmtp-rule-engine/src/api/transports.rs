@@ -24,7 +24,8 @@ use rhai::plugin::{
     PluginFunction, RhaiResult, TypeId,
 };
 use vsmtp_common::Address;
-use vsmtp_delivery::{Deliver, Forward, MBox, Maildir, SenderParameters};
+use vsmtp_delivery::{Deliver, Forward, MBox, Maildir, SenderParameters, TlsPolicy};
+use vsmtp_plugin_vsl::objects::Object;
 
 pub use transport::*;
 
@@ -340,6 +341,105 @@ mod transport {
             .map_err::<Box<EvalAltResult>, _>(|err| err.to_string().into())
     }
 
+    /// Relay the message to another server right away, and get the reply of this
+    /// server to the message, instead of delivering it once the transaction is over.
+    ///
+    /// The envelop of the transaction is replayed, and STARTTLS is used if the
+    /// server offers it. The rules are blocked until the server replies.
+    ///
+    /// The message is still handled by vSMTP once the transaction is over, unless
+    /// the rules decide otherwise. (for example with `state::quarantine`)
+    ///
+    /// # Args
+    ///
+    /// * `host` - the domain or the ip address of the server.
+    /// * `port` - the port of the server.
+    ///
+    /// # Return
+    ///
+    /// * `code` - the reply of the server to the message, or to the first command
+    ///   it rejected.
+    ///
+    /// # Errors
+    ///
+    /// * The server could not be reached, or did not reply.
+    /// * There is no recipient.
+    ///
+    /// # Effective smtp stage
+    ///
+    /// `preq` and onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// #{
+    ///   preq: [
+    ///     rule "relay to the backend" || {
+    ///       let reply = transport::relay("backend.example.com", 25);
+    ///
+    ///       // the client receives the rejection of the backend.
+    ///       if reply.to_string().starts_with("2") {
+    ///         state::accept()
+    ///       } else {
+    ///         state::deny(reply)
+    ///       }
+    ///     },
+    ///   ],
+    /// }
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn(name = "relay", return_raw)]
+    pub fn relay(
+        ncc: NativeCallContext,
+        host: &str,
+        port: rhai::INT,
+    ) -> EngineResult<SharedObject> {
+        let transport = Forward::new(SenderParameters {
+            host: host
+                .parse()
+                .map_err::<Box<EvalAltResult>, _>(|err: anyhow::Error| err.to_string().into())?,
+            hello_name: None,
+            port: u16::try_from(port)
+                .map_err::<Box<EvalAltResult>, _>(|err| err.to_string().into())?,
+            credentials: None,
+            tls: TlsPolicy::StarttlsOpportunistic,
+        });
+
+        let (hello_name, reverse_path, forward_paths, require_tls) = {
+            let ctx = get_global!(ncc, ctx);
+            let guard = ctx.read().expect("mutex poisoned");
+            (
+                guard.server_name().clone(),
+                guard
+                    .reverse_path()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .clone(),
+                guard
+                    .forward_paths()
+                    .map_err(Into::<crate::error::RuntimeError>::into)?
+                    .clone(),
+                guard.is_require_tls(),
+            )
+        };
+        let message = get_global!(ncc, msg)
+            .read()
+            .expect("mutex poisoned")
+            .inner()
+            .to_string();
+
+        let reply = block_on!(transport.relay(
+            &hello_name,
+            &reverse_path,
+            &forward_paths,
+            message.as_bytes(),
+            require_tls
+        ))
+        .map_err::<Box<EvalAltResult>, _>(|err| err.to_string().into())?;
+
+        Ok(std::sync::Arc::new(Object::Code(reply)))
+    }
+
     /// Set the delivery method to deliver for a single recipient.
     /// After all rules are evaluated, the email will be sent
     /// to the recipient using the domain of its address.
@@ -419,7 +519,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:3
+    /// # rhai-autodocs:index:4
     #[rhai_fn(name = "deliver", return_raw)]
     pub fn deliver(ncc: NativeCallContext, rcpt: &str) -> EngineResult<()> {
         let rcpt = <Address as std::str::FromStr>::from_str(rcpt)
@@ -526,7 +626,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:4
+    /// # rhai-autodocs:index:5
     #[rhai_fn(return_raw)]
     pub fn deliver_all(ncc: NativeCallContext) -> EngineResult<()> {
         let ctx = get_global!(ncc, ctx);
@@ -609,7 +709,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:5
+    /// # rhai-autodocs:index:6
     #[rhai_fn(name = "mbox", return_raw)]
     pub fn mbox(ncc: NativeCallContext, rcpt: &str) -> EngineResult<()> {
         let rcpt = <Address as std::str::FromStr>::from_str(rcpt)
@@ -711,7 +811,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:6
+    /// # rhai-autodocs:index:7
     #[rhai_fn(return_raw)]
     pub fn mbox_all(ncc: NativeCallContext) -> EngineResult<()> {
         let ctx = get_global!(ncc, ctx);
@@ -795,7 +895,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:7
+    /// # rhai-autodocs:index:8
     #[rhai_fn(name = "maildir", return_raw)]
     pub fn maildir(ncc: NativeCallContext, rcpt: &str) -> EngineResult<()> {
         let rcpt = <Address as std::str::FromStr>::from_str(rcpt)
@@ -899,7 +999,7 @@ mod transport {
     /// # }
     /// ```
     ///
-    /// # rhai-autodocs:index:8
+    /// # rhai-autodocs:index:9
     #[rhai_fn(return_raw)]
     pub fn maildir_all(ncc: NativeCallContext) -> EngineResult<()> {
         let ctx = get_global!(ncc, ctx);
@@ -108,4 +108,522 @@ mod utils {
     pub fn env_obj(variable: &mut SharedObject) -> rhai::Dynamic {
         std::env::var(variable.to_string()).map_or(rhai::Dynamic::UNIT, std::convert::Into::into)
     }
+
+    /// Encode a string in base64 (rfc4648, with padding).
+    ///
+    /// # Args
+    ///
+    /// * `value` - the string to encode.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the base64 encoded string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "encode" || {
+    ///       // "\x00john\x00secret", a PLAIN authentication payload.
+    ///       let payload = utils::base64_encode("\x00john\x00secret");
+    /// #     if payload != "AGpvaG4Ac2VjcmV0" { return state::deny(`500 ${payload}`); }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:3
+    #[rhai_fn()]
+    #[must_use]
+    pub fn base64_encode(value: &str) -> String {
+        super::base64_encode_impl(value.as_bytes())
+    }
+
+    /// Decode a base64 encoded string (rfc4648, with padding).
+    ///
+    /// # Args
+    ///
+    /// * `value` - the base64 string to decode.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the decoded string.
+    ///
+    /// # Errors
+    ///
+    /// * The value is not valid base64.
+    /// * The decoded value is not valid utf-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "decode" || {
+    ///       let payload = utils::base64_decode("AGpvaG4Ac2VjcmV0");
+    ///       let credentials = payload.split("\x00");
+    /// #     if credentials[1] != "john" { return state::deny(`500 ${credentials}`); }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:4
+    #[rhai_fn(return_raw)]
+    pub fn base64_decode(value: &str) -> EngineResult<String> {
+        super::base64_decode_impl(value)
+            .and_then(super::to_utf8)
+            .map_err(Into::into)
+    }
+
+    /// Encode a string in hexadecimal (lowercase), two digits per byte.
+    ///
+    /// # Args
+    ///
+    /// * `value` - the string to encode.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the hexadecimal encoded string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "encode" || {
+    ///       let hex = utils::hex_encode("vSMTP");
+    /// #     if hex != "76534d5450" { return state::deny(`500 ${hex}`); }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:5
+    #[rhai_fn()]
+    #[must_use]
+    pub fn hex_encode(value: &str) -> String {
+        super::hex_encode_impl(value.as_bytes())
+    }
+
+    /// Decode a hexadecimal encoded string, the digits can be lowercase or uppercase.
+    ///
+    /// # Args
+    ///
+    /// * `value` - the hexadecimal string to decode.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the decoded string.
+    ///
+    /// # Errors
+    ///
+    /// * The value has an odd length or contains a non hexadecimal digit.
+    /// * The decoded value is not valid utf-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "decode" || {
+    ///       let value = utils::hex_decode("76534D5450");
+    /// #     if value != "vSMTP" { return state::deny(`500 ${value}`); }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:6
+    #[rhai_fn(return_raw)]
+    pub fn hex_decode(value: &str) -> EngineResult<String> {
+        super::hex_decode_impl(value)
+            .and_then(super::to_utf8)
+            .map_err(Into::into)
+    }
+
+    /// Compute the SHA-256 digest of a string.
+    ///
+    /// # Args
+    ///
+    /// * `data` - the string to hash.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the digest, in hexadecimal (lowercase).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "hash" || {
+    ///       let digest = utils::sha256("abc");
+    /// #     if digest != "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad" {
+    /// #       return state::deny(`500 ${digest}`);
+    /// #     }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:7
+    #[rhai_fn()]
+    #[must_use]
+    pub fn sha256(data: &str) -> String {
+        super::digest(&ring::digest::SHA256, data)
+    }
+
+    /// Compute the SHA-1 digest of a string.
+    ///
+    /// SHA-1 is not collision resistant anymore, only use it to interoperate
+    /// with existing systems, and prefer `utils::sha256` otherwise.
+    ///
+    /// # Args
+    ///
+    /// * `data` - the string to hash.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the digest, in hexadecimal (lowercase).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "hash" || {
+    ///       let digest = utils::sha1("abc");
+    /// #     if digest != "a9993e364706816aba3e25717850c26c9cd0d89d" {
+    /// #       return state::deny(`500 ${digest}`);
+    /// #     }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:8
+    #[rhai_fn()]
+    #[must_use]
+    pub fn sha1(data: &str) -> String {
+        super::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, data)
+    }
+
+    /// Compute the HMAC-SHA256 (rfc2104) of a string.
+    ///
+    /// # Args
+    ///
+    /// * `key` - the secret key.
+    /// * `data` - the string to authenticate.
+    ///
+    /// # Return
+    ///
+    /// * `string` - the authentication code, in hexadecimal (lowercase).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "sign" || {
+    ///       let signature = utils::hmac_sha256("Jefe", "what do ya want for nothing?");
+    /// #     if signature != "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843" {
+    /// #       return state::deny(`500 ${signature}`);
+    /// #     }
+    /// #     state::accept()
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:9
+    #[rhai_fn()]
+    #[must_use]
+    pub fn hmac_sha256(key: &str, data: &str) -> String {
+        super::hmac_sha256_impl(key, data)
+    }
+
+    /// Check a HMAC-SHA256 computed by `utils::hmac_sha256`.
+    ///
+    /// The comparison is made in constant time, so that the time taken does not
+    /// reveal how much of the expected value is correct.
+    ///
+    /// # Args
+    ///
+    /// * `key` - the secret key.
+    /// * `data` - the authenticated string.
+    /// * `expected` - the authentication code to check, in hexadecimal.
+    ///
+    /// # Return
+    ///
+    /// * `bool` - true if `expected` is the authentication code of `data` with `key`,
+    ///   false otherwise, or if `expected` is not valid hexadecimal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let states = vsmtp_test::vsl::run(
+    /// # |builder| Ok(builder.add_root_filter_rules(r#"
+    /// #{
+    ///   connect: [
+    ///     rule "verify" || {
+    ///       let signature = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+    ///       if utils::hmac_verify("Jefe", "what do ya want for nothing?", signature) {
+    ///         state::accept()
+    ///       } else {
+    ///         state::deny()
+    ///       }
+    ///     }
+    ///   ],
+    /// }
+    /// # "#)?.build()));
+    /// # assert_eq!(
+    /// #   states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+    /// #   vsmtp_rule_engine::api::state::accept()
+    /// # );
+    /// ```
+    ///
+    /// # rhai-autodocs:index:10
+    #[rhai_fn()]
+    #[must_use]
+    pub fn hmac_verify(key: &str, data: &str, expected: &str) -> bool {
+        super::hmac_verify_impl(key, data, expected)
+    }
+}
+
+fn base64_encode_impl(value: &[u8]) -> String {
+    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value)
+}
+
+fn base64_decode_impl(value: &str) -> Result<Vec<u8>, String> {
+    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)
+        .map_err(|err| format!("cannot decode '{value}' as base64: {err}"))
+}
+
+fn hex_encode_impl(value: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    value
+        .iter()
+        .flat_map(|byte| {
+            [
+                DIGITS[usize::from(byte >> 4)],
+                DIGITS[usize::from(byte & 0xf)],
+            ]
+        })
+        .map(char::from)
+        .collect()
+}
+
+fn hex_decode_impl(value: &str) -> Result<Vec<u8>, String> {
+    if value.len() % 2 != 0 {
+        return Err(format!(
+            "cannot decode '{value}' as hexadecimal: odd length"
+        ));
+    }
+
+    let digit = |digit: u8| char::from(digit).to_digit(16);
+
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+            (Some(high), Some(low)) => Ok(u8::try_from((high << 4) | low).expect("two digits")),
+            _ => Err(format!(
+                "cannot decode '{value}' as hexadecimal: invalid digits"
+            )),
+        })
+        .collect()
+}
+
+fn digest(algorithm: &'static ring::digest::Algorithm, data: &str) -> String {
+    hex_encode_impl(ring::digest::digest(algorithm, data.as_bytes()).as_ref())
+}
+
+fn hmac_sha256_impl(key: &str, data: &str) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_bytes());
+    hex_encode_impl(ring::hmac::sign(&key, data.as_bytes()).as_ref())
+}
+
+fn hmac_verify_impl(key: &str, data: &str, expected: &str) -> bool {
+    let Ok(expected) = hex_decode_impl(expected) else {
+        return false;
+    };
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_bytes());
+    ring::hmac::verify(&key, data.as_bytes(), &expected).is_ok()
+}
+
+fn to_utf8(value: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(value).map_err(|err| format!("the decoded value is not valid utf-8: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        base64_decode_impl, base64_encode_impl, digest, hex_decode_impl, hex_encode_impl,
+        hmac_sha256_impl, hmac_verify_impl,
+    };
+
+    #[test]
+    fn base64_round_trip() {
+        for value in ["", "f", "fo", "foo", "\0john\0secret", "é€😀"] {
+            let encoded = base64_encode_impl(value.as_bytes());
+            assert_eq!(base64_decode_impl(&encoded).unwrap(), value.as_bytes());
+        }
+        assert_eq!(base64_encode_impl(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn base64_invalid() {
+        for value in ["Zm9v!", "Zm9vY", "Zm9vYmFy=", "Z m9v"] {
+            assert!(base64_decode_impl(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for value in [&b""[..], b"\x00\xff", b"foobar", "é€😀".as_bytes()] {
+            let encoded = hex_encode_impl(value);
+            assert_eq!(hex_decode_impl(&encoded).unwrap(), value);
+        }
+        assert_eq!(hex_encode_impl(b"\x00\x0f\xab"), "000fab");
+        assert_eq!(hex_decode_impl("000FAB").unwrap(), b"\x00\x0f\xab");
+    }
+
+    #[test]
+    fn hex_invalid() {
+        for value in ["0", "abc", "zz", "+f", "0x00", "é"] {
+            assert!(hex_decode_impl(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn sha256() {
+        for (data, expected) in [
+            (
+                "",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                "abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ] {
+            assert_eq!(digest(&ring::digest::SHA256, data), expected, "{data}");
+        }
+    }
+
+    #[test]
+    fn sha1() {
+        for (data, expected) in [
+            ("", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            ("abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ] {
+            assert_eq!(
+                digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, data),
+                expected,
+                "{data}"
+            );
+        }
+    }
+
+    // NOTE: the first one is the test case 2 of rfc4231.
+    const HMAC_SHA256: [(&str, &str, &str); 2] = [
+        (
+            "Jefe",
+            "what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        ),
+        (
+            "key",
+            "The quick brown fox jumps over the lazy dog",
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+        ),
+    ];
+
+    #[test]
+    fn hmac() {
+        for (key, data, expected) in HMAC_SHA256 {
+            assert_eq!(hmac_sha256_impl(key, data), expected, "{data}");
+        }
+    }
+
+    #[test]
+    fn hmac_check() {
+        for (key, data, expected) in HMAC_SHA256 {
+            assert!(hmac_verify_impl(key, data, expected), "{data}");
+            assert!(
+                hmac_verify_impl(key, data, &expected.to_uppercase()),
+                "{data}"
+            );
+            assert!(!hmac_verify_impl("wrong key", data, expected), "{data}");
+            assert!(!hmac_verify_impl(key, "tampered", expected), "{data}");
+            assert!(!hmac_verify_impl(key, data, &expected[..62]), "{data}");
+            assert!(!hmac_verify_impl(key, data, "not hexadecimal"), "{data}");
+        }
+    }
 }
@@ -0,0 +1,187 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use trust_dns_resolver::{
+    error::{ResolveError, ResolveErrorKind},
+    TokioAsyncResolver,
+};
+use vsmtp_common::DnsblListing;
+
+/// Look up `ip` in the DNS blocklist `zone`, see <https://www.rfc-editor.org/rfc/rfc5782>.
+///
+/// The A and TXT records are queried concurrently. A failed lookup is considered as
+/// not listed, an unreachable blocklist must not reject every client.
+pub async fn lookup(
+    resolver: &TokioAsyncResolver,
+    ip: std::net::IpAddr,
+    zone: &str,
+) -> Option<DnsblListing> {
+    let name = query_name(ip, zone);
+
+    match listing(
+        async {
+            records(
+                resolver
+                    .ipv4_lookup(name.as_str())
+                    .await
+                    .map(|lookup| lookup.iter().copied().collect()),
+            )
+        },
+        async {
+            records(
+                resolver
+                    .txt_lookup(name.as_str())
+                    .await
+                    .map(|lookup| lookup.iter().map(ToString::to_string).collect()),
+            )
+        },
+    )
+    .await
+    {
+        Ok(listing) => {
+            tracing::debug!(%ip, zone, listed = listing.is_some(), "DNSBL lookup.");
+            listing
+        }
+        Err(error) => {
+            tracing::warn!(%error, %ip, zone, "DNSBL lookup failed.");
+            None
+        }
+    }
+}
+
+/// The reversed octets of an IPv4, or the reversed nibbles of an IPv6, followed by the zone.
+fn query_name(ip: std::net::IpAddr, zone: &str) -> String {
+    let reversed = match ip {
+        std::net::IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}")
+        }
+        std::net::IpAddr::V6(ip) => ip
+            .octets()
+            .iter()
+            .rev()
+            .flat_map(|byte| [byte & 0x0f, byte >> 4])
+            .map(|nibble| format!("{nibble:x}"))
+            .collect::<Vec<_>>()
+            .join("."),
+    };
+
+    // NOTE: fully qualified, the search domains of the resolver must not be appended.
+    format!("{reversed}.{}.", zone.trim_end_matches('.'))
+}
+
+/// An address not listed has no record.
+pub fn records<T>(result: Result<Vec<T>, ResolveError>) -> Result<Vec<T>, ResolveError> {
+    match result {
+        Err(error) if matches!(error.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(vec![]),
+        otherwise => otherwise,
+    }
+}
+
+async fn listing<E: Send>(
+    records: impl std::future::Future<Output = Result<Vec<std::net::Ipv4Addr>, E>> + Send,
+    explanation: impl std::future::Future<Output = Result<Vec<String>, E>> + Send,
+) -> Result<Option<DnsblListing>, E> {
+    let (records, explanation) = tokio::join!(records, explanation);
+    let records = records?;
+
+    Ok((!records.is_empty()).then(|| DnsblListing {
+        records,
+        explanation: explanation
+            .ok()
+            .and_then(|explanation| explanation.into_iter().next()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{listing, query_name};
+    use vsmtp_common::DnsblListing;
+
+    /// A resolver answering the records of the blocklist, `Err` if the blocklist is unreachable.
+    async fn mock_resolver<T: Clone + Sync>(
+        records: Result<&[T], &'static str>,
+    ) -> Result<Vec<T>, &'static str> {
+        tokio::task::yield_now().await;
+        records.map(<[T]>::to_vec)
+    }
+
+    #[test]
+    fn reversed_name() {
+        assert_eq!(
+            query_name("192.0.2.17".parse().unwrap(), "zen.spamhaus.org"),
+            "17.2.0.192.zen.spamhaus.org."
+        );
+        assert_eq!(
+            query_name("2001:db8::1".parse().unwrap(), "dnsbl.example.net."),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.dnsbl.example.net."
+        );
+    }
+
+    #[tokio::test]
+    async fn listed() {
+        assert_eq!(
+            listing(
+                mock_resolver(Ok(&[
+                    "127.0.0.2".parse().unwrap(),
+                    "127.0.0.4".parse().unwrap()
+                ])),
+                mock_resolver(Ok(&["Listed, see https://dnsbl.example.net".to_owned()])),
+            )
+            .await,
+            Ok(Some(DnsblListing {
+                records: vec!["127.0.0.2".parse().unwrap(), "127.0.0.4".parse().unwrap()],
+                explanation: Some("Listed, see https://dnsbl.example.net".to_owned()),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn listed_without_explanation() {
+        assert_eq!(
+            listing(
+                mock_resolver(Ok(&["127.0.0.2".parse().unwrap()])),
+                mock_resolver(Ok(&[])),
+            )
+            .await,
+            Ok(Some(DnsblListing {
+                records: vec!["127.0.0.2".parse().unwrap()],
+                explanation: None,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn not_listed() {
+        assert_eq!(
+            listing(mock_resolver(Ok(&[])), mock_resolver(Ok(&[]))).await,
+            Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable() {
+        assert_eq!(
+            listing(
+                mock_resolver(Err("SERVFAIL")),
+                mock_resolver(Err("SERVFAIL"))
+            )
+            .await,
+            Err("SERVFAIL")
+        );
+    }
+}
@@ -139,6 +139,12 @@ impl Script {
         self.directives.values().flatten()
     }
 
+    /// Run the directives of a stage in the order of declaration.
+    ///
+    /// The evaluation stops at the first directive returning anything else than
+    /// [`Status::Next`] (`accept`, `deny`, `quarantine` ...): the following directives
+    /// of the stage are skipped and this status is returned. An action, or a rule
+    /// returning `next`, continues with the next directive.
     pub(crate) fn execute(
         rule_state: &RuleState,
         ast: &rhai::AST,
@@ -207,6 +213,7 @@ impl SubDomainHierarchy {
             FieldAppVSL {
                 filter_path: Some(filter_path),
                 domain_dir,
+                ..
             } => {
                 tracing::info!("Analyzing vSL rules at {}", filter_path.display());
 
@@ -0,0 +1,113 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::{ExecutionStage, RuleEngine};
+use vsmtp_common::{
+    status::Status, Address, ClientName, ConnectProperties, ContextFinished, Domain,
+    FinishedProperties, HeloProperties, MailFromProperties, RcptToProperties, TransactionType,
+};
+use vsmtp_mail_parser::MessageBody;
+
+/// Build the context of a transaction received on the loopback interface,
+/// used to run the rules outside of any SMTP session.
+#[must_use]
+pub fn local_context(
+    server_name: Domain,
+    client_name: ClientName,
+    reverse_path: Option<Address>,
+    forward_paths: Vec<Address>,
+    transaction_type: TransactionType,
+) -> ContextFinished {
+    ContextFinished {
+        connect: ConnectProperties {
+            connect_timestamp: time::OffsetDateTime::now_utc(),
+            client_addr: "127.0.0.1:25".parse().expect("valid socket address"),
+            server_addr: "127.0.0.1:5977".parse().expect("valid socket address"),
+            server_name,
+            connect_uuid: uuid::Uuid::new_v4(),
+            auth: None,
+            tls: None,
+            skipped: None,
+            client_rdns: None,
+            connection_blocked: false,
+            dnsbl: std::collections::HashMap::new(),
+            tarpit: None,
+        },
+        helo: HeloProperties {
+            client_name,
+            using_deprecated: false,
+            capabilities: vec![],
+        },
+        mail_from: MailFromProperties {
+            mail_timestamp: time::OffsetDateTime::now_utc(),
+            message_uuid: uuid::Uuid::new_v4(),
+            transaction_id: uuid::Uuid::new_v4(),
+            reverse_path,
+            spf: None,
+            utf8: false,
+            require_tls: false,
+            envelop_id: None,
+            ret: None,
+            asserted_sender: None,
+            sender_has_mx: None,
+        },
+        rcpt_to: RcptToProperties {
+            forward_paths,
+            delivery: std::collections::HashMap::new(),
+            transaction_type,
+            dsn: std::collections::HashMap::new(),
+        },
+        finished: FinishedProperties {
+            dkim: None,
+            dkim_public_keys: std::collections::HashMap::new(),
+            authentication_results: false,
+            quarantine: None,
+        },
+    }
+}
+
+impl RuleEngine {
+    /// Run the rules of `stage` on a message, outside of any SMTP session.
+    ///
+    /// # Return
+    ///
+    /// A tuple with the mail context, body and result status.
+    ///
+    /// # Errors
+    ///
+    /// * the runtime used to execute the rules could not be built
+    pub fn dry_run(
+        &self,
+        stage: ExecutionStage,
+        mail_context: ContextFinished,
+        mail_message: MessageBody,
+    ) -> anyhow::Result<(vsmtp_common::Context, MessageBody, Status)> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let mut skipped = None;
+
+        Ok(runtime.block_on(async move {
+            self.just_run_when(
+                &mut skipped,
+                stage,
+                vsmtp_common::Context::Finished(mail_context),
+                mail_message,
+            )
+        }))
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+/// Entries of a file, and the modification time of the file when it was read.
+#[derive(Debug)]
+struct List {
+    modified: std::time::SystemTime,
+    entries: std::collections::HashSet<String>,
+}
+
+/// The newline-delimited files consulted by `fs::in_file()`, shared by all the
+/// connections of the server.
+///
+/// A file is read on its first lookup, and read again only when its modification
+/// time has changed, so that a list can be edited without restarting the server.
+#[derive(Debug, Default)]
+pub struct FileLists {
+    lists: std::sync::Mutex<std::collections::HashMap<std::path::PathBuf, List>>,
+}
+
+/// One entry per line, surrounding whitespaces are trimmed.
+/// Empty lines and the lines starting with `#` are ignored.
+fn parse(content: &str) -> std::collections::HashSet<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+impl FileLists {
+    /// Is `value` one of the entries of the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// * The file cannot be read.
+    pub fn contains(&self, path: &std::path::Path, value: &str) -> std::io::Result<bool> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        self.contains_at(path, modified, value, || std::fs::read_to_string(path))
+    }
+
+    fn contains_at(
+        &self,
+        path: &std::path::Path,
+        modified: std::time::SystemTime,
+        value: &str,
+        read: impl FnOnce() -> std::io::Result<String>,
+    ) -> std::io::Result<bool> {
+        // NOTE: the lock is held while reading, a file is read once even if
+        //       it is looked up by several connections at the same time.
+        let mut lists = self.lists.lock().unwrap();
+
+        if lists
+            .get(path)
+            .map_or(true, |list| list.modified != modified)
+        {
+            let entries = parse(&read()?);
+            tracing::debug!(path = %path.display(), entries = entries.len(), "List loaded.");
+
+            lists.insert(path.to_path_buf(), List { modified, entries });
+        }
+
+        Ok(lists[path].entries.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileLists;
+
+    const LIST: &str = "# blocklist\n\n  spam.example.com  \nphishing.example.com\n";
+
+    #[test]
+    fn contains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocklist.txt");
+        std::fs::write(&path, LIST).unwrap();
+
+        let lists = FileLists::default();
+        for (value, expected) in [
+            ("spam.example.com", true),
+            ("phishing.example.com", true),
+            ("example.com", false),
+            ("# blocklist", false),
+            ("", false),
+        ] {
+            assert_eq!(lists.contains(&path, value).unwrap(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn reload_when_modified() {
+        let lists = FileLists::default();
+        let path = std::path::Path::new("blocklist.txt");
+        let modified = std::time::SystemTime::UNIX_EPOCH;
+
+        assert!(lists
+            .contains_at(path, modified, "spam.example.com", || Ok(LIST.to_owned()))
+            .unwrap());
+
+        // NOTE: same modification time, the file is not read again.
+        assert!(lists
+            .contains_at(path, modified, "spam.example.com", || unreachable!())
+            .unwrap());
+
+        let modified = modified + std::time::Duration::from_secs(1);
+        assert!(!lists
+            .contains_at(path, modified, "spam.example.com", || Ok(String::new()))
+            .unwrap());
+    }
+
+    #[test]
+    fn unreadable() {
+        assert!(FileLists::default()
+            .contains(std::path::Path::new("/does/not/exist"), "spam.example.com")
+            .is_err());
+    }
+}
@@ -0,0 +1,110 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use anyhow::Context;
+use vsmtp_config::field::FieldAppGeoIp;
+
+/// Autonomous system announcing an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asn {
+    /// Number of the autonomous system.
+    pub number: u32,
+    /// Organization operating the autonomous system.
+    pub organization: String,
+}
+
+/// Lookup of the client addresses in the `MaxMind` databases of `app.geoip`.
+pub struct GeoIp {
+    asn: Option<maxminddb::Reader<Vec<u8>>>,
+    country: Option<maxminddb::Reader<Vec<u8>>>,
+}
+
+impl std::fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIp")
+            .field(
+                "asn",
+                &self.asn.as_ref().map(|db| &db.metadata.database_type),
+            )
+            .field(
+                "country",
+                &self.country.as_ref().map(|db| &db.metadata.database_type),
+            )
+            .finish()
+    }
+}
+
+fn open(path: Option<&std::path::PathBuf>) -> anyhow::Result<Option<maxminddb::Reader<Vec<u8>>>> {
+    path.map(|path| {
+        maxminddb::Reader::open_readfile(path)
+            .with_context(|| format!("cannot open the MaxMind database '{}'", path.display()))
+    })
+    .transpose()
+}
+
+impl GeoIp {
+    /// Load the databases in memory.
+    ///
+    /// # Errors
+    ///
+    /// * a database cannot be read, or is not in the `MaxMind` DB format
+    pub fn load(config: &FieldAppGeoIp) -> anyhow::Result<Self> {
+        Ok(Self {
+            asn: open(config.asn.as_ref())?,
+            country: open(config.country.as_ref())?,
+        })
+    }
+
+    /// Has an ASN database been configured.
+    pub const fn has_asn(&self) -> bool {
+        self.asn.is_some()
+    }
+
+    /// Has a Country database been configured.
+    pub const fn has_country(&self) -> bool {
+        self.country.is_some()
+    }
+
+    /// Autonomous system of `ip`, `None` if the address is not in the database.
+    pub fn asn(&self, ip: std::net::IpAddr) -> Option<Asn> {
+        let asn = self
+            .asn
+            .as_ref()?
+            .lookup::<maxminddb::geoip2::Asn<'_>>(ip)
+            .map_err(|error| tracing::trace!(%ip, %error, "ASN lookup failed."))
+            .ok()?;
+
+        Some(Asn {
+            number: asn.autonomous_system_number?,
+            organization: asn
+                .autonomous_system_organization
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    /// ISO 3166-1 code of the country of `ip`, `None` if the address is not in the database.
+    pub fn country(&self, ip: std::net::IpAddr) -> Option<String> {
+        let country = self
+            .country
+            .as_ref()?
+            .lookup::<maxminddb::geoip2::Country<'_>>(ip)
+            .map_err(|error| tracing::trace!(%ip, %error, "Country lookup failed."))
+            .ok()?;
+
+        country.country?.iso_code.map(str::to_string)
+    }
+}
@@ -0,0 +1,374 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use anyhow::Context;
+use vsmtp_common::Address;
+use vsmtp_config::field::FieldAppGreylist;
+
+/// Name of the file storing the triplets, in the application directory.
+pub const GREYLIST_FILENAME: &str = "greylist.json";
+
+/// Outcome of the greylisting of a triplet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum Decision {
+    /// The triplet has never been seen, or has expired: deferred.
+    FirstContact,
+    /// The triplet has been seen, but the delay has not elapsed yet: deferred.
+    TooEarly,
+    /// The client has retried after the delay: accepted.
+    Passed,
+}
+
+/// The client network (`/24` in IPv4, `/64` in IPv6), the sender and the recipient of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+struct Triplet {
+    network: String,
+    sender: String,
+    recipient: String,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct Entry {
+    // NOTE: seconds since the unix epoch, the store outlives the process.
+    first_seen: u64,
+    last_seen: u64,
+    passed: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredEntry {
+    #[serde(flatten)]
+    triplet: Triplet,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+/// Interval between two saves of the triplets on disk.
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Triplets seen by `greylist::check()`, shared by all the connections of the server.
+///
+/// The triplets are saved on disk by a background thread every [`FLUSH_INTERVAL`]
+/// and when the greylist is dropped, so that a restart does not reset them.
+#[derive(Debug)]
+pub struct Greylist {
+    delay: u64,
+    store: std::sync::Arc<Store>,
+}
+
+#[derive(Debug)]
+struct Store {
+    expiry: u64,
+    path: std::path::PathBuf,
+    triplets: std::sync::Mutex<std::collections::HashMap<Triplet, Entry>>,
+    dirty: std::sync::atomic::AtomicBool,
+    // NOTE: held while writing the file, the background thread and the drop can flush concurrently.
+    writing: std::sync::Mutex<()>,
+}
+
+fn client_network(ip: std::net::IpAddr) -> String {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        std::net::IpAddr::V6(ip) => {
+            let [a, b, c, d, ..] = ip.segments();
+            format!("{}/64", std::net::Ipv6Addr::new(a, b, c, d, 0, 0, 0, 0))
+        }
+    }
+}
+
+fn unix_timestamp(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl Greylist {
+    /// Load the triplets stored at `path`, without the expired ones.
+    /// The store is empty if the file does not exist.
+    ///
+    /// # Errors
+    ///
+    /// * The file could not be read or is malformed.
+    pub fn load(config: &FieldAppGreylist, path: std::path::PathBuf) -> anyhow::Result<Self> {
+        let stored = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice::<Vec<StoredEntry>>(&content)
+                .with_context(|| format!("Greylist at {path:?} is malformed"))?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(error) => {
+                return Err(error).with_context(|| format!("Cannot read greylist at {path:?}"))
+            }
+        };
+
+        let store = std::sync::Arc::new(Store {
+            expiry: config.expiry.as_secs(),
+            path,
+            triplets: std::sync::Mutex::new(
+                stored
+                    .into_iter()
+                    .map(|StoredEntry { triplet, entry }| (triplet, entry))
+                    .collect(),
+            ),
+            dirty: std::sync::atomic::AtomicBool::new(false),
+            writing: std::sync::Mutex::new(()),
+        });
+        store.flush(unix_timestamp(std::time::SystemTime::now()));
+
+        std::thread::Builder::new()
+            .name("greylist-flush".to_owned())
+            .spawn({
+                let store = std::sync::Arc::downgrade(&store);
+                move || loop {
+                    std::thread::sleep(FLUSH_INTERVAL);
+                    let Some(store) = store.upgrade() else {
+                        break;
+                    };
+                    store.flush(unix_timestamp(std::time::SystemTime::now()));
+                }
+            })
+            .context("Cannot spawn the greylist flush thread")?;
+
+        Ok(Self {
+            delay: config.delay.as_secs(),
+            store,
+        })
+    }
+
+    /// Greylist the triplet of the transaction, and record it.
+    ///
+    /// # Panics
+    ///
+    /// * the triplets have been poisoned
+    pub fn check(
+        &self,
+        client_ip: std::net::IpAddr,
+        sender: Option<&Address>,
+        recipient: &Address,
+    ) -> Decision {
+        let triplet = Triplet {
+            network: client_network(client_ip),
+            sender: sender.map_or_else(String::new, ToString::to_string),
+            recipient: recipient.to_string(),
+        };
+
+        let decision = self.check_at(&triplet, unix_timestamp(std::time::SystemTime::now()));
+
+        tracing::info!(
+            network = triplet.network,
+            sender = triplet.sender,
+            recipient = triplet.recipient,
+            decision = decision.as_ref(),
+            "Greylisting."
+        );
+
+        decision
+    }
+
+    fn check_at(&self, triplet: &Triplet, now: u64) -> Decision {
+        let mut triplets = self.store.triplets.lock().unwrap();
+
+        let decision = match triplets.get_mut(triplet) {
+            Some(entry) if !self.store.is_expired(entry, now) => {
+                entry.last_seen = now;
+                if entry.passed || entry.first_seen.saturating_add(self.delay) <= now {
+                    entry.passed = true;
+                    Decision::Passed
+                } else {
+                    Decision::TooEarly
+                }
+            }
+            _ => {
+                triplets.insert(
+                    triplet.clone(),
+                    Entry {
+                        first_seen: now,
+                        last_seen: now,
+                        passed: false,
+                    },
+                );
+                Decision::FirstContact
+            }
+        };
+        self.store
+            .dirty
+            .store(true, std::sync::atomic::Ordering::Release);
+
+        decision
+    }
+}
+
+impl Drop for Greylist {
+    fn drop(&mut self) {
+        self.store
+            .flush(unix_timestamp(std::time::SystemTime::now()));
+    }
+}
+
+impl Store {
+    const fn is_expired(&self, entry: &Entry, now: u64) -> bool {
+        entry.last_seen.saturating_add(self.expiry) < now
+    }
+
+    /// Remove the expired triplets and, if the store has changed since the last flush,
+    /// write it in a temporary file first, then rename it, so that a partially written
+    /// store is never loaded.
+    ///
+    /// The triplets are copied under the lock, but serialized and written without it.
+    fn flush(&self, now: u64) {
+        let stored = {
+            let mut triplets = self.triplets.lock().unwrap();
+            let len = triplets.len();
+            triplets.retain(|_, entry| !self.is_expired(entry, now));
+
+            if !self
+                .dirty
+                .swap(false, std::sync::atomic::Ordering::AcqRel)
+                && len == triplets.len()
+            {
+                return;
+            }
+
+            triplets
+                .iter()
+                .map(|(triplet, entry)| StoredEntry {
+                    triplet: triplet.clone(),
+                    entry: *entry,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let _writing = self.writing.lock().unwrap();
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        if let Err(error) = serde_json::to_vec(&stored)
+            .map_err(std::io::Error::from)
+            .and_then(|content| std::fs::write(&tmp, content))
+            .and_then(|()| std::fs::rename(&tmp, &self.path))
+        {
+            tracing::warn!(%error, path = ?self.path, "Failed to save the greylist.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_network, Decision, Greylist, Triplet};
+    use vsmtp_config::field::FieldAppGreylist;
+
+    fn greylist(dir: &tempfile::TempDir) -> Greylist {
+        Greylist::load(
+            &FieldAppGreylist {
+                delay: std::time::Duration::from_secs(300),
+                expiry: std::time::Duration::from_secs(3600),
+            },
+            dir.path().join(super::GREYLIST_FILENAME),
+        )
+        .unwrap()
+    }
+
+    fn triplet(recipient: &str) -> Triplet {
+        Triplet {
+            network: "192.0.2.0/24".to_owned(),
+            sender: "john@doe".to_owned(),
+            recipient: recipient.to_owned(),
+        }
+    }
+
+    #[test]
+    fn network() {
+        assert_eq!(
+            client_network("192.0.2.17".parse().unwrap()),
+            "192.0.2.0/24"
+        );
+        assert_eq!(
+            client_network("2001:db8:1:2:3:4:5:6".parse().unwrap()),
+            "2001:db8:1:2::/64"
+        );
+    }
+
+    #[test]
+    fn deferred_then_passed() {
+        let dir = tempfile::tempdir().unwrap();
+        let greylist = greylist(&dir);
+
+        assert_eq!(
+            greylist.check_at(&triplet("aa@bb"), 1000),
+            Decision::FirstContact
+        );
+        assert_eq!(
+            greylist.check_at(&triplet("aa@bb"), 1299),
+            Decision::TooEarly
+        );
+        assert_eq!(
+            greylist.check_at(&triplet("cc@dd"), 1299),
+            Decision::FirstContact
+        );
+        assert_eq!(greylist.check_at(&triplet("aa@bb"), 1300), Decision::Passed);
+        assert_eq!(greylist.check_at(&triplet("aa@bb"), 1301), Decision::Passed);
+    }
+
+    #[test]
+    fn expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let greylist = greylist(&dir);
+
+        assert_eq!(
+            greylist.check_at(&triplet("aa@bb"), 1000),
+            Decision::FirstContact
+        );
+        assert_eq!(greylist.check_at(&triplet("aa@bb"), 1300), Decision::Passed);
+        assert_eq!(greylist.check_at(&triplet("aa@bb"), 4900), Decision::Passed);
+        assert_eq!(
+            greylist.check_at(&triplet("aa@bb"), 8501),
+            Decision::FirstContact
+        );
+    }
+
+    #[test]
+    fn flushed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(super::GREYLIST_FILENAME);
+
+        let greylist = greylist(&dir);
+        assert_eq!(
+            greylist.check_at(&triplet("aa@bb"), 1000),
+            Decision::FirstContact
+        );
+        assert!(!path.exists());
+
+        drop(greylist);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn persisted() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let now = super::unix_timestamp(std::time::SystemTime::now());
+        assert_eq!(
+            greylist(&dir).check_at(&triplet("aa@bb"), now - 600),
+            Decision::FirstContact
+        );
+        assert_eq!(
+            greylist(&dir).check_at(&triplet("aa@bb"), now),
+            Decision::Passed
+        );
+    }
+}
@@ -53,13 +53,23 @@ pub use rhai;
 
 #[macro_use]
 mod error;
+mod dnsbl;
+mod dry_run;
 mod execution_stage;
+mod file_list;
+mod geoip;
+mod greylist;
+mod mx;
+mod reverse_lookup;
 mod rule_engine;
 mod rule_state;
 mod server_api;
+mod srs;
 
+pub use dry_run::local_context;
 pub use dsl::directives::Directive;
 pub use execution_stage::ExecutionStage;
+pub use reverse_lookup::client_rdns;
 pub use rule_engine::RuleEngine;
 pub use rule_state::RuleState;
 
@@ -106,6 +116,10 @@ pub mod api {
     pub mod envelop;
     /// API to write of the message on disk.
     pub mod fs;
+    /// Geolocation of the clients.
+    pub mod geoip;
+    /// Greylisting of the clients.
+    pub mod greylist;
     /// Log a message of `level` in the `app` target, which will be written to the
     /// the fie you specified in the field `app.logs.filename` form the [`vsmtp_config::Config`].
     pub mod logging;
@@ -148,7 +162,7 @@ pub mod api {
 
     /// Get vsmtp static modules.
     #[must_use]
-    pub fn vsmtp_static_modules() -> [(&'static str, rhai::Module); 20] {
+    pub fn vsmtp_static_modules() -> [(&'static str, rhai::Module); 22] {
         [
             ("state", rhai::exported_module!(state)),
             ("envelop", rhai::exported_module!(envelop)),
@@ -162,6 +176,8 @@ pub mod api {
             ("spf", rhai::exported_module!(spf)),
             ("dkim", rhai::exported_module!(dkim)),
             ("dmarc", rhai::exported_module!(dmarc)),
+            ("greylist", rhai::exported_module!(greylist)),
+            ("geoip", rhai::exported_module!(geoip)),
             ("transport", rhai::exported_module!(transports)),
             ("utils", rhai::exported_module!(utils)),
             ("ctx", rhai::exported_module!(mail_context)),
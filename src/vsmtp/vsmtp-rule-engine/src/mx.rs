@@ -0,0 +1,164 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use crate::dnsbl::records;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Does `domain` accept mails, see <https://www.rfc-editor.org/rfc/rfc5321#section-5.1>.
+///
+/// The domain has MX records, or no MX record but an A or AAAA record, the domain itself
+/// being the implicit MX. A domain publishing a null MX (rfc 7505) does not accept mails.
+///
+/// # Errors
+///
+/// * the lookup failed or was not completed within `timeout` (temperror),
+///   unlike a domain without records, the answer is unknown.
+pub async fn has_mx(
+    resolver: &TokioAsyncResolver,
+    domain: &str,
+    timeout: std::time::Duration,
+) -> Result<bool, String> {
+    // NOTE: fully qualified, the search domains of the resolver must not be appended.
+    let name = format!("{}.", domain.trim_end_matches('.'));
+
+    let lookup = accepts_mails(
+        async {
+            records(
+                resolver
+                    .mx_lookup(name.as_str())
+                    .await
+                    .map(|lookup| lookup.iter().map(|mx| mx.exchange().to_string()).collect()),
+            )
+        },
+        async {
+            records(
+                resolver
+                    .lookup_ip(name.as_str())
+                    .await
+                    .map(|lookup| lookup.iter().collect()),
+            )
+        },
+    );
+
+    match tokio::time::timeout(timeout, lookup).await {
+        Ok(Ok(has_mx)) => {
+            tracing::debug!(domain, has_mx, "MX lookup.");
+            Ok(has_mx)
+        }
+        Ok(Err(error)) => {
+            tracing::warn!(%error, domain, "MX lookup failed.");
+            Err(error.to_string())
+        }
+        Err(_elapsed) => {
+            tracing::warn!(?timeout, domain, "MX lookup timed out.");
+            Err(format!(
+                "MX lookup of '{domain}' timed out after {timeout:?}"
+            ))
+        }
+    }
+}
+
+/// The addresses are only queried if the domain has no MX record.
+async fn accepts_mails<E: Send>(
+    exchanges: impl std::future::Future<Output = Result<Vec<String>, E>> + Send,
+    addresses: impl std::future::Future<Output = Result<Vec<std::net::IpAddr>, E>> + Send,
+) -> Result<bool, E> {
+    let exchanges = exchanges.await?;
+    if !exchanges.is_empty() {
+        // NOTE: a single MX record with the root as exchange is a null MX.
+        return Ok(exchanges.iter().any(|exchange| exchange != "."));
+    }
+
+    Ok(!addresses.await?.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::accepts_mails;
+
+    /// A resolver answering the records of the domain, `Err` if the DNS server is unreachable.
+    async fn mock_resolver<T: Clone + Sync>(
+        records: Result<&[T], &'static str>,
+    ) -> Result<Vec<T>, &'static str> {
+        tokio::task::yield_now().await;
+        records.map(<[T]>::to_vec)
+    }
+
+    #[tokio::test]
+    async fn with_mx() {
+        assert_eq!(
+            accepts_mails(
+                mock_resolver(Ok(&["mx1.example.com.".to_owned()])),
+                mock_resolver(Err("the addresses are not queried")),
+            )
+            .await,
+            Ok(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn only_a() {
+        assert_eq!(
+            accepts_mails(
+                mock_resolver(Ok(&[])),
+                mock_resolver(Ok(&["192.0.2.1".parse().unwrap()])),
+            )
+            .await,
+            Ok(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn neither() {
+        assert_eq!(
+            accepts_mails(mock_resolver(Ok(&[])), mock_resolver(Ok(&[]))).await,
+            Ok(false)
+        );
+    }
+
+    #[tokio::test]
+    async fn null_mx() {
+        assert_eq!(
+            accepts_mails(
+                mock_resolver(Ok(&[".".to_owned()])),
+                mock_resolver(Ok(&["192.0.2.1".parse().unwrap()])),
+            )
+            .await,
+            Ok(false)
+        );
+    }
+
+    #[tokio::test]
+    async fn temperror() {
+        assert_eq!(
+            accepts_mails(
+                mock_resolver(Ok(&[])),
+                mock_resolver::<std::net::IpAddr>(Err("SERVFAIL")),
+            )
+            .await,
+            Err("SERVFAIL")
+        );
+        assert_eq!(
+            accepts_mails(
+                mock_resolver::<String>(Err("SERVFAIL")),
+                mock_resolver(Ok(&["192.0.2.1".parse().unwrap()])),
+            )
+            .await,
+            Err("SERVFAIL")
+        );
+    }
+}
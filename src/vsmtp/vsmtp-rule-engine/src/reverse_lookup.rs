@@ -0,0 +1,117 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use trust_dns_resolver::TokioAsyncResolver;
+use vsmtp_common::Domain;
+
+/// Resolve the names of the client from the PTR records of its address.
+///
+/// An address without PTR record, a failed lookup or a lookup not completed within `timeout`
+/// produce an empty list, a slow DNS server must not stall the session.
+pub async fn client_rdns(
+    resolver: &TokioAsyncResolver,
+    ip: std::net::IpAddr,
+    timeout: std::time::Duration,
+) -> Vec<Domain> {
+    lookup_with_timeout(
+        async {
+            resolver
+                .reverse_lookup(ip)
+                .await
+                .map(|lookup| lookup.iter().cloned().collect())
+        },
+        timeout,
+    )
+    .await
+}
+
+async fn lookup_with_timeout<E: std::fmt::Display>(
+    lookup: impl std::future::Future<Output = Result<Vec<Domain>, E>> + Send,
+    timeout: std::time::Duration,
+) -> Vec<Domain> {
+    match tokio::time::timeout(timeout, lookup).await {
+        Ok(Ok(names)) => names,
+        Ok(Err(error)) => {
+            tracing::debug!(%error, "Reverse lookup of the client failed.");
+            vec![]
+        }
+        Err(_elapsed) => {
+            tracing::warn!(?timeout, "Reverse lookup of the client timed out.");
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lookup_with_timeout;
+    use vsmtp_common::Domain;
+
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+    /// A resolver answering the PTR records `names` after `delay`.
+    async fn mock_resolver(
+        names: &[&str],
+        delay: std::time::Duration,
+    ) -> Result<Vec<Domain>, &'static str> {
+        tokio::time::sleep(delay).await;
+        if names.is_empty() {
+            Err("no record found")
+        } else {
+            Ok(names.iter().map(|name| name.parse().unwrap()).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn no_ptr() {
+        assert!(
+            lookup_with_timeout(mock_resolver(&[], std::time::Duration::ZERO), TIMEOUT)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn multiple_ptr() {
+        assert_eq!(
+            lookup_with_timeout(
+                mock_resolver(
+                    &["mail.example.com.", "mx.example.com."],
+                    std::time::Duration::ZERO
+                ),
+                TIMEOUT
+            )
+            .await,
+            vec![
+                "mail.example.com.".parse::<Domain>().unwrap(),
+                "mx.example.com.".parse().unwrap()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_resolver() {
+        let now = std::time::Instant::now();
+        assert!(lookup_with_timeout(
+            mock_resolver(&["mail.example.com."], std::time::Duration::from_secs(10)),
+            TIMEOUT
+        )
+        .await
+        .is_empty());
+        assert!(now.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -21,8 +21,11 @@ use crate::{
         directives::{Directive, Directives},
         smtp::service,
     },
+    geoip::GeoIp,
+    greylist::Greylist,
     rule_state::RuleState,
     server_api::ServerAPI,
+    srs::Srs,
     ExecutionStage, SubDomainHierarchy,
 };
 use anyhow::Context;
@@ -73,6 +76,28 @@ impl RuleEngine {
             config,
             resolvers,
             queue_manager,
+            None,
+        )
+    }
+
+    /// Build a new instance from the scripts of the configuration of this one, used to
+    /// reload the rules without restarting the server.
+    ///
+    /// The greylist store and the file lists are shared with this instance, so that
+    /// the triplets recorded before the reload are kept.
+    ///
+    /// # Errors
+    /// * failed to compile or load the scripts.
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        Self::new_inner(
+            #[cfg(not(feature = "builder"))]
+            (),
+            #[cfg(feature = "builder")]
+            either::Left(()),
+            self.server.config.clone(),
+            self.server.resolvers.clone(),
+            self.server.queue_manager.clone(),
+            Some(&self.server),
         )
     }
 
@@ -95,6 +120,7 @@ impl RuleEngine {
             config,
             resolvers,
             queue_manager,
+            None,
         )
     }
 
@@ -111,6 +137,7 @@ impl RuleEngine {
         config: std::sync::Arc<Config>,
         resolvers: std::sync::Arc<DnsResolvers>,
         queue_manager: std::sync::Arc<dyn GenericQueueManager>,
+        previous: Option<&ServerAPI>,
     ) -> anyhow::Result<Self> {
         if rhai::config::hashing::get_ahash_seed().is_none() {
             rhai::config::hashing::set_ahash_seed(Some([1, 2, 3, 4]))
@@ -129,11 +156,43 @@ impl RuleEngine {
 
         let global_modules = Self::build_global_modules(&mut engine)?;
 
+        // A single store per file, two stores would overwrite each other's triplets.
+        let greylist = match previous {
+            Some(previous) => previous.greylist.clone(),
+            None => config
+                .app
+                .greylist
+                .as_ref()
+                .map(|greylist| {
+                    Greylist::load(
+                        greylist,
+                        config.app.dirpath.join(crate::greylist::GREYLIST_FILENAME),
+                    )
+                    .map(std::sync::Arc::new)
+                })
+                .transpose()?,
+        };
+        let srs = config
+            .app
+            .srs
+            .as_ref()
+            .map(|srs| std::sync::Arc::new(Srs::new(srs)));
+        let geoip = config
+            .app
+            .geoip
+            .as_ref()
+            .map(|geoip| GeoIp::load(geoip).map(std::sync::Arc::new))
+            .transpose()?;
+
         // Modules can use the configuration on startup. (i.e. when embedded in modules)
         let server = std::sync::Arc::new(ServerAPI {
             config,
             resolvers,
             queue_manager,
+            greylist,
+            srs,
+            geoip,
+            lists: previous.map_or_else(std::sync::Arc::default, |previous| previous.lists.clone()),
         });
         engine.register_fn("srv", {
             let server_cpy = server.clone();
@@ -371,6 +430,10 @@ impl RuleEngine {
 
     /// Runs all rules from a stage using the current transaction state.
     ///
+    /// The rules are evaluated in order until one of them returns a status other than
+    /// `next`, the remaining rules of the stage are not evaluated. If this status is
+    /// final (see [`Status::is_finished`]), the rules of the next stages are skipped too.
+    ///
     /// the `server_address` parameter is used to distinguish logs from each other,
     /// printing the address & port associated with this run session, not the current
     /// context. (because the context could have been pulled from the filesystem when
@@ -14,6 +14,7 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
+use crate::{file_list::FileLists, geoip::GeoIp, greylist::Greylist, srs::Srs};
 use vqueue::GenericQueueManager;
 use vsmtp_config::{Config, DnsResolvers};
 
@@ -23,4 +24,8 @@ pub struct ServerAPI {
     pub config: std::sync::Arc<Config>,
     pub resolvers: std::sync::Arc<DnsResolvers>,
     pub queue_manager: std::sync::Arc<dyn GenericQueueManager>,
+    pub greylist: Option<std::sync::Arc<Greylist>>,
+    pub srs: Option<std::sync::Arc<Srs>>,
+    pub geoip: Option<std::sync::Arc<GeoIp>>,
+    pub lists: std::sync::Arc<FileLists>,
 }
@@ -0,0 +1,277 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use base64::{engine::general_purpose::STANDARD, Engine};
+use vsmtp_common::Address;
+use vsmtp_config::field::FieldAppSrs;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// NOTE: the timestamp is the number of days since the unix epoch, modulo 1024,
+//       encoded with two characters of base32.
+const TIMESTAMP_PERIOD: u64 = 1024;
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const HASH_LENGTH: usize = 4;
+const PREFIX: &str = "SRS0=";
+
+/// Failure to rewrite an address with the Sender Rewriting Scheme.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SrsError {
+    /// The local part does not follow `SRS0=hash=timestamp=domain=local`.
+    #[error("`{0}` is not a valid SRS0 address")]
+    Malformed(String),
+    /// The hash does not authenticate the address, it has not been produced with our secret.
+    #[error("the hash of `{0}` is invalid")]
+    InvalidHash(String),
+    /// The address is older than `app.srs.max_age`.
+    #[error("`{0}` has expired")]
+    Expired(String),
+    /// The rewritten address cannot be parsed.
+    #[error("the rewritten address `{0}` is invalid")]
+    InvalidAddress(String),
+}
+
+/// Encoding of the senders of the forwarded messages into addresses of our domain, and
+/// decoding of the bounces sent to these addresses,
+/// see <https://www.libsrs2.org/srs/srs.pdf>.
+pub struct Srs {
+    key: ring::hmac::Key,
+    max_age_days: u64,
+}
+
+impl std::fmt::Debug for Srs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Srs")
+            .field("max_age_days", &self.max_age_days)
+            .finish_non_exhaustive()
+    }
+}
+
+fn today() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() / SECONDS_PER_DAY)
+}
+
+fn encode_timestamp(days: u64) -> String {
+    let days = days % TIMESTAMP_PERIOD;
+    #[allow(clippy::indexing_slicing, clippy::cast_possible_truncation)]
+    [BASE32[(days >> 5) as usize], BASE32[(days & 31) as usize]]
+        .iter()
+        .map(|c| char::from(*c))
+        .collect()
+}
+
+fn decode_timestamp(timestamp: &str) -> Option<u64> {
+    let mut days = 0;
+    for c in timestamp.bytes() {
+        let position = BASE32
+            .iter()
+            .position(|i| *i == c.to_ascii_uppercase())?;
+        days = (days << 5) | position as u64;
+    }
+    (timestamp.len() == 2).then_some(days)
+}
+
+impl Srs {
+    /// Create a rewriter from the configuration.
+    #[must_use]
+    pub fn new(config: &FieldAppSrs) -> Self {
+        Self {
+            key: ring::hmac::Key::new(
+                ring::hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+                config.secret.as_bytes(),
+            ),
+            max_age_days: config.max_age.as_secs() / SECONDS_PER_DAY,
+        }
+    }
+
+    fn hash(&self, timestamp: &str, domain: &str, local_part: &str) -> String {
+        let mut context = ring::hmac::Context::with_key(&self.key);
+        for i in [timestamp, domain, local_part] {
+            context.update(i.to_lowercase().as_bytes());
+        }
+
+        let mut hash = STANDARD.encode(context.sign().as_ref());
+        hash.truncate(HASH_LENGTH);
+        hash
+    }
+
+    /// Rewrite `sender` into `SRS0=hash=timestamp=domain=local@alias_domain`.
+    ///
+    /// # Errors
+    ///
+    /// * the rewritten address is invalid (too long for instance)
+    pub fn forward(&self, sender: &Address, alias_domain: &str) -> Result<Address, SrsError> {
+        self.forward_at(sender, alias_domain, today())
+    }
+
+    fn forward_at(
+        &self,
+        sender: &Address,
+        alias_domain: &str,
+        days: u64,
+    ) -> Result<Address, SrsError> {
+        let (local_part, domain) = sender
+            .full()
+            .rsplit_once('@')
+            .expect("an address contains an '@'");
+        let timestamp = encode_timestamp(days);
+        let hash = self.hash(&timestamp, domain, local_part);
+
+        let rewritten = format!("{PREFIX}{hash}={timestamp}={domain}={local_part}@{alias_domain}");
+        <Address as std::str::FromStr>::from_str(&rewritten)
+            .map_err(|_| SrsError::InvalidAddress(rewritten))
+    }
+
+    /// Decode the original sender from a recipient rewritten by [`Srs::forward`],
+    /// `None` if the recipient is not a `SRS0` address.
+    ///
+    /// # Errors
+    ///
+    /// * the address is malformed
+    /// * the hash does not authenticate the address
+    /// * the address is older than `app.srs.max_age`
+    pub fn reverse(&self, recipient: &Address) -> Result<Option<Address>, SrsError> {
+        self.reverse_at(recipient, today())
+    }
+
+    fn reverse_at(&self, recipient: &Address, days: u64) -> Result<Option<Address>, SrsError> {
+        let local_part = recipient.local_part();
+        let Some(encoded) = local_part
+            .get(..PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(PREFIX))
+            .and_then(|_| local_part.get(PREFIX.len()..))
+        else {
+            return Ok(None);
+        };
+
+        let malformed = || SrsError::Malformed(recipient.to_string());
+        let mut parts = encoded.splitn(4, '=');
+        let (Some(hash), Some(timestamp), Some(domain), Some(original_local_part)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed());
+        };
+
+        if !self
+            .hash(timestamp, domain, original_local_part)
+            .eq_ignore_ascii_case(hash)
+        {
+            return Err(SrsError::InvalidHash(recipient.to_string()));
+        }
+
+        let age = (days % TIMESTAMP_PERIOD + TIMESTAMP_PERIOD
+            - decode_timestamp(timestamp).ok_or_else(malformed)?)
+            % TIMESTAMP_PERIOD;
+        if age > self.max_age_days {
+            return Err(SrsError::Expired(recipient.to_string()));
+        }
+
+        <Address as std::str::FromStr>::from_str(&format!("{original_local_part}@{domain}"))
+            .map(Some)
+            .map_err(|_| malformed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Srs, SrsError};
+    use vsmtp_common::addr;
+    use vsmtp_config::field::FieldAppSrs;
+
+    const TODAY: u64 = 19_650;
+
+    fn srs(secret: &str) -> Srs {
+        Srs::new(&FieldAppSrs {
+            secret: secret.to_owned(),
+            max_age: std::time::Duration::from_secs(21 * 24 * 60 * 60),
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let srs = srs("secret");
+        let rewritten = srs
+            .forward_at(&addr!("john.doe@example.com"), "forwarder.net", TODAY)
+            .unwrap();
+
+        assert!(rewritten.local_part().starts_with("SRS0="));
+        assert!(rewritten
+            .full()
+            .ends_with("=example.com=john.doe@forwarder.net"));
+
+        assert_eq!(
+            srs.reverse_at(&rewritten, TODAY + 2).unwrap(),
+            Some(addr!("john.doe@example.com"))
+        );
+        // NOTE: some servers change the case of the local part.
+        assert_eq!(
+            srs.reverse_at(&addr!(&rewritten.full().to_lowercase()), TODAY)
+                .unwrap(),
+            Some(addr!("john.doe@example.com"))
+        );
+    }
+
+    #[test]
+    fn not_rewritten() {
+        assert_eq!(
+            srs("secret")
+                .reverse_at(&addr!("john.doe@forwarder.net"), TODAY)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_hash() {
+        let rewritten = srs("secret")
+            .forward_at(&addr!("john.doe@example.com"), "forwarder.net", TODAY)
+            .unwrap();
+
+        assert_eq!(
+            srs("another secret").reverse_at(&rewritten, TODAY),
+            Err(SrsError::InvalidHash(rewritten.to_string()))
+        );
+
+        let tampered = addr!(&rewritten.full().replace("john.doe", "jenny.doe"));
+        assert_eq!(
+            srs("secret").reverse_at(&tampered, TODAY),
+            Err(SrsError::InvalidHash(tampered.to_string()))
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let srs = srs("secret");
+        let rewritten = srs
+            .forward_at(&addr!("john.doe@example.com"), "forwarder.net", TODAY)
+            .unwrap();
+
+        assert_eq!(
+            srs.reverse_at(&rewritten, TODAY + 22),
+            Err(SrsError::Expired(rewritten.to_string()))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let malformed = addr!("SRS0=abcd@forwarder.net");
+        assert_eq!(
+            srs("secret").reverse_at(&malformed, TODAY),
+            Err(SrsError::Malformed(malformed.to_string()))
+        );
+    }
+}
@@ -36,9 +36,9 @@ fn run_benchmark(body_size: u64, port: u16) {
                     )
                     .unwrap();
 
-                let rule_engine = std::sync::Arc::new(
+                let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
                     RuleEngine::new(config.clone(), resolvers.clone(), queue_manager).unwrap(),
-                );
+                ));
 
                 Server::new(
                     config.clone(),
@@ -59,6 +59,9 @@ fn run_benchmark(body_size: u64, port: u16) {
                         .unwrap(),
                     vec![],
                     vec![],
+                    vec![],
+                    vec![],
+                    vec![],
                 ))
                 .await
                 .unwrap();
@@ -150,7 +150,7 @@ pub async fn handle_one<Q: GenericQueueManager + Sized + 'static>(
         None => {}
     };
 
-    add_trace_information(&ctx, &mut msg, &result)?;
+    add_trace_information(&ctx, &mut msg, &result);
 
     match split_and_sort_and_send(config, &mut ctx, &msg).await {
         SenderOutcome::MoveToDead => {
@@ -21,8 +21,6 @@ use crate::{
     },
     scheduler,
 };
-use anyhow::Context;
-use time::format_description::well_known::Rfc2822;
 use tokio_stream::StreamExt;
 use vqueue::GenericQueueManager;
 use vsmtp_common::status::Status;
@@ -38,11 +36,16 @@ pub mod deliver;
 
 pub(crate) async fn start<Q: GenericQueueManager + Sized + 'static>(
     config: std::sync::Arc<Config>,
-    rule_engine: std::sync::Arc<RuleEngine>,
+    rule_engine: std::sync::Arc<arc_swap::ArcSwap<RuleEngine>>,
     queue_manager: std::sync::Arc<Q>,
     mut receiver: scheduler::Receiver,
 ) {
-    flush_deliver_queue(config.clone(), queue_manager.clone(), rule_engine.clone()).await;
+    flush_deliver_queue(
+        config.clone(),
+        queue_manager.clone(),
+        rule_engine.load_full(),
+    )
+    .await;
 
     let mut flush_deferred_interval =
         tokio::time::interval(config.server.queues.delivery.deferred_retry_period);
@@ -52,7 +55,7 @@ pub(crate) async fn start<Q: GenericQueueManager + Sized + 'static>(
             config.clone(),
             queue_manager.clone(),
             pm,
-            rule_engine.clone(),
+            rule_engine.load_full(),
         ))
     });
     tokio::pin!(delivery_receiver);
@@ -81,7 +84,7 @@ fn add_trace_information(
     ctx: &ContextFinished,
     message: &mut MessageBody,
     status: &Status,
-) -> anyhow::Result<()> {
+) {
     message.prepend_header(
         "X-VSMTP",
         &format!(
@@ -91,29 +94,11 @@ fn add_trace_information(
             status = status.as_ref()
         ),
     );
-
-    message.prepend_header(
-        "Received",
-        &format!(
-            "from {client_helo} by {server_domain} with SMTP id {message_uuid}; {date}",
-            client_helo = ctx.helo.client_name,
-            server_domain = ctx.connect.server_name,
-            message_uuid = ctx.mail_from.message_uuid,
-            date = ctx
-                .mail_from
-                .mail_timestamp
-                .format(&Rfc2822)
-                .context("failed to create Receive header timestamp")?
-        ),
-    );
-
-    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::add_trace_information;
-    use time::format_description::well_known::Rfc2822;
     use vsmtp_common::status::Status;
     use vsmtp_mail_parser::{MessageBody, RawBody};
     use vsmtp_test::config::local_ctx;
@@ -125,25 +110,14 @@ mod test {
         let mut message = MessageBody::default();
         let msg_uuid = uuid::Uuid::nil();
         ctx.mail_from.message_uuid = msg_uuid;
-        add_trace_information(&ctx, &mut message, &Status::Next).unwrap();
+        add_trace_information(&ctx, &mut message, &Status::Next);
 
         pretty_assertions::assert_eq!(
             *message.inner(),
-            RawBody::new_empty(vec![
-                [
-                    "Received: from client.testserver.com".to_string(),
-                    " by testserver.com".to_string(),
-                    " with SMTP".to_string(),
-                    " id 00000000-0000-0000-0000-000000000000; ".to_string(),
-                    ctx.mail_from.mail_timestamp.format(&Rfc2822).unwrap(),
-                    "\r\n".to_string()
-                ]
-                .concat(),
-                format!(
-                    "X-VSMTP: id=\"00000000-0000-0000-0000-000000000000\"; version=\"{ver}\"; status=\"next\"\r\n",
-                    ver = env!("CARGO_PKG_VERSION"),
-                ),
-            ])
+            RawBody::new_empty(vec![format!(
+                "X-VSMTP: id=\"00000000-0000-0000-0000-000000000000\"; version=\"{ver}\"; status=\"next\"\r\n",
+                ver = env!("CARGO_PKG_VERSION"),
+            )])
         );
     }
 }
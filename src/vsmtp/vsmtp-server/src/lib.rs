@@ -33,9 +33,11 @@ mod channel_message;
 mod runtime;
 mod server;
 mod receiver {
+    pub mod connection_limit;
     pub mod handler;
     mod post_transaction;
     pub mod pre_transaction;
+    pub mod rate_limit;
 }
 
 /// This module is responsible of the delivery of the message, and the management of failures.
@@ -44,12 +46,16 @@ pub mod delivery;
 pub mod scheduler;
 /// This module execute logics on message after taking their responsibility, and before sending them.
 pub mod working;
+/// This module export the counters and histograms of the receiver in the `Prometheus` format.
+#[cfg(feature = "metrics")]
+pub mod metrics;
 
 pub use channel_message::ProcessMessage;
 pub use receiver::handler::Handler;
 pub use receiver::pre_transaction::ValidationVSL;
-pub use runtime::start_runtime;
-pub use server::{socket_bind_anyhow, Server};
+pub use receiver::rate_limit::RateLimiter;
+pub use runtime::{reload_rules, start_runtime, watch_rules};
+pub use server::{socket_bind_anyhow, Server, Sockets};
 
 use anyhow::Context;
 use vsmtp_common::status::SmtpConnection;
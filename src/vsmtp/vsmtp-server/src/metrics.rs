@@ -0,0 +1,198 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::Reply;
+use vsmtp_protocol::ConnectionKind;
+
+struct Metrics {
+    registry: prometheus::Registry,
+    connections: prometheus::IntCounterVec,
+    open_connections: prometheus::IntGauge,
+    messages: prometheus::IntCounterVec,
+    replies: prometheus::IntCounterVec,
+    received_bytes: prometheus::IntCounter,
+    message_size: prometheus::Histogram,
+    transaction_duration: prometheus::Histogram,
+    tarpits: prometheus::IntCounter,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = prometheus::Registry::new_custom(Some("vsmtp".to_string()), None)?;
+
+        let connections = prometheus::IntCounterVec::new(
+            prometheus::Opts::new("connections_total", "Number of connections accepted."),
+            &["kind"],
+        )?;
+        let open_connections =
+            prometheus::IntGauge::new("open_connections", "Number of sessions currently served.")?;
+        let messages = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "messages_total",
+                "Number of messages received, by reply to the end of data.",
+            ),
+            &["status"],
+        )?;
+        let replies = prometheus::IntCounterVec::new(
+            prometheus::Opts::new("replies_total", "Number of replies sent to the clients."),
+            &["code"],
+        )?;
+        let received_bytes = prometheus::IntCounter::new(
+            "received_bytes_total",
+            "Number of bytes of message received.",
+        )?;
+        let message_size = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new("message_size_bytes", "Size of the messages received.")
+                // from 1 KiB to 256 MiB
+                .buckets(prometheus::exponential_buckets(1024.0, 4.0, 10)?),
+        )?;
+        let transaction_duration =
+            prometheus::Histogram::with_opts(prometheus::HistogramOpts::new(
+                "transaction_duration_seconds",
+                "Time between the `MAIL FROM` command and the end of the message.",
+            ))?;
+        let tarpits = prometheus::IntCounter::new(
+            "tarpits_total",
+            "Number of replies delayed by `tarpit()`.",
+        )?;
+
+        registry.register(Box::new(connections.clone()))?;
+        registry.register(Box::new(open_connections.clone()))?;
+        registry.register(Box::new(messages.clone()))?;
+        registry.register(Box::new(replies.clone()))?;
+        registry.register(Box::new(received_bytes.clone()))?;
+        registry.register(Box::new(message_size.clone()))?;
+        registry.register(Box::new(transaction_duration.clone()))?;
+        registry.register(Box::new(tarpits.clone()))?;
+
+        Ok(Self {
+            registry,
+            connections,
+            open_connections,
+            messages,
+            replies,
+            received_bytes,
+            message_size,
+            transaction_duration,
+            tarpits,
+        })
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref METRICS: Metrics = Metrics::new().expect("metrics are valid");
+}
+
+/// A client has been accepted on one of the interfaces.
+pub(crate) fn connection_accepted(kind: ConnectionKind) {
+    METRICS
+        .connections
+        .with_label_values(&[&kind.to_string()])
+        .inc();
+}
+
+/// A session has started, after the check of `server.client_count_max`.
+pub(crate) fn connection_opened() {
+    METRICS.open_connections.inc();
+}
+
+/// A session has ended.
+pub(crate) fn connection_closed() {
+    METRICS.open_connections.dec();
+}
+
+/// The body of a message has been received, `accepted` is false if the server
+/// answered the end of data with an error.
+pub(crate) fn message_received(
+    size: usize,
+    transaction_duration: std::time::Duration,
+    accepted: bool,
+) {
+    METRICS
+        .messages
+        .with_label_values(&[if accepted { "accepted" } else { "rejected" }])
+        .inc();
+    METRICS.received_bytes.inc_by(size as u64);
+    #[allow(clippy::cast_precision_loss)]
+    METRICS.message_size.observe(size as f64);
+    METRICS
+        .transaction_duration
+        .observe(transaction_duration.as_secs_f64());
+}
+
+/// A reply has been sent to a client, at any stage of the session.
+pub(crate) fn reply_sent(reply: &Reply) {
+    METRICS
+        .replies
+        .with_label_values(&[&reply.code().value().to_string()])
+        .inc();
+}
+
+/// The reply to a client has been delayed by `tarpit()`.
+pub(crate) fn client_tarpitted() {
+    METRICS.tarpits.inc();
+}
+
+/// Encode the current value of the metrics in the `Prometheus` text format.
+///
+/// # Errors
+///
+/// * the metrics could not be encoded
+pub fn gather() -> prometheus::Result<String> {
+    prometheus::TextEncoder::new().encode_to_string(&METRICS.registry.gather())
+}
+
+fn on_request(request: &hyper::Request<hyper::Body>) -> hyper::Response<hyper::Body> {
+    if request.method() != hyper::Method::GET || request.uri().path() != "/metrics" {
+        return hyper::Response::builder()
+            .status(hyper::StatusCode::NOT_FOUND)
+            .body(hyper::Body::empty())
+            .expect("valid response");
+    }
+
+    match gather() {
+        Ok(metrics) => hyper::Response::builder()
+            .header(hyper::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)
+            .body(hyper::Body::from(metrics))
+            .expect("valid response"),
+        Err(error) => {
+            tracing::error!(%error, "Failed to encode the metrics.");
+            hyper::Response::builder()
+                .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+                .body(hyper::Body::empty())
+                .expect("valid response")
+        }
+    }
+}
+
+/// Serve the metrics on `http://{address}/metrics`, until the runtime is stopped.
+///
+/// # Errors
+///
+/// * the address could not be bound
+/// * the HTTP server failed
+pub async fn serve(address: std::net::SocketAddr) -> anyhow::Result<()> {
+    let make_service = hyper::service::make_service_fn(|_| async {
+        Ok::<_, std::convert::Infallible>(hyper::service::service_fn(|request| async move {
+            Ok::<_, std::convert::Infallible>(on_request(&request))
+        }))
+    });
+
+    let server = hyper::Server::try_bind(&address)?.serve(make_service);
+    tracing::info!(%address, "Serving the metrics.");
+
+    server.await.map_err(anyhow::Error::new)
+}
@@ -0,0 +1,162 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::pre_transaction::canonical_ip;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+// NOTE: the addresses are spread over several maps so that the accept loop
+//       and the sessions closing do not all contend on the same lock.
+const SHARD_COUNT: usize = 16;
+
+// NOTE: a single IPv6 client usually owns a whole /64 network.
+const IPV6_PREFIX_LEN: u32 = 64;
+
+/// The address the sessions of `client` are counted for: the IPv4 address of an
+/// IPv4-mapped address, and the /64 network of an IPv6 address.
+fn client_key(client: std::net::IpAddr) -> std::net::IpAddr {
+    match canonical_ip(client) {
+        std::net::IpAddr::V6(v6) => std::net::IpAddr::V6(std::net::Ipv6Addr::from(
+            u128::from(v6) & (u128::MAX << (128 - IPV6_PREFIX_LEN)),
+        )),
+        v4 @ std::net::IpAddr::V4(_) => v4,
+    }
+}
+
+type Shard = std::sync::Mutex<std::collections::HashMap<std::net::IpAddr, usize>>;
+
+/// Number of sessions opened by each client address,
+/// shared by all the connections of the server.
+pub struct ConnectionLimiter {
+    max_per_ip: usize,
+    hasher: std::collections::hash_map::RandomState,
+    shards: Vec<Shard>,
+}
+
+/// A session of a client address, released when dropped.
+pub struct ConnectionSlot {
+    limiter: std::sync::Arc<ConnectionLimiter>,
+    /// The key of the client, see [`client_key`].
+    client: std::net::IpAddr,
+}
+
+impl ConnectionLimiter {
+    /// Create an instance allowing `max_per_ip` sessions for each client address.
+    #[must_use]
+    pub fn new(max_per_ip: usize) -> Self {
+        Self {
+            max_per_ip,
+            hasher: std::collections::hash_map::RandomState::new(),
+            shards: (0..SHARD_COUNT).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    fn shard(&self, client: std::net::IpAddr) -> &Shard {
+        let mut hasher = self.hasher.build_hasher();
+        client.hash(&mut hasher);
+        #[allow(clippy::cast_possible_truncation)]
+        &self.shards[hasher.finish() as usize % SHARD_COUNT]
+    }
+
+    /// Take a slot for a session of the client, `None` if the client already uses all of its slots.
+    ///
+    /// # Panics
+    ///
+    /// * the shard has been poisoned
+    #[must_use]
+    pub fn acquire(
+        self: &std::sync::Arc<Self>,
+        client: std::net::IpAddr,
+    ) -> Option<ConnectionSlot> {
+        let client = client_key(client);
+        let mut shard = self.shard(client).lock().unwrap();
+        let count = shard.entry(client).or_insert(0);
+        if *count >= self.max_per_ip {
+            return None;
+        }
+        *count += 1;
+
+        Some(ConnectionSlot {
+            limiter: self.clone(),
+            client,
+        })
+    }
+
+    #[cfg(test)]
+    fn count(&self, client: std::net::IpAddr) -> usize {
+        let client = client_key(client);
+        self.shard(client)
+            .lock()
+            .unwrap()
+            .get(&client)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut shard = self.limiter.shard(self.client).lock().unwrap();
+        if let std::collections::hash_map::Entry::Occupied(mut count) = shard.entry(self.client) {
+            *count.get_mut() -= 1;
+            // NOTE: the addresses without session are removed to keep the maps small.
+            if *count.get() == 0 {
+                count.remove();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectionLimiter;
+
+    #[test]
+    fn keyed_by_client() {
+        let limiter = std::sync::Arc::new(ConnectionLimiter::new(2));
+        let client = "192.0.2.1".parse().unwrap();
+
+        let slots = (0..2)
+            .map(|_| limiter.acquire(client).unwrap())
+            .collect::<Vec<_>>();
+        assert!(limiter.acquire(client).is_none());
+        assert!(limiter.acquire("192.0.2.2".parse().unwrap()).is_some());
+
+        drop(slots);
+        assert_eq!(limiter.count(client), 0);
+        assert!(limiter.acquire(client).is_some());
+    }
+
+    #[test]
+    fn keyed_by_network() {
+        let limiter = std::sync::Arc::new(ConnectionLimiter::new(1));
+
+        let v4 = limiter.acquire("192.0.2.1".parse().unwrap()).unwrap();
+        assert!(limiter
+            .acquire("::ffff:192.0.2.1".parse().unwrap())
+            .is_none());
+        drop(v4);
+
+        let v6 = limiter.acquire("2001:db8:0:1::1".parse().unwrap()).unwrap();
+        assert!(limiter
+            .acquire("2001:db8:0:1:ffff::2".parse().unwrap())
+            .is_none());
+        assert!(limiter
+            .acquire("2001:db8:0:2::1".parse().unwrap())
+            .is_some());
+        drop(v6);
+        assert_eq!(limiter.count("2001:db8:0:1::3".parse().unwrap()), 0);
+    }
+}
@@ -14,17 +14,21 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use crate::scheduler;
+use super::{connection_limit::ConnectionSlot, post_transaction::recipient_error};
+use crate::{scheduler, RateLimiter};
 
 use tokio_rustls::rustls;
 use vqueue::GenericQueueManager;
-use vsmtp_common::{status::Status, Address, ContextFinished, Reply, Stage, TransactionType};
-use vsmtp_config::Config;
+use vsmtp_common::{
+    status::Status, Address, ContextFinished, Domain, RecipientDsn, RejectionReason, Reply, Stage,
+    TransactionType,
+};
+use vsmtp_config::{field::FieldServerInterfacesListener, Config};
 use vsmtp_delivery::Deliver;
 use vsmtp_mail_parser::{MailParser, MessageBody};
 use vsmtp_protocol::{
-    AuthArgs, AuthError, CallbackWrap, EhloArgs, Error, HeloArgs, MailFromArgs, RcptToArgs,
-    ReceiverContext,
+    AuthArgs, AuthError, CallbackWrap, EhloArgs, Error, ExpnArgs, HeloArgs, HelpArgs, MailFromArgs,
+    RateLimit, RcptToArgs, ReceiverContext, VrfyArgs, XClientArgs,
 };
 use vsmtp_rule_engine::{ExecutionStage, RuleEngine, RuleState};
 
@@ -52,6 +56,35 @@ where
     pub(super) message_parser_factory: ParserFactory,
 
     pub(super) emitter: std::sync::Arc<scheduler::Emitter>,
+
+    /// Is the client allowed to forward the identity of its own clients with `XCLIENT`
+    /// and the `AUTH` argument of `MAIL FROM`?
+    pub(super) xclient_trusted: bool,
+    /// Throttling of the recipients, shared by all the connections.
+    pub(super) rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+    /// Policy of the listener of `server.interfaces.listeners` which accepted the connection.
+    pub(super) listener: Option<FieldServerInterfacesListener>,
+    /// Names of the client resolved from its address, looked up once per connection
+    /// and shared by all its transactions.
+    pub(super) client_rdns: std::sync::Arc<tokio::sync::OnceCell<Vec<Domain>>>,
+    /// Session of the client counted by `server.client_count_max_per_ip`, released with the handler.
+    pub(super) connection_slot: Option<ConnectionSlot>,
+}
+
+/// Make the receiver wait before the reply, for the delay requested by `tarpit()` in the rules.
+fn tarpit(state: &RuleState, config: &Config, ctx: &mut ReceiverContext) {
+    let requested = state
+        .context()
+        .write()
+        .expect("state poisoned")
+        .take_tarpit();
+    if let Some(delay) = requested {
+        let delay = delay.min(config.server.smtp.tarpit_max);
+        tracing::info!(?delay, "Tarpitting the client.");
+        #[cfg(feature = "metrics")]
+        crate::metrics::client_tarpitted();
+        ctx.tarpit(delay);
+    }
 }
 
 #[async_trait::async_trait]
@@ -105,18 +138,100 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
         self.on_ehlo_inner(ctx, args)
     }
 
+    async fn on_xclient(&mut self, ctx: &mut ReceiverContext, args: XClientArgs) -> Reply {
+        self.on_xclient_inner(ctx, args)
+    }
+
+    async fn on_help(&mut self, _: &mut ReceiverContext, args: HelpArgs) -> Reply {
+        self.on_help_inner(args)
+    }
+
+    async fn on_vrfy(&mut self, _: &mut ReceiverContext, args: VrfyArgs) -> Reply {
+        self.on_vrfy_inner("VRFY user", &args.query)
+    }
+
+    async fn on_expn(&mut self, _: &mut ReceiverContext, args: ExpnArgs) -> Reply {
+        self.on_vrfy_inner("EXPN list", &args.list)
+    }
+
+    async fn on_rate_limit(
+        &mut self,
+        client_addr: std::net::SocketAddr,
+        reverse_path: Option<&Address>,
+    ) -> RateLimit {
+        match &self.rate_limiter {
+            Some(rate_limiter) if !rate_limiter.acquire(client_addr.ip()) => {
+                tracing::warn!(
+                    %client_addr,
+                    reverse_path = ?reverse_path.map(ToString::to_string),
+                    throttled = rate_limiter.throttled(),
+                    "Recipient deferred, the client has exceeded the rate limit."
+                );
+                RateLimit::Defer(self.config.server.smtp.reply(RejectionReason::RateLimited))
+            }
+            _ => RateLimit::Allow,
+        }
+    }
+
     async fn on_mail_from(&mut self, ctx: &mut ReceiverContext, args: MailFromArgs) -> Reply {
-        self.state
-            .context()
-            .write()
-            .expect("state poisoned")
-            .to_mail_from(args.reverse_path, args.use_smtputf8)
-            .expect("bad state");
+        // The client can declare the size of the message with the SIZE extension,
+        // see <https://datatracker.ietf.org/doc/html/rfc1870#section-6>
+        if let Some(size) = args.size {
+            let size_max = self.config.server.esmtp.size;
+            if (size_max != 0 && size > size_max) || size >= ctx.message_size_max() {
+                return self
+                    .config
+                    .server
+                    .smtp
+                    .reply(RejectionReason::MessageTooBig);
+            }
+        }
 
-        match self
-            .rule_engine
-            .run_when(&self.state, &mut self.skipped, ExecutionStage::MailFrom)
+        // REQUIRETLS is only advertised on secured sessions,
+        // see <https://datatracker.ietf.org/doc/html/rfc8689#section-4.1>
+        if args.requiretls
+            && !self
+                .state
+                .context()
+                .read()
+                .expect("state poisoned")
+                .is_secured()
         {
+            return self.config.server.smtp.reply(RejectionReason::RequireTls);
+        }
+
+        if let Some(reason) = self.listener_rejection() {
+            return self.config.server.smtp.reply(reason);
+        }
+
+        {
+            let ctx = self.state.context();
+            let mut ctx = ctx.write().expect("state poisoned");
+            ctx.to_mail_from(args.reverse_path, args.use_smtputf8, args.requiretls)
+                .expect("bad state");
+            ctx.set_mail_from_dsn(args.envelop_id, args.ret)
+                .expect("bad state");
+
+            // The identity asserted by an untrusted client is replaced by `AUTH=<>`,
+            // see <https://datatracker.ietf.org/doc/html/rfc4954#section-5>
+            let asserted_sender = args
+                .auth
+                .filter(|_| self.xclient_trusted || ctx.is_authenticated());
+            ctx.set_asserted_sender(asserted_sender).expect("bad state");
+
+            // NOTE: recorded before running the rules, so that their logs carry it.
+            tracing::Span::current().record(
+                "transaction_id",
+                tracing::field::display(ctx.transaction_id().expect("bad state")),
+            );
+        }
+
+        let status =
+            self.rule_engine
+                .run_when(&self.state, &mut self.skipped, ExecutionStage::MailFrom);
+        tarpit(&self.state, &self.config, ctx);
+
+        match status {
             Status::Faccept(reply) | Status::Accept(reply) => reply,
             Status::Quarantine(_) | Status::Next | Status::DelegationResult => {
                 "250 Ok\r\n".parse::<Reply>().unwrap()
@@ -138,14 +253,21 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
             let context = locked_context.read().expect("state poisoned");
             if context.forward_paths().map_or(0, Vec::len) >= self.config.server.smtp.rcpt_count_max
             {
-                return "452 Requested action not taken: too many recipients\r\n"
-                    .parse::<Reply>()
-                    .unwrap();
+                return self
+                    .config
+                    .server
+                    .smtp
+                    .reply(RejectionReason::TooManyRecipients);
             } else if !context.is_utf8_advertised() && !args.forward_path.full().is_ascii() {
                 return "553 mailbox name not allowed\r\n".parse::<Reply>().unwrap();
             }
         }
 
+        let dsn = RecipientDsn {
+            notify_on: args.notify_on,
+            original_forward_path: args.original_forward_path,
+        };
+
         let is_internal = {
             let ctx = self.state.context();
             let mut ctx = ctx.write().expect("state poisoned");
@@ -196,13 +318,16 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
                     let mut internal_guard = internal_ctx.write().expect("state poisoned");
                     internal_guard
                         .add_forward_path(
-                            args.forward_path,
+                            args.forward_path.clone(),
                             std::sync::Arc::new(Deliver::new(
                                 self.rule_engine.srv().resolvers.get_resolver_root(),
                                 self.config.clone(),
                             )),
                         )
                         .expect("bad state");
+                    internal_guard
+                        .set_recipient_dsn(args.forward_path, dsn)
+                        .expect("bad state");
                     internal_guard
                         .set_transaction_type(TransactionType::Internal)
                         .expect("bad state");
@@ -222,13 +347,15 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
                     );
 
                     ctx.add_forward_path(
-                        args.forward_path,
+                        args.forward_path.clone(),
                         std::sync::Arc::new(Deliver::new(
                             self.rule_engine.srv().resolvers.get_resolver_root(),
                             self.config.clone(),
                         )),
                     )
                     .expect("bad state");
+                    ctx.set_recipient_dsn(args.forward_path, dsn)
+                        .expect("bad state");
                     ctx.set_transaction_type(reverse_path.as_ref().map_or(
                         TransactionType::Incoming(None),
                         |reverse_path| TransactionType::Outgoing {
@@ -255,13 +382,15 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
                     ))
                     .expect("bad state");
                     ctx.add_forward_path(
-                        args.forward_path,
+                        args.forward_path.clone(),
                         std::sync::Arc::new(Deliver::new(
                             self.rule_engine.srv().resolvers.get_resolver_root(),
                             self.config.clone(),
                         )),
                     )
                     .expect("bad state");
+                    ctx.set_recipient_dsn(args.forward_path, dsn)
+                        .expect("bad state");
 
                     false
                 }
@@ -273,10 +402,12 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
             _ => &mut self.state,
         };
 
-        match self
+        let status = self
             .rule_engine
-            .run_when(state, &mut self.skipped, ExecutionStage::RcptTo)
-        {
+            .run_when(state, &mut self.skipped, ExecutionStage::RcptTo);
+        tarpit(state, &self.config, ctx);
+
+        match status {
             Status::Faccept(reply) | Status::Accept(reply) | Status::Reject(reply) => reply,
             Status::Quarantine(_) | Status::Next | Status::DelegationResult => {
                 "250 Ok\r\n".parse::<Reply>().unwrap()
@@ -290,15 +421,18 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
     }
 
     async fn on_rset(&mut self) -> Reply {
+        // NOTE: only the transaction is reset, the connection (TLS, authentication)
+        // and the HELO/EHLO of the client are kept.
         self.state
             .context()
             .write()
             .expect("state poisoned")
             .reset();
 
-        self.state_internal = None;
+        // The headers added by the rules before the DATA command belong to the aborted transaction.
+        *self.state.message().write().expect("message poisoned") = MessageBody::default();
 
-        // TODO: reset message?
+        self.state_internal = None;
 
         "250 Ok\r\n".parse::<Reply>().unwrap()
     }
@@ -312,14 +446,30 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
     }
 
     async fn on_message_completed(&mut self, item: Self::Item) -> Option<Reply> {
-        let (ctx, msg) = item;
-        self.on_message_completed_inner(ctx, msg).await
+        let (mut ctx, msg) = item;
+        self.on_message_completed_inner(&mut ctx, msg).await
+    }
+
+    async fn on_message_completed_lmtp(&mut self, item: Self::Item) -> Vec<(Address, Reply)> {
+        let (mut ctx, msg) = item;
+        let error = self.on_message_completed_inner(&mut ctx, msg).await;
+
+        ctx.rcpt_to
+            .forward_paths
+            .iter()
+            .filter_map(|rcpt| {
+                error
+                    .clone()
+                    .or_else(|| recipient_error(&ctx, rcpt))
+                    .map(|reply| (rcpt.clone(), reply))
+            })
+            .collect()
     }
 
     async fn on_hard_error(&mut self, ctx: &mut ReceiverContext, reply: Reply) -> Reply {
         ctx.deny();
         reply.extended(
-            &"451 Too many errors from the client\r\n"
+            &"421 Too many errors from the client\r\n"
                 .parse::<Reply>()
                 .unwrap(),
         )
@@ -330,6 +480,17 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
         reply
     }
 
+    #[cfg(feature = "metrics")]
+    fn on_reply(&mut self, reply: &Reply) {
+        crate::metrics::reply_sent(reply);
+    }
+
+    async fn on_early_talker(&mut self) -> Option<Reply> {
+        let greeting_delay = self.config.server.smtp.greeting_delay.as_ref()?;
+        tracing::warn!("Client sent data before the banner.");
+        greeting_delay.reject.then(|| greeting_delay.reply.clone())
+    }
+
     fn get_stage(&self) -> Stage {
         self.state
             .context()
@@ -337,4 +498,33 @@ impl<Parser: MailParser + Send + Sync, ParserFactory: Fn() -> Parser + Send + Sy
             .expect("state poisoned")
             .stage()
     }
+
+    fn get_message_uuid(&self) -> Option<uuid::Uuid> {
+        self.state
+            .context()
+            .read()
+            .expect("state poisoned")
+            .message_uuid()
+            .ok()
+            .copied()
+    }
+
+    fn get_transaction_id(&self) -> Option<uuid::Uuid> {
+        self.state
+            .context()
+            .read()
+            .expect("state poisoned")
+            .transaction_id()
+            .ok()
+            .copied()
+    }
+
+    fn get_ages(&self) -> (Option<std::time::Duration>, Option<std::time::Duration>) {
+        let context = self.state.context();
+        let context = context.read().expect("state poisoned");
+        (
+            Some(context.connection_age()),
+            context.transaction_age().ok(),
+        )
+    }
 }
@@ -16,17 +16,97 @@
 */
 
 use crate::{Handler, ProcessMessage};
+use anyhow::Context;
 use futures_util::TryStreamExt;
+use time::format_description::well_known::Rfc2822;
 use vqueue::QueueID;
 use vsmtp_common::{
+    auth::Credentials,
     status::{self, Status},
     transfer::{self, error::Rule},
-    ContextFinished, Reply,
+    Address, ClientName, ContextFinished, RejectionReason, Reply,
 };
 use vsmtp_mail_parser::{Mail, MailParser, MessageBody, ParserError, RawBody};
-use vsmtp_protocol::{Error, ParseArgsError, ReceiverContext};
+use vsmtp_protocol::{Error, ErrorKind, ParseArgsError, ReceiverContext};
 use vsmtp_rule_engine::{ExecutionStage, RuleEngine, RuleState};
 
+/// Build the value of the `Received` header (rfc 5321 section 4.4) stamped on the accepted messages.
+///
+/// The `with` clause is `ESMTP`, followed by `S` if the session is secured with TLS and by `A`
+/// if the client is authenticated (rfc 3848), or `SMTP` for a client greeted with `HELO`.
+/// `client_rdns` is the name resolved from the client address, if any.
+pub(super) fn received_header(
+    ctx: &ContextFinished,
+    client_rdns: Option<&str>,
+) -> anyhow::Result<String> {
+    let client_ip = match ctx.connect.client_addr.ip() {
+        std::net::IpAddr::V4(ip) => ClientName::Ip4(ip),
+        std::net::IpAddr::V6(ip) => ClientName::Ip6(ip),
+    };
+    let auth = ctx.connect.auth.as_ref().filter(|auth| auth.authenticated);
+    let authid = auth
+        .and_then(|auth| auth.credentials.as_ref())
+        .and_then(Credentials::authid);
+
+    let protocol = match (ctx.connect.tls.is_some(), auth.is_some()) {
+        (false, false) if ctx.helo.using_deprecated => "SMTP",
+        (false, false) => "ESMTP",
+        (true, false) => "ESMTPS",
+        (false, true) => "ESMTPA",
+        (true, true) => "ESMTPSA",
+    };
+
+    let mut received = format!(
+        "from {client_helo} ({client_rdns}{client_ip}) by {server_name} with {protocol}",
+        client_helo = ctx.helo.client_name,
+        client_rdns = client_rdns
+            .map(|rdns| format!("{rdns} "))
+            .unwrap_or_default(),
+        server_name = ctx.connect.server_name,
+    );
+    if let Some(tls) = &ctx.connect.tls {
+        received.push_str(&format!(
+            " (version={} cipher={})",
+            tls.protocol_version, tls.cipher_suite
+        ));
+    }
+    if let Some(authid) = authid {
+        received.push_str(&format!(" (authenticated as {authid})"));
+    }
+    received.push_str(&format!(
+        " id {message_uuid}; {date}",
+        message_uuid = ctx.mail_from.message_uuid,
+        date = ctx
+            .mail_from
+            .mail_timestamp
+            .format(&Rfc2822)
+            .context("failed to create Received header timestamp")?
+    ));
+
+    Ok(received)
+}
+
+/// The reply of a recipient of an LMTP transaction (rfc 2033 section 4.2), if the message
+/// will not be delivered to it, the others receive the reply of the message.
+pub(super) fn recipient_error(ctx: &ContextFinished, rcpt: &Address) -> Option<Reply> {
+    let failed = ctx
+        .rcpt_to
+        .delivery
+        .values()
+        .flatten()
+        .any(|(addr, status)| addr == rcpt && matches!(status, transfer::Status::Failed { .. }));
+    if !failed {
+        return None;
+    }
+
+    Some(match &ctx.connect.skipped {
+        Some(status::Status::Deny(code)) => code.clone(),
+        _ => "554 permanent problems with the remote server\r\n"
+            .parse::<Reply>()
+            .unwrap(),
+    })
+}
+
 impl<Parser, ParserFactory> Handler<Parser, ParserFactory>
 where
     Parser: MailParser + Send + Sync,
@@ -79,8 +159,8 @@ where
     // TODO: enhance error handling
     pub(super) async fn on_message_completed_inner(
         &self,
-        mut ctx: ContextFinished,
-        msg: MessageBody,
+        ctx: &mut ContextFinished,
+        mut msg: MessageBody,
     ) -> Option<Reply> {
         let (mut message_uuid, skipped) = (ctx.mail_from.message_uuid, ctx.connect.skipped.clone());
 
@@ -88,10 +168,26 @@ where
             .parse::<Reply>()
             .unwrap();
 
+        if ctx.connect.client_rdns.is_none() {
+            // NOTE: the lookup has been started when the connection was accepted.
+            let resolver = self.rule_engine.srv().resolvers.get_resolver_root();
+            let client_rdns = self
+                .client_rdns
+                .get_or_init(|| {
+                    vsmtp_rule_engine::client_rdns(
+                        &resolver,
+                        ctx.connect.client_addr.ip(),
+                        self.config.server.smtp.rdns_timeout,
+                    )
+                })
+                .await;
+            ctx.connect.client_rdns = Some(client_rdns.clone());
+        }
+
         let (queue, should_skip_working, delegated) = match &skipped {
             Some(status @ status::Status::Quarantine(path)) => {
                 let quarantine = QueueID::Quarantine { name: path.into() };
-                match self.queue_manager.write_ctx(&quarantine, &ctx).await {
+                match self.queue_manager.write_ctx(&quarantine, ctx).await {
                     Ok(()) => (),
                     Err(_e) => return Some(denied),
                 };
@@ -136,13 +232,24 @@ where
             }
         };
 
+        let client_rdns = ctx
+            .connect
+            .client_rdns
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.to_string().trim_end_matches('.').to_string());
+        match received_header(ctx, client_rdns.as_deref()) {
+            Ok(received) => msg.prepend_header("Received", &received),
+            Err(_e) => return Some(denied),
+        }
+
         match self.queue_manager.write_msg(&message_uuid, &msg).await {
             Ok(()) => (),
             Err(_e) => return Some(denied),
         };
 
         if let Some(queue) = queue {
-            match self.queue_manager.write_ctx(&queue, &ctx).await {
+            match self.queue_manager.write_ctx(&queue, ctx).await {
                 Ok(()) => (),
                 Err(_e) => {
                     return Some(denied);
@@ -169,13 +276,18 @@ where
     }
 
     fn convert_error(e: Error) -> ParserError {
-        if e.get_ref().is_some() {
+        if matches!(e.kind(), ErrorKind::ConnectionAborted) {
+            ParserError::Io(std::io::Error::new(e.kind().to_std(), e.to_string()))
+        } else if e.get_ref().is_some() {
             match e.into_inner().unwrap().downcast::<std::io::Error>() {
                 Ok(io) => ParserError::Io(*io),
                 Err(otherwise) => match otherwise.downcast::<ParseArgsError>().map(|i| *i) {
                     Ok(ParseArgsError::BufferTooLong { expected, got }) => {
                         ParserError::BufferTooLong { expected, got }
                     }
+                    Ok(ParseArgsError::LineTooLong { expected, got }) => {
+                        ParserError::LineTooLong { expected, got }
+                    }
                     Ok(otherwise) => ParserError::InvalidMail(otherwise.to_string()),
                     Err(otherwise) => ParserError::InvalidMail(otherwise.to_string()),
                 },
@@ -211,6 +323,14 @@ where
                         .unwrap(),
                 )
             }
+            Err(ParserError::LineTooLong { .. }) => {
+                return Err("500 Line too long\r\n".parse::<Reply>().unwrap());
+            }
+            // NOTE: the message has been aborted by the pre-scan,
+            // the client receives the reply it produced instead of this one.
+            Err(ParserError::Io(e)) if e.kind() == std::io::ErrorKind::ConnectionAborted => {
+                return Err("554 5.6.0 Message aborted\r\n".parse::<Reply>().unwrap());
+            }
 
             Err(otherwise) => todo!("handle error cleanly {:?}", otherwise),
         };
@@ -219,15 +339,84 @@ where
         Ok(mail)
     }
 
-    #[allow(clippy::too_many_lines)]
+    /// Reject the message in a loop, before the rules are run (rfc 5321 section 6.3).
+    fn check_received_count(
+        &self,
+        mail: either::Either<RawBody, Mail>,
+    ) -> Result<either::Either<RawBody, Mail>, Reply> {
+        let Some(received_count_max) = self.config.server.smtp.received_count_max else {
+            return Ok(mail);
+        };
+
+        let received_count = mail.as_ref().either(
+            |raw| raw.count_header("Received"),
+            |parsed| parsed.count_header("Received"),
+        );
+        if received_count > received_count_max {
+            tracing::warn!(
+                received_count,
+                received_count_max,
+                "Too many hops, message rejected."
+            );
+            return Err(self.config.server.smtp.reply(RejectionReason::TooManyHops));
+        }
+
+        Ok(mail)
+    }
+
     pub(super) async fn on_message_inner(
         &mut self,
         ctx: &mut ReceiverContext,
         stream: impl tokio_stream::Stream<Item = Result<Vec<u8>, Error>> + Send + Unpin,
     ) -> (Reply, Option<Vec<(ContextFinished, MessageBody)>>) {
-        let mail = match self.get_message_body(stream).await {
+        #[cfg(feature = "metrics")]
+        let (transaction_start, mut size) = (
+            self.state
+                .context()
+                .read()
+                .expect("state poisoned")
+                .mail_timestamp()
+                .map_or_else(|_| time::OffsetDateTime::now_utc(), |timestamp| *timestamp),
+            0,
+        );
+        #[cfg(feature = "metrics")]
+        let stream = stream.inspect_ok(|line| size += line.len());
+
+        let (reply, messages) = self.on_message_body(ctx, stream).await;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::message_received(
+            size,
+            std::time::Duration::try_from(time::OffsetDateTime::now_utc() - transaction_start)
+                .unwrap_or_default(),
+            !reply.code().is_error(),
+        );
+
+        (reply, messages)
+    }
+
+    #[allow(clippy::too_many_lines)]
+    async fn on_message_body(
+        &mut self,
+        ctx: &mut ReceiverContext,
+        stream: impl tokio_stream::Stream<Item = Result<Vec<u8>, Error>> + Send + Unpin,
+    ) -> (Reply, Option<Vec<(ContextFinished, MessageBody)>>) {
+        let mail = match self
+            .get_message_body(stream)
+            .await
+            .and_then(|mail| self.check_received_count(mail))
+        {
             Ok(mail) => mail,
-            Err(reply) => return (reply, None),
+            Err(reply) => {
+                // The transaction is aborted, the client can start a new one.
+                self.state
+                    .context()
+                    .write()
+                    .expect("state poisoned")
+                    .reset();
+                self.state_internal = None;
+                return (reply, None);
+            }
         };
 
         let internal_reply = if let Some(state_internal) = &self.state_internal {
@@ -293,16 +482,24 @@ where
                 .unwrap_finished()
                 .expect("has been set to finished");
 
-            self.state
-                .context()
-                .write()
-                .expect("state poisoned")
-                .to_helo(
+            {
+                let ctx = self.state.context();
+                let mut ctx = ctx.write().expect("state poisoned");
+                ctx.to_helo(
                     mail_ctx.helo.client_name.clone(),
                     mail_ctx.helo.using_deprecated,
                 )
                 .expect("bad state");
 
+                // The names resolved by the rules or in the background are kept for the next transactions.
+                if let Some(client_rdns) = &mail_ctx.connect.client_rdns {
+                    let _already_set = self.client_rdns.set(client_rdns.clone());
+                }
+                if let Some(client_rdns) = self.client_rdns.get() {
+                    ctx.set_client_rdns(client_rdns.clone());
+                }
+            }
+
             if mail_ctx.rcpt_to.delivery.is_empty() {
                 None
             } else {
@@ -335,3 +532,115 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{received_header, recipient_error};
+    use time::format_description::well_known::Rfc2822;
+    use tokio_rustls::rustls;
+    use vsmtp_common::{
+        auth::Credentials,
+        status::Status,
+        transfer::{self, error::Rule},
+        transport::WrapperSerde,
+        AuthProperties, CipherSuite, ClientName, ProtocolVersion, Reply, TlsProperties,
+    };
+    use vsmtp_test::config::local_ctx;
+
+    #[test]
+    fn received_authenticated_tls() {
+        let mut ctx = local_ctx();
+        ctx.mail_from.message_uuid = uuid::Uuid::nil();
+        ctx.connect.tls = Some(TlsProperties {
+            protocol_version: ProtocolVersion(rustls::ProtocolVersion::TLSv1_3),
+            cipher_suite: CipherSuite(rustls::CipherSuite::TLS13_AES_256_GCM_SHA384),
+            peer_certificates: None,
+            alpn_protocol: None,
+            sni: None,
+            client_certificate: None,
+        });
+        ctx.connect.auth = Some(AuthProperties {
+            authenticated: true,
+            cancel_count: 0,
+            credentials: Some(Credentials::Verify {
+                authid: "john".to_string(),
+                authpass: "doe".to_string(),
+            }),
+        });
+
+        pretty_assertions::assert_eq!(
+            received_header(&ctx, Some("client.testserver.com")).unwrap(),
+            [
+                "from client.testserver.com (client.testserver.com [127.0.0.1])",
+                " by testserver.com with ESMTPSA",
+                " (version=TLSv1_3 cipher=TLS_AES_256_GCM_SHA384)",
+                " (authenticated as john)",
+                " id 00000000-0000-0000-0000-000000000000; ",
+                &ctx.mail_from.mail_timestamp.format(&Rfc2822).unwrap(),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn received_protocol() {
+        for (secured, authenticated, using_deprecated, expected) in [
+            (false, false, true, "SMTP"),
+            (false, false, false, "ESMTP"),
+            (true, false, false, "ESMTPS"),
+            (false, true, false, "ESMTPA"),
+        ] {
+            let mut ctx = local_ctx();
+            ctx.helo.using_deprecated = using_deprecated;
+            ctx.connect.tls = secured.then_some(TlsProperties {
+                protocol_version: ProtocolVersion(rustls::ProtocolVersion::TLSv1_2),
+                cipher_suite: CipherSuite(rustls::CipherSuite::TLS13_AES_128_GCM_SHA256),
+                peer_certificates: None,
+                alpn_protocol: None,
+                sni: None,
+                client_certificate: None,
+            });
+            ctx.connect.auth = Some(AuthProperties {
+                authenticated,
+                cancel_count: 0,
+                credentials: None,
+            });
+
+            let received = received_header(&ctx, None).unwrap();
+            assert!(
+                received.starts_with(&format!(
+                    "from client.testserver.com ([127.0.0.1]) by testserver.com with {expected} "
+                )),
+                "{received}"
+            );
+        }
+    }
+
+    #[test]
+    fn received_address_literal() {
+        let mut ctx = local_ctx();
+        ctx.helo.client_name = ClientName::Ip6("2001:db8::1".parse().unwrap());
+
+        assert!(received_header(&ctx, None)
+            .unwrap()
+            .starts_with("from [IPv6:2001:db8::1] ([127.0.0.1]) by testserver.com with ESMTP "));
+    }
+
+    #[test]
+    fn recipient_denied() {
+        let mut ctx = local_ctx();
+        let rcpt = ctx.rcpt_to.forward_paths[0].clone();
+        assert_eq!(recipient_error(&ctx, &rcpt), None);
+
+        let code = "554 5.7.1 denied\r\n".parse::<Reply>().unwrap();
+        ctx.connect.skipped = Some(Status::Deny(code.clone()));
+        ctx.rcpt_to.delivery.insert(
+            WrapperSerde::Raw("deliver".to_owned()),
+            vec![(
+                rcpt.clone(),
+                transfer::Status::failed(Rule::Denied(code.clone())),
+            )],
+        );
+        assert_eq!(recipient_error(&ctx, &rcpt), Some(code));
+    }
+}
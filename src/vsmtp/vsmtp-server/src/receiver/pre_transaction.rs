@@ -15,23 +15,201 @@
  *
 */
 
-use crate::{scheduler::Emitter, Handler};
+use super::connection_limit::ConnectionSlot;
+use crate::{scheduler::Emitter, Handler, RateLimiter};
 use tokio_rustls::rustls;
 use vqueue::GenericQueueManager;
 use vsmtp_common::{
     auth::{Credentials, Mechanism},
     status::Status,
-    ClientName, Reply,
+    AuthProperties, ClientCertificate, Domain, HeloProperties, RejectionReason, Reply,
+};
+use vsmtp_config::{
+    field::{FieldServerInterfacesListener, FieldServerSMTPVrfy, ListenerTls},
+    Config,
 };
-use vsmtp_config::Config;
 use vsmtp_mail_parser::MailParser;
 use vsmtp_protocol::{
-    AcceptArgs, AuthArgs, AuthError, CallbackWrap, ConnectionKind, EhloArgs, HeloArgs,
-    ReceiverContext,
+    AcceptArgs, AuthArgs, AuthError, CallbackWrap, ConnectionKind, EhloArgs, HeloArgs, HelpArgs,
+    ReceiverContext, XClientArgs, XClientProto,
 };
 use vsmtp_rule_engine::{ExecutionStage, RuleEngine, RuleState};
 
-fn build_ehlo_reply(config: &vsmtp_config::Config, is_transaction_secured: bool) -> Reply {
+/// Start the reverse lookup of the client in the background, its names are then most likely
+/// known by the time the `Received` header of the first message is stamped.
+fn spawn_client_rdns(
+    rule_engine: &RuleEngine,
+    config: &Config,
+    client_ip: std::net::IpAddr,
+) -> std::sync::Arc<tokio::sync::OnceCell<Vec<Domain>>> {
+    let client_rdns = std::sync::Arc::new(tokio::sync::OnceCell::new());
+    let resolver = rule_engine.srv().resolvers.get_resolver_root();
+    let timeout = config.server.smtp.rdns_timeout;
+
+    tokio::spawn({
+        let client_rdns = client_rdns.clone();
+        async move {
+            client_rdns
+                .get_or_init(|| vsmtp_rule_engine::client_rdns(&resolver, client_ip, timeout))
+                .await;
+        }
+    });
+
+    client_rdns
+}
+
+/// Extract the subject of the certificate presented by the client,
+/// the certificate has already been verified by `rustls`.
+fn to_client_certificate(certificate: &rustls::Certificate) -> Option<ClientCertificate> {
+    use x509_parser::extensions::GeneralName;
+
+    let (_, certificate) = x509_parser::parse_x509_certificate(&certificate.0).ok()?;
+
+    let subject_alt_names = certificate
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|san| {
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(format!("DNS:{dns}")),
+                    GeneralName::RFC822Name(email) => Some(format!("email:{email}")),
+                    GeneralName::URI(uri) => Some(format!("URI:{uri}")),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => <[u8; 4]>::try_from(*ip)
+                            .ok()
+                            .map(|ip| format!("IP:{}", std::net::Ipv4Addr::from(ip))),
+                        16 => <[u8; 16]>::try_from(*ip)
+                            .ok()
+                            .map(|ip| format!("IP:{}", std::net::Ipv6Addr::from(ip))),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(ClientCertificate {
+        subject: certificate.subject().to_string(),
+        subject_alt_names,
+    })
+}
+
+/// The IPv4 address of an IPv4-mapped IPv6 address (`::ffff:a.b.c.d`), as accepted
+/// by a dual stack socket, or the address itself.
+pub(crate) fn canonical_ip(ip: std::net::IpAddr) -> std::net::IpAddr {
+    match ip {
+        std::net::IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, std::net::IpAddr::V4),
+        std::net::IpAddr::V4(_) => ip,
+    }
+}
+
+/// Is the client address rejected by the access lists of the configuration.
+fn is_connection_blocked(
+    access: &vsmtp_config::field::FieldServerSMTPAccess,
+    client: std::net::IpAddr,
+) -> bool {
+    let client = canonical_ip(client);
+    let denied = access.deny.iter().any(|net| net.contains(&client));
+    let allowed = access.allow.iter().any(|net| net.contains(&client));
+
+    denied && !(access.allow_overrides_deny && allowed)
+}
+
+/// Split the argument of a `VRFY` or `EXPN` command in a local part and an optional domain.
+fn parse_vrfy_query(query: &str) -> (&str, Option<&str>) {
+    let query = query
+        .strip_prefix('<')
+        .and_then(|query| query.strip_suffix('>'))
+        .unwrap_or(query);
+
+    query
+        .split_once('@')
+        .map_or((query, None), |(local_part, domain)| {
+            (local_part, Some(domain))
+        })
+}
+
+/// Usage of the commands, sent in reply to `HELP <command>`.
+fn command_help(command: &str) -> Option<&'static str> {
+    const USAGES: [(&str, &str); 13] = [
+        ("HELO", "HELO <domain>"),
+        ("EHLO", "EHLO <domain>"),
+        ("STARTTLS", "STARTTLS"),
+        ("AUTH", "AUTH <mechanism> [<initial-response>]"),
+        (
+            "MAIL",
+            "MAIL FROM:<reverse-path> [SIZE=<size>] [BODY=7BIT|8BITMIME] [SMTPUTF8] [RET=FULL|HDRS] [ENVID=<id>] [REQUIRETLS]",
+        ),
+        (
+            "RCPT",
+            "RCPT TO:<forward-path> [NOTIFY=NEVER|SUCCESS,FAILURE,DELAY] [ORCPT=<type>;<address>]",
+        ),
+        ("DATA", "DATA, then the message ended by <CRLF>.<CRLF>"),
+        ("RSET", "RSET, abort the current transaction"),
+        ("NOOP", "NOOP"),
+        ("VRFY", "VRFY <user>"),
+        ("EXPN", "EXPN <list>"),
+        ("HELP", "HELP [<command>]"),
+        ("QUIT", "QUIT"),
+    ];
+
+    USAGES
+        .iter()
+        .find(|(verb, _)| verb.eq_ignore_ascii_case(command))
+        .map(|(_, usage)| *usage)
+}
+
+/// The commands listed in reply to `HELP`: `STARTTLS` only if the server can secure the session,
+/// and `AUTH` only if the mechanisms are advertised by `EHLO`.
+fn help_commands(
+    config: &vsmtp_config::Config,
+    is_transaction_secured: bool,
+    has_tls: bool,
+    tls_required: bool,
+) -> String {
+    let esmtp = &config.server.esmtp;
+
+    let starttls = !is_transaction_secured && has_tls;
+    let auth = esmtp.auth.is_some()
+        && (is_transaction_secured || !(esmtp.auth_require_tls || tls_required));
+
+    ["HELO", "EHLO"]
+        .into_iter()
+        .chain(starttls.then_some("STARTTLS"))
+        .chain(auth.then_some("AUTH"))
+        .chain([
+            "MAIL", "RCPT", "DATA", "RSET", "NOOP", "VRFY", "EXPN", "HELP", "QUIT",
+        ])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Build the `220` reply from `server.smtp.banner`, one reply line per line of the text.
+fn build_banner(config: &vsmtp_config::Config, hostname: &str) -> Reply {
+    // NOTE: the text is checked when the configuration is loaded,
+    //       but a configuration can also be built without the checks.
+    config
+        .server
+        .smtp
+        .banner_reply(hostname)
+        .unwrap_or_else(|error| {
+            tracing::warn!(%error, "Invalid `server.smtp.banner`, using the default one.");
+            vsmtp_config::field::FieldServerSMTP::default()
+                .banner_reply(hostname)
+                .expect("the default banner is valid")
+        })
+}
+
+fn build_ehlo_reply(
+    config: &vsmtp_config::Config,
+    is_transaction_secured: bool,
+    xclient_trusted: bool,
+    tls_required: bool,
+) -> Reply {
     let auth_mechanism_list: Option<(Vec<Mechanism>, Vec<Mechanism>)> = config
         .server
         .esmtp
@@ -41,7 +219,10 @@ fn build_ehlo_reply(config: &vsmtp_config::Config, is_transaction_secured: bool)
 
     let esmtp = &config.server.esmtp;
 
-    let auth = if is_transaction_secured {
+    let auth = if !is_transaction_secured && (esmtp.auth_require_tls || tls_required) {
+        // The credentials must not be sent before STARTTLS.
+        None
+    } else if is_transaction_secured {
         // All "unsafe" mechanisms are available under tls.
         auth_mechanism_list.as_ref().map(|(must_be_secured, _)| {
             (
@@ -110,8 +291,13 @@ fn build_ehlo_reply(config: &vsmtp_config::Config, is_transaction_secured: bool)
             .pipelining
             .then_some(("250", "PIPELINING".to_string())),
         esmtp.chunking.then_some(("250", "CHUNKING".to_string())),
+        esmtp
+            .enhanced_status_codes
+            .then_some(("250", "ENHANCEDSTATUSCODES".to_string())),
         Some(("250", "DSN".to_owned())),
+        is_transaction_secured.then_some(("250", "REQUIRETLS".to_string())),
         Some(("250", format!("SIZE {}", esmtp.size))),
+        xclient_trusted.then_some(("250", "XCLIENT ADDR PORT NAME HELO PROTO LOGIN".to_owned())),
     ]
     .into_iter()
     .flatten()
@@ -138,6 +324,7 @@ where
     ParserFactory: Fn() -> Parser + Send + Sync,
 {
     /// Callback to provided to [`vsmtp_protocol::Receiver`] to handle the connection
+    #[allow(clippy::too_many_arguments, clippy::too_many_lines)]
     pub fn on_accept(
         AcceptArgs {
             client_addr,
@@ -153,9 +340,18 @@ where
         queue_manager: std::sync::Arc<dyn GenericQueueManager>,
         emitter: std::sync::Arc<Emitter>,
         message_parser_factory: ParserFactory,
+        rate_limiter: Option<std::sync::Arc<RateLimiter>>,
     ) -> (Self, ReceiverContext, Option<Reply>) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::connection_accepted(kind);
+
         let mut ctx = ReceiverContext::default();
         let mut skipped = None;
+        let xclient_trusted = config
+            .server
+            .smtp
+            .xclient_trusted
+            .contains(&client_addr.ip());
         let state = rule_engine.spawn_at_connect(
             client_addr,
             server_addr,
@@ -176,15 +372,40 @@ where
             skipped = Some(Status::DelegationResult);
         }
 
+        let blocked = is_connection_blocked(&config.server.smtp.access, client_addr.ip());
+        if blocked {
+            state
+                .context()
+                .write()
+                .expect("bad state")
+                .set_connection_blocked(true);
+        }
+
         let reply = match rule_engine.run_when(&state, &mut skipped, ExecutionStage::Connect) {
+            // NOTE: the connect rules are run to let them log the decision of the access lists,
+            // but cannot override it.
+            _ if blocked => {
+                tracing::warn!("Client address blocked by the access lists, closing connection.");
+                Err(config
+                    .server
+                    .smtp
+                    .access
+                    .reply
+                    .then(|| config.server.smtp.reply(RejectionReason::ConnectionRefused)))
+            }
             // FIXME: do we really want to let the end-user override the EHLO/HELO reply?
-            Status::Faccept(reply) | Status::Accept(reply) => reply,
+            Status::Faccept(reply) | Status::Accept(reply) => Ok(reply),
             Status::Quarantine(_) | Status::Next | Status::DelegationResult => {
-                format!("220 {} Service ready\r\n", config.server.name)
-                    .parse::<Reply>()
-                    .expect("valid")
+                Ok(build_banner(&config, &config.server.name.to_string()))
             }
-            Status::Deny(reply) | Status::Reject(reply) => {
+            Status::Deny(reply) | Status::Reject(reply) => Err(Some(reply)),
+            // FIXME: user ran a delegate method before postq/delivery
+            Status::Delegated(_) => unreachable!(),
+        };
+
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(reply) => {
                 ctx.deny();
                 return (
                     Self {
@@ -197,15 +418,24 @@ where
                         state,
                         state_internal: None,
                         skipped,
+                        xclient_trusted,
+                        rate_limiter,
+                        listener: None,
+                        client_rdns: std::sync::Arc::default(),
+                        connection_slot: None,
                     },
                     ctx,
-                    Some(reply),
+                    reply,
                 );
             }
-            // FIXME: user ran a delegate method before postq/delivery
-            Status::Delegated(_) => unreachable!(),
         };
 
+        if let Some(session_timeout) = config.server.smtp.session_timeout {
+            ctx.limit_session(session_timeout);
+        }
+
+        let client_rdns = spawn_client_rdns(&rule_engine, &config, client_addr.ip());
+
         // NOTE: in that case, the return value is ignored and
         // we have to manually trigger the TLS handshake,
         if kind == ConnectionKind::Tunneled
@@ -226,12 +456,21 @@ where
                     state,
                     state_internal: None,
                     skipped,
+                    xclient_trusted,
+                    rate_limiter,
+                    listener: None,
+                    client_rdns,
+                    connection_slot: None,
                 },
                 ctx,
                 None,
             );
         }
 
+        if let Some(greeting_delay) = &config.server.smtp.greeting_delay {
+            ctx.delay_greeting(greeting_delay.delay);
+        }
+
         (
             Self {
                 config,
@@ -243,6 +482,11 @@ where
                 state,
                 state_internal: None,
                 skipped,
+                xclient_trusted,
+                rate_limiter,
+                listener: None,
+                client_rdns,
+                connection_slot: None,
             },
             ctx,
             Some(reply),
@@ -253,6 +497,7 @@ where
         CallbackWrap(Box::new(RsaslSessionCallback {
             rule_engine: self.rule_engine.clone(),
             state: self.state.clone(),
+            token_accepted: std::sync::atomic::AtomicBool::new(false),
         }))
     }
 
@@ -265,6 +510,10 @@ where
         alpn_protocol: Option<Vec<u8>>,
     ) -> Reply {
         let server_name = sni.map(|sni| sni.parse().unwrap());
+        let client_certificate = peer_certificates
+            .as_ref()
+            .and_then(|certificates| certificates.first())
+            .and_then(to_client_certificate);
 
         self.state
             .context()
@@ -275,16 +524,17 @@ where
                 protocol_version,
                 cipher_suite,
                 peer_certificates,
+                client_certificate,
                 alpn_protocol,
             )
             .expect("bad state");
 
-        format!(
-            "220 {} Service ready\r\n",
-            server_name.unwrap_or_else(|| self.config.server.name.clone())
+        build_banner(
+            &self.config,
+            &server_name
+                .unwrap_or_else(|| self.config.server.name.clone())
+                .to_string(),
         )
-        .parse::<Reply>()
-        .unwrap()
     }
 
     pub(super) fn on_starttls_inner(&mut self, ctx: &mut ReceiverContext) -> Reply {
@@ -311,6 +561,36 @@ where
         }
     }
 
+    /// Apply the policy of a listener of `server.interfaces.listeners` to the connection.
+    pub fn set_listener(&mut self, listener: FieldServerInterfacesListener) {
+        self.listener = Some(listener);
+    }
+
+    /// Hold the slot of the client in the count of its sessions until the end of the connection.
+    pub(crate) fn set_connection_slot(&mut self, slot: ConnectionSlot) {
+        self.connection_slot = Some(slot);
+    }
+
+    fn listener_requires_tls(&self) -> bool {
+        self.listener
+            .map_or(false, |listener| listener.tls == ListenerTls::Required)
+    }
+
+    /// Reason to refuse the transaction required by the policy of the listener, if any.
+    pub(super) fn listener_rejection(&self) -> Option<RejectionReason> {
+        let listener = self.listener?;
+        let ctx = self.state.context();
+        let ctx = ctx.read().expect("state poisoned");
+
+        if listener.tls == ListenerTls::Required && !ctx.is_secured() {
+            Some(RejectionReason::TlsRequired)
+        } else if listener.auth_required && !ctx.is_authenticated() {
+            Some(RejectionReason::AuthRequired)
+        } else {
+            None
+        }
+    }
+
     pub(super) fn on_auth_inner(
         &mut self,
         ctx: &mut ReceiverContext,
@@ -323,8 +603,10 @@ where
                 .read()
                 .expect("state poisoned")
                 .is_secured()
-                && args.mechanism.must_be_under_tls()
-                && !auth.enable_dangerous_mechanism_in_clair
+                && (self.config.server.esmtp.auth_require_tls
+                    || self.listener_requires_tls()
+                    || (args.mechanism.must_be_under_tls()
+                        && !auth.enable_dangerous_mechanism_in_clair))
             {
                 return Some(
                     "538 5.7.11 Encryption required for requested authentication mechanism\r\n"
@@ -398,6 +680,12 @@ where
             Err(AuthError::Base64 { .. }) => "501 5.5.2 Invalid, not base64\r\n"
                 .parse::<Reply>()
                 .unwrap(),
+            Err(AuthError::SessionError(e)) if e.is_mechanism_error() => {
+                tracing::warn!(%e, "malformed auth data");
+                "501 5.5.2 Invalid, malformed authentication data\r\n"
+                    .parse::<Reply>()
+                    .unwrap()
+            }
             Err(AuthError::SessionError(e)) => {
                 tracing::warn!(%e, "auth error");
                 ctx.deny();
@@ -421,7 +709,7 @@ where
             .context()
             .write()
             .expect("state poisoned")
-            .to_helo(ClientName::Domain(args.client_name), true)
+            .to_helo(args.client_name, true)
             .expect("bad state");
 
         match self
@@ -453,15 +741,99 @@ where
             .to_helo(args.client_name, false)
             .expect("bad state");
 
+        let reply =
+            match self
+                .rule_engine
+                .run_when(&self.state, &mut self.skipped, ExecutionStage::Helo)
+            {
+                Status::Faccept(reply) | Status::Accept(reply) => reply,
+                Status::Quarantine(_) | Status::Next | Status::DelegationResult => {
+                    let ctx = vsl_ctx.read().expect("state poisoned");
+
+                    build_ehlo_reply(
+                        &self.state.server().config,
+                        ctx.is_secured(),
+                        self.xclient_trusted,
+                        self.listener_requires_tls(),
+                    )
+                }
+                Status::Deny(reply) | Status::Reject(reply) => {
+                    ctx.deny();
+                    return reply;
+                }
+                // FIXME: user ran a delegate method before postq/delivery
+                Status::Delegated(_) => unreachable!(),
+            };
+
+        // NOTE: the first line of the reply is the greeting, the others are the capabilities.
+        if !reply.code().is_error() {
+            vsl_ctx
+                .write()
+                .expect("state poisoned")
+                .set_advertised_capabilities(reply.lines().skip(1).cloned().collect())
+                .expect("bad state");
+        }
+
+        reply
+    }
+
+    pub(super) fn on_xclient_inner(
+        &mut self,
+        ctx: &mut ReceiverContext,
+        args: XClientArgs,
+    ) -> Reply {
+        if !self.xclient_trusted {
+            return self
+                .config
+                .server
+                .smtp
+                .reply(RejectionReason::XClientUnauthorized);
+        }
+
+        {
+            let vsl_ctx = self.state.context();
+            let mut vsl_ctx = vsl_ctx.write().expect("state poisoned");
+
+            let client_addr = *vsl_ctx.client_addr();
+            let client_ip = args.addr.unwrap_or_else(|| client_addr.ip());
+            if client_ip != client_addr.ip() {
+                self.client_rdns = spawn_client_rdns(&self.rule_engine, &self.config, client_ip);
+            }
+
+            // NOTE: the `NAME` attribute has no equivalent in the context.
+            vsl_ctx
+                .to_forwarded(
+                    std::net::SocketAddr::new(
+                        client_ip,
+                        args.port.unwrap_or_else(|| client_addr.port()),
+                    ),
+                    args.helo.map(|client_name| HeloProperties {
+                        client_name,
+                        using_deprecated: args.proto == Some(XClientProto::Smtp),
+                        capabilities: vec![],
+                    }),
+                    args.login.map(|authid| AuthProperties {
+                        authenticated: true,
+                        cancel_count: 0,
+                        credentials: Some(Credentials::Forwarded { authid }),
+                    }),
+                )
+                .expect("bad state");
+        }
+
+        // The session is reset as a new connection of the original client,
+        // the rules of the `connect` stage are run again.
+        if !matches!(self.skipped, Some(Status::DelegationResult)) {
+            self.skipped = None;
+        }
+
         match self
             .rule_engine
-            .run_when(&self.state, &mut self.skipped, ExecutionStage::Helo)
+            .run_when(&self.state, &mut self.skipped, ExecutionStage::Connect)
         {
             Status::Faccept(reply) | Status::Accept(reply) => reply,
             Status::Quarantine(_) | Status::Next | Status::DelegationResult => {
-                let ctx = vsl_ctx.read().expect("state poisoned");
-
-                build_ehlo_reply(&self.state.server().config, ctx.is_secured())
+                build_banner(&self.config, &self.config.server.name.to_string())
             }
             Status::Deny(reply) | Status::Reject(reply) => {
                 ctx.deny();
@@ -471,6 +843,76 @@ where
             Status::Delegated(_) => unreachable!(),
         }
     }
+
+    /// Answer to the `HELP` command, the text of `server.smtp.help` or the usage of a command.
+    pub(super) fn on_help_inner(&self, args: HelpArgs) -> Reply {
+        let Some(command) = args.command else {
+            let commands = help_commands(
+                &self.config,
+                self.state
+                    .context()
+                    .read()
+                    .expect("state poisoned")
+                    .is_secured(),
+                self.rustls_config.is_some(),
+                self.listener_requires_tls(),
+            );
+
+            // NOTE: the text is checked when the configuration is loaded,
+            //       but a configuration can also be built without the checks.
+            return self
+                .config
+                .server
+                .smtp
+                .help_reply(&commands)
+                .unwrap_or_else(|error| {
+                    tracing::warn!(%error, "Invalid `server.smtp.help`, using the default one.");
+                    vsmtp_config::field::FieldServerSMTP::default()
+                        .help_reply(&commands)
+                        .expect("the default help is valid")
+                });
+        };
+
+        command_help(&command)
+            .map_or_else(
+                || "214 No help available, send HELP for the supported commands\r\n".to_owned(),
+                |usage| format!("214 {usage}\r\n"),
+            )
+            .parse::<Reply>()
+            .unwrap()
+    }
+
+    /// Answer to the `VRFY` and `EXPN` commands, depending on `server.smtp.vrfy`.
+    ///
+    /// `subject` is the object of the command, used in the `252` reply ("VRFY user", "EXPN list").
+    pub(super) fn on_vrfy_inner(&self, subject: &str, query: &str) -> Reply {
+        let cannot = || {
+            format!("252 Cannot {subject}, but will accept message and attempt delivery\r\n")
+                .parse::<Reply>()
+                .unwrap()
+        };
+
+        match self.config.server.smtp.vrfy {
+            FieldServerSMTPVrfy::Cannot => cannot(),
+            FieldServerSMTPVrfy::Disabled => {
+                "502 Command not implemented\r\n".parse::<Reply>().unwrap()
+            }
+            // NOTE: the mailboxes of the other domains are neither confirmed nor denied.
+            FieldServerSMTPVrfy::Lookup => match parse_vrfy_query(query) {
+                (_, Some(domain))
+                    if domain.parse::<Domain>().ok().as_ref() != Some(&self.config.server.name) =>
+                {
+                    cannot()
+                }
+                (local_part, _) if users::get_user_by_name(local_part).is_some() => {
+                    format!("250 <{local_part}@{}>\r\n", self.config.server.name)
+                        .parse::<Reply>()
+                        .unwrap()
+                }
+                _ => "550 mailbox unavailable\r\n".parse::<Reply>().unwrap(),
+            },
+        }
+    }
 }
 
 ///
@@ -493,6 +935,9 @@ pub enum ValidationError {
 struct RsaslSessionCallback {
     rule_engine: std::sync::Arc<RuleEngine>,
     state: std::sync::Arc<RuleState>,
+    /// The bearer token of `OAUTHBEARER` / `XOAUTH2` is checked in [`Self::callback`]
+    /// because the mechanism must produce an error challenge on failure.
+    token_accepted: std::sync::atomic::AtomicBool,
 }
 
 impl RsaslSessionCallback {
@@ -519,15 +964,59 @@ impl RsaslSessionCallback {
 
         Ok(())
     }
+
+    fn validate_token(
+        &self,
+        session_data: &rsasl::callback::SessionData,
+        context: &rsasl::callback::Context<'_>,
+    ) -> bool {
+        let accepted = Credentials::try_from((session_data, context))
+            .map_err(|e| tracing::warn!(%e, "invalid bearer token credentials"))
+            .map_or(false, |credentials| {
+                self.inner_validate(credentials).is_ok()
+            });
+
+        self.token_accepted
+            .store(accepted, std::sync::atomic::Ordering::Release);
+        accepted
+    }
 }
 
 impl rsasl::callback::SessionCallback for RsaslSessionCallback {
     fn callback(
         &self,
-        _session_data: &rsasl::callback::SessionData,
-        _context: &rsasl::callback::Context<'_>,
-        _request: &mut rsasl::callback::Request<'_>,
+        session_data: &rsasl::callback::SessionData,
+        context: &rsasl::callback::Context<'_>,
+        request: &mut rsasl::callback::Request<'_>,
     ) -> Result<(), rsasl::prelude::SessionError> {
+        use rsasl::mechanisms::{
+            oauthbearer::properties::{OAuthBearerError, OAuthBearerValidate},
+            xoauth2::properties::XOAuth2Validate,
+        };
+
+        // The error challenges sent to the client, see <https://datatracker.ietf.org/doc/html/rfc7628#section-3.2.2>
+        const OAUTHBEARER_ERROR: &str = r#"{"status":"invalid_token"}"#;
+        const XOAUTH2_ERROR: &str = r#"{"status":"401","schemes":"bearer"}"#;
+
+        if request.is::<OAuthBearerValidate>() {
+            let result = if self.validate_token(session_data, context) {
+                Ok(())
+            } else {
+                Err(
+                    serde_json::from_str::<OAuthBearerError<'_>>(OAUTHBEARER_ERROR)
+                        .expect("valid error"),
+                )
+            };
+            request.satisfy::<OAuthBearerValidate>(&result)?;
+        } else if request.is::<XOAuth2Validate>() {
+            let result = if self.validate_token(session_data, context) {
+                Ok(())
+            } else {
+                Err(XOAUTH2_ERROR)
+            };
+            request.satisfy::<XOAuth2Validate>(&result)?;
+        }
+
         Ok(())
     }
 
@@ -544,6 +1033,18 @@ impl rsasl::callback::SessionCallback for RsaslSessionCallback {
             otherwise => rsasl::validate::ValidationError::Boxed(Box::new(otherwise)),
         })?;
 
+        // The rules have already been run in `callback`, a rejected token
+        // leaves the validation empty until the client has acknowledged the error challenge.
+        if matches!(credentials, Credentials::BearerToken { .. }) {
+            if self
+                .token_accepted
+                .load(std::sync::atomic::Ordering::Acquire)
+            {
+                validate.with::<ValidationVSL, _>(|| Ok(()))?;
+            }
+            return Ok(());
+        }
+
         validate.with::<ValidationVSL, _>(|| {
             self.inner_validate(credentials)
                 .map_err(|e| rsasl::validate::ValidationError::Boxed(Box::new(e)))
@@ -555,10 +1056,135 @@ impl rsasl::callback::SessionCallback for RsaslSessionCallback {
 
 #[cfg(test)]
 mod tests {
-    use vsmtp_config::field::FieldServerESMTP;
+    use vsmtp_config::field::{FieldServerESMTP, FieldServerSMTPAccess};
 
     use super::*;
 
+    fn access(allow: &[&str], deny: &[&str]) -> FieldServerSMTPAccess {
+        FieldServerSMTPAccess {
+            allow: allow.iter().map(|net| net.parse().unwrap()).collect(),
+            deny: deny.iter().map(|net| net.parse().unwrap()).collect(),
+            ..FieldServerSMTPAccess::default()
+        }
+    }
+
+    #[test]
+    fn access_denied_network() {
+        let access = access(&[], &["192.0.2.0/24"]);
+
+        assert!(is_connection_blocked(&access, "192.0.2.1".parse().unwrap()));
+        assert!(is_connection_blocked(
+            &access,
+            "192.0.2.255".parse().unwrap()
+        ));
+        assert!(!is_connection_blocked(
+            &access,
+            "192.0.3.1".parse().unwrap()
+        ));
+        // NOTE: the IPv4 clients of a dual stack socket.
+        assert!(is_connection_blocked(
+            &access,
+            "::ffff:192.0.2.1".parse().unwrap()
+        ));
+        assert!(!is_connection_blocked(
+            &access,
+            "::ffff:192.0.3.1".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn access_allowed_address() {
+        let mut access = access(&["192.0.2.10/32"], &["192.0.2.0/24"]);
+
+        assert!(!is_connection_blocked(
+            &access,
+            "192.0.2.10".parse().unwrap()
+        ));
+        assert!(is_connection_blocked(
+            &access,
+            "192.0.2.11".parse().unwrap()
+        ));
+
+        access.allow_overrides_deny = false;
+        assert!(is_connection_blocked(
+            &access,
+            "192.0.2.10".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn access_ipv6_range() {
+        let access = access(&["2001:db8:1::/48"], &["2001:db8::/32"]);
+
+        assert!(is_connection_blocked(
+            &access,
+            "2001:db8::1".parse().unwrap()
+        ));
+        assert!(!is_connection_blocked(
+            &access,
+            "2001:db8:1::1".parse().unwrap()
+        ));
+        assert!(!is_connection_blocked(
+            &access,
+            "2001:db9::1".parse().unwrap()
+        ));
+        assert!(!is_connection_blocked(
+            &access,
+            "192.0.2.1".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn help_commands_available() {
+        let mut config = vsmtp_config::Config::builder()
+            .with_version_str("<1.0.0")
+            .unwrap()
+            .without_path()
+            .with_server_name("testserver.com".parse::<vsmtp_common::Domain>().unwrap())
+            .with_user_group_and_default_system("root", "root")
+            .unwrap()
+            .with_ipv4_localhost()
+            .with_default_logs_settings()
+            .with_spool_dir_and_default_queues("./tmp/spool")
+            .without_tls_support()
+            .with_default_smtp_options()
+            .with_default_smtp_error_handler()
+            .with_default_extensions()
+            .with_app_at_location("./tmp/app")
+            .with_vsl(format!(
+                "{}/src/template/ignore_vsl/domain-enabled",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .with_default_app_logs()
+            .with_system_dns()
+            .without_virtual_entries()
+            .validate();
+
+        assert_eq!(
+            help_commands(&config, false, false, false),
+            "HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT"
+        );
+        assert_eq!(
+            help_commands(&config, false, true, false),
+            "HELO EHLO STARTTLS MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT"
+        );
+        assert_eq!(
+            help_commands(&config, true, true, false),
+            "HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT"
+        );
+
+        config.server.esmtp.auth = Some(vsmtp_config::field::FieldServerSMTPAuth::default());
+        config.server.esmtp.auth_require_tls = true;
+        assert_eq!(
+            help_commands(&config, false, true, false),
+            "HELO EHLO STARTTLS MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT"
+        );
+        assert_eq!(
+            help_commands(&config, true, true, false),
+            "HELO EHLO AUTH MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT"
+        );
+    }
+
     #[test]
     fn build_full_ehlo() {
         let config = vsmtp_config::Config::builder()
@@ -584,7 +1210,7 @@ mod tests {
             .with_system_dns()
             .without_virtual_entries()
             .validate();
-        let reply = build_ehlo_reply(&config, true);
+        let reply = build_ehlo_reply(&config, true, false, false);
         assert_eq!(reply.code().value(), 250);
         assert_eq!(
             reply.to_string(),
@@ -594,6 +1220,7 @@ mod tests {
                 "250-SMTPUTF8",
                 "250-PIPELINING",
                 "250-DSN",
+                "250-REQUIRETLS",
                 "250 SIZE 20000000\r\n",
             ]
             .join("\r\n")
@@ -605,10 +1232,12 @@ mod tests {
     fn build_ehlo_without_8bit() {
         let extensions = FieldServerESMTP {
             auth: None,
+            auth_require_tls: true,
             eightbitmime: false,
             smtputf8: true,
             pipelining: true,
             chunking: false,
+            enhanced_status_codes: false,
             size: 10,
         };
         let config = vsmtp_config::Config::builder()
@@ -634,7 +1263,7 @@ mod tests {
             .with_system_dns()
             .without_virtual_entries()
             .validate();
-        let reply = build_ehlo_reply(&config, true);
+        let reply = build_ehlo_reply(&config, true, false, false);
         assert_eq!(reply.code().value(), 250);
         assert_eq!(
             reply.to_string(),
@@ -642,6 +1271,7 @@ mod tests {
                 "250-testserver.com",
                 "250-PIPELINING",
                 "250-DSN",
+                "250-REQUIRETLS",
                 "250 SIZE 10\r\n",
             ]
             .join("\r\n")
@@ -0,0 +1,181 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+
+use vsmtp_config::field::FieldServerSMTPRateLimit;
+
+// NOTE: past this number of clients, the full buckets are removed, then the least recently used ones.
+const MAX_CLIENTS: usize = 10_000;
+// NOTE: the number of least recently used buckets removed at once, so that the eviction is amortized.
+const EVICTED_CLIENTS: usize = MAX_CLIENTS / 10;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: std::time::Instant,
+}
+
+/// Token bucket of the recipients accepted from each client address,
+/// shared by all the connections of the server.
+pub struct RateLimiter {
+    // NOTE: tokens per second.
+    rate: f64,
+    burst: f64,
+    buckets: std::sync::Mutex<std::collections::HashMap<std::net::IpAddr, Bucket>>,
+    throttled: std::sync::atomic::AtomicU64,
+}
+
+impl RateLimiter {
+    /// Create an instance where the bucket of every client is full.
+    #[must_use]
+    pub fn new(config: &FieldServerSMTPRateLimit) -> Self {
+        Self {
+            rate: f64::from(config.rate) / config.period.as_secs_f64(),
+            burst: f64::from(config.burst),
+            buckets: std::sync::Mutex::new(std::collections::HashMap::new()),
+            throttled: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    /// Take a recipient from the bucket of the client, returns `false` if the bucket is empty.
+    ///
+    /// # Panics
+    ///
+    /// * the buckets have been poisoned
+    #[must_use]
+    pub fn acquire(&self, client: std::net::IpAddr) -> bool {
+        self.acquire_at(client, std::time::Instant::now())
+    }
+
+    /// Number of recipients deferred since the startup.
+    #[must_use]
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn tokens_at(&self, bucket: &Bucket, now: std::time::Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        self.burst
+            .min(elapsed.as_secs_f64().mul_add(self.rate, bucket.tokens))
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: std::time::Instant) {
+        bucket.tokens = self.tokens_at(bucket, now);
+        bucket.last_refill = now;
+    }
+
+    fn acquire_at(&self, client: std::net::IpAddr, now: std::time::Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| self.tokens_at(bucket, now) < self.burst);
+        }
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+            let mut last_used = buckets
+                .values()
+                .map(|bucket| bucket.last_refill)
+                .collect::<Vec<_>>();
+            let (_, oldest_kept, _) = last_used.select_nth_unstable(EVICTED_CLIENTS);
+            let oldest_kept = *oldest_kept;
+            buckets.retain(|_, bucket| bucket.last_refill >= oldest_kept);
+        }
+
+        let bucket = buckets.entry(client).or_insert_with(|| Bucket {
+            tokens: self.burst,
+            last_refill: now,
+        });
+        self.refill(bucket, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            self.throttled
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use vsmtp_config::field::FieldServerSMTPRateLimit;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(&FieldServerSMTPRateLimit {
+            rate: 1,
+            period: std::time::Duration::from_secs(10),
+            burst: 3,
+        })
+    }
+
+    #[test]
+    fn exhausted_and_recovered() {
+        let limiter = limiter();
+        let client = "192.0.2.1".parse().unwrap();
+        let now = std::time::Instant::now();
+
+        assert!((0..3).all(|_| limiter.acquire_at(client, now)));
+        assert!(!limiter.acquire_at(client, now));
+        assert!(!limiter.acquire_at(client, now + std::time::Duration::from_secs(9)));
+        assert_eq!(limiter.throttled(), 2);
+
+        assert!(limiter.acquire_at(client, now + std::time::Duration::from_secs(10)));
+        assert!(!limiter.acquire_at(client, now + std::time::Duration::from_secs(10)));
+
+        // NOTE: the bucket does not exceed the burst.
+        let later = now + std::time::Duration::from_secs(3600);
+        assert!((0..3).all(|_| limiter.acquire_at(client, later)));
+        assert!(!limiter.acquire_at(client, later));
+        assert_eq!(limiter.throttled(), 4);
+    }
+
+    #[test]
+    fn bounded() {
+        let limiter = RateLimiter::new(&FieldServerSMTPRateLimit {
+            rate: 1,
+            period: std::time::Duration::from_secs(3600),
+            burst: 3,
+        });
+        let now = std::time::Instant::now();
+        let client = |i: u64| std::net::IpAddr::from(std::net::Ipv6Addr::from(u128::from(i)));
+        let at = |i: u64| now + std::time::Duration::from_millis(i);
+        let clients = (0..).take(super::MAX_CLIENTS).collect::<Vec<u64>>();
+
+        // NOTE: none of the buckets are full, the least recently used ones are removed.
+        for i in &clients {
+            assert!(limiter.acquire_at(client(*i), at(*i)));
+        }
+        let last = clients[clients.len() - 1];
+        assert!(limiter.acquire_at(client(last + 1), at(last + 1)));
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= super::MAX_CLIENTS);
+        assert!(!buckets.contains_key(&client(0)));
+        assert!(buckets.contains_key(&client(last)));
+        assert!(buckets.contains_key(&client(last + 1)));
+    }
+
+    #[test]
+    fn keyed_by_client() {
+        let limiter = limiter();
+        let now = std::time::Instant::now();
+
+        assert!((0..3).all(|_| limiter.acquire_at("192.0.2.1".parse().unwrap(), now)));
+        assert!(!limiter.acquire_at("192.0.2.1".parse().unwrap(), now));
+        assert!(limiter.acquire_at("192.0.2.2".parse().unwrap(), now));
+    }
+}
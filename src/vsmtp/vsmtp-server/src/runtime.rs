@@ -14,7 +14,7 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use crate::{delivery, scheduler, working, Server};
+use crate::{delivery, scheduler, working, Server, Sockets};
 use anyhow::Context;
 use vsmtp_common::transport::{AbstractTransport, DeserializerFn, DESERIALIZER_SYMBOL_NAME};
 use vsmtp_config::{Config, DnsResolvers};
@@ -26,7 +26,6 @@ fn init_runtime<F>(
     name: impl Into<String>,
     worker_thread_count: usize,
     future: F,
-    timeout: Option<std::time::Duration>,
 ) -> anyhow::Result<std::thread::JoinHandle<anyhow::Result<()>>>
 where
     F: std::future::Future<Output = ()> + Send + 'static,
@@ -54,13 +53,7 @@ where
             let name_rt = name.clone();
             runtime.block_on(async move {
                 tracing::info!(name = name_rt, "Runtime started successfully.");
-
-                match timeout {
-                    Some(duration) => {
-                        tokio::time::timeout(duration, future).await.unwrap_err();
-                    }
-                    None => future.await,
-                }
+                future.await;
             });
 
             sender.blocking_send(())?;
@@ -119,18 +112,110 @@ fn get_transport_deserializer(libs: &[libloading::Library]) -> Vec<DeserializerF
         .collect::<Vec<_>>()
 }
 
+/// Recompile the rules from the scripts of the configuration, and swap them with
+/// the ones currently in use.
+///
+/// The sessions and messages already being processed keep running on the previous
+/// rules, the new ones are used for the next transactions.
+///
+/// The configuration is not reloaded, a change of the configuration requires a restart.
+///
+/// # Errors
+///
+/// * the rules failed to compile, the previous ones are kept
+pub fn reload_rules(rule_engine: &arc_swap::ArcSwap<RuleEngine>) -> anyhow::Result<()> {
+    let reloaded = rule_engine.load().reload()?;
+    rule_engine.store(std::sync::Arc::new(reloaded));
+
+    Ok(())
+}
+
+/// Watch the directories of the scripts, and reload the rules once they have not
+/// changed for `app.vsl.auto_reload_debounce`, so that a burst of writes (an editor
+/// saving a file, a deployment copying a directory) triggers a single reload.
+///
+/// Only the rules are reloaded, with the configuration loaded at startup (see [`reload_rules`]).
+///
+/// A failed reload is logged and the previous rules are kept. The watcher stops once
+/// `rule_engine` has been dropped.
+///
+/// # Errors
+///
+/// * a directory of the scripts could not be watched
+pub fn watch_rules(
+    rule_engine: &std::sync::Arc<arc_swap::ArcSwap<RuleEngine>>,
+) -> anyhow::Result<()> {
+    use notify::Watcher;
+
+    let config = rule_engine.load().srv().config.clone();
+    let debounce = config.app.vsl.auto_reload_debounce;
+
+    let (events, changes) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events)?;
+    for path in config
+        .app
+        .vsl
+        .filter_path
+        .iter()
+        .filter_map(|filter_path| filter_path.parent())
+        .chain(config.app.vsl.domain_dir.as_deref())
+    {
+        watcher
+            .watch(path, notify::RecursiveMode::Recursive)
+            .with_context(|| format!("cannot watch '{}'", path.display()))?;
+    }
+
+    let rule_engine = std::sync::Arc::downgrade(rule_engine);
+    std::thread::Builder::new()
+        .name("vsl-watcher".to_string())
+        .spawn(move || {
+            let _watcher = watcher;
+            // NOTE: the other files of the directories do not delay the reload.
+            let mut last_change = None::<std::time::Instant>;
+            loop {
+                let timeout =
+                    last_change.map_or(debounce, |last| debounce.saturating_sub(last.elapsed()));
+                match changes.recv_timeout(timeout) {
+                    Ok(Ok(event)) if is_script_change(&event) => {
+                        last_change = Some(std::time::Instant::now());
+                    }
+                    Ok(Ok(_)) | Err(std::sync::mpsc::RecvTimeoutError::Timeout) => (),
+                    Ok(Err(error)) => tracing::warn!(%error, "Scripts watcher failure."),
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+
+                let Some(rule_engine) = rule_engine.upgrade() else {
+                    return;
+                };
+                if last_change.map_or(false, |last| last.elapsed() >= debounce) {
+                    last_change = None;
+                    tracing::info!("Scripts changed on disk, reloading the rules.");
+                    if let Err(error) = reload_rules(&rule_engine) {
+                        tracing::error!(%error, "Rules reload failure, keeping the previous rules.");
+                    }
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+fn is_script_change(event: &notify::Event) -> bool {
+    !event.kind.is_access()
+        && event.paths.iter().any(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "vsl")
+        })
+}
+
 /// Start the `vSMTP` server's runtime
 ///
 /// # Errors
 ///
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::too_many_lines)]
 pub fn start_runtime(
     config: Config,
-    sockets: (
-        Vec<std::net::TcpListener>,
-        Vec<std::net::TcpListener>,
-        Vec<std::net::TcpListener>,
-    ),
+    sockets: Sockets,
     timeout: Option<std::time::Duration>,
 ) -> anyhow::Result<()> {
     let config = std::sync::Arc::new(config);
@@ -162,11 +247,15 @@ pub fn start_runtime(
         DnsResolvers::from_config(&config).context("could not initialize dns")?,
     );
 
-    let rule_engine = std::sync::Arc::new(RuleEngine::new(
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(RuleEngine::new(
         config.clone(),
         resolvers,
         queue_manager.clone(),
-    )?);
+    )?));
+
+    if config.app.vsl.auto_reload {
+        watch_rules(&rule_engine).context("could not watch the scripts")?;
+    }
 
     let _tasks_delivery = init_runtime(
         error_handler.0.clone(),
@@ -178,7 +267,6 @@ pub fn start_runtime(
             queue_manager.clone(),
             delivery_rx,
         ),
-        timeout,
     )?;
 
     let _tasks_processing = init_runtime(
@@ -191,14 +279,26 @@ pub fn start_runtime(
             emitter.clone(),
             working_rx,
         ),
-        timeout,
     )?;
 
+    let rule_engine_sig = rule_engine.clone();
+    let stop = std::sync::Arc::new(tokio::sync::Notify::new());
+    let stop_sig = stop.clone();
     let _tasks_receiver = init_runtime(
         error_handler.0.clone(),
         "receiver",
         config.server.system.thread_pool.receiver.get(),
         async move {
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &config.server.metrics {
+                let address = metrics.address;
+                tokio::spawn(async move {
+                    if let Err(error) = crate::metrics::serve(address).await {
+                        tracing::error!(%error, "Metrics endpoint failure.");
+                    }
+                });
+            }
+
             let server = match Server::new(
                 config.clone(),
                 rule_engine.clone(),
@@ -211,11 +311,20 @@ pub fn start_runtime(
                     return;
                 }
             };
-            if let Err(error) = server.listen(sockets).await {
+            // NOTE: the process exits once the receiver has drained its sessions.
+            let shutdown = async move {
+                match timeout {
+                    Some(duration) => tokio::select! {
+                        () = tokio::time::sleep(duration) => {}
+                        () = stop.notified() => {}
+                    },
+                    None => stop.notified().await,
+                }
+            };
+            if let Err(error) = server.listen_until(sockets, shutdown).await {
                 tracing::error!(%error, "Receiver failure.");
             }
         },
-        timeout,
     );
 
     let error_handler_sig = error_handler.0.clone();
@@ -224,10 +333,29 @@ pub fn start_runtime(
         signal_hook::consts::SIGTERM,
         // Ctrl+C on a terminal
         signal_hook::consts::SIGINT,
+        // Send by `systemctl reload`
+        signal_hook::consts::SIGHUP,
     ])?;
     let _signal_handler = std::thread::spawn(move || {
+        let mut stopping = false;
         for sig in signals.forever() {
-            tracing::warn!(signal = sig, "Stopping vSMTP server.");
+            if sig == signal_hook::consts::SIGHUP {
+                tracing::info!(signal = sig, "Reloading the rules.");
+                if let Err(error) = reload_rules(&rule_engine_sig) {
+                    tracing::error!(%error, "Rules reload failure, keeping the previous rules.");
+                }
+                continue;
+            }
+            if !stopping {
+                tracing::warn!(
+                    signal = sig,
+                    "Stopping vSMTP server, draining the sessions."
+                );
+                stopping = true;
+                stop_sig.notify_one();
+                continue;
+            }
+            tracing::warn!(signal = sig, "Stopping vSMTP server without draining.");
             error_handler_sig
                 .blocking_send(())
                 .expect("failed to send terminating instruction");
@@ -260,6 +388,9 @@ mod tests {
                 vec![std::net::TcpListener::bind("0.0.0.0:22001").unwrap()],
                 vec![std::net::TcpListener::bind("0.0.0.0:22002").unwrap()],
                 vec![std::net::TcpListener::bind("0.0.0.0:22003").unwrap()],
+                vec![std::net::TcpListener::bind("0.0.0.0:22004").unwrap()],
+                vec![std::net::TcpListener::bind("0.0.0.0:22005").unwrap()],
+                vec![],
             ),
             Some(std::time::Duration::from_millis(100)),
         )
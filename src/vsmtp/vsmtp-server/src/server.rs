@@ -14,13 +14,17 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use crate::{receiver::handler::Handler, scheduler::Emitter, ValidationVSL};
+use crate::{
+    receiver::{connection_limit::ConnectionLimiter, handler::Handler},
+    scheduler::Emitter,
+    RateLimiter, ValidationVSL,
+};
 use anyhow::Context;
 use tokio_rustls::rustls;
 use tokio_stream::StreamExt;
 use vqueue::GenericQueueManager;
 use vsmtp_common::Reply;
-use vsmtp_config::{get_rustls_config, Config};
+use vsmtp_config::{field::FieldServerInterfacesListener, get_rustls_config, Config};
 use vsmtp_mail_parser::BasicParser;
 use vsmtp_protocol::{AcceptArgs, ConnectionKind};
 use vsmtp_rule_engine::RuleEngine;
@@ -28,12 +32,20 @@ use vsmtp_rule_engine::RuleEngine;
 /// TCP/IP server
 pub struct Server {
     conn_max_reach_reply: Reply,
+    conn_max_per_ip_reach_reply: Reply,
+    // NOTE: `None` if `server.client_count_max` is `-1`, a permit is held by each session.
+    connections: Option<std::sync::Arc<tokio::sync::Semaphore>>,
+    // NOTE: `None` if `server.client_count_max_per_ip` is `-1`, a slot is held by each session.
+    connections_per_ip: Option<std::sync::Arc<ConnectionLimiter>>,
+    // NOTE: set to `true` when the server stops, the sessions are then drained.
+    shutdown: tokio::sync::watch::Sender<bool>,
 
     config: std::sync::Arc<Config>,
     tls_config: Option<std::sync::Arc<rustls::ServerConfig>>,
-    rule_engine: std::sync::Arc<RuleEngine>,
+    rule_engine: std::sync::Arc<arc_swap::ArcSwap<RuleEngine>>,
     queue_manager: std::sync::Arc<dyn GenericQueueManager>,
     emitter: std::sync::Arc<Emitter>,
+    rate_limiter: Option<std::sync::Arc<RateLimiter>>,
 }
 
 /// Create a `TCPListener` ready to be listened to
@@ -55,6 +67,17 @@ pub fn socket_bind_anyhow<A: std::net::ToSocketAddrs + std::fmt::Debug>(
     Ok(socket)
 }
 
+/// Sockets to listen on, for each kind of connection: relay, submission,
+/// submissions (tunneled), proxied and LMTP, then the ones of `server.interfaces.listeners`.
+pub type Sockets = (
+    Vec<std::net::TcpListener>,
+    Vec<std::net::TcpListener>,
+    Vec<std::net::TcpListener>,
+    Vec<std::net::TcpListener>,
+    Vec<std::net::TcpListener>,
+    Vec<(std::net::TcpListener, FieldServerInterfacesListener)>,
+);
+
 type ListenerStreamItem = std::io::Result<(tokio::net::TcpStream, std::net::SocketAddr)>;
 
 fn listener_to_stream(
@@ -70,6 +93,9 @@ fn listener_to_stream(
 impl Server {
     /// Create a server with the configuration provided, and the sockets already bound
     ///
+    /// Each new connection uses the rule engine stored in `rule_engine` at the time
+    /// it is accepted, swapping it does not affect the sessions already opened.
+    ///
     /// # Errors
     ///
     /// * `spool_dir` does not exist and failed to be created
@@ -77,7 +103,7 @@ impl Server {
     /// * cannot initialize [rustls] config
     pub fn new(
         config: std::sync::Arc<Config>,
-        rule_engine: std::sync::Arc<RuleEngine>,
+        rule_engine: std::sync::Arc<arc_swap::ArcSwap<RuleEngine>>,
         queue_manager: std::sync::Arc<dyn GenericQueueManager>,
         emitter: std::sync::Arc<Emitter>,
     ) -> anyhow::Result<Self> {
@@ -88,9 +114,23 @@ impl Server {
         }
 
         Ok(Self {
-            conn_max_reach_reply: "554 Cannot process connection, closing\r\n"
+            conn_max_reach_reply: "421 Too many connections, closing\r\n"
+                .parse::<Reply>()
+                .expect("valid smtp reply"),
+            conn_max_per_ip_reach_reply: "421 Too many connections from your address, closing\r\n"
                 .parse::<Reply>()
                 .expect("valid smtp reply"),
+            shutdown: tokio::sync::watch::channel(false).0,
+            connections_per_ip: usize::try_from(config.server.client_count_max_per_ip)
+                .ok()
+                .map(|max| std::sync::Arc::new(ConnectionLimiter::new(max))),
+            connections: usize::try_from(config.server.client_count_max)
+                .ok()
+                .map(|max| {
+                    std::sync::Arc::new(tokio::sync::Semaphore::new(
+                        max.min(tokio::sync::Semaphore::MAX_PERMITS),
+                    ))
+                }),
             tls_config: if let Some(smtps) = &config.server.tls {
                 Some(std::sync::Arc::new(get_rustls_config(
                     smtps,
@@ -101,6 +141,12 @@ impl Server {
             },
             rule_engine,
             queue_manager,
+            rate_limiter: config
+                .server
+                .smtp
+                .rate_limit
+                .as_ref()
+                .map(|rate_limit| std::sync::Arc::new(RateLimiter::new(rate_limit))),
             config,
             emitter,
         })
@@ -109,39 +155,45 @@ impl Server {
     #[tracing::instrument(name = "handle-client", skip_all, fields(client = %client_addr, server = %server_addr))]
     async fn handle_client(
         &self,
-        client_counter: std::sync::Arc<std::sync::atomic::AtomicI64>,
+        sessions: &mut tokio::task::JoinSet<()>,
         kind: ConnectionKind,
+        listener: Option<FieldServerInterfacesListener>,
         mut stream: tokio::net::TcpStream,
         client_addr: std::net::SocketAddr,
         server_addr: std::net::SocketAddr,
     ) {
         tracing::info!(%kind, "Connection accepted.");
 
-        if self.config.server.client_count_max != -1
-            && client_counter.load(std::sync::atomic::Ordering::SeqCst)
-                >= self.config.server.client_count_max
-        {
+        let Ok(permit) = self
+            .connections
+            .clone()
+            .map(tokio::sync::Semaphore::try_acquire_owned)
+            .transpose()
+        else {
             tracing::warn!(
                 max = self.config.server.client_count_max,
                 "Connection count max reached, rejecting connection.",
             );
-
-            if let Err(error) = tokio::io::AsyncWriteExt::write_all(
-                &mut stream,
-                self.conn_max_reach_reply.as_ref().as_bytes(),
-            )
-            .await
-            {
-                tracing::error!(%error, "Code delivery failure.");
-            }
-
-            if let Err(error) = tokio::io::AsyncWriteExt::shutdown(&mut stream).await {
-                tracing::error!(%error, "Closing connection failure.");
-            }
+            Self::refuse(&mut stream, &self.conn_max_reach_reply).await;
             return;
-        }
+        };
 
-        client_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        // NOTE: the client of a proxied connection is only known once the PROXY header
+        //       has been read, its sessions are counted in `serve`.
+        let Ok(slot) = self
+            .connections_per_ip
+            .as_ref()
+            .filter(|_| kind != ConnectionKind::Proxied)
+            .map(|limiter| limiter.acquire(client_addr.ip()).ok_or(()))
+            .transpose()
+        else {
+            tracing::warn!(
+                max = self.config.server.client_count_max_per_ip,
+                "Connection count max of the client address reached, rejecting connection.",
+            );
+            Self::refuse(&mut stream, &self.conn_max_per_ip_reach_reply).await;
+            return;
+        };
 
         let session = Self::serve(
             AcceptArgs::new(
@@ -152,33 +204,68 @@ impl Server {
                 kind,
             ),
             stream,
+            listener,
+            self.shutdown.subscribe(),
             self.tls_config.clone(),
             self.config.clone(),
-            self.rule_engine.clone(),
+            self.rule_engine.load_full(),
             self.queue_manager.clone(),
             self.emitter.clone(),
+            self.rate_limiter.clone(),
+            self.connections_per_ip
+                .clone()
+                .filter(|_| kind == ConnectionKind::Proxied),
+            self.conn_max_per_ip_reach_reply.clone(),
         );
-        let client_counter_copy = client_counter.clone();
-        tokio::spawn(async move {
-            let _err = session.await;
+        #[cfg(feature = "metrics")]
+        crate::metrics::connection_opened();
+        sessions.spawn(async move {
+            let _err = Box::pin(session).await;
 
-            client_counter_copy.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            #[cfg(feature = "metrics")]
+            crate::metrics::connection_closed();
+            drop((permit, slot));
         });
     }
 
+    async fn refuse(stream: &mut tokio::net::TcpStream, reply: &Reply) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::reply_sent(reply);
+
+        if let Err(error) =
+            tokio::io::AsyncWriteExt::write_all(stream, reply.as_ref().as_bytes()).await
+        {
+            tracing::error!(%error, "Code delivery failure.");
+        }
+
+        if let Err(error) = tokio::io::AsyncWriteExt::shutdown(stream).await {
+            tracing::error!(%error, "Closing connection failure.");
+        }
+    }
+
     /// Main loop of `vSMTP`'s server
     ///
     /// # Errors
     ///
     /// * failed to convert sockets to `[tokio::net::TcpListener]`
+    pub async fn listen(self, sockets: Sockets) -> anyhow::Result<()> {
+        self.listen_until(sockets, std::future::pending()).await
+    }
+
+    /// Main loop of `vSMTP`'s server, until `shutdown` completes.
+    ///
+    /// The sockets are then closed and the sessions drained: the transactions in progress
+    /// are completed, and the clients receive a `421` before their next command.
+    /// The sessions still opened after `server.smtp.drain_timeout` are closed.
+    ///
+    /// # Errors
+    ///
+    /// * failed to convert sockets to `[tokio::net::TcpListener]`
     #[tracing::instrument(skip_all)]
-    pub async fn listen(
+    pub async fn listen_until(
         self,
-        sockets: (
-            Vec<std::net::TcpListener>,
-            Vec<std::net::TcpListener>,
-            Vec<std::net::TcpListener>,
-        ),
+        sockets: Sockets,
+        shutdown: impl std::future::Future<Output = ()> + Send,
     ) -> anyhow::Result<()> {
         fn to_tokio(
             s: Vec<std::net::TcpListener>,
@@ -188,35 +275,59 @@ impl Server {
                 .collect::<std::io::Result<Vec<tokio::net::TcpListener>>>()
         }
 
-        if self.config.server.tls.is_none() && !sockets.2.is_empty() {
+        if self.config.server.tls.is_none()
+            && (!sockets.2.is_empty()
+                || sockets
+                    .5
+                    .iter()
+                    .any(|(_, listener)| listener.kind == ConnectionKind::Tunneled))
+        {
             tracing::warn!(
                 "No TLS configuration provided, listening on submissions protocol (port 465) will cause issue"
             );
         }
 
-        let client_counter = std::sync::Arc::new(std::sync::atomic::AtomicI64::new(0));
-
-        let (listener, listener_submission, listener_tunneled) = (
+        let (listener, listener_submission, listener_tunneled, listener_proxied, listener_lmtp) = (
             to_tokio(sockets.0)?,
             to_tokio(sockets.1)?,
             to_tokio(sockets.2)?,
+            to_tokio(sockets.3)?,
+            to_tokio(sockets.4)?,
         );
+        let listeners_with_policy = sockets
+            .5
+            .into_iter()
+            .map(|(socket, listener)| {
+                tokio::net::TcpListener::from_std(socket).map(|socket| (socket, listener))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
 
-        let mut map = tokio_stream::StreamMap::new();
-        for (kind, sockets) in [
+        let mut sockets = vec![];
+        for (kind, listeners) in [
             (ConnectionKind::Relay, &listener),
             (ConnectionKind::Submission, &listener_submission),
             (ConnectionKind::Tunneled, &listener_tunneled),
+            (ConnectionKind::Proxied, &listener_proxied),
+            (ConnectionKind::Lmtp, &listener_lmtp),
         ] {
-            for listener in sockets {
-                let accept = listener_to_stream(listener);
-                let transform = tokio_stream::StreamExt::map(accept, move |client| (kind, client));
+            sockets.extend(listeners.iter().map(|socket| (socket, kind, None)));
+        }
+        sockets.extend(
+            listeners_with_policy
+                .iter()
+                .map(|(socket, listener)| (socket, listener.kind, Some(*listener))),
+        );
 
-                map.insert(
-                    listener.local_addr().expect("retrieve local address"),
-                    Box::pin(transform),
-                );
-            }
+        let mut map = tokio_stream::StreamMap::new();
+        for (socket, kind, listener) in sockets {
+            let accept = listener_to_stream(socket);
+            let transform =
+                tokio_stream::StreamExt::map(accept, move |client| (kind, listener, client));
+
+            map.insert(
+                socket.local_addr().expect("retrieve local address"),
+                Box::pin(transform),
+            );
         }
 
         tracing::info!(
@@ -224,34 +335,87 @@ impl Server {
             "Listening for clients.",
         );
 
-        while let Some((server_addr, (kind, client))) =
-            tokio_stream::StreamExt::next(&mut map).await
-        {
-            let (stream, client_addr) = client?;
+        tokio::pin!(shutdown);
+        let mut sessions = tokio::task::JoinSet::new();
+        loop {
+            tokio::select! {
+                () = &mut shutdown => break,
+                Some(_) = sessions.join_next(), if !sessions.is_empty() => {}
+                next = tokio_stream::StreamExt::next(&mut map) => {
+                    let Some((server_addr, (kind, listener, client))) = next else {
+                        break;
+                    };
+                    let (stream, client_addr) = client?;
 
-            self.handle_client(
-                client_counter.clone(),
-                kind,
-                stream,
-                client_addr,
-                server_addr,
-            )
-            .await;
+                    self.handle_client(&mut sessions, kind, listener, stream, client_addr, server_addr)
+                        .await;
+                }
+            }
         }
+
+        // NOTE: closing the sockets, the new clients are refused.
+        drop(map);
+        drop((
+            listener,
+            listener_submission,
+            listener_tunneled,
+            listener_proxied,
+            listener_lmtp,
+            listeners_with_policy,
+        ));
+
+        self.drain(sessions).await;
         Ok(())
     }
 
+    async fn drain(&self, mut sessions: tokio::task::JoinSet<()>) {
+        self.shutdown.send_replace(true);
+        tracing::info!(
+            sessions = sessions.len(),
+            "Shutting down, draining the sessions."
+        );
+
+        let drained = tokio::time::timeout(self.config.server.smtp.drain_timeout, async {
+            while sessions.join_next().await.is_some() {}
+        })
+        .await;
+
+        if drained.is_err() {
+            tracing::warn!(
+                sessions = sessions.len(),
+                "Drain timeout reached, closing the remaining sessions.",
+            );
+            sessions.shutdown().await;
+        }
+    }
+
+    /// Handle a SMTP session, its span is the root of the session's trace.
+    ///
+    /// The sessions of the client are counted by `connections_per_ip` once the connection
+    /// is accepted, the session is refused with `conn_max_per_ip_reach_reply` if the client
+    /// already uses all of its slots.
     ///
     /// # Errors
-    #[tracing::instrument(skip_all, err, fields(uuid = %args.uuid))]
+    #[tracing::instrument(
+        parent = None,
+        skip_all,
+        err,
+        fields(uuid = %args.uuid)
+    )]
+    #[allow(clippy::too_many_arguments)]
     pub async fn serve(
         args: AcceptArgs,
         tcp_stream: tokio::net::TcpStream,
+        listener: Option<FieldServerInterfacesListener>,
+        shutdown: tokio::sync::watch::Receiver<bool>,
         tls_config: Option<std::sync::Arc<rustls::ServerConfig>>,
         config: std::sync::Arc<Config>,
         rule_engine: std::sync::Arc<RuleEngine>,
         queue_manager: std::sync::Arc<dyn GenericQueueManager>,
         emitter: std::sync::Arc<Emitter>,
+        rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+        connections_per_ip: Option<std::sync::Arc<ConnectionLimiter>>,
+        conn_max_per_ip_reach_reply: Reply,
     ) -> anyhow::Result<()> {
         let receiver = vsmtp_protocol::Receiver::<_, ValidationVSL, _, _>::new(
             tcp_stream,
@@ -260,10 +424,20 @@ impl Server {
             config.server.smtp.error.hard_count,
             config.server.message_size_limit,
             config.server.esmtp.pipelining,
-        );
+        )
+        .with_shutdown(shutdown)
+        .with_enhanced_status_codes(config.server.esmtp.enhanced_status_codes)
+        .with_line_length(
+            config.server.smtp.line_length_max,
+            config.server.smtp.line_length_policy,
+        )
+        .with_prescan(config.server.smtp.prescan.clone());
         let smtp_stream = receiver.into_stream(
             |args| async move {
-                Handler::on_accept(
+                let slot = connections_per_ip.map(|limiter| limiter.acquire(args.client_addr.ip()));
+                let client_count_max_per_ip = config.server.client_count_max_per_ip;
+
+                let (mut handler, mut ctx, mut reply) = Handler::on_accept(
                     args,
                     rule_engine,
                     config,
@@ -271,7 +445,24 @@ impl Server {
                     queue_manager,
                     emitter,
                     BasicParser::default,
-                )
+                    rate_limiter,
+                );
+                if let Some(listener) = listener {
+                    handler.set_listener(listener);
+                }
+                match slot {
+                    Some(Some(slot)) => handler.set_connection_slot(slot),
+                    Some(None) => {
+                        tracing::warn!(
+                            max = client_count_max_per_ip,
+                            "Connection count max of the client address reached, rejecting connection.",
+                        );
+                        ctx.deny();
+                        reply = Some(conn_max_per_ip_reach_reply);
+                    }
+                    None => (),
+                }
+                (handler, ctx, reply)
             },
             args.client_addr,
             args.server_addr,
@@ -29,14 +29,14 @@ use vsmtp_common::{
 use vsmtp_rule_engine::{ExecutionStage, RuleEngine};
 
 pub(super) async fn start<Q: GenericQueueManager + Sized + 'static>(
-    rule_engine: std::sync::Arc<RuleEngine>,
+    rule_engine: std::sync::Arc<arc_swap::ArcSwap<RuleEngine>>,
     queue_manager: std::sync::Arc<Q>,
     emitter: std::sync::Arc<Emitter>,
     mut receiver: scheduler::Receiver,
 ) {
     let working_receiver = receiver.as_stream().map(|pm| {
         tokio::spawn(handle_one(
-            rule_engine.clone(),
+            rule_engine.load_full(),
             queue_manager.clone(),
             pm,
             emitter.clone(),
@@ -14,10 +14,7 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use vsmtp_common::{
-    ClientName, ConnectProperties, ContextFinished, FinishedProperties, HeloProperties,
-    MailFromProperties, RcptToProperties, TransactionType,
-};
+use vsmtp_common::{ClientName, ContextFinished, TransactionType};
 use vsmtp_config::Config;
 use vsmtp_mail_parser::MessageBody;
 
@@ -101,35 +98,13 @@ pub fn with_tls() -> Config {
 ///
 #[must_use]
 pub fn local_ctx() -> ContextFinished {
-    ContextFinished {
-        connect: ConnectProperties {
-            connect_timestamp: time::OffsetDateTime::now_utc(),
-            client_addr: "127.0.0.1:25".parse().expect(""),
-            server_addr: "127.0.0.1:5977".parse().expect(""),
-            server_name: "testserver.com".parse().expect(""),
-            connect_uuid: uuid::Uuid::new_v4(),
-            auth: None,
-            tls: None,
-            skipped: None,
-        },
-        helo: HeloProperties {
-            client_name: ClientName::Domain("client.testserver.com".parse().expect("")),
-            using_deprecated: false,
-        },
-        mail_from: MailFromProperties {
-            mail_timestamp: time::OffsetDateTime::now_utc(),
-            message_uuid: uuid::Uuid::new_v4(),
-            reverse_path: Some("client@testserver.com".to_string().parse().expect("")),
-            spf: None,
-            utf8: false,
-        },
-        rcpt_to: RcptToProperties {
-            forward_paths: vec!["recipient@testserver.com".to_string().parse().expect("")],
-            delivery: std::collections::HashMap::new(),
-            transaction_type: TransactionType::Internal,
-        },
-        finished: FinishedProperties { dkim: None },
-    }
+    vsmtp_rule_engine::local_context(
+        "testserver.com".parse().expect(""),
+        ClientName::Domain("client.testserver.com".parse().expect("")),
+        Some("client@testserver.com".to_string().parse().expect("")),
+        vec!["recipient@testserver.com".to_string().parse().expect("")],
+        TransactionType::Internal,
+    )
 }
 
 ///
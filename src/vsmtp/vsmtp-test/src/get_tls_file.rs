@@ -20,6 +20,12 @@ pub const fn get_certificate() -> &'static str {
     include_str!("./template/certs/certificate.crt")
 }
 
+///
+#[must_use]
+pub const fn get_ec256_certificate() -> &'static str {
+    include_str!("./template/certs/certificate.ec256.crt")
+}
+
 ///
 #[must_use]
 pub const fn get_rsa_key() -> &'static str {
@@ -23,6 +23,10 @@ macro_rules! run_test {
         expected = $expected:expr
         $(, starttls $( = $server_name_starttls:expr )? => $secured_input:expr)?
         $(, tunnel = $server_name_tunnel:expr)?
+        $(, client_cert = $client_cert:expr)?
+        $(, proxy = $proxy_header:expr)?
+        $(, early_input = $early_input:expr)?
+        $(, kind = $kind:expr)?
         $(, config = $config:expr)?
         $(, config_arc = $config_arc:expr)?
         $(, mail_handler = $mail_handler:expr)?
@@ -31,7 +35,11 @@ macro_rules! run_test {
     ) => {{
         use tokio_rustls::rustls;
         #[allow(dead_code)]
-        async fn upgrade_tls(server_name: &str, stream: tokio::net::TcpStream) -> tokio_rustls::client::TlsStream<tokio::net::TcpStream> {
+        async fn upgrade_tls(
+            server_name: &str,
+            client_cert: Option<(&str, &str)>,
+            stream: tokio::net::TcpStream
+        ) -> tokio_rustls::client::TlsStream<tokio::net::TcpStream> {
             struct CertVerifier {
                 webpki: rustls::client::WebPkiVerifier,
             }
@@ -83,15 +91,25 @@ macro_rules! run_test {
                 root_store.add(&i).unwrap();
             }
 
-            let client_config = std::sync::Arc::new(rustls::ClientConfig::builder()
+            let client_config = rustls::ClientConfig::builder()
                 .with_safe_defaults()
                 .with_custom_certificate_verifier(std::sync::Arc::new(
                     CertVerifier {
                         webpki: rustls::client::WebPkiVerifier::new(root_store, None),
                     }
-                ))
-                .with_no_client_auth()
-            );
+                ));
+            let client_config = std::sync::Arc::new(match client_cert {
+                Some((certificate, private_key)) => {
+                    let tls = vsmtp_config::field::FieldServerVirtualTls::from_path(
+                        certificate,
+                        private_key,
+                    ).unwrap();
+                    client_config
+                        .with_single_cert(tls.certificate.inner, tls.private_key.inner)
+                        .unwrap()
+                }
+                None => client_config.with_no_client_auth(),
+            });
 
             let connector = tokio_rustls::TlsConnector::from(client_config.clone());
             connector
@@ -111,6 +129,12 @@ macro_rules! run_test {
         $( let secured_input: Vec<String> = $secured_input.into_iter().map(|s| s.to_string()).collect::<Vec<_>>(); )?
 
         $( let server_name: &str = $server_name_tunnel; )?
+        #[allow(unused_variables)]
+        let client_cert: Option<(&str, &str)> = {
+            let _client_cert = Option::<(&str, &str)>::None;
+            $( let _client_cert = Some($client_cert); )?
+            _client_cert
+        };
         $( let server_name: &str = {
             #[allow(clippy::no_effect)]
             $secured_input;
@@ -148,7 +172,13 @@ macro_rules! run_test {
                     #[allow(clippy::no_effect)]
                     $server_name_tunnel;
                     vsmtp_protocol::ConnectionKind::Tunneled
-                };)?
+                };)?                                                                $(
+                let _f = || {
+                    #[allow(clippy::no_effect)]
+                    $proxy_header;
+                    vsmtp_protocol::ConnectionKind::Proxied
+                };)?                                                                $(
+                let _f = || $kind;)?
                 _f()
             };
 
@@ -179,7 +209,13 @@ macro_rules! run_test {
                 config.server.smtp.error.hard_count,
                 config.server.message_size_limit,
                 config.server.esmtp.pipelining,
-            );
+            )
+            .with_enhanced_status_codes(config.server.esmtp.enhanced_status_codes)
+            .with_line_length(
+                config.server.smtp.line_length_max,
+                config.server.smtp.line_length_policy,
+            )
+            .with_prescan(config.server.smtp.prescan.clone());
             let smtp_stream = smtp_receiver.into_stream(
                 |args| async move {
                     let smtp_handler = || vsmtp_server::Handler::on_accept(
@@ -207,6 +243,9 @@ macro_rules! run_test {
                         queue_manager,
                         emitter,
                         vsmtp_mail_parser::BasicParser::default,
+                        config.server.smtp.rate_limit.as_ref().map(|rate_limit| {
+                            std::sync::Arc::new(vsmtp_server::RateLimiter::new(rate_limit))
+                        }),
                     );
 
                     let _f = smtp_handler;          $(
@@ -236,9 +275,20 @@ macro_rules! run_test {
                 .await
                 .unwrap();
 
+            $( let stream = {
+                let mut stream = stream;
+                stream.write_all(AsRef::<[u8]>::as_ref(&$proxy_header)).await.unwrap();
+                stream
+            }; )?
+            // NOTE: sent without waiting for the banner, like a client talking too early.
+            $( let stream = {
+                let mut stream = stream;
+                stream.write_all(AsRef::<[u8]>::as_ref(&$early_input)).await.unwrap();
+                stream
+            }; )?
             $( let stream = {
                 #[allow(clippy::no_effect)] $server_name_tunnel;
-                upgrade_tls(server_name, stream).await
+                upgrade_tls(server_name, client_cert, stream).await
             }; )?
             let mut stream = tokio::io::BufReader::new(stream);
 
@@ -266,7 +316,7 @@ macro_rules! run_test {
                     todo!();
                 }
 
-                let stream = upgrade_tls(server_name, stream.into_inner()).await;
+                let stream = upgrade_tls(server_name, client_cert, stream.into_inner()).await;
                 let mut stream = tokio::io::BufReader::new(stream);
 
                 let mut line_to_send = secured_input.iter().cloned();
@@ -305,6 +355,10 @@ macro_rules! run_test {
         expected = $expected:expr
         $(, starttls $( = $server_name_starttls:expr )? => $secured_input:expr)?
         $(, tunnel = $server_name_tunnel:expr)?
+        $(, client_cert = $client_cert:expr)?
+        $(, proxy = $proxy_header:expr)?
+        $(, early_input = $early_input:expr)?
+        $(, kind = $kind:expr)?
         $(, config = $config:expr)?
         $(, config_arc = $config_arc:expr)?
         $(, mail_handler = $mail_handler:expr)?
@@ -318,6 +372,10 @@ macro_rules! run_test {
                 expected = $expected
                 $(, starttls $( = $server_name_starttls )? => $secured_input)?
                 $(, tunnel = $server_name_tunnel)?
+                $(, client_cert = $client_cert)?
+                $(, proxy = $proxy_header)?
+                $(, early_input = $early_input)?
+                $(, kind = $kind)?
                 $(, config = $config)?
                 $(, config_arc = $config_arc)?
                 $(, mail_handler = $mail_handler)?
@@ -418,7 +476,13 @@ macro_rules! run_pipelined_test {
                 config.server.smtp.error.hard_count,
                 config.server.message_size_limit,
                 config.server.esmtp.pipelining,
-            );
+            )
+            .with_enhanced_status_codes(config.server.esmtp.enhanced_status_codes)
+            .with_line_length(
+                config.server.smtp.line_length_max,
+                config.server.smtp.line_length_policy,
+            )
+            .with_prescan(config.server.smtp.prescan.clone());
             let smtp_stream = smtp_receiver.into_stream(
                 |args| async move {
                     let smtp_handler = || vsmtp_server::Handler::on_accept(
@@ -446,6 +510,9 @@ macro_rules! run_pipelined_test {
                         queue_manager,
                         emitter,
                         vsmtp_mail_parser::BasicParser::default,
+                        config.server.smtp.rate_limit.as_ref().map(|rate_limit| {
+                            std::sync::Arc::new(vsmtp_server::RateLimiter::new(rate_limit))
+                        }),
                     );
 
                     let _f = smtp_handler;          $(
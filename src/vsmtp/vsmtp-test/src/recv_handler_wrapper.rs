@@ -18,18 +18,23 @@
 extern crate alloc;
 
 use tokio_rustls::rustls;
-use vsmtp_common::ContextFinished;
-use vsmtp_common::{Reply, Stage};
+use vsmtp_common::{Address, Reply, Stage};
+use vsmtp_common::{ClientName, ContextFinished};
 use vsmtp_mail_parser::MessageBody;
 use vsmtp_protocol::{
-    AuthArgs, AuthError, CallbackWrap, EhloArgs, Error, HeloArgs, MailFromArgs, RcptToArgs,
-    ReceiverContext, ReceiverHandler,
+    AuthArgs, AuthError, CallbackWrap, EhloArgs, Error, ExpnArgs, HeloArgs, HeloCheck, HelpArgs,
+    MailFromArgs, RateLimit, RcptToArgs, ReceiverContext, ReceiverHandler, VrfyArgs, XClientArgs,
 };
 
 // NOTE: could be enhance to allow entry point on each call
 ///
 pub trait OnMessageCompletedHook {
     fn on_message_completed(self, ctx: ContextFinished, msg: MessageBody);
+
+    /// Validate the client name before the inner handler, see [`ReceiverHandler::on_helo_check()`].
+    fn on_helo_check(&self, _client_name: &ClientName, _using_deprecated: bool) -> HeloCheck {
+        HeloCheck::Allow
+    }
 }
 
 impl<F> OnMessageCompletedHook for F
@@ -64,6 +69,18 @@ where
         self.inner.get_stage()
     }
 
+    fn get_message_uuid(&self) -> Option<uuid::Uuid> {
+        self.inner.get_message_uuid()
+    }
+
+    fn get_transaction_id(&self) -> Option<uuid::Uuid> {
+        self.inner.get_transaction_id()
+    }
+
+    fn get_ages(&self) -> (Option<std::time::Duration>, Option<std::time::Duration>) {
+        self.inner.get_ages()
+    }
+
     fn generate_sasl_callback(&self) -> CallbackWrap {
         self.inner.generate_sasl_callback()
     }
@@ -103,6 +120,22 @@ where
         self.inner.on_post_auth(ctx, result).await
     }
 
+    async fn on_helo_check(
+        &mut self,
+        client_name: &ClientName,
+        using_deprecated: bool,
+    ) -> HeloCheck {
+        let check = self.hook.on_helo_check(client_name, using_deprecated);
+        match check {
+            HeloCheck::Allow => {
+                self.inner
+                    .on_helo_check(client_name, using_deprecated)
+                    .await
+            }
+            reject => reject,
+        }
+    }
+
     async fn on_helo(&mut self, ctx: &mut ReceiverContext, args: HeloArgs) -> Reply {
         self.inner.on_helo(ctx, args).await
     }
@@ -111,10 +144,34 @@ where
         self.inner.on_ehlo(ctx, args).await
     }
 
+    async fn on_xclient(&mut self, ctx: &mut ReceiverContext, args: XClientArgs) -> Reply {
+        self.inner.on_xclient(ctx, args).await
+    }
+
+    async fn on_help(&mut self, ctx: &mut ReceiverContext, args: HelpArgs) -> Reply {
+        self.inner.on_help(ctx, args).await
+    }
+
+    async fn on_vrfy(&mut self, ctx: &mut ReceiverContext, args: VrfyArgs) -> Reply {
+        self.inner.on_vrfy(ctx, args).await
+    }
+
+    async fn on_expn(&mut self, ctx: &mut ReceiverContext, args: ExpnArgs) -> Reply {
+        self.inner.on_expn(ctx, args).await
+    }
+
     async fn on_mail_from(&mut self, ctx: &mut ReceiverContext, args: MailFromArgs) -> Reply {
         self.inner.on_mail_from(ctx, args).await
     }
 
+    async fn on_rate_limit(
+        &mut self,
+        client_addr: std::net::SocketAddr,
+        reverse_path: Option<&Address>,
+    ) -> RateLimit {
+        self.inner.on_rate_limit(client_addr, reverse_path).await
+    }
+
     async fn on_rcpt_to(&mut self, ctx: &mut ReceiverContext, args: RcptToArgs) -> Reply {
         self.inner.on_rcpt_to(ctx, args).await
     }
@@ -133,6 +190,12 @@ where
         None
     }
 
+    async fn on_message_completed_lmtp(&mut self, item: Self::Item) -> Vec<(Address, Reply)> {
+        let (ctx, msg) = item;
+        self.hook.clone().on_message_completed(ctx, msg);
+        vec![]
+    }
+
     async fn on_hard_error(&mut self, ctx: &mut ReceiverContext, reply: Reply) -> Reply {
         self.inner.on_hard_error(ctx, reply).await
     }
@@ -141,6 +204,10 @@ where
         self.inner.on_soft_error(ctx, reply).await
     }
 
+    async fn on_early_talker(&mut self) -> Option<Reply> {
+        self.inner.on_early_talker().await
+    }
+
     async fn on_rset(&mut self) -> Reply {
         self.inner.on_rset().await
     }
@@ -22,18 +22,36 @@ mod examples {
     mod message;
 }
 mod protocol {
+    mod access;
+    mod banner;
     mod clair;
+    mod client;
     mod dsn;
+    mod enhanced_status_codes;
+    mod errors;
+    mod greeting_delay;
+    mod help;
+    mod line_length;
+    mod lmtp;
     mod mail_from;
     mod message_max_size;
+    mod noop;
     mod pipelining;
+    mod prescan;
+    mod proxy;
+    mod rate_limit;
+    mod rcpt_limit;
+    mod received_count;
     mod rset;
+    mod session_timeout;
     mod vrfy;
+    mod xclient;
 
     pub mod auth;
     mod helo;
     mod tls {
         //mod cipher_suite;
+        mod client_auth;
         mod starttls;
         mod tunneled;
         mod tunneled_with_auth;
@@ -50,12 +68,21 @@ mod rule_engine {
     // mod todo;
     mod codes;
     mod context;
+    mod dkim;
     mod domains;
     mod dotenv;
+    mod envelop;
+    mod geoip;
     mod getters;
+    mod greylist;
+    mod headers;
+    mod mime;
+    mod mx;
     mod quarantine;
+    mod relay;
     mod rule_default;
     mod rule_triage;
+    mod short_circuit;
 }
 mod server;
 mod vqueue;
@@ -0,0 +1,92 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_config::field::FieldServerSMTPAccess;
+
+fn access_config(allow: &[&str], deny: &[&str], reply: bool) -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.access = FieldServerSMTPAccess {
+        allow: allow.iter().map(|net| net.parse().unwrap()).collect(),
+        deny: deny.iter().map(|net| net.parse().unwrap()).collect(),
+        allow_overrides_deny: true,
+        reply,
+    };
+    config
+}
+
+const RULES: &str = r#"#{
+    connect: [
+      rule "blocked status" || if ctx::connection_blocked() { state::deny() } else { state::next() }
+    ],
+}
+"#;
+
+run_test! {
+    fn access_allowed_address,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    proxy = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 25\r\n",
+    config = access_config(&["192.0.2.1/32"], &["192.0.2.0/24"], true),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(RULES)?.build()),
+}
+
+run_test! {
+    fn access_denied_network,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "554 5.7.1 Connection refused\r\n",
+    ],
+    proxy = b"PROXY TCP4 192.0.2.2 198.51.100.1 56324 25\r\n",
+    config = access_config(&["192.0.2.1/32"], &["192.0.2.0/24"], true),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        connect: [
+          rule "cannot override the access lists" || state::faccept()
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn access_denied_ipv6_range,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = Vec::<&str>::new(),
+    proxy = b"PROXY TCP6 2001:db8::1 2001:db8:ffff::1 56324 25\r\n",
+    config = access_config(&[], &["2001:db8::/32"], false),
+}
+
+run_test! {
+    fn access_outside_ipv6_range,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    proxy = b"PROXY TCP6 2001:db9::1 2001:db8:ffff::1 56324 25\r\n",
+    config = access_config(&[], &["2001:db8::/32"], false),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(RULES)?.build()),
+}
@@ -19,6 +19,7 @@ use crate::run_test;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use vsmtp_common::addr;
+use vsmtp_common::auth::Credentials;
 use vsmtp_common::ContextFinished;
 use vsmtp_mail_parser::MessageBody;
 
@@ -31,7 +32,6 @@ run_test! {
     expected = [
         "220 testserver.com Service ready\r\n",
         "250-testserver.com\r\n",
-        "250-AUTH \r\n",
         "250-8BITMIME\r\n",
         "250-SMTPUTF8\r\n",
         "250-STARTTLS\r\n",
@@ -43,6 +43,32 @@ run_test! {
     config = safe_auth_config()
 }
 
+run_test! {
+    fn anonymous_in_clair_require_tls,
+    input = [
+        "EHLO foo\r\n",
+        "AUTH ANONYMOUS dG9rZW5fYWJjZGVm\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "538 5.7.11 Encryption required for requested authentication mechanism\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = {
+        let mut config = unsafe_auth_config();
+        config.server.esmtp.auth_require_tls = true;
+        config
+    }
+}
+
 run_test! {
     fn plain_in_clair_unsecured,
     input = [
@@ -110,8 +136,8 @@ run_test! {
         "250-PIPELINING\r\n",
         "250-DSN\r\n",
         "250 SIZE 20000000\r\n",
-        &format!("334 {}\r\n", STANDARD.encode("User Name\0")),
-        &format!("334 {}\r\n", STANDARD.encode("Password\0")),
+        "334 VXNlcm5hbWU6\r\n",
+        "334 UGFzc3dvcmQ6\r\n",
         "235 2.7.0 Authentication succeeded\r\n",
         "250 Ok\r\n",
         "250 Ok\r\n",
@@ -121,6 +147,13 @@ run_test! {
     ],
     config = unsafe_auth_config(),
     mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(
+            ctx.connect.auth.unwrap().credentials,
+            Some(Credentials::Verify {
+                authid: "hello".to_string(),
+                authpass: "world".to_string()
+            })
+        );
         assert_eq!(ctx.helo.client_name.to_string(), "client.com");
         assert_eq!(ctx.mail_from.reverse_path, Some(addr!("foo@bar")));
         assert!(ctx.rcpt_to.delivery
@@ -135,6 +168,37 @@ run_test! {
     },
 }
 
+run_test! {
+    fn login_in_clair_unsecured_cancel,
+    input = [
+        "EHLO client.com\r\n",
+        "AUTH LOGIN\r\n",
+        "*\r\n",
+        "AUTH LOGIN\r\n",
+        &format!("{}\r\n", STANDARD.encode("hello")),
+        "*\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5 ANONYMOUS\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "334 VXNlcm5hbWU6\r\n",
+        "501 Authentication canceled by client\r\n",
+        "334 VXNlcm5hbWU6\r\n",
+        "334 UGFzc3dvcmQ6\r\n",
+        "501 Authentication canceled by client\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = unsafe_auth_config()
+}
+
 run_test! {
     fn anonymous_in_clair_unsecured,
     input = [
@@ -386,6 +450,72 @@ run_test! {
     }
 }
 
+const AUTH_USER_RULES: &str = r#"#{
+  authenticate: [
+    rule "known user" || {
+      if auth::credentials().authid == "hello" && auth::credentials().authpass == "world" {
+        state::accept()
+      } else {
+        state::deny()
+      }
+    }
+  ],
+  mail: [
+    rule "auth user" || state::accept(`250 user '${auth::auth_user()}' authenticated: ${auth::is_authenticated()}`)
+  ],
+}
+"#;
+
+run_test! {
+    fn auth_user_at_mail,
+    input = [
+        "EHLO client.com\r\n",
+        &format!("AUTH PLAIN {}\r\n", STANDARD.encode(format!("\0{}\0{}", "hello", "world"))),
+        "MAIL FROM:<foo@bar>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5 ANONYMOUS\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "250 user 'hello' authenticated: true\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = unsafe_auth_config(),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(AUTH_USER_RULES)?.build())
+}
+
+run_test! {
+    fn auth_user_unauthenticated,
+    input = [
+        "EHLO client.com\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5 ANONYMOUS\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 user '' authenticated: false\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = unsafe_auth_config(),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(AUTH_USER_RULES)?.build())
+}
+
 run_test! {
     fn client_must_not_start,
     input = [
@@ -41,7 +41,7 @@ pub fn safe_auth_config() -> Config {
 }
 
 pub fn unsafe_auth_config() -> Config {
-    Config::builder()
+    let mut config = Config::builder()
         .with_version_str("<1.0.0")
         .unwrap()
         .without_path()
@@ -69,7 +69,37 @@ pub fn unsafe_auth_config() -> Config {
         .with_default_app_logs()
         .with_system_dns()
         .without_virtual_entries()
-        .validate()
+        .validate();
+    // Authentication in clair is tested.
+    config.server.esmtp.auth_require_tls = false;
+    config
+}
+
+pub fn oauth_config() -> Config {
+    let mut config = Config::builder()
+        .with_version_str("<1.0.0")
+        .unwrap()
+        .without_path()
+        .with_server_name("testserver.com".parse::<vsmtp_common::Domain>().unwrap())
+        .with_user_group_and_default_system("root", "root")
+        .unwrap()
+        .with_ipv4_localhost()
+        .with_default_logs_settings()
+        .with_spool_dir_and_default_queues("./tmp/spool")
+        .without_tls_support()
+        .with_default_smtp_options()
+        .with_default_smtp_error_handler()
+        .with_auth(true, vec![Mechanism::OAuthBearer, Mechanism::XOAuth2], -1)
+        .with_app_at_location("./tmp/app")
+        .with_vsl("./src/template/auth/domain-enabled")
+        .with_default_app_logs()
+        .with_system_dns()
+        .without_virtual_entries()
+        .validate();
+    // Authentication in clair is tested.
+    config.server.esmtp.auth_require_tls = false;
+    config
 }
 
 mod basic;
+mod oauth;
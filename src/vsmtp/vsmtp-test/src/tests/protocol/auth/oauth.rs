@@ -0,0 +1,170 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::oauth_config;
+use crate::run_test;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use vsmtp_common::addr;
+use vsmtp_common::ContextFinished;
+use vsmtp_mail_parser::MessageBody;
+
+const VALID_TOKEN: &str = "ya29.vF9dft4qmTc2Nvb3RlckBhdHRhdmlzdGEuY29tCg";
+const EXPIRED_TOKEN: &str = "ya29.dGhpcyB0b2tlbiBoYXMgZXhwaXJlZAo";
+
+run_test! {
+    fn oauthbearer_valid_token,
+    input = [
+        "EHLO client.com\r\n",
+        &format!(
+            "AUTH OAUTHBEARER {}\r\n",
+            STANDARD.encode(format!("n,a=hello@testserver.com,\x01auth=Bearer {VALID_TOKEN}\x01\x01"))
+        ),
+        "MAIL FROM:<foo@bar>\r\n",
+        "RCPT TO:<joe@doe>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH OAUTHBEARER XOAUTH2\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = oauth_config(),
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.reverse_path, Some(addr!("foo@bar")));
+    },
+}
+
+run_test! {
+    fn oauthbearer_expired_token,
+    input = [
+        "EHLO client.com\r\n",
+        &format!(
+            "AUTH OAUTHBEARER {}\r\n",
+            STANDARD.encode(format!("n,a=hello@testserver.com,\x01auth=Bearer {EXPIRED_TOKEN}\x01\x01"))
+        ),
+        // the client acknowledges the error with a dummy response.
+        &format!("{}\r\n", STANDARD.encode("\x01")),
+        "MAIL FROM:<foo@bar>\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH OAUTHBEARER XOAUTH2\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        &format!("334 {}\r\n", STANDARD.encode(r#"{"status":"invalid_token"}"#)),
+        "535 5.7.8 Authentication credentials invalid\r\n"
+    ],
+    config = oauth_config()
+}
+
+run_test! {
+    fn oauthbearer_malformed_gs2_header,
+    input = [
+        "EHLO client.com\r\n",
+        &format!(
+            "AUTH OAUTHBEARER {}\r\n",
+            STANDARD.encode(format!("x,a=hello@testserver.com\x01auth=Bearer {VALID_TOKEN}\x01\x01"))
+        ),
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH OAUTHBEARER XOAUTH2\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "501 5.5.2 Invalid, malformed authentication data\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = oauth_config()
+}
+
+run_test! {
+    fn xoauth2_valid_token,
+    input = [
+        "EHLO client.com\r\n",
+        &format!(
+            "AUTH XOAUTH2 {}\r\n",
+            STANDARD.encode(format!("user=hello@testserver.com\x01auth=Bearer {VALID_TOKEN}\x01\x01"))
+        ),
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH OAUTHBEARER XOAUTH2\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = oauth_config()
+}
+
+run_test! {
+    fn xoauth2_expired_token,
+    input = [
+        "EHLO client.com\r\n",
+        &format!(
+            "AUTH XOAUTH2 {}\r\n",
+            STANDARD.encode(format!("user=hello@testserver.com\x01auth=Bearer {EXPIRED_TOKEN}\x01\x01"))
+        ),
+        "\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH OAUTHBEARER XOAUTH2\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        &format!("334 {}\r\n", STANDARD.encode(r#"{"status":"401","schemes":"bearer"}"#)),
+        "535 5.7.8 Authentication credentials invalid\r\n"
+    ],
+    config = oauth_config()
+}
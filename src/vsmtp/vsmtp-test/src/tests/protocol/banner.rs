@@ -0,0 +1,58 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+
+run_test! {
+    fn multiline_banner,
+    input = [
+        "HELO foo\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220-testserver.com ESMTP\r\n",
+        "220-Unsolicited bulk email is not accepted\r\n",
+        "220 Contact postmaster@testserver.com\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.smtp.banner = [
+            "{hostname} ESMTP",
+            "Unsolicited bulk email is not accepted",
+            "Contact postmaster@{hostname}",
+        ].join("\n");
+        config
+    },
+}
+
+run_test! {
+    fn banner_with_version,
+    input = [
+        "QUIT\r\n",
+    ],
+    expected = [
+        format!("220 testserver.com vSMTP {}\r\n", env!("CARGO_PKG_VERSION")),
+        "221 Service closing transmission channel\r\n".to_owned(),
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.smtp.banner = "{hostname} vSMTP {version}".to_owned();
+        config
+    },
+}
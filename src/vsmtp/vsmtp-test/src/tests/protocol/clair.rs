@@ -112,7 +112,7 @@ run_test! {
     input = ["HELP\r\n", "QUIT\r\n"],
     expected = [
         "220 testserver.com Service ready\r\n",
-        "214 joining us https://viridit.com/support\r\n",
+        "214 Commands supported: HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT\r\n",
         "221 Service closing transmission channel\r\n"
     ]
 }
@@ -404,6 +404,15 @@ async fn test_receiver_9() {
             "HELP\r\n",
             "aieari\r\n",
             "not a valid smtp command\r\n",
+            // NOTE: only the consecutive errors are counted, `NOOP` and `HELP` reset the count.
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
+            "foo\r\n",
         ],
         expected = [
             "220 testserver.com Service ready\r\n",
@@ -416,10 +425,18 @@ async fn test_receiver_9() {
             "503 Bad sequence of commands\r\n",
             "500 Syntax error command unrecognized\r\n",
             "500 Syntax error command unrecognized\r\n",
-            "214 joining us https://viridit.com/support\r\n",
+            "214 Commands supported: HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT\r\n",
             "500 Syntax error command unrecognized\r\n",
-            "451-Syntax error command unrecognized\r\n",
-            "451 Too many errors from the client\r\n"
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "500 Syntax error command unrecognized\r\n",
+            "421-Syntax error command unrecognized\r\n",
+            "421 Too many errors from the client\r\n"
         ],
         config_arc = config.clone(),
     };
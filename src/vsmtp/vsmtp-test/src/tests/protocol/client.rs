@@ -0,0 +1,324 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::auth::unsafe_auth_config;
+use crate::config::{local_test, with_tls};
+use tokio_rustls::rustls;
+use vsmtp_common::{addr, auth::Mechanism};
+use vsmtp_config::{field::FieldServerVirtualTls, Config};
+use vsmtp_protocol::{Client, ConnectionKind};
+
+const MESSAGE: &[u8] = b"Subject: client\r\n\r\n.leading dot\r\n";
+
+/// Serve a single connection with `vSMTP` on the loopback interface.
+pub(super) async fn serve_once(config: Config, kind: ConnectionKind) -> tokio::net::TcpStream {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let config = std::sync::Arc::new(config);
+        let queue_manager = <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(
+            config.clone(),
+            vec![],
+        )
+        .unwrap();
+        let resolvers =
+            std::sync::Arc::new(vsmtp_config::DnsResolvers::from_config(&config).unwrap());
+        let (emitter, _working_rx, _delivery_rx) = vsmtp_server::scheduler::init(1, 1);
+        let rule_engine = std::sync::Arc::new(
+            vsmtp_rule_engine::RuleEngine::new(config.clone(), resolvers, queue_manager.clone())
+                .unwrap(),
+        );
+        let tls_config = config.server.tls.as_ref().map(|tls| {
+            std::sync::Arc::new(
+                vsmtp_config::get_rustls_config(tls, &config.server.r#virtual).unwrap(),
+            )
+        });
+
+        let (client_stream, client_addr) = listener.accept().await.unwrap();
+
+        let receiver = vsmtp_protocol::Receiver::<_, vsmtp_server::ValidationVSL, _, _>::new(
+            client_stream,
+            kind,
+            config.server.smtp.error.soft_count,
+            config.server.smtp.error.hard_count,
+            config.server.message_size_limit,
+            config.server.esmtp.pipelining,
+        );
+        let smtp_stream = receiver.into_stream(
+            |args| async move {
+                vsmtp_server::Handler::on_accept(
+                    args,
+                    rule_engine,
+                    config.clone(),
+                    tls_config,
+                    queue_manager,
+                    emitter,
+                    vsmtp_mail_parser::BasicParser::default,
+                    None,
+                )
+            },
+            client_addr,
+            server_addr,
+            time::OffsetDateTime::now_utc(),
+            uuid::Uuid::new_v4(),
+        );
+        tokio::pin!(smtp_stream);
+
+        while matches!(
+            tokio_stream::StreamExt::next(&mut smtp_stream).await,
+            Some(Ok(()))
+        ) {}
+    });
+
+    tokio::net::TcpStream::connect(server_addr).await.unwrap()
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn send_message() {
+    let stream = serve_once(local_test(), ConnectionKind::Relay).await;
+    let mut client = Client::new(stream, ConnectionKind::Relay);
+
+    assert_eq!(
+        client.read_greeting().await.unwrap().to_string(),
+        "220 testserver.com Service ready\r\n"
+    );
+    assert_eq!(client.ehlo("client.com").await.unwrap().code().value(), 250);
+    assert_eq!(
+        client.extensions(),
+        [
+            "8BITMIME",
+            "SMTPUTF8",
+            "STARTTLS",
+            "PIPELINING",
+            "DSN",
+            "SIZE 20000000"
+        ]
+    );
+    assert!(client.has_extension("pipelining"));
+    assert_eq!(client.message_size_max(), Some(20_000_000));
+    assert!(client.auth_mechanisms().is_empty());
+
+    let reply = client
+        .mail_from(Some(&addr!("john@doe")), Some(MESSAGE.len()))
+        .await
+        .unwrap();
+    assert_eq!(reply.to_string(), "250 Ok\r\n");
+    let reply = client.rcpt_to(&addr!("aa@bb")).await.unwrap();
+    assert_eq!(reply.to_string(), "250 Ok\r\n");
+
+    let replies = client.data(MESSAGE).await.unwrap();
+    assert_eq!(
+        replies.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["250 Ok\r\n"]
+    );
+
+    assert_eq!(
+        client.quit().await.unwrap().to_string(),
+        "221 Service closing transmission channel\r\n"
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn lmtp_reply_for_each_recipient() {
+    let stream = serve_once(local_test(), ConnectionKind::Lmtp).await;
+    let mut client = Client::new(stream, ConnectionKind::Lmtp);
+
+    assert!(!client.read_greeting().await.unwrap().code().is_error());
+    assert_eq!(client.ehlo("client.com").await.unwrap().code().value(), 250);
+
+    assert!(!client
+        .mail_from(None, None)
+        .await
+        .unwrap()
+        .code()
+        .is_error());
+    assert!(!client
+        .rcpt_to(&addr!("aa@bb"))
+        .await
+        .unwrap()
+        .code()
+        .is_error());
+    assert!(!client
+        .rcpt_to(&addr!("bb@bb"))
+        .await
+        .unwrap()
+        .code()
+        .is_error());
+
+    let replies = client.data(MESSAGE).await.unwrap();
+    assert_eq!(
+        replies.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["250 Ok\r\n", "250 Ok\r\n"]
+    );
+    assert!(!client.quit().await.unwrap().code().is_error());
+}
+
+#[rstest::rstest]
+#[case::plain(Mechanism::Plain, "world", "235 2.7.0 Authentication succeeded\r\n")]
+#[case::login(Mechanism::Login, "world", "235 2.7.0 Authentication succeeded\r\n")]
+#[case::plain_invalid(
+    Mechanism::Plain,
+    "wrong",
+    "535 5.7.8 Authentication credentials invalid\r\n"
+)]
+#[case::login_invalid(
+    Mechanism::Login,
+    "wrong",
+    "535 5.7.8 Authentication credentials invalid\r\n"
+)]
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn authenticate(
+    #[case] mechanism: Mechanism,
+    #[case] authpass: &str,
+    #[case] expected: &str,
+) {
+    let stream = serve_once(unsafe_auth_config(), ConnectionKind::Relay).await;
+    let mut client = Client::new(stream, ConnectionKind::Relay);
+
+    assert!(!client.read_greeting().await.unwrap().code().is_error());
+    assert!(!client.ehlo("client.com").await.unwrap().code().is_error());
+    assert_eq!(
+        client.auth_mechanisms(),
+        [
+            Mechanism::Plain,
+            Mechanism::Login,
+            Mechanism::CramMd5,
+            Mechanism::Anonymous
+        ]
+    );
+
+    assert_eq!(
+        client
+            .auth(mechanism, "hello", authpass)
+            .await
+            .unwrap()
+            .to_string(),
+        expected
+    );
+}
+
+struct AcceptAny;
+
+impl rustls::client::ServerCertVerifier for AcceptAny {
+    fn verify_server_cert(
+        &self,
+        _: &rustls::Certificate,
+        _: &[rustls::Certificate],
+        _: &rustls::ServerName,
+        _: &mut dyn Iterator<Item = &[u8]>,
+        _: &[u8],
+        _: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn starttls() {
+    let mut config = with_tls();
+    config.server.tls.as_mut().unwrap().root = Some(
+        FieldServerVirtualTls::from_path(
+            "src/template/certs/certificate.crt",
+            "src/template/certs/private_key.rsa.key",
+        )
+        .unwrap(),
+    );
+    let stream = serve_once(config, ConnectionKind::Relay).await;
+    let mut client = Client::new(stream, ConnectionKind::Relay);
+
+    assert!(!client.read_greeting().await.unwrap().code().is_error());
+    assert!(!client.ehlo("client.com").await.unwrap().code().is_error());
+    assert!(client.has_extension("STARTTLS"));
+
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(std::sync::Arc::new(AcceptAny))
+        .with_no_client_auth();
+    let mut client = client
+        .starttls(
+            std::sync::Arc::new(config),
+            rustls::ServerName::try_from("testserver.com").unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert!(client.extensions().is_empty());
+    assert!(!client.ehlo("client.com").await.unwrap().code().is_error());
+    assert!(!client.has_extension("STARTTLS"));
+    assert!(client.has_extension("REQUIRETLS"));
+
+    assert!(!client
+        .mail_from(Some(&addr!("john@doe")), None)
+        .await
+        .unwrap()
+        .code()
+        .is_error());
+    assert!(!client
+        .rcpt_to(&addr!("aa@bb"))
+        .await
+        .unwrap()
+        .code()
+        .is_error());
+    assert_eq!(
+        client.data(MESSAGE).await.unwrap()[0].to_string(),
+        "250 Ok\r\n"
+    );
+    assert!(!client.quit().await.unwrap().code().is_error());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn tunneled() {
+    let mut config = with_tls();
+    config.server.tls.as_mut().unwrap().root = Some(
+        FieldServerVirtualTls::from_path(
+            "src/template/certs/certificate.crt",
+            "src/template/certs/private_key.rsa.key",
+        )
+        .unwrap(),
+    );
+    let stream = serve_once(config, ConnectionKind::Tunneled).await;
+
+    // NOTE: the server waits for the TLS handshake, nothing is sent in plaintext.
+    let mut buffer = [0; 1];
+    assert!(tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        stream.peek(&mut buffer)
+    )
+    .await
+    .is_err());
+
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(std::sync::Arc::new(AcceptAny))
+        .with_no_client_auth();
+    let mut client = Client::new(stream, ConnectionKind::Tunneled)
+        .upgrade_tls(
+            std::sync::Arc::new(config),
+            rustls::ServerName::try_from("testserver.com").unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.read_greeting().await.unwrap().to_string(),
+        "220 testserver.com Service ready\r\n"
+    );
+    assert!(!client.ehlo("client.com").await.unwrap().code().is_error());
+    assert!(!client.has_extension("STARTTLS"));
+    assert!(client.has_extension("REQUIRETLS"));
+    assert!(!client.quit().await.unwrap().code().is_error());
+}
@@ -16,6 +16,10 @@
 */
 
 use crate::run_test;
+use vsmtp_common::{
+    addr, ContextFinished, DsnReturn, NotifyOn, OriginalRecipient, RecipientDsn,
+};
+use vsmtp_mail_parser::MessageBody;
 
 run_test! {
     fn submission,
@@ -108,6 +112,79 @@ run_test! {
         "250 Ok\r\n",
         "221 Service closing transmission channel\r\n",
     ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.envelop_id.as_deref(), Some("QQ314159"));
+        assert_eq!(ctx.mail_from.ret, Some(DsnReturn::Headers));
+        assert_eq!(
+            ctx.rcpt_to.dsn[&addr!("Carol@Ivory.EDU")],
+            RecipientDsn {
+                notify_on: NotifyOn::Some { success: false, failure: true, delay: false },
+                original_forward_path: Some(OriginalRecipient {
+                    addr_type: "rfc822".to_owned(),
+                    mailbox: addr!("Carol@Ivory.EDU"),
+                }),
+            }
+        );
+        assert_eq!(
+            ctx.rcpt_to.dsn[&addr!("Dana@Ivory.EDU")].notify_on,
+            NotifyOn::Some { success: true, failure: true, delay: false }
+        );
+    }
+}
+
+run_test! {
+    fn rule_engine_accessors,
+    input = [
+        "EHLO Example.ORG\r\n",
+        "MAIL FROM:<Alice@Example.ORG> RET=HDRS ENVID=QQ314159\r\n",
+        "RCPT TO:<Dana@Ivory.EDU> NOTIFY=SUCCESS,FAILURE ORCPT=rfc822;Dana@Ivory.EDU\r\n",
+        "RCPT TO:<Fred@Bombs.AF.MIL> NOTIFY=NEVER\r\n",
+        "RCPT TO:<George@Tax-ME.GOV>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    hierarchy_builder = |builder| {
+        Ok(builder.add_root_filter_rules(r#"#{
+          mail: [
+            rule "envelope dsn" || {
+              if ctx::dsn_envid() == "QQ314159" && ctx::dsn_ret() == "hdrs" {
+                state::next()
+              } else {
+                state::deny()
+              }
+            }
+          ],
+          rcpt: [
+            rule "recipient dsn" || {
+              let expected = switch ctx::rcpt().local_part {
+                "Dana" => [["success", "failure"], "rfc822;Dana@Ivory.EDU"],
+                "Fred" => [["never"], ()],
+                "George" => [["failure"], ()],
+              };
+              if ctx::dsn_notify(ctx::rcpt()) == expected[0] && ctx::dsn_orcpt(ctx::rcpt()) == expected[1] {
+                state::next()
+              } else {
+                state::deny()
+              }
+            }
+          ],
+        }
+      "#).unwrap().build())
+    }
 }
 
 /*
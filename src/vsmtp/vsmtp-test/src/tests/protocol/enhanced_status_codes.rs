@@ -0,0 +1,118 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+
+fn enhanced_status_codes_config() -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.esmtp.enhanced_status_codes = true;
+    config
+}
+
+run_test! {
+    fn enhanced_status_codes_after_ehlo,
+    input = [
+        "EHLO foo\r\n",
+        "RCPT TO:<b@c>\r\n",
+        "MAIL FROM:<a@b>\r\n",
+        "RCPT TO:<galvin@>\r\n",
+        "RCPT TO:<b@c>\r\n",
+        "DATA\r\n",
+        concat!(
+            "from: a b <a@b>\r\n",
+            "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+            "\r\n",
+            "mail content wow\r\n",
+            ".\r\n",
+        ),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-ENHANCEDSTATUSCODES\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "503 5.5.1 Bad sequence of commands\r\n",
+        "250 2.1.0 Ok\r\n",
+        "553 5.1.7 The address <galvin@> is not a valid RFC-5321 address\r\n",
+        "250 2.1.5 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 2.0.0 Ok\r\n",
+        "221 2.0.0 Service closing transmission channel\r\n"
+    ],
+    config = enhanced_status_codes_config(),
+}
+
+run_test! {
+    fn no_enhanced_status_codes_after_helo,
+    input = [
+        "HELO foo\r\n",
+        "RCPT TO:<b@c>\r\n",
+        "MAIL FROM:<a@b>\r\n",
+        "RCPT TO:<galvin@>\r\n",
+        "RCPT TO:<b@c>\r\n",
+        "DATA\r\n",
+        concat!(
+            "from: a b <a@b>\r\n",
+            "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+            "\r\n",
+            "mail content wow\r\n",
+            ".\r\n",
+        ),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "503 Bad sequence of commands\r\n",
+        "250 Ok\r\n",
+        "553 The address <galvin@> is not a valid RFC-5321 address\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = enhanced_status_codes_config(),
+}
+
+run_test! {
+    fn enhanced_status_codes_disabled,
+    input = [
+        "EHLO foo\r\n",
+        "MAIL FROM:<a@b>\r\n",
+        "RCPT TO:<galvin@>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "553 5.1.7 The address <galvin@> is not a valid RFC-5321 address\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+}
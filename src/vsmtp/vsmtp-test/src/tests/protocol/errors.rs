@@ -0,0 +1,123 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+
+run_test! {
+    fn too_many_errors,
+    input = [
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "421-Syntax error command unrecognized\r\n",
+        "421 Too many errors from the client\r\n",
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.error.soft_count = -1;
+        config.server.smtp.error.hard_count = 10;
+        config
+    },
+}
+
+run_test! {
+    fn errors_reset_on_success,
+    input = [
+        "foo\r\n",
+        "foo\r\n",
+        "NOOP\r\n",
+        "foo\r\n",
+        "foo\r\n",
+        "foo\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "250 Ok\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "421-Syntax error command unrecognized\r\n",
+        "421 Too many errors from the client\r\n",
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.error.soft_count = -1;
+        config.server.smtp.error.hard_count = 3;
+        config
+    },
+}
+
+run_test! {
+    fn errors_not_consecutive,
+    input = [
+        "RCPT TO:<bar@foo>\r\n",
+        "MAIL FROM: <foo@bar>\r\n",
+        "EHLO\r\n",
+        "NOOP\r\n",
+        "azeai\r\n",
+        "STARTTLS\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "EHLO\r\n",
+        "HELP\r\n",
+        "aieari\r\n",
+        "not a valid smtp command\r\n",
+        "foo\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "503 Bad sequence of commands\r\n",
+        "503 Bad sequence of commands\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "250 Ok\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "454 TLS not available due to temporary reason\r\n",
+        "503 Bad sequence of commands\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "214 Commands supported: HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.error.soft_count = -1;
+        config.server.smtp.error.hard_count = 5;
+        config
+    },
+}
@@ -0,0 +1,77 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_config::field::FieldServerSMTPGreetingDelay;
+
+const DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+fn greeting_delay_config(reject: bool) -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.greeting_delay = Some(FieldServerSMTPGreetingDelay {
+        delay: DELAY,
+        reject,
+        reply: "554 5.5.1 Protocol error, data received before the banner\r\n"
+            .parse()
+            .unwrap(),
+    });
+    config
+}
+
+run_test! {
+    fn early_talker_rejected,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "554 5.5.1 Protocol error, data received before the banner\r\n",
+    ],
+    early_input = "HELO foo\r\n",
+    config = greeting_delay_config(true),
+}
+
+run_test! {
+    fn early_talker_not_rejected,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+    ],
+    early_input = "HELO foo\r\n",
+    config = greeting_delay_config(false),
+}
+
+#[tokio::test]
+async fn well_behaved_client_delayed() {
+    let before_test = std::time::Instant::now();
+    run_test! {
+        input = [
+            "HELO foo\r\n",
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+        config = greeting_delay_config(true),
+    };
+
+    assert!(before_test.elapsed() >= DELAY);
+}
@@ -19,14 +19,7 @@ use crate::run_test;
 use vsmtp_common::ClientName;
 use vsmtp_common::ContextFinished;
 use vsmtp_mail_parser::MessageBody;
-
-fn to_args(client_name: &ClientName) -> String {
-    match client_name {
-        ClientName::Domain(domain) => domain.to_string(),
-        ClientName::Ip4(ip) => format!("[{ip}]"),
-        ClientName::Ip6(ip) => format!("[IPv6:{ip}]"),
-    }
-}
+use vsmtp_protocol::HeloCheck;
 
 macro_rules! to_tab {
     ($e:expr) => {
@@ -78,6 +71,38 @@ macro_rules! to_tab {
     to_tab!(["{verb} 0.0.0.0\r\n"]),
     to_tab!(["501 Syntax error in parameters or arguments\r\n"]),
 )]
+#[case::bad_literal4(
+    None,
+    None,
+    to_tab!(["{verb} [192.0.2.256]\r\n"]),
+    to_tab!(["501 Syntax error in parameters or arguments\r\n"]),
+)]
+#[case::bad_literal6(
+    None,
+    None,
+    to_tab!(["{verb} [IPv6:2001:db8::g]\r\n"]),
+    to_tab!(["501 Syntax error in parameters or arguments\r\n"]),
+)]
+#[case::bare_ip4_helo(
+    Some("HELO"),
+    None,
+    to_tab!(["{verb} 192.0.2.1\r\n"]),
+    to_tab!(["250 Ok\r\n"]),
+)]
+#[case::bare_ip4_ehlo(
+    Some("EHLO"),
+    None,
+    to_tab!(["{verb} 192.0.2.1\r\n"]),
+    to_tab!([
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+    ]),
+)]
 #[case::two_word(
     None,
     None,
@@ -92,7 +117,7 @@ macro_rules! to_tab {
 )]
 #[case(
     Some("HELO"),
-    Some(ClientName::Domain("mydomain.com".parse().unwrap())),
+    None,
     to_tab!([
         "{verb} {client_name}\r\n",
         "MAIL FROM:<mailbox@mydomain.com>\r\n",
@@ -163,7 +188,7 @@ fn test(
             input = input.iter()
                 .map(|s| s
                     .replace("{verb}", verb)
-                    .replace("{client_name}", &to_args(&client_name))
+                    .replace("{client_name}", &client_name.to_string())
                 )
                 .collect::<Vec<String>>(),
             expected = std::iter::once("220 testserver.com Service ready\r\n".to_string())
@@ -188,3 +213,61 @@ fn test(
         };
     });
 }
+
+#[derive(Clone)]
+struct RejectAddressLiteral;
+
+impl crate::recv_handler_wrapper::OnMessageCompletedHook for RejectAddressLiteral {
+    fn on_message_completed(self, ctx: ContextFinished, _: MessageBody) {
+        assert_eq!(
+            ctx.helo.client_name,
+            ClientName::Domain("mail.example.com".parse().unwrap())
+        );
+    }
+
+    fn on_helo_check(&self, client_name: &ClientName, _: bool) -> HeloCheck {
+        match client_name {
+            ClientName::Domain(_) => HeloCheck::Allow,
+            ClientName::Ip4(_) | ClientName::Ip6(_) => HeloCheck::Reject(
+                "550 5.7.1 Client name must be a domain\r\n"
+                    .parse()
+                    .unwrap(),
+            ),
+        }
+    }
+}
+
+run_test! {
+    fn helo_check_reject_ip,
+    input = [
+        "HELO 10.0.0.1\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "550 5.7.1 Client name must be a domain\r\n",
+    ],
+    mail_handler = RejectAddressLiteral
+}
+
+run_test! {
+    fn helo_check_allow_domain,
+    input = [
+        "HELO mail.example.com\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    mail_handler = RejectAddressLiteral
+}
@@ -0,0 +1,95 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_common::ContextFinished;
+use vsmtp_mail_parser::MessageBody;
+
+run_test! {
+    fn help_bare,
+    input = [
+        "HELP\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "214 Commands supported: HELO EHLO MAIL RCPT DATA RSET NOOP VRFY EXPN HELP QUIT\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+}
+
+run_test! {
+    fn help_command,
+    input = [
+        "HELP MAIL\r\n",
+        "help rcpt\r\n",
+        "HELP TURN\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "214 MAIL FROM:<reverse-path> [SIZE=<size>] [BODY=7BIT|8BITMIME] [SMTPUTF8] [RET=FULL|HDRS] [ENVID=<id>] [REQUIRETLS]\r\n",
+        "214 RCPT TO:<forward-path> [NOTIFY=NEVER|SUCCESS,FAILURE,DELAY] [ORCPT=<type>;<address>]\r\n",
+        "214 No help available, send HELP for the supported commands\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+}
+
+run_test! {
+    fn help_custom_text,
+    input = [
+        "HELP\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "214-Contact postmaster@testserver.com\r\n",
+        "214 for any question\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.smtp.help = "Contact postmaster@testserver.com\nfor any question".to_owned();
+        config
+    },
+}
+
+run_test! {
+    fn help_during_transaction,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "HELP DATA\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "214 DATA, then the message ended by <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.rcpt_to.forward_paths.len(), 1);
+    },
+}
@@ -0,0 +1,90 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms &of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_protocol::LineLengthPolicy;
+
+fn line_length_config(policy: LineLengthPolicy) -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.line_length_policy = policy;
+    config
+}
+
+run_test! {
+    fn line_too_long_strict,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &format!(
+            concat!(
+                "from: john doe <john@doe>\r\n",
+                "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+                "\r\n",
+                "{}\r\n",
+                ".\r\n",
+            ),
+            // NOTE: 2000 octets with the CRLF.
+            "X".repeat(1998)
+        ),
+        "MAIL FROM:<john@doe>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "500 Line too long\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = line_length_config(LineLengthPolicy::Strict),
+}
+
+run_test! {
+    fn line_too_long_logged_by_default,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &format!(
+            concat!(
+                "from: john doe <john@doe>\r\n",
+                "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+                "\r\n",
+                "{}\r\n",
+                ".\r\n",
+            ),
+            // NOTE: 2000 octets with the CRLF.
+            "X".repeat(1998)
+        ),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
@@ -0,0 +1,164 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use vsmtp_protocol::ConnectionKind;
+
+// NOTE: the client send the next line after each reply received, the empty lines
+// are used to read the replies of the recipients.
+
+run_test! {
+    fn reply_for_each_recipient,
+    input = [
+        "LHLO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<bb@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    kind = ConnectionKind::Lmtp,
+}
+
+run_test! {
+    fn no_reply_for_rejected_recipient,
+    input = [
+        "LHLO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<rejected@bb>\r\n",
+        "RCPT TO:<cc@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "550 unknown recipient\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    kind = ConnectionKind::Lmtp,
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        rcpt: [
+          rule "reject recipient" || {
+            if ctx::rcpt().local_part is "rejected" {
+              state::reject("550 unknown recipient\r\n")
+            } else {
+              state::next()
+            }
+          }
+        ],
+    }
+    "#)?.build()),
+}
+
+run_test! {
+    fn denied_message_reply_for_each_recipient,
+    input = [
+        "LHLO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<bb@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "554 message refused\r\n",
+        "554 message refused\r\n",
+    ],
+    kind = ConnectionKind::Lmtp,
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        preq: [
+          rule "deny" || state::deny("554 message refused\r\n")
+        ],
+    }
+    "#)?.build()),
+}
+
+run_test! {
+    fn helo_and_ehlo_rejected,
+    input = [
+        "HELO foobar\r\n",
+        "EHLO foobar\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    kind = ConnectionKind::Lmtp,
+}
+
+run_test! {
+    fn lhlo_rejected_on_smtp,
+    input = [
+        "LHLO foobar\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "500 Syntax error command unrecognized\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
@@ -16,6 +16,7 @@
 */
 
 use crate::run_test;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use vsmtp_common::addr;
 use vsmtp_common::Address;
 use vsmtp_common::ClientName;
@@ -88,3 +89,135 @@ fn test(#[case] mail_from: &str, #[case] reverse_path: Option<&str>) {
         }
     });
 }
+
+run_test! {
+    fn requiretls_without_tls,
+    input = [
+        "EHLO foobar\r\n",
+        "MAIL FROM:<foo@bar> REQUIRETLS\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "530 5.7.10 REQUIRETLS needs a TLS-protected session\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
+
+const ASSERTED_SENDER_RULES: &str = r#"#{
+    mail: [
+        rule "echo submitter" || state::accept(`250 submitter <${ctx::asserted_sender()}>`),
+    ],
+}
+"#;
+
+run_test! {
+    fn auth_from_untrusted_client_ignored,
+    input = [
+        "EHLO foobar\r\n",
+        "MAIL FROM:<foo@bar> AUTH=john@doe\r\n",
+        "RCPT TO:<bar@foo>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 submitter <>\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.asserted_sender, None);
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(ASSERTED_SENDER_RULES)?.build()),
+}
+
+run_test! {
+    fn auth_from_trusted_upstream,
+    input = [
+        "EHLO proxy.example.com\r\n",
+        "MAIL FROM:<foo@bar> AUTH=john+2Bsubmitter@doe\r\n",
+        "RCPT TO:<bar@foo>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-SIZE 20000000\r\n",
+        "250 XCLIENT ADDR PORT NAME HELO PROTO LOGIN\r\n",
+        "250 submitter <john+submitter@doe>\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.xclient_trusted = vec!["127.0.0.1".parse().unwrap()];
+        config
+    },
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.asserted_sender, Some(addr!("john+submitter@doe")));
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(ASSERTED_SENDER_RULES)?.build()),
+}
+
+run_test! {
+    fn auth_from_authenticated_client,
+    input = [
+        "EHLO client.com\r\n",
+        &format!("AUTH PLAIN {}\r\n", STANDARD.encode("\0hello\0world")),
+        "MAIL FROM:<foo@bar> AUTH=hello@client.com\r\n",
+        "RCPT TO:<bar@foo>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5 ANONYMOUS\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = super::auth::unsafe_auth_config(),
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.asserted_sender, Some(addr!("hello@client.com")));
+    },
+}
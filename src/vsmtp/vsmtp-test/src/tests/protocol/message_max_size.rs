@@ -25,7 +25,7 @@ run_test! {
         "MAIL FROM:<john@doe>\r\n",
         "RCPT TO:<aa@bb>\r\n",
         "DATA\r\n",
-        &("X".repeat(1_000_000) + ".\r\n"),
+        &("X".repeat(1_000_000) + "\r\n.\r\n"),
         "QUIT\r\n",
     ],
     expected = [
@@ -43,3 +43,99 @@ run_test! {
         config
     },
 }
+
+run_test! {
+    fn test_message_size_ko_without_declared_size,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &("X".repeat(200) + "\r\n.\r\n"),
+        "MAIL FROM:<john@doe>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "552 4.3.1 Message size exceeds fixed maximum message size\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.esmtp.size = 100;
+        config
+    },
+}
+
+run_test! {
+    fn test_declared_size_ko,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe> SIZE=101\r\n",
+        "MAIL FROM:<john@doe> SIZE=100\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "552 5.3.4 Message size exceeds fixed maximum message size\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.esmtp.size = 100;
+        config
+    },
+}
+
+run_test! {
+    fn test_declared_size_above_message_size_limit,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe> BODY=8BITMIME SIZE=1000000\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "552 5.3.4 Message size exceeds fixed maximum message size\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.message_size_limit = 1_000_000;
+        config
+    },
+}
+
+run_test! {
+    fn test_declared_size_ok,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe> SIZE=50\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        "hello world\r\n.\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.esmtp.size = 100;
+        config
+    },
+}
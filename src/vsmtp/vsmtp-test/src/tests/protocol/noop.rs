@@ -0,0 +1,65 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use vsmtp_common::{addr, ContextFinished};
+use vsmtp_mail_parser::MessageBody;
+
+run_test! {
+    fn noop_before_helo,
+    input = [
+        "NOOP\r\n",
+        "noop keepalive\r\n",
+        "NOOPX\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "501 Syntax error in parameters or arguments\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+}
+
+run_test! {
+    fn noop_during_transaction,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "NOOP\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "NOOP keepalive\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.mail_from.reverse_path, Some(addr!("john@doe")));
+        assert_eq!(ctx.rcpt_to.forward_paths, vec![addr!("aa@bb")]);
+    },
+}
@@ -267,3 +267,59 @@ run_pipelined_test! {
         "221 Service closing transmission channel\r\n",
     ]
 }
+
+run_pipelined_test! {
+    fn line_too_long_in_group,
+    input = [
+        "EHLO foobar\r\n",
+        &["MAIL FROM:<john@doe>\r\n",
+        &format!("RCPT TO:<{}@trusted.com>\r\n", "x".repeat(1024)),
+        "RCPT TO:<galvin@trusted.com>\r\n",
+        "DATA\r\n"].concat(),
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n\
+        250-8BITMIME\r\n\
+        250-SMTPUTF8\r\n\
+        250-STARTTLS\r\n\
+        250-PIPELINING\r\n\
+        250-DSN\r\n\
+        250 SIZE 20000000\r\n",
+        "250 Ok\r\n\
+        501 Syntax error in parameters or arguments\r\n\
+        250 Ok\r\n\
+        354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ]
+}
+
+run_pipelined_test! {
+    fn denied_in_group,
+    input = [
+        "EHLO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n\
+        RCPT TO:<henry@trusted.com>\r\n\
+        RCPT TO:<galvin@trusted.com>\r\n\
+        DATA\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n\
+        250-8BITMIME\r\n\
+        250-SMTPUTF8\r\n\
+        250-STARTTLS\r\n\
+        250-PIPELINING\r\n\
+        250-DSN\r\n\
+        250 SIZE 20000000\r\n",
+        "554 permanent problems with the remote server\r\n",
+    ],
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        mail: [
+          rule "deny sender" || if ctx::mail_from() is "john@doe" { state::deny() } else { state::next() }
+        ],
+    }"#)?.build()),
+}
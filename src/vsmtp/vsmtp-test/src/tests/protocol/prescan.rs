@@ -0,0 +1,123 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms &of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_config::field::FieldServerSMTPPrescan;
+
+fn prescan_config(size_max: Option<usize>, signatures: &[&str]) -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.prescan = Some(FieldServerSMTPPrescan {
+        size_max,
+        signatures: signatures.iter().map(ToString::to_string).collect(),
+        depth: 1000,
+    });
+    config
+}
+
+run_test! {
+    fn prescan_size_max,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &format!(
+            concat!(
+                "from: john doe <john@doe>\r\n",
+                "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+                "\r\n",
+                "{}",
+                ".\r\n",
+            ),
+            format!("{}\r\n", "X".repeat(78)).repeat(100)
+        ),
+        "MAIL FROM:<john@doe>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "552 5.3.4 Message size exceeds fixed maximum message size\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = prescan_config(Some(1000), &[]),
+}
+
+run_test! {
+    fn prescan_signature,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        concat!(
+            "from: john doe <john@doe>\r\n",
+            "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+            "x-mailer: junk-o-matic\r\n",
+            "\r\n",
+            "mail content wow\r\n",
+            ".\r\n",
+        ),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "554 5.7.1 Message content rejected\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = prescan_config(None, &["junk-o-matic"]),
+}
+
+run_test! {
+    fn prescan_signature_past_depth,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &format!(
+            concat!(
+                "from: john doe <john@doe>\r\n",
+                "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+                "\r\n",
+                "{}",
+                "junk-o-matic\r\n",
+                ".\r\n",
+            ),
+            format!("{}\r\n", "X".repeat(78)).repeat(20)
+        ),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = prescan_config(None, &["junk-o-matic"]),
+}
@@ -0,0 +1,98 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+
+const RULES: &str = r#"#{
+    connect: [
+      rule "client ip is the one of the proxy header" || {
+        if ctx::client_ip() is "192.0.2.1" && ctx::client_port() == 56324 {
+          state::next()
+        } else {
+          state::deny()
+        }
+      }
+    ],
+}
+"#;
+
+run_test! {
+    fn proxy_v1,
+    input = [
+        "NOOP\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    proxy = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 25\r\n",
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(RULES)?.build()),
+}
+
+run_test! {
+    fn proxy_v2,
+    input = [
+        "NOOP\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    proxy = [
+        b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C".as_slice(),
+        &[192, 0, 2, 1, 198, 51, 100, 1, 0xDC, 0x04, 0, 25],
+    ].concat(),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(RULES)?.build()),
+}
+
+run_test! {
+    fn proxy_v1_unknown_keep_socket_address,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    proxy = b"PROXY UNKNOWN\r\n",
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        connect: [
+          rule "socket address" || if ctx::client_ip() is "127.0.0.1" { state::next() } else { state::deny() }
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn proxy_malformed,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = Vec::<&str>::new(),
+    proxy = b"PROXY TCP4 192.0.2 198.51.100.1 56324 25\r\n",
+}
+
+run_test! {
+    fn proxy_missing_header,
+    input = [
+        "QUIT\r\n"
+    ],
+    expected = Vec::<&str>::new(),
+    proxy = b"EHLO foobar\r\n",
+}
@@ -0,0 +1,64 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_common::{addr, ContextFinished};
+use vsmtp_config::field::FieldServerSMTPRateLimit;
+use vsmtp_mail_parser::MessageBody;
+
+run_test! {
+    fn rate_limit_exhausted,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@dd>\r\n",
+        "RCPT TO:<ee@ff>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "450 4.7.1 Too many recipients, try again later\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.smtp.rate_limit = Some(FieldServerSMTPRateLimit {
+            rate: 1,
+            period: std::time::Duration::from_secs(3600),
+            burst: 2,
+        });
+        config
+    },
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert!(ctx.rcpt_to.delivery
+            .values()
+            .flatten()
+            .map(|(addr, _)| addr)
+            .cloned()
+            .eq([addr!("aa@bb"), addr!("cc@dd")])
+        );
+    }
+}
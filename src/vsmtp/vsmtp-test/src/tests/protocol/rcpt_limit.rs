@@ -0,0 +1,122 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use vsmtp_common::{addr, ContextFinished, RejectionReason};
+use vsmtp_mail_parser::MessageBody;
+
+run_test! {
+    fn rcpt_limit_keeps_accepted_recipients,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@dd>\r\n",
+        "RCPT TO:<ee@ff>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "452 Requested action not taken: too many recipients\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.rcpt_count_max = 2;
+        config
+    },
+    mail_handler = |ctx: ContextFinished, body: MessageBody| {
+        let mut recipients = ctx.rcpt_to.delivery
+            .values()
+            .flatten()
+            .map(|(addr, _)| addr.clone())
+            .collect::<Vec<_>>();
+        recipients.sort_by_key(ToString::to_string);
+        assert_eq!(recipients, [addr!("aa@bb"), addr!("cc@dd")]);
+        assert_eq!(body.get_header("X-Rcpt-Count").as_deref(), Some("2"));
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        preq: [
+          action "count recipients" || msg::append_header("X-Rcpt-Count", ctx::rcpt_count().to_string()),
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn rcpt_count_in_rules,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@dd>\r\n",
+        "RCPT TO:<ee@ff>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "452 4.5.3 Too many recipients\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        rcpt: [
+          rule "two recipients at most" || if ctx::rcpt_count() > 2 {
+            state::reject("452 4.5.3 Too many recipients\r\n")
+          } else {
+            state::next()
+          },
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn rcpt_limit_custom_reply,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@dd>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "452 4.5.3 Only one recipient per message\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = {
+        let mut config = crate::config::local_test();
+        config.server.smtp.rcpt_count_max = 1;
+        config.server.smtp.replies.insert(
+            RejectionReason::TooManyRecipients,
+            "452 4.5.3 Only one recipient per message\r\n".parse().unwrap(),
+        );
+        config
+    },
+}
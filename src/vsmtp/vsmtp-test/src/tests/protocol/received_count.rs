@@ -0,0 +1,115 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms &of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+
+/// A message which went through `hops` relays.
+fn message_with_hops(hops: usize) -> String {
+    let received = (0..hops)
+        .rev()
+        .map(|i| {
+            format!(
+                "Received: from mx{i}.example.com by mx{}.example.com\r\n",
+                i + 1
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        concat!(
+            "{}",
+            "from: john doe <john@doe>\r\n",
+            "date: tue, 30 nov 2021 20:54:27 +0100\r\n",
+            "\r\n",
+            "mail content wow\r\n",
+            ".\r\n",
+        ),
+        received
+    )
+}
+
+fn unlimited_config() -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.received_count_max = None;
+    config
+}
+
+run_test! {
+    fn too_many_hops,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &message_with_hops(31),
+        "MAIL FROM:<john@doe>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "554 5.4.6 Too many hops\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
+
+run_test! {
+    fn hops_at_the_limit,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &message_with_hops(30),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
+
+run_test! {
+    fn hops_unlimited,
+    input = [
+        "HELO foobar\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        &message_with_hops(31),
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = unlimited_config(),
+}
@@ -14,7 +14,10 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
+use super::auth::unsafe_auth_config;
 use crate::run_test;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use vsmtp_common::addr;
 use vsmtp_common::ContextFinished;
 use vsmtp_mail_parser::BodyType;
@@ -254,3 +257,78 @@ run_test! {
         );
     },
 }
+
+run_test! {
+    fn reset_keep_authentication,
+    input = [
+        "EHLO client.com\r\n",
+        &format!("AUTH PLAIN {}\r\n", STANDARD.encode(format!("\0{}\0{}", "hello", "world"))),
+        "MAIL FROM:<foo@bar>\r\n",
+        "RSET\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<joe@doe>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5 ANONYMOUS\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = unsafe_auth_config(),
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.helo.client_name.to_string(), "client.com");
+        assert!(ctx.connect.auth.as_ref().map_or(false, |auth| auth.authenticated));
+        assert_eq!(ctx.mail_from.reverse_path, Some(addr!("john@doe")));
+        assert_eq!(ctx.rcpt_to.forward_paths, vec![addr!("joe@doe")]);
+    },
+}
+
+run_test! {
+    fn reset_headers_added_by_rules,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<a@b>\r\n",
+        "RSET\r\n",
+        "MAIL FROM:<c@d>\r\n",
+        "RCPT TO:<b@c>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    mail_handler = |_: ContextFinished, body: MessageBody| {
+        assert_eq!(body.count_header("X-Sender"), 1);
+        assert_eq!(body.get_header("X-Sender").as_deref(), Some("c@d"));
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        mail: [
+          action "tag the sender" || msg::append_header("X-Sender", ctx::mail_from().to_string()),
+        ],
+    }"#)?.build()),
+}
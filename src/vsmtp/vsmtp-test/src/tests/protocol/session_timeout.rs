@@ -0,0 +1,62 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::client::serve_once;
+use crate::config::local_test;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use vsmtp_protocol::ConnectionKind;
+
+const SESSION_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn active_client_disconnected() {
+    let mut config = local_test();
+    config.server.smtp.session_timeout = Some(SESSION_TIMEOUT);
+
+    let before_test = std::time::Instant::now();
+    let mut stream = tokio::io::BufReader::new(serve_once(config, ConnectionKind::Relay).await);
+
+    let mut banner = String::new();
+    stream.read_line(&mut banner).await.unwrap();
+    assert_eq!(banner, "220 testserver.com Service ready\r\n");
+
+    // NOTE: the client is never idle, but the session is closed past its deadline.
+    let mut replies = vec![];
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        stream.write_all(b"NOOP\r\n").await.unwrap();
+
+        let mut reply = String::new();
+        stream.read_line(&mut reply).await.unwrap();
+        let closed = reply.starts_with("421 ");
+        replies.push(reply);
+        if closed {
+            break;
+        }
+    }
+
+    let mut eof = String::new();
+    assert_eq!(stream.read_line(&mut eof).await.unwrap(), 0);
+
+    assert!(before_test.elapsed() >= SESSION_TIMEOUT);
+    assert_eq!(
+        replies.last().unwrap(),
+        "421 4.4.2 Session time limit exceeded - closing connection\r\n"
+    );
+    assert!(replies[..replies.len() - 1]
+        .iter()
+        .all(|reply| reply == "250 Ok\r\n"));
+}
@@ -0,0 +1,123 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config::with_tls;
+use crate::run_test;
+use vsmtp_config::{
+    field::{FieldServerTlsClientAuth, FieldServerVirtual, FieldServerVirtualTls},
+    Config,
+};
+
+const CLIENT_CERT: (&str, &str) = (
+    "src/template/certs/client/client.crt",
+    "src/template/certs/client/client.key",
+);
+
+fn with_client_auth(required: bool) -> Config {
+    let mut config = with_tls();
+    config.server.tls.as_mut().unwrap().client_auth = Some(
+        FieldServerTlsClientAuth::from_path("src/template/certs/client/ca.crt", required).unwrap(),
+    );
+    config.app.vsl.domain_dir = Some("./src/template/sni".into());
+    config.server.r#virtual.insert(
+        "testserver.com".parse().unwrap(),
+        FieldServerVirtual {
+            tls: Some(
+                FieldServerVirtualTls::from_path(
+                    "src/template/certs/certificate.crt",
+                    "src/template/certs/private_key.rsa.key",
+                )
+                .unwrap(),
+            ),
+            dns: None,
+            dkim: None,
+        },
+    );
+    config
+}
+
+run_test! {
+    fn subject_and_san,
+    input = [
+        "HELO client.com\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    tunnel = "testserver.com",
+    client_cert = CLIENT_CERT,
+    config = with_client_auth(true),
+    hierarchy_builder = |builder| {
+        Ok(builder.add_root_filter_rules(r#"#{
+          helo: [
+            rule "client certificate" || {
+              if ctx::client_cert_subject() == "CN=client.testserver.com, O=vSMTP test"
+                && ctx::client_cert_san() == [
+                  "DNS:client.testserver.com",
+                  "email:postmaster@client.testserver.com",
+                ] {
+                state::next()
+              } else {
+                state::deny()
+              }
+            }
+          ],
+        }
+      "#).unwrap().build())
+    }
+}
+
+run_test! {
+    fn optional_and_absent,
+    input = [
+        "HELO client.com\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    tunnel = "testserver.com",
+    config = with_client_auth(false),
+    hierarchy_builder = |builder| {
+        Ok(builder.add_root_filter_rules(r#"#{
+          helo: [
+            rule "no client certificate" || {
+              if ctx::client_cert_subject() == "" && ctx::client_cert_san() == [] {
+                state::next()
+              } else {
+                state::deny()
+              }
+            }
+          ],
+        }
+      "#).unwrap().build())
+    }
+}
+
+run_test! {
+    fn required_and_absent,
+    input = [
+        "HELO client.com\r\n",
+    ],
+    expected = Vec::<&str>::new(),
+    tunnel = "testserver.com",
+    config = with_client_auth(true),
+}
@@ -40,6 +40,7 @@ run_test! {
         "250-SMTPUTF8\r\n",
         "250-PIPELINING\r\n",
         "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
         "250 SIZE 20000000\r\n",
         "250 Ok\r\n",
         "250 Ok\r\n",
@@ -90,6 +91,196 @@ run_test! {
     }
 }
 
+run_test! {
+    fn tls_details,
+    input = [
+        "EHLO client.com\r\n",
+        "STARTTLS\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "220 TLS go ahead\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
+        "250 SIZE 20000000\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    starttls = "testserver.com" => [
+        "EHLO client.com\r\n",
+        "QUIT\r\n",
+    ],
+    config = {
+      let mut config = with_tls();
+      config.server.tls.as_mut().unwrap().cipher_suite = vec![vsmtp_common::CipherSuite(
+          tokio_rustls::rustls::CipherSuite::TLS13_AES_256_GCM_SHA384,
+      )];
+      config.app.vsl.domain_dir = Some("./src/template/sni".into());
+      config.server.r#virtual.insert(
+          "testserver.com".parse().unwrap(),
+          FieldServerVirtual {
+              tls: Some(
+                  FieldServerVirtualTls::from_path(
+                      "src/template/certs/certificate.crt",
+                      "src/template/certs/private_key.rsa.key",
+                  )
+                  .unwrap(),
+              ),
+              dns: None,
+              dkim: None,
+          },
+      );
+      config
+    },
+    hierarchy_builder = |builder| {
+      Ok(builder.add_root_filter_rules(r#"#{
+        helo: [
+          rule "tls details" || {
+            let details = [ctx::tls_version(), ctx::tls_cipher(), ctx::sni()];
+            let expected = if ctx::is_secured() {
+              ["TLSv1_3", "TLS_AES_256_GCM_SHA384", "testserver.com"]
+            } else {
+              ["", "", ""]
+            };
+            if details == expected { state::next() } else { state::deny() }
+          }
+        ],
+      }
+    "#).unwrap().build())
+    }
+}
+
+run_test! {
+    fn pipelined_command_after_starttls_is_discarded,
+    input = [
+        "EHLO client.com\r\n",
+        // the `MAIL FROM` is injected in the same tcp segment as the `STARTTLS`
+        "STARTTLS\r\nMAIL FROM:<attacker@example.com>\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "220 TLS go ahead\r\n",
+        // the session is reset, a new EHLO is required
+        "503 Bad sequence of commands\r\n",
+        "503 Bad sequence of commands\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "503 Bad sequence of commands\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    starttls = "testserver.com" => [
+        "RCPT TO:<bar@foo>\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+        "EHLO client.com\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+        "DATA\r\n",
+        "QUIT\r\n",
+    ],
+    config = {
+      let mut config = with_tls();
+      config.app.vsl.domain_dir = Some("./src/template/sni".into());
+      config.server.r#virtual.insert(
+          "testserver.com".parse().unwrap(),
+          FieldServerVirtual {
+              tls: Some(
+                  FieldServerVirtualTls::from_path(
+                      "src/template/certs/certificate.crt",
+                      "src/template/certs/private_key.rsa.key",
+                  )
+                  .unwrap(),
+              ),
+              dns: None,
+              dkim: None,
+          },
+      );
+      config
+    },
+}
+
+run_test! {
+    fn requiretls,
+    input = [
+        "EHLO client.com\r\n",
+        "STARTTLS\r\n"
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "220 TLS go ahead\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    starttls = "testserver.com" => [
+        "EHLO client.com\r\n",
+        "MAIL FROM:<foo@bar> REQUIRETLS\r\n",
+        "RCPT TO:<bar@foo>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    config = {
+      let mut config = with_tls();
+      config.app.vsl.domain_dir = Some("./src/template/sni".into());
+      config.server.r#virtual.insert(
+          "testserver.com".parse().unwrap(),
+          FieldServerVirtual {
+              tls: Some(
+                  FieldServerVirtualTls::from_path(
+                      "src/template/certs/certificate.crt",
+                      "src/template/certs/private_key.rsa.key",
+                  )
+                  .unwrap(),
+              ),
+              dns: None,
+              dkim: None,
+          },
+      );
+      config
+    },
+    mail_handler = |ctx: vsmtp_common::ContextFinished, _: vsmtp_mail_parser::MessageBody| {
+        assert!(ctx.mail_from.require_tls);
+    }
+}
+
 #[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
 #[should_panic] // the client is panicking, not the server
 async fn domain_not_defined() {
@@ -183,6 +374,7 @@ run_test! {
         "250-SMTPUTF8\r\n",
         "250-PIPELINING\r\n",
         "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
         "250 SIZE 20000000\r\n",
         "554 5.5.1 Error: TLS already active\r\n",
         "221 Service closing transmission channel\r\n",
@@ -288,3 +480,46 @@ async fn config_ill_formed() {
         }
     };
 }
+
+run_test! {
+    fn starttls_advertised_in_clair,
+    input = [
+        "EHLO client.com\r\n",
+        "MAIL FROM:<foo@bar>\r\n",
+        "RCPT TO:<bar@foo>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = with_tls(),
+    mail_handler = |ctx: vsmtp_common::ContextFinished, _: vsmtp_mail_parser::MessageBody| {
+        assert_eq!(
+            ctx.helo.capabilities,
+            ["8BITMIME", "SMTPUTF8", "STARTTLS", "PIPELINING", "DSN", "SIZE 20000000"]
+        );
+    },
+    hierarchy_builder = |builder| {
+      Ok(builder.add_root_filter_rules(r#"#{
+        mail: [
+          rule "must advertise starttls" || {
+            if "STARTTLS" in ctx::advertised_capabilities() { state::next() } else { state::deny() }
+          }
+        ],
+      }"#)?.build())
+    },
+}
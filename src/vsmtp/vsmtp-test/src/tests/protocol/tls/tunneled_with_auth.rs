@@ -66,6 +66,7 @@ run_test! {
         "250-SMTPUTF8\r\n",
         "250-PIPELINING\r\n",
         "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
         "250 SIZE 20000000\r\n",
         "334 \r\n",
         "235 2.7.0 Authentication succeeded\r\n",
@@ -96,3 +97,58 @@ run_test! {
         config
     },
 }
+
+run_test! {
+    fn auth_after_starttls,
+    input = [
+        "EHLO client.com\r\n",
+        &format!("AUTH PLAIN {}\r\n", STANDARD.encode("\0hello\0world")),
+        "STARTTLS\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250 SIZE 20000000\r\n",
+        "538 5.7.11 Encryption required for requested authentication mechanism\r\n",
+        "220 TLS go ahead\r\n",
+        "250-testserver.com\r\n",
+        "250-AUTH PLAIN LOGIN CRAM-MD5\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-REQUIRETLS\r\n",
+        "250 SIZE 20000000\r\n",
+        "235 2.7.0 Authentication succeeded\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    starttls = "testserver.com" => [
+        "EHLO client.com\r\n",
+        &format!("AUTH PLAIN {}\r\n", STANDARD.encode("\0hello\0world")),
+        "QUIT\r\n",
+    ],
+    config = {
+        let mut config = get_tls_auth_config();
+        config.app.vsl.domain_dir = Some("./src/template/sni".into());
+        config.server.r#virtual.insert(
+            "testserver.com".parse().unwrap(),
+            FieldServerVirtual {
+              tls: Some(
+                    FieldServerVirtualTls::from_path(
+                        "src/template/certs/certificate.crt",
+                        "src/template/certs/private_key.rsa.key",
+                    )
+                    .unwrap(),
+                ),
+                dns: None,
+                dkim: None,
+            },
+        );
+        config
+    },
+}
@@ -15,19 +15,82 @@
  *
 */
 
+use crate::config;
 use crate::run_test;
+use vsmtp_config::field::FieldServerSMTPVrfy;
+
+fn vrfy_config(vrfy: FieldServerSMTPVrfy) -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.vrfy = vrfy;
+    config
+}
+
+run_test! {
+    fn vrfy_cannot_by_default,
+    input = [
+        "HELO foo\r\n",
+        "VRFY root\r\n",
+        "EXPN staff\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "252 Cannot VRFY user, but will accept message and attempt delivery\r\n",
+        "252 Cannot EXPN list, but will accept message and attempt delivery\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+}
 
 run_test! {
     fn vrfy_unimplemented,
     input = [
         "HELO foo\r\n",
         "VRFY foobar\r\n",
+        "EXPN staff\r\n",
         "QUIT\r\n",
     ],
     expected = [
         "220 testserver.com Service ready\r\n",
         "250 Ok\r\n",
         "502 Command not implemented\r\n",
+        "502 Command not implemented\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = vrfy_config(FieldServerSMTPVrfy::Disabled),
+}
+
+run_test! {
+    fn vrfy_lookup,
+    input = [
+        "VRFY root\r\n",
+        "VRFY <root@testserver.com>\r\n",
+        "VRFY no-such-user-vsmtp\r\n",
+        "VRFY root@example.com\r\n",
+        "EXPN root\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 <root@testserver.com>\r\n",
+        "250 <root@testserver.com>\r\n",
+        "550 mailbox unavailable\r\n",
+        "252 Cannot VRFY user, but will accept message and attempt delivery\r\n",
+        "250 <root@testserver.com>\r\n",
+        "221 Service closing transmission channel\r\n"
+    ],
+    config = vrfy_config(FieldServerSMTPVrfy::Lookup),
+}
+
+run_test! {
+    fn vrfy_missing_argument,
+    input = [
+        "VRFY  \r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "501 Syntax error in parameters or arguments\r\n",
         "221 Service closing transmission channel\r\n"
     ],
 }
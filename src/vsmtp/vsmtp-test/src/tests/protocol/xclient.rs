@@ -0,0 +1,118 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use crate::run_test;
+use vsmtp_common::{auth::Credentials, ContextFinished};
+use vsmtp_mail_parser::MessageBody;
+
+fn trusted_config() -> vsmtp_config::Config {
+    let mut config = config::local_test();
+    config.server.smtp.xclient_trusted = vec!["127.0.0.1".parse().unwrap()];
+    config
+}
+
+run_test! {
+    fn xclient_advertised,
+    input = [
+        "EHLO proxy.example.com\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-SIZE 20000000\r\n",
+        "250 XCLIENT ADDR PORT NAME HELO PROTO LOGIN\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = trusted_config(),
+}
+
+run_test! {
+    fn xclient_forwarded_identity,
+    input = [
+        "EHLO proxy.example.com\r\n",
+        "XCLIENT ADDR=192.0.2.1 PORT=56324 HELO=client.example.com LOGIN=john\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250-testserver.com\r\n",
+        "250-8BITMIME\r\n",
+        "250-SMTPUTF8\r\n",
+        "250-STARTTLS\r\n",
+        "250-PIPELINING\r\n",
+        "250-DSN\r\n",
+        "250-SIZE 20000000\r\n",
+        "250 XCLIENT ADDR PORT NAME HELO PROTO LOGIN\r\n",
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = trusted_config(),
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(ctx.connect.client_addr, "192.0.2.1:56324".parse().unwrap());
+        assert_eq!(ctx.helo.client_name.to_string(), "client.example.com");
+        let auth = ctx.connect.auth.unwrap();
+        assert!(auth.authenticated);
+        assert_eq!(
+            auth.credentials,
+            Some(Credentials::Forwarded { authid: "john".to_string() })
+        );
+    }
+}
+
+run_test! {
+    fn xclient_connect_rules,
+    input = [
+        "XCLIENT ADDR=192.0.2.66\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "554 permanent problems with the remote server\r\n",
+    ],
+    config = trusted_config(),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        connect: [
+          rule "forwarded client" || if ctx::client_ip() is "192.0.2.66" { state::deny() } else { state::next() }
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn xclient_untrusted,
+    input = [
+        "XCLIENT ADDR=192.0.2.1\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "550 5.7.0 Insufficient authorization\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+}
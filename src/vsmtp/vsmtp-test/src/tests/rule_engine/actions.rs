@@ -182,6 +182,86 @@ async fn context_write(
     std::fs::remove_dir_all(&dirpath).unwrap();
 }
 
+fn run_write_with_template(dirpath: &std::path::Path, template: &str) -> vsmtp_common::Context {
+    let _ = std::fs::remove_dir_all(dirpath);
+
+    let mut config = crate::config::local_test();
+    config.app.dirpath = dirpath.to_path_buf();
+
+    let rules = format!(
+        r#"#{{
+    preq: [
+        action "write to file" || fs::write("archives", "{template}"),
+    ]
+}}"#
+    );
+
+    let states = crate::vsl::run_with_msg_and_config(
+        move |builder| {
+            Ok(builder
+                .add_root_filter_rules("#{}")?
+                .add_domain_rules("testserver.com".parse().unwrap())
+                .with_incoming(&rules)?
+                .with_outgoing(&rules)?
+                .with_internal(&rules)?
+                .build()
+                .build())
+        },
+        None,
+        config,
+    );
+
+    states[&vsmtp_rule_engine::ExecutionStage::PreQ].0.clone()
+}
+
+#[test]
+fn test_write_with_date_template() {
+    let dirpath = std::path::PathBuf::from("./tmp/write_date_template");
+    let ctx = run_write_with_template(&dirpath, "{date}_{msgid}");
+
+    let date = ctx
+        .mail_timestamp()
+        .unwrap()
+        .to_offset(time::UtcOffset::UTC)
+        .format(time::macros::format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .unwrap();
+
+    pretty_assertions::assert_eq!(
+        std::fs::read_dir(dirpath.join("archives"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>(),
+        vec![format!("{date}_{}.eml", ctx.message_uuid().unwrap())]
+    );
+}
+
+#[test]
+fn test_write_with_traversal_template() {
+    let dirpath = std::path::PathBuf::from("./tmp/write_traversal_template");
+    let ctx = run_write_with_template(&dirpath, "../../{sender}/{msgid}");
+
+    // the email is still written in the `archives` folder.
+    pretty_assertions::assert_eq!(
+        std::fs::read_dir(&dirpath)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["archives".to_string()]
+    );
+    pretty_assertions::assert_eq!(
+        std::fs::read_dir(dirpath.join("archives"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>(),
+        vec![format!(
+            "....client@testserver.com{}.eml",
+            ctx.message_uuid().unwrap()
+        )]
+    );
+}
+
 /*
 use crate::rule_engine::RuleEngine;
 use crate::rule_state::RuleState;
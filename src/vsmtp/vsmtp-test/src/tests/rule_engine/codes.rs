@@ -14,8 +14,9 @@
  * this program. If not, see https://www.gnu.org/licenses/.
  *
 */
-use crate::{run_test, tests::protocol::auth::unsafe_auth_config};
+use crate::{config, run_test, tests::protocol::auth::unsafe_auth_config};
 use base64::{engine::general_purpose::STANDARD, Engine};
+use vsmtp_common::RejectionReason;
 
 run_test! {
     fn deny_message_1,
@@ -77,3 +78,53 @@ run_test! {
     config = unsafe_auth_config(),
     hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(include_str!("custom_codes_accept.vsl"))?.build()),
 }
+
+const ANTI_RELAY_RULES: &str = r#"#{
+    rcpt: [
+        rule "anti relay" || {
+            if ctx::rcpt().domain == "testserver.com" { state::next() } else { state::deny(code::c554_7_1()) }
+        },
+    ],
+}"#;
+
+run_test! {
+    fn relay_denied_default_reply,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<satan@any.com>\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "554 5.7.1 Relay access denied\r\n",
+    ],
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(ANTI_RELAY_RULES)?.build()),
+}
+
+run_test! {
+    fn relay_denied_custom_reply,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<satan@any.com>\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "550 5.7.1 Relaying is restricted to the customers of testserver.com\r\n",
+    ],
+    config = {
+        let mut config = config::local_test();
+        config.server.smtp.replies.insert(
+            RejectionReason::RelayDenied,
+            "550 5.7.1 Relaying is restricted to the customers of testserver.com\r\n"
+                .parse()
+                .unwrap(),
+        );
+        config
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(ANTI_RELAY_RULES)?.build()),
+}
@@ -78,6 +78,21 @@ fn snipped_info(
             "is_secured",
             &["connect", "helo", "mail", "rcpt", "preq"],
         ),
+        "ctx::tls_version()" => (
+            "connect".parse().unwrap(),
+            "tls_version",
+            &["connect", "helo", "mail", "rcpt", "preq"],
+        ),
+        "ctx::tls_cipher()" => (
+            "connect".parse().unwrap(),
+            "tls_cipher",
+            &["connect", "helo", "mail", "rcpt", "preq"],
+        ),
+        "ctx::sni()" => (
+            "connect".parse().unwrap(),
+            "sni",
+            &["connect", "helo", "mail", "rcpt", "preq"],
+        ),
         "auth::is_authenticated()" => (
             "connect".parse().unwrap(),
             "is_authenticated",
@@ -207,6 +222,9 @@ fn expected(
 #[case("ctx::server_port()")]
 #[case("ctx::server_name()")]
 #[case("ctx::is_secured()")]
+#[case("ctx::tls_version()")]
+#[case("ctx::tls_cipher()")]
+#[case("ctx::sni()")]
 #[case("auth::is_authenticated()")]
 // #[case("auth::credentials()")]
 // #[case("auth::credentials().anonymous_token")]
@@ -296,3 +314,342 @@ fn each(
             .any(|l| dbg!(l).contains(&logs)));
     }
 }
+
+#[rstest::rstest]
+#[case("recipient@testserver.com", vsmtp_common::status::Status::Next)]
+#[case(
+    "spam@blocked.com",
+    vsmtp_common::status::Status::Deny(
+        "554 permanent problems with the remote server\r\n".parse().unwrap()
+    )
+)]
+fn test_run_with_context_rcpt(#[case] rcpt: &str, #[case] expected: vsmtp_common::status::Status) {
+    let ctx = vsmtp_rule_engine::local_context(
+        "testserver.com".parse().unwrap(),
+        vsmtp_common::ClientName::Domain("client.testserver.com".parse().unwrap()),
+        Some("client@testserver.com".parse().unwrap()),
+        vec![rcpt.parse().unwrap()],
+        vsmtp_common::TransactionType::Internal,
+    );
+
+    let states = crate::vsl::run_with_context(
+        |builder| {
+            Ok(builder
+                .add_root_filter_rules(
+                    r#"#{
+    rcpt: [
+        rule "deny blocked domain" || if ctx::rcpt().domain == "blocked.com" { state::deny() } else { state::next() },
+    ]
+}"#,
+                )?
+                .build())
+        },
+        &ctx,
+        None,
+        ExecutionStage::RcptTo,
+    );
+
+    assert_eq!(
+        states
+            .keys()
+            .copied()
+            .collect::<std::collections::BTreeSet<_>>(),
+        std::collections::BTreeSet::from([
+            ExecutionStage::Connect,
+            ExecutionStage::Helo,
+            ExecutionStage::MailFrom,
+            ExecutionStage::RcptTo,
+        ])
+    );
+    assert_eq!(states[&ExecutionStage::RcptTo].2, expected);
+}
+
+#[rstest::rstest]
+#[case(&[], "\"\"")]
+#[case(&["mail.example.com."], "\"mail.example.com\"")]
+#[case(&["mail.example.com.", "mx.example.com."], "[\"mail.example.com\", \"mx.example.com\"]")]
+fn test_client_rdns(#[case] names: &[&str], #[case] expected: &str) {
+    let mut ctx = crate::config::local_ctx();
+    ctx.connect.client_rdns = Some(names.iter().map(|name| name.parse().unwrap()).collect());
+
+    let states = crate::vsl::run_with_context(
+        |builder| {
+            Ok(builder
+                .add_root_filter_rules(
+                    r#"#{
+    connect: [
+        rule "client rdns" || state::accept(`250 ${ctx::client_rdns().to_debug()}`),
+    ]
+}"#,
+                )?
+                .build())
+        },
+        &ctx,
+        None,
+        ExecutionStage::Connect,
+    );
+
+    assert_eq!(
+        states[&ExecutionStage::Connect].2,
+        vsmtp_common::status::Status::Accept(format!("250 {expected}").parse().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_client_rdns_once_per_connection() {
+    let queue_manager = run_test! {
+        input = [
+            "HELO foobar\r\n",
+            "MAIL FROM:<john.doe@mydomain.com>\r\n",
+            "RCPT TO:<aa@mydomain.com>\r\n",
+            "DATA\r\n",
+            concat!("from: 'abc'\r\n", "to: 'def'\r\n", ".\r\n"),
+            "MAIL FROM:<john.doe@mydomain.com>\r\n",
+            "RCPT TO:<aa@mydomain.com>\r\n",
+            "DATA\r\n",
+            concat!("from: 'abc'\r\n", "to: 'def'\r\n", ".\r\n"),
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "250 Ok\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+        // NOTE: quarantined, the messages are not sent to the working channel of the test server.
+        hierarchy_builder = |builder| Ok(
+            builder
+                .add_root_filter_rules(r#"#{ preq: [ rule "hold" || state::quarantine("rdns") ] }"#)?
+                .build()
+            ),
+    };
+
+    let contexts = std::fs::read_dir(vqueue::FilesystemQueueManagerExt::get_queue_path(
+        &*queue_manager,
+        &vqueue::QueueID::Quarantine {
+            name: "rdns".to_string(),
+        },
+    ))
+    .unwrap()
+    .map(|entry| {
+        serde_json::from_str::<vsmtp_common::ContextFinished>(
+            &std::fs::read_to_string(entry.unwrap().path()).unwrap(),
+        )
+        .unwrap()
+    })
+    .collect::<Vec<_>>();
+    assert_eq!(contexts.len(), 2);
+
+    // the names resolved when the connection was accepted are shared by both transactions.
+    assert!(contexts[0].connect.client_rdns.is_some());
+    assert_eq!(
+        contexts[0].connect.client_rdns,
+        contexts[1].connect.client_rdns
+    );
+}
+
+#[rstest::rstest]
+#[case(false)]
+#[case(true)]
+fn test_connection_blocked(#[case] blocked: bool) {
+    let mut ctx = crate::config::local_ctx();
+    ctx.connect.connection_blocked = blocked;
+
+    let states = crate::vsl::run_with_context(
+        |builder| {
+            Ok(builder
+                .add_root_filter_rules(
+                    r#"#{
+    connect: [
+        rule "connection blocked" || state::accept(`250 ${ctx::connection_blocked()}`),
+    ]
+}"#,
+                )?
+                .build())
+        },
+        &ctx,
+        None,
+        ExecutionStage::Connect,
+    );
+
+    assert_eq!(
+        states[&ExecutionStage::Connect].2,
+        vsmtp_common::status::Status::Accept(format!("250 {blocked}").parse().unwrap())
+    );
+}
+
+#[test]
+fn test_connection_and_transaction_age() {
+    let mut ctx = crate::config::local_ctx();
+    let now = time::OffsetDateTime::now_utc();
+    ctx.connect.connect_timestamp = now - time::Duration::seconds(2);
+    ctx.mail_from.mail_timestamp = now - time::Duration::seconds(1);
+
+    let states = crate::vsl::run_with_context(
+        |builder| {
+            Ok(builder
+                .add_root_filter_rules(
+                    r#"#{
+    connect: [
+        rule "connect age" || state::accept(`250 ${ctx::connection_age_ms()} 0`),
+    ],
+    helo: [
+        rule "helo age" || state::accept(`250 ${ctx::connection_age_ms()} 0`),
+    ],
+    mail: [
+        rule "mail age" || state::accept(`250 ${ctx::connection_age_ms()} ${ctx::transaction_age_ms()}`),
+    ],
+    rcpt: [
+        rule "rcpt age" || state::accept(`250 ${ctx::connection_age_ms()} ${ctx::transaction_age_ms()}`),
+    ],
+}"#,
+                )?
+                .build())
+        },
+        &ctx,
+        None,
+        ExecutionStage::RcptTo,
+    );
+
+    let ages = [
+        ExecutionStage::Connect,
+        ExecutionStage::Helo,
+        ExecutionStage::MailFrom,
+        ExecutionStage::RcptTo,
+    ]
+    .map(|stage| {
+        let vsmtp_common::status::Status::Accept(reply) = &states[&stage].2 else {
+            panic!("{stage}: {:?}", states[&stage].2);
+        };
+        let reply = reply.to_string();
+        let mut ages = reply
+            .trim_end()
+            .split(' ')
+            .skip(1)
+            .map(|age| age.parse::<i64>().unwrap());
+        (ages.next().unwrap(), ages.next().unwrap())
+    });
+
+    for (connection_age, transaction_age) in ages {
+        assert!(connection_age >= 2000, "{connection_age}");
+        assert!(transaction_age >= 0, "{transaction_age}");
+    }
+    for (connection_age, transaction_age) in &ages[2..] {
+        assert!(*transaction_age >= 1000, "{transaction_age}");
+        assert!(connection_age > transaction_age);
+    }
+    for window in ages.windows(2) {
+        assert!(window[0].0 <= window[1].0, "{ages:?}");
+    }
+    assert!(ages[2].1 <= ages[3].1, "{ages:?}");
+}
+
+#[derive(Clone, Default)]
+struct Logs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_transaction_id() {
+    const RULES: &str = r#"#{
+    mail: [
+        action "log mail txid" || log("info", `txid=${ctx::transaction_id()}`),
+    ],
+    rcpt: [
+        action "log rcpt txid" || log("info", `txid=${ctx::transaction_id()}`),
+    ],
+}"#;
+
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let received = std::sync::Arc::new(std::sync::Mutex::new(None));
+    let received_hook = received.clone();
+
+    // NOTE: a single threaded runtime, so that the session is traced by the subscriber above.
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async move {
+            run_test! {
+                input = [
+                    "EHLO foo\r\n",
+                    "MAIL FROM:<john@server.com>\r\n",
+                    "RCPT TO:<doe@server.com>\r\n",
+                    "DATA\r\n",
+                    "Subject: test email\r\n\r\nThis is a raw email.\r\n.\r\n",
+                    "QUIT\r\n",
+                ],
+                expected = [
+                    "220 testserver.com Service ready\r\n",
+                    "250-testserver.com\r\n",
+                    "250-8BITMIME\r\n",
+                    "250-SMTPUTF8\r\n",
+                    "250-STARTTLS\r\n",
+                    "250-PIPELINING\r\n",
+                    "250-DSN\r\n",
+                    "250 SIZE 20000000\r\n",
+                    "250 Ok\r\n",
+                    "250 Ok\r\n",
+                    "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+                    "250 Ok\r\n",
+                    "221 Service closing transmission channel\r\n",
+                ],
+                mail_handler = move |ctx: vsmtp_common::ContextFinished, _: vsmtp_mail_parser::MessageBody| {
+                    *received_hook.lock().unwrap() = Some(ctx.mail_from.transaction_id);
+                },
+                hierarchy_builder = move |builder| {
+                    Ok(builder.add_root_filter_rules(RULES).unwrap()
+                        .add_domain_rules("server.com".parse().unwrap())
+                        .with_incoming(RULES).unwrap()
+                        .with_outgoing(RULES).unwrap()
+                        .with_internal(RULES).unwrap()
+                        .build()
+                    .build())
+                },
+            }
+        });
+
+    let transaction_id = received.lock().unwrap().expect("message received");
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let lines = logs
+        .lines()
+        // NOTE: the source of the rules is traced too, skip it.
+        .filter(|line| line.contains("txid=") && !line.contains("ctx::transaction_id()"))
+        .collect::<Vec<_>>();
+
+    // logged by the rules of the `mail` and `rcpt` stages.
+    assert!(lines.len() >= 2, "{logs}");
+    for line in lines {
+        assert!(line.contains(&format!("txid={transaction_id}")), "{line}");
+        assert!(
+            line.contains(&format!("transaction_id={transaction_id}")),
+            "{line}"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::{status::Status, Reply};
+use vsmtp_mail_parser::MessageBody;
+
+#[test]
+fn verify_all_unsigned() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(
+                MessageBody::try_from(concat!(
+                    "From: john.doe@example.com\r\n",
+                    "Subject: Unit test are cool\r\n",
+                    "\r\n",
+                    "Hello world!\r\n",
+                ))
+                .unwrap()
+            ),
+            r#"#{
+    preq: [
+        rule "verify_all" || if dkim::verify_all() == [] { state::accept() } else { state::deny() }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn verify_all_malformed() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(
+                MessageBody::try_from(concat!(
+                    "DKIM-Signature: v=1; a=rsa-sha256; d=example.com\r\n",
+                    "DKIM-Signature: this is not a signature\r\n",
+                    "From: john.doe@example.com\r\n",
+                    "Subject: Unit test are cool\r\n",
+                    "\r\n",
+                    "Hello world!\r\n",
+                ))
+                .unwrap()
+            ),
+            r#"#{
+    preq: [
+        rule "verify_all" || {
+            let results = dkim::verify_all();
+
+            if results.len() == 2
+            && results.all(|r| r.status == "neutral" && r.sdid == "" && r.auid == "") {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn verify_all_caches_public_keys() {
+    let (ctx, _, status) = crate::vsl::run_preq(
+        Some(
+            MessageBody::try_from(concat!(
+                "DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=selector; h=From;\r\n",
+                "  bh=frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=; b=dGVzdA==\r\n",
+                "DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=selector; h=Subject;\r\n",
+                "  bh=frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=; b=dGVzdA==\r\n",
+                "From: john.doe@example.com\r\n",
+                "Subject: Unit test are cool\r\n",
+                "\r\n",
+                "Hello world!\r\n",
+            ))
+            .unwrap(),
+        ),
+        r#"#{
+    preq: [
+        rule "verify_all" || {
+            let first = dkim::verify_all();
+            let second = dkim::verify_all();
+
+            if first.len() == 2 && first == second { state::accept() } else { state::deny() }
+        }
+    ]
+}"#,
+    );
+
+    assert_eq!(status, Status::Accept("250 Ok".parse::<Reply>().unwrap()));
+    assert!(ctx
+        .dkim_public_keys("selector._domainkey.example.com")
+        .unwrap()
+        .is_some());
+}
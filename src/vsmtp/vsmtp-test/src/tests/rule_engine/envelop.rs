@@ -0,0 +1,94 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use vsmtp_common::{addr, Address, ContextFinished};
+use vsmtp_mail_parser::MessageBody;
+
+fn delivered_to(ctx: &ContextFinished) -> Vec<Address> {
+    let mut recipients = ctx
+        .rcpt_to
+        .delivery
+        .values()
+        .flatten()
+        .map(|(addr, _)| addr.clone())
+        .collect::<Vec<_>>();
+    recipients.sort_by(|a, b| a.full().cmp(b.full()));
+    recipients
+}
+
+run_test! {
+    fn alias_expanded,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<team@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(delivered_to(&ctx), [addr!("aa@bb"), addr!("cc@bb")]);
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        preq: [
+            action "expand team" || {
+                envelop::rewrite_rcpt("team@bb", "aa@bb");
+                envelop::add_rcpt("cc@bb");
+            },
+        ],
+    }"#)?.build()),
+}
+
+run_test! {
+    fn recipient_removed,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@bb>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    mail_handler = |ctx: ContextFinished, _: MessageBody| {
+        assert_eq!(delivered_to(&ctx), [addr!("aa@bb")]);
+    },
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(r#"#{
+        preq: [
+            action "remove cc" || envelop::remove_rcpt("cc@bb"),
+        ],
+    }"#)?.build()),
+}
@@ -0,0 +1,77 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_config::field::FieldAppGeoIp;
+use vsmtp_rule_engine::ExecutionStage;
+
+// NOTE: the test databases contain the networks 1.1.1.0/24 and 89.160.20.0/24.
+const GEOIP_RULES: &str = r#"#{
+    connect: [
+        rule "geoip" || {
+            let asn = geoip::client_asn();
+            let country = geoip::client_country();
+            if asn.is_empty() && country == "" {
+                state::accept("250 unknown")
+            } else {
+                state::accept(`250 AS${asn.number} ${asn.organization} ${country}`)
+            }
+        },
+    ]
+}"#;
+
+#[rstest::rstest]
+#[case("1.1.1.1", "AS13335 CLOUDFLARENET AU")]
+#[case("89.160.20.112", "AS29518 Bredband2 AB SE")]
+#[case("192.0.2.1", "unknown")]
+fn test_client_asn_and_country(#[case] client_ip: &str, #[case] expected: &str) {
+    let mut ctx = crate::config::local_ctx();
+    ctx.connect.client_addr = std::net::SocketAddr::new(client_ip.parse().unwrap(), 25);
+
+    let mut config = crate::config::local_test();
+    config.app.geoip = Some(FieldAppGeoIp {
+        asn: Some("./src/tests/rule_engine/geoip/GeoLite2-ASN-Test.mmdb".into()),
+        country: Some("./src/tests/rule_engine/geoip/GeoLite2-Country-Test.mmdb".into()),
+    });
+
+    let states = crate::vsl::run_with_context_and_config(
+        |builder| Ok(builder.add_root_filter_rules(GEOIP_RULES)?.build()),
+        &ctx,
+        None,
+        ExecutionStage::Connect,
+        config,
+    );
+
+    assert_eq!(
+        states[&ExecutionStage::Connect].2,
+        vsmtp_common::status::Status::Accept(format!("250 {expected}").parse().unwrap())
+    );
+}
+
+#[test]
+fn test_client_asn_without_database() {
+    let states = crate::vsl::run_with_context(
+        |builder| Ok(builder.add_root_filter_rules(GEOIP_RULES)?.build()),
+        &crate::config::local_ctx(),
+        None,
+        ExecutionStage::Connect,
+    );
+
+    // NOTE: the rule fails, `app.geoip` is not set in the configuration.
+    assert_eq!(
+        states[&ExecutionStage::Connect].2,
+        vsmtp_rule_engine::api::state::deny()
+    );
+}
@@ -0,0 +1,122 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::{config, run_test};
+use vsmtp_config::{field::FieldAppGreylist, Config};
+
+const GREYLIST_RULES: &str = r#"#{
+    rcpt: [
+        rule "greylisting" || greylist::check(),
+    ],
+}"#;
+
+/// Greylisting with a delay of 5 minutes, the triplets are stored in `dirpath`.
+fn greylist_config(dirpath: &str, stored: Option<&str>) -> Config {
+    let dirpath = std::path::PathBuf::from(dirpath);
+    let _ = std::fs::remove_dir_all(&dirpath);
+    std::fs::create_dir_all(&dirpath).unwrap();
+    if let Some(stored) = stored {
+        std::fs::write(dirpath.join("greylist.json"), stored).unwrap();
+    }
+
+    let mut config = config::local_test();
+    config.app.dirpath = dirpath;
+    config.app.greylist = Some(FieldAppGreylist {
+        delay: std::time::Duration::from_secs(300),
+        expiry: std::time::Duration::from_secs(3600),
+    });
+    config
+}
+
+fn seconds_ago(seconds: u64) -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - seconds
+}
+
+run_test! {
+    fn first_contact_deferred,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "451 4.7.1 Sender is not authorized. Please try again.\r\n",
+        "451 4.7.1 Sender is not authorized. Please try again.\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = greylist_config("./tmp/greylist_first_contact", None),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(GREYLIST_RULES)?.build()),
+}
+
+run_test! {
+    fn accepted_after_delay,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "RCPT TO:<cc@dd>\r\n",
+        "DATA\r\n",
+        ".\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "451 4.7.1 Sender is not authorized. Please try again.\r\n",
+        "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+        "250 Ok\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    // NOTE: the first attempt of the client has been stored 10 minutes ago.
+    config = greylist_config("./tmp/greylist_after_delay", Some(&format!(
+        r#"[{{ "network": "127.0.0.0/24", "sender": "john@doe", "recipient": "aa@bb", "first_seen": {first_seen}, "last_seen": {first_seen}, "passed": false }}]"#,
+        first_seen = seconds_ago(600)
+    ))),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(GREYLIST_RULES)?.build()),
+}
+
+run_test! {
+    fn too_early_retry_deferred,
+    input = [
+        "HELO foo\r\n",
+        "MAIL FROM:<john@doe>\r\n",
+        "RCPT TO:<aa@bb>\r\n",
+        "QUIT\r\n",
+    ],
+    expected = [
+        "220 testserver.com Service ready\r\n",
+        "250 Ok\r\n",
+        "250 Ok\r\n",
+        "451 4.7.1 Sender is not authorized. Please try again.\r\n",
+        "221 Service closing transmission channel\r\n",
+    ],
+    config = greylist_config("./tmp/greylist_too_early", Some(&format!(
+        r#"[{{ "network": "127.0.0.0/24", "sender": "john@doe", "recipient": "aa@bb", "first_seen": {first_seen}, "last_seen": {first_seen}, "passed": false }}]"#,
+        first_seen = seconds_ago(60)
+    ))),
+    hierarchy_builder = |builder| Ok(builder.add_root_filter_rules(GREYLIST_RULES)?.build()),
+}
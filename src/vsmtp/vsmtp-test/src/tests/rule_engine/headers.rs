@@ -0,0 +1,748 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::{status::Status, Reply};
+use vsmtp_mail_parser::MessageBody;
+use vsmtp_rule_engine::ExecutionStage;
+
+fn msg() -> MessageBody {
+    MessageBody::try_from(concat!(
+        "Received: from mx3.example.com by mx4.example.com\r\n",
+        "Subject: Unit test are cool\r\n",
+        "Received: from mx2.example.com by mx3.example.com\r\n",
+        "received: from mx1.example.com\r\n",
+        "  by mx2.example.com\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_get_header_at_success() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "get_header_at" || {
+            if msg::get_header_at("Received", 0) == "from mx3.example.com by mx4.example.com"
+            && msg::get_header_at("Received", 1) == "from mx2.example.com by mx3.example.com"
+            && msg::get_header_at(identifier("received"), 2) == "from mx1.example.com\r\n  by mx2.example.com"
+            && msg::get_header_at("Subject", 0) == "Unit test are cool" {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_get_header_at_out_of_range() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "get_header_at" || {
+            if msg::get_header_at("Received", 3) == ""
+            && msg::get_header_at("Received", -1) == ""
+            && msg::get_header_at("X-Unknown", 0) == "" {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_get_header_raw_folded() {
+    let subject = concat!(
+        "Subject:  A folded \r\n",
+        "\tsubject,\r\n",
+        "   over three lines\r\n"
+    );
+    let msg = MessageBody::try_from(
+        [
+            subject,
+            "From: john.doe@example.com\r\n",
+            "\r\n",
+            "Hello world!\r\n",
+        ]
+        .concat()
+        .as_str(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        msg.inner().raw_headers()[..3].concat(),
+        subject,
+        "the folded header must be kept as is by the parser"
+    );
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "get_header_raw" || {
+            if "Subject:" + msg::get_header_raw("Subject") + "\r\n"
+                == "Subject:  A folded \r\n\tsubject,\r\n   over three lines\r\n"
+            && msg::get_header_raw("From") == " john.doe@example.com"
+            && msg::get_header_raw("X-Unknown") == "" {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_header_matches() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "header_matches" || {
+            if msg::header_matches("Subject", "^Unit test")
+            && msg::header_matches(identifier("subject"), "(?i)ARE COOL$")
+            && msg::header_capture("Subject", "^(\\w+) (\\w+)", 2) == "test"
+            && msg::header_capture("Subject", "cool", 0) == "cool" {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_header_not_matches() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "header_not_matches" || {
+            if !msg::header_matches("Subject", "^\\[SPAM\\]")
+            && !msg::header_matches("X-Unknown", ".*")
+            && msg::header_capture("Subject", "^\\[(SPAM)\\]", 1) == ""
+            && msg::header_capture("Subject", "^(\\w+)", 2) == ""
+            && msg::header_capture("Subject", "^(\\w+)", -1) == "" {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_header_matches_pattern_too_big() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "header_matches" || {
+            if msg::header_matches("Subject", "a{50000}") {
+                state::accept()
+            } else {
+                state::next()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Deny(
+            "554 permanent problems with the remote server\r\n"
+                .parse::<Reply>()
+                .unwrap()
+        )
+    );
+}
+
+#[rstest::rstest]
+#[case::base64_utf8("=?UTF-8?B?w4dhIHZhIMOgIGxhIHBsYWdlIOKYgA==?=", "Ça va à la plage ☀")]
+#[case::quoted_printable_latin1(
+    "Re: =?ISO-8859-1?Q?=C9t=E9_ensoleill=E9?= !",
+    "Re: Été ensoleillé !"
+)]
+#[case::unencoded("Unit test are cool", "Unit test are cool")]
+#[case::invalid("=?UTF-8?Q?caf=C3?=", "=?UTF-8?Q?caf=C3?=")]
+fn test_get_header_decoded(#[case] subject: &str, #[case] expected: &str) {
+    let msg = MessageBody::try_from(format!("Subject: {subject}\r\n\r\nHello world!\r\n").as_str())
+        .unwrap();
+    let rules = r#"#{
+    preq: [
+        rule "get_header_decoded" || {
+            state::accept(`250 ${msg::get_header_decoded("Subject")}`)
+        }
+    ]
+}"#;
+
+    assert_eq!(
+        crate::vsl::run_preq(Some(msg), rules).2,
+        Status::Accept(format!("250 {expected}").parse::<Reply>().unwrap())
+    );
+}
+
+#[rstest::rstest]
+#[case::exact(r#"msg::header_equals("Subject", "Unit test are cool")"#, true)]
+#[case::other_value(r#"msg::header_equals("Subject", "Unit test are bad")"#, false)]
+#[case::value_case(r#"msg::header_equals("Subject", "unit test are cool")"#, false)]
+#[case::ignore_case(
+    r#"msg::header_equals_ignore_case("subject", "unit test are cool")"#,
+    true
+)]
+#[case::among_several(
+    r#"msg::header_equals("Received", "from mx2.example.com by mx3.example.com")"#,
+    true
+)]
+#[case::missing(r#"msg::header_equals("X-Missing", "")"#, false)]
+fn test_header_equals(#[case] snippet: &str, #[case] expected: bool) {
+    let rules = r#"#{
+    preq: [
+        rule "header_equals" || state::accept(`250 ${{snippet}}`)
+    ]
+}"#
+    .replace("{snippet}", snippet);
+
+    assert_eq!(
+        crate::vsl::run_preq(Some(msg()), &rules).2,
+        Status::Accept(format!("250 {expected}").parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_message_size() {
+    let rules = r#"#{
+    preq: [
+        rule "message_size" || state::accept(`250 ${msg::message_size()}`)
+    ]
+}"#;
+
+    assert_eq!(
+        crate::vsl::run_preq(Some(msg()), rules).2,
+        Status::Accept(
+            format!("250 {}", msg().inner().to_string().len())
+                .parse::<Reply>()
+                .unwrap()
+        )
+    );
+}
+
+const AUTHENTICATION_RESULTS: &str = concat!(
+    "testserver.com; spf=pass smtp.mailfrom=john.doe@example.com; dkim=pass header.d=example.com ",
+    "header.s=selector header.b=abcdefgh; dmarc=pass (p=reject) header.from=example.com; ",
+    "arc=none; auth=none (ok)",
+);
+
+#[test]
+fn test_append_header_folded() {
+    assert_eq!(AUTHENTICATION_RESULTS.len(), 200);
+
+    let headers = crate::vsl::run_preq(
+        Some(MessageBody::try_from("Subject: Unit test are cool\r\n\r\nHello world!\r\n").unwrap()),
+        r#"#{
+    preq: [
+        rule "append_header_folded" || {
+            msg::append_header_folded("Authentication-Results", "testserver.com; spf=pass smtp.mailfrom=john.doe@example.com; dkim=pass header.d=example.com header.s=selector header.b=abcdefgh; dmarc=pass (p=reject) header.from=example.com; arc=none; auth=none (ok)");
+        }
+    ]
+}"#
+    )
+    .1
+    .inner()
+    .raw_headers()
+    .clone();
+
+    let header = headers.last().unwrap();
+    let lines = header
+        .strip_suffix("\r\n")
+        .unwrap()
+        .split("\r\n")
+        .collect::<Vec<_>>();
+    assert!(lines.len() > 1, "{header:?}");
+    for line in &lines {
+        assert!(line.len() <= 78, "{line:?}");
+    }
+    assert_eq!(
+        lines.concat(),
+        format!("Authentication-Results: {AUTHENTICATION_RESULTS}")
+    );
+}
+
+#[test]
+fn test_insert_header_before() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "insert_header_before" || {
+            msg::insert_header_before("Received", "Authentication-Results", "testserver.com; none");
+            msg::insert_header_before("Subject", "X-Before-Subject", identifier("foo"));
+            msg::insert_header_before("X-Unknown", "X-Top", "bar");
+        }
+    ]
+}"#
+        )
+        .1
+        .inner()
+        .raw_headers()
+        .clone(),
+        vec![
+            "X-Top: bar\r\n",
+            "Authentication-Results: testserver.com; none\r\n",
+            "Received: from mx3.example.com by mx4.example.com\r\n",
+            "X-Before-Subject: foo\r\n",
+            "Subject: Unit test are cool\r\n",
+            "Received: from mx2.example.com by mx3.example.com\r\n",
+            "received: from mx1.example.com\r\n",
+            "  by mx2.example.com\r\n",
+        ]
+    );
+}
+
+#[test]
+fn test_insert_header_after() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "insert_header_after" || {
+            msg::insert_header_after("Received", "X-After-Received", "foo");
+            msg::insert_header_after("Subject", "X-After-Subject", identifier("bar"));
+            msg::rm_header("Received");
+            msg::rm_header("Received");
+            msg::insert_header_after("Received", "X-After-Folded", "baz");
+            msg::insert_header_after("X-Unknown", "X-Bottom", "qux");
+        }
+    ]
+}"#
+        )
+        .1
+        .inner()
+        .raw_headers()
+        .clone(),
+        vec![
+            "X-After-Received: foo\r\n",
+            "Subject: Unit test are cool\r\n",
+            "X-After-Subject: bar\r\n",
+            "received: from mx1.example.com\r\n",
+            "  by mx2.example.com\r\n",
+            "X-After-Folded: baz\r\n",
+            "X-Bottom: qux\r\n",
+        ]
+    );
+}
+
+#[test]
+fn test_remove_all_headers() {
+    let msg = MessageBody::try_from(concat!(
+        "X-Spam: yes\r\n",
+        "Subject: Unit test are cool\r\n",
+        "x-spam: score=10,\r\n",
+        "  tests=FOO\r\n",
+        "X-SPAM: yes\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap();
+    let rules = r#"#{
+    preq: [
+        rule "remove_all_headers" || {
+            if msg::rm_all_headers("X-Spam") == 3
+            && msg::count_header("X-Spam") == 0
+            && msg::rm_all_headers(identifier("X-Spam")) == 0 {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#;
+
+    let (_, body, result) = &crate::vsl::run_preq(Some(msg), rules);
+
+    assert_eq!(*result, Status::Accept("250 Ok".parse::<Reply>().unwrap()));
+    assert_eq!(
+        body.inner().raw_headers(),
+        &vec!["Subject: Unit test are cool\r\n".to_string()]
+    );
+}
+
+#[test]
+fn test_set_all_headers() {
+    let msg = MessageBody::try_from(concat!(
+        "Content-Type: text/plain\r\n",
+        "Subject: Unit test are cool\r\n",
+        "content-type: text/html\r\n",
+        "CONTENT-TYPE: multipart/mixed;\r\n",
+        "  boundary=foo\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap();
+    let rules = r#"#{
+    preq: [
+        rule "set_all_headers" || {
+            // only the first occurrence is replaced.
+            msg::set_header("Content-Type", "text/html");
+            if msg::count_header("Content-Type") != 3 {
+                return state::deny();
+            }
+
+            msg::set_all_headers("Content-Type", "text/plain; charset=utf-8");
+            if msg::count_header("Content-Type") == 1 {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#;
+
+    let (_, body, result) = &crate::vsl::run_preq(Some(msg), rules);
+
+    assert_eq!(*result, Status::Accept("250 Ok".parse::<Reply>().unwrap()));
+    assert_eq!(
+        body.inner().raw_headers(),
+        &vec![
+            "Subject: Unit test are cool\r\n".to_string(),
+            "Content-Type: text/plain; charset=utf-8\r\n".to_string(),
+        ]
+    );
+}
+
+const ENSURE_MESSAGE_ID_RULES: &str = r#"#{
+    preq: [
+        rule "ensure_message_id" || {
+            msg::append_header("X-Id", msg::ensure_message_id());
+            msg::append_header("X-Id", msg::ensure_message_id());
+        }
+    ]
+}"#;
+
+#[test]
+fn test_ensure_message_id_existing() {
+    let msg = MessageBody::try_from(concat!(
+        "Subject: Unit test are cool\r\n",
+        "message-id: <1234@example.com>\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(Some(msg), ENSURE_MESSAGE_ID_RULES)
+            .1
+            .inner()
+            .raw_headers()
+            .clone(),
+        vec![
+            "Subject: Unit test are cool\r\n",
+            "message-id: <1234@example.com>\r\n",
+            "X-Id: <1234@example.com>\r\n",
+            "X-Id: <1234@example.com>\r\n",
+        ]
+    );
+}
+
+#[test]
+fn test_ensure_message_id_missing() {
+    let msg = MessageBody::try_from(concat!(
+        "Subject: Unit test are cool\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap();
+
+    let headers = crate::vsl::run_preq(Some(msg), ENSURE_MESSAGE_ID_RULES)
+        .1
+        .inner()
+        .raw_headers()
+        .clone();
+    let id = headers[0]
+        .strip_prefix("Message-ID: ")
+        .and_then(|id| id.strip_suffix("\r\n"))
+        .unwrap();
+
+    assert!(
+        id.starts_with('<') && id.ends_with('>') && id.contains('@'),
+        "{id}"
+    );
+    assert_eq!(
+        headers,
+        vec![
+            format!("Message-ID: {id}\r\n"),
+            "Subject: Unit test are cool\r\n".to_string(),
+            format!("X-Id: {id}\r\n"),
+            format!("X-Id: {id}\r\n"),
+        ]
+    );
+}
+
+fn addresses_msg() -> MessageBody {
+    MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "To: \"doe, jane\"@example.com, Team: Bob <bob@example.com>,\r\n",
+        " \"Smith, Alice\" <alice@example.com> (work);\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_parse_addresses() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(addresses_msg()),
+            r#"#{
+    preq: [
+        rule "parse_addresses" || {
+            let from = msg::parse_addresses("From");
+            let to = msg::parse_addresses("To");
+
+            if from == [#{ display_name: "", address: "john.doe@example.com" }]
+            && to == [
+                #{ display_name: "", address: "\"doe, jane\"@example.com" },
+                #{ display_name: "Bob", address: "bob@example.com" },
+                #{ display_name: "Smith, Alice", address: "alice@example.com" },
+            ]
+            && msg::parse_addresses("Cc") == [] {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_set_body_keeps_headers() {
+    let rules = r#"#{
+    preq: [
+        action "set_body" || {
+            msg::set_body("This message has been removed.");
+            msg::append_to_body(identifier("-- footer"));
+        },
+        rule "headers after set_body" || {
+            if msg::get_header("Subject") == "Unit test are cool"
+            && msg::mail().ends_with("\r\n\r\nThis message has been removed.\r\n-- footer\r\n") {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#;
+
+    let (_, body, result) = &crate::vsl::run_preq(Some(msg()), rules);
+
+    assert_eq!(*result, Status::Accept("250 Ok".parse::<Reply>().unwrap()));
+    assert_eq!(body.inner().raw_headers(), msg().inner().raw_headers());
+    assert_eq!(
+        body.inner().body().as_deref(),
+        Some("This message has been removed.\r\n-- footer\r\n")
+    );
+}
+
+#[test]
+fn test_returned_body_contains_changes() {
+    let rules = r#"#{
+    preq: [
+        action "add disclaimer" || {
+            msg::append_header("X-Disclaimer", "added");
+            msg::append_to_body("-- disclaimer");
+        },
+    ]
+}"#;
+
+    let states = crate::vsl::run_with_msg(
+        move |builder| {
+            Ok(builder
+                .add_root_filter_rules("#{}")?
+                .add_domain_rules("testserver.com".parse().unwrap())
+                .with_incoming(rules)?
+                .with_outgoing(rules)?
+                .with_internal(rules)?
+                .build()
+                .build())
+        },
+        None,
+    );
+
+    let body = &states[&ExecutionStage::PreQ].1;
+    assert_eq!(body.get_header("X-Disclaimer").as_deref(), Some("added"));
+    assert!(body.inner().to_string().ends_with("-- disclaimer\r\n"));
+
+    // the other stages did not change the message.
+    assert_eq!(
+        states[&ExecutionStage::RcptTo].1,
+        crate::config::local_msg()
+    );
+}
+
+#[test]
+fn test_add_authentication_results_merged() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(MessageBody::try_from(concat!(
+                "Authentication-Results: mx.example.com; spf=fail smtp.mailfrom=example.com\r\n",
+                "Subject: Unit test are cool\r\n",
+                "\r\n",
+                "Hello world!\r\n",
+            ))
+            .unwrap()),
+            r#"#{
+    preq: [
+        rule "add_authentication_results" || {
+            msg::add_authentication_results("spf", "pass smtp.mailfrom=example.com");
+            msg::add_authentication_results("dkim", "fail header.d=example.com");
+        }
+    ]
+}"#
+        )
+        .1
+        .inner()
+        .raw_headers()
+        .clone(),
+        vec![
+            "Authentication-Results: testserver.com; spf=pass smtp.mailfrom=example.com; dkim=fail header.d=example.com\r\n",
+            // NOTE: the results of another server are left untouched.
+            "Authentication-Results: mx.example.com; spf=fail smtp.mailfrom=example.com\r\n",
+            "Subject: Unit test are cool\r\n",
+        ]
+    );
+}
+
+#[test]
+fn test_add_authentication_results_forged() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(
+                MessageBody::try_from(concat!(
+                "Authentication-Results: testserver.com;\r\n",
+                "  dkim=pass header.d=example.com\r\n",
+                "Subject: Unit test are cool\r\n",
+                "Authentication-Results: testserver.com 1; spf=pass smtp.mailfrom=example.com\r\n",
+                "\r\n",
+                "Hello world!\r\n",
+            ))
+                .unwrap()
+            ),
+            r#"#{
+    preq: [
+        rule "add_authentication_results" || {
+            msg::add_authentication_results("spf", "fail smtp.mailfrom=example.com");
+        }
+    ]
+}"#
+        )
+        .1
+        .inner()
+        .raw_headers()
+        .clone(),
+        vec![
+            // NOTE: the headers claiming the server's authserv-id are not merged, but removed.
+            "Authentication-Results: testserver.com; spf=fail smtp.mailfrom=example.com\r\n",
+            "Subject: Unit test are cool\r\n",
+        ]
+    );
+}
+
+#[test]
+fn test_add_authentication_results_replace_none() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "add_authentication_results" || {
+            msg::prepend_header("Authentication-Results", "testserver.com; none");
+            msg::add_authentication_results("dmarc", "pass header.from=example.com");
+        }
+    ]
+}"#
+        )
+        .1
+        .inner()
+        .raw_headers()
+        .clone()[0],
+        "Authentication-Results: testserver.com; dmarc=pass header.from=example.com\r\n"
+    );
+}
+
+#[test]
+fn test_add_authentication_results_invalid_method() {
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg()),
+            r#"#{
+    preq: [
+        rule "add_authentication_results" || {
+            msg::add_authentication_results("spf; dkim", "pass");
+            state::accept()
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Deny(
+            "554 permanent problems with the remote server\r\n"
+                .parse::<Reply>()
+                .unwrap()
+        )
+    );
+}
@@ -0,0 +1,288 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::{status::Status, Reply};
+use vsmtp_mail_parser::MessageBody;
+
+#[test]
+fn test_mime_parts_attachment() {
+    let msg = MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+        "To: jane.doe@example.com\r\n",
+        "Subject: Your invoice\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"boundary42\"\r\n",
+        "\r\n",
+        "--boundary42\r\n",
+        "Content-Type: text/plain; charset=us-ascii\r\n",
+        "\r\n",
+        "Please find your invoice attached.\r\n",
+        "--boundary42\r\n",
+        "Content-Type: application/pdf; name=\"invoice.pdf\"\r\n",
+        "Content-Disposition: attachment; filename=\"invoice-2023.pdf\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "JVBERi0xLjQKJcOkw7zDtsOfCg==\r\n",
+        "--boundary42--\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "mime_parts" || {
+            let parts = msg::mime_parts();
+
+            if parts.len() == 2
+            && parts[0].content_type == "text/plain"
+            && parts[0].filename == ""
+            && parts[0].content_transfer_encoding == "7bit"
+            && parts[1].content_type == "application/pdf"
+            && parts[1].filename == "invoice-2023.pdf"
+            && parts[1].content_transfer_encoding == "base64"
+            && parts[1].size == 30 {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_mime_parts_nested() {
+    let msg = MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"mixed\"\r\n",
+        "\r\n",
+        "--mixed\r\n",
+        "Content-Type: multipart/alternative; boundary=\"alternative\"\r\n",
+        "\r\n",
+        "--alternative\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+        "--alternative\r\n",
+        "Content-Type: text/html\r\n",
+        "\r\n",
+        "<p>Hello world!</p>\r\n",
+        "--alternative--\r\n",
+        "--mixed\r\n",
+        "Content-Type: application/octet-stream; name=\"=?UTF-8?Q?r=C3=A9sum=C3=A9.txt?=\"\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+        "--mixed--\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "mime_parts" || {
+            let parts = msg::mime_parts();
+
+            if parts.map(|part| part.content_type) == ["text/plain", "text/html", "application/octet-stream"]
+            && parts.map(|part| part.filename) == ["", "", "résumé.txt"] {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_mime_parts_not_multipart() {
+    let msg = MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+        "Subject: Unit test are cool\r\n",
+        "\r\n",
+        "Hello world!\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "mime_parts" || {
+            let part = msg::mime_parts();
+
+            if type_of(part) == "map"
+            && part == #{
+                content_type: "text/plain",
+                filename: "",
+                size: 14,
+                content_transfer_encoding: "7bit",
+                content_disposition: "",
+            } {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_attachments() {
+    let msg = MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"mixed\"\r\n",
+        "\r\n",
+        "--mixed\r\n",
+        "Content-Type: multipart/related; boundary=\"related\"\r\n",
+        "\r\n",
+        "--related\r\n",
+        "Content-Type: text/html\r\n",
+        "\r\n",
+        "<img src=\"cid:logo\"><img src=\"cid:banner\">\r\n",
+        "--related\r\n",
+        "Content-Type: image/png; name=\"logo.png\"\r\n",
+        "Content-Disposition: inline; filename=\"logo.png\"\r\n",
+        "Content-ID: <logo>\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "iVBORw0KGgo=\r\n",
+        "--related\r\n",
+        "Content-Type: image/gif\r\n",
+        "Content-Disposition: inline\r\n",
+        "Content-ID: <banner>\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "R0lGODlh\r\n",
+        "--related--\r\n",
+        "--mixed\r\n",
+        "Content-Type: application/pdf\r\n",
+        "Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "JVBERi0xLjQ=\r\n",
+        "--mixed\r\n",
+        "Content-Type: text/csv; name=\"invoice.csv\"\r\n",
+        "\r\n",
+        "id,amount\r\n",
+        "--mixed--\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "attachments" || {
+            if msg::mime_parts().len() == 5
+            && msg::attachment_count() == 2
+            && msg::attachment_names() == ["invoice.pdf", "invoice.csv"] {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
+
+#[test]
+fn test_strip_attachments() {
+    let msg = MessageBody::try_from(concat!(
+        "From: john.doe@example.com\r\n",
+        "Date: Tue, 30 May 2023 10:00:00 +0200\r\n",
+        "To: jane.doe@example.com\r\n",
+        "Subject: Your invoice\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"=_boundary42\"\r\n",
+        "\r\n",
+        "--=_boundary42\r\n",
+        "Content-Type: text/plain; charset=us-ascii\r\n",
+        "\r\n",
+        "Please run the attached program.\r\n",
+        "--=_boundary42\r\n",
+        "Content-Type: application/octet-stream; name=\"setup.EXE\"\r\n",
+        "Content-Disposition: attachment; filename=\"setup.EXE\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "TVqQAAMAAAAEAAAA\r\n",
+        "--=_boundary42\r\n",
+        "Content-Type: application/pdf\r\n",
+        "Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "JVBERi0xLjQKJcOkw7zDtsOfCg==\r\n",
+        "--=_boundary42--\r\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        crate::vsl::run_preq(
+            Some(msg),
+            r#"#{
+    preq: [
+        rule "strip_attachments" || {
+            let removed = msg::strip_attachments(["exe", ".bat"]);
+            let parts = msg::mime_parts();
+
+            if removed == 1
+            && parts.map(|part| part.content_type) == ["text/plain", "text/plain", "application/pdf"]
+            && parts.map(|part| part.filename) == ["", "", "invoice.pdf"]
+            && msg::get_header("Subject") == "Your invoice"
+            && msg::get_header("To") == "jane.doe@example.com"
+            && msg::body_contains("Please run the attached program.")
+            && msg::body_contains("The attachment \"setup.EXE\" has been removed.")
+            && msg::strip_attachments(["exe"]) == 0 {
+                state::accept()
+            } else {
+                state::deny()
+            }
+        }
+    ]
+}"#
+        )
+        .2,
+        Status::Accept("250 Ok".parse::<Reply>().unwrap())
+    );
+}
@@ -0,0 +1,101 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::status::Status;
+use vsmtp_config::field::FieldServerVirtual;
+use vsmtp_rule_engine::ExecutionStage;
+
+const MX_RULES: &str = r#"#{
+    mail: [
+        rule "sender has mx" || {
+            if dns::sender_has_mx() { state::next() } else { state::deny("550 no mx") }
+        },
+    ],
+    rcpt: [
+        rule "local recipient" || {
+            if dns::recipient_domain_is_local() { state::accept() } else { state::deny("554 not local") }
+        },
+    ],
+}"#;
+
+#[rstest::rstest]
+#[case("recipient@testserver.com", true)]
+#[case("recipient@example.com", true)]
+#[case("recipient@mail.example.com", true)]
+#[case("recipient@example.org", false)]
+#[case("recipient@sub.testserver.com", false)]
+fn test_recipient_domain_is_local(#[case] rcpt: &str, #[case] local: bool) {
+    let mut ctx = crate::config::local_ctx();
+    // NOTE: cached, no DNS query is made for the sender.
+    ctx.mail_from.sender_has_mx = Some(true);
+    ctx.rcpt_to.forward_paths = vec![rcpt.parse().unwrap()];
+
+    let mut config = crate::config::local_test();
+    config.server.r#virtual = std::collections::BTreeMap::from_iter([(
+        "example.com".parse().unwrap(),
+        FieldServerVirtual::default(),
+    )]);
+
+    let states = crate::vsl::run_with_context_and_config(
+        |builder| Ok(builder.add_root_filter_rules(MX_RULES)?.build()),
+        &ctx,
+        None,
+        ExecutionStage::RcptTo,
+        config,
+    );
+
+    assert_eq!(
+        states[&ExecutionStage::RcptTo].2,
+        if local {
+            vsmtp_rule_engine::api::state::accept()
+        } else {
+            Status::Deny("554 not local\r\n".parse().unwrap())
+        }
+    );
+}
+
+#[test]
+fn test_sender_has_mx_cached() {
+    let mut ctx = crate::config::local_ctx();
+    ctx.mail_from.sender_has_mx = Some(false);
+
+    let states = crate::vsl::run_with_context(
+        |builder| Ok(builder.add_root_filter_rules(MX_RULES)?.build()),
+        &ctx,
+        None,
+        ExecutionStage::MailFrom,
+    );
+
+    assert_eq!(
+        states[&ExecutionStage::MailFrom].2,
+        Status::Deny("550 no mx\r\n".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_sender_has_mx_null_sender() {
+    let mut ctx = crate::config::local_ctx();
+    ctx.mail_from.reverse_path = None;
+
+    let states = crate::vsl::run_with_context(
+        |builder| Ok(builder.add_root_filter_rules(MX_RULES)?.build()),
+        &ctx,
+        None,
+        ExecutionStage::MailFrom,
+    );
+
+    assert_eq!(states[&ExecutionStage::MailFrom].2, Status::Next);
+}
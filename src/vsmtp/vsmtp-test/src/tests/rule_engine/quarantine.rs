@@ -90,3 +90,147 @@ async fn test_quarantine(
 ) {
     actual_test(stage).await;
 }
+
+#[test_log::test(tokio::test)]
+async fn test_fs_quarantine() {
+    let queue_manager = run_test! {
+        input = [
+            "HELO foobar\r\n",
+            "MAIL FROM:<john.doe@mydomain.com>\r\n",
+            "RCPT TO:<aa@mydomain.com>\r\n",
+            "DATA\r\n",
+            concat!(
+                "from: 'abc'\r\n",
+                "to: 'def'\r\n",
+                ".\r\n",
+            ),
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "250 Ok\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+        hierarchy_builder = |builder| Ok(
+            builder
+                .add_root_filter_rules(r#"#{
+    preq: [
+        rule "quarantine virus" || fs::quarantine("virus", "EICAR test file"),
+        rule "never evaluated" || state::deny(),
+    ]
+}"#)?
+                .build()
+            ),
+    };
+
+    // the context is stored once by the server, with the record of the rule.
+    let quarantine = std::fs::read_dir(vqueue::FilesystemQueueManagerExt::get_queue_path(
+        &*queue_manager,
+        &vqueue::QueueID::Quarantine {
+            name: "virus".to_string(),
+        },
+    ))
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .collect::<Vec<_>>();
+    assert_eq!(quarantine.len(), 1);
+
+    let ctx = serde_json::from_str::<vsmtp_common::ContextFinished>(
+        &std::fs::read_to_string(&quarantine[0]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        ctx.connect.skipped,
+        Some(vsmtp_common::status::Status::Quarantine("virus".to_string()))
+    );
+    let record = ctx.finished.quarantine.unwrap();
+    assert_eq!(record.name, "virus");
+    assert_eq!(record.reason, "EICAR test file");
+
+    // NOTE: the temporary queue manager stores its queues outside of the application path.
+    let app = vqueue::GenericQueueManager::get_config(&*queue_manager)
+        .app
+        .dirpath
+        .join("quarantine/virus");
+    let uuid = ctx.mail_from.message_uuid;
+    assert!(app.join(format!("{uuid}.eml")).exists());
+    assert!(app.join(format!("{uuid}.json")).exists());
+}
+
+#[test]
+fn test_fs_quarantine_files() {
+    let dirpath = std::path::PathBuf::from("./tmp/quarantine_app");
+    let _ = std::fs::remove_dir_all(&dirpath);
+
+    let mut config = crate::config::local_test();
+    config.app.dirpath = dirpath.clone();
+
+    let rules = r#"#{
+    preq: [
+        rule "quarantine virus" || fs::quarantine("virus", "EICAR test file"),
+        rule "never evaluated" || state::deny(),
+    ]
+}"#;
+
+    let states = crate::vsl::run_with_msg_and_config(
+        move |builder| {
+            Ok(builder
+                .add_root_filter_rules("#{}")?
+                .add_domain_rules("testserver.com".parse().unwrap())
+                .with_incoming(rules)?
+                .with_outgoing(rules)?
+                .with_internal(rules)?
+                .build()
+                .build())
+        },
+        None,
+        config,
+    );
+    let (ctx, body, result) = &states[&ExecutionStage::PreQ];
+
+    assert_eq!(
+        *result,
+        vsmtp_common::status::Status::Quarantine("virus".to_string())
+    );
+
+    let quarantine = dirpath.join("quarantine/virus");
+    let uuid = ctx.message_uuid().unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(quarantine.join(format!("{uuid}.eml"))).unwrap(),
+        body.inner().to_string()
+    );
+
+    let json = serde_json::from_str::<serde_json::Value>(
+        &std::fs::read_to_string(quarantine.join(format!("{uuid}.json"))).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(json["quarantine"]["name"], "virus");
+    assert_eq!(json["quarantine"]["reason"], "EICAR test file");
+    assert!(json["quarantine"]["timestamp"].is_string());
+    assert_eq!(json["message_uuid"], uuid.to_string());
+}
+
+#[test]
+fn test_fs_quarantine_invalid_name() {
+    let states = crate::vsl::run(|builder| {
+        Ok(builder
+            .add_root_filter_rules(
+                r#"#{
+    connect: [
+        rule "quarantine" || fs::quarantine("../virus"),
+    ]
+}"#,
+            )?
+            .build())
+    });
+
+    assert!(matches!(
+        states[&vsmtp_rule_engine::ExecutionStage::Connect].2,
+        vsmtp_common::status::Status::Deny(_)
+    ));
+}
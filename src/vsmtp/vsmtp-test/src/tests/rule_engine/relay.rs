@@ -0,0 +1,158 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+const MESSAGE: &str = "Subject: relay\r\n\r\nhello\r\n";
+
+/// A downstream server accepting one transaction, answering `final_reply` to the message.
+/// Return the commands and the message received.
+async fn mock_receiver(
+    address: &str,
+    final_reply: &'static str,
+) -> tokio::task::JoinHandle<String> {
+    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = tokio::io::BufReader::new(read).lines();
+        let mut transcript = String::new();
+
+        write
+            .write_all(b"220 mock.downstream.com\r\n")
+            .await
+            .unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            transcript.push_str(&line);
+            transcript.push_str("\r\n");
+
+            let reply = match line.split_once(':').map_or(line.as_str(), |(verb, _)| verb) {
+                "EHLO testserver.com" => "250 mock.downstream.com\r\n",
+                "MAIL FROM" | "RCPT TO" => "250 Ok\r\n",
+                "DATA" => {
+                    write.write_all(b"354 Go ahead\r\n").await.unwrap();
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                        transcript.push_str(&line);
+                        transcript.push_str("\r\n");
+                    }
+                    final_reply
+                }
+                "QUIT" => {
+                    write.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => "502 Command not implemented\r\n",
+            };
+            write.write_all(reply.as_bytes()).await.unwrap();
+        }
+
+        transcript
+    })
+}
+
+const RELAY_RULES: &str = r#"#{
+    preq: [
+        rule "relay to the downstream" || {
+            let reply = transport::relay("127.0.0.1", {port});
+
+            if reply.to_string().starts_with("2") {
+                state::accept()
+            } else {
+                state::deny(reply)
+            }
+        },
+    ],
+}"#;
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn relayed_and_accepted() {
+    let downstream = mock_receiver("127.0.0.1:10093", "250 2.0.0 Queued as 42\r\n").await;
+
+    run_test! {
+        input = [
+            "HELO foo\r\n",
+            "MAIL FROM:<john@doe>\r\n",
+            "RCPT TO:<aa@bb>\r\n",
+            "DATA\r\n",
+            &format!("{MESSAGE}.\r\n"),
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "250 Ok\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+        hierarchy_builder = |builder| Ok(
+            builder.add_root_filter_rules(&RELAY_RULES.replace("{port}", "10093"))?.build()
+        ),
+    };
+
+    pretty_assertions::assert_eq!(
+        downstream.await.unwrap(),
+        [
+            "EHLO testserver.com\r\n",
+            "MAIL FROM:<john@doe>\r\n",
+            "RCPT TO:<aa@bb>\r\n",
+            "DATA\r\n",
+            MESSAGE,
+            "QUIT\r\n",
+        ]
+        .concat()
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn relayed_and_rejected() {
+    let downstream = mock_receiver(
+        "127.0.0.1:10094",
+        "554 5.7.1 Rejected by the downstream\r\n",
+    )
+    .await;
+
+    run_test! {
+        input = [
+            "HELO foo\r\n",
+            "MAIL FROM:<john@doe>\r\n",
+            "RCPT TO:<aa@bb>\r\n",
+            "DATA\r\n",
+            &format!("{MESSAGE}.\r\n"),
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "554 5.7.1 Rejected by the downstream\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+        hierarchy_builder = |builder| Ok(
+            builder.add_root_filter_rules(&RELAY_RULES.replace("{port}", "10094"))?.build()
+        ),
+    };
+
+    assert!(downstream.await.unwrap().contains("Subject: relay\r\n"));
+}
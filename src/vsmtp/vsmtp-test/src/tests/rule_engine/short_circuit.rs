@@ -0,0 +1,50 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use vsmtp_common::status::Status;
+
+const RULES: &str = r#"#{
+    preq: [
+        rule "first" || {status},
+        action "second" || msg::append_header("X-Second-Rule", "evaluated"),
+    ],
+}"#;
+
+#[rstest::rstest]
+#[case("state::accept()", vsmtp_rule_engine::api::state::accept())]
+#[case("state::deny()", vsmtp_rule_engine::api::state::deny())]
+#[case("state::quarantine(\"short_circuit\")", Status::Quarantine("short_circuit".to_string()))]
+fn test_short_circuit(#[case] status: &str, #[case] expected: Status) {
+    let rules = RULES.replace("{status}", status);
+
+    let (_, msg, result) = &crate::vsl::run_preq(None, &rules);
+
+    assert_eq!(*result, expected);
+    assert_eq!(msg.get_header("X-Second-Rule"), None);
+}
+
+#[test]
+fn test_next_continues() {
+    let rules = RULES.replace("{status}", "state::next()");
+
+    let (_, msg, result) = &crate::vsl::run_preq(None, &rules);
+
+    assert_eq!(*result, Status::Next);
+    assert_eq!(
+        msg.get_header("X-Second-Rule").as_deref(),
+        Some("evaluated")
+    );
+}
@@ -0,0 +1,202 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::reload::Client;
+use crate::config;
+use vsmtp_config::DnsResolvers;
+use vsmtp_rule_engine::RuleEngine;
+use vsmtp_server::{socket_bind_anyhow, Server};
+
+const PORT: u16 = 10049;
+const CLIENT_COUNT_MAX: i64 = 2;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn refused_when_full() {
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.server.client_count_max = CLIENT_COUNT_MAX;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen((
+        vec![socket_bind_anyhow(format!("127.0.0.1:{PORT}")).unwrap()],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    )));
+
+    let mut clients = vec![];
+    for _ in 0..CLIENT_COUNT_MAX {
+        let (client, greetings) = Client::connect(PORT).await;
+        assert!(greetings.starts_with("220 "), "{greetings}");
+        clients.push(client);
+    }
+
+    #[cfg(feature = "metrics")]
+    {
+        let gauge = vsmtp_server::metrics::gather()
+            .unwrap()
+            .lines()
+            .find_map(|line| {
+                line.strip_prefix("vsmtp_open_connections ")?
+                    .parse::<i64>()
+                    .ok()
+            })
+            .unwrap();
+        assert!(gauge >= CLIENT_COUNT_MAX, "{gauge}");
+    }
+
+    let (_, greetings) = Client::connect(PORT).await;
+    assert_eq!(greetings, "421 Too many connections, closing\r\n");
+
+    // a slot is released when a session ends.
+    let mut client = clients.pop().unwrap();
+    assert!(client.send("QUIT\r\n").await.starts_with("221"));
+    drop(client);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let (_, greetings) = Client::connect(PORT).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn refused_when_address_full() {
+    let port = PORT + 1;
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.server.client_count_max = 10;
+        config.server.client_count_max_per_ip = CLIENT_COUNT_MAX;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen((
+        vec![socket_bind_anyhow(format!("127.0.0.1:{port}")).unwrap()],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    )));
+
+    let abusive = "127.0.0.2".parse().unwrap();
+    let mut clients = vec![];
+    for _ in 0..CLIENT_COUNT_MAX {
+        let (client, greetings) = Client::connect_from(abusive, port).await;
+        assert!(greetings.starts_with("220 "), "{greetings}");
+        clients.push(client);
+    }
+
+    let (_, greetings) = Client::connect_from(abusive, port).await;
+    assert_eq!(
+        greetings,
+        "421 Too many connections from your address, closing\r\n"
+    );
+
+    let (_, greetings) = Client::connect_from("127.0.0.3".parse().unwrap(), port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn refused_when_proxied_address_full() {
+    let port = 10055;
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.server.client_count_max = 10;
+        config.server.client_count_max_per_ip = CLIENT_COUNT_MAX;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen((
+        vec![],
+        vec![],
+        vec![],
+        vec![socket_bind_anyhow(format!("127.0.0.1:{port}")).unwrap()],
+        vec![],
+        vec![],
+    )));
+
+    // all the sessions come from the address of the proxy, they are counted for the client
+    // address of their PROXY header.
+    let abusive = "192.0.2.1".parse().unwrap();
+    let mut clients = vec![];
+    for _ in 0..CLIENT_COUNT_MAX {
+        let (client, greetings) = Client::connect_proxied(abusive, port).await;
+        assert!(greetings.starts_with("220 "), "{greetings}");
+        clients.push(client);
+    }
+
+    let (_, greetings) = Client::connect_proxied(abusive, port).await;
+    assert_eq!(
+        greetings,
+        "421 Too many connections from your address, closing\r\n"
+    );
+
+    let (_, greetings) = Client::connect_proxied("192.0.2.2".parse().unwrap(), port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+
+    // a slot is released when a session ends.
+    let mut client = clients.pop().unwrap();
+    assert!(client.send("QUIT\r\n").await.starts_with("221"));
+    drop(client);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let (_, greetings) = Client::connect_proxied(abusive, port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+
+    server.abort();
+}
@@ -0,0 +1,95 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::reload::Client;
+use crate::config;
+use vsmtp_config::{
+    field::{FieldServerInterfacesListener, ListenerTls},
+    DnsResolvers,
+};
+use vsmtp_protocol::ConnectionKind;
+use vsmtp_rule_engine::RuleEngine;
+use vsmtp_server::{socket_bind_anyhow, Server};
+
+const RELAY_PORT: u16 = 10046;
+const SUBMISSION_PORT: u16 = 10047;
+const SUBMISSION_TLS_PORT: u16 = 10048;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn policy_of_each_listener() {
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.server.interfaces.listeners = vec![
+            FieldServerInterfacesListener {
+                addr: format!("127.0.0.1:{RELAY_PORT}").parse().unwrap(),
+                kind: ConnectionKind::Relay,
+                tls: ListenerTls::Optional,
+                auth_required: false,
+            },
+            FieldServerInterfacesListener {
+                addr: format!("127.0.0.1:{SUBMISSION_PORT}").parse().unwrap(),
+                kind: ConnectionKind::Submission,
+                tls: ListenerTls::Optional,
+                auth_required: true,
+            },
+            FieldServerInterfacesListener {
+                addr: format!("127.0.0.1:{SUBMISSION_TLS_PORT}").parse().unwrap(),
+                kind: ConnectionKind::Submission,
+                tls: ListenerTls::Required,
+                auth_required: true,
+            },
+        ];
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let listeners = config
+        .server
+        .interfaces
+        .listeners
+        .iter()
+        .map(|listener| (socket_bind_anyhow(listener.addr).unwrap(), *listener))
+        .collect::<Vec<_>>();
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen((vec![], vec![], vec![], vec![], vec![], listeners)));
+
+    let (mut relay, _) = Client::connect(RELAY_PORT).await;
+    relay.send("EHLO client.com\r\n").await;
+    let reply = relay.send("MAIL FROM:<john@doe.com>\r\n").await;
+    assert_eq!(reply, "250 Ok\r\n");
+
+    let (mut submission, _) = Client::connect(SUBMISSION_PORT).await;
+    submission.send("EHLO client.com\r\n").await;
+    let reply = submission.send("MAIL FROM:<john@doe.com>\r\n").await;
+    assert_eq!(reply, "530 5.7.0 Authentication required\r\n");
+
+    let (mut submission_tls, _) = Client::connect(SUBMISSION_TLS_PORT).await;
+    submission_tls.send("EHLO client.com\r\n").await;
+    let reply = submission_tls.send("MAIL FROM:<john@doe.com>\r\n").await;
+    assert_eq!(reply, "530 5.7.0 Must issue a STARTTLS command first\r\n");
+
+    server.abort();
+}
@@ -0,0 +1,108 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::run_test;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const ADDRESS: &str = "127.0.0.1:10090";
+const MESSAGE: &str = "Subject: metrics\r\n\r\nhello\r\n";
+
+async fn scrape(path: &str) -> String {
+    let mut stream = tokio::net::TcpStream::connect(ADDRESS).await.unwrap();
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Retry to connect until the endpoint accepts connections, the server is not
+/// expected to stop before that.
+async fn wait_until_bound(server: &tokio::task::JoinHandle<anyhow::Result<()>>) {
+    while tokio::net::TcpStream::connect(ADDRESS).await.is_err() {
+        assert!(!server.is_finished(), "the metrics endpoint failed to start");
+        tokio::task::yield_now().await;
+    }
+}
+
+fn counter(response: &str, name: &str) -> u64 {
+    response
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+async fn counters_increment_after_transaction() {
+    let server = tokio::spawn(vsmtp_server::metrics::serve(ADDRESS.parse().unwrap()));
+    wait_until_bound(&server).await;
+
+    let before = scrape("/metrics").await;
+    assert!(before.starts_with("HTTP/1.1 200 OK\r\n"), "{before}");
+
+    run_test! {
+        input = [
+            "HELO foo\r\n",
+            "RCPT TO:<aa@bb>\r\n",
+            "MAIL FROM:<john@doe>\r\n",
+            "RCPT TO:<aa@bb>\r\n",
+            "DATA\r\n",
+            &format!("{MESSAGE}.\r\n"),
+            "QUIT\r\n",
+        ],
+        expected = [
+            "220 testserver.com Service ready\r\n",
+            "250 Ok\r\n",
+            "503 Bad sequence of commands\r\n",
+            "250 Ok\r\n",
+            "250 Ok\r\n",
+            "354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            "250 Ok\r\n",
+            "221 Service closing transmission channel\r\n",
+        ],
+    };
+
+    let after = scrape("/metrics").await;
+
+    // NOTE: the metrics are shared by the tests running concurrently.
+    for name in [
+        "vsmtp_connections_total{kind=\"relay\"}",
+        "vsmtp_messages_total{status=\"accepted\"}",
+        "vsmtp_replies_total{code=\"220\"}",
+        "vsmtp_replies_total{code=\"250\"}",
+        "vsmtp_replies_total{code=\"503\"}",
+        "vsmtp_replies_total{code=\"221\"}",
+        "vsmtp_message_size_bytes_count",
+        "vsmtp_transaction_duration_seconds_count",
+    ] {
+        assert!(
+            counter(&after, name) > counter(&before, name),
+            "{name} did not increment:\n{after}"
+        );
+    }
+    assert!(
+        counter(&after, "vsmtp_received_bytes_total")
+            >= counter(&before, "vsmtp_received_bytes_total") + MESSAGE.len() as u64
+    );
+
+    assert!(scrape("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
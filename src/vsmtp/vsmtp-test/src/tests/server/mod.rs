@@ -20,6 +20,14 @@ use vsmtp_config::DnsResolvers;
 use vsmtp_rule_engine::RuleEngine;
 use vsmtp_server::{socket_bind_anyhow, Server};
 
+mod connections;
+mod listeners;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod reload;
+mod shutdown;
+mod tarpit;
+
 macro_rules! listen_with {
     ($addr:expr, $addr_submission:expr, $addr_submissions:expr, $timeout:expr, $client_count_max:expr) => {{
         let config = std::sync::Arc::new({
@@ -44,9 +52,9 @@ macro_rules! listen_with {
 
         let s = Server::new(
             config.clone(),
-            std::sync::Arc::new(
+            std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
                 RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
-            ),
+            )),
             queue_manager,
             emitter,
         )
@@ -82,6 +90,25 @@ macro_rules! listen_with {
                     .map(socket_bind_anyhow)
                     .collect::<anyhow::Result<Vec<std::net::TcpListener>>>()
                     .unwrap(),
+                config
+                    .server
+                    .interfaces
+                    .addr_proxied
+                    .iter()
+                    .cloned()
+                    .map(socket_bind_anyhow)
+                    .collect::<anyhow::Result<Vec<std::net::TcpListener>>>()
+                    .unwrap(),
+                config
+                    .server
+                    .interfaces
+                    .addr_lmtp
+                    .iter()
+                    .cloned()
+                    .map(socket_bind_anyhow)
+                    .collect::<anyhow::Result<Vec<std::net::TcpListener>>>()
+                    .unwrap(),
+                vec![],
             )),
         )
         .await
@@ -211,10 +238,10 @@ async fn one_client_max_err() {
     // one of the client has been denied on connection, but we cant know which one
     let ok1_failed2 = client1
         == "permanent error (554): permanent problems with the remote server"
-        && client2 == "permanent error (554): Cannot process connection, closing";
+        && client2 == "transient error (421): Too many connections, closing";
     let ok2_failed1 = client2
         == "permanent error (554): permanent problems with the remote server"
-        && client1 == "permanent error (554): Cannot process connection, closing";
+        && client1 == "transient error (421): Too many connections, closing";
 
     assert!(ok1_failed2 || ok2_failed1);
 }
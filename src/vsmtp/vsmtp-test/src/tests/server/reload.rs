@@ -0,0 +1,306 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use crate::config;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use vsmtp_config::DnsResolvers;
+use vsmtp_rule_engine::RuleEngine;
+use vsmtp_server::{reload_rules, socket_bind_anyhow, watch_rules, Server};
+
+pub(super) const ACCEPT_RULES: &str = r#"#{ rcpt: [ rule "accept" || state::accept() ] }"#;
+const DENY_RULES: &str = r#"#{ rcpt: [ rule "deny" || state::deny() ] }"#;
+
+pub(super) struct Client(tokio::io::BufReader<tokio::net::TcpStream>);
+
+impl Client {
+    pub(super) async fn connect(port: u16) -> (Self, String) {
+        Self::connect_from("127.0.0.1".parse().unwrap(), port).await
+    }
+
+    /// Connect to the loopback interface using `source` as the client address.
+    pub(super) async fn connect_from(source: std::net::Ipv4Addr, port: u16) -> (Self, String) {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind((source, 0).into()).unwrap();
+
+        let mut client = Self(tokio::io::BufReader::new(
+            socket.connect(([127, 0, 0, 1], port).into()).await.unwrap(),
+        ));
+        let greetings = client.read_reply().await;
+        (client, greetings)
+    }
+
+    /// Connect through a proxy, forwarding `source` as the client address in a PROXY header.
+    pub(super) async fn connect_proxied(source: std::net::Ipv4Addr, port: u16) -> (Self, String) {
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        stream
+            .write_all(format!("PROXY TCP4 {source} 127.0.0.1 56324 {port}\r\n").as_bytes())
+            .await
+            .unwrap();
+
+        let mut client = Self(tokio::io::BufReader::new(stream));
+        let greetings = client.read_reply().await;
+        (client, greetings)
+    }
+
+    pub(super) async fn read_reply(&mut self) -> String {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            self.0.read_line(&mut line).await.unwrap();
+            reply.push_str(&line);
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return reply;
+            }
+        }
+    }
+
+    pub(super) async fn write(&mut self, command: &str) {
+        self.0
+            .get_mut()
+            .write_all(command.as_bytes())
+            .await
+            .unwrap();
+    }
+
+    pub(super) async fn send(&mut self, command: &str) -> String {
+        self.write(command).await;
+        self.read_reply().await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn reload_during_session() {
+    let filter_path = std::path::PathBuf::from("./tmp/reload_during_session/filter.vsl");
+    std::fs::create_dir_all(filter_path.parent().unwrap()).unwrap();
+    std::fs::write(&filter_path, ACCEPT_RULES).unwrap();
+
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.vsl.filter_path = Some(filter_path.clone());
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let server = Server::new(config, rule_engine.clone(), queue_manager, emitter).unwrap();
+    let port = 10036;
+    let server = tokio::spawn(server.listen((
+        vec![socket_bind_anyhow(format!("127.0.0.1:{port}")).unwrap()],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    )));
+
+    let (mut active, greetings) = Client::connect(port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(active.send("EHLO client.com\r\n").await.starts_with("250"));
+
+    std::fs::write(&filter_path, DENY_RULES).unwrap();
+    reload_rules(&rule_engine).unwrap();
+
+    // the session opened before the reload keeps the original rules.
+    assert!(active
+        .send("MAIL FROM:<john@doe.com>\r\n")
+        .await
+        .starts_with("250"));
+    let reply = active.send("RCPT TO:<jenny@doe.com>\r\n").await;
+    assert!(reply.starts_with("250"), "{reply}");
+    assert!(active.send("DATA\r\n").await.starts_with("354"));
+    let reply = active
+        .send("From: john@doe.com\r\nSubject: reload\r\n\r\nhello\r\n.\r\n")
+        .await;
+    assert!(reply.starts_with("250"), "{reply}");
+    assert!(active.send("QUIT\r\n").await.starts_with("221"));
+
+    // a new session uses the reloaded rules.
+    let (mut client, _) = Client::connect(port).await;
+    client.send("EHLO client.com\r\n").await;
+    client.send("MAIL FROM:<john@doe.com>\r\n").await;
+    let reply = client.send("RCPT TO:<jenny@doe.com>\r\n").await;
+    assert!(reply.starts_with("554"), "{reply}");
+
+    // invalid rules are not loaded, the previous ones are kept.
+    std::fs::write(&filter_path, "#{ rcpt: [ rule").unwrap();
+    assert!(reload_rules(&rule_engine).is_err());
+
+    let (mut client, _) = Client::connect(port).await;
+    client.send("EHLO client.com\r\n").await;
+    client.send("MAIL FROM:<john@doe.com>\r\n").await;
+    let reply = client.send("RCPT TO:<jenny@doe.com>\r\n").await;
+    assert!(reply.starts_with("554"), "{reply}");
+
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn auto_reload_after_debounce() {
+    let filter_path = std::path::PathBuf::from("./tmp/auto_reload_after_debounce/filter.vsl");
+    std::fs::create_dir_all(filter_path.parent().unwrap()).unwrap();
+    std::fs::write(&filter_path, ACCEPT_RULES).unwrap();
+
+    let debounce = std::time::Duration::from_millis(500);
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.vsl.filter_path = Some(filter_path.clone());
+        config.app.vsl.auto_reload = true;
+        config.app.vsl.auto_reload_debounce = debounce;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config, resolvers, queue_manager).unwrap(),
+    ));
+    watch_rules(&rule_engine).unwrap();
+
+    let wait_for_swap = |previous: std::sync::Arc<RuleEngine>| {
+        let rule_engine = rule_engine.clone();
+        async move {
+            let start = std::time::Instant::now();
+            while std::sync::Arc::ptr_eq(&previous, &rule_engine.load_full()) {
+                if start.elapsed() > std::time::Duration::from_secs(10) {
+                    return None;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Some(start.elapsed())
+        }
+    };
+
+    let initial = rule_engine.load_full();
+    let start = std::time::Instant::now();
+    // several writes in a row are coalesced in a single reload.
+    for _ in 0..3 {
+        std::fs::write(&filter_path, DENY_RULES).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(std::sync::Arc::ptr_eq(&initial, &rule_engine.load_full()));
+
+    wait_for_swap(initial).await.expect("rules reloaded");
+    assert!(start.elapsed() >= debounce);
+
+    // invalid rules are not loaded, the previous ones are kept.
+    let reloaded = rule_engine.load_full();
+    std::fs::write(&filter_path, "#{ rcpt: [ rule").unwrap();
+    tokio::time::sleep(debounce * 3).await;
+    assert!(std::sync::Arc::ptr_eq(&reloaded, &rule_engine.load_full()));
+
+    // the watcher keeps running after a failure.
+    std::fs::write(&filter_path, ACCEPT_RULES).unwrap();
+    wait_for_swap(reloaded).await.expect("rules reloaded");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn auto_reload_despite_other_files() {
+    let filter_path = std::path::PathBuf::from("./tmp/auto_reload_despite_other_files/filter.vsl");
+    let other_path = filter_path.with_file_name("notes.txt");
+    std::fs::create_dir_all(filter_path.parent().unwrap()).unwrap();
+    std::fs::write(&filter_path, ACCEPT_RULES).unwrap();
+
+    let debounce = std::time::Duration::from_millis(500);
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.vsl.filter_path = Some(filter_path.clone());
+        config.app.vsl.auto_reload = true;
+        config.app.vsl.auto_reload_debounce = debounce;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config, resolvers, queue_manager).unwrap(),
+    ));
+    watch_rules(&rule_engine).unwrap();
+
+    let initial = rule_engine.load_full();
+    std::fs::write(&filter_path, DENY_RULES).unwrap();
+
+    // the files written more often than the debounce do not delay the reload of the scripts.
+    let start = std::time::Instant::now();
+    while std::sync::Arc::ptr_eq(&initial, &rule_engine.load_full()) {
+        assert!(
+            start.elapsed() < debounce * 6,
+            "rules not reloaded while other files change"
+        );
+        std::fs::write(&other_path, format!("{:?}", start.elapsed())).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(start.elapsed() >= debounce);
+}
+
+#[tokio::test]
+async fn reload_keeps_greylist() {
+    let dirpath = std::path::PathBuf::from("./tmp/reload_keeps_greylist");
+    let _ = std::fs::remove_dir_all(&dirpath);
+    std::fs::create_dir_all(&dirpath).unwrap();
+
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.dirpath = dirpath;
+        config.app.greylist = Some(vsmtp_config::field::FieldAppGreylist {
+            delay: std::time::Duration::from_secs(300),
+            expiry: std::time::Duration::from_secs(3600),
+        });
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+
+    let rule_engine =
+        arc_swap::ArcSwap::from_pointee(RuleEngine::new(config, resolvers, queue_manager).unwrap());
+
+    let client_ip = "192.0.2.1".parse().unwrap();
+    let sender = vsmtp_common::addr!("john@doe");
+    let recipient = vsmtp_common::addr!("aa@bb");
+    let check = |rule_engine: &arc_swap::ArcSwap<RuleEngine>| {
+        rule_engine
+            .load()
+            .srv()
+            .greylist
+            .as_ref()
+            .unwrap()
+            .check(client_ip, Some(&sender), &recipient)
+            .as_ref()
+            .to_string()
+    };
+
+    assert_eq!(check(&rule_engine), "first_contact");
+    reload_rules(&rule_engine).unwrap();
+    // the triplet recorded before the reload is still in the store.
+    assert_eq!(check(&rule_engine), "too_early");
+}
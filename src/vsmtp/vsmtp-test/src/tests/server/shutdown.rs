@@ -0,0 +1,107 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::reload::{Client, ACCEPT_RULES};
+use crate::config;
+use vsmtp_config::DnsResolvers;
+use vsmtp_rule_engine::RuleEngine;
+use vsmtp_server::{socket_bind_anyhow, Server};
+
+const PORT: u16 = 10051;
+const SHUTTING_DOWN: &str = "421 4.3.2 Service shutting down - closing connection\r\n";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn drain_in_flight_transaction() {
+    let filter_path = std::path::PathBuf::from("./tmp/drain_in_flight_transaction/filter.vsl");
+    std::fs::create_dir_all(filter_path.parent().unwrap()).unwrap();
+    std::fs::write(&filter_path, ACCEPT_RULES).unwrap();
+
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.vsl.filter_path = Some(filter_path);
+        config.server.smtp.drain_timeout = std::time::Duration::from_secs(5);
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen_until(
+        (
+            vec![socket_bind_anyhow(format!("127.0.0.1:{PORT}")).unwrap()],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        ),
+        async move {
+            let _ = stopped.await;
+        },
+    ));
+
+    let (mut in_flight, greetings) = Client::connect(PORT).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(in_flight
+        .send("EHLO client.com\r\n")
+        .await
+        .starts_with("250"));
+    assert!(in_flight
+        .send("MAIL FROM:<john@doe.com>\r\n")
+        .await
+        .starts_with("250"));
+    let reply = in_flight.send("RCPT TO:<jenny@doe.com>\r\n").await;
+    assert!(reply.starts_with("250"), "{reply}");
+
+    let (mut idle, greetings) = Client::connect(PORT).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(idle.send("EHLO client.com\r\n").await.starts_with("250"));
+
+    stop.send(()).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // the sessions outside of a transaction are closed right away.
+    assert_eq!(idle.read_reply().await, SHUTTING_DOWN);
+
+    // the new clients are refused.
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", PORT))
+        .await
+        .is_err());
+
+    // the transaction in progress is completed.
+    assert!(in_flight.send("DATA\r\n").await.starts_with("354"));
+    let reply = in_flight
+        .send("From: john@doe.com\r\nSubject: drain\r\n\r\nhello\r\n.\r\n")
+        .await;
+    assert!(reply.starts_with("250"), "{reply}");
+    assert_eq!(in_flight.read_reply().await, SHUTTING_DOWN);
+
+    tokio::time::timeout(std::time::Duration::from_secs(1), server)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
@@ -0,0 +1,166 @@
+/*
+ * vSMTP mail transfer agent
+ * Copyright (C) 2022 viridIT SAS
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program. If not, see https://www.gnu.org/licenses/.
+ *
+*/
+use super::reload::Client;
+use crate::config;
+use vsmtp_config::DnsResolvers;
+use vsmtp_rule_engine::RuleEngine;
+use vsmtp_server::{socket_bind_anyhow, Server};
+
+const TARPIT_RULES: &str = r#"#{ rcpt: [ rule "tarpit" || state::tarpit("1m") ] }"#;
+
+/// Serve the `rules` on `port`, until the sender is used or dropped.
+fn serve(
+    name: &str,
+    port: u16,
+    rules: &str,
+    tarpit_max: std::time::Duration,
+) -> (
+    tokio::task::JoinHandle<anyhow::Result<()>>,
+    tokio::sync::oneshot::Sender<()>,
+) {
+    let filter_path = std::path::PathBuf::from(format!("./tmp/{name}/filter.vsl"));
+    std::fs::create_dir_all(filter_path.parent().unwrap()).unwrap();
+    std::fs::write(&filter_path, rules).unwrap();
+
+    let config = std::sync::Arc::new({
+        let mut config = config::local_test();
+        config.app.vsl.filter_path = Some(filter_path);
+        config.server.smtp.tarpit_max = tarpit_max;
+        config
+    });
+    let queue_manager =
+        <vqueue::temp::QueueManager as vqueue::GenericQueueManager>::init(config.clone(), vec![])
+            .unwrap();
+    let (emitter, _working, _delivery) = vsmtp_server::scheduler::init(
+        config.server.queues.working.channel_size,
+        config.server.queues.delivery.channel_size,
+    );
+    let resolvers = std::sync::Arc::new(DnsResolvers::from_config(&config).unwrap());
+    let rule_engine = std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(
+        RuleEngine::new(config.clone(), resolvers, queue_manager.clone()).unwrap(),
+    ));
+
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = Server::new(config, rule_engine, queue_manager, emitter).unwrap();
+    let server = tokio::spawn(server.listen_until(
+        (
+            vec![socket_bind_anyhow(format!("127.0.0.1:{port}")).unwrap()],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        ),
+        async move {
+            let _ = stopped.await;
+        },
+    ));
+
+    (server, stop)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn reply_delayed_by_tarpit() {
+    let port = 10052;
+    let (server, _stop) = serve(
+        "reply_delayed_by_tarpit",
+        port,
+        TARPIT_RULES,
+        std::time::Duration::from_secs(1),
+    );
+
+    let (mut client, greetings) = Client::connect(port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(client.send("EHLO client.com\r\n").await.starts_with("250"));
+    assert!(client
+        .send("MAIL FROM:<john@doe.com>\r\n")
+        .await
+        .starts_with("250"));
+
+    // the requested duration is bounded by `server.smtp.tarpit_max`.
+    let start = std::time::Instant::now();
+    let reply = client.send("RCPT TO:<jenny@doe.com>\r\n").await;
+    let elapsed = start.elapsed();
+    assert!(reply.starts_with("554"), "{reply}");
+    assert!(elapsed >= std::time::Duration::from_secs(1), "{elapsed:?}");
+    assert!(elapsed < std::time::Duration::from_secs(3), "{elapsed:?}");
+
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn closing_aborts_tarpit() {
+    let port = 10053;
+    let (server, stop) = serve(
+        "closing_aborts_tarpit",
+        port,
+        TARPIT_RULES,
+        std::time::Duration::from_secs(60),
+    );
+
+    let (mut client, greetings) = Client::connect(port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(client.send("EHLO client.com\r\n").await.starts_with("250"));
+    assert!(client
+        .send("MAIL FROM:<john@doe.com>\r\n")
+        .await
+        .starts_with("250"));
+    client.write("RCPT TO:<jenny@doe.com>\r\n").await;
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    drop(client);
+
+    // the server waits for its sessions before stopping, the tarpitted one has ended.
+    stop.send(()).unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(2), server)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn tarpit_outside_transaction() {
+    let port = 10054;
+    let (server, _stop) = serve(
+        "tarpit_outside_transaction",
+        port,
+        r#"#{
+    helo: [
+        rule "tarpit at helo" || {
+            try { state::tarpit("1m"); } catch { log("info", "tarpit is not available at helo"); }
+            state::next()
+        },
+    ],
+}"#,
+        std::time::Duration::from_secs(60),
+    );
+
+    let (mut client, greetings) = Client::connect(port).await;
+    assert!(greetings.starts_with("220 "), "{greetings}");
+    assert!(client.send("EHLO client.com\r\n").await.starts_with("250"));
+
+    // the delay is not recorded at `helo`, so it is not applied to the next command.
+    let reply = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        client.send("MAIL FROM:<john@doe.com>\r\n"),
+    )
+    .await
+    .expect("the reply is not delayed");
+    assert!(reply.starts_with("250"), "{reply}");
+
+    server.abort();
+}
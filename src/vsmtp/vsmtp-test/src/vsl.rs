@@ -17,16 +17,37 @@
 
 use crate::config::{local_ctx, local_msg, local_test};
 use vqueue::GenericQueueManager;
-use vsmtp_common::status::Status;
+use vsmtp_common::{status::Status, ContextFinished};
 use vsmtp_config::{Config, DnsResolvers};
 use vsmtp_mail_parser::MessageBody;
 use vsmtp_rule_engine::{Builder, ExecutionStage, RuleEngine, SubDomainHierarchy};
 
+/// The stages executed by the functions of this module, in order.
+const STAGES: [ExecutionStage; 6] = [
+    ExecutionStage::Connect,
+    ExecutionStage::Helo,
+    ExecutionStage::MailFrom,
+    ExecutionStage::RcptTo,
+    ExecutionStage::PreQ,
+    ExecutionStage::PostQ,
+];
+
+/// Run the rules with a custom context, for every stage up to `stage` (included).
+///
+/// Each stage is executed independently, with a copy of `ctx` and of `msg`
+/// (or [`local_msg`] if `None`).
+///
+/// The returned map contains, for each executed stage, a tuple with:
+/// * the context after the execution of the rules,
+/// * the message after the execution of the rules,
+/// * the status returned by the rules.
 #[doc(hidden)]
 #[must_use]
-pub fn run_with_msg_and_config(
+pub fn run_with_context_and_config(
     callback: impl Fn(Builder) -> anyhow::Result<SubDomainHierarchy> + 'static,
+    ctx: &ContextFinished,
     msg: Option<MessageBody>,
+    stage: ExecutionStage,
     config: Config,
 ) -> std::collections::HashMap<ExecutionStage, (vsmtp_common::Context, MessageBody, Status)> {
     let config = arc!(config);
@@ -34,54 +55,90 @@ pub fn run_with_msg_and_config(
         vqueue::temp::QueueManager::init(config.clone(), vec![]).expect("queue_manager");
     let resolvers = arc!(DnsResolvers::from_config(&config).expect("resolvers"));
 
-    let rule_engine = std::sync::Arc::new(
-        RuleEngine::with_hierarchy(callback, config, resolvers, queue_manager)
-            .expect("rule engine"),
-    );
+    let rule_engine = RuleEngine::with_hierarchy(callback, config, resolvers, queue_manager)
+        .expect("rule engine");
 
     let msg = msg.unwrap_or_else(local_msg);
 
-    let mut out = std::collections::HashMap::<
-        ExecutionStage,
-        (vsmtp_common::Context, MessageBody, Status),
-    >::new();
-
-    for i in [
-        ExecutionStage::Connect,
-        ExecutionStage::Helo,
-        ExecutionStage::MailFrom,
-        ExecutionStage::RcptTo,
-        ExecutionStage::PreQ,
-        ExecutionStage::PostQ,
-    ] {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .expect("runtime");
-        let re = rule_engine.clone();
-        let msg = msg.clone();
-        let mut skipped = None;
-
-        let state = runtime.block_on(async move {
-            re.just_run_when(
-                &mut skipped,
+    STAGES
+        .into_iter()
+        .filter(|i| *i <= stage)
+        .map(|i| {
+            (
                 i,
-                vsmtp_common::Context::Finished(local_ctx()),
-                msg,
+                rule_engine
+                    .dry_run(i, ctx.clone(), msg.clone())
+                    .expect("runtime"),
             )
-        });
-        out.insert(i, state);
-    }
-    out
+        })
+        .collect()
+}
+
+/// Same as [`run_with_context_and_config`], with the [`local_test`] configuration.
+///
+/// Useful to test the rules of a stage with specific values, like the recipients
+/// for the `rcpt` stage.
+#[doc(hidden)]
+#[must_use]
+pub fn run_with_context(
+    callback: impl Fn(Builder) -> anyhow::Result<SubDomainHierarchy> + 'static,
+    ctx: &ContextFinished,
+    msg: Option<MessageBody>,
+    stage: ExecutionStage,
+) -> std::collections::HashMap<ExecutionStage, (vsmtp_common::Context, MessageBody, Status)> {
+    run_with_context_and_config(callback, ctx, msg, stage, local_test())
+}
+
+#[doc(hidden)]
+#[must_use]
+pub fn run_with_msg_and_config(
+    callback: impl Fn(Builder) -> anyhow::Result<SubDomainHierarchy> + 'static,
+    msg: Option<MessageBody>,
+    config: Config,
+) -> std::collections::HashMap<ExecutionStage, (vsmtp_common::Context, MessageBody, Status)> {
+    run_with_context_and_config(callback, &local_ctx(), msg, ExecutionStage::PostQ, config)
 }
 
+/// Run the rules with the [`local_ctx`] context, for every stage.
+///
+/// The message returned for each stage contains the changes made by the rules
+/// (see [`run_with_context_and_config`]), so that they can be asserted.
 #[doc(hidden)]
 #[must_use]
 pub fn run_with_msg(
     callback: impl Fn(Builder) -> anyhow::Result<SubDomainHierarchy> + 'static,
     msg: Option<MessageBody>,
 ) -> std::collections::HashMap<ExecutionStage, (vsmtp_common::Context, MessageBody, Status)> {
-    run_with_msg_and_config(callback, msg, local_test())
+    run_with_context(callback, &local_ctx(), msg, ExecutionStage::PostQ)
+}
+
+/// Run `rules` as the rules of the `testserver.com` domain (for the incoming,
+/// outgoing and internal transactions) with the [`local_ctx`] context, and
+/// return the context, the message and the status of the `preq` stage.
+#[doc(hidden)]
+#[must_use]
+pub fn run_preq(
+    msg: Option<MessageBody>,
+    rules: &str,
+) -> (vsmtp_common::Context, MessageBody, Status) {
+    let rules = rules.to_owned();
+    let mut states = run_with_msg(
+        move |builder| {
+            Ok(builder
+                .add_root_filter_rules("#{}")?
+                .add_domain_rules("testserver.com".parse().expect("valid domain"))
+                .with_incoming(&rules)?
+                .with_outgoing(&rules)?
+                .with_internal(&rules)?
+                .build()
+                .build())
+        },
+        msg,
+    );
+
+    states
+        .remove(&ExecutionStage::PreQ)
+        .expect("the preq stage is executed")
 }
 
 #[doc(hidden)]